@@ -0,0 +1,265 @@
+/*!
+ * UniFFI bindings for diaryx-core, for embedding the engine in iOS / Android journaling apps.
+ *
+ * The host app reads vault files from its own storage (app sandbox, SAF, iCloud container, ...)
+ * into a `{ path: contents }` map; core runs over an in-memory `FileProvider` built from it, so no
+ * filesystem access happens on the Rust side. Swift and Kotlin sources are generated from the
+ * compiled library:
+ *
+ * ```text
+ * cargo run -p diaryx-ffi --features bindgen --bin uniffi-bindgen -- \
+ *     generate --library target/debug/libdiaryx_ffi.so --language swift --out-dir out/
+ * ```
+ *
+ * Frontmatter crosses the boundary as a JSON string, since its shape is open-ended.
+ */
+
+use std::collections::HashMap;
+use std::fmt;
+
+use diaryx_core::memfs::InMemoryFs;
+use diaryx_core::{CoreBuildOptions, OutputLayout, PageOutput, Warning, site};
+
+uniffi::setup_scaffolding!();
+
+#[derive(Debug, uniffi::Error)]
+pub enum DiaryxError {
+    /// The build could not run (missing entry, nothing public, render failure, ...).
+    Build { message: String },
+    /// `strict` was set and the build produced warnings.
+    StrictWarnings { warnings: Vec<String> },
+}
+
+impl fmt::Display for DiaryxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiaryxError::Build { message } => write!(f, "Build error: {message}"),
+            DiaryxError::StrictWarnings { warnings } => write!(
+                f,
+                "Strict mode: build failed due to {} warning(s)",
+                warnings.len()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DiaryxError {}
+
+impl From<anyhow::Error> for DiaryxError {
+    fn from(e: anyhow::Error) -> Self {
+        DiaryxError::Build {
+            message: format!("{e:#}"),
+        }
+    }
+}
+
+#[derive(uniffi::Record)]
+pub struct BuildOptions {
+    #[uniffi(default = false)]
+    pub include_nonpublic: bool,
+    /// Also publish the pages whose visibility lists this audience (e.g. `family`).
+    #[uniffi(default = None)]
+    pub audience: Option<String>,
+    #[uniffi(default = false)]
+    pub flat: bool,
+    /// Fail with `StrictWarnings` when the build produces warnings.
+    #[uniffi(default = false)]
+    pub strict: bool,
+    #[uniffi(default = true)]
+    pub rewrite_links: bool,
+    #[uniffi(default = None)]
+    pub large_body_bytes: Option<u64>,
+    /// Curly quotes, en / em dashes and ellipses in bodies and titles.
+    #[uniffi(default = false)]
+    pub smart_punctuation: bool,
+    /// Render standalone images with a caption as `<figure>` blocks.
+    #[uniffi(default = false)]
+    pub figures: bool,
+    /// Embed PDF attachments linked on a line of their own as inline previews.
+    #[uniffi(default = false)]
+    pub pdf_previews: bool,
+    /// Glob patterns (e.g. `templates/**`) for files that are never built or copied.
+    #[uniffi(default = [])]
+    pub exclude: Vec<String>,
+    /// Only entries created on or after this date (`YYYY-MM-DD`).
+    #[uniffi(default = None)]
+    pub since: Option<String>,
+    /// Only entries created on or before this date (`YYYY-MM-DD`, inclusive).
+    #[uniffi(default = None)]
+    pub until: Option<String>,
+    /// Only entries tagged with one of these tags.
+    #[uniffi(default = [])]
+    pub tags: Vec<String>,
+    /// Publish entries marked `draft: true` too.
+    #[uniffi(default = false)]
+    pub drafts: bool,
+    /// Publish entries whose `published` time is still ahead too.
+    #[uniffi(default = false)]
+    pub future: bool,
+    /// Build the entry as the site root even if it is a section index.
+    #[uniffi(default = false)]
+    pub as_root: bool,
+    /// Include the bundled stylesheet in `files` (and link it from pages).
+    #[uniffi(default = true)]
+    pub include_css: bool,
+    /// Include `diaryx-data.json` in `files`.
+    #[uniffi(default = false)]
+    pub emit_json: bool,
+}
+
+#[derive(uniffi::Record)]
+pub struct Page {
+    /// Page slug.
+    pub id: String,
+    pub source_path: String,
+    pub file_name: String,
+    pub title: String,
+    /// Rendered body HTML (links already rewritten).
+    pub html: String,
+    pub metadata_html: String,
+    pub is_root_index: bool,
+    pub is_index: bool,
+    pub parents: Vec<String>,
+    pub children: Vec<String>,
+    pub frontmatter_json: String,
+    pub warnings: Vec<String>,
+}
+
+#[derive(uniffi::Record)]
+pub struct BuildResult {
+    /// Complete site as `{ path: contents }`, laid out exactly like the CLI output directory.
+    pub files: HashMap<String, String>,
+    pub pages: Vec<Page>,
+    pub warnings: Vec<String>,
+    pub multi_page: bool,
+    pub root_slug: Option<String>,
+}
+
+#[derive(uniffi::Record)]
+pub struct ValidationResult {
+    /// True when the vault produced no warnings.
+    pub valid: bool,
+    pub warnings: Vec<String>,
+    pub page_count: u64,
+}
+
+#[derive(uniffi::Record)]
+pub struct ParsedDocument {
+    pub title: Option<String>,
+    pub frontmatter_json: String,
+    pub body: String,
+    pub is_root_index: bool,
+    pub contents: Vec<String>,
+    pub part_of: Vec<String>,
+    pub visibility: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Build a site from vault files supplied by the host app.
+#[uniffi::export]
+pub fn build_site(
+    entry: String,
+    files: HashMap<String, String>,
+    options: BuildOptions,
+) -> Result<BuildResult, DiaryxError> {
+    let fs = InMemoryFs::new(files);
+    let opts = CoreBuildOptions {
+        include_nonpublic: options.include_nonpublic,
+        audience: options.audience,
+        seal_private: false,
+        layout: OutputLayout::default(),
+        flat: options.flat,
+        strict: options.strict,
+        rewrite_links: options.rewrite_links,
+        large_body_bytes: options.large_body_bytes.map(|n| n as usize),
+        smart_punctuation: options.smart_punctuation,
+        figures: options.figures,
+        pdf_previews: options.pdf_previews,
+        exclude: options.exclude,
+        since: options.since,
+        until: options.until,
+        tags: options.tags,
+        now: Some(std::time::SystemTime::now().into()),
+        drafts: options.drafts,
+        future: options.future,
+        as_root: options.as_root,
+        env: None,
+        infer_dates: false,
+    };
+    let artifacts = diaryx_core::build_site(&entry, opts, &fs)?;
+    if options.strict && !artifacts.warnings.is_empty() {
+        return Err(DiaryxError::StrictWarnings {
+            warnings: messages(artifacts.warnings),
+        });
+    }
+    let site_opts = site::SiteOptions {
+        include_css: options.include_css,
+        emit_json: options.emit_json,
+    };
+    Ok(BuildResult {
+        files: site::site_files(&artifacts, &site_opts)
+            .into_iter()
+            .collect(),
+        pages: artifacts.pages.into_iter().map(page).collect(),
+        warnings: messages(artifacts.warnings),
+        multi_page: artifacts.multi_page,
+        root_slug: artifacts.root_slug,
+    })
+}
+
+/// Check vault files without producing a site; warnings are returned, not raised.
+#[uniffi::export]
+pub fn validate_site(
+    entry: String,
+    files: HashMap<String, String>,
+    include_nonpublic: bool,
+) -> Result<ValidationResult, DiaryxError> {
+    let fs = InMemoryFs::new(files);
+    let report = diaryx_core::validate_site(&entry, include_nonpublic, &fs)?;
+    Ok(ValidationResult {
+        valid: report.valid,
+        warnings: messages(report.warnings),
+        page_count: report.page_count as u64,
+    })
+}
+
+/// Parse a single file's frontmatter and body. `path` is only used in warning messages.
+#[uniffi::export]
+pub fn parse_document(path: String, content: String) -> ParsedDocument {
+    let doc = diaryx_core::parse_document(&path, content);
+    ParsedDocument {
+        title: doc.title,
+        frontmatter_json: json(&doc.frontmatter),
+        body: doc.body,
+        is_root_index: doc.is_root_index,
+        contents: doc.contents,
+        part_of: doc.part_of,
+        visibility: doc.visibility,
+        warnings: messages(doc.warnings),
+    }
+}
+
+fn page(p: PageOutput) -> Page {
+    Page {
+        frontmatter_json: json(&p.frontmatter),
+        id: p.id,
+        source_path: p.source_path,
+        file_name: p.file_name,
+        title: p.title,
+        html: p.html,
+        metadata_html: p.metadata_html,
+        is_root_index: p.is_root_index,
+        is_index: p.is_index,
+        parents: p.parents,
+        children: p.children,
+        warnings: messages(p.warnings),
+    }
+}
+
+fn messages(warnings: Vec<Warning>) -> Vec<String> {
+    warnings.into_iter().map(String::from).collect()
+}
+
+fn json(value: &diaryx_core::YamlValue) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "null".into())
+}
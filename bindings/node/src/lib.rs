@@ -0,0 +1,271 @@
+/*!
+ * Node.js bindings for diaryx-core (N-API via napi-rs), published as `@diaryx/core`.
+ *
+ * Vault files go in and site files come out as `Buffer`s, so large vaults cross the JS boundary
+ * without UTF-16 string conversion or JSON round-trips. Objects use camelCase field names, as is
+ * usual for Node APIs. Errors are thrown with a stable `code`: `INVALID_INPUT`, `BUILD_FAILED` or
+ * `STRICT_WARNINGS`.
+ */
+
+use std::collections::HashMap;
+
+use diaryx_core::memfs::InMemoryFs;
+use diaryx_core::{CoreBuildOptions, OutputLayout, PageOutput, Warning, site};
+use napi::bindgen_prelude::Buffer;
+use napi_derive::napi;
+
+/// Stable error codes, exposed to JS as `error.code`.
+pub enum ErrorCode {
+    InvalidInput,
+    BuildFailed,
+    StrictWarnings,
+}
+
+impl AsRef<str> for ErrorCode {
+    fn as_ref(&self) -> &str {
+        match self {
+            ErrorCode::InvalidInput => "INVALID_INPUT",
+            ErrorCode::BuildFailed => "BUILD_FAILED",
+            ErrorCode::StrictWarnings => "STRICT_WARNINGS",
+        }
+    }
+}
+
+type Result<T> = napi::Result<T, ErrorCode>;
+
+fn build_error(e: anyhow::Error) -> napi::Error<ErrorCode> {
+    napi::Error::new(ErrorCode::BuildFailed, format!("Build error: {e:#}"))
+}
+
+#[napi(object)]
+pub struct BuildInput {
+    /// Path of the entry file inside `files`.
+    pub entry: String,
+    /// Virtual vault: `{ path: contents }`.
+    pub files: HashMap<String, Buffer>,
+    pub include_nonpublic: Option<bool>,
+    /// Also publish the pages whose visibility lists this audience (e.g. `family`).
+    pub audience: Option<String>,
+    pub flat: Option<bool>,
+    /// Throw `STRICT_WARNINGS` when the build produces warnings.
+    pub strict: Option<bool>,
+    /// Default: true.
+    pub rewrite_links: Option<bool>,
+    pub large_body_bytes: Option<u32>,
+    /// Curly quotes, en / em dashes and ellipses in bodies and titles. Default: false.
+    pub smart_punctuation: Option<bool>,
+    /// Render standalone images with a caption as `<figure>` blocks. Default: false.
+    pub figures: Option<bool>,
+    /// Embed PDF attachments linked on a line of their own as inline previews. Default: false.
+    pub pdf_previews: Option<bool>,
+    /// Glob patterns (e.g. `templates/**`) for files that are never built or copied.
+    pub exclude: Option<Vec<String>>,
+    /// Only entries created on or after this date (`YYYY-MM-DD`).
+    pub since: Option<String>,
+    /// Only entries created on or before this date (`YYYY-MM-DD`, inclusive).
+    pub until: Option<String>,
+    /// Only entries tagged with one of these tags.
+    pub tags: Option<Vec<String>>,
+    /// Publish entries marked `draft: true` too. Default: false.
+    pub drafts: Option<bool>,
+    /// Publish entries whose `published` time is still ahead too. Default: false.
+    pub future: Option<bool>,
+    /// Build the entry as the site root even if it is a section index. Default: false.
+    pub as_root: Option<bool>,
+    /// Include the bundled stylesheet in `files` (and link it from pages). Default: true.
+    pub include_css: Option<bool>,
+    /// Include `diaryx-data.json` in `files`. Default: false.
+    pub emit_json: Option<bool>,
+}
+
+#[napi(object)]
+pub struct ValidateInput {
+    pub entry: String,
+    pub files: HashMap<String, Buffer>,
+    pub include_nonpublic: Option<bool>,
+}
+
+#[napi(object)]
+pub struct Page {
+    /// Page slug.
+    pub id: String,
+    pub source_path: String,
+    pub file_name: String,
+    pub title: String,
+    /// Rendered body HTML (links already rewritten).
+    pub html: String,
+    pub metadata_html: String,
+    pub is_root_index: bool,
+    pub is_index: bool,
+    pub parents: Vec<String>,
+    pub children: Vec<String>,
+    pub frontmatter: serde_json::Value,
+    pub warnings: Vec<String>,
+}
+
+#[napi(object)]
+pub struct BuildOutput {
+    /// Complete site as `{ path: contents }`, laid out exactly like the CLI output directory.
+    pub files: HashMap<String, Buffer>,
+    pub pages: Vec<Page>,
+    pub warnings: Vec<String>,
+    pub multi_page: bool,
+    pub root_slug: Option<String>,
+}
+
+#[napi(object)]
+pub struct ValidationResult {
+    /// True when the vault produced no warnings.
+    pub valid: bool,
+    pub warnings: Vec<String>,
+    pub page_count: u32,
+}
+
+#[napi(object)]
+pub struct ParsedDocument {
+    pub title: Option<String>,
+    pub frontmatter: serde_json::Value,
+    pub body: String,
+    pub is_root_index: bool,
+    pub contents: Vec<String>,
+    pub part_of: Vec<String>,
+    pub visibility: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Build a site from an in-memory vault.
+#[napi]
+pub fn build(input: BuildInput) -> Result<BuildOutput> {
+    let fs = memory_fs(input.files)?;
+    let flat = input.flat.unwrap_or(false);
+    let strict = input.strict.unwrap_or(false);
+    let opts = CoreBuildOptions {
+        include_nonpublic: input.include_nonpublic.unwrap_or(false),
+        audience: input.audience,
+        seal_private: false,
+        layout: OutputLayout::default(),
+        flat,
+        strict,
+        rewrite_links: input.rewrite_links.unwrap_or(true),
+        large_body_bytes: input.large_body_bytes.map(|n| n as usize),
+        smart_punctuation: input.smart_punctuation.unwrap_or(false),
+        figures: input.figures.unwrap_or(false),
+        pdf_previews: input.pdf_previews.unwrap_or(false),
+        exclude: input.exclude.unwrap_or_default(),
+        since: input.since,
+        until: input.until,
+        tags: input.tags.unwrap_or_default(),
+        now: Some(std::time::SystemTime::now().into()),
+        drafts: input.drafts.unwrap_or(false),
+        future: input.future.unwrap_or(false),
+        as_root: input.as_root.unwrap_or(false),
+        env: None,
+        infer_dates: false,
+    };
+    let artifacts = diaryx_core::build_site(&input.entry, opts, &fs).map_err(build_error)?;
+    if strict && !artifacts.warnings.is_empty() {
+        return Err(napi::Error::new(
+            ErrorCode::StrictWarnings,
+            format!(
+                "Strict mode: build failed due to {} warning(s):\n{}",
+                artifacts.warnings.len(),
+                messages(artifacts.warnings).join("\n")
+            ),
+        ));
+    }
+    let site_opts = site::SiteOptions {
+        include_css: input.include_css.unwrap_or(true),
+        emit_json: input.emit_json.unwrap_or(false),
+    };
+    let files = site::site_files(&artifacts, &site_opts)
+        .into_iter()
+        .map(|(path, content)| (path, content.into_bytes().into()))
+        .collect();
+    Ok(BuildOutput {
+        files,
+        pages: artifacts.pages.into_iter().map(page).collect(),
+        warnings: messages(artifacts.warnings),
+        multi_page: artifacts.multi_page,
+        root_slug: artifacts.root_slug,
+    })
+}
+
+/// Check an in-memory vault; warnings are returned, not thrown.
+#[napi]
+pub fn validate(input: ValidateInput) -> Result<ValidationResult> {
+    let fs = memory_fs(input.files)?;
+    let report =
+        diaryx_core::validate_site(&input.entry, input.include_nonpublic.unwrap_or(false), &fs)
+            .map_err(build_error)?;
+    Ok(ValidationResult {
+        valid: report.valid,
+        warnings: messages(report.warnings),
+        page_count: report.page_count as u32,
+    })
+}
+
+/// Parse a single file's frontmatter and body. `path` is only used in warning messages.
+#[napi]
+pub fn parse(path: String, content: Buffer) -> Result<ParsedDocument> {
+    let raw = utf8(&path, &content)?;
+    let doc = diaryx_core::parse_document(&path, raw);
+    Ok(ParsedDocument {
+        title: doc.title,
+        frontmatter: json(&doc.frontmatter),
+        body: doc.body,
+        is_root_index: doc.is_root_index,
+        contents: doc.contents,
+        part_of: doc.part_of,
+        visibility: doc.visibility,
+        warnings: messages(doc.warnings),
+    })
+}
+
+/// Markdown files must be UTF-8; anything else (attachments) is stored lossily since core only
+/// checks that it exists.
+fn memory_fs(files: HashMap<String, Buffer>) -> Result<InMemoryFs> {
+    let mut map = HashMap::with_capacity(files.len());
+    for (path, content) in files {
+        let text = if path.to_ascii_lowercase().ends_with(".md") {
+            utf8(&path, &content)?
+        } else {
+            String::from_utf8_lossy(&content).into_owned()
+        };
+        map.insert(path, text);
+    }
+    Ok(InMemoryFs::new(map))
+}
+
+fn utf8(path: &str, content: &[u8]) -> Result<String> {
+    String::from_utf8(content.to_vec()).map_err(|e| {
+        napi::Error::new(
+            ErrorCode::InvalidInput,
+            format!("File is not valid UTF-8: {path} ({e})"),
+        )
+    })
+}
+
+fn page(p: PageOutput) -> Page {
+    Page {
+        frontmatter: json(&p.frontmatter),
+        id: p.id,
+        source_path: p.source_path,
+        file_name: p.file_name,
+        title: p.title,
+        html: p.html,
+        metadata_html: p.metadata_html,
+        is_root_index: p.is_root_index,
+        is_index: p.is_index,
+        parents: p.parents,
+        children: p.children,
+        warnings: messages(p.warnings),
+    }
+}
+
+fn messages(warnings: Vec<Warning>) -> Vec<String> {
+    warnings.into_iter().map(String::from).collect()
+}
+
+fn json(value: &diaryx_core::YamlValue) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or(serde_json::Value::Null)
+}
@@ -0,0 +1,227 @@
+/*!
+ * Python bindings for diaryx-core (pyo3), published as the `diaryx` package.
+ *
+ * Vaults are passed as plain dicts (`{path: contents}`) and results come back as dicts and lists,
+ * so they drop straight into pandas (`pd.DataFrame(site["pages"])`) or NLP pipelines:
+ *
+ * ```text
+ * import diaryx
+ * site = diaryx.build("vault/index.md", files, include_nonpublic=True)
+ * report = diaryx.validate("vault/index.md", files)
+ * doc = diaryx.parse("entry.md", text)
+ * ```
+ *
+ * Builds release the GIL. Failures raise `diaryx.BuildError` or `diaryx.StrictWarningsError`
+ * (`args[1]` holds the warning list), both subclasses of `diaryx.DiaryxError`.
+ */
+
+use std::collections::HashMap;
+
+use diaryx_core::memfs::InMemoryFs;
+use diaryx_core::{CoreBuildOptions, OutputLayout, site};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyList, PyString};
+use serde::Serialize;
+
+create_exception!(
+    diaryx,
+    DiaryxError,
+    PyException,
+    "Base class for diaryx errors."
+);
+create_exception!(
+    diaryx,
+    BuildError,
+    DiaryxError,
+    "The build could not run (missing entry, nothing public, render failure, ...)."
+);
+create_exception!(
+    diaryx,
+    StrictWarningsError,
+    DiaryxError,
+    "strict=True and the build produced warnings; args[1] is the warning list."
+);
+
+fn build_error(e: anyhow::Error) -> PyErr {
+    BuildError::new_err(format!("Build error: {e:#}"))
+}
+
+/// Build a site. Returns `{files, pages, warnings, multi_page, root_slug}` where `files` maps
+/// output paths (laid out like the CLI output directory) to their contents.
+#[pyfunction]
+#[pyo3(signature = (
+    entry,
+    files,
+    *,
+    include_nonpublic = false,
+    audience = None,
+    flat = false,
+    strict = false,
+    rewrite_links = true,
+    large_body_bytes = None,
+    smart_punctuation = false,
+    figures = false,
+    pdf_previews = false,
+    exclude = Vec::new(),
+    since = None,
+    until = None,
+    tags = Vec::new(),
+    drafts = false,
+    future = false,
+    as_root = false,
+    include_css = true,
+    emit_json = false,
+))]
+#[allow(clippy::too_many_arguments)]
+fn build<'py>(
+    py: Python<'py>,
+    entry: String,
+    files: HashMap<String, String>,
+    include_nonpublic: bool,
+    audience: Option<String>,
+    flat: bool,
+    strict: bool,
+    rewrite_links: bool,
+    large_body_bytes: Option<usize>,
+    smart_punctuation: bool,
+    figures: bool,
+    pdf_previews: bool,
+    exclude: Vec<String>,
+    since: Option<String>,
+    until: Option<String>,
+    tags: Vec<String>,
+    drafts: bool,
+    future: bool,
+    as_root: bool,
+    include_css: bool,
+    emit_json: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let opts = CoreBuildOptions {
+        include_nonpublic,
+        audience,
+        seal_private: false,
+        layout: OutputLayout::default(),
+        flat,
+        strict,
+        rewrite_links,
+        large_body_bytes,
+        smart_punctuation,
+        figures,
+        pdf_previews,
+        exclude,
+        since,
+        until,
+        tags,
+        now: Some(std::time::SystemTime::now().into()),
+        drafts,
+        future,
+        as_root,
+        env: None,
+        infer_dates: false,
+    };
+    let site_opts = site::SiteOptions {
+        include_css,
+        emit_json,
+    };
+    let (artifacts, site_files) = py
+        .detach(|| {
+            let fs = InMemoryFs::new(files);
+            let artifacts = diaryx_core::build_site(&entry, opts, &fs)?;
+            let site_files = site::site_files(&artifacts, &site_opts);
+            Ok::<_, anyhow::Error>((artifacts, site_files))
+        })
+        .map_err(build_error)?;
+    let warnings: Vec<String> = artifacts.warnings.into_iter().map(String::from).collect();
+    if strict && !warnings.is_empty() {
+        let message = format!(
+            "Strict mode: build failed due to {} warning(s)",
+            warnings.len()
+        );
+        return Err(StrictWarningsError::new_err((message, warnings)));
+    }
+
+    let out = PyDict::new(py);
+    out.set_item("files", site_files)?;
+    out.set_item("pages", to_py(py, &artifacts.pages)?)?;
+    out.set_item("warnings", warnings)?;
+    out.set_item("multi_page", artifacts.multi_page)?;
+    out.set_item("root_slug", artifacts.root_slug)?;
+    Ok(out.into_any())
+}
+
+/// Check a vault without producing a site. Returns `{valid, warnings, page_count}`.
+#[pyfunction]
+#[pyo3(signature = (entry, files, *, include_nonpublic = false))]
+fn validate<'py>(
+    py: Python<'py>,
+    entry: String,
+    files: HashMap<String, String>,
+    include_nonpublic: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let report = py
+        .detach(|| {
+            let fs = InMemoryFs::new(files);
+            diaryx_core::validate_site(&entry, include_nonpublic, &fs)
+        })
+        .map_err(build_error)?;
+    to_py(py, &report)
+}
+
+/// Parse a single file's frontmatter and body. `path` is only used in warning messages.
+#[pyfunction]
+fn parse<'py>(py: Python<'py>, path: &str, content: String) -> PyResult<Bound<'py, PyAny>> {
+    to_py(py, &diaryx_core::parse_document(path, content))
+}
+
+/// Convert any serializable core type to plain Python objects (dict / list / str / ...).
+fn to_py<'py, T: Serialize>(py: Python<'py>, value: &T) -> PyResult<Bound<'py, PyAny>> {
+    let json = serde_json::to_value(value)
+        .map_err(|e| DiaryxError::new_err(format!("Serialize error: {e}")))?;
+    json_to_py(py, &json)
+}
+
+fn json_to_py<'py>(py: Python<'py>, value: &serde_json::Value) -> PyResult<Bound<'py, PyAny>> {
+    use serde_json::Value;
+    Ok(match value {
+        Value::Null => py.None().into_bound(py),
+        Value::Bool(b) => PyBool::new(py, *b).to_owned().into_any(),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                i.into_pyobject(py)?.into_any()
+            } else if let Some(u) = n.as_u64() {
+                u.into_pyobject(py)?.into_any()
+            } else {
+                n.as_f64().unwrap_or(f64::NAN).into_pyobject(py)?.into_any()
+            }
+        }
+        Value::String(s) => PyString::new(py, s).into_any(),
+        Value::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(json_to_py(py, item)?)?;
+            }
+            list.into_any()
+        }
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (k, v) in map {
+                dict.set_item(k, json_to_py(py, v)?)?;
+            }
+            dict.into_any()
+        }
+    })
+}
+
+#[pymodule]
+fn diaryx(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add_function(wrap_pyfunction!(build, m)?)?;
+    m.add_function(wrap_pyfunction!(validate, m)?)?;
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add("DiaryxError", py.get_type::<DiaryxError>())?;
+    m.add("BuildError", py.get_type::<BuildError>())?;
+    m.add("StrictWarningsError", py.get_type::<StrictWarningsError>())?;
+    Ok(())
+}
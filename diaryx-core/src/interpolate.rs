@@ -0,0 +1,69 @@
+/*!
+ * `${NAME}` interpolation of environment variables, so secrets and machine-specific values (an
+ * author's e-mail address, a local output folder) stay out of the vault.
+ *
+ * A build given [`CoreBuildOptions::env`](crate::CoreBuildOptions::env) substitutes them in the
+ * `author` field and in the `string` fields declared in `diaryx.toml`. A variable that is not set
+ * is replaced by nothing, with a warning. `$${NAME}` stands for a literal `${NAME}`.
+ */
+
+use std::collections::BTreeMap;
+
+use serde_yaml::Value;
+
+/// Replace each `${NAME}` in `text`; the names that were not set are pushed to `unset`.
+pub fn interpolate(text: &str, vars: &BTreeMap<String, String>, unset: &mut Vec<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if let Some(escaped) = tail.strip_prefix("$${") {
+            out.push_str("${");
+            rest = escaped;
+            continue;
+        }
+        let reference = tail
+            .strip_prefix("${")
+            .and_then(|inner| inner.split_once('}'))
+            .filter(|(name, _)| is_name(name));
+        match reference {
+            Some((name, after)) => {
+                match vars.get(name) {
+                    Some(value) => out.push_str(value),
+                    None => unset.push(name.to_string()),
+                }
+                rest = after;
+            }
+            None => {
+                out.push('$');
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// [`interpolate`] every string in `value`, including those in lists.
+pub(crate) fn interpolate_value(
+    value: &mut Value,
+    vars: &BTreeMap<String, String>,
+    unset: &mut Vec<String>,
+) {
+    match value {
+        Value::String(text) if text.contains("${") => *text = interpolate(text, vars, unset),
+        Value::Sequence(items) => {
+            for item in items {
+                interpolate_value(item, vars, unset);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
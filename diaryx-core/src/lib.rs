@@ -6,17 +6,23 @@
  *
  * This crate is intentionally independent of direct filesystem (`std::fs`) so it can:
  * - Be embedded in a CLI (provide a real FS adapter)
- * - Run in a WebAssembly environment (provide an in-memory virtual FS)
+ * - Run in a WebAssembly environment (in-memory virtual FS, see `memfs::InMemoryFs`)
  * - Be unit tested with synthetic file graphs
  *
  * High-Level Flow (build_site):
  * 1. Load entry file (a Diaryx Markdown file).
- * 2. Parse YAML frontmatter; record missing required fields as warnings.
- * 3. If entry (or files reached through traversal) declares `this_file_is_root_index: true`,
- *    recursively walk `contents:` lists (markdown link syntax) to load additional files.
+ * 2. Parse YAML frontmatter; record missing required fields as warnings (including the fields a
+ *    vault declares in `diaryx.toml`, see [`schema`]).
+ * 3. If the entry declares `this_file_is_root_index: true` (or `CoreBuildOptions::as_root`
+ *    promotes it), the build is a site: every index reached from it (any file with `contents`)
+ *    expands its own `contents:` list (markdown link syntax) to load additional files. Otherwise
+ *    only the entry is built. Only the entry can be the site root; a nested file declaring
+ *    `this_file_is_root_index` is an ordinary index within the site.
  * 4. Construct parent/child relationships from `contents` arrays.
  * 5. Render Markdown bodies to HTML (using `markdown` crate).
  * 6. Rewrite internal markdown links (.md) in rendered HTML to corresponding .html page names.
+ *    `.html` / `.htm` sources (and Markdown files with `format: html`) skip step 5: their body
+ *    is passed through as-is, but takes part in traversal and link rewriting like any page.
  * 7. Produce `BuildArtifacts` containing pages & warnings (and an attachment copy plan hook).
  *
  * Added: minimal metadata HTML rendering (unordered list) is now generated per page (`metadata_html`)
@@ -28,12 +34,23 @@
 
 use anyhow::{Context, Result, anyhow};
 use once_cell::sync::Lazy;
+pub use output::OutputLayout;
 use regex::Regex;
 use serde::Deserialize;
 use serde::Serialize;
 pub use serde_yaml::Value as YamlValue;
-use std::collections::{HashMap, VecDeque};
-use time::{OffsetDateTime, UtcOffset};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use time::{OffsetDateTime, Time, UtcOffset};
+
+pub mod interpolate;
+pub mod memfs;
+pub mod output;
+pub mod remote;
+pub mod schema;
+pub mod site;
+pub mod synth;
+#[cfg(feature = "wasm")]
+mod wasm;
 
 // -------------------------------------------------------------------------------------------------
 // Public API Types
@@ -62,6 +79,26 @@ pub trait FileProvider {
     fn canonical_display(&self, path: &str) -> String {
         path.to_string()
     }
+    /// Timestamps and size of a file, for [`CoreBuildOptions::infer_dates`] and the attachment
+    /// plan; default: unknown.
+    fn metadata(&self, _path: &str) -> Option<FileMetadata> {
+        None
+    }
+    /// Every file below the folder `dir`, recursively, skipping hidden (`.`-prefixed) files and
+    /// folders, for the orphan check; default: `None` (cannot list, so no orphans are reported).
+    fn list_files(&self, _dir: &str) -> Option<Vec<String>> {
+        None
+    }
+}
+
+/// File timestamps and size reported by [`FileProvider::metadata`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileMetadata {
+    /// Creation (birth) time; not every filesystem records it.
+    pub created: Option<OffsetDateTime>,
+    pub modified: Option<OffsetDateTime>,
+    /// Size in bytes.
+    pub size: Option<u64>,
 }
 
 /// Build configuration options.
@@ -69,20 +106,76 @@ pub trait FileProvider {
 pub struct CoreBuildOptions {
     /// Include non-public documents (visibility not containing 'public'). If false, only public + entry.
     pub include_nonpublic: bool,
-    /// Flat mode: no nested pages/ path. (The caller can choose how to persist pages.)
+    /// Publish the pages of this audience too: those whose `visibility` lists it (e.g. `family`,
+    /// case-insensitive) alongside the public ones. Ignored with `include_nonpublic`.
+    pub audience: Option<String>,
+    /// The host seals the pages that are not public once they are rendered (the CLI's
+    /// `build --encrypt`), so keep them out of the public pages: their `recent_entries` sections
+    /// and translation links leave such pages out.
+    pub seal_private: bool,
+    /// Where pages are written, and so how they link to each other (see [`output`]).
+    pub layout: OutputLayout,
+    /// Shorthand for `layout: OutputLayout::Flat`, for hosts that only offer the flat switch.
     pub flat: bool,
     /// If true, warnings are still returned but *not* upgraded to errors here; the caller decides enforcement.
     pub strict: bool,
     /// When true, internal link rewrite will attempt cross-page rewriting. If false, leaves .md links intact.
     pub rewrite_links: bool,
+    /// Bodies larger than this (in bytes) skip attachment scanning and get a warning instead, so
+    /// entries embedding huge inline blobs don't stall the build. `None` uses [`DEFAULT_LARGE_BODY_BYTES`].
+    pub large_body_bytes: Option<usize>,
+    /// Typographic punctuation in bodies and titles: curly quotes and apostrophes, `--` / `---`
+    /// as en / em dashes, `...` as an ellipsis. Code is left alone.
+    pub smart_punctuation: bool,
+    /// Wrap images that stand alone in a paragraph as `<figure>`, captioned by an italic line
+    /// right below the image or, failing that, by the image's title text.
+    pub figures: bool,
+    /// Embed PDF attachments linked on a line of their own as an inline preview (with a download
+    /// link). Per link, the title `"preview"` / `"no-preview"` overrides this.
+    pub pdf_previews: bool,
+    /// Glob patterns (relative to the entry's folder, e.g. `templates/**`, `*.excalidraw.md`)
+    /// for files that are never traversed, built, or copied, even when linked; referencing one
+    /// is a warning. A pattern without `/` matches a file or folder name at any depth.
+    pub exclude: Vec<String>,
+    /// Only publish entries created on or after this date (`YYYY-MM-DD` or RFC 3339).
+    pub since: Option<String>,
+    /// Only publish entries created on or before this date (a bare date includes the whole day).
+    pub until: Option<String>,
+    /// Only publish entries carrying at least one of these tags (case-insensitive, `#` optional).
+    /// With `since` / `until` / `tags`, index pages are kept only when they lead to a selected
+    /// entry, and list only included children.
+    pub tags: Vec<String>,
+    /// The time of the build, for holding back drafts (`draft: true`) and entries whose
+    /// `published` time is after it (both listed in [`BuildSummary::held_back`]), together with the
+    /// entries reachable only through them. `None` publishes every entry.
+    pub now: Option<OffsetDateTime>,
+    /// With `now`, publish drafts as well.
+    pub drafts: bool,
+    /// With `now`, publish entries scheduled for later as well.
+    pub future: bool,
+    /// Treat the entry as the root index even without `this_file_is_root_index`, so a section
+    /// index builds as a site of its own subtree.
+    pub as_root: bool,
+    /// Variables substituted for `${NAME}` in `author` and in the `string` fields declared in
+    /// `diaryx.toml` (see [`interpolate`]). `None` leaves such references as written.
+    pub env: Option<BTreeMap<String, String>>,
+    /// Fill in a missing `created` (the file's creation time, else its modification time) and
+    /// `updated` (its modification time) from [`FileProvider::metadata`] instead of warning. Such
+    /// fields are listed in [`PageOutput::inferred`] and marked in the metadata block.
+    pub infer_dates: bool,
 }
 
+/// Default threshold for [`CoreBuildOptions::large_body_bytes`] (8 MiB).
+pub const DEFAULT_LARGE_BODY_BYTES: usize = 8 * 1024 * 1024;
+
 /// A single generated page artifact.
 #[derive(Debug, Clone, Serialize)]
 pub struct PageOutput {
     pub id: String,          // slug
     pub source_path: String, // original input path
     pub file_name: String,   // recommended html file name (e.g. "<slug>.html" or "index.html")
+    /// Site-relative output path under the build's [`OutputLayout`] (e.g. "pages/<slug>.html").
+    pub path: String,
     pub title: String,
     pub html: String,
     pub metadata_html: String, // rendered frontmatter (no outer <html>, CSS added by CLI)
@@ -91,14 +184,53 @@ pub struct PageOutput {
     pub parents: Vec<String>,  // parent slugs
     pub children: Vec<String>, // child slugs
     pub frontmatter: serde_yaml::Value,
-    pub warnings: Vec<String>, // warnings local to this page
+    /// `visibility` values (for an HTML page without frontmatter, those of the index listing it).
+    pub visibility: Vec<String>,
+    pub warnings: Vec<Warning>, // warnings local to this page
+    /// Each `reachable` item, classified and checked.
+    pub reachable: Vec<Reachability>,
+    /// License from `copying`, or inherited from the nearest ancestor index declaring one.
+    pub license: Option<License>,
+    /// Structured `author` entries (see [`parse_authors`]).
+    pub authors: Vec<Author>,
+    /// The `version` field as text (see [`version_text`]).
+    pub version: Option<String>,
+    /// Language of the page: its `lang` field, else the root index's.
+    pub lang: Option<String>,
+    /// Other language versions (from `translations`, followed both ways), in vault order.
+    pub translations: Vec<Translation>,
+    /// Name of the [`site::Layout`] the page is rendered with (`layout:` / `template:`).
+    pub layout: String,
+    /// Frontmatter fields the build filled in rather than read from the file (`created` /
+    /// `updated` with [`CoreBuildOptions::infer_dates`]).
+    pub inferred: Vec<String>,
+}
+
+/// A language variant of a page.
+#[derive(Debug, Clone, Serialize)]
+pub struct Translation {
+    pub lang: String,
+    pub id: String,
+    pub title: String,
+    /// Link to the variant, relative to the page that lists it.
+    pub href: String,
 }
 
-/// (Future) Attachment copy plan.
+/// An attachment to copy into the site, with what is known about it (for auditing what gets
+/// published).
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct AttachmentPlanEntry {
     pub source: String,
     pub target: String,
+    /// Size in bytes; `None` when the file's metadata could not be read.
+    pub size: Option<u64>,
+    /// MIME type guessed from the extension (`application/octet-stream` when unknown).
+    pub mime: String,
+    /// Hex SHA-256 of the contents. Core does not read attachments, so callers that do (the CLI
+    /// build) fill this in.
+    pub sha256: Option<String>,
+    /// Slugs of the pages referencing the attachment, in build order.
+    pub pages: Vec<String>,
 }
 
 /// The result of a build.
@@ -106,23 +238,477 @@ pub struct AttachmentPlanEntry {
 pub struct BuildArtifacts {
     pub pages: Vec<PageOutput>,
     pub attachments: Vec<AttachmentPlanEntry>,
-    pub warnings: Vec<String>, // global + collected per-page (flattened summary)
+    pub warnings: Vec<Warning>, // global + collected per-page (flattened summary)
+    pub multi_page: bool,
+    pub root_slug: Option<String>,
+}
+
+/// Site-wide facts known before the first page is emitted (handed to every [`PageSink`] call).
+#[derive(Debug, Clone, Serialize)]
+pub struct SiteShape {
+    pub multi_page: bool,
+    pub root_slug: Option<String>,
+    /// Total number of pages the build will emit.
+    pub page_count: usize,
+}
+
+/// Everything a streaming build produces except the pages themselves.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildSummary {
+    pub attachments: Vec<AttachmentPlanEntry>,
+    pub warnings: Vec<Warning>, // global + collected per-page (flattened summary)
     pub multi_page: bool,
     pub root_slug: Option<String>,
+    pub pages_emitted: usize,
+    /// Drafts and entries scheduled for later that the build left out.
+    pub held_back: Vec<HeldBack>,
+}
+
+/// An entry left out of a build as a draft or as scheduled for later (see
+/// [`CoreBuildOptions::now`]).
+#[derive(Debug, Clone, Serialize)]
+pub struct HeldBack {
+    pub source_path: String,
+    pub title: String,
+    /// When a scheduled entry is due (RFC 3339); `None` for a draft.
+    pub published: Option<String>,
+}
+
+/// Receiver for pages produced by [`build_site_streaming`].
+/// Implemented for any `FnMut(&SiteShape, PageOutput) -> Result<()>` closure.
+pub trait PageSink {
+    fn emit(&mut self, site: &SiteShape, page: PageOutput) -> Result<()>;
+}
+
+impl<F> PageSink for F
+where
+    F: FnMut(&SiteShape, PageOutput) -> Result<()>,
+{
+    fn emit(&mut self, site: &SiteShape, page: PageOutput) -> Result<()> {
+        self(site, page)
+    }
+}
+
+/// A single Diaryx file parsed on its own, without following `contents` / `part_of` links.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParsedDocument {
+    /// `title` from frontmatter, if present.
+    pub title: Option<String>,
+    /// Raw frontmatter (`Null` when there is none or it failed to parse).
+    pub frontmatter: serde_yaml::Value,
+    /// Markdown body after the frontmatter block.
+    pub body: String,
+    pub is_root_index: bool,
+    /// `contents` entries as written (Markdown link syntax).
+    pub contents: Vec<String>,
+    /// `part_of` entries as written.
+    pub part_of: Vec<String>,
+    pub visibility: Vec<String>,
+    /// Frontmatter problems (missing required fields, bad types, invalid YAML).
+    pub warnings: Vec<Warning>,
+}
+
+/// A single Diaryx file rendered on its own, for live previews (see [`render_document`]).
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderedDocument {
+    /// Slug the file's page gets in a build (from the file name).
+    pub slug: String,
+    /// `title` from frontmatter, else the file name.
+    pub title: String,
+    pub visibility: Vec<String>,
+    /// Raw frontmatter (`Null` when there is none or it failed to parse).
+    pub frontmatter: serde_yaml::Value,
+    /// Body as HTML: rendered Markdown with callouts, or the body as-is for `format: html`.
+    pub html: String,
+    /// Frontmatter problems (missing required fields, bad types, invalid YAML).
+    pub warnings: Vec<Warning>,
+}
+
+/// Outcome of [`validate_site`] and [`validate_graph`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationReport {
+    /// True when the vault produced no warnings.
+    pub valid: bool,
+    pub warnings: Vec<Warning>,
+    /// The same warnings, classified and attributed to their file.
+    pub diagnostics: Vec<Diagnostic>,
+    pub page_count: usize,
+}
+
+/// A build warning: the message shown to people, and a stable code for tools to filter on
+/// instead of matching the text (`missing-field`, `broken-link`, `reachable`, ...).
+///
+/// Serializes as its message, so the JSON of pages and builds keeps its shape; [`Diagnostic`]
+/// carries the code next to it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(into = "String")]
+pub struct Warning {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<Warning> for String {
+    fn from(warning: Warning) -> Self {
+        warning.message
+    }
+}
+
+/// A validation warning with its code and, when it concerns one entry, that entry's path.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub message: String,
+    pub path: Option<String>,
+}
+
+impl Diagnostic {
+    fn new(warning: &Warning, path: Option<&str>) -> Self {
+        Self {
+            code: warning.code,
+            message: warning.message.clone(),
+            path: path.map(str::to_string),
+        }
+    }
+}
+
+/// Progress notification delivered to the observer of [`build_site_observed`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BuildEvent<'a> {
+    /// A pipeline phase is starting.
+    Phase { phase: BuildPhase },
+    /// A page finished rendering (`done` of `total`).
+    Page {
+        done: usize,
+        total: usize,
+        source_path: &'a str,
+    },
+    /// A warning, reported as soon as it is known (it is also part of the final warning list).
+    Warning { message: &'a str },
+}
+
+/// Pipeline phases reported by [`BuildEvent::Phase`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildPhase {
+    /// Reading and parsing files reachable from the entry.
+    Collect,
+    /// Resolving `contents` / `part_of` into parents and children.
+    Link,
+    /// Rendering and emitting pages.
+    Render,
+}
+
+/// Parse one file's frontmatter and body. `path` is only used in warning messages.
+pub fn parse_document(path: &str, raw: String) -> ParsedDocument {
+    parse_document_with_schema(path, raw, &schema::Schema::default())
+}
+
+/// [`parse_document`], also checking the fields declared in a vault's `diaryx.toml`.
+pub fn parse_document_with_schema(
+    path: &str,
+    raw: String,
+    schema: &schema::Schema,
+) -> ParsedDocument {
+    let SourceParts {
+        frontmatter,
+        fm_struct,
+        body_md,
+        warnings,
+        ..
+    } = parse_source(path, raw, schema, None);
+    ParsedDocument {
+        is_root_index: fm_struct.this_file_is_root_index.unwrap_or(false),
+        contents: normalize_contents(&fm_struct.contents),
+        part_of: parse_part_of(&fm_struct.part_of),
+        visibility: normalize_string_or_list(&fm_struct.visibility),
+        title: fm_struct.title,
+        frontmatter,
+        body: body_md,
+        warnings,
+    }
+}
+
+/// Parse one file and render its body, without a vault: links and attachments are left as
+/// written, and nothing but the file itself is read. `path` names the file (for its slug and in
+/// warning messages).
+pub fn render_document(path: &str, raw: String) -> Result<RenderedDocument> {
+    let SourceParts {
+        frontmatter,
+        fm_struct,
+        body_md,
+        warnings,
+        ..
+    } = parse_source(path, raw, &schema::Schema::default(), None);
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let (html, document_title) = if is_html_format(&fm_struct.format) {
+        html_document_body(body_md)
+    } else {
+        let html = render_markdown(&body_md, &markdown::Options::default())?;
+        (render_callouts(html), None)
+    };
+    let stem = file_name
+        .rsplit_once('.')
+        .map_or(file_name, |(stem, _)| stem);
+    Ok(RenderedDocument {
+        slug: slugify(stem),
+        title: fm_struct
+            .title
+            .or(document_title)
+            .unwrap_or_else(|| file_name.to_string()),
+        visibility: normalize_string_or_list(&fm_struct.visibility),
+        frontmatter,
+        html,
+        warnings,
+    })
+}
+
+/// Run a full build from `entry` but keep only its warnings and page count.
+///
+/// Pages are dropped as they are produced, so this is cheap on memory for large vaults.
+pub fn validate_site(
+    entry: &str,
+    include_nonpublic: bool,
+    fs: &impl FileProvider,
+) -> Result<ValidationReport> {
+    let opts = CoreBuildOptions {
+        include_nonpublic,
+        ..Default::default()
+    };
+    let mut page_diagnostics = Vec::new();
+    let summary = build_site_streaming(entry, opts, fs, |_: &SiteShape, page: PageOutput| {
+        for message in &page.warnings {
+            page_diagnostics.push(Diagnostic::new(message, Some(&page.source_path)));
+        }
+        Ok(())
+    })?;
+    // Build-wide warnings come first in the summary, followed by each page's.
+    let global = summary.warnings.len() - page_diagnostics.len();
+    let mut diagnostics: Vec<Diagnostic> = summary.warnings[..global]
+        .iter()
+        .map(|message| Diagnostic::new(message, None))
+        .collect();
+    diagnostics.extend(page_diagnostics);
+    Ok(ValidationReport {
+        valid: summary.warnings.is_empty(),
+        page_count: summary.pages_emitted,
+        warnings: summary.warnings,
+        diagnostics,
+    })
+}
+
+/// Check frontmatter, `reachable`, licenses and the `contents` / `part_of` graph from `entry`
+/// without rendering anything: cheap enough to run on every edit. Body-level findings (missing
+/// attachments, translations without a `lang`) are only reported by [`validate_site`].
+pub fn validate_graph(
+    entry: &str,
+    include_nonpublic: bool,
+    fs: &impl FileProvider,
+) -> Result<ValidationReport> {
+    let opts = CoreBuildOptions {
+        include_nonpublic,
+        ..Default::default()
+    };
+    let mut warnings = Vec::new();
+    let exclusions = Exclusions::new(entry, &opts.exclude, fs)?;
+    let schema = schema::Schema::discover(entry, fs)?;
+    let mut docs = collect_documents(entry, &opts, &exclusions, &schema, fs, &mut warnings, None)?;
+    warnings.extend(orphan_warnings(entry, &docs, &exclusions, fs));
+    check_reachable(&mut docs, fs);
+    link_graph(&mut docs, fs);
+    check_part_of(&mut docs, fs);
+    assign_licenses(&mut docs);
+    if !include_nonpublic {
+        docs.retain(|d| d.is_public() || d.abs_path == entry);
+    }
+
+    let mut diagnostics: Vec<Diagnostic> = warnings
+        .iter()
+        .map(|message| Diagnostic::new(message, None))
+        .collect();
+    for d in &docs {
+        for message in &d.warnings {
+            diagnostics.push(Diagnostic::new(message, Some(&d.abs_path)));
+            warnings.push(message.clone());
+        }
+    }
+    Ok(ValidationReport {
+        valid: warnings.is_empty(),
+        page_count: docs.len(),
+        warnings,
+        diagnostics,
+    })
 }
 
 /// Build the site from a single entry file path.
+///
+/// Collects every page in memory; see [`build_site_streaming`] for large vaults.
 pub fn build_site(
     entry: &str,
     opts: CoreBuildOptions,
     fs: &impl FileProvider,
 ) -> Result<BuildArtifacts> {
+    let mut pages = Vec::new();
+    let summary = build_site_streaming(entry, opts, fs, |_: &SiteShape, page: PageOutput| {
+        pages.push(page);
+        Ok(())
+    })?;
+    Ok(BuildArtifacts {
+        pages,
+        attachments: summary.attachments,
+        warnings: summary.warnings,
+        multi_page: summary.multi_page,
+        root_slug: summary.root_slug,
+    })
+}
+
+/// Streaming variant of [`build_site`].
+///
+/// Only frontmatter and Markdown sources are held for the whole vault; each page is rendered,
+/// link-rewritten and handed to `sink` as soon as it is finished, then dropped. Pages arrive in
+/// traversal order. If rendering or the sink fails the build stops, and pages emitted so far
+/// have already been delivered.
+pub fn build_site_streaming(
+    entry: &str,
+    opts: CoreBuildOptions,
+    fs: &impl FileProvider,
+    sink: impl PageSink,
+) -> Result<BuildSummary> {
+    build_pages(
+        entry,
+        opts,
+        fs,
+        sink,
+        None,
+        &mut |_: &BuildEvent<'_>| Ok(()),
+    )
+}
+
+/// [`build_site_streaming`] reusing parses and renders from `cache` (see [`build_site_cached`]),
+/// for long-running callers that rebuild as files change.
+pub fn build_site_streaming_cached(
+    entry: &str,
+    opts: CoreBuildOptions,
+    fs: &impl FileProvider,
+    cache: &mut RenderCache,
+    sink: impl PageSink,
+) -> Result<BuildSummary> {
+    build_pages(entry, opts, fs, sink, Some(cache), &mut |_: &BuildEvent<
+        '_,
+    >| Ok(()))
+}
+
+/// Like [`build_site`], but reuses work from the previous build with the same `cache` (editors
+/// rebuilding on every keystroke): the frontmatter of unchanged files, Markdown renders of
+/// unchanged bodies, and the link graph while no page changed its links.
+///
+/// Link rewriting, attachments and metadata are always recomputed, so the result is identical to
+/// an uncached build.
+pub fn build_site_cached(
+    entry: &str,
+    opts: CoreBuildOptions,
+    fs: &impl FileProvider,
+    cache: &mut RenderCache,
+) -> Result<BuildArtifacts> {
+    build_site_observed(entry, opts, fs, Some(cache), |_: &BuildEvent<'_>| Ok(()))
+}
+
+/// [`build_site`] with progress reporting (and optionally a [`RenderCache`]).
+///
+/// `on_event` is called synchronously as phases start, pages finish, and warnings are found.
+/// Returning an error from it aborts the build with that error (e.g. to cancel a stale build).
+pub fn build_site_observed(
+    entry: &str,
+    opts: CoreBuildOptions,
+    fs: &impl FileProvider,
+    cache: Option<&mut RenderCache>,
+    mut on_event: impl FnMut(&BuildEvent<'_>) -> Result<()>,
+) -> Result<BuildArtifacts> {
+    let mut pages = Vec::new();
+    let summary = build_pages(
+        entry,
+        opts,
+        fs,
+        |_: &SiteShape, page: PageOutput| {
+            pages.push(page);
+            Ok(())
+        },
+        cache,
+        &mut on_event,
+    )?;
+    Ok(BuildArtifacts {
+        pages,
+        attachments: summary.attachments,
+        warnings: summary.warnings,
+        multi_page: summary.multi_page,
+        root_slug: summary.root_slug,
+    })
+}
+
+fn build_pages(
+    entry: &str,
+    opts: CoreBuildOptions,
+    fs: &impl FileProvider,
+    mut sink: impl PageSink,
+    mut cache: Option<&mut RenderCache>,
+    on_event: &mut dyn FnMut(&BuildEvent<'_>) -> Result<()>,
+) -> Result<BuildSummary> {
     // 1. Collect all documents (recursive if root index pattern)
+    on_event(&BuildEvent::Phase {
+        phase: BuildPhase::Collect,
+    })?;
     let mut warnings_global = Vec::new();
-    let mut docs = collect_documents(entry, &opts, fs, &mut warnings_global)?;
+    let entry_filter = EntryFilter::new(&opts)?;
+    let exclusions = Exclusions::new(entry, &opts.exclude, fs)?;
+    let schema = schema::Schema::discover(entry, fs)?;
+    if let Some(cache) = cache.as_deref_mut() {
+        cache.start(&schema);
+    }
+    let mut docs = collect_documents(
+        entry,
+        &opts,
+        &exclusions,
+        &schema,
+        fs,
+        &mut warnings_global,
+        cache.as_deref_mut().map(|c| &mut c.sources),
+    )?;
+    warnings_global.extend(orphan_warnings(entry, &docs, &exclusions, fs));
+    for warning in &warnings_global {
+        on_event(&BuildEvent::Warning {
+            message: &warning.message,
+        })?;
+    }
+
+    check_reachable(&mut docs, fs);
 
     // 2. Link graph (parents / children)
-    link_graph(&mut docs, fs);
+    on_event(&BuildEvent::Phase {
+        phase: BuildPhase::Link,
+    })?;
+    match cache.as_deref_mut() {
+        Some(cache) => cache.link(&mut docs, fs),
+        None => {
+            link_graph(&mut docs, fs);
+            check_part_of(&mut docs, fs);
+        }
+    }
+    assign_licenses(&mut docs);
 
     // 3. Filter by visibility (always keep entry)
     let entry_abs = entry.to_string();
@@ -133,272 +719,754 @@ pub fn build_site(
         .ok_or_else(|| anyhow!("Entry path not loaded: {entry_abs}"))?;
 
     if !opts.include_nonpublic {
-        docs.retain(|d| d.is_public() || d.abs_path == entry_abs);
+        let audience = opts.audience.as_deref();
+        docs.retain(|d| d.is_visible_to(audience) || d.abs_path == entry_abs);
     }
 
+    let held_back = match opts.now {
+        Some(now) => hold_back(&mut docs, &entry_abs, now, &opts, &schema.dates),
+        None => Vec::new(),
+    };
+
     if docs.is_empty() {
         return Err(anyhow!(
-            "No documents after filtering. Ensure visibility includes 'public' or enable include_nonpublic."
+            "No documents after filtering. Ensure visibility includes 'public' (or the audience) or enable include_nonpublic."
         ));
     }
-
-    // 4. Render HTML (already done in parse step) + rewrite links if requested
-    if opts.rewrite_links {
-        rewrite_internal_links(&mut docs, &opts);
+    if let Some(filter) = &entry_filter {
+        filter.apply(&mut docs, &entry_abs);
+        if docs.iter().all(|d| d.is_index || d.abs_path == entry_abs) {
+            let warning = Warning::new("other", "No entries match the date / tag filter");
+            on_event(&BuildEvent::Warning {
+                message: &warning.message,
+            })?;
+            warnings_global.push(warning);
+        }
+    }
+    if opts.smart_punctuation {
+        for d in &mut docs {
+            d.title = smarten(&d.title, &mut None);
+        }
     }
 
-    // 5. Determine root / multipage
-    let multi_page = docs.iter().any(|d| d.is_root_index) && docs.len() > 1;
-    let root_slug = docs.iter().find(|d| d.is_root_index).map(|d| d.id.clone());
+    // 4. Determine root / multipage; shared render state is built once for all pages
+    let ctx = RenderContext::new(&docs, &opts, schema);
+    let multi_page = ctx.multi_page;
+    let root_slug = ctx.root_slug.clone();
+    let site = SiteShape {
+        multi_page,
+        root_slug: root_slug.clone(),
+        page_count: docs.len(),
+    };
 
-    // 5b. Attachment/resource discovery & rewriting (non-.md relative links)
-    // This scans each rendered HTML body for src/href attributes pointing to relative,
-    // non-.md files (images, PDFs, etc.), assigns them a unique target under assets/,
-    // rewrites the HTML to point there (adjusting for nested layout), and produces an
-    // attachment copy plan.
-    let attachments = {
-        use once_cell::sync::Lazy;
-        use regex::Regex;
-        static RES_REF: Lazy<Regex> =
-            Lazy::new(|| Regex::new(r#"(?i)(src|href)="([^"]+)""#).unwrap());
-        use std::collections::{HashMap, HashSet};
-        let mut source_to_target: HashMap<String, String> = HashMap::new();
-        let mut used_names: HashSet<String> = HashSet::new();
+    // 5. Per-page pipeline: render -> rewrite internal links -> rewrite attachments -> emit
+    let mut planner = AttachmentPlanner {
+        exclusions,
+        ..AttachmentPlanner::default()
+    };
+    let mut aggregated: Vec<Warning> = warnings_global;
+    let mut emitted = 0usize;
+    on_event(&BuildEvent::Phase {
+        phase: BuildPhase::Render,
+    })?;
+    if let Some(cache) = cache.as_deref_mut() {
+        cache.begin(&docs);
+    }
+    let default_lang = docs
+        .iter()
+        .find(|d| d.is_root_index)
+        .and_then(|d| d.lang.clone());
+    // slug -> (lang, title) for translation links; variants filtered out above are dropped, and
+    // so are the ones a public page may not name (see `CoreBuildOptions::seal_private`).
+    let variants: HashMap<String, (Option<String>, String, bool)> = docs
+        .iter()
+        .filter(|d| !d.translations.is_empty())
+        .map(|d| {
+            let lang = d.lang.clone().or_else(|| default_lang.clone());
+            let sealed = opts.seal_private && !d.is_public();
+            (d.id.clone(), (lang, d.title.clone(), sealed))
+        })
+        .collect();
+    let recent = docs
+        .iter()
+        .any(|d| d.frontmatter.get("recent_entries").is_some())
+        .then(|| RecentIndex::new(&docs));
+    for mut d in docs.into_iter() {
+        let mut html = ctx
+            .render_body(&d, cache.as_deref_mut())
+            .with_context(|| format!("Markdown render failure: {}", d.abs_path))?;
+        if d.body_md.len() > ctx.large_body_bytes {
+            d.warnings.push(Warning::new(
+                "large-body",
+                format!(
+                    "Body exceeds {} bytes; attachment scanning skipped ({})",
+                    ctx.large_body_bytes, d.abs_path
+                ),
+            ));
+        } else {
+            let to_root = ctx.layout.to_root(&d.id);
+            html = planner.rewrite(fs, html, &d.id, &d.abs_path, &to_root, &mut d.warnings);
+        }
 
-        for doc in docs.iter_mut() {
-            // Fast skip if no candidate attributes
-            if !doc.html.contains("src=\"") && !doc.html.contains("href=\"") {
+        let lang = d.lang.clone().or_else(|| default_lang.clone());
+        let mut translations = Vec::new();
+        for slug in &d.translations {
+            let Some((variant_lang, title, sealed)) = variants.get(slug) else {
+                continue;
+            };
+            if *sealed && d.is_public() {
                 continue;
             }
-            let parent_dir = std::path::Path::new(&doc.abs_path)
-                .parent()
-                .unwrap_or(std::path::Path::new(""));
-
-            let mut new_html = String::with_capacity(doc.html.len());
-            let mut last = 0;
-
-            for cap in RES_REF.captures_iter(&doc.html) {
-                let m = cap.get(0).unwrap();
-                let attr_name = cap.get(1).unwrap().as_str();
-                let val = cap.get(2).unwrap().as_str();
-
-                // Write portion before this attribute match
-                new_html.push_str(&doc.html[last..m.start()]);
-
-                // Filter out values we do NOT treat as attachments
-                if val.is_empty()
-                    || val.starts_with('#')
-                    || val.starts_with('/')
-                    || val.starts_with("data:")
-                    || val.starts_with("mailto:")
-                    || val.contains("://")
-                {
-                    new_html.push_str(m.as_str());
-                    last = m.end();
-                    continue;
-                }
-
-                // Strip query / fragment for resolution, retain original for replacement basis
-                let core_val = val.split(|c| c == '?' || c == '#').next().unwrap_or(val);
-                let lower = core_val.to_ascii_lowercase();
-                if lower.ends_with(".md") || lower.ends_with(".html") || lower.ends_with(".htm") {
-                    // Skip site page links (.md rewritten, and generated .html/.htm pages)
-                    new_html.push_str(m.as_str());
-                    last = m.end();
-                    continue;
-                }
-
-                // Decode simple %20 for filesystem lookup
-                let decoded = core_val.replace("%20", " ");
-                let abs_path_buf = parent_dir.join(&decoded);
-                let abs_path_string = abs_path_buf.to_string_lossy().to_string();
-
-                if !abs_path_buf.exists() {
-                    doc.warnings
-                        .push(format!("Attachment not found: {}", abs_path_string));
-                    new_html.push_str(m.as_str());
-                    last = m.end();
-                    continue;
-                }
-                if abs_path_buf.is_dir() {
-                    doc.warnings.push(format!(
-                        "Attachment path is directory (skipped): {}",
-                        abs_path_string
-                    ));
-                    new_html.push_str(m.as_str());
-                    last = m.end();
-                    continue;
+            match variant_lang {
+                Some(variant_lang) if Some(variant_lang) != lang.as_ref() => {
+                    translations.push(Translation {
+                        lang: variant_lang.clone(),
+                        id: slug.clone(),
+                        title: title.clone(),
+                        href: ctx.layout.href(&d.id, slug),
+                    })
                 }
-
-                // Map / reuse target
-                let target_rel = if let Some(existing) = source_to_target.get(&abs_path_string) {
-                    existing.clone()
-                } else {
-                    // Assign new unique name under assets/
-                    let mut base_name = abs_path_buf
-                        .file_name()
-                        .and_then(|s| s.to_str())
-                        .unwrap_or("attachment")
-                        .to_string();
-
-                    if !used_names.insert(base_name.clone()) {
-                        // Collision: append -N before extension
-                        let (stem, ext) = if let Some((s, e)) = base_name.rsplit_once('.') {
-                            (s.to_string(), format!(".{}", e))
-                        } else {
-                            (base_name.clone(), "".to_string())
-                        };
-                        let mut counter = 1;
-                        loop {
-                            let candidate = format!("{}-{}{}", stem, counter, ext);
-                            if used_names.insert(candidate.clone()) {
-                                base_name = candidate;
-                                break;
-                            }
-                            counter += 1;
-                        }
-                    }
-                    let rel = format!("assets/{}", base_name);
-                    source_to_target.insert(abs_path_string.clone(), rel.clone());
-                    rel
-                };
-
-                // Compute path relative to page output location
-                let mut final_path = target_rel.clone();
-                if multi_page && !opts.flat && !doc.is_root_index {
-                    // child page lives under pages/
-                    final_path = format!("../{}", final_path);
-                }
-                // Re-encode spaces minimally (only spaces)
-                let encoded = final_path.replace(' ', "%20");
-
-                // Emit rewritten attribute
-                new_html.push_str(attr_name);
-                new_html.push_str("=\"");
-                new_html.push_str(&encoded);
-                new_html.push('"');
-
-                last = m.end();
+                _ => d.warnings.push(Warning::new(
+                    "translation",
+                    format!(
+                        "Translation '{slug}' needs its own `lang` to be listed ({})",
+                        d.abs_path
+                    ),
+                )),
             }
-            // Tail
-            new_html.push_str(&doc.html[last..]);
-            doc.html = new_html;
         }
-
-        // Convert mapping to plan
-        let mut plan: Vec<AttachmentPlanEntry> = source_to_target
-            .into_iter()
-            .map(|(source, target)| AttachmentPlanEntry { source, target })
-            .collect();
-        plan.sort_by(|a, b| a.target.cmp(&b.target));
-        plan
-    };
-
-    // 6. Produce PageOutput
-    let mut all_pages = Vec::new();
-    let mut aggregated: Vec<String> = warnings_global.clone();
-    for d in docs.into_iter() {
+        if let Some(recent) = &recent
+            && let Some(count) = recent_entries_count(&mut d)
+        {
+            html.push_str(&recent.render(&ctx, &d, count));
+        }
+        if let Some(warning) = ctx.layout.warning(&d.id) {
+            d.warnings.push(Warning::new("invalid-field", warning));
+        }
+        let layout = page_layout(&mut d);
         aggregated.extend(d.warnings.iter().cloned());
-        let file_name = if multi_page {
-            if d.is_root_index {
-                "index.html".to_string()
-            } else {
-                format!("{}.html", d.id)
-            }
-        } else {
-            // Single page site => always index.html
-            "index.html".to_string()
-        };
-        all_pages.push(PageOutput {
+        let path = ctx.layout.path(&d.id);
+        let file_name = path.rsplit('/').next().unwrap_or(&path).to_string();
+        let authors = d
+            .frontmatter
+            .get("author")
+            .map(parse_authors)
+            .unwrap_or_default();
+        let version = d.frontmatter.get("version").and_then(version_text);
+        let page = PageOutput {
+            metadata_html: build_metadata_html(&ctx, &d),
             id: d.id,
             source_path: d.abs_path,
             file_name,
+            path,
             title: d.title,
-            html: d.html,
-            metadata_html: build_metadata_html(
-                &d.frontmatter,
-                d.is_root_index,
-                d.is_index,
-                multi_page,
-                opts.flat,
-                &d.children,
-                &d.parents,
-                &d.raw_part_of,
-                root_slug.as_deref(),
-                &d.child_aliases,
-                &d.parent_aliases,
-            ),
+            html,
             is_root_index: d.is_root_index,
             is_index: d.is_index,
             parents: d.parents,
             children: d.children,
             frontmatter: d.frontmatter,
+            visibility: d.visibility,
             warnings: d.warnings,
-        });
+            reachable: d.reachable,
+            license: d.license,
+            authors,
+            version,
+            lang,
+            translations,
+            layout: layout.name().to_string(),
+            inferred: d.inferred,
+        };
+        for warning in &page.warnings {
+            on_event(&BuildEvent::Warning {
+                message: &warning.message,
+            })?;
+        }
+        on_event(&BuildEvent::Page {
+            done: emitted + 1,
+            total: site.page_count,
+            source_path: &page.source_path,
+        })?;
+        sink.emit(&site, page)?;
+        emitted += 1;
     }
 
-    Ok(BuildArtifacts {
-        pages: all_pages,
-        attachments,
+    Ok(BuildSummary {
+        attachments: planner.into_plan(fs),
         warnings: aggregated,
         multi_page,
         root_slug,
+        pages_emitted: emitted,
+        held_back,
     })
 }
 
-// -------------------------------------------------------------------------------------------------
-// Internal Document Model
-// -------------------------------------------------------------------------------------------------
+/// Entries listed when `recent_entries: true` gives no count.
+const DEFAULT_RECENT_ENTRIES: usize = 5;
+/// Excerpt length (characters) in recent-entries blocks.
+const EXCERPT_CHARS: usize = 200;
 
-#[derive(Debug, Deserialize, Default)]
-#[serde(default)]
-struct FrontmatterRaw {
-    title: Option<String>,
-    author: Option<serde_yaml::Value>,
-    created: Option<String>,
-    updated: Option<String>,
-    visibility: Option<serde_yaml::Value>,
-    format: Option<serde_yaml::Value>,
-    contents: Option<Vec<String>>,
-    part_of: Option<serde_yaml::Value>,
-    version: Option<String>,
-    copying: Option<String>,
-    tags: Option<Vec<String>>,
-    aliases: Option<Vec<String>>,
-    this_file_is_root_index: Option<bool>,
-    reachable: Option<serde_yaml::Value>,
-    // Additional fields ignored for now
+/// How many recent entries a page asks for with `recent_entries:` (a count, or `true`).
+fn recent_entries_count(doc: &mut Doc) -> Option<usize> {
+    use serde_yaml::Value;
+    match doc.frontmatter.get("recent_entries")? {
+        Value::Bool(true) => Some(DEFAULT_RECENT_ENTRIES),
+        Value::Bool(false) => None,
+        Value::Number(n) if n.as_u64().is_some_and(|n| n > 0) => n.as_u64().map(|n| n as usize),
+        _ => {
+            let warning = format!(
+                "Field 'recent_entries' should be a positive number or true ({})",
+                doc.abs_path
+            );
+            doc.warnings.push(Warning::new("invalid-field", warning));
+            None
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
-struct Doc {
-    id: String,
-    abs_path: String,
+/// Data behind `recent_entries` blocks, gathered once per build (and only when a page asks):
+/// every page's children, plus each dated entry with a plain-text excerpt.
+struct RecentIndex {
+    children: HashMap<String, Vec<String>>,
+    dated: HashMap<String, RecentEntry>,
+}
+
+struct RecentEntry {
     title: String,
-    visibility: Vec<String>,
-    #[allow(dead_code)]
-    tags: Vec<String>,
-    #[allow(dead_code)]
-    aliases: Vec<String>,
-    is_root_index: bool,
-    is_index: bool,
-    contents_raw: Vec<String>,
-    raw_part_of: Vec<String>,
-    children: Vec<String>,
-    parents: Vec<String>,
-    child_aliases: HashMap<String, String>,  // slug -> alias
-    parent_aliases: HashMap<String, String>, // slug -> alias
-    html: String,
-    frontmatter: serde_yaml::Value,
-    warnings: Vec<String>,
-    #[allow(dead_code)]
-    body_md: String,
+    created: OffsetDateTime,
+    excerpt: String,
+    public: bool,
 }
 
-impl Doc {
-    fn is_public(&self) -> bool {
+impl RecentIndex {
+    fn new(docs: &[Doc]) -> Self {
+        let children = docs
+            .iter()
+            .map(|d| (d.id.clone(), d.children.clone()))
+            .collect();
+        // Index pages are sections, not entries; undated pages cannot be ordered.
+        let dated = docs
+            .iter()
+            .filter(|d| !d.is_index)
+            .filter_map(|d| {
+                let created = parse_created(d.frontmatter.get("created")?.as_str()?)?;
+                let entry = RecentEntry {
+                    title: d.title.clone(),
+                    created,
+                    excerpt: excerpt(&d.body_md, d.html_body),
+                    public: d.is_public(),
+                };
+                Some((d.id.clone(), entry))
+            })
+            .collect();
+        Self { children, dated }
+    }
+
+    /// The `count` most recently created descendants of `doc`, newest first, as a list section.
+    /// With [`CoreBuildOptions::seal_private`], a public `doc` only lists public entries.
+    fn render(&self, ctx: &RenderContext, doc: &Doc, count: usize) -> String {
+        let public_only = ctx.seal_private && doc.is_public();
+        let mut seen: HashSet<&str> = HashSet::from([doc.id.as_str()]);
+        let mut queue: VecDeque<&str> = doc.children.iter().map(String::as_str).collect();
+        let mut found: Vec<(&str, &RecentEntry)> = Vec::new();
+        while let Some(slug) = queue.pop_front() {
+            if !seen.insert(slug) {
+                continue;
+            }
+            if let Some(entry) = self.dated.get(slug)
+                && (entry.public || !public_only)
+            {
+                found.push((slug, entry));
+            }
+            if let Some(children) = self.children.get(slug) {
+                queue.extend(children.iter().map(String::as_str));
+            }
+        }
+        found.sort_by(|a, b| b.1.created.cmp(&a.1.created).then(a.0.cmp(b.0)));
+        found.truncate(count);
+        if found.is_empty() {
+            return String::new();
+        }
+
+        let date_fmt =
+            time::format_description::parse("[month repr:long] [day padding:none], [year]")
+                .expect("valid date format");
+        let mut out = String::from("<section class=\"recent-entries\"><h2>Recent entries</h2><ul>");
+        for (slug, entry) in found {
+            let href = ctx.layout.href(&doc.id, slug);
+            out.push_str(&format!(
+                "<li><a href=\"{}\">{}</a> <time datetime=\"{}\">{}</time>",
+                html_escape_text(&href),
+                html_escape_text(&entry.title),
+                entry.created.date(),
+                entry.created.format(&date_fmt).unwrap_or_default()
+            ));
+            if !entry.excerpt.is_empty() {
+                out.push_str(&format!(
+                    "<p class=\"excerpt\">{}</p>",
+                    html_escape_text(&entry.excerpt)
+                ));
+            }
+            out.push_str("</li>");
+        }
+        out.push_str("</ul></section>");
+        out
+    }
+}
+
+/// `created` as an RFC 3339 timestamp or a shorthand [`parse_date`] accepts; a bare date is
+/// midnight, and a time without an offset UTC.
+fn parse_created(raw: &str) -> Option<OffsetDateTime> {
+    parse_date(raw, Time::MIDNIGHT, UtcOffset::UTC)
+}
+
+/// An RFC 3339 timestamp, or a common shorthand: a date (`2025-08-25`, `2025/8/25`), optionally
+/// followed by a time (`2025-08-25 14:30`, `2025-08-25T14:30:05`) and an offset (`Z`, `+02:00`,
+/// `+0200`). A bare date is read at `default_time`, and a time without an offset in
+/// `default_offset`.
+fn parse_date(raw: &str, default_time: Time, default_offset: UtcOffset) -> Option<OffsetDateTime> {
+    use time::format_description::well_known::Rfc3339;
+    static SHORTHAND: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"(?i)^(\d{4})[-/](\d{1,2})[-/](\d{1,2})(?:(?:T|\s+)(\d{1,2}):(\d{2})(?::(\d{2})(?:\.(\d{1,9}))?)?\s*(Z|[+-]\d{2}(?::?\d{2})?)?)?$",
+        )
+        .unwrap()
+    });
+    let raw = raw.trim();
+    if let Ok(at) = OffsetDateTime::parse(raw, &Rfc3339) {
+        return Some(at);
+    }
+    let c = SHORTHAND.captures(raw)?;
+    let field = |i: usize| c.get(i).and_then(|m| m.as_str().parse::<u32>().ok());
+    let month = time::Month::try_from(u8::try_from(field(2)?).ok()?).ok()?;
+    let date =
+        time::Date::from_calendar_date(field(1)? as i32, month, u8::try_from(field(3)?).ok()?)
+            .ok()?;
+    let time = match field(4) {
+        Some(hour) => {
+            let nanos = match c.get(7) {
+                Some(m) => format!("{:0<9}", m.as_str()).parse().ok()?,
+                None => 0,
+            };
+            Time::from_hms_nano(
+                u8::try_from(hour).ok()?,
+                u8::try_from(field(5)?).ok()?,
+                u8::try_from(field(6).unwrap_or(0)).ok()?,
+                nanos,
+            )
+            .ok()?
+        }
+        None => default_time,
+    };
+    let offset = match c.get(8) {
+        Some(m) => parse_offset(m.as_str())?,
+        None => default_offset,
+    };
+    Some(date.with_time(time).assume_offset(offset))
+}
+
+/// A UTC offset: `Z`, `UTC`, `+02:00`, `+0200` or `+02`.
+fn parse_offset(raw: &str) -> Option<UtcOffset> {
+    let raw = raw.trim();
+    if raw.eq_ignore_ascii_case("z") || raw.eq_ignore_ascii_case("utc") {
+        return Some(UtcOffset::UTC);
+    }
+    let (sign, rest) = match raw.as_bytes().first()? {
+        b'+' => (1, &raw[1..]),
+        b'-' => (-1, &raw[1..]),
+        _ => return None,
+    };
+    let digits = rest.replacen(':', "", 1);
+    if !matches!(digits.len(), 2 | 4) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i8 = digits[..2].parse().ok()?;
+    let minutes: i8 = match &digits[2..] {
+        "" => 0,
+        m => m.parse().ok()?,
+    };
+    UtcOffset::from_hms(sign * hours, sign * minutes, 0).ok()
+}
+
+/// Plain text of the first prose paragraph of a body (headings, code, tables and raw HTML
+/// blocks skipped; HTML bodies are stripped of tags), cut at a word boundary.
+pub fn excerpt(body: &str, html_body: bool) -> String {
+    static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+    static IMAGE: Lazy<Regex> = Lazy::new(|| Regex::new(r"!\[[^\]]*\]\([^)]*\)").unwrap());
+    static LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap());
+    static MARKS: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"[*_`~]+|^\s*(?:>|[-+]|\d+\.)\s+").unwrap());
+
+    let text = if html_body {
+        TAG.replace_all(body, " ").into_owned()
+    } else {
+        let mut paragraph: Vec<&str> = Vec::new();
+        let mut in_fence = false;
+        for line in body.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+                if !paragraph.is_empty() {
+                    break;
+                }
+                continue;
+            }
+            if in_fence {
+                continue;
+            }
+            if trimmed.is_empty() {
+                if !paragraph.is_empty() {
+                    break;
+                }
+                continue;
+            }
+            if paragraph.is_empty()
+                && (trimmed.starts_with('#')
+                    || trimmed.starts_with('|')
+                    || trimmed.starts_with('<'))
+            {
+                continue;
+            }
+            paragraph.push(trimmed);
+        }
+        let joined = paragraph
+            .iter()
+            .map(|line| MARKS.replace_all(line, "").into_owned())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let joined = IMAGE.replace_all(&joined, "");
+        LINK.replace_all(&joined, "$1").into_owned()
+    };
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= EXCERPT_CHARS {
+        return text;
+    }
+    let cut: String = text.chars().take(EXCERPT_CHARS).collect();
+    let cut = cut.rsplit_once(' ').map_or(cut.as_str(), |(head, _)| head);
+    format!("{}…", cut.trim_end_matches([',', ';', ':', '.']))
+}
+
+/// The layout a document asks for with `layout:` (or its alias `template:`); unknown names fall
+/// back to the default with a warning.
+fn page_layout(doc: &mut Doc) -> site::Layout {
+    let requested = ["layout", "template"]
+        .iter()
+        .find_map(|key| doc.frontmatter.get(*key).and_then(|v| v.as_str()));
+    let Some(name) = requested else {
+        return site::Layout::Default;
+    };
+    site::Layout::from_name(name).unwrap_or_else(|| {
+        let warning = format!(
+            "Unknown layout '{name}' (expected one of {}); using default ({})",
+            site::Layout::NAMES.join(", "),
+            doc.abs_path
+        );
+        doc.warnings.push(Warning::new("other", warning));
+        site::Layout::Default
+    })
+}
+
+/// Attachment/resource discovery & rewriting (non-.md relative links).
+///
+/// Scans rendered HTML for src/href attributes pointing to relative, non-page files (images,
+/// PDFs, etc.), assigns each a unique target under assets/, rewrites the HTML to point there
+/// (relative to the page's place in the layout), and accumulates the attachment copy plan across pages.
+#[derive(Default)]
+struct AttachmentPlanner {
+    source_to_target: HashMap<String, String>,
+    used_names: HashSet<String>,
+    pages: HashMap<String, Vec<String>>, // source -> referencing page slugs
+    exclusions: Exclusions,
+}
+
+impl AttachmentPlanner {
+    fn rewrite(
+        &mut self,
+        fs: &impl FileProvider,
+        html: String,
+        page_id: &str,
+        source_path: &str,
+        to_root: &str,
+        warnings: &mut Vec<Warning>,
+    ) -> String {
+        static RES_REF: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r#"(?i)(src|href)="([^"]+)""#).unwrap());
+
+        // Fast skip if no candidate attributes
+        if !html.contains("src=\"") && !html.contains("href=\"") {
+            return html;
+        }
+        let parent_dir = std::path::Path::new(source_path)
+            .parent()
+            .unwrap_or(std::path::Path::new(""));
+
+        let mut new_html = String::with_capacity(html.len());
+        let mut last = 0;
+
+        for cap in RES_REF.captures_iter(&html) {
+            let m = cap.get(0).unwrap();
+            let attr_name = cap.get(1).unwrap().as_str();
+            let val = cap.get(2).unwrap().as_str();
+
+            // Write portion before this attribute match
+            new_html.push_str(&html[last..m.start()]);
+
+            // Filter out values we do NOT treat as attachments
+            if val.is_empty()
+                || val.starts_with('#')
+                || val.starts_with('/')
+                || val.starts_with("data:")
+                || val.starts_with("mailto:")
+                || val.contains("://")
+            {
+                new_html.push_str(m.as_str());
+                last = m.end();
+                continue;
+            }
+
+            // Strip query / fragment for resolution, retain original for replacement basis
+            let core_val = val.split(['?', '#']).next().unwrap_or(val);
+            // Decode simple %20 for filesystem lookup
+            let decoded = core_val.replace("%20", " ");
+            let abs_path_buf = parent_dir.join(&decoded);
+            let abs_path_string = abs_path_buf.to_string_lossy().to_string();
+            let lower = core_val.to_ascii_lowercase();
+            let html_link = lower.ends_with(".html") || lower.ends_with(".htm");
+
+            // Links already rewritten to generated pages (.html) only ever point at built pages
+            if !html_link && self.exclusions.matches(&abs_path_string) {
+                warnings.push(Warning::new(
+                    "excluded",
+                    format!(
+                        "Excluded file referenced: {} (from {})",
+                        abs_path_string, source_path
+                    ),
+                ));
+                new_html.push_str(m.as_str());
+                last = m.end();
+                continue;
+            }
+            if html_link || lower.ends_with(".md") {
+                // Skip site page links (.md rewritten, and generated .html/.htm pages)
+                new_html.push_str(m.as_str());
+                last = m.end();
+                continue;
+            }
+
+            // Attachments are looked up on disk and through the provider: in-memory providers
+            // usually hold only the Markdown, while a vault in object storage is only reachable
+            // through its provider.
+            let on_disk = abs_path_buf.exists();
+            if !on_disk && !fs.exists(&abs_path_string) {
+                warnings.push(Warning::new(
+                    "missing-attachment",
+                    format!("Attachment not found: {}", abs_path_string),
+                ));
+                new_html.push_str(m.as_str());
+                last = m.end();
+                continue;
+            }
+            if (on_disk && abs_path_buf.is_dir()) || (!on_disk && !fs.is_file(&abs_path_string)) {
+                warnings.push(Warning::new(
+                    "missing-attachment",
+                    format!(
+                        "Attachment path is directory (skipped): {}",
+                        abs_path_string
+                    ),
+                ));
+                new_html.push_str(m.as_str());
+                last = m.end();
+                continue;
+            }
+
+            // Map / reuse target
+            let pages = self.pages.entry(abs_path_string.clone()).or_default();
+            if !pages.iter().any(|p| p == page_id) {
+                pages.push(page_id.to_string());
+            }
+            let target_rel = if let Some(existing) = self.source_to_target.get(&abs_path_string) {
+                existing.clone()
+            } else {
+                // Assign new unique name under assets/
+                let mut base_name = abs_path_buf
+                    .file_name()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("attachment")
+                    .to_string();
+
+                if !self.used_names.insert(base_name.clone()) {
+                    // Collision: append -N before extension
+                    let (stem, ext) = if let Some((s, e)) = base_name.rsplit_once('.') {
+                        (s.to_string(), format!(".{}", e))
+                    } else {
+                        (base_name.clone(), "".to_string())
+                    };
+                    let mut counter = 1;
+                    loop {
+                        let candidate = format!("{}-{}{}", stem, counter, ext);
+                        if self.used_names.insert(candidate.clone()) {
+                            base_name = candidate;
+                            break;
+                        }
+                        counter += 1;
+                    }
+                }
+                let rel = format!("assets/{}", base_name);
+                self.source_to_target
+                    .insert(abs_path_string.clone(), rel.clone());
+                rel
+            };
+
+            // Compute path relative to page output location
+            let final_path = format!("{to_root}{target_rel}");
+            // Re-encode spaces minimally (only spaces)
+            let encoded = final_path.replace(' ', "%20");
+
+            // Emit rewritten attribute
+            new_html.push_str(attr_name);
+            new_html.push_str("=\"");
+            new_html.push_str(&encoded);
+            new_html.push('"');
+
+            last = m.end();
+        }
+        // Tail
+        new_html.push_str(&html[last..]);
+        new_html
+    }
+
+    /// Convert the accumulated mapping into a deterministic copy plan.
+    fn into_plan(mut self, fs: &impl FileProvider) -> Vec<AttachmentPlanEntry> {
+        let mut plan: Vec<AttachmentPlanEntry> = self
+            .source_to_target
+            .into_iter()
+            .map(|(source, target)| AttachmentPlanEntry {
+                size: fs.metadata(&source).and_then(|m| m.size),
+                mime: mime_type(&source).to_string(),
+                sha256: None,
+                pages: self.pages.remove(&source).unwrap_or_default(),
+                source,
+                target,
+            })
+            .collect();
+        plan.sort_by(|a, b| a.target.cmp(&b.target));
+        plan
+    }
+}
+
+/// MIME type for a file name, from its extension.
+pub fn mime_type(path: &str) -> &'static str {
+    let ext = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        "heic" => "image/heic",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "wav" => "audio/wav",
+        "ogg" | "oga" => "audio/ogg",
+        "flac" => "audio/flac",
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "txt" => "text/plain",
+        "csv" => "text/csv",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "zip" => "application/zip",
+        "epub" => "application/epub+zip",
+        "woff2" => "font/woff2",
+        "woff" => "font/woff",
+        "ttf" => "font/ttf",
+        _ => "application/octet-stream",
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// Internal Document Model
+// -------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+struct FrontmatterRaw {
+    title: Option<String>,
+    author: Option<serde_yaml::Value>,
+    created: Option<String>,
+    updated: Option<String>,
+    visibility: Option<serde_yaml::Value>,
+    format: Option<serde_yaml::Value>,
+    contents: Option<Vec<String>>,
+    part_of: Option<serde_yaml::Value>,
+    version: Option<serde_yaml::Value>,
+    copying: Option<String>,
+    tags: Option<Vec<String>>,
+    aliases: Option<Vec<String>>,
+    lang: Option<String>,
+    translations: Option<serde_yaml::Value>,
+    this_file_is_root_index: Option<bool>,
+    reachable: Option<serde_yaml::Value>,
+    // Additional fields ignored for now
+}
+
+/// A collected source file. Docs own their fields; rendering consumes each one, moving them
+/// into its `PageOutput` rather than cloning.
+#[derive(Debug, Clone)]
+struct Doc {
+    id: String,
+    abs_path: String,
+    title: String,
+    visibility: Vec<String>,
+    tags: Vec<String>,
+    #[allow(dead_code)]
+    aliases: Vec<String>,
+    is_root_index: bool,
+    is_index: bool,
+    contents_raw: Vec<String>,
+    raw_part_of: Vec<String>,
+    lang: Option<String>,
+    raw_translations: Vec<String>,
+    translations: Vec<String>, // slugs of the other language variants
+    html_body: bool,           // body is raw HTML (passed through, not rendered as Markdown)
+    children: Vec<String>,
+    parents: Vec<String>,
+    child_aliases: HashMap<String, String>,  // slug -> alias
+    parent_aliases: HashMap<String, String>, // slug -> alias
+    contents_alias_by_name: HashMap<String, String>, // normalized target basename -> alias
+    part_of_alias_by_name: HashMap<String, String>, // normalized target basename -> alias
+    frontmatter: serde_yaml::Value,
+    warnings: Vec<Warning>,
+    reachable: Vec<Reachability>,
+    license: Option<License>,
+    body_md: String, // rendered lazily at emission time
+    inferred: Vec<String>,
+}
+
+impl Doc {
+    fn is_public(&self) -> bool {
         self.visibility.iter().any(|v| v == "public")
     }
+
+    /// Public, or listing `audience` in its `visibility`.
+    fn is_visible_to(&self, audience: Option<&str>) -> bool {
+        self.is_public()
+            || audience.is_some_and(|audience| {
+                self.visibility
+                    .iter()
+                    .any(|v| v.trim().eq_ignore_ascii_case(audience.trim()))
+            })
+    }
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -407,78 +1475,99 @@ impl Doc {
 
 fn collect_documents(
     entry: &str,
-    _opts: &CoreBuildOptions,
+    opts: &CoreBuildOptions,
+    exclusions: &Exclusions,
+    schema: &schema::Schema,
     fs: &impl FileProvider,
-    warnings_global: &mut Vec<String>,
+    warnings_global: &mut Vec<Warning>,
+    mut sources: Option<&mut SourceCache>,
 ) -> Result<Vec<Doc>> {
-    let mut queue = VecDeque::new();
+    let (as_root, env) = (opts.as_root, opts.env.as_ref());
+    // Each queued path carries the visibility of the index listing it, inherited by plain HTML
+    // files that have no frontmatter of their own.
+    let mut queue: VecDeque<(String, Vec<String>)> = VecDeque::new();
     let mut visited: HashMap<String, Doc> = HashMap::new();
     let mut order: Vec<String> = Vec::new();
+    // Decided by the entry: whether indexes expand their contents at all
+    let mut site_mode = false;
 
-    queue.push_back(entry.to_string());
+    queue.push_back((entry.to_string(), Vec::new()));
 
-    while let Some(path) = queue.pop_front() {
+    while let Some((path, listed_visibility)) = queue.pop_front() {
         if visited.contains_key(&path) {
             continue;
         }
         if !fs.exists(&path) {
-            warnings_global.push(format!("Entry or referenced path missing: {path}"));
+            warnings_global.push(Warning::new(
+                "broken-link",
+                format!("Entry or referenced path missing: {path}"),
+            ));
             continue;
         }
         if !fs.is_file(&path) {
-            warnings_global.push(format!("Skipping non-file path: {path}"));
+            warnings_global.push(Warning::new(
+                "skipped-file",
+                format!("Skipping non-file path: {path}"),
+            ));
             continue;
         }
 
-        // Skip non-markdown
-        if let Some(ext) = fs.extension_lowercase(&path) {
-            if ext != "md" {
-                // Non-diaryx file – skip silently (user-level decision to add warning if needed)
+        // Markdown and HTML pages only
+        let html_file = match fs.extension_lowercase(&path).as_deref() {
+            Some("md") => false,
+            Some("html" | "htm") => true,
+            _ => {
+                warnings_global.push(Warning::new(
+                    "skipped-file",
+                    format!("Skipping {path}: only .md and .html files can be pages"),
+                ));
                 continue;
             }
-        } else {
-            // No extension, assume not Diaryx
-            continue;
-        }
+        };
 
         let raw = match fs.read_to_string(&path) {
             Ok(s) => s,
             Err(e) => {
-                warnings_global.push(format!("Failed to read {path}: {e}"));
+                warnings_global.push(Warning::new(
+                    "read-failed",
+                    format!("Failed to read {path}: {e}"),
+                ));
                 continue;
             }
         };
 
-        let mut doc_warnings = Vec::new();
-        let split = match split_frontmatter(&raw) {
-            Ok(s) => s,
-            Err(e) => {
-                doc_warnings.push(format!("Unterminated YAML frontmatter block ({path}): {e}"));
-                SplitFrontmatter {
-                    frontmatter_yaml: None,
-                    body_md: raw.to_string(),
-                }
-            }
-        };
-        let (fm_val, fm_struct, fm_warns) = match parse_frontmatter(&split.frontmatter_yaml) {
-            Ok(v) => v,
-            Err(e) => {
-                doc_warnings.push(format!("Invalid YAML frontmatter: {} ({path})", e));
-                (
-                    serde_yaml::Value::Null,
-                    FrontmatterRaw::default(),
-                    Vec::new(),
-                )
-            }
+        let times = opts.infer_dates.then(|| fs.metadata(&path)).flatten();
+        let SourceParts {
+            frontmatter: mut fm_val,
+            mut fm_struct,
+            mut body_md,
+            warnings: mut doc_warnings,
+            inferred,
+        } = match sources.as_deref_mut() {
+            Some(sources) => sources.parse(&path, raw, html_file, schema, times),
+            None if html_file => parse_html_source(&path, raw, schema, times),
+            None => parse_source(&path, raw, schema, times),
         };
-        for w in fm_warns {
-            doc_warnings.push(format!("{} ({path})", w));
+        if let Some(env) = env {
+            interpolate_fields(
+                &mut fm_val,
+                &mut fm_struct,
+                schema,
+                env,
+                &path,
+                &mut doc_warnings,
+            );
+        }
+        let html_body = html_file || is_html_format(&fm_struct.format);
+        let mut document_title = None;
+        if html_body {
+            (body_md, document_title) = html_document_body(body_md);
         }
-        check_required(&fm_struct, &mut doc_warnings, &path);
 
         let title = fm_struct
             .title
-            .clone()
+            .take()
+            .or(document_title)
             .unwrap_or_else(|| fs.file_name(&path).unwrap_or_else(|| path.clone()));
         // Derive slug from filename stem instead of title to ensure stable cross-file linking / alias resolution
         // (prevents mismatch when title differs from physical filename used in links)
@@ -492,12 +1581,26 @@ fn collect_documents(
             slugify(&stem)
         };
 
-        let html = render_markdown(&split.body_md)
-            .with_context(|| format!("Markdown render failure: {path}"))?;
-
-        let visibility = normalize_string_or_list(&fm_struct.visibility);
+        let visibility = if html_file && fm_val.is_null() {
+            listed_visibility
+        } else {
+            normalize_string_or_list(&fm_struct.visibility)
+        };
         let contents_norm = normalize_contents(&fm_struct.contents);
-        let is_root = fm_struct.this_file_is_root_index.unwrap_or(false);
+        let is_entry = path == entry;
+        let declared_root = fm_struct.this_file_is_root_index.unwrap_or(false);
+        let promoted = as_root && is_entry && !declared_root;
+        let is_root = is_entry && (declared_root || promoted);
+        if is_root {
+            site_mode = true;
+        }
+        // A promoted section index heads its own site; its parents are not part of the build.
+        if promoted {
+            fm_struct.part_of = None;
+            if let Some(map) = fm_val.as_mapping_mut() {
+                map.remove("part_of");
+            }
+        }
 
         let doc = Doc {
             id: slug,
@@ -510,42 +1613,59 @@ fn collect_documents(
             is_index: !contents_norm.is_empty(),
             contents_raw: contents_norm,
             raw_part_of: parse_part_of(&fm_struct.part_of),
+            lang: fm_struct
+                .lang
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty()),
+            raw_translations: parse_part_of(&fm_struct.translations),
+            translations: Vec::new(),
+            html_body,
             children: Vec::new(),
             parents: Vec::new(),
             child_aliases: HashMap::new(),
             parent_aliases: HashMap::new(),
-            html,
+            contents_alias_by_name: HashMap::new(),
+            part_of_alias_by_name: HashMap::new(),
             frontmatter: fm_val,
             warnings: doc_warnings,
-            body_md: split.body_md,
+            reachable: Vec::new(),
+            license: None,
+            body_md,
+            inferred,
         };
 
-        let is_index = doc.is_index;
-        let is_root_index = doc.is_root_index;
-        let contents_links = doc.contents_raw.clone();
-
         order.push(path.clone());
         visited.insert(path.clone(), doc);
+        let doc = &visited[&path];
 
-        // Recursion gate
-        if is_index && (is_root_index || entry_metadata_had_root(entry, &visited)) {
-            if let Some(parent_dir) = fs.parent(&path) {
-                for raw_link in contents_links {
-                    if let Some(resolved) = resolve_contents_link(&raw_link, &parent_dir, fs) {
-                        if fs.exists(&resolved) && fs.is_file(&resolved) {
-                            queue.push_back(resolved);
-                        } else {
-                            warnings_global.push(format!(
+        // Recursion gate: in a site, every index expands its own contents
+        if site_mode
+            && doc.is_index
+            && let Some(parent_dir) = fs.parent(&path)
+        {
+            for raw_link in &doc.contents_raw {
+                if let Some(resolved) = resolve_contents_link(raw_link, &parent_dir, fs) {
+                    if exclusions.matches(&resolved) {
+                        warnings_global.push(Warning::new(
+                            "excluded",
+                            format!("Excluded file referenced: {} (from {})", resolved, path),
+                        ));
+                    } else if fs.exists(&resolved) && fs.is_file(&resolved) {
+                        queue.push_back((resolved, doc.visibility.clone()));
+                    } else {
+                        warnings_global.push(Warning::new(
+                            "other",
+                            format!(
                                 "contents target not found or not a file: {} (from {})",
                                 resolved, path
-                            ));
-                        }
-                    } else {
-                        warnings_global.push(format!(
-                            "Could not parse contents entry '{}' in {}",
-                            raw_link, path
+                            ),
                         ));
                     }
+                } else {
+                    warnings_global.push(Warning::new(
+                        "broken-link",
+                        format!("Could not parse contents entry '{}' in {}", raw_link, path),
+                    ));
                 }
             }
         }
@@ -561,72 +1681,608 @@ fn collect_documents(
     Ok(out)
 }
 
-fn entry_metadata_had_root(entry: &str, visited: &HashMap<String, Doc>) -> bool {
-    visited.get(entry).map(|d| d.is_root_index).unwrap_or(false)
-}
-
-struct SplitFrontmatter {
-    frontmatter_yaml: Option<String>,
-    body_md: String,
+/// Entry selection from [`CoreBuildOptions::since`], [`CoreBuildOptions::until`] and
+/// [`CoreBuildOptions::tags`]; entries are judged by their `created` date and `tags`.
+struct EntryFilter {
+    since: Option<OffsetDateTime>,
+    until: Option<OffsetDateTime>,
+    tags: Vec<String>,
 }
 
-fn split_frontmatter(raw: &str) -> Result<SplitFrontmatter> {
-    let mut lines = raw.lines();
-    if lines.next() != Some("---") {
-        return Ok(SplitFrontmatter {
-            frontmatter_yaml: None,
-            body_md: raw.to_string(),
-        });
+impl EntryFilter {
+    /// `None` when no filter option is set.
+    fn new(opts: &CoreBuildOptions) -> Result<Option<Self>> {
+        let tags: Vec<String> = opts
+            .tags
+            .iter()
+            .map(|t| normalize_tag(t))
+            .filter(|t| !t.is_empty())
+            .collect();
+        if opts.since.is_none() && opts.until.is_none() && tags.is_empty() {
+            return Ok(None);
+        }
+        let parse = |raw: &str| {
+            parse_created(raw).ok_or_else(|| {
+                anyhow!("Invalid date '{raw}' (expected YYYY-MM-DD or an RFC 3339 timestamp)")
+            })
+        };
+        let since = opts.since.as_deref().map(parse).transpose()?;
+        let until = match opts.until.as_deref() {
+            // A bare date covers that whole day
+            Some(raw) if raw.trim().len() == 10 => {
+                Some(parse(raw)? + time::Duration::days(1) - time::Duration::nanoseconds(1))
+            }
+            Some(raw) => Some(parse(raw)?),
+            None => None,
+        };
+        Ok(Some(Self { since, until, tags }))
     }
-    let mut yaml = Vec::new();
-    let mut body = Vec::new();
-    let mut in_yaml = true;
-    for line in raw.lines().skip(1) {
-        if in_yaml {
-            if line == "---" {
-                in_yaml = false;
-                continue;
+
+    fn selects(&self, doc: &Doc) -> bool {
+        if self.since.is_some() || self.until.is_some() {
+            let Some(created) = doc
+                .frontmatter
+                .get("created")
+                .and_then(|v| v.as_str())
+                .and_then(parse_created)
+            else {
+                return false;
+            };
+            if self.since.is_some_and(|s| created < s) || self.until.is_some_and(|u| created > u) {
+                return false;
             }
-            yaml.push(line);
-        } else {
-            body.push(line);
         }
+        self.tags.is_empty()
+            || doc
+                .tags
+                .iter()
+                .any(|t| self.tags.contains(&normalize_tag(t)))
     }
-    if in_yaml {
-        return Err(anyhow!("Unterminated frontmatter block"));
+
+    /// Keep the entry, selected entries and the indexes leading to them; drop links to the rest.
+    fn apply(&self, docs: &mut Vec<Doc>, entry_abs: &str) {
+        let mut kept: HashSet<String> = docs
+            .iter()
+            .filter(|d| d.abs_path == entry_abs || (!d.is_index && self.selects(d)))
+            .map(|d| d.id.clone())
+            .collect();
+        loop {
+            let reached: Vec<String> = docs
+                .iter()
+                .filter(|d| d.is_index && !kept.contains(&d.id))
+                .filter(|d| d.children.iter().any(|c| kept.contains(c)))
+                .map(|d| d.id.clone())
+                .collect();
+            if reached.is_empty() {
+                break;
+            }
+            kept.extend(reached);
+        }
+        docs.retain(|d| kept.contains(&d.id));
+        for d in docs.iter_mut() {
+            d.children.retain(|c| kept.contains(c));
+            d.parents.retain(|p| kept.contains(p));
+        }
     }
-    Ok(SplitFrontmatter {
-        frontmatter_yaml: Some(yaml.join("\n")),
-        body_md: body.join("\n"),
-    })
 }
 
-fn parse_frontmatter(
-    yaml_opt: &Option<String>,
-) -> Result<(serde_yaml::Value, FrontmatterRaw, Vec<String>)> {
-    if let Some(yaml) = yaml_opt {
-        if yaml.trim().is_empty() {
-            return Ok((
-                serde_yaml::Value::Null,
-                FrontmatterRaw::default(),
-                Vec::new(),
-            ));
+/// Remove drafts (unless [`CoreBuildOptions::drafts`]) and entries `published` after `now`
+/// (unless [`CoreBuildOptions::future`]) from `docs`, along with the entries reachable only
+/// through them and links to them all; the entry is always kept. Returns the drafts and scheduled
+/// entries removed.
+fn hold_back(
+    docs: &mut Vec<Doc>,
+    entry_abs: &str,
+    now: OffsetDateTime,
+    opts: &CoreBuildOptions,
+    dates: &schema::DateConfig,
+) -> Vec<HeldBack> {
+    use time::format_description::well_known::Rfc3339;
+    let mut held = Vec::new();
+    let mut removed = HashSet::new();
+    for d in docs.iter_mut() {
+        if d.abs_path == entry_abs {
+            continue;
         }
-        let value: serde_yaml::Value =
-            serde_yaml::from_str(yaml).context("Invalid YAML frontmatter")?;
-        let mut warnings = Vec::new();
-        let fm_struct: FrontmatterRaw = match serde_yaml::from_value(value.clone()) {
-            Ok(v) => v,
-            Err(e) => {
-                warnings.push(format!(
-                    "Frontmatter shape mismatch; defaults applied: {}",
-                    e
-                ));
-                FrontmatterRaw::default()
+        let draft = d.frontmatter.get("draft").and_then(|v| v.as_bool()) == Some(true);
+        let published = match d.frontmatter.get("published") {
+            None | Some(serde_yaml::Value::Null) => None,
+            Some(value) => {
+                let at = value.as_str().and_then(|raw| dates.parse(raw));
+                if at.is_none() {
+                    d.warnings.push(Warning::new("invalid-field", format!(
+                        "Field 'published' should be a date or timestamp; the entry is published as if it had none ({})",
+                        d.abs_path
+                    )));
+                }
+                at
             }
         };
-        Ok((value, fm_struct, warnings))
-    } else {
+        let published = if draft && !opts.drafts {
+            None
+        } else if let Some(at) = published.filter(|at| *at > now && !opts.future) {
+            Some(at.format(&Rfc3339).unwrap_or_default())
+        } else {
+            continue;
+        };
+        removed.insert(d.id.clone());
+        held.push(HeldBack {
+            source_path: d.abs_path.clone(),
+            title: d.title.clone(),
+            published,
+        });
+    }
+    if !removed.is_empty() {
+        // Entries linked only from held-back ones would otherwise be published unlisted.
+        let before = reachable_from(docs, entry_abs, &HashSet::new());
+        let after = reachable_from(docs, entry_abs, &removed);
+        removed.extend(before.difference(&after).cloned());
+        docs.retain(|d| !removed.contains(&d.id));
+        for d in docs.iter_mut() {
+            d.children.retain(|c| !removed.contains(c));
+            d.parents.retain(|p| !removed.contains(p));
+        }
+    }
+    held
+}
+
+/// Ids of the docs reachable from the entry through `contents` links, not passing through
+/// `skipped`.
+fn reachable_from(docs: &[Doc], entry_abs: &str, skipped: &HashSet<String>) -> HashSet<String> {
+    let by_id: HashMap<&str, &Doc> = docs.iter().map(|d| (d.id.as_str(), d)).collect();
+    let mut seen = HashSet::new();
+    let mut queue: VecDeque<&Doc> = docs.iter().filter(|d| d.abs_path == entry_abs).collect();
+    while let Some(doc) = queue.pop_front() {
+        if !seen.insert(doc.id.clone()) {
+            continue;
+        }
+        for child in &doc.children {
+            if let Some(next) = by_id
+                .get(child.as_str())
+                .filter(|c| !skipped.contains(&c.id))
+            {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen
+}
+
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
+
+/// Compiled [`CoreBuildOptions::exclude`] patterns, matched against paths relative to the
+/// entry's folder (`..` and `.` resolved lexically).
+#[derive(Default)]
+struct Exclusions {
+    root: String,
+    /// Patterns with a `/` match the whole relative path, others any single component.
+    patterns: Vec<(glob::Pattern, bool)>,
+}
+
+impl Exclusions {
+    fn new(entry: &str, patterns: &[String], fs: &impl FileProvider) -> Result<Self> {
+        let mut compiled = Vec::with_capacity(patterns.len());
+        for raw in patterns {
+            let trimmed = raw.trim().trim_start_matches("./").trim_start_matches('/');
+            if trimmed.is_empty() {
+                continue;
+            }
+            // `drafts/` excludes the folder's contents, like `drafts/**`
+            let pattern = match trimmed.strip_suffix('/') {
+                Some(dir) => format!("{dir}/**"),
+                None => trimmed.to_string(),
+            };
+            let whole_path = pattern.contains('/');
+            let glob = glob::Pattern::new(&pattern)
+                .with_context(|| format!("Invalid exclude pattern '{raw}'"))?;
+            compiled.push((glob, whole_path));
+        }
+        Ok(Self {
+            root: normalize_lexically(&fs.parent(entry).unwrap_or_default()),
+            patterns: compiled,
+        })
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+        let path = normalize_lexically(path);
+        let rel = if self.root.is_empty() {
+            Some(path.as_str())
+        } else {
+            path.strip_prefix(&self.root)
+                .and_then(|rest| rest.strip_prefix('/'))
+        };
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..glob::MatchOptions::new()
+        };
+        self.patterns.iter().any(|(glob, whole_path)| match rel {
+            Some(rel) if *whole_path => glob.matches_with(rel, options),
+            Some(rel) => rel.split('/').any(|c| glob.matches_with(c, options)),
+            // Outside the vault folder only the file name is considered
+            None => {
+                !whole_path && glob.matches_with(path.rsplit('/').next().unwrap_or(""), options)
+            }
+        })
+    }
+}
+
+/// Entries no `contents` traversal from the root index `entry` reaches: `.md` files with
+/// frontmatter anywhere below its folder (hidden and excluded ones aside), sorted. Files the
+/// vault links to only from a body or `part_of` are orphans too.
+///
+/// Nothing is reported when `entry` is not a root index (then it has no traversal to compare
+/// against) or `fs` cannot list folders.
+pub fn find_orphans(
+    entry: &str,
+    exclude: &[String],
+    fs: &impl FileProvider,
+) -> Result<Vec<String>> {
+    let opts = CoreBuildOptions {
+        include_nonpublic: true,
+        exclude: exclude.to_vec(),
+        ..Default::default()
+    };
+    let exclusions = Exclusions::new(entry, &opts.exclude, fs)?;
+    let schema = schema::Schema::discover(entry, fs)?;
+    let docs = collect_documents(
+        entry,
+        &opts,
+        &exclusions,
+        &schema,
+        fs,
+        &mut Vec::new(),
+        None,
+    )?;
+    Ok(orphans(entry, &docs, &exclusions, fs))
+}
+
+/// A `contents` / `part_of` link present on one side only (see [`find_part_of_mismatches`]).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct PartOfMismatch {
+    /// The index.
+    pub parent: String,
+    /// The entry.
+    pub child: String,
+    /// The side without the link: `part_of` (the child's) or `contents` (the parent's).
+    pub missing: &'static str,
+}
+
+/// Every `contents` / `part_of` link between the documents reachable from `entry` (private
+/// ones included) that is missing its counterpart: a child in an index's `contents` whose
+/// `part_of` does not name the index, or an entry whose `part_of` names an index that does not
+/// list it. Builds report the same as `part-of-mismatch` warnings.
+pub fn find_part_of_mismatches(entry: &str, fs: &impl FileProvider) -> Result<Vec<PartOfMismatch>> {
+    let opts = CoreBuildOptions {
+        include_nonpublic: true,
+        ..Default::default()
+    };
+    let exclusions = Exclusions::new(entry, &opts.exclude, fs)?;
+    let schema = schema::Schema::discover(entry, fs)?;
+    let docs = collect_documents(
+        entry,
+        &opts,
+        &exclusions,
+        &schema,
+        fs,
+        &mut Vec::new(),
+        None,
+    )?;
+    Ok(part_of_mismatches(&docs, fs))
+}
+
+fn orphans(
+    entry: &str,
+    docs: &[Doc],
+    exclusions: &Exclusions,
+    fs: &impl FileProvider,
+) -> Vec<String> {
+    if !docs.iter().any(|d| d.abs_path == entry && d.is_root_index) {
+        return Vec::new();
+    }
+    let Some(files) = fs.list_files(&fs.parent(entry).unwrap_or_default()) else {
+        return Vec::new();
+    };
+    let reached: HashSet<String> = docs
+        .iter()
+        .map(|d| normalize_lexically(&d.abs_path))
+        .collect();
+    let mut orphans: Vec<String> = files
+        .into_iter()
+        .filter(|path| {
+            fs.extension_lowercase(path).as_deref() == Some("md")
+                && !reached.contains(&normalize_lexically(path))
+                && !exclusions.matches(path)
+                // Notes without frontmatter (a README) are not entries.
+                && fs
+                    .read_to_string(path)
+                    .is_ok_and(|text| text.trim_start_matches('\u{feff}').starts_with("---"))
+        })
+        .collect();
+    orphans.sort();
+    orphans
+}
+
+fn orphan_warnings(
+    entry: &str,
+    docs: &[Doc],
+    exclusions: &Exclusions,
+    fs: &impl FileProvider,
+) -> Vec<Warning> {
+    orphans(entry, docs, exclusions, fs)
+        .into_iter()
+        .map(|path| {
+            Warning::new(
+                "orphan",
+                format!("Orphan entry (not reachable from the root index): {path}"),
+            )
+        })
+        .collect()
+}
+
+/// Forward slashes, with `.` and `..` components resolved without touching the filesystem.
+fn normalize_lexically(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "." => {}
+            ".." if parts.last().is_some_and(|p| !p.is_empty() && *p != "..") => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// A single file split into frontmatter (raw + typed view) and body, with its parse warnings.
+#[derive(Debug, Clone)]
+struct SourceParts {
+    frontmatter: serde_yaml::Value,
+    fm_struct: FrontmatterRaw,
+    body_md: String,
+    warnings: Vec<Warning>,
+    /// Fields filled in from `times` (see [`infer_dates`]).
+    inferred: Vec<String>,
+}
+
+fn parse_source(
+    path: &str,
+    raw: String,
+    schema: &schema::Schema,
+    times: Option<FileMetadata>,
+) -> SourceParts {
+    let mut warnings = Vec::new();
+    let split = match split_frontmatter(raw) {
+        Ok(s) => s,
+        Err((e, raw)) => {
+            warnings.push(Warning::new(
+                "frontmatter",
+                format!("Unterminated YAML frontmatter block ({path}): {e}"),
+            ));
+            SplitFrontmatter {
+                frontmatter_yaml: None,
+                body_md: raw,
+            }
+        }
+    };
+    let (mut frontmatter, mut fm_struct, fm_warns) =
+        match parse_frontmatter(&split.frontmatter_yaml) {
+            Ok(v) => v,
+            Err(e) => {
+                warnings.push(Warning::new(
+                    "frontmatter",
+                    format!("Invalid YAML frontmatter: {} ({path})", e),
+                ));
+                (
+                    serde_yaml::Value::Null,
+                    FrontmatterRaw::default(),
+                    Vec::new(),
+                )
+            }
+        };
+    for w in fm_warns {
+        warnings.push(Warning::new(w.code, format!("{} ({path})", w.message)));
+    }
+    normalize_dates(&mut frontmatter, &mut fm_struct, &schema.dates);
+    let inferred = times
+        .map(|times| infer_dates(&mut frontmatter, &mut fm_struct, times))
+        .unwrap_or_default();
+    check_required(&fm_struct, &mut warnings, path);
+    schema.validate(&frontmatter, path, &mut warnings);
+    SourceParts {
+        frontmatter,
+        fm_struct,
+        body_md: split.body_md,
+        warnings,
+        inferred,
+    }
+}
+
+/// Rewrite a `created` / `updated` written as a shorthand (`2025-08-25`, `2025-08-25 14:30`) as
+/// RFC 3339, read with the vault's `[dates]` settings; anything unparseable is left as written.
+fn normalize_dates(
+    frontmatter: &mut serde_yaml::Value,
+    fm: &mut FrontmatterRaw,
+    dates: &schema::DateConfig,
+) {
+    use time::format_description::well_known::Rfc3339;
+    for (field, slot) in [("created", &mut fm.created), ("updated", &mut fm.updated)] {
+        let Some(raw) = slot.as_deref() else {
+            continue;
+        };
+        if OffsetDateTime::parse(raw.trim(), &Rfc3339).is_ok() {
+            continue;
+        }
+        let Some(stamp) = dates.parse(raw).and_then(|at| at.format(&Rfc3339).ok()) else {
+            continue;
+        };
+        if let Some(map) = frontmatter.as_mapping_mut() {
+            map.insert(field.into(), stamp.clone().into());
+        }
+        *slot = Some(stamp);
+    }
+}
+
+/// Fill in a missing `created` (creation time, else modification time) and `updated`
+/// (modification time) from the file's timestamps; returns the fields set.
+fn infer_dates(
+    frontmatter: &mut serde_yaml::Value,
+    fm: &mut FrontmatterRaw,
+    times: FileMetadata,
+) -> Vec<String> {
+    use time::format_description::well_known::Rfc3339;
+    let mut inferred = Vec::new();
+    for (field, slot, time) in [
+        ("created", &mut fm.created, times.created.or(times.modified)),
+        ("updated", &mut fm.updated, times.modified),
+    ] {
+        let Some(time) = time.filter(|_| slot.is_none()) else {
+            continue;
+        };
+        let Ok(stamp) = time
+            .to_offset(UtcOffset::UTC)
+            .replace_nanosecond(0)
+            .unwrap_or(time)
+            .format(&Rfc3339)
+        else {
+            continue;
+        };
+        if !frontmatter.is_mapping() {
+            *frontmatter = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+        }
+        if let Some(map) = frontmatter.as_mapping_mut() {
+            map.insert(field.into(), stamp.clone().into());
+        }
+        *slot = Some(stamp);
+        inferred.push(field.to_string());
+    }
+    inferred
+}
+
+/// Like [`parse_source`] for `.html` files. A file without frontmatter is a plain web page, not a
+/// Diaryx entry, so the required-field checks are skipped for it.
+fn parse_html_source(
+    path: &str,
+    raw: String,
+    schema: &schema::Schema,
+    times: Option<FileMetadata>,
+) -> SourceParts {
+    if raw.starts_with("---\n") || raw.starts_with("---\r\n") {
+        return parse_source(path, raw, schema, times);
+    }
+    SourceParts {
+        frontmatter: serde_yaml::Value::Null,
+        fm_struct: FrontmatterRaw::default(),
+        body_md: raw,
+        warnings: Vec::new(),
+        inferred: Vec::new(),
+    }
+}
+
+/// `format: html`, or a link whose label names HTML (`"[HTML](https://html.spec.whatwg.org/)"`).
+fn is_html_format(format: &Option<serde_yaml::Value>) -> bool {
+    let Some(format) = format.as_ref().and_then(|v| v.as_str()) else {
+        return false;
+    };
+    let label = extract_md_link_parts_raw(format).map_or_else(|| format.to_string(), |(a, _)| a);
+    let label = label.trim().to_ascii_lowercase();
+    label == "html" || label.starts_with("html ")
+}
+
+/// A complete HTML document keeps only what goes inside the site's page shell: the contents of
+/// its `<body>`, plus its `<title>` (used when frontmatter sets none). Fragments pass unchanged.
+fn html_document_body(html: String) -> (String, Option<String>) {
+    static TITLE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+    static BODY: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?is)<body[^>]*>(.*?)(?:</body>|\z)").unwrap());
+    let title = TITLE
+        .captures(&html)
+        .map(|c| c[1].trim().to_string())
+        .filter(|t| !t.is_empty());
+    match BODY.captures(&html) {
+        Some(c) => (c[1].trim().to_string(), title),
+        None => (html, title),
+    }
+}
+
+struct SplitFrontmatter {
+    frontmatter_yaml: Option<String>,
+    body_md: String,
+}
+
+/// Split leading `---` YAML frontmatter from the Markdown body.
+///
+/// Takes ownership so the body reuses the file buffer instead of being rebuilt line by line,
+/// which matters for very large entries. On an unterminated block the input is handed back.
+fn split_frontmatter(
+    mut raw: String,
+) -> std::result::Result<SplitFrontmatter, (anyhow::Error, String)> {
+    let mut pos = 0usize;
+    let mut yaml_start = 0usize;
+    for (i, line) in raw.split_inclusive('\n').enumerate() {
+        let bare = line.trim_end_matches('\n').trim_end_matches('\r');
+        if i == 0 {
+            if bare != "---" {
+                return Ok(SplitFrontmatter {
+                    frontmatter_yaml: None,
+                    body_md: raw,
+                });
+            }
+            yaml_start = line.len();
+        } else if bare == "---" {
+            let yaml = raw[yaml_start..pos].to_string();
+            raw.drain(..pos + line.len());
+            return Ok(SplitFrontmatter {
+                frontmatter_yaml: Some(yaml),
+                body_md: raw,
+            });
+        }
+        pos += line.len();
+    }
+    if pos == 0 {
+        // Empty input
+        return Ok(SplitFrontmatter {
+            frontmatter_yaml: None,
+            body_md: raw,
+        });
+    }
+    Err((anyhow!("Unterminated frontmatter block"), raw))
+}
+
+fn parse_frontmatter(
+    yaml_opt: &Option<String>,
+) -> Result<(serde_yaml::Value, FrontmatterRaw, Vec<Warning>)> {
+    if let Some(yaml) = yaml_opt {
+        if yaml.trim().is_empty() {
+            return Ok((
+                serde_yaml::Value::Null,
+                FrontmatterRaw::default(),
+                Vec::new(),
+            ));
+        }
+        let value: serde_yaml::Value =
+            serde_yaml::from_str(yaml).context("Invalid YAML frontmatter")?;
+        let mut warnings = Vec::new();
+        // Deserialize the typed view by reference; the raw value is kept for metadata / JSON output.
+        let fm_struct: FrontmatterRaw = match FrontmatterRaw::deserialize(&value) {
+            Ok(v) => v,
+            Err(e) => {
+                warnings.push(Warning::new(
+                    "frontmatter",
+                    format!("Frontmatter shape mismatch; defaults applied: {}", e),
+                ));
+                FrontmatterRaw::default()
+            }
+        };
+        Ok((value, fm_struct, warnings))
+    } else {
         Ok((
             serde_yaml::Value::Null,
             FrontmatterRaw::default(),
@@ -635,24 +2291,78 @@ fn parse_frontmatter(
     }
 }
 
-fn check_required(fm: &FrontmatterRaw, warnings: &mut Vec<String>, path: &str) {
+/// Substitute `${NAME}` references in `author` and the declared `string` fields.
+fn interpolate_fields(
+    frontmatter: &mut serde_yaml::Value,
+    fm_struct: &mut FrontmatterRaw,
+    schema: &schema::Schema,
+    env: &BTreeMap<String, String>,
+    path: &str,
+    warnings: &mut Vec<Warning>,
+) {
+    let Some(map) = frontmatter.as_mapping_mut() else {
+        return;
+    };
+    let declared = schema
+        .fields
+        .iter()
+        .filter(|(_, spec)| spec.kind == schema::FieldType::String)
+        .map(|(name, _)| name.as_str());
+    for field in std::iter::once("author").chain(declared) {
+        let Some(value) = map.get_mut(field) else {
+            continue;
+        };
+        let mut unset = Vec::new();
+        interpolate::interpolate_value(value, env, &mut unset);
+        unset.dedup();
+        for name in unset {
+            warnings.push(Warning::new(
+                "unset-env",
+                format!(
+                    "Environment variable {name} is not set; '{field}' uses an empty value ({path})"
+                ),
+            ));
+        }
+    }
+    fm_struct.author = map.get("author").cloned();
+}
+
+fn check_required(fm: &FrontmatterRaw, warnings: &mut Vec<Warning>, path: &str) {
     if fm.title.is_none() {
-        warnings.push(format!("Missing required field: title ({path})"));
+        warnings.push(Warning::new(
+            "missing-field",
+            format!("Missing required field: title ({path})"),
+        ));
     }
     if fm.author.is_none() {
-        warnings.push(format!("Missing required field: author ({path})"));
+        warnings.push(Warning::new(
+            "missing-field",
+            format!("Missing required field: author ({path})"),
+        ));
     }
     if fm.created.is_none() {
-        warnings.push(format!("Missing required field: created ({path})"));
+        warnings.push(Warning::new(
+            "missing-field",
+            format!("Missing required field: created ({path})"),
+        ));
     }
     if fm.updated.is_none() {
-        warnings.push(format!("Missing required field: updated ({path})"));
+        warnings.push(Warning::new(
+            "missing-field",
+            format!("Missing required field: updated ({path})"),
+        ));
     }
     if fm.visibility.is_none() {
-        warnings.push(format!("Missing required field: visibility ({path})"));
+        warnings.push(Warning::new(
+            "missing-field",
+            format!("Missing required field: visibility ({path})"),
+        ));
     }
     if fm.format.is_none() {
-        warnings.push(format!("Missing required field: format ({path})"));
+        warnings.push(Warning::new(
+            "missing-field",
+            format!("Missing required field: format ({path})"),
+        ));
     }
     // reachable: required, but can be any non-empty scalar, sequence, or mapping value.
     // Treat missing, null, empty string, or empty sequence as "missing".
@@ -663,18 +2373,28 @@ fn check_required(fm: &FrontmatterRaw, warnings: &mut Vec<String>, path: &str) {
         Some(serde_yaml::Value::Sequence(seq)) => seq.is_empty(),
         _ => false,
     } {
-        warnings.push(format!("Missing required field: reachable ({path})"));
+        warnings.push(Warning::new(
+            "missing-field",
+            format!("Missing required field: reachable ({path})"),
+        ));
     }
 
     // Type sanity checks
+    let author_ok = |v: &serde_yaml::Value| {
+        v.as_str().is_some()
+            || v.get("name")
+                .and_then(|n| n.as_str())
+                .is_some_and(|n| !n.trim().is_empty())
+    };
     if let Some(v) = &fm.author {
         match v {
             serde_yaml::Value::String(_) => {}
+            serde_yaml::Value::Mapping(_) if author_ok(v) => {}
             serde_yaml::Value::Sequence(seq) => {
-                if !seq.iter().all(|x| x.as_str().is_some()) {
-                    warnings.push(format!(
-                        "Field 'author' list should contain only strings ({path})"
-                    ));
+                if !seq.iter().all(author_ok) {
+                    warnings.push(Warning::new("invalid-field", format!(
+                        "Field 'author' list should contain only names or mappings with a name ({path})"
+                    )));
                 }
             }
             other => {
@@ -687,13 +2407,21 @@ fn check_required(fm: &FrontmatterRaw, warnings: &mut Vec<String>, path: &str) {
                     serde_yaml::Value::Mapping(_) => "mapping",
                     serde_yaml::Value::Tagged(_) => "tagged",
                 };
-                warnings.push(format!(
-                    "Field 'author' should be a string or list of strings; got {} ({path})",
+                warnings.push(Warning::new("invalid-field", format!(
+                    "Field 'author' should be a name, a mapping with a name, or a list of them; got {} ({path})",
                     ty
-                ));
+                )));
             }
         }
     }
+    if let Some(v) = &fm.version
+        && version_text(v).is_none()
+    {
+        warnings.push(Warning::new(
+            "invalid-field",
+            format!("Field 'version' should be a non-empty string or number ({path})"),
+        ));
+    }
 }
 
 fn parse_part_of(value: &Option<serde_yaml::Value>) -> Vec<String> {
@@ -736,78 +2464,198 @@ fn normalize_contents(c: &Option<Vec<String>>) -> Vec<String> {
 // -------------------------------------------------------------------------------------------------
 
 fn link_graph(docs: &mut [Doc], fs: &impl FileProvider) {
-    // Build quick lookup: abs_path -> (index, slug)
-    let mut path_to_index: HashMap<String, usize> = HashMap::new();
-    for (i, d) in docs.iter().enumerate() {
-        path_to_index.insert(d.abs_path.clone(), i);
-    }
+    // Build lookup once: abs_path -> index. Every contents / part_of link is resolved exactly
+    // once and mapped through this table, keeping linking linear in the number of links.
+    let path_to_index: HashMap<String, usize> = docs
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (d.abs_path.clone(), i))
+        .collect();
+    let resolve = |raw: &str, parent_dir: &str| -> Option<usize> {
+        resolve_contents_link(raw, parent_dir, fs).and_then(|abs| path_to_index.get(&abs).copied())
+    };
+
     for i in 0..docs.len() {
-        if !docs[i].is_index {
-            continue;
-        }
-        let parent_dir = match fs.parent(&docs[i].abs_path) {
-            Some(p) => p,
-            None => "".to_string(),
-        };
-        let entries = docs[i].contents_raw.clone();
-        for raw_link in entries {
-            if let Some(abs) = resolve_contents_link(&raw_link, &parent_dir, fs) {
-                if let Some(child_idx) = docs.iter().position(|d| d.abs_path == abs) {
-                    let child_slug = docs[child_idx].id.clone();
-                    if !docs[i].children.contains(&child_slug) {
-                        docs[i].children.push(child_slug.clone());
-                    }
-                    if !docs[child_idx].parents.contains(&docs[i].id) {
-                        docs[child_idx].parents.push(docs[i].id.clone());
-                    }
+        let parent_dir = fs.parent(&docs[i].abs_path).unwrap_or_default();
+
+        // Structural links (children / parents) plus child aliases from the same contents entries
+        if docs[i].is_index {
+            let entries = std::mem::take(&mut docs[i].contents_raw);
+            let mut seen_children: HashSet<usize> = HashSet::with_capacity(entries.len());
+            for raw_link in &entries {
+                let alias = extract_md_link_parts_raw(raw_link).filter(|(a, _)| !a.is_empty());
+                if let Some((alias, target)) = &alias {
+                    docs[i]
+                        .contents_alias_by_name
+                        .insert(normalize_target_basename_raw(target), alias.clone());
+                }
+                let Some(child_idx) = resolve(raw_link, &parent_dir) else {
+                    continue;
+                };
+                let child_slug = docs[child_idx].id.clone();
+                if seen_children.insert(child_idx) {
+                    docs[i].children.push(child_slug.clone());
+                }
+                if !docs[child_idx].parents.contains(&docs[i].id) {
+                    let parent_slug = docs[i].id.clone();
+                    docs[child_idx].parents.push(parent_slug);
+                }
+                if let Some((alias, _target)) = alias {
+                    docs[i].child_aliases.insert(child_slug, alias);
                 }
             }
+            docs[i].contents_raw = entries;
         }
-    }
 
-    // After structural links, derive alias maps from original frontmatter link strings
-    // Child aliases
-    for i in 0..docs.len() {
-        if !docs[i].is_index || docs[i].contents_raw.is_empty() {
-            continue;
-        }
-        let parent_dir = fs.parent(&docs[i].abs_path).unwrap_or_default();
-        for raw in docs[i].contents_raw.clone() {
-            if let Some((alias, _target)) = extract_md_link_parts_raw(&raw) {
+        // Parent aliases from raw_part_of
+        if !docs[i].raw_part_of.is_empty() {
+            let part_of = std::mem::take(&mut docs[i].raw_part_of);
+            for raw in &part_of {
+                let Some((alias, target)) = extract_md_link_parts_raw(raw) else {
+                    continue;
+                };
                 if alias.is_empty() {
                     continue;
                 }
-                // Resolve target to slug
-                if let Some(abs) = resolve_contents_link(&raw, &parent_dir, fs) {
-                    if let Some(idx) = docs.iter().position(|d| d.abs_path == abs) {
-                        let slug = docs[idx].id.clone();
-                        docs[i].child_aliases.insert(slug, alias);
-                    }
+                docs[i]
+                    .part_of_alias_by_name
+                    .insert(normalize_target_basename_raw(&target), alias.clone());
+                if let Some(idx) = resolve(raw, &parent_dir) {
+                    let slug = docs[idx].id.clone();
+                    docs[i].parent_aliases.insert(slug, alias);
                 }
             }
+            docs[i].raw_part_of = part_of;
         }
     }
 
-    // Parent aliases from raw_part_of
-    for i in 0..docs.len() {
-        if docs[i].raw_part_of.is_empty() {
+    link_translations(docs, |raw, parent_dir| resolve(raw, parent_dir), fs);
+}
+
+/// Warn about `contents` / `part_of` links that only go one way, on the entry's page.
+fn check_part_of(docs: &mut [Doc], fs: &impl FileProvider) {
+    for m in part_of_mismatches(docs, fs) {
+        let Some(child) = docs.iter_mut().find(|d| d.abs_path == m.child) else {
             continue;
+        };
+        let message = match m.missing {
+            "part_of" => format!(
+                "Contents/part_of mismatch: {} is in the contents of {}, but its part_of does not name it",
+                m.child, m.parent
+            ),
+            _ => format!(
+                "Contents/part_of mismatch: {} names {} in part_of, but is not in its contents",
+                m.child, m.parent
+            ),
+        };
+        child
+            .warnings
+            .push(Warning::new("part-of-mismatch", message));
+    }
+}
+
+/// Pairs of `docs` linked through `contents` or `part_of` but not both, sorted. Files without
+/// frontmatter (plain HTML pages) cannot declare either side, so links to them are left out.
+fn part_of_mismatches(docs: &[Doc], fs: &impl FileProvider) -> Vec<PartOfMismatch> {
+    let path_to_index: HashMap<String, usize> = docs
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (normalize_lexically(&d.abs_path), i))
+        .collect();
+    let resolve = |raw: &str, dir: &str| {
+        // `part_of` may also be a bare path.
+        let link = if raw.contains("](") {
+            raw.to_string()
+        } else {
+            format!("[]({})", raw.trim())
+        };
+        resolve_contents_link(&link, dir, fs)
+            .and_then(|abs| path_to_index.get(&normalize_lexically(&abs)).copied())
+    };
+    // (parent, child)
+    let mut listed: BTreeSet<(usize, usize)> = BTreeSet::new();
+    let mut declared: BTreeSet<(usize, usize)> = BTreeSet::new();
+    for (i, d) in docs.iter().enumerate() {
+        let dir = fs.parent(&d.abs_path).unwrap_or_default();
+        for raw in &d.contents_raw {
+            if let Some(child) = resolve(raw, &dir).filter(|&c| c != i) {
+                listed.insert((i, child));
+            }
         }
-        let parent_dir = fs.parent(&docs[i].abs_path).unwrap_or_default();
-        for raw in docs[i].raw_part_of.clone() {
-            if let Some((alias, _target)) = extract_md_link_parts_raw(&raw) {
-                if alias.is_empty() {
-                    continue;
-                }
-                if let Some(abs) = resolve_contents_link(&raw, &parent_dir, fs) {
-                    if let Some(idx) = docs.iter().position(|d| d.abs_path == abs) {
-                        let slug = docs[idx].id.clone();
-                        docs[i].parent_aliases.insert(slug, alias);
-                    }
+        for raw in &d.raw_part_of {
+            if let Some(parent) = resolve(raw, &dir).filter(|&p| p != i) {
+                declared.insert((parent, i));
+            }
+        }
+    }
+    let writable = |i: usize| docs[i].frontmatter.is_mapping();
+    let mut mismatches: Vec<PartOfMismatch> = listed
+        .difference(&declared)
+        .filter(|&&(_, child)| writable(child))
+        .map(|&(parent, child)| (parent, child, "part_of"))
+        .chain(
+            declared
+                .difference(&listed)
+                .filter(|&&(parent, _)| writable(parent))
+                .map(|&(parent, child)| (parent, child, "contents")),
+        )
+        .map(|(parent, child, missing)| PartOfMismatch {
+            parent: docs[parent].abs_path.clone(),
+            child: docs[child].abs_path.clone(),
+            missing,
+        })
+        .collect();
+    mismatches.sort();
+    mismatches
+}
+
+/// Group documents connected through `translations` links (in either direction, transitively)
+/// and record, for each, the slugs of the other members of its group.
+fn link_translations(
+    docs: &mut [Doc],
+    resolve: impl Fn(&str, &str) -> Option<usize>,
+    fs: &impl FileProvider,
+) {
+    fn find(group: &mut [usize], mut i: usize) -> usize {
+        while group[i] != i {
+            group[i] = group[group[i]];
+            i = group[i];
+        }
+        i
+    }
+    let mut group: Vec<usize> = (0..docs.len()).collect();
+    for (i, doc) in docs.iter_mut().enumerate() {
+        let parent_dir = fs.parent(&doc.abs_path).unwrap_or_default();
+        for raw in std::mem::take(&mut doc.raw_translations) {
+            match resolve(&raw, &parent_dir) {
+                Some(j) => {
+                    let (a, b) = (find(&mut group, i), find(&mut group, j));
+                    group[a.max(b)] = a.min(b);
                 }
+                None => doc.warnings.push(Warning::new(
+                    "other",
+                    format!(
+                        "translations target is not part of the vault: {raw} ({})",
+                        doc.abs_path
+                    ),
+                )),
             }
         }
     }
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..docs.len() {
+        let root = find(&mut group, i);
+        members.entry(root).or_default().push(i);
+    }
+    for list in members.values().filter(|list| list.len() > 1) {
+        for &i in list {
+            docs[i].translations = list
+                .iter()
+                .filter(|&&j| j != i)
+                .map(|&j| docs[j].id.clone())
+                .collect();
+        }
+    }
 }
 
 fn resolve_contents_link(raw: &str, parent_dir: &str, fs: &impl FileProvider) -> Option<String> {
@@ -831,1016 +2679,3535 @@ fn resolve_contents_link(raw: &str, parent_dir: &str, fs: &impl FileProvider) ->
 }
 
 // -------------------------------------------------------------------------------------------------
-// Markdown Rendering & Link Rewriting
+// Reachability
 // -------------------------------------------------------------------------------------------------
 
-fn render_markdown(src: &str) -> Result<String> {
-    let opts = markdown::Options::default();
-    markdown::to_html_with_options(src, &opts).map_err(|e| anyhow!("Markdown render error: {e}"))
+/// One item of a page's `reachable` field: where (a URL or a file path) or by whom (an audience
+/// label) the entry can be reached.
+#[derive(Debug, Clone, Serialize)]
+pub struct Reachability {
+    pub kind: ReachKind,
+    /// URL, path as written, or audience label.
+    pub target: String,
+    /// Link text of a Markdown link (`[text](target)`).
+    pub label: Option<String>,
+    /// Well-formed URL, existing path, or audience used in the vault's `visibility` values.
+    pub valid: bool,
 }
 
-/// Update doc.html in-place rewriting internal .md links.
-fn rewrite_internal_links(docs: &mut [Doc], opts: &CoreBuildOptions) {
-    if docs.is_empty() {
-        return;
-    }
-    let has_root = docs.iter().any(|d| d.is_root_index);
-    let multi_page = has_root && docs.len() > 1;
-    let by_basename: HashMap<String, (String, bool)> = docs
-        .iter()
-        .map(|d| {
-            let name = d
-                .abs_path
-                .rsplit('/')
-                .next()
-                .unwrap_or(&d.abs_path)
-                .to_string();
-            (name, (d.id.clone(), d.is_root_index))
-        })
-        .collect();
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReachKind {
+    Url,
+    Path,
+    Audience,
+}
 
-    static HREF_MD: Lazy<Regex> =
-        Lazy::new(|| Regex::new(r#"href="([^"]+?\.(?i:md)(?:[?#][^"]*)?)""#).unwrap());
+/// Audiences recognized even when no `visibility` in the vault mentions them.
+const KNOWN_AUDIENCES: &[&str] = &[
+    "public", "private", "self", "me", "family", "friends", "work", "everyone",
+];
 
+/// Classify and check every page's `reachable` items; problems become page warnings. URLs are
+/// checked for shape only (no network access), paths against the file provider relative to the
+/// page, and audience labels against the visibility values used anywhere in the vault.
+fn check_reachable(docs: &mut [Doc], fs: &impl FileProvider) {
+    let audiences: HashSet<String> = docs
+        .iter()
+        .flat_map(|d| d.visibility.iter().map(|v| v.to_lowercase()))
+        .chain(KNOWN_AUDIENCES.iter().map(|a| a.to_string()))
+        .collect();
     for doc in docs.iter_mut() {
-        if !doc.html.to_ascii_lowercase().contains(".md") {
+        let Some(value) = doc.frontmatter.get("reachable") else {
             continue;
-        }
-        let current_is_root = doc.is_root_index;
-        let mut new_html = String::with_capacity(doc.html.len());
-        let mut last = 0;
-        for cap in HREF_MD.captures_iter(&doc.html) {
-            let m = cap.get(0).unwrap();
-            let url = cap.get(1).unwrap().as_str();
-            let core = url.split(&['?', '#'][..]).next().unwrap_or(url);
-            let basename = core.rsplit('/').next().unwrap_or(core);
-            let basename_norm = basename.replace("%20", " ");
-            let mapping = by_basename.get(&basename_norm);
-            if let Some((target_slug, target_is_root)) = mapping {
-                let new_href = if multi_page && !opts.flat {
-                    // Nested layout (root at top-level, children under pages/)
-                    if current_is_root {
-                        if *target_is_root {
-                            "index.html".into()
-                        } else {
-                            // root -> child
-                            format!("pages/{}.html", target_slug)
-                        }
-                    } else {
-                        // Current doc is a child (lives in pages/)
-                        if *target_is_root {
-                            // child -> root
-                            "../index.html".into()
-                        } else {
-                            // child -> sibling
-                            format!("{}.html", target_slug)
-                        }
-                    }
-                } else if multi_page {
-                    // Flat multi-page: everything at one level
-                    if *target_is_root {
-                        "index.html".into()
-                    } else {
-                        format!("{}.html", target_slug)
-                    }
-                } else {
-                    // Single-page build: all internal links point to index.html
-                    "index.html".into()
+        };
+        let mut raw = Vec::new();
+        reachable_strings(value, &mut raw);
+        let parent = fs.parent(&doc.abs_path).unwrap_or_default();
+        for item in raw {
+            let reach = classify_reachable(&item, &parent, &audiences, fs);
+            if !reach.valid {
+                let problem = match reach.kind {
+                    ReachKind::Url => format!("'{}' is not a valid URL", reach.target),
+                    ReachKind::Path => format!("path '{}' does not exist", reach.target),
+                    ReachKind::Audience => format!("unknown audience '{}'", reach.target),
                 };
-                let mut suffix = "";
-                if let Some(idx) = url.find(|c| c == '?' || c == '#') {
-                    suffix = &url[idx..];
-                }
-                new_html.push_str(&doc.html[last..m.start()]);
-                new_html.push_str("href=\"");
-                new_html.push_str(&new_href);
-                new_html.push_str(suffix);
-                new_html.push('"');
-                last = m.end();
+                doc.warnings.push(Warning::new(
+                    "reachable",
+                    format!("reachable: {problem} ({})", doc.abs_path),
+                ));
             }
+            doc.reachable.push(reach);
         }
-        new_html.push_str(&doc.html[last..]);
-        doc.html = new_html;
     }
+}
 
-    if opts.strict {
-        // Placeholder for future strict validation of fully rewritten links.
+fn reachable_strings(value: &serde_yaml::Value, out: &mut Vec<String>) {
+    use serde_yaml::Value;
+    match value {
+        Value::Null => {}
+        Value::String(s) if s.trim().is_empty() => {}
+        Value::String(s) => out.push(s.trim().to_string()),
+        Value::Bool(b) => out.push(b.to_string()),
+        Value::Number(n) => out.push(n.to_string()),
+        Value::Sequence(seq) => seq.iter().for_each(|v| reachable_strings(v, out)),
+        Value::Mapping(map) => map.values().for_each(|v| reachable_strings(v, out)),
+        Value::Tagged(tagged) => reachable_strings(&tagged.value, out),
     }
 }
 
-// -------------------------------------------------------------------------------------------------
-// Utilities
-// -------------------------------------------------------------------------------------------------
-
-fn slugify(s: &str) -> String {
-    static NON_ALNUM: Lazy<Regex> = Lazy::new(|| Regex::new(r"[^a-z0-9]+").unwrap());
-    let lower = s.to_ascii_lowercase();
-    let replaced = NON_ALNUM.replace_all(&lower, "-");
-    replaced.trim_matches('-').to_string()
+fn classify_reachable(
+    item: &str,
+    parent_dir: &str,
+    audiences: &HashSet<String>,
+    fs: &impl FileProvider,
+) -> Reachability {
+    static MD_LINK: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^\[([^\]]*)\]\(\s*<?([^)>]*?)>?\s*\)$").unwrap());
+    static SCHEME: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z][A-Za-z0-9+.-]*:").unwrap());
+    let (label, target) = match MD_LINK.captures(item) {
+        Some(caps) => (Some(caps[1].trim().to_string()), caps[2].trim().to_string()),
+        None => (None, item.to_string()),
+    };
+    let linked = label.is_some();
+    let url = target.contains("://")
+        || linked
+        || ["mailto:", "tel:"]
+            .iter()
+            .any(|p| target.to_ascii_lowercase().starts_with(p));
+    let (kind, valid) = if SCHEME.is_match(&target) && url {
+        (ReachKind::Url, is_well_formed_url(&target))
+    } else if linked || target.contains('/') || fs.extension_lowercase(&target).is_some() {
+        let path = fs.join(parent_dir, &target.replace("%20", " "));
+        (ReachKind::Path, !target.is_empty() && fs.exists(&path))
+    } else {
+        (
+            ReachKind::Audience,
+            audiences.contains(&target.to_lowercase()),
+        )
+    };
+    Reachability {
+        kind,
+        target,
+        label,
+        valid,
+    }
 }
 
-#[allow(dead_code)]
-fn humanize_timestamp(raw: &str) -> String {
-    if raw.is_empty() || !raw.contains('T') {
-        return raw.to_string();
+/// `scheme:rest` without whitespace; hierarchical URLs (`scheme://`) also need a host.
+fn is_well_formed_url(url: &str) -> bool {
+    if url.chars().any(char::is_whitespace) {
+        return false;
     }
-    if let Ok(dt) =
-        OffsetDateTime::parse(raw.trim(), &time::format_description::well_known::Rfc3339)
-    {
-        let date_fmt =
-            time::format_description::parse("[month repr:long] [day padding:zero], [year]")
-                .unwrap_or_else(|_| {
-                    time::format_description::parse("[year]-[month]-[day]").unwrap()
-                });
-        let date_str = dt.format(&date_fmt).unwrap_or_else(|_| raw.to_string());
-        let hour = dt.hour();
-        let (h12, ampm) = match hour {
-            0 => (12, "am"),
-            1..=11 => (hour, "am"),
-            12 => (12, "pm"),
-            _ => (hour - 12, "pm"),
+    match url.split_once("://") {
+        Some((_, rest)) => {
+            let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+            let host = authority.rsplit('@').next().unwrap_or("");
+            let host = host.split(':').next().unwrap_or("");
+            !host.is_empty() && !host.starts_with('.') && !host.ends_with('.')
+        }
+        None => url
+            .split_once(':')
+            .is_some_and(|(_, rest)| !rest.is_empty()),
+    }
+}
+
+/// `reachable` items as links / audience chips with a count per kind; invalid items are marked.
+fn render_reachable(items: &[Reachability]) -> String {
+    let mut out = String::new();
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push_str("<br/>");
+        }
+        let class = match (item.kind, item.valid) {
+            (ReachKind::Url, true) => "reachable-url",
+            (ReachKind::Path, true) => "reachable-path",
+            (ReachKind::Audience, true) => "reachable-audience",
+            _ => "reachable-invalid",
         };
-        let minute = dt.minute();
-        let offset: UtcOffset = dt.offset();
-        let hours = offset.whole_hours();
-        let tz_label = if hours == 0 {
-            "UTC".to_string()
+        let text = item.label.as_deref().unwrap_or(&item.target);
+        if item.kind == ReachKind::Audience {
+            out.push_str(&format!("<span class=\"{class}\">"));
+            html_esc_simple(&mut out, text);
+            out.push_str("</span>");
         } else {
-            format!("UTC{:+}", hours)
-        };
-        return format!(
-            "{}, {:02}:{:02}{} ({})",
-            date_str, h12, minute, ampm, tz_label
-        );
+            out.push_str(&format!("<a class=\"{class}\" href=\""));
+            html_esc_simple(&mut out, &item.target);
+            out.push_str("\">");
+            html_esc_simple(&mut out, text);
+            out.push_str("</a>");
+        }
     }
-    raw.to_string()
+    let counts: Vec<String> = [
+        (ReachKind::Url, "URL", "URLs"),
+        (ReachKind::Path, "path", "paths"),
+        (ReachKind::Audience, "audience", "audiences"),
+    ]
+    .iter()
+    .filter_map(|(kind, one, many)| {
+        let n = items.iter().filter(|r| r.kind == *kind).count();
+        (n > 0).then(|| format!("{n} {}", if n == 1 { one } else { many }))
+    })
+    .collect();
+    let invalid = items.iter().filter(|r| !r.valid).count();
+    out.push_str(&format!(
+        " <span class=\"reachable-summary\">({}{})</span>",
+        counts.join(", "),
+        if invalid > 0 {
+            format!("; {invalid} unresolved")
+        } else {
+            String::new()
+        }
+    ));
+    out
 }
-/// Build minimal metadata HTML (unordered list). Caller supplies CSS.
-/// Includes special formatting for created / updated if present.
-fn build_metadata_html(
-    frontmatter: &serde_yaml::Value,
-    is_root_index: bool,
-    is_index: bool,
-    multi_page: bool,
-    flat: bool,
-    children: &[String],
-    parents: &[String],
-    raw_part_of: &[String],
-    root_slug: Option<&str>,
-    child_alias_map: &HashMap<String, String>,
-    parent_alias_map: &HashMap<String, String>,
-) -> String {
-    use serde_yaml::Value;
-    let mapping = match frontmatter {
-        Value::Mapping(m) => m,
-        _ => return String::new(),
-    };
-    if mapping.is_empty() {
-        return String::new();
+
+// -------------------------------------------------------------------------------------------------
+// Authors
+// -------------------------------------------------------------------------------------------------
+
+/// One author of a page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Author {
+    pub name: String,
+    pub email: Option<String>,
+    pub url: Option<String>,
+}
+
+impl Author {
+    /// Where the byline links: the URL, else a `mailto:` link.
+    pub fn href(&self) -> Option<String> {
+        self.url
+            .clone()
+            .or_else(|| self.email.as_ref().map(|e| format!("mailto:{e}")))
     }
+}
 
-    // 1. Gather original key insertion order (preserve author intent)
-    // serde_yaml::Mapping preserves insertion order in iteration
-    let mut ordered: Vec<(&String, &Value)> = Vec::new();
-    for (k, v) in mapping {
-        if let Value::String(s) = k {
-            ordered.push((s, v));
+/// Normalize an `author` value: a name, `Name <email>`, `Name <https://…>`, a Markdown link
+/// `[Name](https://…)`, a mapping with `name` and optional `email` / `url`, or a list of these.
+/// Items without a name are skipped.
+pub fn parse_authors(value: &serde_yaml::Value) -> Vec<Author> {
+    use serde_yaml::Value;
+    static ANGLE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(.*?)\s*<([^<>]+)>$").unwrap());
+    static MD_LINK: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^\[([^\]]+)\]\(\s*<?([^)>]*?)>?\s*\)$").unwrap());
+    let contact = |target: &str| -> (Option<String>, Option<String>) {
+        let target = target.trim();
+        let target = target.strip_prefix("mailto:").unwrap_or(target);
+        if target.contains("://") {
+            (None, Some(target.to_string()))
+        } else if target.contains('@') {
+            (Some(target.to_string()), None)
+        } else {
+            (None, None)
         }
+    };
+    let text = |v: Option<&Value>| {
+        v.and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+    let mut authors = Vec::new();
+    let items: Vec<&Value> = match value {
+        Value::Sequence(seq) => seq.iter().collect(),
+        other => vec![other],
+    };
+    for item in items {
+        let author = match item {
+            Value::String(raw) => {
+                let raw = raw.trim();
+                let caps = MD_LINK.captures(raw).or_else(|| ANGLE.captures(raw));
+                match caps {
+                    Some(caps) if !caps[1].trim().is_empty() => {
+                        let (email, url) = contact(&caps[2]);
+                        Some(Author {
+                            name: caps[1].trim().to_string(),
+                            email,
+                            url,
+                        })
+                    }
+                    _ => (!raw.is_empty()).then(|| Author {
+                        name: raw.to_string(),
+                        email: None,
+                        url: None,
+                    }),
+                }
+            }
+            Value::Mapping(_) => text(item.get("name")).map(|name| Author {
+                name,
+                email: text(item.get("email")),
+                url: text(item.get("url")),
+            }),
+            _ => None,
+        };
+        authors.extend(author);
     }
+    authors
+}
 
-    // Ensure 'reachable' renders last regardless of YAML insertion order
-    {
-        let mut reachable_entry: Option<(&String, &Value)> = None;
-        ordered.retain(|(k, v)| {
-            if *k == "reachable" {
-                reachable_entry = Some((*k, *v));
-                false
-            } else {
-                true
+/// Comma-separated author bylines, linked to each author's URL or email.
+fn render_authors(authors: &[Author]) -> String {
+    let mut out = String::new();
+    for (i, author) in authors.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        out.push_str("<span class=\"byline\">");
+        match author.href() {
+            Some(href) => {
+                out.push_str("<a href=\"");
+                html_esc_simple(&mut out, &href);
+                out.push_str("\">");
+                html_esc_simple(&mut out, &author.name);
+                out.push_str("</a>");
             }
-        });
-        if let Some(r) = reachable_entry {
-            ordered.push(r);
+            None => html_esc_simple(&mut out, &author.name),
         }
+        out.push_str("</span>");
     }
+    out
+}
 
-    // 2. Prepare alias-aware child link rendering.
-    // We attempt to recover alias text from the original 'contents' sequence of markdown links:
-    // "[Alias](target.md)" so that the metadata view shows human-friendly labels.
-    // Build a map: normalized_target_basename -> alias.
-    let mut alias_map: std::collections::HashMap<String, String> = std::collections::HashMap::new();
-    if is_index {
-        if let Some(contents_val) = mapping.get(&Value::String("contents".to_string())) {
-            if let Value::Sequence(seq) = contents_val {
-                for item in seq {
-                    if let Value::String(s) = item {
-                        if let Some((alias, target)) = extract_md_link_parts_raw(s) {
-                            if !alias.is_empty() {
-                                let norm = normalize_target_basename_raw(&target);
-                                alias_map.insert(norm, alias);
-                            }
-                        }
-                    }
-                }
-            }
-        }
+// -------------------------------------------------------------------------------------------------
+// Versions
+// -------------------------------------------------------------------------------------------------
+
+/// A `version` value as text: a string, or a number as YAML reads it (`version: 2` is `"2"`;
+/// quote versions like `"1.10"` to keep trailing zeros). Other shapes give `None`.
+pub fn version_text(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        _ => None,
     }
+}
 
-    // 3. Child links (alias text if available) with layout-aware hrefs
-    let mut child_links: Vec<String> = Vec::new();
-    if is_index && !children.is_empty() {
-        for slug in children {
-            // Normalized key used when deriving aliases from raw contents link targets
-            let md_key = format!("{slug}.md");
-            // Precedence:
-            // 1. Explicit child_alias_map (slug -> alias) from graph phase
-            // 2. Raw contents alias_map (normalized basename -> alias)
-            // 3. Fallback to slug
-            let label = child_alias_map
-                .get(slug)
-                .cloned()
-                .or_else(|| alias_map.get(&md_key).cloned())
-                .unwrap_or_else(|| slug.to_string());
-            let href = if multi_page {
-                if flat {
-                    format!("{slug}.html")
-                } else if is_root_index {
-                    format!("pages/{slug}.html")
-                } else {
-                    format!("{slug}.html")
-                }
-            } else {
-                // single-page build (all content together)
-                "index.html".to_string()
-            };
-            child_links.push(format!(
-                "<a href=\"{}\">{}</a>",
-                href,
-                html_escape_text(&label)
-            ));
-        }
+// -------------------------------------------------------------------------------------------------
+// Licenses (`copying`)
+// -------------------------------------------------------------------------------------------------
+
+/// A page's license, parsed from `copying`: an SPDX license expression (`CC-BY-4.0`,
+/// `MIT OR Apache-2.0`), a license URL, or a Markdown link to one (`[CC BY 4.0](https://…)`).
+/// Anything else is kept as free text (`All rights reserved`) with a warning.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct License {
+    /// SPDX license expression, when `copying` (or its link text) is one.
+    pub spdx: Option<String>,
+    /// The linked or given URL, else the SPDX list page of a single SPDX license.
+    pub url: Option<String>,
+    /// Display text: link text, the SPDX expression, the URL, or the free text.
+    pub label: String,
+    /// Full license name of a single SPDX license.
+    pub name: Option<String>,
+    /// Taken from an ancestor index rather than the page's own `copying`.
+    pub inherited: bool,
+}
+
+impl License {
+    /// Recognized as an SPDX expression or a well-formed URL.
+    pub fn is_valid(&self) -> bool {
+        self.spdx.is_some() || self.url.is_some()
     }
+}
 
-    // 3b. Parent (part_of) alias extraction & links
-    // Build map from normalized basename -> alias extracted directly from raw_part_of
-    let mut raw_parent_alias_map: std::collections::HashMap<String, String> =
-        std::collections::HashMap::new();
-    if !raw_part_of.is_empty() {
-        for raw in raw_part_of {
-            if let Some((alias, target)) = extract_md_link_parts_raw(raw) {
-                if !alias.is_empty() {
-                    let norm = normalize_target_basename_raw(&target);
-                    raw_parent_alias_map.insert(norm, alias);
-                }
-            }
+fn parse_copying(raw: &str) -> License {
+    static MD_LINK: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^\[([^\]]*)\]\(\s*<?([^)>]*?)>?\s*\)$").unwrap());
+    let raw = raw.trim();
+    let (label, url) = match MD_LINK.captures(raw) {
+        Some(caps) => (caps[1].trim().to_string(), Some(caps[2].trim().to_string())),
+        None if raw.contains("://") => (raw.to_string(), Some(raw.to_string())),
+        None => (raw.to_string(), None),
+    };
+    let url = url.filter(|u| is_well_formed_url(u));
+    let expression = spdx::Expression::parse(&label).ok();
+    let single = expression.as_ref().and_then(|e| {
+        let mut reqs = e.requirements();
+        match (reqs.next(), reqs.next()) {
+            (Some(req), None) => req.req.license.id(),
+            _ => None,
         }
+    });
+    License {
+        url: url.or_else(|| single.map(|id| format!("https://spdx.org/licenses/{}.html", id.name))),
+        name: single.map(|id| id.full_name.to_string()),
+        spdx: expression.map(|e| e.as_ref().to_string()),
+        label,
+        inherited: false,
     }
-    let mut parent_links: Vec<String> = Vec::new();
-    if !parents.is_empty() {
-        for parent_slug in parents {
-            let md_key = format!("{parent_slug}.md");
-            // Precedence:
-            // 1. parent_aliases (slug -> alias) from graph phase
-            // 2. raw_parent_alias_map (normalized target -> alias)
-            // 3. fallback to slug
-            let label = parent_alias_map
-                .get(parent_slug)
-                .cloned()
-                .or_else(|| parent_alias_map.get(&md_key).cloned())
-                .or_else(|| raw_parent_alias_map.get(&md_key).cloned())
-                .unwrap_or_else(|| parent_slug.to_string());
-            let is_root_parent = root_slug.map(|r| r == parent_slug).unwrap_or(false);
-
-            let href = if multi_page && !flat {
-                if is_root_index {
-                    if is_root_parent {
-                        "index.html".to_string()
-                    } else {
-                        format!("pages/{parent_slug}.html")
-                    }
-                } else if is_root_parent {
-                    "../index.html".to_string()
-                } else {
-                    format!("../pages/{parent_slug}.html")
-                }
-            } else if multi_page {
-                if is_root_parent {
-                    "index.html".to_string()
-                } else {
-                    format!("{parent_slug}.html")
-                }
-            } else {
-                "index.html".to_string()
-            };
+}
 
-            parent_links.push(format!(
-                "<a href=\"{}\">{}</a>",
-                href,
-                html_escape_text(&label)
+/// Parse each page's `copying` (warning about values that are neither SPDX nor a URL), then let
+/// pages without one inherit from their nearest ancestor that has one.
+fn assign_licenses(docs: &mut [Doc]) {
+    for doc in docs.iter_mut() {
+        let Some(raw) = doc
+            .frontmatter
+            .get("copying")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty())
+        else {
+            continue;
+        };
+        let license = parse_copying(raw);
+        if !license.is_valid() {
+            let hint = spdx::imprecise_license_id(raw.trim())
+                .map(|(id, _)| format!("; did you mean '{}'?", id.name))
+                .unwrap_or_default();
+            doc.warnings.push(Warning::new(
+                "other",
+                format!(
+                    "copying: '{}' is neither an SPDX license expression nor a URL{hint} ({})",
+                    raw.trim(),
+                    doc.abs_path
+                ),
             ));
         }
+        doc.license = Some(license);
     }
-    // Include non-structural part_of targets (aliases) not present in structural parents.
-    // These are entries in raw_part_of whose targets were not discovered via contents traversal.
-    if !raw_parent_alias_map.is_empty() {
-        // Build a set of already linked slugs (derive slug from filename stem).
-        let mut existing: std::collections::HashSet<String> = parents.iter().cloned().collect();
-        // Also capture slugs already pushed (in case of duplicates)
-        for link in &parent_links {
-            // crude extraction: href=".../slug.html" or href="slug.html"
-            if let Some(href_start) = link.find("href=\"") {
-                let rest = &link[href_start + 6..];
-                if let Some(end_q) = rest.find('"') {
-                    let target = &rest[..end_q];
-                    // pull final component without extension
-                    let slug = target
-                        .rsplit('/')
-                        .next()
-                        .unwrap_or(target)
-                        .strip_suffix(".html")
-                        .unwrap_or(target)
-                        .to_string();
-                    existing.insert(slug);
-                }
-            }
-        }
-        for (norm, alias_label) in &raw_parent_alias_map {
-            // norm like "alpha.md"
-            let stem = norm.strip_suffix(".md").unwrap_or(norm).to_string();
-            let slug_candidate = slugify(&stem);
-            if existing.contains(&slug_candidate) {
-                continue;
+    let by_id: HashMap<&str, usize> = docs
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (d.id.as_str(), i))
+        .collect();
+    let inherited: Vec<Option<License>> = docs
+        .iter()
+        .map(|doc| {
+            if doc.license.is_some() {
+                return None;
             }
-            // Derive href using same layout rules
-            let href = if multi_page && !flat {
-                if is_root_index {
-                    // root page linking "up" to a non-structural parent (treat as pages/)
-                    format!("pages/{}.html", slug_candidate)
-                } else {
-                    // child page linking to another non-root parent
-                    format!("../pages/{}.html", slug_candidate)
+            let mut current = doc;
+            // Bounded walk up the first parent, in case of part_of cycles
+            for _ in 0..docs.len() {
+                let parent = current
+                    .parents
+                    .first()
+                    .and_then(|p| by_id.get(p.as_str()))?;
+                current = &docs[*parent];
+                if let Some(license) = &current.license {
+                    return Some(License {
+                        inherited: true,
+                        ..license.clone()
+                    });
                 }
-            } else if multi_page {
-                format!("{}.html", slug_candidate)
-            } else {
-                "index.html".to_string()
-            };
-            parent_links.push(format!(
-                "<a href=\"{}\">{}</a>",
-                href,
-                html_escape_text(alias_label)
-            ));
-            existing.insert(slug_candidate);
+            }
+            None
+        })
+        .collect();
+    for (doc, license) in docs.iter_mut().zip(inherited) {
+        if license.is_some() {
+            doc.license = license;
         }
     }
+}
 
-    static MD_LINK_RE: Lazy<Regex> =
-        Lazy::new(|| Regex::new(r"(?x)\[([^\]]+)\]\(([^)]+)\)").unwrap());
-
-    let mut out = String::new();
-    out.push_str("<ul class=\"metadata\">");
+// -------------------------------------------------------------------------------------------------
+// Markdown Rendering & Link Rewriting
+// -------------------------------------------------------------------------------------------------
 
-    for (k, v) in ordered {
-        out.push_str("<li><strong>");
-        html_esc_simple(&mut out, k);
-        out.push_str(":</strong> ");
+fn render_markdown(src: &str, opts: &markdown::Options) -> Result<String> {
+    markdown::to_html_with_options(src, opts).map_err(|e| anyhow!("Markdown render error: {e}"))
+}
 
-        // contents: emit alias-aware links (replace raw value)
-        if *k == "contents" {
-            if !child_links.is_empty() {
-                // For nested layout: adjust root vs child link prefixes.
-                if multi_page && !flat {
-                    let mut adjusted: Vec<String> = Vec::with_capacity(child_links.len());
-                    if is_root_index {
-                        // Links already have pages/ prefix (set earlier) so just reuse.
-                        adjusted = child_links.clone();
-                    } else {
-                        // Child page: remove any leading "pages/" (should not be present) to keep sibling links relative.
-                        for link in &child_links {
-                            // link format: <a href="...">label</a>
-                            if let Some(start) = link.find("href=\"") {
-                                let prefix_cut = &link[start + 6..];
-                                if let Some(end_quote) = prefix_cut.find('"') {
-                                    let href = &prefix_cut[..end_quote];
-                                    let label_start = link.find('>').unwrap_or(0) + 1;
-                                    let label_end = link.rfind('<').unwrap_or(link.len());
-                                    let label = &link[label_start..label_end];
-                                    // If href begins with "pages/", strip it.
-                                    let adjusted_href = if href.starts_with("pages/") {
-                                        &href[6..]
-                                    } else {
-                                        href
-                                    };
-                                    adjusted.push(format!(
-                                        "<a href=\"{}\">{}</a>",
-                                        adjusted_href, label
-                                    ));
-                                } else {
-                                    adjusted.push(link.clone());
-                                }
-                            } else {
-                                adjusted.push(link.clone());
-                            }
-                        }
-                    }
-                    out.push_str(&adjusted.join("<br/>"));
-                } else {
-                    out.push_str(&child_links.join("<br/>"));
-                }
-            } else {
-                let rendered = inline_yaml(v);
-                push_maybe_md_links(&mut out, &rendered, &MD_LINK_RE);
-            }
-            out.push_str("</li>");
+/// Turn Obsidian / MkDocs-style callouts (a blockquote starting with `[!type] Title`) into
+/// `<aside class="callout callout-<kind>">` blocks; `[!type]-` / `[!type]+` make them foldable
+/// `<details>` (collapsed / open). Aliases map to a common kind (`caution` -> `warning`, unknown
+/// types -> `note`) and the original type is kept in `data-callout`; icons come from the CSS.
+fn render_callouts(html: String) -> String {
+    static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<blockquote>|</blockquote>").unwrap());
+    static MARKER: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^<blockquote>\s*<p>\[!([A-Za-z][\w-]*)\]([+-]?)[ \t]*").unwrap());
+    if !html.contains("[!") {
+        return html;
+    }
+    let mut out = String::with_capacity(html.len() + 256);
+    let mut closers: Vec<&'static str> = Vec::new();
+    let mut last = 0;
+    for tag in TAG.find_iter(&html) {
+        if tag.start() < last {
             continue;
         }
-
-        // part_of: emit alias-aware parent links
-        if *k == "part_of" {
-            if !parent_links.is_empty() {
-                out.push_str(&parent_links.join("<br/>"));
-            } else {
-                let rendered = inline_yaml(v);
-                push_maybe_md_links(&mut out, &rendered, &MD_LINK_RE);
-            }
-            out.push_str("</li>");
+        out.push_str(&html[last..tag.start()]);
+        last = tag.end();
+        if tag.as_str() == "</blockquote>" {
+            out.push_str(closers.pop().unwrap_or("</blockquote>"));
             continue;
         }
-
-        // timestamps
-        if (*k == "created" || *k == "updated") && v.as_str().is_some() {
-            if let Some(s) = v.as_str() {
-                let pretty = humanize_timestamp(s);
-                html_esc_simple(&mut out, &pretty);
-                out.push_str("</li>");
-                continue;
+        let Some(caps) = MARKER.captures(&html[tag.start()..]) else {
+            out.push_str("<blockquote>");
+            closers.push("</blockquote>");
+            continue;
+        };
+        let kind_raw = caps[1].to_ascii_lowercase();
+        let kind = callout_kind(&kind_raw);
+        // The title runs to the end of the marker's line; the rest of that paragraph is body.
+        let title_start = tag.start() + caps[0].len();
+        let line = &html[title_start..];
+        let (title_end, rest) = match (line.find('\n'), line.find("</p>")) {
+            (Some(nl), Some(p)) if nl < p => (nl, nl + 1),
+            (_, Some(p)) => (p, p + "</p>".len()),
+            (Some(nl), None) => (nl, nl + 1),
+            (None, None) => (line.len(), line.len()),
+        };
+        let title = line[..title_end].trim();
+        let title = if title.is_empty() {
+            let mut chars = kind_raw.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        } else {
+            title.to_string()
+        };
+        let attrs = format!("class=\"callout callout-{kind}\" data-callout=\"{kind_raw}\"");
+        match &caps[2] {
+            "" => {
+                out.push_str(&format!(
+                    "<aside {attrs}><p class=\"callout-title\">{title}</p>\n"
+                ));
+                closers.push("</aside>");
+            }
+            fold => {
+                let open = if fold == "+" { " open" } else { "" };
+                out.push_str(&format!(
+                    "<details {attrs}{open}><summary class=\"callout-title\">{title}</summary>\n"
+                ));
+                closers.push("</details>");
             }
         }
-
-        // General value (with markdown link conversion)
-        let rendered = inline_yaml(v);
-        push_maybe_md_links(&mut out, &rendered, &MD_LINK_RE);
-        out.push_str("</li>");
+        if line[..rest].ends_with('\n') {
+            out.push_str("<p>");
+        }
+        last = title_start + rest;
     }
-
-    out.push_str("</ul>");
+    out.push_str(&html[last..]);
     out
 }
 
-/// If the string contains markdown links, convert them to HTML anchors (escaping text & href);
-/// otherwise escape the whole string.
-fn push_maybe_md_links(out: &mut String, s: &str, re: &Regex) {
-    if !re.is_match(s) {
-        html_esc_simple(out, s);
-        return;
-    }
-    let mut last = 0;
-    for cap in re.captures_iter(s) {
-        let m = cap.get(0).unwrap();
-        // text before match
-        if m.start() > last {
-            html_esc_simple(out, &s[last..m.start()]);
-        }
-        let text = &cap[1];
-        let href = &cap[2];
-        out.push_str("<a href=\"");
-        html_esc_simple(out, href);
-        out.push_str("\">");
-        html_esc_simple(out, text);
-        out.push_str("</a>");
-        last = m.end();
+/// Turn paragraphs holding only an image, optionally followed by an italic line, into
+/// `<figure>` blocks. The italic line becomes the caption, else the image's title text; images
+/// with neither stay as they are.
+fn render_figures(html: String) -> String {
+    static FIGURE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?s)<p>(<img\s[^>]*>)(?:[ \t]*\n[ \t]*<em>(.*?)</em>)?</p>").unwrap()
+    });
+    static TITLE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\stitle="([^"]*)""#).unwrap());
+    if !html.contains("<p><img") {
+        return html;
     }
-    if last < s.len() {
-        html_esc_simple(out, &s[last..]);
+    FIGURE
+        .replace_all(&html, |caps: &regex::Captures| {
+            let img = &caps[1];
+            let caption = match caps.get(2) {
+                // Two italic runs on the line (`*a* and *b*`) are not a caption.
+                Some(em) if em.as_str().contains("</em>") => return caps[0].to_string(),
+                Some(em) => em.as_str().to_string(),
+                None => match TITLE.captures(img) {
+                    Some(title) if !title[1].trim().is_empty() => title[1].to_string(),
+                    _ => return caps[0].to_string(),
+                },
+            };
+            format!("<figure>{img}<figcaption>{caption}</figcaption></figure>")
+        })
+        .into_owned()
+}
+
+/// Replace paragraphs holding only a link to a PDF with an embedded preview and a download link,
+/// when `enabled` or the link's title is `preview` (a `no-preview` title opts a link out). The
+/// hint title is dropped; attachment rewriting later points both URLs at the copied file.
+fn render_pdf_previews(html: String, enabled: bool) -> String {
+    static PDF_LINK: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r#"<p><a href="([^"]+?\.(?i:pdf)(?:#[^"]*)?)"(?: title="([^"]*)")?>(.*?)</a></p>"#,
+        )
+        .unwrap()
+    });
+    static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+    if !html.contains("<p><a href=") {
+        return html;
     }
+    PDF_LINK
+        .replace_all(&html, |caps: &regex::Captures| {
+            let hint = caps.get(2).map(|t| t.as_str().trim().to_ascii_lowercase());
+            let preview = match hint.as_deref() {
+                Some("preview") => true,
+                Some("no-preview") => false,
+                _ => enabled,
+            };
+            let (href, text) = (&caps[1], &caps[3]);
+            if !preview || text.contains("</a>") {
+                return match hint.as_deref() {
+                    Some("preview" | "no-preview") => format!("<p><a href=\"{href}\">{text}</a></p>"),
+                    _ => caps[0].to_string(),
+                };
+            }
+            let label = TAG.replace_all(text, "");
+            format!(
+                "<figure class=\"pdf-preview\"><iframe src=\"{href}\" title=\"{}\" loading=\"lazy\"></iframe>\
+                 <figcaption><a href=\"{href}\" download>{text}</a> (PDF)</figcaption></figure>",
+                label.replace('"', "&quot;")
+            )
+        })
+        .into_owned()
 }
-fn inline_yaml(v: &serde_yaml::Value) -> String {
-    use serde_yaml::Value;
-    match v {
-        Value::Null => "null".into(),
-        Value::Bool(b) => b.to_string(),
-        Value::Number(n) => n.to_string(),
-        Value::String(s) => s.trim().to_string(),
-        Value::Sequence(seq) => {
-            if seq.is_empty() {
-                "[]".into()
-            } else {
-                seq.iter().map(inline_yaml).collect::<Vec<_>>().join(", ")
+
+/// [`smarten`] the text of rendered HTML, leaving tags, entities other than `&quot;`, and the
+/// contents of code-like elements (`code`, `pre`, `kbd`, `samp`, `script`, `style`) untouched.
+fn smarten_html(html: &str) -> String {
+    const VERBATIM: [&str; 6] = ["code", "pre", "kbd", "samp", "script", "style"];
+    let mut out = String::with_capacity(html.len());
+    let mut prev = None;
+    let mut verbatim = 0usize;
+    let mut rest = html;
+    while !rest.is_empty() {
+        let (text, tail) = rest.split_at(rest.find('<').unwrap_or(rest.len()));
+        if verbatim > 0 {
+            out.push_str(text);
+            if let Some(c) = text.chars().last() {
+                prev = Some(c);
             }
+        } else {
+            out.push_str(&smarten(&text.replace("&quot;", "\""), &mut prev));
         }
-        Value::Mapping(map) => {
-            if map.is_empty() {
-                "{}".into()
+        let tag_end = tail.find('>').map_or(tail.len(), |i| i + 1);
+        let tag = &tail[..tag_end];
+        let name: String = tag
+            .trim_start_matches(['<', '/'])
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if VERBATIM.contains(&name.as_str()) && !tag.ends_with("/>") {
+            if tag.starts_with("</") {
+                verbatim = verbatim.saturating_sub(1);
             } else {
-                let mut kvs: Vec<String> = Vec::new();
-                for (k, vv) in map {
-                    if let Value::String(ks) = k {
-                        kvs.push(format!("{}={}", ks, inline_yaml(vv)));
-                    }
-                }
-                kvs.join("; ")
+                verbatim += 1;
             }
         }
-        Value::Tagged(tag) => format!("!{} {}", tag.tag, inline_yaml(&tag.value)),
+        out.push_str(tag);
+        rest = &tail[tag_end..];
     }
+    out
 }
-fn html_esc_simple(out: &mut String, s: &str) {
-    for ch in s.chars() {
-        match ch {
-            '&' => out.push_str("&amp;"),
-            '<' => out.push_str("&lt;"),
-            '>' => out.push_str("&gt;"),
-            '"' => out.push_str("&quot;"),
-            '\'' => out.push_str("&#39;"),
-            _ => out.push(ch),
+
+/// Typographic punctuation for plain text. `prev` is the character before `text` (carried
+/// across chunks so quotes next to markup still open or close correctly).
+fn smarten(text: &str, prev: &mut Option<char>) -> String {
+    let opens = |p: Option<char>| p.is_none_or(|c| c.is_whitespace() || "([{<–—-/".contains(c));
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let run = chars[i..].iter().take_while(|&&x| x == c).count();
+        let replaced = match c {
+            '-' if run == 3 => Some('—'),
+            '-' if run == 2 => Some('–'),
+            '.' if run == 3 => Some('…'),
+            '"' => Some(if opens(*prev) { '“' } else { '”' }),
+            '\'' => {
+                let next = chars.get(i + 1).copied();
+                let apostrophe = prev.is_some_and(char::is_alphanumeric)
+                    || next.is_some_and(|n| n.is_ascii_digit());
+                Some(if !apostrophe && opens(*prev) {
+                    '‘'
+                } else {
+                    '’'
+                })
+            }
+            _ => None,
+        };
+        match replaced {
+            Some(r) => {
+                out.push(r);
+                *prev = Some(r);
+                i += if matches!(c, '-' | '.') { run } else { 1 };
+            }
+            None => {
+                // Longer dash / dot runs stay as typed.
+                let n = if matches!(c, '-' | '.') { run } else { 1 };
+                out.extend(&chars[i..i + n]);
+                *prev = Some(c);
+                i += n;
+            }
         }
     }
+    out
 }
 
-/// Stand‑alone helpers (moved out of build_metadata_html to avoid nested fn declarations)
-fn extract_md_link_parts_raw(raw: &str) -> Option<(String, String)> {
-    static LINK_RE: Lazy<Regex> =
-        Lazy::new(|| Regex::new(r"^\[([^\]]*)]\(\s*<?([^)>]+)>?\s*\)$").unwrap());
-    let caps = LINK_RE.captures(raw.trim())?;
-    let alias = caps
-        .get(1)
-        .map(|m| m.as_str().trim().to_string())
-        .unwrap_or_default();
-    let target = caps
-        .get(2)
-        .map(|m| m.as_str().trim().to_string())
-        .unwrap_or_default();
-    Some((alias, target))
+/// Styling kind for a callout type, following Obsidian's aliases.
+fn callout_kind(kind: &str) -> &'static str {
+    match kind {
+        "abstract" | "summary" | "tldr" => "abstract",
+        "info" => "info",
+        "todo" => "todo",
+        "tip" | "hint" | "important" => "tip",
+        "success" | "check" | "done" => "success",
+        "question" | "help" | "faq" => "question",
+        "warning" | "caution" | "attention" => "warning",
+        "failure" | "fail" | "missing" => "failure",
+        "danger" | "error" => "danger",
+        "bug" => "bug",
+        "example" => "example",
+        "quote" | "cite" => "quote",
+        _ => "note",
+    }
 }
 
-fn normalize_target_basename_raw(t: &str) -> String {
-    let last = t.rsplit('/').next().unwrap_or(t);
-    if last.to_ascii_lowercase().ends_with(".md") {
-        last.to_string()
-    } else {
-        format!("{}.md", last)
-    }
+/// Per-build rendering state shared by every page.
+///
+/// Built once after filtering: layout facts, Markdown parser options, and the internal link
+/// rewriter (basename table). Pages only borrow it, so per-page work is limited to the page itself.
+struct RenderContext {
+    multi_page: bool,
+    /// Output path of every page; all links between pages are computed from it.
+    layout: output::SiteLayout,
+    root_slug: Option<String>,
+    markdown: markdown::Options,
+    rewriter: Option<LinkRewriter>,
+    large_body_bytes: usize,
+    smart_punctuation: bool,
+    figures: bool,
+    pdf_previews: bool,
+    /// See [`CoreBuildOptions::seal_private`].
+    seal_private: bool,
+    /// Vault-declared fields, for metadata labels and rendering.
+    schema: schema::Schema,
 }
 
-fn html_escape_text(s: &str) -> String {
-    let mut out = String::new();
-    for ch in s.chars() {
-        match ch {
-            '&' => out.push_str("&amp;"),
-            '<' => out.push_str("&lt;"),
-            '>' => out.push_str("&gt;"),
-            '"' => out.push_str("&quot;"),
-            '\'' => out.push_str("&#39;"),
-            _ => out.push(ch),
+impl RenderContext {
+    fn new(docs: &[Doc], opts: &CoreBuildOptions, schema: schema::Schema) -> Self {
+        let multi_page = docs.iter().any(|d| d.is_root_index) && docs.len() > 1;
+        let layout = if opts.flat {
+            OutputLayout::Flat
+        } else {
+            opts.layout
+        };
+        Self {
+            multi_page,
+            layout: output::SiteLayout::new(layout, multi_page, docs),
+            root_slug: docs.iter().find(|d| d.is_root_index).map(|d| d.id.clone()),
+            markdown: markdown::Options::default(),
+            rewriter: opts.rewrite_links.then(|| LinkRewriter::new(docs)),
+            large_body_bytes: opts.large_body_bytes.unwrap_or(DEFAULT_LARGE_BODY_BYTES),
+            smart_punctuation: opts.smart_punctuation,
+            figures: opts.figures,
+            pdf_previews: opts.pdf_previews,
+            seal_private: opts.seal_private,
+            schema,
         }
     }
-    out
+
+    /// Render a document body to HTML (HTML bodies pass through) and rewrite its internal links.
+    fn render_body(&self, doc: &Doc, cache: Option<&mut RenderCache>) -> Result<String> {
+        let html = match cache {
+            _ if doc.html_body => doc.body_md.clone(),
+            Some(cache) => render_callouts(cache.render(doc, &self.markdown)?),
+            None => render_callouts(render_markdown(&doc.body_md, &self.markdown)?),
+        };
+        let html = if self.figures && !doc.html_body {
+            render_figures(html)
+        } else {
+            html
+        };
+        let html = if doc.html_body {
+            html
+        } else {
+            render_pdf_previews(html, self.pdf_previews)
+        };
+        let html = if self.smart_punctuation {
+            smarten_html(&html)
+        } else {
+            html
+        };
+        Ok(match &self.rewriter {
+            Some(rewriter) => rewriter.rewrite(&self.layout, &doc.id, html),
+            None => html,
+        })
+    }
 }
 
-// -------------------------------------------------------------------------------------------------
-// (Optional) WASM bindings (behind "wasm" feature)
-// -------------------------------------------------------------------------------------------------
+/// Work kept between builds (see [`build_site_cached`]): each file's parsed frontmatter and
+/// rendered Markdown, keyed by source path, and the link graph.
+///
+/// A parse is reused while the file is byte-for-byte unchanged (and the `diaryx.toml` schema with
+/// it), a render while the page's Markdown body is, and the graph while no page changed its
+/// `contents`, `part_of` or `translations` and none was added or removed. Entries for documents
+/// that are no longer part of the site are dropped as each build starts.
+#[derive(Debug, Default)]
+pub struct RenderCache {
+    sources: SourceCache,
+    graph: Option<CachedGraph>,
+    bodies: HashMap<String, CachedBody>,
+    stats: RenderCacheStats,
+}
 
-#[cfg(feature = "wasm")]
-mod wasm_bindings {
-    use super::*;
-    use serde::{Deserialize, Serialize};
-    use std::collections::HashMap;
-    use wasm_bindgen::prelude::*;
+#[derive(Debug)]
+struct CachedBody {
+    body_hash: u64,
+    body_len: usize,
+    html: String,
+}
+
+/// Parsed sources by path, for the schema they were parsed with.
+#[derive(Debug, Default)]
+struct SourceCache {
+    schema_hash: u64,
+    entries: HashMap<String, CachedSource>,
+    parsed: usize,
+}
+
+#[derive(Debug)]
+struct CachedSource {
+    raw_hash: u64,
+    raw_len: usize,
+    html_file: bool,
+    times: Option<FileMetadata>,
+    parts: SourceParts,
+}
+
+/// The outcome of [`link_graph`] and [`check_part_of`] for one document set.
+#[derive(Debug)]
+struct CachedGraph {
+    key: u64,
+    links: HashMap<String, DocLinks>,
+}
+
+/// What linking adds to a [`Doc`].
+#[derive(Debug)]
+struct DocLinks {
+    children: Vec<String>,
+    parents: Vec<String>,
+    child_aliases: HashMap<String, String>,
+    parent_aliases: HashMap<String, String>,
+    contents_alias_by_name: HashMap<String, String>,
+    part_of_alias_by_name: HashMap<String, String>,
+    translations: Vec<String>,
+    warnings: Vec<Warning>,
+}
+
+/// Per-build counters of a [`RenderCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RenderCacheStats {
+    /// Pages whose Markdown was rendered during the last build.
+    pub rendered: usize,
+    /// Pages whose previous render was reused.
+    pub reused: usize,
+    /// Files whose frontmatter was parsed during the last build (the others were reused).
+    pub parsed: usize,
+    /// Whether the last build had to link the page graph again.
+    pub relinked: bool,
+}
 
-    // Simple in-memory FS for WASM usage
-    pub struct InMemoryFs {
-        files: HashMap<String, String>,
+fn hash_of(value: &impl std::hash::Hash) -> u64 {
+    use std::hash::Hasher;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl RenderCache {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    impl InMemoryFs {
-        pub fn new(files: HashMap<String, String>) -> Self {
-            Self { files }
-        }
-        fn normalize(path: &str) -> String {
-            // Very light normalization; real impl might collapse ../ .
-            path.replace('\\', "/")
+    /// Counters for the most recent build.
+    pub fn stats(&self) -> RenderCacheStats {
+        RenderCacheStats {
+            parsed: self.sources.parsed,
+            ..self.stats
         }
     }
 
-    impl FileProvider for InMemoryFs {
-        fn read_to_string(&self, path: &str) -> Result<String> {
-            let p = Self::normalize(path);
-            self.files
-                .get(&p)
-                .cloned()
-                .ok_or_else(|| anyhow!("File not found: {p}"))
-        }
-        fn exists(&self, path: &str) -> bool {
-            let p = Self::normalize(path);
-            self.files.contains_key(&p)
-        }
-        fn is_file(&self, path: &str) -> bool {
-            self.exists(path)
-        }
-        fn join(&self, parent: &str, rel: &str) -> String {
-            if parent.is_empty() {
-                Self::normalize(rel)
-            } else {
-                let mut base = parent.trim_end_matches('/').to_string();
-                base.push('/');
-                base.push_str(rel.trim_start_matches('/'));
-                Self::normalize(&base)
-            }
-        }
-        fn extension_lowercase(&self, path: &str) -> Option<String> {
-            let p = Self::normalize(path);
-            p.rsplit('/')
-                .next()
-                .and_then(|f| f.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()))
-        }
-        fn parent(&self, path: &str) -> Option<String> {
-            let p = Self::normalize(path);
-            match p.rsplit_once('/') {
-                Some((dir, _)) if !dir.is_empty() => Some(dir.to_string()),
-                _ => Some(String::new()),
-            }
-        }
-        fn file_name(&self, path: &str) -> Option<String> {
-            let p = Self::normalize(path);
-            Some(p.rsplit('/').next().unwrap_or(&p).to_string())
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Reset the counters, and forget every parse when the schema changed.
+    fn start(&mut self, schema: &schema::Schema) {
+        self.stats = RenderCacheStats::default();
+        self.sources.parsed = 0;
+        let schema_hash = hash_of(schema);
+        if schema_hash != self.sources.schema_hash {
+            self.sources.entries.clear();
+            self.sources.schema_hash = schema_hash;
         }
     }
 
-    #[derive(Deserialize)]
-    struct WasmInput {
-        entry: String,
-        files: HashMap<String, String>,
-        #[serde(default)]
-        include_nonpublic: bool,
-        #[serde(default)]
-        flat: bool,
-        #[serde(default)]
-        strict: bool,
-        #[serde(default = "default_true")]
-        rewrite_links: bool,
+    /// [`link_graph`] and [`check_part_of`], or their previous outcome when the links of `docs`
+    /// are unchanged. Parses of files that are no longer collected are dropped.
+    fn link(&mut self, docs: &mut [Doc], fs: &impl FileProvider) {
+        let live: HashSet<&str> = docs.iter().map(|d| d.abs_path.as_str()).collect();
+        self.sources
+            .entries
+            .retain(|path, _| live.contains(path.as_str()));
+
+        let key = hash_of(
+            &docs
+                .iter()
+                .map(|d| {
+                    (
+                        &d.abs_path,
+                        &d.id,
+                        d.is_index,
+                        &d.contents_raw,
+                        &d.raw_part_of,
+                        &d.raw_translations,
+                        d.frontmatter.is_mapping(),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        );
+        if let Some(graph) = self.graph.as_ref().filter(|g| g.key == key) {
+            for doc in docs.iter_mut() {
+                if let Some(links) = graph.links.get(&doc.abs_path) {
+                    links.apply(doc);
+                }
+            }
+            return;
+        }
+        let warned: Vec<usize> = docs.iter().map(|d| d.warnings.len()).collect();
+        link_graph(docs, fs);
+        check_part_of(docs, fs);
+        self.stats.relinked = true;
+        let links = docs
+            .iter()
+            .zip(warned)
+            .map(|(d, n)| (d.abs_path.clone(), DocLinks::of(d, n)))
+            .collect();
+        self.graph = Some(CachedGraph { key, links });
     }
 
-    fn default_true() -> bool {
-        true
+    fn begin(&mut self, docs: &[Doc]) {
+        let live: HashSet<&str> = docs.iter().map(|d| d.abs_path.as_str()).collect();
+        self.bodies.retain(|path, _| live.contains(path.as_str()));
     }
 
-    #[derive(Serialize)]
-    struct WasmOutput {
-        pages: Vec<super::PageOutput>,
-        warnings: Vec<String>,
-        multi_page: bool,
-        root_slug: Option<String>,
+    fn render(&mut self, doc: &Doc, opts: &markdown::Options) -> Result<String> {
+        let body_hash = hash_of(&doc.body_md);
+        if let Some(hit) = self.bodies.get(&doc.abs_path)
+            && hit.body_hash == body_hash
+            && hit.body_len == doc.body_md.len()
+        {
+            self.stats.reused += 1;
+            return Ok(hit.html.clone());
+        }
+        let html = render_markdown(&doc.body_md, opts)?;
+        self.stats.rendered += 1;
+        self.bodies.insert(
+            doc.abs_path.clone(),
+            CachedBody {
+                body_hash,
+                body_len: doc.body_md.len(),
+                html: html.clone(),
+            },
+        );
+        Ok(html)
     }
+}
 
-    #[wasm_bindgen]
-    pub fn build_diaryx(payload_json: String) -> Result<String, JsValue> {
-        let input: WasmInput = serde_json::from_str(&payload_json)
-            .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {e}")))?;
-        let fs = InMemoryFs::new(input.files);
-        let opts = CoreBuildOptions {
-            include_nonpublic: input.include_nonpublic,
-            flat: input.flat,
-            strict: input.strict,
-            rewrite_links: input.rewrite_links,
-        };
-        let artifacts = build_site(&input.entry, opts, &fs)
-            .map_err(|e| JsValue::from_str(&format!("Build error: {e}")))?;
-        let out = WasmOutput {
-            pages: artifacts.pages,
-            warnings: artifacts.warnings,
-            multi_page: artifacts.multi_page,
-            root_slug: artifacts.root_slug,
+impl SourceCache {
+    /// [`parse_source`] / [`parse_html_source`], or the previous parse of the same text.
+    fn parse(
+        &mut self,
+        path: &str,
+        raw: String,
+        html_file: bool,
+        schema: &schema::Schema,
+        times: Option<FileMetadata>,
+    ) -> SourceParts {
+        let raw_hash = hash_of(&raw);
+        if let Some(hit) = self.entries.get(path)
+            && hit.raw_hash == raw_hash
+            && hit.raw_len == raw.len()
+            && hit.html_file == html_file
+            && hit.times == times
+        {
+            return hit.parts.clone();
+        }
+        let raw_len = raw.len();
+        let parts = if html_file {
+            parse_html_source(path, raw, schema, times)
+        } else {
+            parse_source(path, raw, schema, times)
         };
-        serde_json::to_string(&out).map_err(|e| JsValue::from_str(&format!("Serialize error: {e}")))
+        self.parsed += 1;
+        self.entries.insert(
+            path.to_string(),
+            CachedSource {
+                raw_hash,
+                raw_len,
+                html_file,
+                times,
+                parts: parts.clone(),
+            },
+        );
+        parts
     }
 }
 
-// -------------------------------------------------------------------------------------------------
-// Tests (basic smoke)
-// -------------------------------------------------------------------------------------------------
+impl DocLinks {
+    /// The links of a linked `doc`, which had `warned` warnings before linking.
+    fn of(doc: &Doc, warned: usize) -> Self {
+        Self {
+            children: doc.children.clone(),
+            parents: doc.parents.clone(),
+            child_aliases: doc.child_aliases.clone(),
+            parent_aliases: doc.parent_aliases.clone(),
+            contents_alias_by_name: doc.contents_alias_by_name.clone(),
+            part_of_alias_by_name: doc.part_of_alias_by_name.clone(),
+            translations: doc.translations.clone(),
+            warnings: doc.warnings[warned..].to_vec(),
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    fn apply(&self, doc: &mut Doc) {
+        doc.children.clone_from(&self.children);
+        doc.parents.clone_from(&self.parents);
+        doc.child_aliases.clone_from(&self.child_aliases);
+        doc.parent_aliases.clone_from(&self.parent_aliases);
+        doc.contents_alias_by_name
+            .clone_from(&self.contents_alias_by_name);
+        doc.part_of_alias_by_name
+            .clone_from(&self.part_of_alias_by_name);
+        doc.translations.clone_from(&self.translations);
+        doc.raw_translations.clear();
+        doc.warnings.extend(self.warnings.iter().cloned());
+    }
+}
 
-    struct TestFs {
-        map: HashMap<String, String>,
+/// Rewrites internal .md links (and links to .html / .htm source pages) in rendered HTML to the
+/// corresponding .html page names. Links to files outside the site are left alone. Built once per build from the (filtered) document set, then applied page by page.
+struct LinkRewriter {
+    by_basename: HashMap<String, String>, // basename -> slug
+}
+
+impl LinkRewriter {
+    fn new(docs: &[Doc]) -> Self {
+        let by_basename = docs
+            .iter()
+            .map(|d| {
+                let name = d
+                    .abs_path
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(&d.abs_path)
+                    .to_string();
+                (name, d.id.clone())
+            })
+            .collect();
+        Self { by_basename }
     }
-    impl TestFs {
-        fn new(map: &[(&str, &str)]) -> Self {
-            Self {
-                map: map
-                    .iter()
-                    .map(|(k, v)| (k.to_string(), v.to_string()))
-                    .collect(),
+
+    /// Rewrite the links of the page `current` to the pages' places in `layout`.
+    fn rewrite(&self, layout: &output::SiteLayout, current: &str, html: String) -> String {
+        static HREF_MD: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r#"href="([^"]+?\.(?i:md|html?)(?:[?#][^"]*)?)""#).unwrap());
+
+        // Cheap, allocation-free pre-check (bodies can be very large)
+        if !html
+            .as_bytes()
+            .windows(4)
+            .any(|w| w[..3].eq_ignore_ascii_case(b".md") || w.eq_ignore_ascii_case(b".htm"))
+        {
+            return html;
+        }
+        let mut new_html = String::with_capacity(html.len());
+        let mut last = 0;
+        for cap in HREF_MD.captures_iter(&html) {
+            let m = cap.get(0).unwrap();
+            let url = cap.get(1).unwrap().as_str();
+            let core = url.split(&['?', '#'][..]).next().unwrap_or(url);
+            let basename = core.rsplit('/').next().unwrap_or(core);
+            let basename_norm = basename.replace("%20", " ");
+            if let Some(target_slug) = self.by_basename.get(&basename_norm) {
+                let new_href = layout.href(current, target_slug);
+                let mut suffix = "";
+                if let Some(idx) = url.find(['?', '#']) {
+                    suffix = &url[idx..];
+                }
+                new_html.push_str(&html[last..m.start()]);
+                new_html.push_str("href=\"");
+                new_html.push_str(&new_href);
+                new_html.push_str(suffix);
+                new_html.push('"');
+                last = m.end();
             }
         }
+        new_html.push_str(&html[last..]);
+        new_html
     }
-    impl FileProvider for TestFs {
-        fn read_to_string(&self, path: &str) -> Result<String> {
-            self.map
-                .get(path)
+}
+
+// -------------------------------------------------------------------------------------------------
+// Utilities
+// -------------------------------------------------------------------------------------------------
+
+fn slugify(s: &str) -> String {
+    static NON_ALNUM: Lazy<Regex> = Lazy::new(|| Regex::new(r"[^a-z0-9]+").unwrap());
+    let lower = s.to_ascii_lowercase();
+    let replaced = NON_ALNUM.replace_all(&lower, "-");
+    replaced.trim_matches('-').to_string()
+}
+
+#[allow(dead_code)]
+fn humanize_timestamp(raw: &str) -> String {
+    if let Some(dt) = parse_created(raw) {
+        let date_fmt =
+            time::format_description::parse("[month repr:long] [day padding:zero], [year]")
+                .unwrap_or_else(|_| {
+                    time::format_description::parse("[year]-[month]-[day]").unwrap()
+                });
+        let date_str = dt.format(&date_fmt).unwrap_or_else(|_| raw.to_string());
+        // A bare date has no time to show
+        if raw.trim().len() <= 10 {
+            return date_str;
+        }
+        let hour = dt.hour();
+        let (h12, ampm) = match hour {
+            0 => (12, "am"),
+            1..=11 => (hour, "am"),
+            12 => (12, "pm"),
+            _ => (hour - 12, "pm"),
+        };
+        let minute = dt.minute();
+        let offset: UtcOffset = dt.offset();
+        let hours = offset.whole_hours();
+        let tz_label = if hours == 0 {
+            "UTC".to_string()
+        } else {
+            format!("UTC{:+}", hours)
+        };
+        return format!(
+            "{}, {:02}:{:02}{} ({})",
+            date_str, h12, minute, ampm, tz_label
+        );
+    }
+    raw.to_string()
+}
+/// Build minimal metadata HTML (unordered list). Caller supplies CSS.
+/// Includes special formatting for created / updated if present.
+fn build_metadata_html(ctx: &RenderContext, doc: &Doc) -> String {
+    use serde_yaml::Value;
+    let is_index = doc.is_index;
+    let (children, parents) = (&doc.children, &doc.parents);
+    let (child_alias_map, parent_alias_map) = (&doc.child_aliases, &doc.parent_aliases);
+    let mapping = match &doc.frontmatter {
+        Value::Mapping(m) => m,
+        _ => return String::new(),
+    };
+    if mapping.is_empty() {
+        return String::new();
+    }
+
+    // 1. Gather original key insertion order (preserve author intent)
+    // serde_yaml::Mapping preserves insertion order in iteration
+    let mut ordered: Vec<(&String, &Value)> = Vec::new();
+    for (k, v) in mapping {
+        if let Value::String(s) = k {
+            ordered.push((s, v));
+        }
+    }
+
+    // Ensure 'reachable' renders last regardless of YAML insertion order
+    {
+        let mut reachable_entry: Option<(&String, &Value)> = None;
+        ordered.retain(|(k, v)| {
+            if *k == "reachable" {
+                reachable_entry = Some((*k, *v));
+                false
+            } else {
+                true
+            }
+        });
+        if let Some(r) = reachable_entry {
+            ordered.push(r);
+        }
+    }
+
+    // 2. Alias-aware child link rendering.
+    // Alias text recovered from the original 'contents' markdown links ("[Alias](target.md)")
+    // during linking: normalized_target_basename -> alias.
+    let alias_map = &doc.contents_alias_by_name;
+
+    // 3. Child links (alias text if available) with layout-aware hrefs
+    let mut child_links: Vec<String> = Vec::new();
+    if is_index && !children.is_empty() {
+        for slug in children {
+            // Normalized key used when deriving aliases from raw contents link targets
+            let md_key = format!("{slug}.md");
+            // Precedence:
+            // 1. Explicit child_alias_map (slug -> alias) from graph phase
+            // 2. Raw contents alias_map (normalized basename -> alias)
+            // 3. Fallback to slug
+            let label = child_alias_map
+                .get(slug)
                 .cloned()
-                .ok_or_else(|| anyhow!("not found: {path}"))
+                .or_else(|| alias_map.get(&md_key).cloned())
+                .unwrap_or_else(|| slug.to_string());
+            let href = ctx.layout.href(&doc.id, slug);
+            child_links.push(format!(
+                "<a href=\"{}\">{}</a>",
+                href,
+                html_escape_text(&label)
+            ));
         }
-        fn exists(&self, path: &str) -> bool {
-            self.map.contains_key(path)
+    }
+
+    // 3b. Parent (part_of) links
+    // Map from normalized basename -> alias extracted from raw part_of during linking
+    let raw_parent_alias_map = &doc.part_of_alias_by_name;
+    let mut parent_links: Vec<String> = Vec::new();
+    if !parents.is_empty() {
+        for parent_slug in parents {
+            let md_key = format!("{parent_slug}.md");
+            // Precedence:
+            // 1. parent_aliases (slug -> alias) from graph phase
+            // 2. raw_parent_alias_map (normalized target -> alias)
+            // 3. fallback to slug
+            let label = parent_alias_map
+                .get(parent_slug)
+                .cloned()
+                .or_else(|| parent_alias_map.get(&md_key).cloned())
+                .or_else(|| raw_parent_alias_map.get(&md_key).cloned())
+                .unwrap_or_else(|| parent_slug.to_string());
+            let href = ctx.layout.href(&doc.id, parent_slug);
+
+            parent_links.push(format!(
+                "<a href=\"{}\">{}</a>",
+                href,
+                html_escape_text(&label)
+            ));
         }
-        fn is_file(&self, path: &str) -> bool {
-            self.exists(path)
+    }
+    // Include non-structural part_of targets (aliases) not present in structural parents.
+    // These are entries in raw_part_of whose targets were not discovered via contents traversal.
+    if !raw_parent_alias_map.is_empty() {
+        // Build a set of already linked slugs (derive slug from filename stem).
+        let mut existing: std::collections::HashSet<String> = parents.iter().cloned().collect();
+        // Also capture slugs already pushed (in case of duplicates)
+        for link in &parent_links {
+            // crude extraction: href=".../slug.html" or href="slug.html"
+            if let Some(href_start) = link.find("href=\"") {
+                let rest = &link[href_start + 6..];
+                if let Some(end_q) = rest.find('"') {
+                    let target = &rest[..end_q];
+                    // pull final component without extension
+                    let slug = target
+                        .rsplit('/')
+                        .next()
+                        .unwrap_or(target)
+                        .strip_suffix(".html")
+                        .unwrap_or(target)
+                        .to_string();
+                    existing.insert(slug);
+                }
+            }
         }
-        fn join(&self, parent: &str, rel: &str) -> String {
-            if parent.is_empty() {
-                rel.to_string()
+        for (norm, alias_label) in raw_parent_alias_map {
+            // norm like "alpha.md"
+            let stem = norm.strip_suffix(".md").unwrap_or(norm).to_string();
+            let slug_candidate = slugify(&stem);
+            if existing.contains(&slug_candidate) {
+                continue;
+            }
+            let href = ctx.layout.href(&doc.id, &slug_candidate);
+            parent_links.push(format!(
+                "<a href=\"{}\">{}</a>",
+                href,
+                html_escape_text(alias_label)
+            ));
+            existing.insert(slug_candidate);
+        }
+    }
+
+    static MD_LINK_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?x)\[([^\]]+)\]\(([^)]+)\)").unwrap());
+
+    let mut out = String::new();
+    out.push_str("<ul class=\"metadata\">");
+
+    for (k, v) in ordered {
+        out.push_str("<li><strong>");
+        html_esc_simple(&mut out, ctx.schema.label(k));
+        out.push_str(":</strong> ");
+
+        // contents: emit alias-aware links (replace raw value)
+        if *k == "contents" {
+            if !child_links.is_empty() {
+                out.push_str(&child_links.join("<br/>"));
             } else {
-                format!(
-                    "{}/{}",
-                    parent.trim_end_matches('/'),
-                    rel.trim_start_matches('/')
-                )
+                let rendered = inline_yaml(v);
+                push_maybe_md_links(&mut out, &rendered, &MD_LINK_RE);
             }
+            out.push_str("</li>");
+            continue;
         }
-        fn extension_lowercase(&self, path: &str) -> Option<String> {
-            path.rsplit('/')
-                .next()
-                .and_then(|f| f.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()))
+
+        // part_of: emit alias-aware parent links
+        if *k == "part_of" {
+            if !parent_links.is_empty() {
+                out.push_str(&parent_links.join("<br/>"));
+            } else {
+                let rendered = inline_yaml(v);
+                push_maybe_md_links(&mut out, &rendered, &MD_LINK_RE);
+            }
+            out.push_str("</li>");
+            continue;
         }
-        fn parent(&self, path: &str) -> Option<String> {
-            match path.rsplit_once('/') {
-                Some((dir, _)) => Some(dir.to_string()),
-                None => Some(String::new()),
+
+        if *k == "author" {
+            let authors = parse_authors(v);
+            if !authors.is_empty() {
+                out.push_str(&render_authors(&authors));
+                out.push_str("</li>");
+                continue;
+            }
+        }
+
+        if *k == "version"
+            && let Some(version) = version_text(v)
+        {
+            out.push_str("<span class=\"page-version\">");
+            html_esc_simple(&mut out, &version);
+            out.push_str("</span></li>");
+            continue;
+        }
+
+        if *k == "reachable" && !doc.reachable.is_empty() {
+            out.push_str(&render_reachable(&doc.reachable));
+            out.push_str("</li>");
+            continue;
+        }
+
+        // vault-declared fields (diaryx.toml)
+        if let Some(html) = ctx.schema.fields.get(k.as_str()).and_then(|f| f.render(v)) {
+            match &ctx.rewriter {
+                Some(rewriter) => out.push_str(&rewriter.rewrite(&ctx.layout, &doc.id, html)),
+                None => out.push_str(&html),
+            }
+            out.push_str("</li>");
+            continue;
+        }
+
+        // timestamps
+        if (*k == "created" || *k == "updated" || *k == "published")
+            && v.as_str().is_some()
+            && let Some(s) = v.as_str()
+        {
+            let pretty = humanize_timestamp(s);
+            html_esc_simple(&mut out, &pretty);
+            if doc.inferred.iter().any(|f| f == k) {
+                out.push_str(
+                    " <span class=\"inferred\" title=\"From the file's timestamps\">(inferred)</span>",
+                );
+            }
+            out.push_str("</li>");
+            continue;
+        }
+
+        // General value (with markdown link conversion)
+        let rendered = inline_yaml(v);
+        push_maybe_md_links(&mut out, &rendered, &MD_LINK_RE);
+        out.push_str("</li>");
+    }
+
+    out.push_str("</ul>");
+    out
+}
+
+/// If the string contains markdown links, convert them to HTML anchors (escaping text & href);
+/// otherwise escape the whole string.
+fn push_maybe_md_links(out: &mut String, s: &str, re: &Regex) {
+    if !re.is_match(s) {
+        html_esc_simple(out, s);
+        return;
+    }
+    let mut last = 0;
+    for cap in re.captures_iter(s) {
+        let m = cap.get(0).unwrap();
+        // text before match
+        if m.start() > last {
+            html_esc_simple(out, &s[last..m.start()]);
+        }
+        let text = &cap[1];
+        let href = &cap[2];
+        out.push_str("<a href=\"");
+        html_esc_simple(out, href);
+        out.push_str("\">");
+        html_esc_simple(out, text);
+        out.push_str("</a>");
+        last = m.end();
+    }
+    if last < s.len() {
+        html_esc_simple(out, &s[last..]);
+    }
+}
+fn inline_yaml(v: &serde_yaml::Value) -> String {
+    use serde_yaml::Value;
+    match v {
+        Value::Null => "null".into(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.trim().to_string(),
+        Value::Sequence(seq) => {
+            if seq.is_empty() {
+                "[]".into()
+            } else {
+                seq.iter().map(inline_yaml).collect::<Vec<_>>().join(", ")
+            }
+        }
+        Value::Mapping(map) => {
+            if map.is_empty() {
+                "{}".into()
+            } else {
+                let mut kvs: Vec<String> = Vec::new();
+                for (k, vv) in map {
+                    if let Value::String(ks) = k {
+                        kvs.push(format!("{}={}", ks, inline_yaml(vv)));
+                    }
+                }
+                kvs.join("; ")
+            }
+        }
+        Value::Tagged(tag) => format!("!{} {}", tag.tag, inline_yaml(&tag.value)),
+    }
+}
+fn html_esc_simple(out: &mut String, s: &str) {
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+/// Stand‑alone helpers (moved out of build_metadata_html to avoid nested fn declarations)
+fn extract_md_link_parts_raw(raw: &str) -> Option<(String, String)> {
+    static LINK_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^\[([^\]]*)]\(\s*<?([^)>]+)>?\s*\)$").unwrap());
+    let caps = LINK_RE.captures(raw.trim())?;
+    let alias = caps
+        .get(1)
+        .map(|m| m.as_str().trim().to_string())
+        .unwrap_or_default();
+    let target = caps
+        .get(2)
+        .map(|m| m.as_str().trim().to_string())
+        .unwrap_or_default();
+    Some((alias, target))
+}
+
+fn normalize_target_basename_raw(t: &str) -> String {
+    let last = t.rsplit('/').next().unwrap_or(t);
+    if last.to_ascii_lowercase().ends_with(".md") {
+        last.to_string()
+    } else {
+        format!("{}.md", last)
+    }
+}
+
+fn html_escape_text(s: &str) -> String {
+    let mut out = String::new();
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+// -------------------------------------------------------------------------------------------------
+// Tests (basic smoke)
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestFs {
+        map: HashMap<String, String>,
+    }
+    impl TestFs {
+        fn new(map: &[(&str, &str)]) -> Self {
+            Self {
+                map: map
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            }
+        }
+    }
+    impl FileProvider for TestFs {
+        fn read_to_string(&self, path: &str) -> Result<String> {
+            self.map
+                .get(path)
+                .cloned()
+                .ok_or_else(|| anyhow!("not found: {path}"))
+        }
+        fn exists(&self, path: &str) -> bool {
+            self.map.contains_key(path)
+        }
+        fn is_file(&self, path: &str) -> bool {
+            self.exists(path)
+        }
+        fn join(&self, parent: &str, rel: &str) -> String {
+            if parent.is_empty() {
+                rel.to_string()
+            } else {
+                format!(
+                    "{}/{}",
+                    parent.trim_end_matches('/'),
+                    rel.trim_start_matches('/')
+                )
+            }
+        }
+        fn extension_lowercase(&self, path: &str) -> Option<String> {
+            path.rsplit('/')
+                .next()
+                .and_then(|f| f.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()))
+        }
+        fn parent(&self, path: &str) -> Option<String> {
+            match path.rsplit_once('/') {
+                Some((dir, _)) => Some(dir.to_string()),
+                None => Some(String::new()),
+            }
+        }
+        fn file_name(&self, path: &str) -> Option<String> {
+            Some(path.rsplit('/').next().unwrap_or(path).to_string())
+        }
+        fn metadata(&self, path: &str) -> Option<FileMetadata> {
+            self.map.get(path).map(|content| FileMetadata {
+                created: OffsetDateTime::from_unix_timestamp(1_700_000_000).ok(),
+                modified: OffsetDateTime::from_unix_timestamp(1_710_000_000).ok(),
+                size: Some(content.len() as u64),
+            })
+        }
+        fn list_files(&self, dir: &str) -> Option<Vec<String>> {
+            let prefix = if dir.is_empty() {
+                String::new()
+            } else {
+                format!("{dir}/")
+            };
+            Some(
+                self.map
+                    .keys()
+                    .filter(|k| k.starts_with(&prefix))
+                    .cloned()
+                    .collect(),
+            )
+        }
+    }
+
+    // Helper to extract all hrefs from a snippet
+    fn hrefs(html: &str) -> Vec<String> {
+        let mut v = Vec::new();
+        let re = regex::Regex::new(r#"href="([^"]+)""#).unwrap();
+        for cap in re.captures_iter(html) {
+            v.push(cap[1].to_string());
+        }
+        v
+    }
+
+    // Helper: assert that every expected href appears at least once
+    fn assert_hrefs_contains(html: &str, expected: &[&str]) {
+        let list = hrefs(html);
+        for e in expected {
+            assert!(
+                list.iter().any(|h| h == e),
+                "Expected href '{}' not found in {:?}",
+                e,
+                list
+            );
+        }
+    }
+
+    #[test]
+    fn smoke_single() {
+        let fs = TestFs::new(&[(
+            "entry.md",
+            r#"---
+title: Test Doc
+author: Someone
+created: 2025-08-25T10:00:00Z
+updated: 2025-08-25T10:00:00Z
+visibility: public
+format: "[CommonMark](https://spec.commonmark.org/)"
+---
+Hello **world**.
+"#,
+        )]);
+        let artifacts = build_site(
+            "entry.md",
+            CoreBuildOptions {
+                rewrite_links: true,
+                ..Default::default()
+            },
+            &fs,
+        )
+        .expect("build ok");
+        assert_eq!(artifacts.pages.len(), 1);
+        assert!(artifacts.pages[0].html.contains("<strong>world</strong>"));
+        assert_eq!(artifacts.pages[0].file_name, "index.html");
+        // metadata should contain the CommonMark link converted to anchor
+        assert!(
+            artifacts.pages[0]
+                .metadata_html
+                .contains(r#"<a href="https://spec.commonmark.org/">CommonMark</a>"#)
+        );
+    }
+
+    #[test]
+    fn flat_alias_and_part_of_links() {
+        // Flat multi-page: no pages/ prefix
+        let fs = TestFs::new(&[
+            (
+                "root.md",
+                r#"---
+title: Root Title
+author: A
+created: 2025-08-25T10:00:00Z
+updated: 2025-08-25T10:00:00Z
+visibility: public
+format: "[CommonMark](https://spec.commonmark.org/)"
+this_file_is_root_index: true
+contents:
+  - "[First Child](child-one.md)"
+  - "[Second Child](child-two)"
+---
+Root body
+"#,
+            ),
+            (
+                "child-one.md",
+                r#"---
+title: First Child
+author: A
+created: 2025-08-25T10:01:00Z
+updated: 2025-08-25T10:01:00Z
+visibility: public
+format: "[CommonMark](https://spec.commonmark.org/)"
+part_of:
+  - "[Root Alias](root.md)"
+---
+Child one body referencing [Second](child-two.md) and [Root](root.md).
+"#,
+            ),
+            (
+                "child-two.md",
+                r#"---
+title: Second Child
+author: A
+created: 2025-08-25T10:02:00Z
+updated: 2025-08-25T10:02:00Z
+visibility: public
+format: "[CommonMark](https://spec.commonmark.org/)"
+part_of: "[Root Alias](root.md)"
+---
+Child two body referencing [First](child-one.md) and [Root](root.md).
+"#,
+            ),
+        ]);
+        let artifacts = build_site(
+            "root.md",
+            CoreBuildOptions {
+                rewrite_links: true,
+                flat: true,
+                ..Default::default()
+            },
+            &fs,
+        )
+        .expect("build ok");
+        assert_eq!(artifacts.pages.len(), 3);
+        let root = artifacts.pages.iter().find(|p| p.is_root_index).unwrap();
+        // Root metadata should show alias labels, not slugs
+        assert!(
+            root.metadata_html
+                .contains(r#"<a href="child-one.html">First Child</a>"#),
+            "Flat root should link to child-one.html with alias label: got {}",
+            root.metadata_html
+        );
+        assert!(
+            root.metadata_html
+                .contains(r#"<a href="child-two.html">Second Child</a>"#),
+            "Flat root should link to child-two.html with alias label: got {}",
+            root.metadata_html
+        );
+        // Child part_of alias link
+        let child_one = artifacts
+            .pages
+            .iter()
+            .find(|p| p.title == "First Child")
+            .unwrap();
+        assert!(
+            child_one
+                .metadata_html
+                .contains(r#"<a href="index.html">Root Alias</a>"#),
+            "Flat child_one part_of should show alias 'Root Alias': {}",
+            child_one.metadata_html
+        );
+        // Body link rewrite (flat)
+        assert!(child_one.html.contains(r#"href="child-two.html""#));
+    }
+
+    #[test]
+    fn nested_alias_and_layout_links() {
+        // Nested multi-page layout (flat=false)
+        let fs = TestFs::new(&[
+            (
+                "index.md",
+                r#"---
+title: Root
+author: P
+created: 2025-08-25T10:00:00Z
+updated: 2025-08-25T10:00:00Z
+visibility: public
+format: "[CommonMark](https://spec.commonmark.org/)"
+this_file_is_root_index: true
+contents:
+  - "[Alpha Entry](alpha.md)"
+  - "[Beta Entry](beta)"
+---
+Root body linking to [Alpha](alpha.md) and [Beta](beta.md).
+"#,
+            ),
+            (
+                "alpha.md",
+                r#"---
+title: Alpha Entry
+author: P
+created: 2025-08-25T10:01:00Z
+updated: 2025-08-25T10:01:00Z
+visibility: public
+format: "[CommonMark](https://spec.commonmark.org/)"
+part_of: "[Root Label](index.md)"
+---
+Alpha body linking back to [Root](index.md) and to [Beta](beta.md).
+"#,
+            ),
+            (
+                "beta.md",
+                r#"---
+title: Beta Entry
+author: P
+created: 2025-08-25T10:02:00Z
+updated: 2025-08-25T10:02:00Z
+visibility: public
+format: "[CommonMark](https://spec.commonmark.org/)"
+part_of:
+  - "[Root Label](index.md)"
+  - "[Alpha Alias](alpha.md)"
+---
+Beta body linking back to [Root](index.md) and to [Alpha](alpha.md).
+"#,
+            ),
+        ]);
+        let artifacts = build_site(
+            "index.md",
+            CoreBuildOptions {
+                rewrite_links: true,
+                flat: false,
+                ..Default::default()
+            },
+            &fs,
+        )
+        .expect("build ok");
+        assert_eq!(artifacts.pages.len(), 3);
+        let root = artifacts.pages.iter().find(|p| p.is_root_index).unwrap();
+        // Root metadata: pages/ prefix for children
+        assert!(
+            root.metadata_html
+                .contains(r#"<a href="pages/alpha.html">Alpha Entry</a>"#),
+            "Nested root should have pages/alpha.html alias link (slug from filename stem): {}",
+            root.metadata_html
+        );
+        assert!(
+            root.metadata_html
+                .contains(r#"<a href="pages/beta.html">Beta Entry</a>"#),
+            "Nested root should have pages/beta.html alias link (slug from filename stem): {}",
+            root.metadata_html
+        );
+        // Alpha metadata: part_of root ../index OR index? (Since alpha is nested; root link should be ../index.html in body, index.html in metadata alias depends on design)
+        let alpha = artifacts
+            .pages
+            .iter()
+            .find(|p| p.title == "Alpha Entry")
+            .unwrap();
+        assert!(
+            alpha
+                .metadata_html
+                .contains(r#"<a href="../index.html">Root Label</a>"#),
+            "Nested alpha part_of should use alias 'Root Label': {}",
+            alpha.metadata_html
+        );
+        // Alpha body link to Beta sibling (nested) should be beta.html (slug from filename stem)
+        assert!(alpha.html.contains(r#"href="beta.html""#));
+        // Root body link to Alpha should have pages/ prefix (filename-stem slug)
+        assert!(root.html.contains(r#"href="pages/alpha.html""#));
+        // Beta metadata part_of includes Root Label and Alpha Alias
+        let beta = artifacts
+            .pages
+            .iter()
+            .find(|p| p.title == "Beta Entry")
+            .unwrap();
+        assert!(
+            beta.metadata_html
+                .contains(r#"<a href="../index.html">Root Label</a>"#),
+            "Nested beta part_of should use alias 'Root Label': {}",
+            beta.metadata_html
+        );
+        assert!(
+            beta.metadata_html
+                .contains(r#"<a href="alpha.html">Alpha Alias</a>"#),
+            "Nested beta part_of should link to its sibling alpha by alias (filename stem slug): {}",
+            beta.metadata_html
+        );
+        // Beta body link back to Root
+        assert!(beta.html.contains(r#"href="../index.html""#));
+    }
+
+    #[test]
+    fn synthetic_vault_builds_nested() {
+        let vault = synth::generate_vault(&synth::SynthVaultOptions {
+            entries: 120,
+            entries_per_section: 50,
+            ..Default::default()
+        });
+        // root + 3 sections + 120 entries
+        assert_eq!(vault.len(), 124);
+        let files: Vec<(&str, &str)> = vault
+            .files
+            .iter()
+            .map(|(p, c)| (p.as_str(), c.as_str()))
+            .collect();
+        let fs = TestFs::new(&files);
+        let artifacts = build_site(
+            synth::SynthVault::ROOT,
+            CoreBuildOptions {
+                rewrite_links: true,
+                ..Default::default()
+            },
+            &fs,
+        )
+        .expect("build ok");
+        assert!(artifacts.multi_page);
+        assert_eq!(artifacts.pages.len(), 124);
+        assert!(
+            artifacts.warnings.is_empty(),
+            "synthetic vault should be warning-free: {:?}",
+            artifacts.warnings
+        );
+        let root = artifacts.pages.iter().find(|p| p.is_root_index).unwrap();
+        assert_hrefs_contains(
+            &root.metadata_html,
+            &[
+                "pages/section-001.html",
+                "pages/section-002.html",
+                "pages/section-003.html",
+            ],
+        );
+        let section = artifacts
+            .pages
+            .iter()
+            .find(|p| p.id == "section-002")
+            .unwrap();
+        assert_eq!(section.children.len(), 50);
+        assert_eq!(section.parents, vec!["index".to_string()]);
+    }
+
+    #[test]
+    fn streaming_emits_pages_in_order() {
+        let vault = synth::generate_vault(&synth::SynthVaultOptions {
+            entries: 10,
+            entries_per_section: 5,
+            ..Default::default()
+        });
+        let files: Vec<(&str, &str)> = vault
+            .files
+            .iter()
+            .map(|(p, c)| (p.as_str(), c.as_str()))
+            .collect();
+        let fs = TestFs::new(&files);
+        let mut seen: Vec<String> = Vec::new();
+        let summary = build_site_streaming(
+            synth::SynthVault::ROOT,
+            CoreBuildOptions {
+                rewrite_links: true,
+                ..Default::default()
+            },
+            &fs,
+            |site: &SiteShape, page: PageOutput| {
+                assert!(site.multi_page);
+                assert_eq!(site.page_count, 13);
+                seen.push(page.id);
+                Ok(())
+            },
+        )
+        .expect("build ok");
+        assert_eq!(summary.pages_emitted, 13);
+        assert_eq!(summary.root_slug.as_deref(), Some("index"));
+        // Breadth-first traversal: root, then sections, then entries
+        assert_eq!(&seen[..3], &["index", "section-001", "section-002"]);
+        assert_eq!(seen.last().map(String::as_str), Some("entry-00010"));
+    }
+
+    #[test]
+    fn large_vault_link_graph() {
+        // A single index with thousands of children used to be quadratic in link_graph.
+        let vault = synth::generate_vault(&synth::SynthVaultOptions {
+            entries: 3000,
+            entries_per_section: 3000,
+            link_density: 0.0,
+            paragraphs: 1,
+            ..Default::default()
+        });
+        let files: Vec<(&str, &str)> = vault
+            .files
+            .iter()
+            .map(|(p, c)| (p.as_str(), c.as_str()))
+            .collect();
+        let fs = TestFs::new(&files);
+        let artifacts = build_site(
+            synth::SynthVault::ROOT,
+            CoreBuildOptions {
+                rewrite_links: true,
+                flat: true,
+                ..Default::default()
+            },
+            &fs,
+        )
+        .expect("build ok");
+        assert_eq!(artifacts.pages.len(), 3002);
+        let section = artifacts
+            .pages
+            .iter()
+            .find(|p| p.id == "section-001")
+            .unwrap();
+        assert_eq!(section.children.len(), 3000);
+        assert_eq!(section.children[2999], "entry-03000");
+        assert_hrefs_contains(
+            &section.metadata_html,
+            &["entry-00001.html", "entry-03000.html"],
+        );
+        assert!(section.metadata_html.contains(">Entry 3000</a>"));
+        let last = artifacts.pages.last().unwrap();
+        assert_eq!(last.parents, vec!["section-001".to_string()]);
+        assert!(
+            last.metadata_html
+                .contains(r#"<a href="section-001.html">Section 1</a>"#)
+        );
+    }
+
+    #[test]
+    fn oversized_body_skips_attachment_scan() {
+        let body = "See ![pic](missing.png) and [other](missing.pdf).\r\n".repeat(4);
+        let src = format!(
+            "---\r\ntitle: Big\r\nauthor: A\r\ncreated: 2025-08-25T10:00:00Z\r\nupdated: 2025-08-25T10:00:00Z\r\nvisibility: public\r\nformat: CommonMark\r\nreachable: here\r\n---\r\n{body}"
+        );
+        let fs = TestFs::new(&[("big.md", src.as_str())]);
+        let build = |limit| {
+            build_site(
+                "big.md",
+                CoreBuildOptions {
+                    rewrite_links: true,
+                    large_body_bytes: Some(limit),
+                    ..Default::default()
+                },
+                &fs,
+            )
+            .expect("build ok")
+        };
+
+        let small_limit = build(64);
+        assert_eq!(small_limit.pages[0].title, "Big");
+        assert!(
+            small_limit
+                .warnings
+                .iter()
+                .any(|w| w.message.contains("attachment scanning skipped")),
+            "{:?}",
+            small_limit.warnings
+        );
+        assert!(
+            !small_limit
+                .warnings
+                .iter()
+                .any(|w| w.message.starts_with("Attachment not found"))
+        );
+        assert!(small_limit.pages[0].html.contains(r#"src="missing.png""#));
+
+        let default_limit = build(DEFAULT_LARGE_BODY_BYTES);
+        assert_eq!(
+            default_limit
+                .warnings
+                .iter()
+                .filter(|w| w.message.starts_with("Attachment not found"))
+                .count(),
+            8
+        );
+    }
+
+    #[test]
+    fn site_files_match_layout() {
+        let vault = synth::generate_vault(&synth::SynthVaultOptions {
+            entries: 3,
+            entries_per_section: 3,
+            ..Default::default()
+        });
+        let files: Vec<(&str, &str)> = vault
+            .files
+            .iter()
+            .map(|(p, c)| (p.as_str(), c.as_str()))
+            .collect();
+        let fs = TestFs::new(&files);
+        let artifacts = build_site(
+            synth::SynthVault::ROOT,
+            CoreBuildOptions {
+                rewrite_links: true,
+                ..Default::default()
+            },
+            &fs,
+        )
+        .expect("build ok");
+        let site_files = site::site_files(
+            &artifacts,
+            &site::SiteOptions {
+                emit_json: true,
+                ..Default::default()
+            },
+        );
+        let paths: Vec<&str> = site_files.keys().map(String::as_str).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "css/style.css",
+                "diaryx-data.json",
+                "index.html",
+                "pages/entry-00001.html",
+                "pages/entry-00002.html",
+                "pages/entry-00003.html",
+                "pages/section-001.html",
+            ]
+        );
+        assert!(site_files["index.html"].contains(r#"href="css/style.css""#));
+        assert!(site_files["pages/entry-00001.html"].contains(r#"href="../css/style.css""#));
+        let model: serde_json::Value =
+            serde_json::from_str(&site_files["diaryx-data.json"]).unwrap();
+        assert_eq!(model["pages"].as_array().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn translations_link_both_ways() {
+        let fs = TestFs::new(&[
+            (
+                "index.md",
+                "---\ntitle: Journal\nvisibility: public\nlang: en\nthis_file_is_root_index: true\ncontents:\n  - \"[Walk](walk.md)\"\n  - \"[Paseo](paseo.md)\"\n---\n",
+            ),
+            (
+                "walk.md",
+                "---\ntitle: Walk\nvisibility: public\n---\nA walk.\n",
+            ),
+            (
+                "paseo.md",
+                "---\ntitle: Paseo\nvisibility: public\nlang: es\ntranslations: \"[English](walk.md)\"\n---\nUn paseo.\n",
+            ),
+        ]);
+        let artifacts = build_site("index.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        let page = |id: &str| artifacts.pages.iter().find(|p| p.id == id).unwrap();
+        let walk = page("walk");
+        assert_eq!(walk.lang.as_deref(), Some("en"));
+        assert_eq!(walk.translations.len(), 1);
+        assert_eq!(walk.translations[0].lang, "es");
+        assert_eq!(walk.translations[0].href, "paseo.html");
+        assert_eq!(page("paseo").translations[0].id, "walk");
+        assert!(page("index").translations.is_empty());
+
+        let html = site::wrap_full_html(walk, true, true);
+        assert!(html.starts_with(r#"<!doctype html><html lang="en">"#));
+        assert!(html.contains(r#"<link rel="alternate" hreflang="en" href="walk.html" />"#));
+        assert!(html.contains(r#"<link rel="alternate" hreflang="es" href="paseo.html" />"#));
+        assert!(html.contains(r#"<nav class="translations""#));
+    }
+
+    #[test]
+    fn audience_builds_add_the_pages_visible_to_it() {
+        let fs = TestFs::new(&[
+            (
+                "index.md",
+                "---\ntitle: Journal\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[Walk](walk.md)\"\n  - \"[Dinner](dinner.md)\"\n  - \"[Session](session.md)\"\n---\n",
+            ),
+            (
+                "walk.md",
+                "---\ntitle: Walk\nvisibility: public\n---\nA walk.\n",
+            ),
+            (
+                "dinner.md",
+                "---\ntitle: Dinner\nvisibility: [Family, friends]\n---\nDinner.\n",
+            ),
+            (
+                "session.md",
+                "---\ntitle: Session\nvisibility: therapist\n---\nNotes.\n",
+            ),
+        ]);
+        let ids = |audience: Option<&str>| {
+            let opts = CoreBuildOptions {
+                audience: audience.map(str::to_string),
+                ..Default::default()
+            };
+            let artifacts = build_site("index.md", opts, &fs).expect("build ok");
+            let mut ids: Vec<String> = artifacts.pages.into_iter().map(|p| p.id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids(None), ["index", "walk"]);
+        assert_eq!(ids(Some("family")), ["dinner", "index", "walk"]);
+        assert_eq!(ids(Some("therapist")), ["index", "session", "walk"]);
+    }
+
+    #[test]
+    fn permalinks_override_the_layout_path() {
+        let fs = TestFs::new(&[
+            (
+                "index.md",
+                "---\ntitle: Home\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[About](about.md)\"\n  - \"[Walk](walk.md)\"\n  - \"[Copy](copy.md)\"\n  - \"[Escape](escape.md)\"\n---\n",
+            ),
+            (
+                "about.md",
+                "---\ntitle: About\nvisibility: public\npermalink: /about/\n---\n[Home](index.md)\n",
+            ),
+            (
+                "walk.md",
+                "---\ntitle: Walk\nvisibility: public\noutput_path: notes/walk.htm\n---\nA walk.\n",
+            ),
+            (
+                "copy.md",
+                "---\ntitle: Copy\nvisibility: public\npermalink: about/index.html\n---\nSame place.\n",
+            ),
+            (
+                "escape.md",
+                "---\ntitle: Escape\nvisibility: public\npermalink: ../outside.html\n---\nNo.\n",
+            ),
+        ]);
+        let opts = CoreBuildOptions {
+            rewrite_links: true,
+            ..Default::default()
+        };
+        let artifacts = build_site("index.md", opts, &fs).expect("build ok");
+        let page = |id: &str| artifacts.pages.iter().find(|p| p.id == id).unwrap();
+        assert_eq!(page("about").path, "about/index.html");
+        assert_eq!(page("about").file_name, "index.html");
+        assert!(page("about").html.contains(r#"href="../index.html""#));
+        assert!(
+            page("index")
+                .metadata_html
+                .contains(r#"href="about/index.html""#)
+        );
+        assert_eq!(page("walk").path, "notes/walk.htm");
+
+        assert_eq!(page("copy").path, "pages/copy.html");
+        assert!(page("copy").warnings.iter().any(|w| {
+            w.message
+                .starts_with("Field 'permalink' about/index.html collides with page 'about'")
+        }));
+        assert_eq!(page("escape").path, "pages/escape.html");
+        assert!(page("escape").warnings.iter().any(|w| {
+            w.message
+                .starts_with("Field 'permalink' should be a site path")
+        }));
+    }
+
+    #[test]
+    fn drafts_and_scheduled_entries_are_held_back() {
+        let fs = TestFs::new(&[
+            (
+                "index.md",
+                "---\ntitle: Journal\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[Walk](walk.md)\"\n  - \"[Idea](idea.md)\"\n  - \"[Trip](trip.md)\"\n  - \"[Drafts](drafts.md)\"\n---\n",
+            ),
+            (
+                "drafts.md",
+                "---\ntitle: Drafts\nvisibility: public\ndraft: true\ncontents:\n  - \"[Secret](secret.md)\"\n  - \"[Walk](walk.md)\"\n---\n",
+            ),
+            (
+                "secret.md",
+                "---\ntitle: Secret\nvisibility: public\n---\nNot yet.\n",
+            ),
+            (
+                "walk.md",
+                "---\ntitle: Walk\nvisibility: public\npublished: 2025-05-01\n---\nA walk.\n",
+            ),
+            (
+                "idea.md",
+                "---\ntitle: Idea\nvisibility: public\ndraft: true\n---\nHalf a thought.\n",
+            ),
+            (
+                "trip.md",
+                "---\ntitle: Trip\nvisibility: public\npublished: 2025-07-01T09:00:00Z\n---\nLeaving soon.\n",
+            ),
+        ]);
+        let now = OffsetDateTime::parse(
+            "2025-06-15T12:00:00Z",
+            &time::format_description::well_known::Rfc3339,
+        )
+        .unwrap();
+        let build = |drafts: bool, future: bool| {
+            let opts = CoreBuildOptions {
+                now: Some(now),
+                drafts,
+                future,
+                ..Default::default()
+            };
+            let mut pages = Vec::new();
+            let summary = build_site_streaming("index.md", opts, &fs, |_: &SiteShape, page| {
+                pages.push(page);
+                Ok(())
+            })
+            .expect("build ok");
+            (pages, summary.held_back)
+        };
+
+        let (pages, held_back) = build(false, false);
+        let ids: Vec<&str> = pages.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["index", "walk"]);
+        assert_eq!(pages[0].children, ["walk"]);
+        let held: Vec<(&str, Option<&str>)> = held_back
+            .iter()
+            .map(|h| (h.title.as_str(), h.published.as_deref()))
+            .collect();
+        assert_eq!(
+            held,
+            [
+                ("Idea", None),
+                ("Trip", Some("2025-07-01T09:00:00Z")),
+                ("Drafts", None)
+            ]
+        );
+        // `secret` is reachable only through the held-back `drafts`; `walk` is also linked
+        // from the root.
+        assert!(pages.iter().all(|p| !p.html.contains("Not yet")));
+
+        let (pages, held_back) = build(true, true);
+        assert_eq!(pages.len(), 6);
+        assert!(held_back.is_empty());
+
+        let everything =
+            build_site("index.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        assert_eq!(everything.pages.len(), 6);
+    }
+
+    #[test]
+    fn layout_frontmatter_selects_shell() {
+        let fs = TestFs::new(&[
+            (
+                "index.md",
+                "---\ntitle: Home\nvisibility: public\nthis_file_is_root_index: true\nlayout: index\ncontents:\n  - \"[Shot](shot.md)\"\n  - \"[Odd](odd.md)\"\n---\n",
+            ),
+            (
+                "shot.md",
+                "---\ntitle: Shot\nvisibility: public\ntemplate: Photo\n---\n![x](x.png)\n",
+            ),
+            (
+                "odd.md",
+                "---\ntitle: Odd\nvisibility: public\nlayout: poster\n---\nBody\n",
+            ),
+        ]);
+        let artifacts = build_site("index.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        let page = |id: &str| artifacts.pages.iter().find(|p| p.id == id).unwrap();
+        assert_eq!(page("index").layout, "index");
+        assert_eq!(page("shot").layout, "photo");
+        assert_eq!(page("odd").layout, "default");
+        assert!(
+            page("odd")
+                .warnings
+                .iter()
+                .any(|w| w.message.contains("Unknown layout 'poster'"))
+        );
+
+        let photo = site::wrap_full_html(page("shot"), true, true);
+        assert!(photo.contains(
+            r#"<body class="layout-photo"><main class="content"><h1 class="page-title">Shot</h1>"#
+        ));
+        assert!(photo.contains(r#"<details class="page-details">"#));
+        let plain = site::wrap_full_html(page("odd"), true, true);
+        assert!(plain.contains(r#"</head><body><ul class="metadata">"#));
+    }
+
+    #[test]
+    fn html_pages_join_the_graph() {
+        let fs = TestFs::new(&[
+            (
+                "index.md",
+                "---\ntitle: Home\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[About](about.html)\"\n  - \"[Raw](raw.md)\"\n  - \"[Data](data.csv)\"\n---\nSee [about](about.html).\n",
+            ),
+            (
+                "about.html",
+                "<!DOCTYPE html><html><head><title>About me</title></head>\n<body><p>Back <a href=\"index.md\">home</a> or <a href=\"raw.md#x\">raw</a>.</p></body></html>\n",
+            ),
+            (
+                "raw.md",
+                "---\ntitle: Raw\nvisibility: public\nformat: \"[HTML](https://html.spec.whatwg.org/)\"\n---\n<div>*kept*</div>\n",
+            ),
+            ("data.csv", "a,b\n"),
+        ]);
+        let opts = CoreBuildOptions {
+            rewrite_links: true,
+            ..Default::default()
+        };
+        let artifacts = build_site("index.md", opts, &fs).expect("build ok");
+        let page = |id: &str| artifacts.pages.iter().find(|p| p.id == id).unwrap();
+        assert_eq!(page("index").children, vec!["about", "raw"]);
+        assert!(page("index").html.contains(r#"href="pages/about.html""#));
+
+        let about = page("about");
+        assert_eq!(about.title, "About me");
+        assert_eq!(about.parents, vec!["index"]);
+        assert!(about.warnings.is_empty(), "{:?}", about.warnings);
+        assert_eq!(
+            about.html,
+            r#"<p>Back <a href="../index.html">home</a> or <a href="raw.html#x">raw</a>.</p>"#
+        );
+        assert_eq!(page("raw").html, "<div>*kept*</div>\n");
+        assert!(artifacts.warnings.iter().any(|w| {
+            w.message
+                .contains("data.csv: only .md and .html files can be pages")
+        }));
+    }
+
+    #[test]
+    fn recent_entries_lists_newest_descendants() {
+        let fs = TestFs::new(&[
+            (
+                "index.md",
+                "---\ntitle: Home\nvisibility: public\nthis_file_is_root_index: true\nrecent_entries: 2\ncontents:\n  - \"[2024](y2024.md)\"\n  - \"[Old](old.md)\"\n---\nWelcome.\n",
+            ),
+            (
+                "y2024.md",
+                "---\ntitle: \"2024\"\nvisibility: public\ncreated: 2024-12-31\nrecent_entries: yes please\ncontents:\n  - \"[Mar](mar.md)\"\n  - \"[Jun](jun.md)\"\n---\n",
+            ),
+            (
+                "mar.md",
+                "---\ntitle: March\nvisibility: public\ncreated: 2024-03-02T09:00:00Z\n---\n# Heading\n\nA **walk** by the [river](river.md).\n",
+            ),
+            (
+                "jun.md",
+                "---\ntitle: June\nvisibility: public\ncreated: 2024-06-10\n---\n```\ncode\n```\nSummer & heat.\n",
+            ),
+            (
+                "old.md",
+                "---\ntitle: Old\nvisibility: public\ncreated: 2020-01-01\n---\nOld.\n",
+            ),
+        ]);
+        let artifacts = build_site("index.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        let page = |id: &str| artifacts.pages.iter().find(|p| p.id == id).unwrap();
+        let home = &page("index").html;
+        assert!(home.contains(
+            r#"<li><a href="pages/jun.html">June</a> <time datetime="2024-06-10">June 10, 2024</time><p class="excerpt">Summer &amp; heat.</p></li>"#
+        ));
+        assert!(home.contains(r#"<p class="excerpt">A walk by the river.</p>"#));
+        assert!(!home.contains("Old"), "limited to the two newest: {home}");
+        assert!(!home.contains(">2024</a>"), "index pages are not entries");
+
+        let year = page("y2024");
+        assert!(!year.html.contains("recent-entries"));
+        assert!(year.warnings.iter().any(|w| {
+            w.message
+                .contains("'recent_entries' should be a positive number or true")
+        }));
+    }
+
+    #[test]
+    fn callouts_render_as_asides() {
+        let fs = TestFs::new(&[(
+            "entry.md",
+            "---\ntitle: Notes\nvisibility: public\n---\n> [!Caution] Mind *the* gap\n> Step back.\n\n> [!tip]-\n> Hidden\n>\n> > [!NOTE]\n> > Inner\n\n> Plain quote\n",
+        )]);
+        let artifacts = build_site("entry.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        let html = &artifacts.pages[0].html;
+        assert!(html.contains(
+            "<aside class=\"callout callout-warning\" data-callout=\"caution\"><p class=\"callout-title\">Mind <em>the</em> gap</p>\n<p>Step back.</p>\n</aside>"
+        ), "{html}");
+        assert!(html.contains(
+            "<details class=\"callout callout-tip\" data-callout=\"tip\"><summary class=\"callout-title\">Tip</summary>\n<p>Hidden</p>"
+        ));
+        assert!(html.contains(
+            "<aside class=\"callout callout-note\" data-callout=\"note\"><p class=\"callout-title\">Note</p>\n<p>Inner</p>\n</aside>\n</details>"
+        ), "{html}");
+        assert!(html.contains("<blockquote>\n<p>Plain quote</p>\n</blockquote>"));
+    }
+
+    #[test]
+    fn smart_punctuation_spares_code() {
+        let fs = TestFs::new(&[(
+            "entry.md",
+            "---\ntitle: \"Don't stop -- live\"\nvisibility: public\n---\n\"Wait...\" she said---it's the '90s. *\"Quoted\"* `a -- \"b\"`\n\n```\nx = \"--\"\n```\n",
+        )]);
+        let opts = CoreBuildOptions {
+            smart_punctuation: true,
+            ..Default::default()
+        };
+        let page = &build_site("entry.md", opts, &fs).expect("build ok").pages[0];
+        assert_eq!(page.title, "Don’t stop – live");
+        assert!(page.html.contains(
+            "<p>“Wait…” she said—it’s the ’90s. <em>“Quoted”</em> <code>a -- &quot;b&quot;</code></p>"
+        ), "{}", page.html);
+        assert!(page.html.contains("x = &quot;--&quot;"));
+
+        let plain = &build_site("entry.md", CoreBuildOptions::default(), &fs)
+            .unwrap()
+            .pages[0];
+        assert_eq!(plain.title, "Don't stop -- live");
+    }
+
+    #[test]
+    fn figures_caption_standalone_images() {
+        let fs = TestFs::new(&[(
+            "entry.md",
+            "---\ntitle: Photos\nvisibility: public\n---\n![Lake](https://example.com/lake.jpg)\n*Evening at the lake*\n\n![Hill](https://example.com/hill.jpg \"On the hill\")\n\n![Bare](https://example.com/bare.jpg)\n\n![Mixed](https://example.com/m.jpg)\n*a* and *b*\n",
+        )]);
+        let opts = CoreBuildOptions {
+            figures: true,
+            ..Default::default()
+        };
+        let html = &build_site("entry.md", opts, &fs).expect("build ok").pages[0].html;
+        assert!(html.contains(
+            r#"<figure><img src="https://example.com/lake.jpg" alt="Lake" /><figcaption>Evening at the lake</figcaption></figure>"#
+        ), "{html}");
+        assert!(
+            html.contains(r#"title="On the hill" /><figcaption>On the hill</figcaption></figure>"#)
+        );
+        assert!(html.contains(r#"<p><img src="https://example.com/bare.jpg" alt="Bare" /></p>"#));
+        assert!(html.contains(r#"<p><img src="https://example.com/m.jpg" alt="Mixed" />"#));
+        assert_eq!(html.matches("<figure>").count(), 2);
+    }
+
+    #[test]
+    fn pdf_links_become_previews() {
+        let fs = TestFs::new(&[(
+            "entry.md",
+            "---\ntitle: Docs\nvisibility: public\n---\n[Annual *report*](https://example.com/r.pdf)\n\n[Skip](https://example.com/s.pdf \"no-preview\")\n\nInline [pdf](https://example.com/i.pdf) stays.\n",
+        )]);
+        let build = |pdf_previews| {
+            let opts = CoreBuildOptions {
+                pdf_previews,
+                ..Default::default()
+            };
+            build_site("entry.md", opts, &fs).expect("build ok").pages[0]
+                .html
+                .clone()
+        };
+        let html = build(true);
+        assert!(html.contains(
+            r#"<figure class="pdf-preview"><iframe src="https://example.com/r.pdf" title="Annual report" loading="lazy"></iframe><figcaption><a href="https://example.com/r.pdf" download>Annual <em>report</em></a> (PDF)</figcaption></figure>"#
+        ), "{html}");
+        assert!(html.contains(r#"<p><a href="https://example.com/s.pdf">Skip</a></p>"#));
+        assert!(html.contains(r#"Inline <a href="https://example.com/i.pdf">pdf</a> stays."#));
+        assert!(!build(false).contains("pdf-preview"));
+    }
+
+    #[test]
+    fn attachment_plan_records_metadata() {
+        let photo = "x".repeat(42);
+        let fs = TestFs::new(&[
+            (
+                "root.md",
+                "---\ntitle: Root\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[C](child.md)\"\n---\n![a](photo.JPG) ![again](photo.JPG)\n",
+            ),
+            (
+                "child.md",
+                "---\ntitle: Child\nvisibility: public\n---\n![b](photo.JPG)\n",
+            ),
+            ("photo.JPG", photo.as_str()),
+        ]);
+        let artifacts = build_site("root.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        assert_eq!(artifacts.attachments.len(), 1);
+        let att = &artifacts.attachments[0];
+        assert_eq!(att.target, "assets/photo.JPG");
+        assert_eq!(att.size, Some(42));
+        assert_eq!(att.mime, "image/jpeg");
+        assert_eq!(att.sha256, None);
+        assert_eq!(att.pages, vec!["root", "child"]);
+        assert_eq!(mime_type("notes/archive.tar"), "application/octet-stream");
+    }
+
+    #[test]
+    fn excluded_files_are_skipped_with_warning() {
+        let fs = TestFs::new(&[
+            (
+                "vault/root.md",
+                "---\ntitle: Root\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[A](a.md)\"\n  - \"[T](templates/day.md)\"\n  - \"[D](sketch.excalidraw.md)\"\n---\n![img](private/pic.png)\n",
+            ),
+            ("vault/a.md", "---\ntitle: A\nvisibility: public\n---\nA\n"),
+            (
+                "vault/templates/day.md",
+                "---\ntitle: T\nvisibility: public\n---\nT\n",
+            ),
+            (
+                "vault/sketch.excalidraw.md",
+                "---\ntitle: D\nvisibility: public\n---\nD\n",
+            ),
+        ]);
+        let opts = CoreBuildOptions {
+            exclude: vec![
+                "templates/".into(),
+                "*.excalidraw.md".into(),
+                "private/**".into(),
+            ],
+            ..Default::default()
+        };
+        let artifacts = build_site("vault/root.md", opts, &fs).expect("build ok");
+        let ids: Vec<&str> = artifacts.pages.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["root", "a"]);
+        let excluded: Vec<&Warning> = artifacts
+            .warnings
+            .iter()
+            .filter(|w| w.message.starts_with("Excluded file referenced"))
+            .collect();
+        assert_eq!(excluded.len(), 3, "{:?}", artifacts.warnings);
+        assert!(artifacts.attachments.is_empty());
+
+        let bad = CoreBuildOptions {
+            exclude: vec!["[".into()],
+            ..Default::default()
+        };
+        assert!(build_site("vault/root.md", bad, &fs).is_err());
+    }
+
+    #[test]
+    fn date_and_tag_filters_prune_indexes() {
+        let entry = |created: &str, tags: &str| {
+            format!(
+                "---\ntitle: E\nvisibility: public\ncreated: {created}\ntags: [{tags}]\n---\nBody\n"
+            )
+        };
+        let (a, b, c, d) = (
+            entry("2024-06-01", "travel"),
+            entry("2025-03-10", "Travel, food"),
+            entry("2025-12-31T20:00:00Z", "food"),
+            entry("2026-01-01", "travel"),
+        );
+        let fs = TestFs::new(&[
+            (
+                "root.md",
+                "---\ntitle: Root\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[Old](old.md)\"\n  - \"[New](new.md)\"\n---\n",
+            ),
+            (
+                "old.md",
+                "---\ntitle: Old\nvisibility: public\ncontents:\n  - \"[A](a.md)\"\n---\n",
+            ),
+            (
+                "new.md",
+                "---\ntitle: New\nvisibility: public\ncontents:\n  - \"[B](b.md)\"\n  - \"[C](c.md)\"\n  - \"[D](d.md)\"\n---\n",
+            ),
+            ("a.md", &a),
+            ("b.md", &b),
+            ("c.md", &c),
+            ("d.md", &d),
+        ]);
+        let opts = CoreBuildOptions {
+            since: Some("2025-01-01".into()),
+            until: Some("2025-12-31".into()),
+            tags: vec!["#travel".into(), "food".into()],
+            ..Default::default()
+        };
+        let artifacts = build_site("root.md", opts, &fs).expect("build ok");
+        let page = |id: &str| artifacts.pages.iter().find(|p| p.id == id).unwrap();
+        let ids: Vec<&str> = artifacts.pages.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["root", "new", "b", "c"]);
+        assert_eq!(page("root").children, vec!["new"]);
+        assert_eq!(page("new").children, vec!["b", "c"]);
+
+        let opts = CoreBuildOptions {
+            tags: vec!["nothing".into()],
+            ..Default::default()
+        };
+        let artifacts = build_site("root.md", opts, &fs).expect("build ok");
+        assert_eq!(artifacts.pages.len(), 1);
+        assert!(
+            artifacts
+                .warnings
+                .iter()
+                .any(|w| w.message.contains("date / tag filter"))
+        );
+        let bad = CoreBuildOptions {
+            since: Some("last year".into()),
+            ..Default::default()
+        };
+        assert!(build_site("root.md", bad, &fs).is_err());
+    }
+
+    #[test]
+    fn as_root_builds_a_section_subtree() {
+        let fs = TestFs::new(&[
+            (
+                "root.md",
+                "---\ntitle: Root\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[Trips](trips.md)\"\n  - \"[Other](other.md)\"\n---\n",
+            ),
+            (
+                "trips.md",
+                "---\ntitle: Trips\nvisibility: public\npart_of: \"[Root](root.md)\"\ncontents:\n  - \"[Rome](rome.md)\"\n---\n",
+            ),
+            (
+                "rome.md",
+                "---\ntitle: Rome\nvisibility: public\npart_of: \"[Trips](trips.md)\"\n---\nCiao\n",
+            ),
+            ("other.md", "---\ntitle: Other\nvisibility: public\n---\n"),
+        ]);
+        // Without promotion a section index is a single page
+        let artifacts = build_site("trips.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        assert_eq!(artifacts.pages.len(), 1);
+
+        let opts = CoreBuildOptions {
+            as_root: true,
+            ..Default::default()
+        };
+        let artifacts = build_site("trips.md", opts, &fs).expect("build ok");
+        let ids: Vec<&str> = artifacts.pages.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["trips", "rome"]);
+        let trips = &artifacts.pages[0];
+        assert!(trips.is_root_index);
+        assert!(trips.parents.is_empty());
+        assert!(!trips.metadata_html.contains("root.md"));
+        assert!(artifacts.pages[1].metadata_html.contains("../index.html"));
+    }
+
+    #[test]
+    fn remote_contents_are_fetched_from_allowed_hosts() {
+        use remote::{RemoteFs, RemoteProvider};
+
+        struct Web(HashMap<&'static str, &'static str>);
+        impl RemoteProvider for Web {
+            fn fetch(&self, url: &str) -> Result<String> {
+                self.0
+                    .get(url)
+                    .map(|s| s.to_string())
+                    .ok_or_else(|| anyhow!("404"))
             }
         }
-        fn file_name(&self, path: &str) -> Option<String> {
-            Some(path.rsplit('/').next().unwrap_or(path).to_string())
-        }
+        let local = TestFs::new(&[(
+            "root.md",
+            "---\ntitle: Root\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[Garden](https://alice.example.org/garden/index.md)\"\n  - \"[Blocked](https://evil.test/x.md)\"\n---\n",
+        )]);
+        let web = Web(HashMap::from([
+            (
+                "https://alice.example.org/garden/index.md",
+                "---\ntitle: Garden\nvisibility: public\ncontents:\n  - \"[Roses](../plants/roses)\"\n---\nSee [roses](../plants/roses.md).\n",
+            ),
+            (
+                "https://alice.example.org/plants/roses.md",
+                "---\ntitle: Roses\nvisibility: public\n---\nRed.\n",
+            ),
+            ("https://evil.test/x.md", "---\ntitle: X\n---\n"),
+        ]));
+        let fs = RemoteFs::new(local, web, vec!["example.org".to_string()]);
+        let opts = CoreBuildOptions {
+            rewrite_links: true,
+            ..Default::default()
+        };
+        let artifacts = build_site("root.md", opts, &fs).expect("build ok");
+        let ids: Vec<&str> = artifacts.pages.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["root", "index", "roses"]);
+        assert_eq!(
+            artifacts.pages[2].source_path,
+            "https://alice.example.org/plants/roses.md"
+        );
+        assert!(artifacts.pages[1].html.contains("roses.html"));
+        let refused = fs.take_warnings();
+        assert_eq!(refused.len(), 1, "{refused:?}");
+        assert!(
+            refused[0].message.contains("'evil.test' is not allowed"),
+            "{refused:?}"
+        );
     }
 
-    // Helper to extract all hrefs from a snippet
-    fn hrefs(html: &str) -> Vec<String> {
-        let mut v = Vec::new();
-        let re = regex::Regex::new(r#"href="([^"]+)""#).unwrap();
-        for cap in re.captures_iter(html) {
-            v.push(cap[1].to_string());
-        }
-        v
+    #[test]
+    fn reachable_items_are_classified_and_checked() {
+        let fs = TestFs::new(&[
+            (
+                "root.md",
+                "---\ntitle: Root\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[Club](club.md)\"\nreachable:\n  - \"[Repo](https://example.com/repo)\"\n  - \"https:// broken\"\n  - \"[Club notes](club.md)\"\n  - gone/away.md\n  - book-club\n  - strangers\n---\n",
+            ),
+            (
+                "club.md",
+                "---\ntitle: Club\nvisibility: [public, book-club]\nreachable: friends\n---\n",
+            ),
+        ]);
+        let artifacts = build_site("root.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        let root = &artifacts.pages[0];
+        let kinds: Vec<(ReachKind, bool)> =
+            root.reachable.iter().map(|r| (r.kind, r.valid)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (ReachKind::Url, true),
+                (ReachKind::Url, false),
+                (ReachKind::Path, true),
+                (ReachKind::Path, false),
+                (ReachKind::Audience, true),
+                (ReachKind::Audience, false),
+            ]
+        );
+        assert_eq!(root.reachable[0].label.as_deref(), Some("Repo"));
+        let reach_warnings: Vec<&Warning> = root
+            .warnings
+            .iter()
+            .filter(|w| w.message.starts_with("reachable:"))
+            .collect();
+        assert_eq!(reach_warnings.len(), 3, "{reach_warnings:?}");
+        assert!(
+            root.metadata_html
+                .contains("<span class=\"reachable-audience\">book-club</span>")
+        );
+        assert!(
+            root.metadata_html
+                .contains("(2 URLs, 2 paths, 2 audiences; 3 unresolved)")
+        );
+        assert!(
+            !artifacts.pages[1]
+                .warnings
+                .iter()
+                .any(|w| w.message.starts_with("reachable:"))
+        );
+        let model = site::page_model(root);
+        assert_eq!(model["reachable"][0]["kind"], "url");
     }
 
-    // Helper: assert that every expected href appears at least once
-    fn assert_hrefs_contains(html: &str, expected: &[&str]) {
-        let list = hrefs(html);
-        for e in expected {
-            assert!(
-                list.iter().any(|h| h == e),
-                "Expected href '{}' not found in {:?}",
-                e,
-                list
-            );
-        }
+    #[test]
+    fn copying_licenses_render_and_inherit() {
+        let fs = TestFs::new(&[
+            (
+                "root.md",
+                "---\ntitle: Root\nvisibility: public\nthis_file_is_root_index: true\ncopying: CC-BY-4.0\ncontents:\n  - \"[Photos](photos.md)\"\n  - \"[Code](code.md)\"\n  - \"[Mine](mine.md)\"\n---\n",
+            ),
+            (
+                "photos.md",
+                "---\ntitle: Photos\nvisibility: public\ncopying: \"[CC BY-SA](https://creativecommons.org/licenses/by-sa/4.0/)\"\ncontents:\n  - \"[Shot](shot.md)\"\n---\n",
+            ),
+            ("shot.md", "---\ntitle: Shot\nvisibility: public\n---\n"),
+            (
+                "code.md",
+                "---\ntitle: Code\nvisibility: public\ncopying: mit\n---\n",
+            ),
+            (
+                "mine.md",
+                "---\ntitle: Mine\nvisibility: public\ncopying: All rights reserved\n---\n",
+            ),
+        ]);
+        let artifacts = build_site("root.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        let page = |id: &str| artifacts.pages.iter().find(|p| p.id == id).unwrap();
+        let license = |id: &str| page(id).license.clone().unwrap();
+
+        let root = license("root");
+        assert_eq!(root.spdx.as_deref(), Some("CC-BY-4.0"));
+        assert_eq!(
+            root.url.as_deref(),
+            Some("https://spdx.org/licenses/CC-BY-4.0.html")
+        );
+        assert!(root.name.is_some_and(|n| n.contains("Attribution")));
+        let shot = license("shot");
+        assert!(shot.inherited);
+        assert_eq!(shot.label, "CC BY-SA");
+        assert!(!license("mine").is_valid());
+        let copying_warnings: Vec<&Warning> = artifacts
+            .warnings
+            .iter()
+            .filter(|w| w.message.starts_with("copying:"))
+            .collect();
+        assert_eq!(copying_warnings.len(), 2, "{copying_warnings:?}");
+        assert!(copying_warnings[0].message.contains("did you mean 'MIT'"));
+
+        let html = site::wrap_full_html(page("shot"), true, false);
+        assert!(html.contains("<footer class=\"page-license\">Licensed under <a rel=\"license\" href=\"https://creativecommons.org/licenses/by-sa/4.0/\">CC BY-SA</a> · <a href=\"../licenses.html\">"));
+        let html = site::wrap_full_html(page("mine"), true, false);
+        assert!(html.contains("<footer class=\"page-license\">All rights reserved · "));
+
+        let files = site::site_files(&artifacts, &site::SiteOptions::default());
+        let overview = &files[site::LICENSES_PATH];
+        assert!(overview.contains("CC BY-SA</a>: 2 pages"));
+        assert!(
+            overview.contains("<a href=\"pages/shot.html\">Shot</a></td><td><a rel=\"license\"")
+        );
+        assert!(overview.contains("(inherited)"));
     }
 
     #[test]
-    fn smoke_single() {
+    fn authors_are_normalized_and_linked() {
+        let value: serde_yaml::Value = serde_yaml::from_str(
+            "- Ada Lovelace <ada@example.com>\n- \"[Grace Hopper](https://example.com/grace)\"\n- name: Alan Turing\n  url: https://example.com/alan\n- Charles Babbage\n- email: nameless@example.com\n",
+        )
+        .unwrap();
+        let authors = parse_authors(&value);
+        let names: Vec<&str> = authors.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Ada Lovelace",
+                "Grace Hopper",
+                "Alan Turing",
+                "Charles Babbage"
+            ]
+        );
+        assert_eq!(authors[0].email.as_deref(), Some("ada@example.com"));
+        assert_eq!(authors[0].href().as_deref(), Some("mailto:ada@example.com"));
+        assert_eq!(authors[1].url.as_deref(), Some("https://example.com/grace"));
+        assert_eq!(authors[3].href(), None);
+
+        let fs = TestFs::new(&[(
+            "entry.md",
+            "---\ntitle: Notes\nvisibility: public\nauthor:\n  name: Ada Lovelace\n  email: ada@example.com\n---\nBody\n",
+        )]);
+        let artifacts = build_site("entry.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        let page = &artifacts.pages[0];
+        assert!(
+            !artifacts
+                .warnings
+                .iter()
+                .any(|w| w.message.contains("Field 'author'"))
+        );
+        assert!(page.metadata_html.contains(
+            "<span class=\"byline\"><a href=\"mailto:ada@example.com\">Ada Lovelace</a></span>"
+        ));
+        let html = site::wrap_full_html(page, false, false);
+        assert!(html.contains("<meta name=\"author\" content=\"Ada Lovelace\" />"));
+    }
+
+    #[test]
+    fn diaryx_toml_fields_are_validated_and_rendered() {
+        let fs = TestFs::new(&[
+            (
+                "diaryx.toml",
+                "[fields.mood]\ntype = \"enum\"\nvalues = [\"calm\", \"busy\"]\nrequired = true\n\n[fields.related]\ntype = \"link-list\"\nlabel = \"Related\"\n\n[fields.rating]\ntype = \"number\"\n",
+            ),
+            (
+                "root.md",
+                "---\ntitle: Root\nvisibility: public\nthis_file_is_root_index: true\nmood: calm\nrelated:\n  - \"[Note](note.md)\"\ncontents:\n  - \"[Note](note.md)\"\n---\n",
+            ),
+            (
+                "note.md",
+                "---\ntitle: Note\nvisibility: public\nrating: high\n---\n",
+            ),
+        ]);
+        let opts = CoreBuildOptions {
+            rewrite_links: true,
+            ..Default::default()
+        };
+        let artifacts = build_site("root.md", opts, &fs).expect("build ok");
+        let schema_warnings: Vec<(&str, &str)> = artifacts
+            .warnings
+            .iter()
+            .filter(|w| w.message.contains("mood") || w.message.contains("rating"))
+            .map(|w| (w.code, w.message.as_str()))
+            .collect();
+        assert_eq!(
+            schema_warnings,
+            vec![
+                ("missing-field", "Missing required field: mood (note.md)"),
+                (
+                    "invalid-field",
+                    "Field 'rating' should be a number; got 'high' (note.md)"
+                ),
+            ]
+        );
+        let root = artifacts.pages.iter().find(|p| p.is_root_index).unwrap();
+        assert!(
+            root.metadata_html
+                .contains("<strong>mood:</strong> <span class=\"field-enum\">calm</span>")
+        );
+        assert!(
+            root.metadata_html
+                .contains("<strong>Related:</strong> <a href=\"pages/note.html\">Note</a>")
+        );
+
+        let json = schema::Schema::discover("root.md", &fs)
+            .unwrap()
+            .json_schema();
+        assert_eq!(
+            json["properties"]["mood"]["enum"],
+            serde_json::json!(["calm", "busy"])
+        );
+        assert_eq!(json["properties"]["related"]["title"], "Related");
+        let required = json["required"].as_array().unwrap();
+        assert!(required.contains(&"title".into()) && required.contains(&"mood".into()));
+
+        let err = schema::Schema::parse("[fields.tags]\ntype = \"string\"\n").unwrap_err();
+        assert!(err.to_string().contains("built-in"));
+        assert!(schema::Schema::parse("[fields.kind]\ntype = \"enum\"\n").is_err());
+    }
+
+    #[test]
+    fn env_variables_are_interpolated_in_author_and_string_fields() {
+        let fs = TestFs::new(&[
+            ("diaryx.toml", "[fields.contact]\ntype = \"string\"\n"),
+            (
+                "root.md",
+                "---\ntitle: Root\nauthor: \"Ada <${AUTHOR_EMAIL}>\"\nvisibility: public\ncontact: \"${PHONE} / $${literal}\"\nnote: \"${AUTHOR_EMAIL}\"\n---\n",
+            ),
+        ]);
+        let env = BTreeMap::from([("AUTHOR_EMAIL".to_string(), "ada@example.org".to_string())]);
+        let opts = CoreBuildOptions {
+            env: Some(env),
+            ..Default::default()
+        };
+        let artifacts = build_site("root.md", opts, &fs).expect("build ok");
+        let page = &artifacts.pages[0];
+        assert_eq!(page.frontmatter["author"], "Ada <ada@example.org>");
+        assert_eq!(page.frontmatter["contact"], " / ${literal}");
+        // Only selected fields are interpolated
+        assert_eq!(page.frontmatter["note"], "${AUTHOR_EMAIL}");
+        assert!(artifacts.warnings.contains(&Warning::new(
+            "unset-env",
+            "Environment variable PHONE is not set; 'contact' uses an empty value (root.md)"
+        )));
+
+        // Without an environment references are left as written
+        let artifacts = build_site("root.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        assert_eq!(
+            artifacts.pages[0].frontmatter["author"],
+            "Ada <${AUTHOR_EMAIL}>"
+        );
+    }
+
+    #[test]
+    fn numeric_versions_render_without_shape_mismatch() {
+        let fs = TestFs::new(&[(
+            "entry.md",
+            "---\ntitle: Notes\nvisibility: public\nversion: 2\ntags: [a]\n---\nBody\n",
+        )]);
+        let artifacts = build_site("entry.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        let page = &artifacts.pages[0];
+        assert!(
+            !artifacts
+                .warnings
+                .iter()
+                .any(|w| w.message.contains("shape mismatch")
+                    || w.message.contains("Field 'version'"))
+        );
+        assert_eq!(page.version.as_deref(), Some("2"));
+        assert!(
+            page.metadata_html
+                .contains("version:</strong> <span class=\"page-version\">2</span>")
+        );
+    }
+
+    #[test]
+    fn nested_indexes_expand_only_in_site_mode() {
+        let fs = TestFs::new(&[
+            (
+                "root.md",
+                "---\ntitle: Root\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[Year](year.md)\"\n---\n",
+            ),
+            (
+                "year.md",
+                "---\ntitle: Year\nvisibility: public\ncontents:\n  - \"[Month](month.md)\"\n---\n",
+            ),
+            // An old vault root filed as a section: an ordinary index once nested
+            (
+                "month.md",
+                "---\ntitle: Month\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[Day](day.md)\"\n---\n",
+            ),
+            ("day.md", "---\ntitle: Day\nvisibility: public\n---\nHi\n"),
+        ]);
+        let roots = |artifacts: &BuildArtifacts| -> Vec<String> {
+            artifacts
+                .pages
+                .iter()
+                .filter(|p| p.is_root_index)
+                .map(|p| p.id.clone())
+                .collect()
+        };
+
+        let artifacts = build_site("root.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        let ids: Vec<&str> = artifacts.pages.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["root", "year", "month", "day"]);
+        assert_eq!(roots(&artifacts), vec!["root"]);
+
+        // A plain section index builds alone; promoted, its whole subtree expands
+        let artifacts = build_site("year.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        assert_eq!(artifacts.pages.len(), 1);
+        let opts = CoreBuildOptions {
+            as_root: true,
+            ..Default::default()
+        };
+        let artifacts = build_site("year.md", opts, &fs).expect("build ok");
+        assert_eq!(artifacts.pages.len(), 3);
+        assert_eq!(roots(&artifacts), vec!["year"]);
+
+        // A nested file declaring the flag is a root when it is the entry
+        let artifacts = build_site("month.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        assert_eq!(artifacts.pages.len(), 2);
+        assert_eq!(roots(&artifacts), vec!["month"]);
+    }
+
+    #[test]
+    fn canonical_and_noindex_head_tags() {
         let fs = TestFs::new(&[(
             "entry.md",
             r#"---
-title: Test Doc
-author: Someone
-created: 2025-08-25T10:00:00Z
-updated: 2025-08-25T10:00:00Z
+title: Syndicated
 visibility: public
-format: "[CommonMark](https://spec.commonmark.org/)"
+canonical_url: "https://example.com/posts/a?x=1&y=2"
+noindex: true
 ---
-Hello **world**.
+Body.
 "#,
         )]);
-        let artifacts = build_site(
-            "entry.md",
-            CoreBuildOptions {
-                rewrite_links: true,
-                ..Default::default()
+        let artifacts = build_site("entry.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        let page = &artifacts.pages[0];
+        assert!(site::is_noindex(page));
+        let html = site::wrap_full_html(page, false, false);
+        assert!(html.contains(
+            r#"<link rel="canonical" href="https://example.com/posts/a?x=1&amp;y=2" />"#
+        ));
+        assert!(html.contains(r#"<meta name="robots" content="noindex" />"#));
+    }
+
+    #[test]
+    fn cached_rebuild_renders_only_changed_bodies() {
+        let vault = synth::generate_vault(&synth::SynthVaultOptions {
+            entries: 20,
+            entries_per_section: 10,
+            ..Default::default()
+        });
+        let mut files: Vec<(String, String)> = vault.files.clone();
+        let opts = || CoreBuildOptions {
+            rewrite_links: true,
+            ..Default::default()
+        };
+        let as_fs = |files: &[(String, String)]| {
+            let refs: Vec<(&str, &str)> = files
+                .iter()
+                .map(|(p, c)| (p.as_str(), c.as_str()))
+                .collect();
+            TestFs::new(&refs)
+        };
+        let mut cache = RenderCache::new();
+
+        build_site_cached(synth::SynthVault::ROOT, opts(), &as_fs(&files), &mut cache).unwrap();
+        assert_eq!(cache.stats().rendered, 23);
+        assert!(cache.stats().relinked);
+
+        let edited = files
+            .iter_mut()
+            .find(|(p, _)| p == "entry-00005.md")
+            .unwrap();
+        edited
+            .1
+            .push_str("\nEdited paragraph linking [entry 1](entry-00001.md).\n");
+        let fs = as_fs(&files);
+        let cached = build_site_cached(synth::SynthVault::ROOT, opts(), &fs, &mut cache).unwrap();
+        assert_eq!(
+            cache.stats(),
+            RenderCacheStats {
+                rendered: 1,
+                reused: 22,
+                parsed: 1,
+                relinked: false,
+            }
+        );
+
+        let fresh = build_site(synth::SynthVault::ROOT, opts(), &fs).unwrap();
+        assert_eq!(cached.pages.len(), fresh.pages.len());
+        for (a, b) in cached.pages.iter().zip(&fresh.pages) {
+            assert_eq!(a.html, b.html, "{}", a.source_path);
+            assert_eq!(a.metadata_html, b.metadata_html, "{}", a.source_path);
+        }
+        let edited_page = cached
+            .pages
+            .iter()
+            .find(|p| p.source_path == "entry-00005.md")
+            .unwrap();
+        assert!(edited_page.html.contains("Edited paragraph"));
+
+        // Taking an entry out of its section's contents relinks, and matches a fresh build
+        let section = files
+            .iter_mut()
+            .find(|(p, c)| p != "entry-00005.md" && c.contains("(entry-00005.md)"))
+            .unwrap();
+        section.1 = section
+            .1
+            .lines()
+            .filter(|line| !line.contains("(entry-00005.md)"))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        let fs = as_fs(&files);
+        let cached = build_site_cached(synth::SynthVault::ROOT, opts(), &fs, &mut cache).unwrap();
+        let stats = cache.stats();
+        assert!(stats.relinked);
+        assert_eq!((stats.parsed, stats.rendered), (1, 0));
+        let fresh = build_site(synth::SynthVault::ROOT, opts(), &fs).unwrap();
+        assert_eq!(cached.warnings, fresh.warnings);
+        for (a, b) in cached.pages.iter().zip(&fresh.pages) {
+            assert_eq!(
+                (&a.children, &a.parents, &a.warnings),
+                (&b.children, &b.parents, &b.warnings),
+                "{}",
+                a.source_path
+            );
+            assert_eq!(a.html, b.html, "{}", a.source_path);
+        }
+    }
+
+    #[test]
+    fn observer_reports_phases_pages_and_warnings() {
+        let fs = TestFs::new(&[
+            (
+                "root.md",
+                "---\ntitle: Root\nthis_file_is_root_index: true\nvisibility: public\ncontents:\n  - \"[A](a.md)\"\n  - \"[B](b.md)\"\n---\nRoot body\n",
+            ),
+            ("a.md", "---\ntitle: A\nvisibility: public\n---\nA body\n"),
+            ("b.md", "---\ntitle: B\nvisibility: public\n---\nB body\n"),
+        ]);
+        let mut phases = Vec::new();
+        let mut pages = Vec::new();
+        let mut warnings = 0usize;
+        let artifacts = build_site_observed(
+            "root.md",
+            CoreBuildOptions::default(),
+            &fs,
+            None,
+            |e: &BuildEvent<'_>| {
+                match e {
+                    BuildEvent::Phase { phase } => phases.push(*phase),
+                    BuildEvent::Page { done, total, .. } => pages.push((*done, *total)),
+                    BuildEvent::Warning { .. } => warnings += 1,
+                }
+                Ok(())
             },
+        )
+        .unwrap();
+        assert_eq!(
+            phases,
+            vec![BuildPhase::Collect, BuildPhase::Link, BuildPhase::Render]
+        );
+        assert_eq!(pages, vec![(1, 3), (2, 3), (3, 3)]);
+        assert!(warnings > 0);
+        assert_eq!(warnings, artifacts.warnings.len());
+
+        let err = build_site_observed(
+            "root.md",
+            CoreBuildOptions::default(),
             &fs,
+            None,
+            |e: &BuildEvent<'_>| match e {
+                BuildEvent::Page { done: 2, .. } => Err(anyhow!("cancelled")),
+                _ => Ok(()),
+            },
         )
-        .expect("build ok");
-        assert_eq!(artifacts.pages.len(), 1);
-        assert!(artifacts.pages[0].html.contains("<strong>world</strong>"));
-        assert_eq!(artifacts.pages[0].file_name, "index.html");
-        // metadata should contain the CommonMark link converted to anchor
-        assert!(
-            artifacts.pages[0]
-                .metadata_html
-                .contains(r#"<a href="https://spec.commonmark.org/">CommonMark</a>"#)
+        .unwrap_err();
+        assert_eq!(err.to_string(), "cancelled");
+    }
+
+    #[test]
+    fn memfs_normalizes_paths_and_folds_case() {
+        use memfs::{InMemoryFs, normalize_path};
+        assert_eq!(normalize_path("./a.md"), "a.md");
+        assert_eq!(normalize_path("notes/../a.md"), "a.md");
+        assert_eq!(normalize_path("notes\\.\\sub//b.md"), "notes/sub/b.md");
+        assert_eq!(normalize_path("../outside.md"), "../outside.md");
+        assert_eq!(normalize_path("/x/../../y.md"), "/y.md");
+
+        let files: HashMap<String, String> = [
+            (
+                "./vault/root.md",
+                "---\ntitle: Root\nthis_file_is_root_index: true\nvisibility: public\ncontents:\n  - \"[A](notes/../a.md)\"\n  - \"[B](./B.md)\"\n---\nRoot\n",
+            ),
+            ("vault/a.md", "---\ntitle: A\nvisibility: public\n---\nA\n"),
+            ("vault\\b.md", "---\ntitle: B\nvisibility: public\n---\nB\n"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let mut fs = InMemoryFs::new(files);
+        let artifacts = build_site("vault/root.md", CoreBuildOptions::default(), &fs).unwrap();
+        let mut sources: Vec<&str> = artifacts
+            .pages
+            .iter()
+            .map(|p| p.source_path.as_str())
+            .collect();
+        sources.sort();
+        assert_eq!(sources, vec!["vault/a.md", "vault/b.md", "vault/root.md"]);
+        assert_eq!(fs.canonical_display("VAULT/./B.md"), "vault/b.md");
+
+        // A second file differing only by case makes the folded lookup ambiguous.
+        fs.insert("vault/B.md", "---\ntitle: B2\n---\n".into());
+        assert!(fs.exists("vault/B.md") && fs.exists("vault/b.md"));
+        assert!(!fs.exists("VAULT/b.md"));
+        assert!(fs.remove("vault/b.md"));
+        assert!(fs.exists("VAULT/b.md"));
+
+        // Binary attachments are planned with their size but cannot be read as pages.
+        let png = vec![0x89, b'P', b'N', b'G', 0xff];
+        fs.insert(
+            "vault/a.md",
+            "---\ntitle: A\nvisibility: public\n---\n![x](img/x.png)\n".into(),
+        );
+        fs.insert_bytes("vault/img/x.png", png.clone());
+        assert!(fs.read_to_string("vault/img/x.png").is_err());
+        assert_eq!(fs.read("vault/IMG/x.png"), Some(png.as_slice()));
+        let artifacts = build_site("vault/root.md", CoreBuildOptions::default(), &fs).unwrap();
+        let attachment = &artifacts.attachments[0];
+        assert_eq!(attachment.source, "vault/img/x.png");
+        assert_eq!(attachment.size, Some(5));
+        assert_eq!(attachment.mime, "image/png");
+    }
+
+    #[test]
+    fn clean_urls_move_pages_into_folders_and_rebase_links() {
+        assert_eq!(
+            site::clean_page_path("pages/walk.html"),
+            "pages/walk/index.html"
+        );
+        assert_eq!(site::clean_page_path("index.html"), "index.html");
+
+        let html = r##"<link href="../css/style.css" /><a href="../index.html">Home</a> <a href="beta.html#top">Beta</a> <img src="../assets/a%20b.png" /> <a href="https://example.com/x.html">x</a> <a href="#note">n</a>"##;
+        assert_eq!(
+            site::rewrite_clean_urls(html, "pages/walk.html"),
+            r##"<link href="../../css/style.css" /><a href="../../">Home</a> <a href="../beta/#top">Beta</a> <img src="../../assets/a%20b.png" /> <a href="https://example.com/x.html">x</a> <a href="#note">n</a>"##
+        );
+        assert_eq!(
+            site::rewrite_clean_urls(
+                r#"<a href="pages/walk.html">W</a><a href="index.html">H</a>"#,
+                "index.html"
+            ),
+            r#"<a href="pages/walk/">W</a><a href="./">H</a>"#
         );
     }
 
     #[test]
-    fn flat_alias_and_part_of_links() {
-        // Flat multi-page: no pages/ prefix
+    fn render_document_previews_a_single_file() {
+        let doc = render_document(
+            "notes/Morning Walk.md",
+            "---\ntitle: Walk\nvisibility: [public, family]\ncontents:\n  - \"[A](a.md)\"\n---\n> [!tip] Bring water\n\nSee [a](a.md) ![map](map.png)\n".to_string(),
+        )
+        .expect("render ok");
+        assert_eq!(doc.slug, "morning-walk");
+        assert_eq!(doc.title, "Walk");
+        assert_eq!(doc.visibility, ["public", "family"]);
+        assert_eq!(doc.frontmatter["contents"][0].as_str(), Some("[A](a.md)"));
+        assert!(doc.html.contains("callout-tip"));
+        assert!(doc.html.contains(r#"<a href="a.md">a</a>"#));
+        assert!(doc.html.contains(r#"src="map.png""#));
+        assert!(doc.warnings.iter().any(|w| w.message.contains("created")));
+
+        let html = render_document(
+            "page.md",
+            "---\nformat: html\n---\n<html><title>Hi</title><body><p>x</p></body></html>"
+                .to_string(),
+        )
+        .expect("render ok");
+        assert_eq!(html.title, "Hi");
+        assert_eq!(html.html, "<p>x</p>");
+    }
+
+    #[test]
+    fn output_layouts_place_pages_and_link_between_them() {
         let fs = TestFs::new(&[
             (
-                "root.md",
-                r#"---
-title: Root Title
-author: A
-created: 2025-08-25T10:00:00Z
-updated: 2025-08-25T10:00:00Z
-visibility: public
-format: "[CommonMark](https://spec.commonmark.org/)"
-this_file_is_root_index: true
-contents:
-  - "[First Child](child-one.md)"
-  - "[Second Child](child-two)"
----
-Root body
-"#,
+                "index.md",
+                "---\ntitle: Home\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[Rome](Trips/rome.md)\"\n  - \"[Notes](notes.md)\"\n---\n",
             ),
             (
-                "child-one.md",
-                r#"---
-title: First Child
-author: A
-created: 2025-08-25T10:01:00Z
-updated: 2025-08-25T10:01:00Z
-visibility: public
-format: "[CommonMark](https://spec.commonmark.org/)"
-part_of:
-  - "[Root Alias](root.md)"
----
-Child one body referencing [Second](child-two.md) and [Root](root.md).
-"#,
+                "Trips/rome.md",
+                "---\ntitle: Rome\nvisibility: public\ncreated: 2024-06-10T09:30:00Z\n---\nSee [notes](../notes.md) and ![map](map.png).\n",
             ),
+            ("Trips/map.png", "png"),
             (
-                "child-two.md",
-                r#"---
-title: Second Child
-author: A
-created: 2025-08-25T10:02:00Z
-updated: 2025-08-25T10:02:00Z
-visibility: public
-format: "[CommonMark](https://spec.commonmark.org/)"
-part_of: "[Root Alias](root.md)"
----
-Child two body referencing [First](child-one.md) and [Root](root.md).
-"#,
+                "notes.md",
+                "---\ntitle: Notes\nvisibility: public\n---\nBack to [Rome](Trips/rome.md).\n",
             ),
         ]);
-        let artifacts = build_site(
-            "root.md",
-            CoreBuildOptions {
+        let build = |layout: OutputLayout| {
+            let opts = CoreBuildOptions {
+                layout,
                 rewrite_links: true,
-                flat: true,
-                ..Default::default()
-            },
-            &fs,
-        )
-        .expect("build ok");
-        assert_eq!(artifacts.pages.len(), 3);
-        let root = artifacts.pages.iter().find(|p| p.is_root_index).unwrap();
-        // Root metadata should show alias labels, not slugs
+                ..CoreBuildOptions::default()
+            };
+            build_site("index.md", opts, &fs).expect("build ok")
+        };
+        let page = |artifacts: &BuildArtifacts, id: &str| {
+            artifacts.pages.iter().find(|p| p.id == id).unwrap().clone()
+        };
+
+        let mirror = build(OutputLayout::Mirror);
+        let rome = page(&mirror, "rome");
+        assert_eq!(rome.path, "trips/rome.html");
+        assert_eq!(rome.file_name, "rome.html");
+        assert_eq!(page(&mirror, "notes").path, "notes.html");
+        assert_eq!(page(&mirror, "index").path, "index.html");
+        assert!(rome.html.contains(r#"href="../notes.html""#));
+        assert!(rome.html.contains(r#"src="../assets/map.png""#));
         assert!(
-            root.metadata_html
-                .contains(r#"<a href="child-one.html">First Child</a>"#),
-            "Flat root should link to child-one.html with alias label: got {}",
-            root.metadata_html
+            page(&mirror, "notes")
+                .html
+                .contains(r#"href="trips/rome.html""#)
         );
         assert!(
-            root.metadata_html
-                .contains(r#"<a href="child-two.html">Second Child</a>"#),
-            "Flat root should link to child-two.html with alias label: got {}",
-            root.metadata_html
+            page(&mirror, "index")
+                .metadata_html
+                .contains(r#"href="trips/rome.html""#)
         );
-        // Child part_of alias link
-        let child_one = artifacts
-            .pages
-            .iter()
-            .find(|p| p.title == "First Child")
-            .unwrap();
+        let html = site::wrap_full_html(&rome, true, true);
+        assert!(html.contains(r#"href="../css/style.css""#));
+
+        let dated = build(OutputLayout::Dated);
+        let rome = page(&dated, "rome");
+        assert_eq!(rome.path, "2024/06/10/rome.html");
+        assert_eq!(page(&dated, "notes").path, "pages/notes.html");
+        assert!(rome.html.contains(r#"href="../../../pages/notes.html""#));
+        assert!(rome.html.contains(r#"src="../../../assets/map.png""#));
         assert!(
-            child_one
-                .metadata_html
-                .contains(r#"<a href="index.html">Root Alias</a>"#),
-            "Flat child_one part_of should show alias 'Root Alias': {}",
-            child_one.metadata_html
+            page(&dated, "notes")
+                .html
+                .contains(r#"href="../2024/06/10/rome.html""#)
         );
-        // Body link rewrite (flat)
-        assert!(child_one.html.contains(r#"href="child-two.html""#));
+
+        let flat = build(OutputLayout::Flat);
+        assert_eq!(page(&flat, "rome").path, "rome.html");
+        assert!(page(&flat, "rome").html.contains(r#"href="notes.html""#));
     }
 
     #[test]
-    fn nested_alias_and_layout_links() {
-        // Nested multi-page layout (flat=false)
+    fn infer_dates_fills_missing_timestamps_from_the_file() {
         let fs = TestFs::new(&[
             (
                 "index.md",
-                r#"---
-title: Root
-author: P
-created: 2025-08-25T10:00:00Z
-updated: 2025-08-25T10:00:00Z
-visibility: public
-format: "[CommonMark](https://spec.commonmark.org/)"
-this_file_is_root_index: true
-contents:
-  - "[Alpha Entry](alpha.md)"
-  - "[Beta Entry](beta)"
----
-Root body linking to [Alpha](alpha.md) and [Beta](beta.md).
-"#,
+                "---\ntitle: Home\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[Walk](walk.md)\"\n---\n",
             ),
             (
-                "alpha.md",
-                r#"---
-title: Alpha Entry
-author: P
-created: 2025-08-25T10:01:00Z
-updated: 2025-08-25T10:01:00Z
-visibility: public
-format: "[CommonMark](https://spec.commonmark.org/)"
-part_of: "[Root Label](index.md)"
----
-Alpha body linking back to [Root](index.md) and to [Beta](beta.md).
-"#,
+                "walk.md",
+                "---\ntitle: Walk\nvisibility: public\ncreated: 2024-01-02T03:04:05Z\n---\nBody\n",
+            ),
+        ]);
+        let build = |infer_dates: bool| {
+            let opts = CoreBuildOptions {
+                infer_dates,
+                ..CoreBuildOptions::default()
+            };
+            build_site("index.md", opts, &fs).expect("build ok")
+        };
+        let page = |artifacts: &BuildArtifacts, id: &str| {
+            artifacts.pages.iter().find(|p| p.id == id).unwrap().clone()
+        };
+
+        let off = build(false);
+        assert!(page(&off, "index").inferred.is_empty());
+        assert!(
+            off.warnings
+                .iter()
+                .any(|w| w.message.contains("Missing required field: created"))
+        );
+
+        let on = build(true);
+        let index = page(&on, "index");
+        assert_eq!(index.inferred, ["created", "updated"]);
+        assert_eq!(
+            index.frontmatter["created"].as_str(),
+            Some("2023-11-14T22:13:20Z")
+        );
+        assert_eq!(
+            index.frontmatter["updated"].as_str(),
+            Some("2024-03-09T16:00:00Z")
+        );
+        assert!(index.metadata_html.contains("(inferred)"));
+        assert!(!on.warnings.iter().any(|w| {
+            w.message.contains("Missing required field: created")
+                || w.message.contains("Missing required field: updated")
+        }));
+        let walk = page(&on, "walk");
+        assert_eq!(walk.inferred, ["updated"]);
+        assert_eq!(
+            walk.frontmatter["created"].as_str(),
+            Some("2024-01-02T03:04:05Z")
+        );
+    }
+
+    #[test]
+    fn date_shorthands_are_normalized_with_the_vault_defaults() {
+        let fs = TestFs::new(&[
+            (
+                "diaryx.toml",
+                "[dates]\ndefault_time = \"09:00\"\ntimezone = \"+02:00\"\n",
             ),
             (
-                "beta.md",
-                r#"---
-title: Beta Entry
-author: P
-created: 2025-08-25T10:02:00Z
-updated: 2025-08-25T10:02:00Z
-visibility: public
-format: "[CommonMark](https://spec.commonmark.org/)"
-part_of:
-  - "[Root Label](index.md)"
-  - "[Alpha Alias](alpha.md)"
----
-Beta body linking back to [Root](index.md) and to [Alpha](alpha.md).
-"#,
+                "index.md",
+                "---\ntitle: Home\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[Walk](walk.md)\"\n---\n",
+            ),
+            (
+                "walk.md",
+                "---\ntitle: Walk\nvisibility: public\ncreated: 2025-08-25\nupdated: 2025/8/26 14:30\n---\n",
             ),
         ]);
-        let artifacts = build_site(
-            "index.md",
-            CoreBuildOptions {
-                rewrite_links: true,
-                flat: false,
-                ..Default::default()
-            },
-            &fs,
-        )
-        .expect("build ok");
-        assert_eq!(artifacts.pages.len(), 3);
-        let root = artifacts.pages.iter().find(|p| p.is_root_index).unwrap();
-        // Root metadata: pages/ prefix for children
-        assert!(
-            root.metadata_html
-                .contains(r#"<a href="pages/alpha.html">Alpha Entry</a>"#),
-            "Nested root should have pages/alpha.html alias link (slug from filename stem): {}",
-            root.metadata_html
+        let opts = CoreBuildOptions {
+            layout: OutputLayout::Dated,
+            ..CoreBuildOptions::default()
+        };
+        let artifacts = build_site("index.md", opts, &fs).expect("build ok");
+        let walk = artifacts.pages.iter().find(|p| p.id == "walk").unwrap();
+        assert_eq!(
+            walk.frontmatter["created"].as_str(),
+            Some("2025-08-25T09:00:00+02:00")
         );
+        assert_eq!(
+            walk.frontmatter["updated"].as_str(),
+            Some("2025-08-26T14:30:00+02:00")
+        );
+        assert_eq!(walk.path, "2025/08/25/walk.html");
         assert!(
-            root.metadata_html
-                .contains(r#"<a href="pages/beta.html">Beta Entry</a>"#),
-            "Nested root should have pages/beta.html alias link (slug from filename stem): {}",
-            root.metadata_html
+            walk.metadata_html
+                .contains("August 25, 2025, 09:00am (UTC+2)")
         );
-        // Alpha metadata: part_of root ../index OR index? (Since alpha is nested; root link should be ../index.html in body, index.html in metadata alias depends on design)
-        let alpha = artifacts
-            .pages
+
+        assert_eq!(
+            parse_created("2025-08-25 14:30Z"),
+            parse_created("2025-08-25T14:30:00Z")
+        );
+        assert_eq!(humanize_timestamp("2025-08-25"), "August 25, 2025");
+        assert!(parse_created("25.08.2025").is_none());
+        assert!(schema::Schema::parse("[dates]\ntimezone = \"Mars\"\n").is_err());
+        assert!(schema::Schema::parse("[dates]\ndefault_time = \"9am\"\n").is_err());
+    }
+
+    #[test]
+    fn validate_graph_reports_coded_diagnostics_without_rendering() {
+        let fs = TestFs::new(&[
+            (
+                "index.md",
+                "---\ntitle: Home\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[Walk](walk.md)\"\n  - \"[Gone](gone.md)\"\n---\n",
+            ),
+            (
+                "walk.md",
+                "---\ntitle: Walk\nvisibility: public\n---\n![map](missing.png)\n",
+            ),
+        ]);
+        let report = validate_graph("index.md", false, &fs).expect("validate ok");
+        assert!(!report.valid);
+        assert_eq!(report.page_count, 2);
+        assert_eq!(report.warnings.len(), report.diagnostics.len());
+        let missing = report
+            .diagnostics
             .iter()
-            .find(|p| p.title == "Alpha Entry")
-            .unwrap();
+            .find(|d| {
+                d.message
+                    .starts_with("Missing required field: created (walk.md)")
+            })
+            .expect("missing created");
+        assert_eq!(missing.code, "missing-field");
+        assert_eq!(missing.path.as_deref(), Some("walk.md"));
         assert!(
-            alpha
-                .metadata_html
-                .contains(r#"<a href="../index.html">Root Label</a>"#),
-            "Nested alpha part_of should use alias 'Root Label': {}",
-            alpha.metadata_html
+            report
+                .diagnostics
+                .iter()
+                .any(|d| d.path.is_none() && d.message.contains("gone.md"))
         );
-        // Alpha body link to Beta sibling (nested) should be beta.html (slug from filename stem)
-        assert!(alpha.html.contains(r#"href="beta.html""#));
-        // Root body link to Alpha should have pages/ prefix (filename-stem slug)
-        assert!(root.html.contains(r#"href="pages/alpha.html""#));
-        // Beta metadata part_of includes Root Label and Alpha Alias
-        let beta = artifacts
-            .pages
-            .iter()
-            .find(|p| p.title == "Beta Entry")
-            .unwrap();
+        // Attachments are only checked when pages are rendered.
         assert!(
-            beta.metadata_html
-                .contains(r#"<a href="../index.html">Root Label</a>"#),
-            "Nested beta part_of should use alias 'Root Label': {}",
-            beta.metadata_html
+            !report
+                .diagnostics
+                .iter()
+                .any(|d| d.code == "missing-attachment")
         );
+        let full = validate_site("index.md", false, &fs).expect("validate ok");
         assert!(
-            beta.metadata_html
-                .contains(r#"<a href="../pages/alpha.html">Alpha Alias</a>"#),
-            "Nested beta part_of should link to alpha alias with ../pages/ prefix (filename stem slug): {}",
-            beta.metadata_html
+            full.diagnostics
+                .iter()
+                .any(|d| d.code == "missing-attachment" && d.path.as_deref() == Some("walk.md"))
         );
-        // Beta body link back to Root
-        assert!(beta.html.contains(r#"href="../index.html""#));
+    }
+
+    #[test]
+    fn one_sided_contents_and_part_of_links_are_mismatches() {
+        let fs = TestFs::new(&[
+            (
+                "index.md",
+                "---\ntitle: Home\nthis_file_is_root_index: true\ncontents:\n  - \"[Walk](walk.md)\"\n  - \"[Trips](sub.md)\"\n---\n",
+            ),
+            (
+                "walk.md",
+                "---\ntitle: Walk\npart_of: \"[Home](index.md)\"\n---\n",
+            ),
+            (
+                "sub.md",
+                "---\ntitle: Trips\npart_of: \"[Home](index.md)\"\ncontents:\n  - \"[Lake](lake.md)\"\n---\n",
+            ),
+            // Names the root index (as a bare path) instead of the index listing it.
+            ("lake.md", "---\ntitle: Lake\npart_of: index.md\n---\n"),
+        ]);
+        let mismatches = find_part_of_mismatches("index.md", &fs).unwrap();
+        assert_eq!(
+            mismatches,
+            vec![
+                PartOfMismatch {
+                    parent: "index.md".to_string(),
+                    child: "lake.md".to_string(),
+                    missing: "contents",
+                },
+                PartOfMismatch {
+                    parent: "sub.md".to_string(),
+                    child: "lake.md".to_string(),
+                    missing: "part_of",
+                },
+            ]
+        );
+        let report = validate_graph("index.md", true, &fs).expect("validate ok");
+        let found: Vec<_> = report
+            .diagnostics
+            .iter()
+            .filter(|d| d.code == "part-of-mismatch")
+            .collect();
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().all(|d| d.path.as_deref() == Some("lake.md")));
+        assert!(found.iter().any(|d| d.message
+            == "Contents/part_of mismatch: lake.md is in the contents of sub.md, but its part_of does not name it"));
+    }
+
+    #[test]
+    fn orphans_are_entries_the_root_index_never_reaches() {
+        let fs = TestFs::new(&[
+            (
+                "index.md",
+                "---\ntitle: Home\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[Walk](walk.md)\"\n---\n",
+            ),
+            (
+                "walk.md",
+                "---\ntitle: Walk\nvisibility: public\ncreated: 2025-08-25\npart_of: \"[Home](index.md)\"\n---\nSee [the lake](notes/lake.md).\n",
+            ),
+            (
+                "notes/lake.md",
+                "---\ntitle: Lake\nvisibility: public\ncreated: 2025-08-26\n---\n",
+            ),
+            ("README.md", "# About this vault\n"),
+            ("drafts/idea.md", "---\ntitle: Idea\n---\n"),
+        ]);
+        assert_eq!(
+            find_orphans("index.md", &["drafts/".to_string()], &fs).unwrap(),
+            vec!["notes/lake.md"]
+        );
+        assert_eq!(
+            find_orphans("index.md", &[], &fs).unwrap(),
+            vec!["drafts/idea.md", "notes/lake.md"]
+        );
+        // Only a root index has a traversal to compare against.
+        assert!(find_orphans("walk.md", &[], &fs).unwrap().is_empty());
+
+        let report = validate_graph("index.md", false, &fs).expect("validate ok");
+        let orphan: Vec<_> = report
+            .diagnostics
+            .iter()
+            .filter(|d| d.code == "orphan")
+            .collect();
+        assert_eq!(orphan.len(), 2);
+        assert!(orphan[1].message.ends_with("notes/lake.md"));
+        let built = build_site("index.md", CoreBuildOptions::default(), &fs).expect("build ok");
+        assert!(built.warnings.iter().any(
+            |w| w.message == "Orphan entry (not reachable from the root index): notes/lake.md"
+        ));
     }
 }
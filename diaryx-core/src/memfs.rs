@@ -0,0 +1,189 @@
+/*!
+ * In-memory [`FileProvider`] for hosts without a filesystem (WASM bindings, embedders, tests).
+ *
+ * Paths are virtual and always use `/`. Every path handed in (stored keys and lookups alike) is
+ * lexically normalized with [`normalize_path`], so `notes/../a.md`, `./a.md` and `a.md` all name
+ * the same file, matching what the CLI sees through the operating system.
+ *
+ * Lookups are exact first. When that misses, a case-insensitive match is used if exactly one
+ * stored file matches, which mirrors the case-insensitive filesystems (macOS, Windows) that vaults
+ * are usually authored on; ambiguous case-only differences are treated as missing. Paths handed
+ * back ([`FileProvider::join`], [`FileProvider::canonical_display`]) use the stored spelling.
+ *
+ * Files are stored as bytes: pages must be UTF-8 to be read as text, while attachments (images,
+ * PDFs) can be added with [`InMemoryFs::insert_bytes`] and read back with [`InMemoryFs::read`].
+ */
+
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+
+use crate::{FileMetadata, FileProvider};
+
+pub struct InMemoryFs {
+    files: HashMap<String, Vec<u8>>,
+    /// Lowercased path -> stored path; `None` when several stored paths differ only by case.
+    folded: HashMap<String, Option<String>>,
+}
+
+impl InMemoryFs {
+    pub fn new(files: HashMap<String, String>) -> Self {
+        let mut fs = Self {
+            files: HashMap::with_capacity(files.len()),
+            folded: HashMap::with_capacity(files.len()),
+        };
+        for (path, content) in files {
+            fs.insert(&path, content);
+        }
+        fs
+    }
+
+    /// Add or replace a file.
+    pub fn insert(&mut self, path: &str, content: String) {
+        self.insert_bytes(path, content.into_bytes());
+    }
+
+    /// Add or replace a file with binary contents.
+    pub fn insert_bytes(&mut self, path: &str, content: Vec<u8>) {
+        let key = normalize_path(path);
+        if self.files.insert(key.clone(), content).is_none() {
+            self.folded
+                .entry(key.to_lowercase())
+                .and_modify(|slot| *slot = None)
+                .or_insert(Some(key));
+        }
+    }
+
+    /// Remove a file; returns false when it was not present.
+    pub fn remove(&mut self, path: &str) -> bool {
+        let key = normalize_path(path);
+        if self.files.remove(&key).is_none() {
+            return false;
+        }
+        let lower = key.to_lowercase();
+        let mut remaining = self.files.keys().filter(|k| k.to_lowercase() == lower);
+        match (remaining.next(), remaining.next()) {
+            (None, _) => {
+                self.folded.remove(&lower);
+            }
+            (Some(only), None) => {
+                self.folded.insert(lower, Some(only.clone()));
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Stored key for `path`: exact match, else a unique case-insensitive match.
+    fn resolve(&self, path: &str) -> Option<String> {
+        let key = normalize_path(path);
+        if self.files.contains_key(&key) {
+            return Some(key);
+        }
+        self.folded.get(&key.to_lowercase()).cloned().flatten()
+    }
+
+    /// Contents of a file, text or binary.
+    pub fn read(&self, path: &str) -> Option<&[u8]> {
+        self.resolve(path)
+            .and_then(|key| self.files.get(&key))
+            .map(Vec::as_slice)
+    }
+}
+
+impl FileProvider for InMemoryFs {
+    fn read_to_string(&self, path: &str) -> Result<String> {
+        let bytes = self
+            .read(path)
+            .ok_or_else(|| anyhow!("File not found: {}", normalize_path(path)))?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| anyhow!("{} is not valid UTF-8", normalize_path(path)))
+    }
+    fn exists(&self, path: &str) -> bool {
+        self.resolve(path).is_some()
+    }
+    fn is_file(&self, path: &str) -> bool {
+        self.exists(path)
+    }
+    /// The stored key when the joined path names a file (so a case-folded hit reports the file's
+    /// own spelling), else the normalized path.
+    fn join(&self, parent: &str, rel: &str) -> String {
+        let path = if parent.is_empty() || rel.starts_with('/') {
+            normalize_path(rel)
+        } else {
+            normalize_path(&format!("{parent}/{rel}"))
+        };
+        self.resolve(&path).unwrap_or(path)
+    }
+    fn extension_lowercase(&self, path: &str) -> Option<String> {
+        let p = normalize_path(path);
+        p.rsplit('/')
+            .next()
+            .and_then(|f| f.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()))
+    }
+    fn parent(&self, path: &str) -> Option<String> {
+        let p = normalize_path(path);
+        match p.rsplit_once('/') {
+            Some(("", _)) => Some("/".to_string()),
+            Some((dir, _)) => Some(dir.to_string()),
+            None => Some(String::new()),
+        }
+    }
+    fn file_name(&self, path: &str) -> Option<String> {
+        let p = normalize_path(path);
+        Some(p.rsplit('/').next().unwrap_or(&p).to_string())
+    }
+    fn canonical_display(&self, path: &str) -> String {
+        self.resolve(path).unwrap_or_else(|| normalize_path(path))
+    }
+    fn metadata(&self, path: &str) -> Option<FileMetadata> {
+        self.read(path).map(|bytes| FileMetadata {
+            size: Some(bytes.len() as u64),
+            ..FileMetadata::default()
+        })
+    }
+    fn list_files(&self, dir: &str) -> Option<Vec<String>> {
+        let dir = normalize_path(dir);
+        Some(
+            self.files
+                .keys()
+                .filter_map(|key| {
+                    let rel = match dir.as_str() {
+                        "" => Some(key.as_str()),
+                        "/" => key.strip_prefix('/'),
+                        _ => key.strip_prefix(&dir)?.strip_prefix('/'),
+                    }?;
+                    (!rel.split('/').any(|part| part.starts_with('.'))).then(|| key.clone())
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Lexically normalize a virtual path: `\` becomes `/`, empty and `.` segments are dropped, and
+/// `..` removes the preceding segment. Leading `..` segments that cannot be collapsed are kept
+/// (they point outside the vault), and a leading `/` is preserved.
+pub fn normalize_path(path: &str) -> String {
+    let unified = path.replace('\\', "/");
+    let absolute = unified.starts_with('/');
+    let mut segments: Vec<&str> = Vec::new();
+    for seg in unified.split('/') {
+        match seg {
+            "" | "." => {}
+            ".." => match segments.last() {
+                Some(&last) if last != ".." => {
+                    segments.pop();
+                }
+                _ if absolute => {}
+                _ => segments.push(".."),
+            },
+            _ => segments.push(seg),
+        }
+    }
+    let joined = segments.join("/");
+    if absolute {
+        format!("/{joined}")
+    } else {
+        joined
+    }
+}
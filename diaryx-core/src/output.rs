@@ -0,0 +1,282 @@
+/*!
+ * Output layouts: where each page of a site is written, and therefore how pages link to each
+ * other and to the site's shared files (stylesheet, attachments, license overview).
+ *
+ * A build resolves every page's site-relative path once, into a [`SiteLayout`]; internal links,
+ * metadata links, translation and recent-entry links, and attachment references are all computed
+ * from it, so a layout only has to say where a page lives.
+ *
+ * Paths are relative to the site root and always use forward slashes. The root index is always
+ * `index.html`, as is the only page of a single-page build. Any other page can set its own path
+ * with a `permalink` (or `output_path`) field, whatever the layout.
+ */
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::site::LICENSES_PATH;
+use crate::{Doc, parse_created, slugify};
+
+/// Where the pages of a multi-page site are written (the root index is always `index.html`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputLayout {
+    /// `pages/<slug>.html`.
+    #[default]
+    Pages,
+    /// `<slug>.html`, next to the root index.
+    Flat,
+    /// The source tree, relative to the root index's folder: `trips/2024/rome.md` becomes
+    /// `trips/2024/rome.html` (folder names slugified). Files outside that folder go to `pages/`.
+    Mirror,
+    /// `<year>/<month>/<day>/<slug>.html` from `created`; undated pages go to `pages/`.
+    Dated,
+}
+
+impl OutputLayout {
+    /// Registered layout names.
+    pub const NAMES: [&'static str; 4] = ["pages", "flat", "mirror", "dated"];
+
+    pub fn from_name(name: &str) -> Option<OutputLayout> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "pages" => Some(OutputLayout::Pages),
+            "flat" => Some(OutputLayout::Flat),
+            "mirror" => Some(OutputLayout::Mirror),
+            "dated" => Some(OutputLayout::Dated),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputLayout::Pages => "pages",
+            OutputLayout::Flat => "flat",
+            OutputLayout::Mirror => "mirror",
+            OutputLayout::Dated => "dated",
+        }
+    }
+
+    /// Path of a page the layout cannot otherwise place (or that is not part of the build).
+    fn fallback_path(self, slug: &str) -> String {
+        match self {
+            OutputLayout::Flat => format!("{slug}.html"),
+            _ => format!("pages/{slug}.html"),
+        }
+    }
+}
+
+/// The output path of every page of one build, resolved up front.
+#[derive(Debug, Clone)]
+pub(crate) struct SiteLayout {
+    layout: OutputLayout,
+    multi_page: bool,
+    /// slug -> site-relative path.
+    paths: HashMap<String, String>,
+    /// slug -> why its `permalink` was not used.
+    warnings: HashMap<String, String>,
+}
+
+impl SiteLayout {
+    pub(crate) fn new(layout: OutputLayout, multi_page: bool, docs: &[Doc]) -> Self {
+        let root_dir = docs
+            .iter()
+            .find(|d| d.is_root_index)
+            .map(|d| d.abs_path.rsplit_once('/').map_or("", |(dir, _)| dir));
+        let paths = docs
+            .iter()
+            .map(|d| {
+                let path = if !multi_page || d.is_root_index {
+                    "index.html".to_string()
+                } else {
+                    match layout {
+                        OutputLayout::Mirror => root_dir.and_then(|dir| mirror_path(dir, d)),
+                        OutputLayout::Dated => dated_path(d),
+                        OutputLayout::Pages | OutputLayout::Flat => None,
+                    }
+                    .unwrap_or_else(|| layout.fallback_path(&d.id))
+                };
+                (d.id.clone(), path)
+            })
+            .collect();
+        let mut site = Self {
+            layout,
+            multi_page,
+            paths,
+            warnings: HashMap::new(),
+        };
+        if multi_page {
+            site.apply_permalinks(docs);
+        }
+        site
+    }
+
+    /// Move pages with a `permalink` (or `output_path`) field to that path, unless it is invalid
+    /// or taken by another page or a file of the site; those keep their layout path, with a
+    /// warning.
+    fn apply_permalinks(&mut self, docs: &[Doc]) {
+        let requested: Vec<(&Doc, &str, &serde_yaml::Value)> = docs
+            .iter()
+            .filter(|d| !d.is_root_index)
+            .filter_map(|d| {
+                ["permalink", "output_path"]
+                    .into_iter()
+                    .find_map(|key| Some((d, key, d.frontmatter.get(key)?)))
+            })
+            .collect();
+        let moved: HashSet<&str> = requested.iter().map(|(d, ..)| d.id.as_str()).collect();
+        let mut taken: HashMap<String, String> = docs
+            .iter()
+            .filter(|d| !moved.contains(d.id.as_str()))
+            .map(|d| (self.path(&d.id), format!("page '{}'", d.id)))
+            .collect();
+        taken.insert(
+            LICENSES_PATH.to_string(),
+            "the license overview".to_string(),
+        );
+        for (d, key, value) in requested {
+            let Some(path) = value.as_str().and_then(permalink_path) else {
+                self.warnings.insert(
+                    d.id.clone(),
+                    format!(
+                        "Field '{key}' should be a site path such as `about/` or `about/index.html`; using {} ({})",
+                        self.path(&d.id),
+                        d.abs_path
+                    ),
+                );
+                continue;
+            };
+            let reserved = ["assets/", "css/"].iter().any(|dir| path.starts_with(dir));
+            if let Some(owner) = taken
+                .get(&path)
+                .cloned()
+                .or_else(|| reserved.then(|| "a folder of the site's own files".to_string()))
+            {
+                self.warnings.insert(
+                    d.id.clone(),
+                    format!(
+                        "Field '{key}' {path} collides with {owner}; using {} ({})",
+                        self.path(&d.id),
+                        d.abs_path
+                    ),
+                );
+                taken.insert(self.path(&d.id), format!("page '{}'", d.id));
+                continue;
+            }
+            taken.insert(path.clone(), format!("page '{}'", d.id));
+            self.paths.insert(d.id.clone(), path);
+        }
+    }
+
+    /// Why the `permalink` of the page `slug` was not used, if it was not.
+    pub(crate) fn warning(&self, slug: &str) -> Option<&str> {
+        self.warnings.get(slug).map(String::as_str)
+    }
+
+    /// Site-relative output path of the page `slug`.
+    pub(crate) fn path(&self, slug: &str) -> String {
+        if !self.multi_page {
+            return "index.html".to_string();
+        }
+        self.paths
+            .get(slug)
+            .cloned()
+            .unwrap_or_else(|| self.layout.fallback_path(slug))
+    }
+
+    /// Link from the page `from` to the page `to`.
+    pub(crate) fn href(&self, from: &str, to: &str) -> String {
+        relative_url(&dir_segments(&self.path(from)), &self.path(to))
+    }
+
+    /// Prefix leading from the page `slug` back to the site root (`""` or `../`, repeated).
+    pub(crate) fn to_root(&self, slug: &str) -> String {
+        root_prefix(&self.path(slug))
+    }
+}
+
+/// Path of `doc` relative to the root index's folder (`root_dir`), folders slugified; `None` when
+/// it lives elsewhere.
+fn mirror_path(root_dir: &str, doc: &Doc) -> Option<String> {
+    let rel = if root_dir.is_empty() {
+        doc.abs_path.as_str()
+    } else {
+        doc.abs_path.strip_prefix(root_dir)?.strip_prefix('/')?
+    };
+    let mut path: String = dir_segments(rel)
+        .into_iter()
+        .map(slugify)
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| segment + "/")
+        .collect();
+    path.push_str(&doc.id);
+    path.push_str(".html");
+    Some(path)
+}
+
+/// Site path of a `permalink` value: `about/index.html` as written, `/about/` or `about` as
+/// `about/index.html`; `None` for an empty path or one that leaves the site.
+fn permalink_path(raw: &str) -> Option<String> {
+    let trimmed = raw.trim().trim_start_matches('/');
+    let segments: Vec<&str> = trimmed
+        .trim_end_matches('/')
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect();
+    let invalid = |segment: &&str| *segment == ".." || segment.contains(['\\', '?', '#']);
+    if segments.is_empty() || segments.iter().any(invalid) {
+        return None;
+    }
+    let path = segments.join("/");
+    let last = segments[segments.len() - 1].to_ascii_lowercase();
+    if !trimmed.ends_with('/') && (last.ends_with(".html") || last.ends_with(".htm")) {
+        Some(path)
+    } else {
+        Some(format!("{path}/index.html"))
+    }
+}
+
+fn dated_path(doc: &Doc) -> Option<String> {
+    let created = parse_created(doc.frontmatter.get("created")?.as_str()?)?.date();
+    Some(format!(
+        "{}/{:02}/{:02}/{}.html",
+        created.year(),
+        created.month() as u8,
+        created.day(),
+        doc.id
+    ))
+}
+
+/// Prefix leading from a page at the site path `path` back to the site root.
+pub fn root_prefix(path: &str) -> String {
+    "../".repeat(path.matches('/').count())
+}
+
+/// Folders of a site-relative file path (`pages/walk.html` -> `["pages"]`).
+pub(crate) fn dir_segments(rel: &str) -> Vec<&str> {
+    let mut segments: Vec<&str> = rel.split('/').collect();
+    segments.pop();
+    segments
+}
+
+/// Relative URL from folder `dir` to the site path `target` (`""` is the site root).
+pub(crate) fn relative_url(dir: &[&str], target: &str) -> String {
+    let target_segments: Vec<&str> = target.split('/').collect();
+    let (target_dirs, file) = target_segments.split_at(target_segments.len() - 1);
+    let common = dir
+        .iter()
+        .zip(target_dirs)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut url = "../".repeat(dir.len() - common);
+    for segment in &target_dirs[common..] {
+        url.push_str(segment);
+        url.push('/');
+    }
+    url.push_str(file[0]);
+    if url.is_empty() {
+        "./".to_string()
+    } else {
+        url
+    }
+}
@@ -0,0 +1,325 @@
+/*!
+ * Remote pages: `http(s)` URLs as the entry or as `contents` targets.
+ *
+ * [`RemoteFs`] wraps the host's [`FileProvider`] and hands every `http://` / `https://` path to
+ * a [`RemoteProvider`] (the CLI fetches over the network; other hosts can plug in their own
+ * transport). A root index can thereby aggregate files hosted elsewhere:
+ *
+ * ```yaml
+ * contents:
+ *   - "[Local notes](notes/index.md)"
+ *   - "[Alice's garden](https://alice.example.org/garden/index.md)"
+ * ```
+ *
+ * Relative links inside a remote page resolve against its URL, so a remote index pulls in its
+ * own `contents` from the same host. Only `.md` / `.html` pages (or extensionless paths) are
+ * fetched; attachments of remote pages are not. Hosts must be on the allowlist (an entry
+ * `example.org` also admits its subdomains); refused or failed fetches are collected as
+ * warnings (see [`RemoteFs::take_warnings`]). Each URL is fetched at most once per build.
+ */
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+
+use crate::memfs::normalize_path;
+use crate::{FileMetadata, FileProvider, Warning};
+
+/// Transport for remote pages.
+pub trait RemoteProvider {
+    /// UTF-8 body of the resource at `url`.
+    fn fetch(&self, url: &str) -> Result<String>;
+}
+
+/// A [`FileProvider`] serving `http(s)` paths through a [`RemoteProvider`] and everything else
+/// through `local`.
+pub struct RemoteFs<F, R> {
+    local: F,
+    remote: R,
+    /// Allowed hosts, lowercased.
+    allowed_hosts: Vec<String>,
+    /// URL -> fetched body, or the reason it is unavailable.
+    cache: RefCell<HashMap<String, Result<String, String>>>,
+    /// URLs that could not be loaded, in order.
+    failed: RefCell<Vec<String>>,
+}
+
+impl<F: FileProvider, R: RemoteProvider> RemoteFs<F, R> {
+    pub fn new(local: F, remote: R, allowed_hosts: Vec<String>) -> Self {
+        Self {
+            local,
+            remote,
+            allowed_hosts: allowed_hosts
+                .iter()
+                .map(|h| h.trim().trim_start_matches("*.").to_ascii_lowercase())
+                .filter(|h| !h.is_empty())
+                .collect(),
+            cache: RefCell::new(HashMap::new()),
+            failed: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The provider serving non-remote paths.
+    pub fn local(&self) -> &F {
+        &self.local
+    }
+
+    /// Refused and failed fetches so far (each URL reported once); clears the list. An
+    /// extensionless URL whose `.md` variant loaded is not reported: it was only probed.
+    pub fn take_warnings(&self) -> Vec<Warning> {
+        let cache = self.cache.borrow();
+        std::mem::take(&mut *self.failed.borrow_mut())
+            .into_iter()
+            .filter(|url| !matches!(cache.get(&format!("{url}.md")), Some(Ok(_))))
+            .filter_map(|url| cache.get(&url)?.clone().err())
+            .map(|message| Warning::new("remote", message))
+            .collect()
+    }
+
+    /// Whether `host` (or a parent domain of it) is on the allowlist.
+    fn allows(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.allowed_hosts.iter().any(|allowed| {
+            host == *allowed
+                || host
+                    .strip_suffix(allowed.as_str())
+                    .is_some_and(|sub| sub.ends_with('.'))
+        })
+    }
+
+    /// Body of `url`, fetching it on first use.
+    fn load(&self, url: &str) -> Result<String, String> {
+        if let Some(cached) = self.cache.borrow().get(url) {
+            return cached.clone();
+        }
+        let loaded = match url_host(url) {
+            None => Err(format!("Invalid URL: {url}")),
+            Some(host) if !self.allows(host) => Err(format!(
+                "Remote host '{host}' is not allowed: {url} (add it to the allowlist)"
+            )),
+            Some(_) => self
+                .remote
+                .fetch(url)
+                .map_err(|e| format!("Failed to fetch {url}: {e:#}")),
+        };
+        if loaded.is_err() {
+            self.failed.borrow_mut().push(url.to_string());
+        }
+        self.cache
+            .borrow_mut()
+            .insert(url.to_string(), loaded.clone());
+        loaded
+    }
+}
+
+impl<F: FileProvider, R: RemoteProvider> FileProvider for RemoteFs<F, R> {
+    fn read_to_string(&self, path: &str) -> Result<String> {
+        if is_remote(path) {
+            self.load(path).map_err(|e| anyhow!(e))
+        } else {
+            self.local.read_to_string(path)
+        }
+    }
+    fn exists(&self, path: &str) -> bool {
+        if is_remote(path) {
+            is_page_url(path) && self.load(path).is_ok()
+        } else {
+            self.local.exists(path)
+        }
+    }
+    fn is_file(&self, path: &str) -> bool {
+        if is_remote(path) {
+            self.exists(path)
+        } else {
+            self.local.is_file(path)
+        }
+    }
+    fn join(&self, parent: &str, rel: &str) -> String {
+        if is_remote(rel) {
+            rel.to_string()
+        } else if is_remote(parent) {
+            url_join(parent, rel)
+        } else {
+            self.local.join(parent, rel)
+        }
+    }
+    fn extension_lowercase(&self, path: &str) -> Option<String> {
+        if is_remote(path) {
+            url_extension(path)
+        } else {
+            self.local.extension_lowercase(path)
+        }
+    }
+    fn parent(&self, path: &str) -> Option<String> {
+        if is_remote(path) {
+            let (origin, path) = split_origin(url_path(path))?;
+            let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+            Some(format!("{origin}{dir}"))
+        } else {
+            self.local.parent(path)
+        }
+    }
+    fn file_name(&self, path: &str) -> Option<String> {
+        if is_remote(path) {
+            let (_, path) = split_origin(url_path(path))?;
+            path.rsplit('/')
+                .next()
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+        } else {
+            self.local.file_name(path)
+        }
+    }
+    fn canonical_display(&self, path: &str) -> String {
+        if is_remote(path) {
+            path.to_string()
+        } else {
+            self.local.canonical_display(path)
+        }
+    }
+    fn metadata(&self, path: &str) -> Option<FileMetadata> {
+        if is_remote(path) {
+            None
+        } else {
+            self.local.metadata(path)
+        }
+    }
+    fn list_files(&self, dir: &str) -> Option<Vec<String>> {
+        if is_remote(dir) {
+            None
+        } else {
+            self.local.list_files(dir)
+        }
+    }
+}
+
+/// Whether `path` is an `http://` or `https://` URL.
+pub fn is_remote(path: &str) -> bool {
+    let lower = path.get(..8).unwrap_or(path).to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// Host of an `http(s)` URL (without user info or port).
+pub fn url_host(url: &str) -> Option<&str> {
+    let (origin, _) = split_origin(url)?;
+    let authority = origin.split_once("://")?.1;
+    let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    let host = match host.rsplit_once(':') {
+        Some((h, port)) if port.bytes().all(|b| b.is_ascii_digit()) => h,
+        _ => host,
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+/// `scheme://authority` and the rest of the URL (starting with `/`, `?`, `#` or `\\`, or empty).
+/// The authority ends where HTTP clients end it, so `https://a.org?@b.org/` is on `a.org`.
+fn split_origin(url: &str) -> Option<(&str, &str)> {
+    let scheme_end = url.find("://")? + 3;
+    let path_start = url[scheme_end..]
+        .find(['/', '?', '#', '\\'])
+        .map_or(url.len(), |i| scheme_end + i);
+    Some((&url[..path_start], &url[path_start..]))
+}
+
+/// `url` without its query and fragment.
+fn url_path(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or(url)
+}
+
+fn url_extension(url: &str) -> Option<String> {
+    let (_, path) = split_origin(url_path(url))?;
+    let name = path.rsplit('/').next()?;
+    name.rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty())
+}
+
+/// Pages are fetched; attachments and folders are not.
+fn is_page_url(url: &str) -> bool {
+    !url_path(url).ends_with('/')
+        && matches!(
+            url_extension(url).as_deref(),
+            None | Some("md" | "html" | "htm")
+        )
+}
+
+/// Resolve `rel` against the folder URL `base` (`..` segments stop at the host).
+fn url_join(base: &str, rel: &str) -> String {
+    let Some((origin, dir)) = split_origin(base) else {
+        return rel.to_string();
+    };
+    let joined = if rel.starts_with('/') {
+        rel.to_string()
+    } else {
+        format!("{dir}/{rel}")
+    };
+    // Keep the query and fragment out of the dot-segment normalization.
+    let split = joined.find(['?', '#']).unwrap_or(joined.len());
+    let (path, rest) = joined.split_at(split);
+    format!("{origin}{}{rest}", normalize_path(&format!("/{path}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::memfs::InMemoryFs;
+
+    /// Records the URLs it is asked for.
+    #[derive(Default)]
+    struct Recorder(RefCell<Vec<String>>);
+
+    impl RemoteProvider for &Recorder {
+        fn fetch(&self, url: &str) -> Result<String> {
+            self.0.borrow_mut().push(url.to_string());
+            Ok("---\ntitle: Remote\n---\n".to_string())
+        }
+    }
+
+    #[test]
+    fn host_ends_the_authority_where_clients_do() {
+        assert_eq!(url_host("https://allowed.org/a.md"), Some("allowed.org"));
+        assert_eq!(
+            url_host("https://me@allowed.org:8080/a.md"),
+            Some("allowed.org")
+        );
+        assert_eq!(
+            url_host("https://evil.com?@allowed.org/x.md"),
+            Some("evil.com")
+        );
+        assert_eq!(url_host("https://evil.com#@allowed.org"), Some("evil.com"));
+        assert_eq!(
+            url_host("https://evil.com\\@allowed.org/a.md"),
+            Some("evil.com")
+        );
+        assert_eq!(url_host("https:///a.md"), None);
+    }
+
+    #[test]
+    fn only_allowed_hosts_are_fetched() {
+        let recorder = Recorder::default();
+        let fs = RemoteFs::new(
+            InMemoryFs::new(HashMap::new()),
+            &recorder,
+            vec!["allowed.org".to_string()],
+        );
+        for url in [
+            "https://evil.com?@allowed.org/x.md",
+            "https://evil.com#@allowed.org",
+            "https://evil.com\\@allowed.org/a.md",
+            "https://allowed.org.evil.com/a.md",
+        ] {
+            assert!(fs.read_to_string(url).is_err(), "{url} was fetched");
+        }
+        assert!(fs.read_to_string("https://allowed.org/a.md").is_ok());
+        assert!(fs.read_to_string("https://notes.allowed.org/a.md").is_ok());
+        assert_eq!(
+            *recorder.0.borrow(),
+            ["https://allowed.org/a.md", "https://notes.allowed.org/a.md"]
+        );
+        assert_eq!(fs.take_warnings().len(), 4);
+    }
+}
@@ -0,0 +1,491 @@
+/*!
+ * Vault-defined frontmatter fields, declared in `diaryx.toml`.
+ *
+ * ```toml
+ * [fields.mood]
+ * type = "enum"
+ * values = ["calm", "busy", "tired"]
+ * required = true
+ *
+ * [fields.related]
+ * type = "link-list"
+ * label = "Related entries"
+ * ```
+ *
+ * Field types are `string`, `date` (`YYYY-MM-DD` or RFC 3339), `number`, `enum` (one of
+ * `values`) and `link-list` (a Markdown link or path, or a list of them). A missing required
+ * field or a value of the wrong type is a warning, worded like those for the built-in fields.
+ * The metadata block shows the field under its `label` and renders dates, enum values and links
+ * accordingly. Built-in fields cannot be redeclared.
+ *
+ * The `[dates]` table says how `created` and `updated` written as a shorthand are read:
+ *
+ * ```toml
+ * [dates]
+ * default_time = "09:00"    # time of a bare date (`created: 2025-08-25`); default midnight
+ * timezone = "+02:00"       # offset of a date or time written without one; default UTC
+ * ```
+ *
+ * Such values (`2025-08-25`, `2025/8/25`, `2025-08-25 14:30`) are rewritten as RFC 3339
+ * timestamps when a file is parsed, so everything downstream sees one format.
+ *
+ * The nearest `diaryx.toml` in the folder of the file being built or linted, or in any folder
+ * above it, applies. Other tables in the file are left to the tools that read them.
+ */
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use serde_json::json;
+use serde_yaml::Value;
+use time::{OffsetDateTime, Time, UtcOffset};
+
+use crate::{
+    FileProvider, Warning, extract_md_link_parts_raw, html_esc_simple, humanize_timestamp,
+    parse_created, parse_date, parse_offset,
+};
+
+/// File name of the vault configuration.
+pub const CONFIG_FILE: &str = "diaryx.toml";
+
+/// Fields with a fixed meaning in Diaryx itself.
+const BUILTIN_FIELDS: &[&str] = &[
+    "title",
+    "author",
+    "created",
+    "updated",
+    "visibility",
+    "format",
+    "reachable",
+    "contents",
+    "part_of",
+    "version",
+    "copying",
+    "tags",
+    "aliases",
+    "lang",
+    "translations",
+    "this_file_is_root_index",
+    "draft",
+    "published",
+    "permalink",
+    "output_path",
+];
+
+/// The `[fields]` and `[dates]` of a `diaryx.toml`; empty when there is none.
+#[derive(Debug, Clone, Default, Hash, Deserialize)]
+pub struct Schema {
+    #[serde(default)]
+    pub fields: BTreeMap<String, FieldSpec>,
+    #[serde(default)]
+    pub dates: DateConfig,
+}
+
+/// The `[dates]` table: how dates written without a time or offset are read.
+#[derive(Debug, Clone, Default, Hash, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DateConfig {
+    /// `HH:MM` or `HH:MM:SS`.
+    pub default_time: Option<String>,
+    /// `UTC`, or an offset such as `+02:00`.
+    pub timezone: Option<String>,
+}
+
+/// One declared field.
+#[derive(Debug, Clone, Hash, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldSpec {
+    #[serde(rename = "type")]
+    pub kind: FieldType,
+    #[serde(default)]
+    pub required: bool,
+    /// Allowed values of an `enum` field.
+    #[serde(default)]
+    pub values: Vec<String>,
+    /// Name shown in the metadata block instead of the key.
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FieldType {
+    String,
+    Date,
+    Number,
+    Enum,
+    LinkList,
+}
+
+impl Schema {
+    /// Parse the text of a `diaryx.toml`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let schema: Schema = toml::from_str(text)?;
+        schema.dates.default_time()?;
+        schema.dates.offset()?;
+        for (name, spec) in &schema.fields {
+            if BUILTIN_FIELDS.contains(&name.as_str()) {
+                bail!("'{name}' is a built-in field and cannot be redeclared");
+            }
+            match (spec.kind, spec.values.is_empty()) {
+                (FieldType::Enum, true) => bail!("Enum field '{name}' needs `values`"),
+                (FieldType::Enum, false) | (_, true) => {}
+                (_, false) => bail!("Field '{name}' has `values` but is not an enum"),
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Load the nearest `diaryx.toml` at or above the folder of `path`; an empty schema when
+    /// there is none.
+    pub fn discover(path: &str, fs: &impl FileProvider) -> Result<Self> {
+        let mut dir = fs.parent(path);
+        while let Some(current) = dir {
+            let candidate = fs.join(&current, CONFIG_FILE);
+            if fs.is_file(&candidate) {
+                let text = fs.read_to_string(&candidate)?;
+                return Self::parse(&text).with_context(|| format!("Invalid {candidate}"));
+            }
+            dir = fs.parent(&current).filter(|parent| *parent != current);
+        }
+        Ok(Self::default())
+    }
+
+    /// Report missing required fields and values of the wrong type in `frontmatter`.
+    pub fn validate(&self, frontmatter: &Value, path: &str, warnings: &mut Vec<Warning>) {
+        for (name, spec) in &self.fields {
+            match frontmatter.get(name) {
+                None | Some(Value::Null) if spec.required => {
+                    warnings.push(Warning::new(
+                        "missing-field",
+                        format!("Missing required field: {name} ({path})"),
+                    ));
+                }
+                None | Some(Value::Null) => {}
+                Some(value) if spec.accepts(value) => {}
+                Some(value) => warnings.push(Warning::new(
+                    "invalid-field",
+                    format!(
+                        "Field '{name}' should be {}; got {} ({path})",
+                        spec.expected(),
+                        describe(value)
+                    ),
+                )),
+            }
+        }
+    }
+
+    /// JSON Schema (draft 2020-12) of Diaryx frontmatter: the built-in fields plus the declared
+    /// ones, for editors that validate YAML as you type. Other keys stay allowed.
+    pub fn json_schema(&self) -> serde_json::Value {
+        let text = json!({ "type": "string" });
+        let text_list = json!({ "type": "array", "items": { "type": "string" } });
+        let text_or_list = json!({ "anyOf": [text, text_list] });
+        let timestamp = json!({
+            "type": "string",
+            "anyOf": [
+                { "format": "date-time" },
+                { "format": "date" },
+                { "pattern": r"^\d{4}[-/]\d{1,2}[-/]\d{1,2}([T ]\d{1,2}:\d{2}.*)?$" },
+            ],
+        });
+        let person = json!({
+            "type": "object",
+            "properties": {
+                "name": text,
+                "email": { "type": "string", "format": "email" },
+                "url": { "type": "string", "format": "uri" },
+            },
+            "required": ["name"],
+        });
+        let people = json!({ "type": "array", "items": { "anyOf": [text, person] } });
+        let author = json!({ "anyOf": [text, person, people] });
+        let described = |schema: &serde_json::Value, description: &str| {
+            let mut schema = schema.clone();
+            schema["description"] = json!(description);
+            schema
+        };
+
+        let mut properties = serde_json::Map::new();
+        for (name, schema, description) in [
+            ("title", &text, "Title of the entry."),
+            (
+                "author",
+                &author,
+                "A name, `Name <email>`, a mapping with `name`, or a list of these.",
+            ),
+            (
+                "created",
+                &timestamp,
+                "When the entry was created (RFC 3339, YYYY-MM-DD or YYYY-MM-DD HH:MM).",
+            ),
+            (
+                "updated",
+                &timestamp,
+                "When the entry was last changed (RFC 3339, YYYY-MM-DD or YYYY-MM-DD HH:MM).",
+            ),
+            (
+                "visibility",
+                &text_or_list,
+                "Audiences the entry is meant for, e.g. `public`.",
+            ),
+            (
+                "format",
+                &text,
+                "Body format, usually a link to the CommonMark spec.",
+            ),
+            (
+                "reachable",
+                &json!({ "anyOf": [text, text_list, { "type": "object" }] }),
+                "Where or by whom the entry can be reached: URLs, paths or audiences.",
+            ),
+            (
+                "contents",
+                &text_list,
+                "Child entries of an index, as Markdown links.",
+            ),
+            (
+                "part_of",
+                &text_or_list,
+                "Parent index or indexes, as Markdown links.",
+            ),
+            (
+                "version",
+                &json!({ "type": ["string", "number"] }),
+                "Version of the entry.",
+            ),
+            (
+                "copying",
+                &text,
+                "License: an SPDX expression, a URL, or a Markdown link to one.",
+            ),
+            ("tags", &text_list, "Tags of the entry."),
+            ("aliases", &text_list, "Other names of the entry."),
+            ("lang", &text, "Language of the entry (a BCP 47 tag)."),
+            (
+                "translations",
+                &text_or_list,
+                "Other language versions, as Markdown links.",
+            ),
+            (
+                "this_file_is_root_index",
+                &json!({ "type": "boolean" }),
+                "Marks the root index of a vault.",
+            ),
+            (
+                "draft",
+                &json!({ "type": "boolean" }),
+                "Keeps the entry out of builds unless `--drafts` is given.",
+            ),
+            (
+                "published",
+                &timestamp,
+                "When the entry appears: builds before then leave it out unless `--future` is given.",
+            ),
+            (
+                "permalink",
+                &text,
+                "Site path the page is written to, e.g. `about/` or `about/index.html`.",
+            ),
+            ("output_path", &text, "Same as `permalink`."),
+        ] {
+            properties.insert(name.to_string(), described(schema, description));
+        }
+        let mut required: Vec<&str> = vec![
+            "title",
+            "author",
+            "created",
+            "updated",
+            "visibility",
+            "format",
+            "reachable",
+        ];
+        for (name, spec) in &self.fields {
+            let mut schema = match spec.kind {
+                FieldType::String => text.clone(),
+                FieldType::Number => json!({ "type": "number" }),
+                FieldType::Date => timestamp.clone(),
+                FieldType::Enum => json!({ "enum": spec.values }),
+                FieldType::LinkList => text_or_list.clone(),
+            };
+            if let Some(label) = &spec.label {
+                schema["title"] = json!(label);
+            }
+            schema["description"] =
+                json!(format!("Declared in {CONFIG_FILE} ({}).", spec.expected()));
+            properties.insert(name.clone(), schema);
+            if spec.required {
+                required.push(name);
+            }
+        }
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Diaryx frontmatter",
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": true,
+        })
+    }
+
+    /// Metadata label of `key`: the declared `label`, else the key itself.
+    pub(crate) fn label<'a>(&'a self, key: &'a str) -> &'a str {
+        self.fields
+            .get(key)
+            .and_then(|spec| spec.label.as_deref())
+            .unwrap_or(key)
+    }
+}
+
+impl DateConfig {
+    /// Read `raw` as [`crate::parse_date`] does, with this table's time and offset.
+    pub fn parse(&self, raw: &str) -> Option<OffsetDateTime> {
+        parse_date(
+            raw,
+            self.default_time().unwrap_or(Time::MIDNIGHT),
+            self.offset().unwrap_or(UtcOffset::UTC),
+        )
+    }
+
+    fn default_time(&self) -> Result<Time> {
+        let Some(raw) = &self.default_time else {
+            return Ok(Time::MIDNIGHT);
+        };
+        ["[hour]:[minute]:[second]", "[hour]:[minute]"]
+            .iter()
+            .find_map(|format| {
+                let format = time::format_description::parse(format).ok()?;
+                Time::parse(raw.trim(), &format).ok()
+            })
+            .ok_or_else(|| anyhow!("[dates] default_time '{raw}' should be HH:MM or HH:MM:SS"))
+    }
+
+    fn offset(&self) -> Result<UtcOffset> {
+        let Some(raw) = &self.timezone else {
+            return Ok(UtcOffset::UTC);
+        };
+        parse_offset(raw).ok_or_else(|| {
+            anyhow!("[dates] timezone '{raw}' should be UTC or an offset like +02:00")
+        })
+    }
+}
+
+impl FieldSpec {
+    fn accepts(&self, value: &Value) -> bool {
+        match self.kind {
+            FieldType::String => value.is_string(),
+            FieldType::Number => value.is_number(),
+            FieldType::Date => value.as_str().is_some_and(|s| parse_created(s).is_some()),
+            FieldType::Enum => scalar(value).is_some_and(|s| self.values.contains(&s)),
+            FieldType::LinkList => {
+                let links = links(value);
+                !links.is_empty() && links.len() == count(value)
+            }
+        }
+    }
+
+    fn expected(&self) -> String {
+        match self.kind {
+            FieldType::String => "a string".to_string(),
+            FieldType::Number => "a number".to_string(),
+            FieldType::Date => "a date (YYYY-MM-DD or RFC 3339)".to_string(),
+            FieldType::Enum => format!("one of {}", self.values.join(", ")),
+            FieldType::LinkList => "a link or path, or a list of them".to_string(),
+        }
+    }
+
+    /// Metadata HTML for a valid value: dates humanized, enum values as chips, links as anchors
+    /// (one per line, internal `.md` targets still to be rewritten by the caller). `None` when
+    /// the value is invalid or plain text suits it.
+    pub(crate) fn render(&self, value: &Value) -> Option<String> {
+        if !self.accepts(value) {
+            return None;
+        }
+        let mut out = String::new();
+        match self.kind {
+            FieldType::String | FieldType::Number => return None,
+            FieldType::Date => {
+                let raw = value.as_str()?.trim();
+                out.push_str("<time datetime=\"");
+                html_esc_simple(&mut out, raw);
+                out.push_str("\">");
+                html_esc_simple(&mut out, &humanize_timestamp(raw));
+                out.push_str("</time>");
+            }
+            FieldType::Enum => {
+                out.push_str("<span class=\"field-enum\">");
+                html_esc_simple(&mut out, &scalar(value)?);
+                out.push_str("</span>");
+            }
+            FieldType::LinkList => {
+                for (i, (label, target)) in links(value).iter().enumerate() {
+                    if i > 0 {
+                        out.push_str("<br/>");
+                    }
+                    out.push_str("<a href=\"");
+                    html_esc_simple(&mut out, target);
+                    out.push_str("\">");
+                    html_esc_simple(&mut out, label);
+                    out.push_str("</a>");
+                }
+            }
+        }
+        Some(out)
+    }
+}
+
+/// A string, number or bool as text.
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Number of items in a link-list value (a single value counts as one).
+fn count(value: &Value) -> usize {
+    match value {
+        Value::Sequence(items) => items.len(),
+        _ => 1,
+    }
+}
+
+/// `(label, target)` of each well-formed item: a Markdown link, or a bare path labelled with
+/// itself.
+fn links(value: &Value) -> Vec<(String, String)> {
+    let items = match value {
+        Value::Sequence(items) => items.iter().collect(),
+        other => vec![other],
+    };
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let raw = item.as_str()?.trim();
+            match extract_md_link_parts_raw(raw) {
+                Some((label, target)) if !target.is_empty() => {
+                    let label = if label.is_empty() {
+                        target.clone()
+                    } else {
+                        label
+                    };
+                    Some((label, target))
+                }
+                Some(_) => None,
+                None => (!raw.is_empty() && !raw.contains(['[', ']', '(', ')']))
+                    .then(|| (raw.to_string(), raw.to_string())),
+            }
+        })
+        .collect()
+}
+
+/// A value as quoted in warnings: scalars verbatim, other shapes by kind.
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Sequence(_) => "a list".to_string(),
+        Value::Mapping(_) => "a mapping".to_string(),
+        Value::Tagged(_) => "a tagged value".to_string(),
+        other => scalar(other).map_or_else(|| "null".to_string(), |s| format!("'{s}'")),
+    }
+}
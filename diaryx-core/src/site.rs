@@ -0,0 +1,530 @@
+/*!
+ * Site assembly: turns build output into the final set of site files.
+ *
+ * Shared by the CLI (which writes each file to disk as pages stream in) and the WASM bindings
+ * (which return the whole site as a `{ path: content }` map), so both produce identical output:
+ * - pages at the paths their build's [`OutputLayout`](crate::OutputLayout) gave them
+ * - the bundled default stylesheet (`css/style.css`)
+ * - the optional JSON model (`diaryx-data.json`)
+ * - the license overview (`licenses.html`) of multi-page sites whose pages declare `copying`
+ *
+ * Paths are relative to the site root and always use forward slashes.
+ */
+
+use std::collections::BTreeMap;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::json;
+
+use crate::output::{dir_segments, relative_url, root_prefix};
+use crate::{AttachmentPlanEntry, BuildArtifacts, License, PageOutput, Warning};
+
+/// Bundled default stylesheet.
+pub const DEFAULT_CSS: &str = include_str!("default.css");
+/// Site-relative path of the default stylesheet.
+pub const CSS_PATH: &str = "css/style.css";
+/// Site-relative path of the JSON model.
+pub const JSON_MODEL_PATH: &str = "diaryx-data.json";
+/// Site-relative path of the license overview.
+pub const LICENSES_PATH: &str = "licenses.html";
+
+/// Options controlling which site files are produced.
+#[derive(Debug, Clone)]
+pub struct SiteOptions {
+    /// Emit and link the bundled default stylesheet.
+    pub include_css: bool,
+    /// Emit the JSON model.
+    pub emit_json: bool,
+}
+
+impl Default for SiteOptions {
+    fn default() -> Self {
+        Self {
+            include_css: true,
+            emit_json: false,
+        }
+    }
+}
+
+/// Where a page at `rel` lives with clean URLs: `pages/walk.html` becomes
+/// `pages/walk/index.html`, so hosts serve it at `pages/walk/`. Index files stay where they are.
+pub fn clean_page_path(rel: &str) -> String {
+    match rel.strip_suffix(".html") {
+        Some(stem) if stem != "index" && !stem.ends_with("/index") => format!("{stem}/index.html"),
+        _ => rel.to_string(),
+    }
+}
+
+/// Rewrite the relative URLs (`href`, `src`, `poster`) of a full page written for `rel` so they
+/// hold once the page moves to [`clean_page_path`]: every URL is rebased one folder deeper, and
+/// links to pages (`.html`) point at their folder (`../walk/` rather than `walk.html`). Absolute
+/// URLs, fragments and URLs leaving the site are left alone.
+pub fn rewrite_clean_urls(html: &str, rel: &str) -> String {
+    static URL_ATTR: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"\b(href|src|poster)="([^"]*)""#).unwrap());
+    let from_dir = dir_segments(rel);
+    let clean = clean_page_path(rel);
+    let to_dir = dir_segments(&clean);
+    URL_ATTR
+        .replace_all(html, |caps: &regex::Captures| {
+            let url = &caps[2];
+            if url.is_empty() || url.starts_with(['#', '/', '?']) || url.contains(':') {
+                return caps[0].to_string();
+            }
+            let (path, suffix) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+            let Some(mut target) = resolve_site_path(&from_dir, path) else {
+                return caps[0].to_string();
+            };
+            if target.ends_with(".html") {
+                target = clean_page_path(&target);
+                target.truncate(target.len() - "index.html".len());
+            }
+            format!(
+                "{}=\"{}{suffix}\"",
+                &caps[1],
+                relative_url(&to_dir, &target)
+            )
+        })
+        .into_owned()
+}
+
+/// Site-relative path of `url` seen from `dir`; `None` when it climbs above the site root.
+fn resolve_site_path(dir: &[&str], url: &str) -> Option<String> {
+    let mut segments: Vec<&str> = dir.to_vec();
+    for segment in url.split('/') {
+        match segment {
+            "." | "" => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let mut path = segments.join("/");
+    if url.ends_with('/') && !path.is_empty() {
+        path.push('/');
+    }
+    Some(path)
+}
+
+/// Built-in page shells, chosen per page with a `layout:` (or `template:`) frontmatter key.
+///
+/// Every layout shares the same `<head>`; they differ in how the body is arranged, and each
+/// non-default one tags `<body>` with `layout-<name>` so the stylesheet can adapt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// Metadata panel (sidebar on wide screens) next to the content.
+    #[default]
+    Default,
+    /// Landing pages: a title heading with the metadata as a full-width header above the content.
+    Index,
+    /// Image-led entries: a wide single column, metadata folded away below the content.
+    Photo,
+    /// Just the title and the content, no metadata.
+    Minimal,
+}
+
+impl Layout {
+    /// Registered layout names.
+    pub const NAMES: [&'static str; 4] = ["default", "index", "photo", "minimal"];
+
+    pub fn from_name(name: &str) -> Option<Layout> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "default" => Some(Layout::Default),
+            "index" => Some(Layout::Index),
+            "photo" => Some(Layout::Photo),
+            "minimal" => Some(Layout::Minimal),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Default => "default",
+            Layout::Index => "index",
+            Layout::Photo => "photo",
+            Layout::Minimal => "minimal",
+        }
+    }
+}
+
+/// Wrap the core-rendered HTML content inside a full HTML document, arranged by the page's
+/// [`Layout`].
+pub fn wrap_full_html(page: &PageOutput, multi_page: bool, include_css: bool) -> String {
+    let mut out = String::new();
+    out.push_str("<!doctype html><html lang=\"");
+    crate::html_esc_simple(&mut out, page.lang.as_deref().unwrap_or("en"));
+    out.push_str("\"><head><meta charset=\"utf-8\" />");
+    out.push_str("<meta name=\"viewport\" content=\"width=device-width,initial-scale=1\" />");
+    out.push_str("<title>");
+    crate::html_esc_simple(&mut out, &page.title);
+    out.push_str("</title>");
+    if let Some(url) = canonical_url(page) {
+        out.push_str("<link rel=\"canonical\" href=\"");
+        crate::html_esc_simple(&mut out, url);
+        out.push_str("\" />");
+    }
+    if !page.authors.is_empty() {
+        out.push_str("<meta name=\"author\" content=\"");
+        let names: Vec<&str> = page.authors.iter().map(|a| a.name.as_str()).collect();
+        crate::html_esc_simple(&mut out, &names.join(", "));
+        out.push_str("\" />");
+    }
+    if is_noindex(page) {
+        out.push_str("<meta name=\"robots\" content=\"noindex\" />");
+    }
+    if let Some(lang) = page
+        .lang
+        .as_deref()
+        .filter(|_| !page.translations.is_empty())
+    {
+        // hreflang alternates, the page itself included.
+        let own = std::iter::once((lang, page.file_name.as_str()));
+        for (lang, href) in own.chain(
+            page.translations
+                .iter()
+                .map(|t| (t.lang.as_str(), t.href.as_str())),
+        ) {
+            out.push_str("<link rel=\"alternate\" hreflang=\"");
+            crate::html_esc_simple(&mut out, lang);
+            out.push_str("\" href=\"");
+            crate::html_esc_simple(&mut out, href);
+            out.push_str("\" />");
+        }
+    }
+    if include_css {
+        out.push_str("<link rel=\"stylesheet\" href=\"");
+        out.push_str(&root_prefix(&page.path));
+        out.push_str(CSS_PATH);
+        out.push_str("\" />");
+    }
+    out.push_str("</head>");
+    let layout = Layout::from_name(&page.layout).unwrap_or_default();
+    if layout == Layout::Default {
+        out.push_str("<body>");
+    } else {
+        out.push_str("<body class=\"layout-");
+        out.push_str(layout.name());
+        out.push_str("\">");
+    }
+    let title = |out: &mut String| {
+        out.push_str("<h1 class=\"page-title\">");
+        crate::html_esc_simple(out, &page.title);
+        out.push_str("</h1>");
+    };
+    match layout {
+        Layout::Default => {
+            // Metadata list placed directly under body so it becomes a grid item (no wrapper header)
+            out.push_str(&page.metadata_html);
+            out.push_str("<main class=\"content\">");
+            push_language_switcher(&mut out, page);
+            out.push_str(&page.html);
+            out.push_str("</main>");
+        }
+        Layout::Index => {
+            out.push_str("<header class=\"page-header\">");
+            title(&mut out);
+            out.push_str(&page.metadata_html);
+            out.push_str("</header><main class=\"content\">");
+            push_language_switcher(&mut out, page);
+            out.push_str(&page.html);
+            out.push_str("</main>");
+        }
+        Layout::Photo => {
+            out.push_str("<main class=\"content\">");
+            push_language_switcher(&mut out, page);
+            title(&mut out);
+            out.push_str(&page.html);
+            out.push_str("</main>");
+            if !page.metadata_html.is_empty() {
+                out.push_str("<details class=\"page-details\"><summary>Details</summary>");
+                out.push_str(&page.metadata_html);
+                out.push_str("</details>");
+            }
+        }
+        Layout::Minimal => {
+            out.push_str("<main class=\"content\">");
+            push_language_switcher(&mut out, page);
+            title(&mut out);
+            out.push_str(&page.html);
+            out.push_str("</main>");
+        }
+    }
+    push_license_footer(&mut out, page, multi_page);
+    out.push_str("</body></html>");
+    out
+}
+
+/// `Licensed under …` footer (free-text `copying` values are shown as written), linking to the
+/// license overview on multi-page sites.
+fn push_license_footer(out: &mut String, page: &PageOutput, multi_page: bool) {
+    let Some(license) = &page.license else {
+        return;
+    };
+    out.push_str("<footer class=\"page-license\">");
+    if license.is_valid() {
+        out.push_str("Licensed under ");
+    }
+    out.push_str(&license_html(license));
+    if multi_page {
+        out.push_str(" · <a href=\"");
+        out.push_str(&root_prefix(&page.path));
+        out.push_str(LICENSES_PATH);
+        out.push_str("\">All licenses</a>");
+    }
+    out.push_str("</footer>");
+}
+
+fn license_html(license: &License) -> String {
+    let mut out = String::new();
+    match &license.url {
+        Some(url) => {
+            out.push_str("<a rel=\"license\" href=\"");
+            crate::html_esc_simple(&mut out, url);
+            if let Some(name) = &license.name {
+                out.push_str("\" title=\"");
+                crate::html_esc_simple(&mut out, name);
+            }
+            out.push_str("\">");
+            crate::html_esc_simple(&mut out, &license.label);
+            out.push_str("</a>");
+        }
+        None => crate::html_esc_simple(&mut out, &license.label),
+    }
+    out
+}
+
+/// One page in the license overview.
+pub struct LicenseRow {
+    /// Site-relative output path.
+    pub path: String,
+    pub title: String,
+    pub license: Option<License>,
+}
+
+impl LicenseRow {
+    pub fn new(page: &PageOutput) -> Self {
+        Self {
+            path: page.path.clone(),
+            title: page.title.clone(),
+            license: page.license.clone(),
+        }
+    }
+}
+
+/// The license overview page (at [`LICENSES_PATH`]): each license with its page count, then
+/// every page with its license. `None` when no page has a license, or a page of the site
+/// already lives at that path.
+pub fn licenses_page(rows: &[LicenseRow], include_css: bool) -> Option<String> {
+    if rows.iter().all(|r| r.license.is_none()) || rows.iter().any(|r| r.path == LICENSES_PATH) {
+        return None;
+    }
+    // Distinct licenses (by label and URL), most used first
+    let mut counts: Vec<(&License, usize)> = Vec::new();
+    for license in rows.iter().filter_map(|r| r.license.as_ref()) {
+        match counts
+            .iter_mut()
+            .find(|(l, _)| l.label == license.label && l.url == license.url)
+        {
+            Some((_, n)) => *n += 1,
+            None => counts.push((license, 1)),
+        }
+    }
+    counts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+
+    let mut html = String::from("<ul class=\"license-summary\">");
+    for (license, n) in &counts {
+        html.push_str(&format!(
+            "<li>{}: {n} page{}</li>",
+            license_html(license),
+            if *n == 1 { "" } else { "s" }
+        ));
+    }
+    html.push_str("</ul><table class=\"licenses\"><thead><tr><th>Page</th><th>License</th></tr></thead><tbody>");
+    for row in rows {
+        html.push_str("<tr><td><a href=\"");
+        crate::html_esc_simple(&mut html, &row.path);
+        html.push_str("\">");
+        crate::html_esc_simple(&mut html, &row.title);
+        html.push_str("</a></td><td>");
+        match &row.license {
+            Some(license) => {
+                html.push_str(&license_html(license));
+                if license.inherited {
+                    html.push_str(" <span class=\"license-inherited\">(inherited)</span>");
+                }
+            }
+            None => html.push_str("<span class=\"license-none\">none</span>"),
+        }
+        html.push_str("</td></tr>");
+    }
+    html.push_str("</tbody></table>");
+
+    let page = PageOutput {
+        id: "licenses".to_string(),
+        source_path: String::new(),
+        file_name: LICENSES_PATH.to_string(),
+        path: LICENSES_PATH.to_string(),
+        title: "Licenses".to_string(),
+        html,
+        metadata_html: String::new(),
+        is_root_index: false,
+        is_index: false,
+        parents: Vec::new(),
+        children: Vec::new(),
+        frontmatter: serde_yaml::Value::Null,
+        visibility: Vec::new(),
+        warnings: Vec::new(),
+        reachable: Vec::new(),
+        license: None,
+        authors: Vec::new(),
+        version: None,
+        lang: None,
+        translations: Vec::new(),
+        layout: Layout::Minimal.name().to_string(),
+        inferred: Vec::new(),
+    };
+    Some(wrap_full_html(&page, false, include_css))
+}
+
+/// Links to the page's other language versions, the current one marked with `aria-current`.
+fn push_language_switcher(out: &mut String, page: &PageOutput) {
+    let Some(lang) = page
+        .lang
+        .as_deref()
+        .filter(|_| !page.translations.is_empty())
+    else {
+        return;
+    };
+    out.push_str(
+        "<nav class=\"translations\" aria-label=\"Languages\"><span aria-current=\"page\" lang=\"",
+    );
+    crate::html_esc_simple(out, lang);
+    out.push_str("\">");
+    crate::html_esc_simple(out, lang);
+    out.push_str("</span>");
+    for t in &page.translations {
+        out.push_str("<a href=\"");
+        crate::html_esc_simple(out, &t.href);
+        out.push_str("\" hreflang=\"");
+        crate::html_esc_simple(out, &t.lang);
+        out.push_str("\" lang=\"");
+        crate::html_esc_simple(out, &t.lang);
+        out.push_str("\" title=\"");
+        crate::html_esc_simple(out, &t.title);
+        out.push_str("\">");
+        crate::html_esc_simple(out, &t.lang);
+        out.push_str("</a>");
+    }
+    out.push_str("</nav>");
+}
+
+/// The page's `canonical_url:` override (for syndicated copies), emitted as `<link rel="canonical">`.
+pub fn canonical_url(page: &PageOutput) -> Option<&str> {
+    page.frontmatter
+        .get("canonical_url")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+}
+
+/// Whether the page opts out of indexing (`noindex: true`). Besides the robots meta tag, such
+/// pages should be left out of anything that lists or indexes pages (search, feeds, sitemaps).
+pub fn is_noindex(page: &PageOutput) -> bool {
+    page.frontmatter
+        .get("noindex")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// JSON model entry for one page (rendered HTML is intentionally omitted).
+pub fn page_model(page: &PageOutput) -> serde_json::Value {
+    json!({
+      "id": page.id,
+      "title": page.title,
+      "file_name": page.file_name,
+      "path": page.path,
+      "is_root_index": page.is_root_index,
+      "is_index": page.is_index,
+      "parents": page.parents,
+      "children": page.children,
+      "warnings": page.warnings,
+      "reachable": page.reachable,
+      "license": page.license,
+      "authors": page.authors,
+      "version": page.version,
+      "frontmatter": page.frontmatter, // raw YAML value -> serialized JSON
+      "lang": page.lang,
+      "layout": page.layout,
+      "translations": page.translations,
+      "inferred": page.inferred,
+    })
+}
+
+/// Complete model document from per-page entries, for hosts that encode it themselves (the CLI
+/// also writes CBOR and MessagePack).
+pub fn site_model(
+    multi_page: bool,
+    root_slug: Option<&str>,
+    pages: Vec<serde_json::Value>,
+    attachments: &[AttachmentPlanEntry],
+    warnings: &[Warning],
+) -> serde_json::Value {
+    json!({
+      "multi_page": multi_page,
+      "root_slug": root_slug,
+      "pages": pages,
+      "attachments": attachments,
+      "warnings": warnings,
+    })
+}
+
+/// Complete JSON model document (pretty-printed) from per-page entries.
+pub fn site_model_json(
+    multi_page: bool,
+    root_slug: Option<&str>,
+    pages: Vec<serde_json::Value>,
+    attachments: &[AttachmentPlanEntry],
+    warnings: &[Warning],
+) -> String {
+    let model = site_model(multi_page, root_slug, pages, attachments, warnings);
+    serde_json::to_string_pretty(&model).unwrap_or_default()
+}
+
+/// Assemble every text file of the site from collected build artifacts.
+///
+/// Attachments are not included (they are binary and copied by the host from the
+/// attachment plan).
+pub fn site_files(artifacts: &BuildArtifacts, opts: &SiteOptions) -> BTreeMap<String, String> {
+    let mut files = BTreeMap::new();
+    if opts.include_css {
+        files.insert(CSS_PATH.to_string(), DEFAULT_CSS.to_string());
+    }
+    for page in &artifacts.pages {
+        files.insert(
+            page.path.clone(),
+            wrap_full_html(page, artifacts.multi_page, opts.include_css),
+        );
+    }
+    if artifacts.multi_page {
+        let rows: Vec<LicenseRow> = artifacts.pages.iter().map(LicenseRow::new).collect();
+        if let Some(html) = licenses_page(&rows, opts.include_css) {
+            files.insert(LICENSES_PATH.to_string(), html);
+        }
+    }
+    if opts.emit_json {
+        let pages = artifacts.pages.iter().map(page_model).collect();
+        files.insert(
+            JSON_MODEL_PATH.to_string(),
+            site_model_json(
+                artifacts.multi_page,
+                artifacts.root_slug.as_deref(),
+                pages,
+                &artifacts.attachments,
+                &artifacts.warnings,
+            ),
+        );
+    }
+    files
+}
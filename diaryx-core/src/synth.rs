@@ -0,0 +1,246 @@
+/*!
+ * Synthetic vault generation.
+ *
+ * Produces a deterministic, fully valid Diaryx vault of arbitrary size so that build throughput
+ * can be measured (CLI `bench` / `gen-vault`) and large-vault behavior can be unit tested without
+ * checking fixtures into the repository.
+ *
+ * Layout of a generated vault (all files in one directory):
+ * - `index.md`            root index (`this_file_is_root_index: true`) listing every section
+ * - `section-NNN.md`      section indexes, each listing up to `entries_per_section` entries
+ * - `entry-NNNNN.md`      leaf entries with `part_of` back to their section and random body links
+ */
+
+/// Options controlling the shape of a generated vault.
+#[derive(Debug, Clone)]
+pub struct SynthVaultOptions {
+    /// Number of leaf entries (excluding root and section indexes).
+    pub entries: usize,
+    /// Maximum number of entries listed by a single section index.
+    pub entries_per_section: usize,
+    /// Average number of body links from each entry to other random entries.
+    pub link_density: f64,
+    /// Paragraphs of filler text per entry body.
+    pub paragraphs: usize,
+    /// PRNG seed; the same seed always yields the same vault.
+    pub seed: u64,
+}
+
+impl Default for SynthVaultOptions {
+    fn default() -> Self {
+        Self {
+            entries: 100,
+            entries_per_section: 50,
+            link_density: 2.0,
+            paragraphs: 3,
+            seed: 0x5eed,
+        }
+    }
+}
+
+/// A generated vault: relative file paths (forward slashes) paired with their contents.
+/// The first file is always the root index (`index.md`).
+#[derive(Debug, Clone)]
+pub struct SynthVault {
+    pub files: Vec<(String, String)>,
+}
+
+impl SynthVault {
+    /// Relative path of the root index.
+    pub const ROOT: &'static str = "index.md";
+
+    /// Total number of files (root + sections + entries).
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Generate a synthetic vault according to `opts`.
+pub fn generate_vault(opts: &SynthVaultOptions) -> SynthVault {
+    let mut rng = SplitMix64(opts.seed);
+    let per_section = opts.entries_per_section.max(1);
+    let section_count = opts.entries.div_ceil(per_section).max(1);
+    let mut files = Vec::with_capacity(opts.entries + section_count + 1);
+
+    // Root index
+    let root_contents: Vec<String> = (0..section_count)
+        .map(|s| format!("[Section {}]({})", s + 1, section_file(s)))
+        .collect();
+    files.push((
+        SynthVault::ROOT.to_string(),
+        render_doc(
+            "Synthetic Vault",
+            0,
+            Some(&root_contents),
+            None,
+            true,
+            "Generated root index.\n",
+        ),
+    ));
+
+    // Section indexes
+    for s in 0..section_count {
+        let start = s * per_section;
+        let end = (start + per_section).min(opts.entries);
+        let contents: Vec<String> = (start..end)
+            .map(|e| format!("[Entry {}]({})", e + 1, entry_file(e)))
+            .collect();
+        files.push((
+            section_file(s),
+            render_doc(
+                &format!("Section {}", s + 1),
+                s,
+                Some(&contents),
+                Some(&format!("[Synthetic Vault]({})", SynthVault::ROOT)),
+                false,
+                &format!("Section {} of the synthetic vault.\n", s + 1),
+            ),
+        ));
+    }
+
+    // Leaf entries
+    for e in 0..opts.entries {
+        let section = e / per_section;
+        let mut body = format!("# Entry {}\n\n", e + 1);
+        let links = sample_link_count(&mut rng, opts.link_density);
+        let mut paragraphs = opts.paragraphs.max(1);
+        let mut remaining_links = links;
+        while paragraphs > 0 {
+            body.push_str(&filler_paragraph(&mut rng));
+            // Spread links across paragraphs; the last paragraph takes whatever is left.
+            let here = if paragraphs == 1 {
+                remaining_links
+            } else {
+                remaining_links.min((rng.next() % 3) as usize)
+            };
+            for _ in 0..here {
+                if opts.entries > 1 {
+                    let target = (rng.next() % opts.entries as u64) as usize;
+                    body.push_str(&format!(
+                        " See [entry {}]({}).",
+                        target + 1,
+                        entry_file(target)
+                    ));
+                }
+            }
+            remaining_links -= here;
+            body.push_str("\n\n");
+            paragraphs -= 1;
+        }
+        files.push((
+            entry_file(e),
+            render_doc(
+                &format!("Entry {}", e + 1),
+                e,
+                None,
+                Some(&format!(
+                    "[Section {}]({})",
+                    section + 1,
+                    section_file(section)
+                )),
+                false,
+                &body,
+            ),
+        ));
+    }
+
+    SynthVault { files }
+}
+
+fn section_file(s: usize) -> String {
+    format!("section-{:03}.md", s + 1)
+}
+
+fn entry_file(e: usize) -> String {
+    format!("entry-{:05}.md", e + 1)
+}
+
+fn render_doc(
+    title: &str,
+    day_offset: usize,
+    contents: Option<&[String]>,
+    part_of: Option<&str>,
+    root: bool,
+    body: &str,
+) -> String {
+    let stamp = synthetic_timestamp(day_offset);
+    let mut out = String::from("---\n");
+    out.push_str(&format!("title: {title}\n"));
+    out.push_str("author: Synthetic Author\n");
+    out.push_str(&format!("created: {stamp}\n"));
+    out.push_str(&format!("updated: {stamp}\n"));
+    out.push_str("visibility: public\n");
+    out.push_str("format: \"[CommonMark](https://spec.commonmark.org/)\"\n");
+    out.push_str("reachable: everyone\n");
+    if root {
+        out.push_str("this_file_is_root_index: true\n");
+    }
+    if let Some(p) = part_of {
+        out.push_str(&format!("part_of: \"{p}\"\n"));
+    }
+    if let Some(list) = contents {
+        out.push_str("contents:\n");
+        for item in list {
+            out.push_str(&format!("  - \"{item}\"\n"));
+        }
+    }
+    out.push_str("---\n");
+    out.push_str(body);
+    out
+}
+
+/// Deterministic RFC3339 timestamp: one day per offset starting 2025-01-01.
+fn synthetic_timestamp(day_offset: usize) -> String {
+    let base = time::Date::from_calendar_date(2025, time::Month::January, 1).unwrap();
+    let date = base
+        .checked_add(time::Duration::days(day_offset as i64))
+        .unwrap_or(base);
+    format!(
+        "{:04}-{:02}-{:02}T09:00:00Z",
+        date.year(),
+        date.month() as u8,
+        date.day()
+    )
+}
+
+fn sample_link_count(rng: &mut SplitMix64, density: f64) -> usize {
+    if density <= 0.0 {
+        return 0;
+    }
+    // Uniform in [0, 2 * density] so the mean matches the requested density.
+    let max = (density * 2.0).round() as u64;
+    (rng.next() % (max + 1)) as usize
+}
+
+fn filler_paragraph(rng: &mut SplitMix64) -> String {
+    const WORDS: &[&str] = &[
+        "morning", "coffee", "walked", "the", "river", "notes", "about", "quiet", "garden",
+        "reading", "wrote", "letter", "evening", "rain", "thought", "train", "market", "today",
+        "friend", "long", "**bold**", "_soft_", "`code`",
+    ];
+    let len = 20 + (rng.next() % 40) as usize;
+    let mut words = Vec::with_capacity(len);
+    for _ in 0..len {
+        words.push(WORDS[(rng.next() % WORDS.len() as u64) as usize]);
+    }
+    let mut p = words.join(" ");
+    p.push('.');
+    p
+}
+
+/// Tiny deterministic PRNG (SplitMix64); avoids pulling in `rand` for a developer tool.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
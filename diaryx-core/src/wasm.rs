@@ -0,0 +1,711 @@
+/*!
+ * WebAssembly bindings (behind the `wasm` feature).
+ *
+ * Exports take and return plain JS objects (converted with `serde-wasm-bindgen`) and are typed
+ * through the TypeScript declarations below, so consumers never hand-parse JSON. Failures are
+ * thrown as JS `Error` objects carrying a stable `code` property (see `ErrorCode`).
+ *
+ * `DiaryxSession` is the stateful variant for editors: it owns the vault and a `RenderCache`, so
+ * rebuilding after a single-file edit only re-parses and re-renders that file (and relinks the
+ * graph only when its links changed), and hands back only the outputs that changed.
+ *
+ * Binary vault files (images, PDFs) travel as base64 strings, in `assets` next to the text
+ * `files`; with `include_assets` a build hands back every attachment it references, keyed by its
+ * output path, so a browser can zip up the complete site.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::memfs::InMemoryFs;
+use crate::{
+    AttachmentPlanEntry, BuildArtifacts, BuildEvent, CoreBuildOptions, OutputLayout, PageOutput,
+    RenderCache, Warning, build_site_observed, hash_of, render_document, site, validate_graph,
+};
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &'static str = r#"
+export interface BuildInput {
+  /** Path of the entry file inside `files`. */
+  entry: string;
+  /** Virtual vault: `{ path: markdown }`, forward-slash paths. */
+  files: Record<string, string>;
+  /** Binary vault files (images, PDFs, ...) as `{ path: base64 }`. */
+  assets?: Record<string, string>;
+  include_nonpublic?: boolean;
+  /** Also publish the pages whose visibility lists this audience (e.g. `family`). */
+  audience?: string | null;
+  /** Where pages are written: `pages/<slug>.html` (default), next to the root index, mirroring
+   *  the source folders, or under `<year>/<month>/<day>/`. */
+  layout?: "pages" | "flat" | "mirror" | "dated";
+  /** Same as `layout: "flat"`. */
+  flat?: boolean;
+  /** Throw a `STRICT_WARNINGS` error when the build produces warnings. */
+  strict?: boolean;
+  /** Default: true. */
+  rewrite_links?: boolean;
+  large_body_bytes?: number | null;
+  /** Curly quotes, en / em dashes and ellipses in bodies and titles. Default: false. */
+  smart_punctuation?: boolean;
+  /** Render standalone images with a caption as `<figure>` blocks. Default: false. */
+  figures?: boolean;
+  /** Embed PDF attachments linked on a line of their own as inline previews. Default: false. */
+  pdf_previews?: boolean;
+  /** Glob patterns (e.g. `templates/**`) for files that are never built or copied. */
+  exclude?: string[];
+  /** Only entries created on or after this date (`YYYY-MM-DD`). */
+  since?: string | null;
+  /** Only entries created on or before this date (`YYYY-MM-DD`, inclusive). */
+  until?: string | null;
+  /** Only entries tagged with one of these tags. */
+  tags?: string[];
+  /** Publish entries marked `draft: true` too. Default: false. */
+  drafts?: boolean;
+  /** Publish entries whose `published` time is still ahead too. Default: false. */
+  future?: boolean;
+  /** Build the entry as the site root even if it is a section index. Default: false. */
+  as_root?: boolean;
+  /** Include the bundled stylesheet in `files` (and link it from pages). Default: true. */
+  include_css?: boolean;
+  /** Include `diaryx-data.json` in `files`. Default: false. */
+  emit_json?: boolean;
+  /** Return the contents of every referenced attachment in `assets`. Default: false. */
+  include_assets?: boolean;
+}
+
+export interface ValidateInput {
+  entry: string;
+  files: Record<string, string>;
+  include_nonpublic?: boolean;
+}
+
+export interface ParsedDiaryx {
+  /** Slug the file's page gets in a build (from `path`). */
+  slug: string;
+  /** `title` from frontmatter, else the file name. */
+  title: string;
+  visibility: string[];
+  /** Parsed frontmatter as-is (`null` when there is none or it failed to parse). */
+  frontmatter: unknown;
+  /** Rendered body HTML; links and attachments are left as written. */
+  html: string;
+  /** Frontmatter problems (missing required fields, bad types, invalid YAML). */
+  warnings: string[];
+}
+
+export interface PageOutput {
+  /** Page slug. */
+  id: string;
+  source_path: string;
+  file_name: string;
+  /** Site-relative output path under the build's `layout`, e.g. `pages/<slug>.html`. */
+  path: string;
+  title: string;
+  /** Rendered body HTML (links already rewritten). */
+  html: string;
+  metadata_html: string;
+  is_root_index: boolean;
+  is_index: boolean;
+  /** Parent slugs. */
+  parents: string[];
+  /** Child slugs. */
+  children: string[];
+  /** Parsed frontmatter as-is. */
+  frontmatter: unknown;
+  /** `visibility` values (inherited from the listing index by HTML pages). */
+  visibility: string[];
+  warnings: string[];
+  /** `reachable` items, classified and checked. */
+  reachable: {
+    kind: "url" | "path" | "audience";
+    target: string;
+    label: string | null;
+    valid: boolean;
+  }[];
+  /** License from `copying` (or inherited from an ancestor index). */
+  license: {
+    spdx: string | null;
+    url: string | null;
+    label: string;
+    name: string | null;
+    inherited: boolean;
+  } | null;
+  /** Structured `author` entries. */
+  authors: { name: string; email: string | null; url: string | null }[];
+  version: string | null;
+  /** Page language (`lang`, else the root index's). */
+  lang: string | null;
+  /** Other language versions; `href` is relative to this page. */
+  translations: { lang: string; id: string; title: string; href: string }[];
+  /** Page layout: `default`, `index`, `photo` or `minimal`. */
+  layout: string;
+  /** Fields filled in by the build (`created` / `updated` from file timestamps). */
+  inferred: string[];
+}
+
+/** An attachment referenced by the site and where the build placed it. */
+export interface AttachmentPlanEntry {
+  /** Vault path. */
+  source: string;
+  /** Site-relative output path, e.g. `assets/map.png`. */
+  target: string;
+  size: number | null;
+  mime: string;
+  sha256: string | null;
+  /** Slugs of the pages referencing it. */
+  pages: string[];
+}
+
+export interface BuildOutput {
+  /** Complete site as `{ path: content }`, laid out exactly like the CLI output directory. */
+  files: Record<string, string>;
+  /** With `include_assets`: attachment contents as `{ target: base64 }`, completing `files`.
+   *  Attachments missing from the vault are left out. */
+  assets?: Record<string, string>;
+  attachments: AttachmentPlanEntry[];
+  pages: PageOutput[];
+  warnings: string[];
+  multi_page: boolean;
+  root_slug: string | null;
+}
+
+/** What changed since the previous `rebuild()` (everything, on the first call). */
+export interface RebuildOutput {
+  /** Output files added or modified, as `{ path: content }`. */
+  files: Record<string, string>;
+  /** With `include_assets`: attachments added or modified, as `{ target: base64 }`. */
+  assets?: Record<string, string>;
+  /** Every attachment of the site. */
+  attachments: AttachmentPlanEntry[];
+  /** Pages added or modified. */
+  pages: PageOutput[];
+  /** Every warning of the build. */
+  warnings: string[];
+  multi_page: boolean;
+  root_slug: string | null;
+  /** Output paths added or modified (the keys of `files`). */
+  changed_files: string[];
+  /** Output paths that existed after the previous `rebuild()` but no longer do. */
+  removed_files: string[];
+  /** Pages whose Markdown was re-rendered by this rebuild. */
+  rendered_pages: number;
+  /** Pages whose previous render was reused. */
+  reused_pages: number;
+  /** Vault files whose frontmatter was parsed again. */
+  parsed_files: number;
+}
+
+export interface Diagnostic {
+  /** Stable identifier, e.g. `missing-field`, `invalid-field`, `broken-link` or `reachable`. */
+  code: string;
+  message: string;
+  /** Entry the finding belongs to (`null` for vault-wide ones such as a missing `contents` target). */
+  path: string | null;
+}
+
+export interface ValidationResult {
+  /** True when the vault produced no warnings. */
+  valid: boolean;
+  warnings: string[];
+  /** The same warnings, classified and attributed to their entry. */
+  diagnostics: Diagnostic[];
+  page_count: number;
+}
+
+export type BuildEvent =
+  | { kind: "phase"; phase: "collect" | "link" | "render" }
+  | { kind: "page"; done: number; total: number; source_path: string }
+  | { kind: "warning"; message: string };
+
+/** Called synchronously during a build; throwing aborts it with `CALLBACK_FAILED`. */
+export type BuildEventCallback = (event: BuildEvent) => void;
+
+export type DiaryxErrorCode =
+  | "INVALID_INPUT"
+  | "BUILD_FAILED"
+  | "STRICT_WARNINGS"
+  | "CALLBACK_FAILED"
+  | "SERIALIZE_FAILED";
+
+/**
+ * Error thrown by every export; `warnings` is set for `STRICT_WARNINGS`, and `cause` holds the
+ * original exception for `CALLBACK_FAILED`.
+ */
+export interface DiaryxError extends Error {
+  code: DiaryxErrorCode;
+  warnings?: string[];
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "BuildInput")]
+    pub type JsBuildInput;
+    #[wasm_bindgen(typescript_type = "BuildOutput")]
+    pub type JsBuildOutput;
+    #[wasm_bindgen(typescript_type = "RebuildOutput")]
+    pub type JsRebuildOutput;
+    #[wasm_bindgen(typescript_type = "BuildEventCallback")]
+    pub type JsBuildEventCallback;
+    #[wasm_bindgen(typescript_type = "ValidateInput")]
+    pub type JsValidateInput;
+    #[wasm_bindgen(typescript_type = "ValidationResult")]
+    pub type JsValidationResult;
+    #[wasm_bindgen(typescript_type = "ParsedDiaryx")]
+    pub type JsParsedDiaryx;
+}
+
+/// Stable error codes exposed to JS as `error.code`.
+#[derive(Clone, Copy)]
+enum ErrorCode {
+    InvalidInput,
+    BuildFailed,
+    StrictWarnings,
+    CallbackFailed,
+    SerializeFailed,
+}
+
+impl ErrorCode {
+    fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidInput => "INVALID_INPUT",
+            ErrorCode::BuildFailed => "BUILD_FAILED",
+            ErrorCode::StrictWarnings => "STRICT_WARNINGS",
+            ErrorCode::CallbackFailed => "CALLBACK_FAILED",
+            ErrorCode::SerializeFailed => "SERIALIZE_FAILED",
+        }
+    }
+}
+
+fn js_error(code: ErrorCode, message: &str) -> js_sys::Error {
+    let err = js_sys::Error::new(message);
+    let _ = js_sys::Reflect::set(&err, &"code".into(), &code.as_str().into());
+    err
+}
+
+/// Build settings shared by `build_diaryx` and `DiaryxSession`.
+#[derive(Deserialize, Clone)]
+struct BuildSettings {
+    entry: String,
+    #[serde(default)]
+    include_nonpublic: bool,
+    #[serde(default)]
+    audience: Option<String>,
+    #[serde(default)]
+    layout: OutputLayout,
+    #[serde(default)]
+    flat: bool,
+    #[serde(default)]
+    strict: bool,
+    #[serde(default = "default_true")]
+    rewrite_links: bool,
+    #[serde(default)]
+    large_body_bytes: Option<usize>,
+    #[serde(default)]
+    smart_punctuation: bool,
+    #[serde(default)]
+    figures: bool,
+    #[serde(default)]
+    pdf_previews: bool,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    since: Option<String>,
+    #[serde(default)]
+    until: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    drafts: bool,
+    #[serde(default)]
+    future: bool,
+    #[serde(default)]
+    as_root: bool,
+    #[serde(default = "default_true")]
+    include_css: bool,
+    #[serde(default)]
+    emit_json: bool,
+    #[serde(default)]
+    include_assets: bool,
+}
+
+impl BuildSettings {
+    fn core_options(&self) -> CoreBuildOptions {
+        CoreBuildOptions {
+            include_nonpublic: self.include_nonpublic,
+            audience: self.audience.clone(),
+            seal_private: false,
+            layout: self.layout,
+            flat: self.flat,
+            strict: self.strict,
+            rewrite_links: self.rewrite_links,
+            large_body_bytes: self.large_body_bytes,
+            smart_punctuation: self.smart_punctuation,
+            figures: self.figures,
+            pdf_previews: self.pdf_previews,
+            exclude: self.exclude.clone(),
+            since: self.since.clone(),
+            until: self.until.clone(),
+            tags: self.tags.clone(),
+            now: time::OffsetDateTime::from_unix_timestamp_nanos(
+                (js_sys::Date::now() * 1_000_000.0) as i128,
+            )
+            .ok(),
+            drafts: self.drafts,
+            future: self.future,
+            as_root: self.as_root,
+            env: None,
+            infer_dates: false,
+        }
+    }
+
+    fn site_options(&self) -> site::SiteOptions {
+        site::SiteOptions {
+            include_css: self.include_css,
+            emit_json: self.emit_json,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct BuildInput {
+    files: HashMap<String, String>,
+    #[serde(default)]
+    assets: HashMap<String, String>,
+    #[serde(flatten)]
+    settings: BuildSettings,
+}
+
+impl BuildInput {
+    /// Split into the vault (text and decoded binary files) and the settings.
+    fn into_parts(self) -> Result<(InMemoryFs, BuildSettings), js_sys::Error> {
+        let mut fs = InMemoryFs::new(self.files);
+        for (path, encoded) in self.assets {
+            let bytes = BASE64.decode(encoded.trim()).map_err(|e| {
+                js_error(
+                    ErrorCode::InvalidInput,
+                    &format!("Invalid input: asset {path} is not valid base64: {e}"),
+                )
+            })?;
+            fs.insert_bytes(&path, bytes);
+        }
+        Ok((fs, self.settings))
+    }
+}
+
+#[derive(Deserialize)]
+struct ValidateInput {
+    entry: String,
+    files: HashMap<String, String>,
+    #[serde(default)]
+    include_nonpublic: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize)]
+struct BuildOutput {
+    files: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assets: Option<BTreeMap<String, String>>,
+    attachments: Vec<AttachmentPlanEntry>,
+    pages: Vec<PageOutput>,
+    warnings: Vec<Warning>,
+    multi_page: bool,
+    root_slug: Option<String>,
+}
+
+impl BuildOutput {
+    fn new(artifacts: BuildArtifacts, settings: &BuildSettings, fs: &InMemoryFs) -> Self {
+        let assets = settings.include_assets.then(|| {
+            artifacts
+                .attachments
+                .iter()
+                .filter_map(|a| Some((a.target.clone(), BASE64.encode(fs.read(&a.source)?))))
+                .collect()
+        });
+        Self {
+            files: site::site_files(&artifacts, &settings.site_options()),
+            assets,
+            attachments: artifacts.attachments,
+            pages: artifacts.pages,
+            warnings: artifacts.warnings,
+            multi_page: artifacts.multi_page,
+            root_slug: artifacts.root_slug,
+        }
+    }
+}
+
+/// The parts of a [`BuildOutput`] that changed since the previous rebuild, plus the site-wide
+/// lists.
+#[derive(Serialize)]
+struct RebuildOutput {
+    files: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assets: Option<BTreeMap<String, String>>,
+    attachments: Vec<AttachmentPlanEntry>,
+    pages: Vec<PageOutput>,
+    warnings: Vec<Warning>,
+    multi_page: bool,
+    root_slug: Option<String>,
+    changed_files: Vec<String>,
+    removed_files: Vec<String>,
+    rendered_pages: usize,
+    reused_pages: usize,
+    parsed_files: usize,
+}
+
+fn from_js<T: for<'de> Deserialize<'de>>(value: JsValue) -> Result<T, js_sys::Error> {
+    serde_wasm_bindgen::from_value(value)
+        .map_err(|e| js_error(ErrorCode::InvalidInput, &format!("Invalid input: {e}")))
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, js_sys::Error> {
+    // json_compatible: maps become plain objects (not `Map`), matching the declared types.
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| js_error(ErrorCode::SerializeFailed, &format!("Serialize error: {e}")))
+}
+
+fn build_error(e: anyhow::Error) -> js_sys::Error {
+    js_error(ErrorCode::BuildFailed, &format!("Build error: {e:#}"))
+}
+
+/// Forwards core `BuildEvent`s to an optional JS callback, remembering what the callback threw so
+/// the build error can carry it as `cause`.
+struct EventForwarder {
+    callback: Option<js_sys::Function>,
+    thrown: Option<JsValue>,
+}
+
+impl EventForwarder {
+    fn new(callback: Option<JsBuildEventCallback>) -> Self {
+        Self {
+            callback: callback.map(JsCast::unchecked_into),
+            thrown: None,
+        }
+    }
+
+    fn forward(&mut self, event: &BuildEvent<'_>) -> Result<()> {
+        let Some(callback) = &self.callback else {
+            return Ok(());
+        };
+        let value = to_js(event).map_err(|e| anyhow!(String::from(e.message())))?;
+        if let Err(thrown) = callback.call1(&JsValue::NULL, &value) {
+            self.thrown = Some(thrown);
+            return Err(anyhow!("event callback threw"));
+        }
+        Ok(())
+    }
+
+    fn error(self, e: anyhow::Error) -> js_sys::Error {
+        match self.thrown {
+            Some(thrown) => {
+                let err = js_error(
+                    ErrorCode::CallbackFailed,
+                    "Build aborted: event callback threw",
+                );
+                err.set_cause(&thrown);
+                err
+            }
+            None => build_error(e),
+        }
+    }
+}
+
+/// Turn warnings into a `STRICT_WARNINGS` error when strict mode is on.
+fn enforce_strict(settings: &BuildSettings, warnings: &[Warning]) -> Result<(), js_sys::Error> {
+    if !settings.strict || warnings.is_empty() {
+        return Ok(());
+    }
+    let err = js_error(
+        ErrorCode::StrictWarnings,
+        &format!(
+            "Strict mode: build failed due to {} warning(s)",
+            warnings.len()
+        ),
+    );
+    let _ = js_sys::Reflect::set(&err, &"warnings".into(), &to_js(&warnings)?);
+    Err(err)
+}
+
+/// Build a site from an in-memory vault. `on_event` (optional) receives progress and warnings.
+#[wasm_bindgen]
+pub fn build_diaryx(
+    input: JsBuildInput,
+    on_event: Option<JsBuildEventCallback>,
+) -> Result<JsBuildOutput, js_sys::Error> {
+    let (fs, settings) = from_js::<BuildInput>(input.into())?.into_parts()?;
+    let mut events = EventForwarder::new(on_event);
+    let artifacts = match build_site_observed(
+        &settings.entry,
+        settings.core_options(),
+        &fs,
+        None,
+        |e: &BuildEvent<'_>| events.forward(e),
+    ) {
+        Ok(artifacts) => artifacts,
+        Err(e) => return Err(events.error(e)),
+    };
+    enforce_strict(&settings, &artifacts.warnings)?;
+    Ok(to_js(&BuildOutput::new(artifacts, &settings, &fs))?.unchecked_into())
+}
+
+/// Check the frontmatter and link graph of an in-memory vault without rendering any page, cheap
+/// enough to run on every keystroke; warnings are returned, not thrown.
+#[wasm_bindgen]
+pub fn validate_diaryx(input: JsValidateInput) -> Result<JsValidationResult, js_sys::Error> {
+    let input: ValidateInput = from_js(input.into())?;
+    let fs = InMemoryFs::new(input.files);
+    let out = validate_graph(&input.entry, input.include_nonpublic, &fs).map_err(build_error)?;
+    Ok(to_js(&out)?.unchecked_into())
+}
+
+/// Parse and render a single file for a live preview, without a vault. `path` (default
+/// `untitled.md`) names the file, for its slug and in warnings.
+#[wasm_bindgen]
+pub fn parse_diaryx(
+    content: String,
+    path: Option<String>,
+) -> Result<JsParsedDiaryx, js_sys::Error> {
+    let path = path.unwrap_or_else(|| "untitled.md".to_string());
+    let doc = render_document(&path, content).map_err(build_error)?;
+    Ok(to_js(&doc)?.unchecked_into())
+}
+
+/// Stateful build handle for editors: keeps the vault and previous parses and renders in memory
+/// so a `rebuild()` after a few `update_file` / `remove_file` calls only re-renders edited pages,
+/// and returns only what changed.
+#[wasm_bindgen]
+pub struct DiaryxSession {
+    settings: BuildSettings,
+    fs: InMemoryFs,
+    cache: RenderCache,
+    last_files: BTreeMap<String, String>,
+    /// Hash of each returned page and asset, by page id / asset target.
+    last_pages: HashMap<String, u64>,
+    last_assets: HashMap<String, u64>,
+}
+
+#[wasm_bindgen]
+impl DiaryxSession {
+    /// Create a session from the same input `build_diaryx` takes. Nothing is built until
+    /// `rebuild()` is called.
+    #[wasm_bindgen(constructor)]
+    pub fn new(input: JsBuildInput) -> Result<DiaryxSession, js_sys::Error> {
+        let (fs, settings) = from_js::<BuildInput>(input.into())?.into_parts()?;
+        Ok(Self {
+            settings,
+            fs,
+            cache: RenderCache::new(),
+            last_files: BTreeMap::new(),
+            last_pages: HashMap::new(),
+            last_assets: HashMap::new(),
+        })
+    }
+
+    /// Add or replace a vault file.
+    pub fn update_file(&mut self, path: &str, content: String) {
+        self.fs.insert(path, content);
+    }
+
+    /// Add or replace a binary vault file (an image, a PDF).
+    pub fn update_asset(&mut self, path: &str, content: &[u8]) {
+        self.fs.insert_bytes(path, content.to_vec());
+    }
+
+    /// Remove a vault file; returns false when it was not present.
+    pub fn remove_file(&mut self, path: &str) -> bool {
+        self.fs.remove(path)
+    }
+
+    /// Rebuild the site. Only output files, pages and assets whose content changed since the
+    /// previous rebuild are returned (all of them on the first call); `changed_files` and
+    /// `removed_files` list the output paths. `on_event` (optional) receives progress and warnings.
+    pub fn rebuild(
+        &mut self,
+        on_event: Option<JsBuildEventCallback>,
+    ) -> Result<JsRebuildOutput, js_sys::Error> {
+        let mut events = EventForwarder::new(on_event);
+        let artifacts = match build_site_observed(
+            &self.settings.entry,
+            self.settings.core_options(),
+            &self.fs,
+            Some(&mut self.cache),
+            |e: &BuildEvent<'_>| events.forward(e),
+        ) {
+            Ok(artifacts) => artifacts,
+            Err(e) => return Err(events.error(e)),
+        };
+        enforce_strict(&self.settings, &artifacts.warnings)?;
+        let build = BuildOutput::new(artifacts, &self.settings, &self.fs);
+
+        let removed_files = self
+            .last_files
+            .keys()
+            .filter(|path| !build.files.contains_key(*path))
+            .cloned()
+            .collect();
+        let files: BTreeMap<String, String> = build
+            .files
+            .iter()
+            .filter(|(path, content)| self.last_files.get(*path) != Some(*content))
+            .map(|(path, content)| (path.clone(), content.clone()))
+            .collect();
+        self.last_files = build.files;
+        let pages = changed(&mut self.last_pages, build.pages, |page| {
+            let json = serde_json::to_string(page).unwrap_or_default();
+            (page.id.clone(), hash_of(&json))
+        });
+        let assets = build.assets.map(|assets| {
+            changed(&mut self.last_assets, assets, |(target, data)| {
+                (target.clone(), hash_of(data))
+            })
+            .into_iter()
+            .collect()
+        });
+        let stats = self.cache.stats();
+        let out = RebuildOutput {
+            changed_files: files.keys().cloned().collect(),
+            files,
+            assets,
+            attachments: build.attachments,
+            pages,
+            warnings: build.warnings,
+            multi_page: build.multi_page,
+            root_slug: build.root_slug,
+            removed_files,
+            rendered_pages: stats.rendered,
+            reused_pages: stats.reused,
+            parsed_files: stats.parsed,
+        };
+        Ok(to_js(&out)?.unchecked_into())
+    }
+}
+
+/// The `items` whose content hash differs from what `seen` recorded under their key (new ones
+/// included); `seen` is replaced by the hashes of all of `items`.
+fn changed<T>(
+    seen: &mut HashMap<String, u64>,
+    items: impl IntoIterator<Item = T>,
+    key_hash: impl Fn(&T) -> (String, u64),
+) -> Vec<T> {
+    let mut now = HashMap::new();
+    let mut out = Vec::new();
+    for item in items {
+        let (key, hash) = key_hash(&item);
+        if seen.get(&key) != Some(&hash) {
+            out.push(item);
+        }
+        now.insert(key, hash);
+    }
+    *seen = now;
+    out
+}
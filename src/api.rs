@@ -0,0 +1,379 @@
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use diaryx_core::{BuildArtifacts, CoreBuildOptions, PageOutput, RenderCache, site};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{Value, json};
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::build::RealFs;
+
+/// Read-only query server over a vault: `api` (localhost HTTP) and `api --mcp` (Model Context
+/// Protocol over stdio).
+///
+/// Both transports answer the same four queries (list entries, get a rendered page, search,
+/// graph) from a core build of the vault. The vault is rebuilt on every query so answers follow
+/// edits; a [`RenderCache`] keeps that cheap by re-rendering only bodies that changed. Only public
+/// pages are exposed unless `--include-nonpublic` is given.
+///
+/// The HTTP server binds to 127.0.0.1, sends no CORS headers and rejects requests whose `Host`
+/// is not local, so web pages open in a browser cannot read the journal.
+pub fn run_api(input: &Path, port: u16, include_nonpublic: bool, mcp: bool) -> Result<()> {
+    let mut journal = Journal {
+        entry: input
+            .to_str()
+            .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?
+            .to_string(),
+        include_nonpublic,
+        cache: RenderCache::new(),
+    };
+    // Fail fast on an unusable vault rather than on the first query.
+    let pages = journal.build()?.pages.len();
+    if mcp {
+        serve_mcp(&mut journal)
+    } else {
+        serve_http(&mut journal, port, pages)
+    }
+}
+
+struct Journal {
+    entry: String,
+    include_nonpublic: bool,
+    cache: RenderCache,
+}
+
+impl Journal {
+    fn build(&mut self) -> Result<BuildArtifacts> {
+        let opts = CoreBuildOptions {
+            include_nonpublic: self.include_nonpublic,
+            rewrite_links: true,
+            ..CoreBuildOptions::default()
+        };
+        diaryx_core::build_site_cached(&self.entry, opts, &RealFs, &mut self.cache)
+            .with_context(|| "Core build failed")
+    }
+}
+
+// --- Queries ----------------------------------------------------------------------------------
+
+/// Every page's metadata (no HTML).
+fn list_entries(site: &BuildArtifacts) -> Value {
+    Value::Array(site.pages.iter().map(site::page_model).collect())
+}
+
+/// One page with its rendered HTML, by slug.
+fn get_page(site: &BuildArtifacts, id: &str) -> Option<Value> {
+    let page = site.pages.iter().find(|p| p.id == id)?;
+    let mut model = site::page_model(page);
+    model["html"] = json!(page.html);
+    model["metadata_html"] = json!(page.metadata_html);
+    Some(model)
+}
+
+/// Pages containing every whitespace-separated term (case-insensitive) in their title or text,
+/// best matches first. Title hits outweigh body hits.
+fn search(site: &BuildArtifacts, query: &str, limit: usize) -> Value {
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    if terms.is_empty() {
+        return json!([]);
+    }
+    let mut hits: Vec<(usize, &PageOutput, String)> = Vec::new();
+    for page in site.pages.iter().filter(|p| !site::is_noindex(p)) {
+        let title = page.title.to_lowercase();
+        let text = plain_text(&page.html);
+        let lower = text.to_lowercase();
+        if !terms
+            .iter()
+            .all(|t| title.contains(t.as_str()) || lower.contains(t.as_str()))
+        {
+            continue;
+        }
+        let score = terms
+            .iter()
+            .map(|t| title.matches(t.as_str()).count() * 10 + lower.matches(t.as_str()).count())
+            .sum();
+        hits.push((score, page, snippet(&text, &lower, &terms[0])));
+    }
+    hits.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.id.cmp(&b.1.id)));
+    Value::Array(
+        hits.into_iter()
+            .take(limit)
+            .map(|(score, page, snippet)| {
+                json!({ "id": page.id, "title": page.title, "score": score, "snippet": snippet })
+            })
+            .collect(),
+    )
+}
+
+/// Page graph: nodes plus parent -> child edges from `contents`.
+fn graph(site: &BuildArtifacts) -> Value {
+    let nodes: Vec<Value> = site
+        .pages
+        .iter()
+        .map(|p| json!({ "id": p.id, "title": p.title, "is_index": p.is_index }))
+        .collect();
+    let edges: Vec<Value> = site
+        .pages
+        .iter()
+        .flat_map(|p| {
+            p.children
+                .iter()
+                .map(move |child| json!({ "from": p.id, "to": child }))
+        })
+        .collect();
+    json!({ "root": site.root_slug, "nodes": nodes, "edges": edges })
+}
+
+fn plain_text(html: &str) -> String {
+    static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+    let text = TAG.replace_all(html, " ");
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// About 160 characters of `text` around the first occurrence of `term`.
+fn snippet(text: &str, lower: &str, term: &str) -> String {
+    // Lowercasing can change byte lengths; fall back to the start when offsets disagree.
+    let at = lower
+        .find(term)
+        .filter(|_| lower.len() == text.len())
+        .unwrap_or(0);
+    let mut start = at.saturating_sub(60);
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (at + 100).min(text.len());
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    out.push_str(&text[start..end]);
+    if end < text.len() {
+        out.push('…');
+    }
+    out
+}
+
+// --- HTTP -------------------------------------------------------------------------------------
+
+fn serve_http(journal: &mut Journal, port: u16, pages: usize) -> Result<()> {
+    let addr = format!("127.0.0.1:{port}");
+    let server = Server::http(&addr).map_err(|e| anyhow!("Failed to bind {addr}: {e}"))?;
+    eprintln!("[api] serving {pages} page(s) on http://{addr}/api (Ctrl+C to stop)");
+    let json_type = Header::from_bytes("Content-Type", "application/json; charset=utf-8")
+        .map_err(|_| anyhow!("Invalid header"))?;
+    for request in server.incoming_requests() {
+        let local_host = request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("Host"))
+            .is_none_or(|h| is_local_host(h.value.as_str()));
+        let (status, body) = if !local_host {
+            (403, json!({ "error": "Forbidden host" }))
+        } else if *request.method() != Method::Get {
+            (405, json!({ "error": "Only GET is supported" }))
+        } else {
+            route(journal, request.url())
+        };
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(json_type.clone());
+        if let Err(e) = request.respond(response) {
+            eprintln!("[api] failed to send response: {e}");
+        }
+    }
+    Ok(())
+}
+
+fn route(journal: &mut Journal, url: &str) -> (u16, Value) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let endpoint = path.trim_end_matches('/');
+    if !matches!(endpoint, "/api/entries" | "/api/search" | "/api/graph")
+        && !endpoint.starts_with("/api/pages/")
+    {
+        return (404, json!({ "error": format!("No such endpoint: {path}") }));
+    }
+    let site = match journal.build() {
+        Ok(site) => site,
+        Err(e) => return (500, json!({ "error": format!("{e:#}") })),
+    };
+    match endpoint {
+        "/api/entries" => (200, list_entries(&site)),
+        "/api/graph" => (200, graph(&site)),
+        "/api/search" => {
+            let q = query_param(query, "q").unwrap_or_default();
+            let limit = query_param(query, "limit")
+                .and_then(|l| l.parse().ok())
+                .unwrap_or(20);
+            (200, search(&site, &q, limit))
+        }
+        _ => {
+            let id = percent_decode(&endpoint["/api/pages/".len()..]);
+            match get_page(&site, &id) {
+                Some(page) => (200, page),
+                None => (404, json!({ "error": format!("No page with id: {id}") })),
+            }
+        }
+    }
+}
+
+fn is_local_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    matches!(name, "127.0.0.1" | "localhost" | "[::1]")
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| percent_decode(&v.replace('+', " ")))
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// --- MCP --------------------------------------------------------------------------------------
+
+/// Minimal MCP server: newline-delimited JSON-RPC on stdin/stdout exposing the queries as tools.
+fn serve_mcp(journal: &mut Journal) -> Result<()> {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    for line in stdin.lock().lines() {
+        let line = line.context("Failed to read from stdin")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<Value>(&line) {
+            Ok(msg) => handle_mcp(journal, &msg),
+            Err(e) => Some(rpc_error(Value::Null, -32700, &format!("Parse error: {e}"))),
+        };
+        if let Some(reply) = reply {
+            writeln!(stdout, "{reply}")?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Reply to one JSON-RPC message; notifications (no `id`) get none.
+fn handle_mcp(journal: &mut Journal, msg: &Value) -> Option<Value> {
+    let id = msg.get("id")?.clone();
+    let params = msg.get("params").cloned().unwrap_or(Value::Null);
+    let result = match msg["method"].as_str().unwrap_or_default() {
+        "initialize" => json!({
+            "protocolVersion": params["protocolVersion"].as_str().unwrap_or("2024-11-05"),
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "diaryx", "version": env!("CARGO_PKG_VERSION") },
+        }),
+        "ping" => json!({}),
+        "tools/list" => json!({ "tools": mcp_tools() }),
+        "tools/call" => call_tool(journal, &params),
+        other => return Some(rpc_error(id, -32601, &format!("Method not found: {other}"))),
+    };
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+fn mcp_tools() -> Value {
+    json!([
+        {
+            "name": "list_entries",
+            "description": "List every journal page with its metadata (title, parents, children, frontmatter).",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+        {
+            "name": "get_page",
+            "description": "Get one page, including its rendered HTML, by id (slug).",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "id": { "type": "string" } },
+                "required": ["id"],
+            },
+        },
+        {
+            "name": "search",
+            "description": "Full-text search over page titles and bodies; every term must match.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "limit": { "type": "integer", "default": 20 },
+                },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "graph",
+            "description": "Page graph: nodes and parent -> child edges from `contents`.",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+    ])
+}
+
+/// Run a tool; failures are reported in the tool result (`isError`), as MCP expects.
+fn call_tool(journal: &mut Journal, params: &Value) -> Value {
+    let args = &params["arguments"];
+    let output = journal.build().and_then(|site| {
+        Ok(match params["name"].as_str().unwrap_or_default() {
+            "list_entries" => list_entries(&site),
+            "graph" => graph(&site),
+            "search" => search(
+                &site,
+                args["query"].as_str().unwrap_or_default(),
+                args["limit"].as_u64().map_or(20, |n| n as usize),
+            ),
+            "get_page" => {
+                let id = args["id"].as_str().unwrap_or_default();
+                get_page(&site, id).ok_or_else(|| anyhow!("No page with id: {id}"))?
+            }
+            other => return Err(anyhow!("Unknown tool: {other}")),
+        })
+    });
+    match output {
+        Ok(value) => json!({
+            "content": [{ "type": "text", "text": value.to_string() }],
+            "isError": false,
+        }),
+        Err(e) => json!({
+            "content": [{ "type": "text", "text": format!("{e:#}") }],
+            "isError": true,
+        }),
+    }
+}
+
+fn rpc_error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
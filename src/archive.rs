@@ -0,0 +1,405 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// Minimal zip writer for the archives the CLI produces (reader bundles, snapshots).
+///
+/// Entries are stored uncompressed: archives stay readable by any unzip tool without pulling in
+/// a compression dependency, and most of their bulk (HTML, images) is either small or already
+/// compressed.
+pub struct ZipWriter<W: Write> {
+    out: W,
+    offset: u32,
+    central: Vec<u8>,
+    count: u16,
+    /// MS-DOS time and date stamped on every entry.
+    dos_time: u16,
+    dos_date: u16,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(out: W) -> Self {
+        let now = OffsetDateTime::now_utc();
+        ZipWriter {
+            out,
+            offset: 0,
+            central: Vec::new(),
+            count: 0,
+            dos_time: (u16::from(now.hour()) << 11)
+                | (u16::from(now.minute()) << 5)
+                | u16::from(now.second() / 2),
+            dos_date: ((now.year().clamp(1980, 2107) - 1980) as u16) << 9
+                | (u16::from(u8::from(now.month())) << 5)
+                | u16::from(now.day()),
+        }
+    }
+
+    /// Add a file at `name` (forward slashes, relative).
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let crc = crc32(data);
+        let size = u32::try_from(data.len()).context("File too large for a zip archive")?;
+        let name_len = name.len() as u16;
+        // Version 2.0, UTF-8 names (bit 11), stored.
+        let common = |buf: &mut Vec<u8>| {
+            buf.extend(20u16.to_le_bytes());
+            buf.extend(0x0800u16.to_le_bytes());
+            buf.extend(0u16.to_le_bytes());
+            buf.extend(self.dos_time.to_le_bytes());
+            buf.extend(self.dos_date.to_le_bytes());
+            buf.extend(crc.to_le_bytes());
+            buf.extend(size.to_le_bytes());
+            buf.extend(size.to_le_bytes());
+            buf.extend(name_len.to_le_bytes());
+            buf.extend(0u16.to_le_bytes());
+        };
+
+        let mut local = 0x0403_4b50u32.to_le_bytes().to_vec();
+        common(&mut local);
+        local.extend(name.as_bytes());
+        self.out.write_all(&local)?;
+        self.out.write_all(data)?;
+
+        let mut central = 0x0201_4b50u32.to_le_bytes().to_vec();
+        central.extend(20u16.to_le_bytes()); // made by
+        common(&mut central);
+        central.extend([0u8; 10]); // comment length, disk, internal and external attributes
+        central.extend(self.offset.to_le_bytes());
+        central.extend(name.as_bytes());
+        self.central.extend(central);
+
+        self.offset = self
+            .offset
+            .checked_add(local.len() as u32 + size)
+            .context("Archive too large for a zip file")?;
+        self.count = self
+            .count
+            .checked_add(1)
+            .context("Too many files for a zip archive")?;
+        Ok(())
+    }
+
+    /// Write the central directory and return the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.out.write_all(&self.central)?;
+        let mut end = 0x0605_4b50u32.to_le_bytes().to_vec();
+        end.extend([0u8; 4]); // disk numbers
+        end.extend(self.count.to_le_bytes());
+        end.extend(self.count.to_le_bytes());
+        end.extend((self.central.len() as u32).to_le_bytes());
+        end.extend(self.offset.to_le_bytes());
+        end.extend(0u16.to_le_bytes());
+        self.out.write_all(&end)?;
+        Ok(self.out)
+    }
+}
+
+/// Zip every file under `dir` into `zip_path`, with paths relative to `dir`.
+pub fn zip_dir(dir: &Path, zip_path: &Path) -> Result<()> {
+    let file = fs::File::create(zip_path)
+        .with_context(|| format!("Failed creating {}", zip_path.display()))?;
+    let mut zip = ZipWriter::new(std::io::BufWriter::new(file));
+    for path in files_under(dir)? {
+        let name = entry_name(path.strip_prefix(dir).unwrap_or(&path));
+        let data = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        zip.add(&name, &data)?;
+    }
+    zip.finish()?
+        .flush()
+        .with_context(|| format!("Failed writing {}", zip_path.display()))
+}
+
+/// Forward-slash archive name for a relative path.
+pub fn entry_name(rel: &Path) -> String {
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Every file under `dir`, recursively, in sorted order.
+pub fn files_under(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(current) = stack.pop() {
+        for entry in fs::read_dir(&current)
+            .with_context(|| format!("Failed reading {}", current.display()))?
+        {
+            let path = entry?.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// CRC-32 (IEEE), as used by zip and PNG.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| crc32_step(crc, byte))
+}
+
+/// One byte of the (unfinalized) CRC-32 register.
+fn crc32_step(mut crc: u32, byte: u8) -> u32 {
+    crc ^= u32::from(byte);
+    for _ in 0..8 {
+        crc = if crc & 1 == 1 {
+            (crc >> 1) ^ 0xedb8_8320
+        } else {
+            crc >> 1
+        };
+    }
+    crc
+}
+
+/// Decrypt a traditional PKWARE-encrypted entry (12-byte header, then the data); `None` when
+/// the header's check byte shows the password is wrong.
+fn zip_decrypt(raw: &[u8], password: &[u8], check: u8) -> Option<Vec<u8>> {
+    let mut keys = [0x1234_5678u32, 0x2345_6789, 0x3456_7890];
+    let update = |keys: &mut [u32; 3], byte: u8| {
+        keys[0] = crc32_step(keys[0], byte);
+        keys[1] = keys[1]
+            .wrapping_add(keys[0] & 0xff)
+            .wrapping_mul(134_775_813)
+            .wrapping_add(1);
+        keys[2] = crc32_step(keys[2], (keys[1] >> 24) as u8);
+    };
+    for &byte in password {
+        update(&mut keys, byte);
+    }
+    let mut plain: Vec<u8> = raw
+        .iter()
+        .map(|&byte| {
+            let temp = (keys[2] | 2) & 0xffff;
+            let plain = byte ^ ((temp.wrapping_mul(temp ^ 1) >> 8) as u8);
+            update(&mut keys, plain);
+            plain
+        })
+        .collect();
+    if plain.len() < 12 || plain[11] != check {
+        return None;
+    }
+    Some(plain.split_off(12))
+}
+
+/// One file in a snapshot or build manifest.
+#[derive(Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl ManifestFile {
+    pub fn new(path: String, data: &[u8]) -> Self {
+        ManifestFile {
+            path,
+            size: data.len() as u64,
+            sha256: sha256_hex(data),
+        }
+    }
+
+    /// Whether `data` is what this entry describes.
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.len() as u64 == self.size && sha256_hex(data) == self.sha256
+    }
+}
+
+/// Root hash of a manifest: SHA-256 of the sorted `"<sha256>  <path>\n"` lines (the
+/// `sha256sum` format), so one value pins every file.
+pub fn root_hash(files: &[ManifestFile]) -> String {
+    let mut lines: Vec<String> = files
+        .iter()
+        .map(|f| format!("{}  {}\n", f.sha256, f.path))
+        .collect();
+    lines.sort();
+    sha256_hex(lines.concat().as_bytes())
+}
+
+/// Most a zip archive may unpack to in memory (all entries together).
+const MAX_UNPACKED_TOTAL: u64 = 4 << 30;
+/// Most reserved up front for one entry; larger ones grow as they inflate.
+const MAX_PREALLOCATED: usize = 64 << 20;
+
+/// Files of a zip archive, in archive order: those written by [`ZipWriter`], and stored or
+/// deflated entries from other tools (vault exports, backups). Folder entries are skipped and
+/// each file's CRC is checked. Zip64 archives are not supported.
+pub fn read_zip(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    read_zip_with_password(bytes, None)
+}
+
+/// [`read_zip`] for archives whose entries may be password-protected with traditional PKWARE
+/// encryption (`zip -e`). Entries are decrypted in memory; AES-encrypted entries are not
+/// supported.
+pub fn read_zip_with_password(
+    bytes: &[u8],
+    password: Option<&str>,
+) -> Result<Vec<(String, Vec<u8>)>> {
+    let u16_at = |at: usize| -> Result<u16> {
+        let b = bytes.get(at..at + 2).context("Truncated zip archive")?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    };
+    let u32_at = |at: usize| -> Result<u32> {
+        let b = bytes.get(at..at + 4).context("Truncated zip archive")?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let end = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .find(|&at| bytes[at..].starts_with(&0x0605_4b50u32.to_le_bytes()))
+        .context("Not a zip archive (no end of central directory)")?;
+    let count = u16_at(end + 10)?;
+    let mut at = u32_at(end + 16)? as usize;
+
+    let mut files = Vec::with_capacity(usize::from(count));
+    let mut unpacked_total = 0u64;
+    for _ in 0..count {
+        if u32_at(at)? != 0x0201_4b50 {
+            anyhow::bail!("Corrupt zip central directory");
+        }
+        let flags = u16_at(at + 8)?;
+        let method = u16_at(at + 10)?;
+        let dos_time = u16_at(at + 12)?;
+        let crc = u32_at(at + 16)?;
+        let size = u32_at(at + 20)? as usize;
+        let unpacked_size = u32_at(at + 24)? as usize;
+        let name_len = usize::from(u16_at(at + 28)?);
+        let extra_len = usize::from(u16_at(at + 30)?);
+        let comment_len = usize::from(u16_at(at + 32)?);
+        let local = u32_at(at + 42)? as usize;
+        let name = bytes
+            .get(at + 46..at + 46 + name_len)
+            .context("Truncated zip archive")?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        at += 46 + name_len + extra_len + comment_len;
+        if name.ends_with('/') {
+            continue;
+        }
+        if u32_at(local)? != 0x0403_4b50 {
+            anyhow::bail!("{name}: corrupt local header");
+        }
+        let start =
+            local + 30 + usize::from(u16_at(local + 26)?) + usize::from(u16_at(local + 28)?);
+        let raw = bytes
+            .get(start..start + size)
+            .with_context(|| format!("{name}: truncated data"))?;
+        let decrypted;
+        let raw = if flags & 1 == 1 {
+            let password = password.with_context(|| {
+                format!("{name}: the archive is encrypted; a password is needed")
+            })?;
+            // The last header byte checks the password: the CRC's high byte, or the time's when
+            // the sizes follow the data (bit 3).
+            let check = if flags & 0x08 != 0 {
+                (dos_time >> 8) as u8
+            } else {
+                (crc >> 24) as u8
+            };
+            decrypted = zip_decrypt(raw, password.as_bytes(), check)
+                .with_context(|| format!("{name}: wrong password"))?;
+            &decrypted[..]
+        } else {
+            raw
+        };
+        // The recorded sizes are the archive's word only: cap what is reserved and inflated.
+        unpacked_total += unpacked_size as u64;
+        if unpacked_total > MAX_UNPACKED_TOTAL {
+            anyhow::bail!(
+                "Zip archive unpacks to more than {} MiB",
+                MAX_UNPACKED_TOTAL >> 20
+            );
+        }
+        let data = match method {
+            0 => raw.to_vec(),
+            8 => {
+                let mut data = Vec::with_capacity(unpacked_size.min(MAX_PREALLOCATED));
+                flate2::read::DeflateDecoder::new(raw)
+                    .take(unpacked_size as u64 + 1)
+                    .read_to_end(&mut data)
+                    .with_context(|| format!("{name}: corrupt deflate data"))?;
+                data
+            }
+            99 => anyhow::bail!("{name}: AES-encrypted zip entries are not supported"),
+            _ => anyhow::bail!("{name}: unsupported zip compression method {method}"),
+        };
+        if data.len() != unpacked_size {
+            anyhow::bail!("{name}: size does not match the zip directory");
+        }
+        if crc32(&data) != crc {
+            anyhow::bail!("{name}: CRC mismatch");
+        }
+        files.push((name, data));
+    }
+    Ok(files)
+}
+
+/// Standard base64 (RFC 4648, padded).
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// SHA-256 digest, as lowercase hex.
+pub fn sha256_hex(data: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_matches_the_standard_vectors() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn zip_entries_must_unpack_to_their_recorded_size() {
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.add("notes/a.md", b"hello world").unwrap();
+        let mut bytes = zip.finish().unwrap();
+        let files = read_zip(&bytes).unwrap();
+        assert_eq!(files, [("notes/a.md".to_string(), b"hello world".to_vec())]);
+
+        // Claim a smaller unpacked size in the central directory
+        let end = bytes.len() - 22;
+        let central = u32::from_le_bytes(bytes[end + 16..end + 20].try_into().unwrap()) as usize;
+        bytes[central + 24..central + 28].copy_from_slice(&5u32.to_le_bytes());
+        let err = read_zip(&bytes).unwrap_err();
+        assert!(err.to_string().contains("size does not match"), "{err}");
+
+        // ... or an enormous one
+        bytes[central + 24..central + 28].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read_zip(&bytes).is_err());
+    }
+}
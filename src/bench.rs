@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow};
+use diaryx_core::synth::{SynthVault, SynthVaultOptions, generate_vault};
+use diaryx_core::{CoreBuildOptions, build_site};
+
+use crate::build::RealFs;
+
+/// Developer tooling: synthetic vault generation and build throughput measurement.
+///
+/// `gen-vault` writes a deterministic synthetic vault to disk (useful for profiling the real
+/// `build` command), while `bench` generates one into a scratch directory and times repeated
+/// core builds over it through the real filesystem adapter.
+pub fn run_gen_vault(opts: &SynthVaultOptions, output: &Path) -> Result<()> {
+    let vault = generate_vault(opts);
+    write_vault(&vault, output)?;
+    println!(
+        "[diaryx] generated synthetic vault -> {} ({} files, root: {})",
+        output.display(),
+        vault.len(),
+        output.join(SynthVault::ROOT).display()
+    );
+    Ok(())
+}
+
+pub fn run_bench(opts: &SynthVaultOptions, iterations: usize, keep: bool) -> Result<()> {
+    let dir = scratch_dir();
+    let vault = generate_vault(opts);
+    write_vault(&vault, &dir)?;
+    let result = bench_vault(&dir, vault.len(), iterations.max(1));
+    if keep {
+        eprintln!("[bench] vault kept at {}", dir.display());
+    } else {
+        let _ = fs::remove_dir_all(&dir);
+    }
+    result
+}
+
+fn bench_vault(dir: &Path, file_count: usize, iterations: usize) -> Result<()> {
+    let entry = dir
+        .join(SynthVault::ROOT)
+        .canonicalize()
+        .context("Failed to canonicalize synthetic root")?;
+    let entry_str = entry
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 scratch path"))?
+        .to_string();
+
+    let mut samples: Vec<Duration> = Vec::with_capacity(iterations);
+    let mut pages = 0usize;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let artifacts = build_site(
+            &entry_str,
+            CoreBuildOptions {
+                rewrite_links: true,
+                ..Default::default()
+            },
+            &RealFs,
+        )
+        .context("Core build failed during benchmark")?;
+        samples.push(start.elapsed());
+        pages = artifacts.pages.len();
+    }
+
+    let total: Duration = samples.iter().sum();
+    let mean = total / iterations as u32;
+    let min = samples.iter().min().copied().unwrap_or_default();
+    let max = samples.iter().max().copied().unwrap_or_default();
+    let throughput = if mean.as_secs_f64() > 0.0 {
+        pages as f64 / mean.as_secs_f64()
+    } else {
+        f64::INFINITY
+    };
+    println!(
+        "[bench] files: {}  pages: {}  iterations: {}",
+        file_count, pages, iterations
+    );
+    println!(
+        "[bench] min {:.2?}  mean {:.2?}  max {:.2?}  ({:.0} pages/s)",
+        min, mean, max, throughput
+    );
+    Ok(())
+}
+
+fn write_vault(vault: &SynthVault, dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed creating {}", dir.display()))?;
+    for (rel, content) in &vault.files {
+        let path = dir.join(rel);
+        fs::write(&path, content).with_context(|| format!("Failed writing {}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn scratch_dir() -> PathBuf {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    std::env::temp_dir().join(format!("diaryx-bench-{}-{}", std::process::id(), nanos))
+}
@@ -0,0 +1,269 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use diaryx_core::Warning;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::archive::{entry_name, files_under};
+
+/// Theme colors checked against each other: (text, background). Pairs whose variables a
+/// stylesheet does not define as plain colors are skipped.
+const CONTRAST_PAIRS: &[(&str, &str)] = &[
+    ("fg", "bg"),
+    ("fg", "bg-alt"),
+    ("fg", "code-bg"),
+    ("fg-muted", "bg"),
+    ("fg-muted", "bg-alt"),
+    ("fg-subtle", "bg"),
+    ("accent", "bg"),
+    ("accent", "bg-alt"),
+    ("accent-hover", "bg"),
+    ("accent", "accent-subtle"),
+    ("danger", "danger-bg"),
+    ("success", "success-bg"),
+    ("info", "info-bg"),
+    ("warning", "warning-bg"),
+];
+
+/// WCAG AA minimum contrast ratio for body text.
+const MIN_CONTRAST: f64 = 4.5;
+
+/// `build --check-a11y`: check every page and stylesheet in `output` for basic accessibility
+/// problems: images without alt text, links without text, documents without a language, and
+/// theme colors (the `:root` variables, light and dark) below the WCAG AA contrast ratio.
+pub(super) fn audit(output: &Path) -> Result<Vec<Warning>> {
+    let mut warnings = Vec::new();
+    for path in files_under(output)? {
+        let rel = entry_name(path.strip_prefix(output).unwrap_or(&path));
+        let lower = rel.to_ascii_lowercase();
+        if lower.ends_with(".html") || lower.ends_with(".htm") {
+            if let Ok(html) = fs::read_to_string(&path) {
+                warnings.extend(audit_page(&rel, &html));
+            }
+        } else if lower.ends_with(".css")
+            && let Ok(css) = fs::read_to_string(&path)
+        {
+            warnings.extend(audit_css(&rel, &css));
+        }
+    }
+    Ok(warnings)
+}
+
+fn audit_page(page: &str, html: &str) -> Vec<Warning> {
+    static SKIPPED: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?is)<!--.*?-->|<script\b.*?</script>|<style\b.*?</style>").unwrap()
+    });
+    static HTML: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<html\b[^>]*>").unwrap());
+    static IMG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<img\b[^>]*>").unwrap());
+    static LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<a\b([^>]*)>(.*?)</a\s*>").unwrap());
+    static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+    let html = SKIPPED.replace_all(html, "");
+    let mut warnings = Vec::new();
+    if let Some(tag) = HTML.find(&html)
+        && attr(tag.as_str(), "lang").is_none_or(|lang| lang.trim().is_empty())
+    {
+        warnings.push(Warning::new(
+            "a11y",
+            format!("Accessibility: {page} does not declare its language (<html lang>)"),
+        ));
+    }
+
+    let mut images = BTreeSet::new();
+    for tag in IMG.find_iter(&html) {
+        let tag = tag.as_str();
+        if attr(tag, "alt").is_none_or(|alt| alt.trim().is_empty())
+            && attr(tag, "role") != Some("presentation")
+        {
+            images.insert(attr(tag, "src").unwrap_or("(no src)").to_string());
+        }
+    }
+    for src in images {
+        warnings.push(Warning::new(
+            "a11y",
+            format!("Accessibility: image without alt text in {page}: {src}"),
+        ));
+    }
+
+    let mut links = BTreeSet::new();
+    for caps in LINK.captures_iter(&html) {
+        let (open, inner) = (&caps[1], &caps[2]);
+        let Some(href) = attr(open, "href") else {
+            continue;
+        };
+        let named = |tag: &str| {
+            ["aria-label", "aria-labelledby", "title"]
+                .iter()
+                .any(|name| attr(tag, name).is_some_and(|value| !value.trim().is_empty()))
+        };
+        let text = TAG.replace_all(inner, "").replace("&nbsp;", " ");
+        let labelled_inside = TAG.find_iter(inner).any(|tag| {
+            named(tag.as_str())
+                || attr(tag.as_str(), "alt").is_some_and(|alt| !alt.trim().is_empty())
+        });
+        if text.trim().is_empty()
+            && !named(open)
+            && !labelled_inside
+            && attr(open, "aria-hidden") != Some("true")
+        {
+            links.insert(href.to_string());
+        }
+    }
+    for href in links {
+        warnings.push(Warning::new(
+            "a11y",
+            format!("Accessibility: link without text in {page}: {href}"),
+        ));
+    }
+    warnings
+}
+
+/// Value of attribute `name` of an HTML tag (or of the attribute text of one); `Some("")` for
+/// an attribute without a value.
+fn attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    static ATTR: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r#"(?i)\s([a-z][a-z0-9:-]*)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+)))?"#)
+            .unwrap()
+    });
+    ATTR.captures_iter(tag)
+        .find(|caps| caps[1].eq_ignore_ascii_case(name))
+        .map(|caps| {
+            caps.get(2)
+                .or(caps.get(3))
+                .or(caps.get(4))
+                .map_or("", |value| value.as_str())
+        })
+}
+
+fn audit_css(file: &str, css: &str) -> Vec<Warning> {
+    let (light, dark) = theme_variables(css);
+    let mut warnings = Vec::new();
+    let mut schemes = vec![("light", light.clone())];
+    if !dark.is_empty() {
+        let mut merged = light;
+        merged.extend(dark);
+        schemes.push(("dark", merged));
+    }
+    for (scheme, vars) in &schemes {
+        for (fg, bg) in CONTRAST_PAIRS {
+            let (Some(fg_color), Some(bg_color)) = (color(vars, fg, 0), color(vars, bg, 0)) else {
+                continue;
+            };
+            let ratio = contrast(fg_color, bg_color);
+            if ratio < MIN_CONTRAST {
+                warnings.push(Warning::new("a11y", format!(
+                    "Accessibility: low contrast in {file} ({scheme} scheme): --{fg} on --{bg} is {ratio:.2}:1, below {MIN_CONTRAST}:1"
+                )));
+            }
+        }
+    }
+    warnings
+}
+
+/// Custom properties set on `:root`: at the top level (the light scheme) and within
+/// `@media (prefers-color-scheme: dark)` (overrides for the dark scheme).
+fn theme_variables(css: &str) -> (BTreeMap<String, String>, BTreeMap<String, String>) {
+    static COMMENT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)/\*.*?\*/").unwrap());
+    static VAR: Lazy<Regex> = Lazy::new(|| Regex::new(r"--([\w-]+)\s*:\s*([^;]+)").unwrap());
+
+    let css = COMMENT.replace_all(css, "");
+    let (mut light, mut dark) = (BTreeMap::new(), BTreeMap::new());
+    // (selector or at-rule, start of its block)
+    let mut open: Vec<(String, usize)> = Vec::new();
+    let mut prelude_start = 0;
+    for (i, c) in css.char_indices() {
+        match c {
+            '{' => {
+                open.push((css[prelude_start..i].trim().to_string(), i + 1));
+                prelude_start = i + 1;
+            }
+            '}' => {
+                if let Some((selector, start)) = open.pop()
+                    && selector == ":root"
+                {
+                    let target = match open.as_slice() {
+                        [] => Some(&mut light),
+                        [(media, _)] if is_dark_media(media) => Some(&mut dark),
+                        _ => None,
+                    };
+                    if let Some(target) = target {
+                        for caps in VAR.captures_iter(&css[start..i]) {
+                            target.insert(caps[1].to_string(), caps[2].trim().to_string());
+                        }
+                    }
+                }
+                prelude_start = i + 1;
+            }
+            ';' => prelude_start = i + 1,
+            _ => {}
+        }
+    }
+    (light, dark)
+}
+
+fn is_dark_media(prelude: &str) -> bool {
+    let compact: String = prelude.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.starts_with("@media") && compact.contains("prefers-color-scheme:dark")
+}
+
+/// The color variable `name` is set to, following `var()` references.
+fn color(vars: &BTreeMap<String, String>, name: &str, depth: usize) -> Option<[u8; 3]> {
+    static VAR_REF: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^var\(\s*--([\w-]+)\s*(?:,.*)?\)$").unwrap());
+    let value = vars.get(name)?;
+    match VAR_REF.captures(value) {
+        Some(caps) if depth < 8 => color(vars, &caps[1], depth + 1),
+        Some(_) => None,
+        None => parse_color(value),
+    }
+}
+
+/// `#rgb`, `#rrggbb`, `#rrggbbff`, `rgb(r, g, b)`, `white` or `black`; `None` for anything
+/// else, translucent colors included.
+fn parse_color(value: &str) -> Option<[u8; 3]> {
+    let value = value.trim().to_ascii_lowercase();
+    match value.as_str() {
+        "white" => return Some([255; 3]),
+        "black" => return Some([0; 3]),
+        _ => {}
+    }
+    if let Some(hex) = value.strip_prefix('#') {
+        let hex = match hex.len() {
+            3 => hex.chars().flat_map(|c| [c, c]).collect(),
+            6 => hex.to_string(),
+            8 if hex.ends_with("ff") => hex[..6].to_string(),
+            _ => return None,
+        };
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some([channel(0)?, channel(2)?, channel(4)?]);
+    }
+    let args = value
+        .strip_prefix("rgb(")
+        .and_then(|rest| rest.strip_suffix(')'))?;
+    let channels: Vec<u8> = args
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    channels.try_into().ok()
+}
+
+/// WCAG contrast ratio of two colors (1 to 21).
+fn contrast(a: [u8; 3], b: [u8; 3]) -> f64 {
+    let luminance = |rgb: [u8; 3]| {
+        let [r, g, b] = rgb.map(|channel| {
+            let c = f64::from(channel) / 255.0;
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        });
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    };
+    let (la, lb) = (luminance(a), luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
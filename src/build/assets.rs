@@ -0,0 +1,278 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use diaryx_core::{AttachmentPlanEntry, Warning};
+
+use super::storage::VaultFs;
+use crate::profile::format_bytes;
+
+/// Upper bound on copy worker threads (copying is IO-bound; more threads rarely help).
+const MAX_COPY_THREADS: usize = 8;
+
+/// Outcome of copying the attachment plan.
+#[derive(Debug, Default)]
+pub(super) struct CopyReport {
+    pub copied: usize,
+    pub skipped: usize,
+    pub removed_stale: usize,
+    pub warnings: Vec<Warning>,
+}
+
+enum CopyOutcome {
+    Copied,
+    Unchanged,
+    Failed(Warning),
+}
+
+/// Copy every planned attachment into `output`, in parallel on a bounded set of threads.
+///
+/// Destinations that already hold identical content (same size and mtime, or same bytes) are left
+/// alone, so repeated builds into the same output directory only copy what changed. Files under
+/// `assets/` that are no longer part of the plan are removed afterwards. Warnings and verbose log
+/// lines are reported in plan order regardless of which thread handled an entry. Attachments of a
+/// vault in object storage are downloaded from `vault` instead.
+pub(super) fn copy_attachments(
+    plan: &[AttachmentPlanEntry],
+    vault: &VaultFs,
+    output: &Path,
+    verbose: bool,
+) -> CopyReport {
+    let mut report = CopyReport::default();
+    let outcomes = run_parallel(plan, vault, output);
+
+    for (att, outcome) in plan.iter().zip(outcomes) {
+        match outcome {
+            CopyOutcome::Copied => {
+                report.copied += 1;
+                if verbose {
+                    eprintln!("[asset] {} -> {}", att.source, att.target);
+                }
+            }
+            CopyOutcome::Unchanged => {
+                report.skipped += 1;
+                if verbose {
+                    eprintln!("[asset] {} unchanged", att.target);
+                }
+            }
+            CopyOutcome::Failed(w) => report.warnings.push(w),
+        }
+    }
+
+    report.removed_stale = prune_stale(plan, output);
+    report
+}
+
+/// One line per MIME type (`image/png: 3 file(s), 1.2 MiB`), largest total first.
+pub(super) fn type_summary(plan: &[AttachmentPlanEntry]) -> Vec<String> {
+    let mut by_type: Vec<(&str, usize, u64)> = Vec::new();
+    for att in plan {
+        let size = att.size.unwrap_or(0);
+        match by_type.iter_mut().find(|(mime, ..)| *mime == att.mime) {
+            Some(entry) => {
+                entry.1 += 1;
+                entry.2 += size;
+            }
+            None => by_type.push((&att.mime, 1, size)),
+        }
+    }
+    by_type.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0)));
+    by_type
+        .into_iter()
+        .map(|(mime, count, bytes)| {
+            format!("{mime}: {count} file(s), {}", format_bytes(bytes as usize))
+        })
+        .collect()
+}
+
+fn run_parallel(plan: &[AttachmentPlanEntry], vault: &VaultFs, output: &Path) -> Vec<CopyOutcome> {
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .clamp(1, MAX_COPY_THREADS)
+        .min(plan.len().max(1));
+    let next = AtomicUsize::new(0);
+
+    let outcomes: Vec<Option<CopyOutcome>> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(att) = plan.get(i) else { break };
+                        done.push((i, copy_one(att, vault, output)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        let mut outcomes: Vec<Option<CopyOutcome>> = plan.iter().map(|_| None).collect();
+        for (i, outcome) in workers.into_iter().filter_map(|w| w.join().ok()).flatten() {
+            outcomes[i] = Some(outcome);
+        }
+        outcomes
+    });
+    // The entries of a worker that panicked have no outcome; each fails on its own
+    plan.iter()
+        .zip(outcomes)
+        .map(|(att, outcome)| outcome.unwrap_or_else(|| worker_panicked(att)))
+        .collect()
+}
+
+fn worker_panicked(att: &AttachmentPlanEntry) -> CopyOutcome {
+    CopyOutcome::Failed(Warning::new(
+        "attachment-copy",
+        format!(
+            "Failed to copy attachment '{}': copy worker panicked",
+            att.source
+        ),
+    ))
+}
+
+fn copy_one(att: &AttachmentPlanEntry, vault: &VaultFs, output: &Path) -> CopyOutcome {
+    let target_path = output.join(&att.target);
+    if let Some(parent) = target_path.parent()
+        && let Err(e) = fs::create_dir_all(parent)
+    {
+        return CopyOutcome::Failed(Warning::new(
+            "attachment-copy",
+            format!(
+                "Failed to create asset directory for '{}': {e}",
+                target_path.display()
+            ),
+        ));
+    }
+    if let Some(download) = vault.read_bytes(&att.source) {
+        return write_downloaded(att, download, &target_path);
+    }
+    let source = Path::new(&att.source);
+    if identical(source, &target_path) {
+        return CopyOutcome::Unchanged;
+    }
+    match fs::copy(source, &target_path) {
+        Ok(_) => {
+            // Carry the source mtime over so the next build can skip this file cheaply.
+            if let Ok(modified) = fs::metadata(source).and_then(|m| m.modified())
+                && let Ok(f) = File::options().write(true).open(&target_path)
+            {
+                let _ = f.set_modified(modified);
+            }
+            CopyOutcome::Copied
+        }
+        Err(e) => CopyOutcome::Failed(Warning::new(
+            "attachment-copy",
+            format!(
+                "Failed to copy attachment '{}' -> '{}': {e}",
+                att.source,
+                target_path.display()
+            ),
+        )),
+    }
+}
+
+/// Write an attachment downloaded from object storage, unless `target_path` already holds it.
+fn write_downloaded(
+    att: &AttachmentPlanEntry,
+    download: anyhow::Result<Vec<u8>>,
+    target_path: &Path,
+) -> CopyOutcome {
+    let data = match download {
+        Ok(data) => data,
+        Err(e) => {
+            return CopyOutcome::Failed(Warning::new(
+                "attachment-copy",
+                format!("Failed to download attachment '{}': {e:#}", att.source),
+            ));
+        }
+    };
+    if fs::read(target_path).is_ok_and(|existing| existing == data) {
+        return CopyOutcome::Unchanged;
+    }
+    match fs::write(target_path, &data) {
+        Ok(()) => CopyOutcome::Copied,
+        Err(e) => CopyOutcome::Failed(Warning::new(
+            "attachment-copy",
+            format!(
+                "Failed to write attachment '{}' -> '{}': {e}",
+                att.source,
+                target_path.display()
+            ),
+        )),
+    }
+}
+
+/// True when `dest` exists and has the same content as `src`.
+/// Same size + same mtime is trusted; otherwise the bytes are compared.
+fn identical(src: &Path, dest: &Path) -> bool {
+    let (Ok(sm), Ok(dm)) = (fs::metadata(src), fs::metadata(dest)) else {
+        return false;
+    };
+    if !dm.is_file() || sm.len() != dm.len() {
+        return false;
+    }
+    if let (Ok(a), Ok(b)) = (sm.modified(), dm.modified())
+        && a == b
+    {
+        return true;
+    }
+    same_bytes(src, dest).unwrap_or(false)
+}
+
+fn same_bytes(a: &Path, b: &Path) -> std::io::Result<bool> {
+    let mut ra = BufReader::new(File::open(a)?);
+    let mut rb = BufReader::new(File::open(b)?);
+    let mut ba = [0u8; 64 * 1024];
+    let mut bb = [0u8; 64 * 1024];
+    loop {
+        let n = ra.read(&mut ba)?;
+        if n == 0 {
+            return Ok(rb.read(&mut bb[..1])? == 0);
+        }
+        rb.read_exact(&mut bb[..n])?;
+        if ba[..n] != bb[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Remove files under `output/assets/` that the current plan no longer references.
+fn prune_stale(plan: &[AttachmentPlanEntry], output: &Path) -> usize {
+    let keep: HashSet<PathBuf> = plan.iter().map(|a| output.join(&a.target)).collect();
+    let mut removed = 0usize;
+    let mut stack = vec![output.join("assets")];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else if !keep.contains(&path) && fs::remove_file(&path).is_ok() {
+                removed += 1;
+            }
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_assets_are_pruned_even_without_a_plan() {
+        let out = tempfile::tempdir().unwrap();
+        let stale = out.path().join("assets/old/photo.png");
+        fs::create_dir_all(stale.parent().unwrap()).unwrap();
+        fs::write(&stale, b"png").unwrap();
+
+        let vault = VaultFs::open("index.md", None).unwrap();
+        let report = copy_attachments(&[], &vault, out.path(), false);
+        assert_eq!(report.removed_stale, 1);
+        assert!(!stale.exists());
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::num::NonZeroU32;
+
+use anyhow::{Result, anyhow, bail};
+use diaryx_core::{AttachmentPlanEntry, PageOutput, Warning};
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::archive::base64;
+use crate::config::EncryptConfig;
+
+/// Environment variable holding the passphrase of `build --encrypt`.
+pub const PASSPHRASE_VAR: &str = "DIARYX_ENCRYPT_PASSPHRASE";
+
+/// PBKDF2 iterations when `[encrypt]` does not set them.
+const DEFAULT_ITERATIONS: u32 = 600_000;
+
+/// Title of a sealed page until it is unlocked.
+const SEALED_TITLE: &str = "Encrypted page";
+
+/// Frontmatter keys a sealed page keeps in the clear.
+const CLEAR_FIELDS: [&str; 2] = ["visibility", "noindex"];
+
+/// `build --encrypt`: the key non-public pages are encrypted with. It is derived once per build
+/// (PBKDF2-HMAC-SHA256 over the passphrase and a fresh salt); each page then gets its own nonce.
+#[derive(Clone)]
+pub struct PageEncryption {
+    key: [u8; 32],
+    salt: [u8; 16],
+    iterations: NonZeroU32,
+    hint: Option<String>,
+}
+
+impl fmt::Debug for PageEncryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PageEncryption")
+            .field("iterations", &self.iterations)
+            .field("hint", &self.hint)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PageEncryption {
+    /// Derive the key from the passphrase in [`PASSPHRASE_VAR`], with the `[encrypt]` settings.
+    pub(crate) fn from_env(config: EncryptConfig) -> Result<Self> {
+        let passphrase = std::env::var(PASSPHRASE_VAR).unwrap_or_default();
+        if passphrase.is_empty() {
+            bail!("--encrypt needs the passphrase in the {PASSPHRASE_VAR} environment variable");
+        }
+        Self::derive(&passphrase, config)
+    }
+
+    pub(super) fn derive(passphrase: &str, config: EncryptConfig) -> Result<Self> {
+        let iterations = NonZeroU32::new(config.iterations.unwrap_or(DEFAULT_ITERATIONS))
+            .ok_or_else(|| anyhow!("[encrypt] iterations must be at least 1"))?;
+        let mut salt = [0u8; 16];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| anyhow!("No random numbers available for the encryption salt"))?;
+        let mut key = [0u8; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            &salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        Ok(PageEncryption {
+            key,
+            salt,
+            iterations,
+            hint: config.hint,
+        })
+    }
+
+    /// Replace the body, metadata block and title of `page` with their AES-256-GCM ciphertext
+    /// and a passphrase form whose script decrypts them in the browser (Web Crypto). What else
+    /// the page carries from its frontmatter (authors, `reachable`, every field but
+    /// [`CLEAR_FIELDS`]) is dropped, so the model, graph and host files built from it stay clean.
+    pub(super) fn encrypt_page(&self, page: &mut PageOutput) -> Result<()> {
+        let payload = serde_json::json!({
+            "title": page.title,
+            "meta": page.metadata_html,
+            "body": page.html,
+        });
+        let mut data = serde_json::to_vec(&payload)?;
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("No random numbers available for the encryption nonce"))?;
+        let key = UnboundKey::new(&AES_256_GCM, &self.key)
+            .map_err(|_| anyhow!("Invalid page encryption key"))?;
+        LessSafeKey::new(key)
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| anyhow!("Failed to encrypt {}", page.source_path))?;
+
+        let mut html = format!(
+            "<div class=\"encrypted-page\" data-salt=\"{}\" data-iterations=\"{}\" data-nonce=\"{}\" data-payload=\"{}\">\
+             <form><p>This page is encrypted.</p>",
+            base64(&self.salt),
+            self.iterations,
+            base64(&nonce),
+            base64(&data)
+        );
+        if let Some(hint) = &self.hint {
+            html.push_str(&format!(
+                "<p class=\"encrypted-hint\">Hint: {}</p>",
+                escape(hint)
+            ));
+        }
+        html.push_str(
+            "<label>Passphrase <input type=\"password\" name=\"passphrase\" autocomplete=\"current-password\" required /></label> \
+             <button type=\"submit\">Unlock</button>\
+             <p class=\"encrypted-error\" role=\"alert\" hidden>Wrong passphrase.</p></form>\
+             <noscript><p>Unlocking this page needs JavaScript.</p></noscript></div><script>",
+        );
+        html.push_str(DECRYPT_SCRIPT);
+        html.push_str("</script>");
+        page.html = html;
+        page.metadata_html = String::new();
+        page.title = SEALED_TITLE.to_string();
+        page.authors.clear();
+        page.reachable.clear();
+        page.version = None;
+        let clear: serde_yaml::Mapping = CLEAR_FIELDS
+            .iter()
+            .filter_map(|key| Some(((*key).into(), page.frontmatter.get(*key)?.clone())))
+            .collect();
+        page.frontmatter = serde_yaml::Value::Mapping(clear);
+        Ok(())
+    }
+}
+
+/// Drop the attachments referenced only by `encrypted` pages from the copy plan: attachments are
+/// not encrypted, so publishing them would expose what the pages hide. Returns a warning for each.
+pub(super) fn withhold_attachments(
+    attachments: &mut Vec<AttachmentPlanEntry>,
+    encrypted: &HashSet<String>,
+) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    attachments.retain(|entry| {
+        let private = !entry.pages.is_empty() && entry.pages.iter().all(|p| encrypted.contains(p));
+        if private {
+            warnings.push(Warning::new("encrypted-attachment", format!(
+                "Attachment of an encrypted page not published: {} (attachments are not encrypted)",
+                entry.source
+            )));
+        }
+        !private
+    });
+    warnings
+}
+
+/// Whether `--encrypt` applies to `page`: any page whose visibility does not include `public`.
+pub(super) fn is_private(page: &PageOutput) -> bool {
+    !page.visibility.iter().any(|v| v == "public")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Unlocks the `.encrypted-page` placed just before it. The derived key is kept in
+/// `sessionStorage` (per salt, so per build), so the other encrypted pages of the site open
+/// without asking again until the tab is closed.
+const DECRYPT_SCRIPT: &str = r#"(function () {
+  var box = document.currentScript.previousElementSibling;
+  var form = box.querySelector("form");
+  var error = box.querySelector(".encrypted-error");
+  var slot = "diaryx-key:" + box.dataset.salt;
+  function bytes(text) {
+    var raw = atob(text), out = new Uint8Array(raw.length);
+    for (var i = 0; i < raw.length; i++) out[i] = raw.charCodeAt(i);
+    return out;
+  }
+  function text(buffer) {
+    return btoa(String.fromCharCode.apply(null, new Uint8Array(buffer)));
+  }
+  function unlock(raw) {
+    return crypto.subtle.importKey("raw", raw, "AES-GCM", false, ["decrypt"])
+      .then(function (key) {
+        return crypto.subtle.decrypt({ name: "AES-GCM", iv: bytes(box.dataset.nonce) }, key, bytes(box.dataset.payload));
+      })
+      .then(function (plain) {
+        var page = JSON.parse(new TextDecoder().decode(plain));
+        try { sessionStorage.setItem(slot, text(raw)); } catch (e) {}
+        document.title = page.title;
+        var heading = document.querySelector(".page-title");
+        if (heading) heading.textContent = page.title;
+        var main = box.closest("main");
+        if (page.meta && main) main.insertAdjacentHTML("beforebegin", page.meta);
+        box.nextElementSibling.remove();
+        box.outerHTML = page.body;
+      });
+  }
+  function derive(passphrase) {
+    return crypto.subtle.importKey("raw", new TextEncoder().encode(passphrase), "PBKDF2", false, ["deriveBits"])
+      .then(function (key) {
+        return crypto.subtle.deriveBits({ name: "PBKDF2", hash: "SHA-256", salt: bytes(box.dataset.salt), iterations: +box.dataset.iterations }, key, 256);
+      });
+  }
+  form.addEventListener("submit", function (event) {
+    event.preventDefault();
+    error.hidden = true;
+    derive(form.elements.passphrase.value).then(unlock).catch(function () { error.hidden = false; });
+  });
+  var saved = null;
+  try { saved = sessionStorage.getItem(slot); } catch (e) {}
+  if (saved) unlock(bytes(saved)).catch(function () {});
+})();"#;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use diaryx_core::memfs::InMemoryFs;
+    use diaryx_core::{CoreBuildOptions, build_site};
+
+    use super::*;
+
+    fn page() -> PageOutput {
+        let fs = InMemoryFs::new(HashMap::from([(
+            "secret.md".to_string(),
+            "---\ntitle: Secret\nvisibility: private\n---\nHidden words.\n".to_string(),
+        )]));
+        let opts = CoreBuildOptions {
+            include_nonpublic: true,
+            ..Default::default()
+        };
+        build_site("secret.md", opts, &fs)
+            .expect("build ok")
+            .pages
+            .remove(0)
+    }
+
+    fn attribute<'a>(html: &'a str, name: &str) -> &'a str {
+        let start = html.find(&format!("{name}=\"")).expect(name) + name.len() + 2;
+        &html[start..start + html[start..].find('"').unwrap()]
+    }
+
+    fn unbase64(text: &str) -> Vec<u8> {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut bits = 0u32;
+        let mut count = 0;
+        let mut out = Vec::new();
+        for c in text.bytes().filter(|&c| c != b'=') {
+            bits = bits << 6 | ALPHABET.iter().position(|&a| a == c).unwrap() as u32;
+            count += 6;
+            if count >= 8 {
+                count -= 8;
+                out.push((bits >> count) as u8);
+            }
+        }
+        out
+    }
+
+    /// What the page script does: derive the key from the passphrase and open the payload.
+    fn decrypt(html: &str, passphrase: &str) -> Option<serde_json::Value> {
+        let salt = unbase64(attribute(html, "data-salt"));
+        let iterations: NonZeroU32 = attribute(html, "data-iterations").parse().ok()?;
+        let nonce: [u8; NONCE_LEN] = unbase64(attribute(html, "data-nonce")).try_into().ok()?;
+        let mut data = unbase64(attribute(html, "data-payload"));
+        let mut key = [0u8; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            &salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &key).ok()?);
+        let plain = key
+            .open_in_place(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .ok()?;
+        serde_json::from_slice(plain).ok()
+    }
+
+    #[test]
+    fn sealed_page_opens_with_the_passphrase_only() {
+        let encryption = PageEncryption::derive(
+            "correct horse",
+            EncryptConfig {
+                iterations: Some(1000),
+                hint: Some("the <usual> one".to_string()),
+            },
+        )
+        .unwrap();
+        let original = page();
+        assert!(is_private(&original));
+        let mut sealed = original.clone();
+        encryption.encrypt_page(&mut sealed).unwrap();
+
+        assert!(!sealed.html.contains("Hidden words"));
+        assert!(sealed.metadata_html.is_empty());
+        assert_eq!(sealed.title, SEALED_TITLE);
+        assert_eq!(
+            serde_yaml::to_string(&sealed.frontmatter).unwrap(),
+            "visibility: private\n"
+        );
+        assert!(sealed.html.contains("Hint: the &lt;usual&gt; one"));
+        assert_eq!(attribute(&sealed.html, "data-iterations"), "1000");
+
+        let opened = decrypt(&sealed.html, "correct horse").expect("decrypts");
+        assert_eq!(opened["title"], "Secret");
+        assert_eq!(opened["body"], original.html.as_str());
+        assert_eq!(opened["meta"], original.metadata_html.as_str());
+        assert!(decrypt(&sealed.html, "wrong horse").is_none());
+
+        // Each page gets its own nonce under the build's key.
+        let mut again = original.clone();
+        encryption.encrypt_page(&mut again).unwrap();
+        assert_ne!(
+            attribute(&sealed.html, "data-nonce"),
+            attribute(&again.html, "data-nonce")
+        );
+    }
+
+    #[test]
+    fn attachments_of_encrypted_pages_are_withheld() {
+        let entry = |source: &str, pages: &[&str]| AttachmentPlanEntry {
+            source: source.to_string(),
+            target: format!("assets/{source}"),
+            size: None,
+            mime: "image/png".to_string(),
+            sha256: None,
+            pages: pages.iter().map(|p| p.to_string()).collect(),
+        };
+        let mut attachments = vec![
+            entry("scan.png", &["secret"]),
+            entry("shared.png", &["secret", "walk"]),
+            entry("walk.png", &["walk"]),
+        ];
+        let warnings = withhold_attachments(&mut attachments, &HashSet::from(["secret".into()]));
+        let kept: Vec<&str> = attachments.iter().map(|a| a.source.as_str()).collect();
+        assert_eq!(kept, ["shared.png", "walk.png"]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "encrypted-attachment");
+        assert!(warnings[0].message.contains("scan.png"));
+    }
+}
@@ -0,0 +1,73 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use diaryx_core::site::CSS_PATH;
+
+/// GitHub Pages publishing (`build --gh-pages`).
+///
+/// Three files are added to the output root: `.nojekyll`, so GitHub serves the site as built
+/// (Jekyll would drop files and folders starting with `_`); a `404.html`, which GitHub serves for
+/// any missing URL at any depth and which therefore links the stylesheet and home page under the
+/// site's path prefix rather than relatively; and `CNAME` when a custom domain is configured.
+#[derive(Debug, Clone)]
+pub struct GhPages {
+    /// Path the site is served under: `/` for a user site or custom domain, `/<repo>/` for a
+    /// project site.
+    pub path_prefix: String,
+    pub cname: Option<String>,
+}
+
+pub(super) const NOT_FOUND_PATH: &str = "404.html";
+
+impl GhPages {
+    /// Normalize `prefix` to start and end with `/` (`journal` -> `/journal/`).
+    pub fn new(prefix: Option<&str>, cname: Option<String>) -> Self {
+        let trimmed = prefix.unwrap_or("").trim_matches('/');
+        GhPages {
+            path_prefix: if trimmed.is_empty() {
+                "/".to_string()
+            } else {
+                format!("/{trimmed}/")
+            },
+            cname: cname.filter(|c| !c.trim().is_empty()),
+        }
+    }
+}
+
+/// Write `.nojekyll`, `404.html` (unless a page of the vault already is the 404 page) and
+/// `CNAME` into `output`.
+pub(super) fn write_gh_pages(output: &Path, gh: &GhPages, include_css: bool) -> Result<()> {
+    let write = |name: &str, contents: String| {
+        fs::write(output.join(name), contents).with_context(|| format!("Failed writing {name}"))
+    };
+    write(".nojekyll", String::new())?;
+    if !output.join(NOT_FOUND_PATH).exists() {
+        write(NOT_FOUND_PATH, not_found_page(&gh.path_prefix, include_css))?;
+    }
+    if let Some(cname) = &gh.cname {
+        write("CNAME", format!("{}\n", cname.trim()))?;
+    }
+    Ok(())
+}
+
+fn not_found_page(prefix: &str, include_css: bool) -> String {
+    let prefix = prefix.replace('"', "%22");
+    let mut out = String::from(
+        "<!doctype html><html lang=\"en\"><head><meta charset=\"utf-8\" />\
+         <meta name=\"viewport\" content=\"width=device-width,initial-scale=1\" />\
+         <title>Page not found</title><meta name=\"robots\" content=\"noindex\" />",
+    );
+    if include_css {
+        out.push_str(&format!(
+            "<link rel=\"stylesheet\" href=\"{prefix}{CSS_PATH}\" />"
+        ));
+    }
+    out.push_str(&format!(
+        "</head><body class=\"layout-minimal\"><main class=\"content\">\
+         <h1 class=\"page-title\">Page not found</h1>\
+         <p>There is no page at this address. <a href=\"{prefix}\">Go to the home page</a>.</p>\
+         </main></body></html>"
+    ));
+    out
+}
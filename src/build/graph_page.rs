@@ -0,0 +1,160 @@
+use diaryx_core::site::CSS_PATH;
+
+use crate::graph::Graph;
+
+/// Site-relative path of the graph page (`build --graph-page`).
+pub(super) const GRAPH_PATH: &str = "graph.html";
+
+/// Styles of the graph view; edge kinds are styled as in `diaryx graph --format dot`.
+const STYLE: &str = "\
+#graph{width:100%;height:70vh;border:1px solid #d1d5db;border-radius:6px;touch-action:none}\
+#graph .edge{stroke:#9ca3af;stroke-width:1}\
+#graph .edge-part_of{stroke-dasharray:4 3}\
+#graph .edge-body{stroke:#d1d5db;stroke-dasharray:1 3}\
+#graph .node circle{fill:#2563eb;stroke:#fff;stroke-width:1.5;cursor:pointer}\
+#graph .node.index circle{fill:#059669}\
+#graph .node.private circle{fill:#9ca3af}\
+#graph .node text{font-size:11px;fill:#374151;pointer-events:none}\
+#graph .node.dim{opacity:.25}\
+.graph-legend{font-size:13px;color:#6b7280}";
+
+/// Force layout over the embedded `{nodes, links}` data: nodes repel, edges pull, everything
+/// drifts to the centre; the simulation cools down and stops. Nodes can be dragged; clicking
+/// one opens its page, hovering highlights its neighbours.
+const SCRIPT: &str = r#"(function () {
+  var data = JSON.parse(document.getElementById("graph-data").textContent);
+  var svg = document.getElementById("graph"), NS = "http://www.w3.org/2000/svg";
+  var W = svg.clientWidth || 800, H = svg.clientHeight || 600;
+  svg.setAttribute("viewBox", "0 0 " + W + " " + H);
+  var byId = {};
+  data.nodes.forEach(function (n, i) {
+    var a = 2 * Math.PI * i / data.nodes.length;
+    n.x = W / 2 + Math.cos(a) * W / 4; n.y = H / 2 + Math.sin(a) * H / 4;
+    n.vx = 0; n.vy = 0; n.near = {}; byId[n.id] = n;
+  });
+  var links = data.links.filter(function (l) { return byId[l.source] && byId[l.target]; });
+  var gEdges = document.createElementNS(NS, "g"), gNodes = document.createElementNS(NS, "g");
+  svg.appendChild(gEdges); svg.appendChild(gNodes);
+  links.forEach(function (l) {
+    l.s = byId[l.source]; l.t = byId[l.target];
+    l.s.near[l.t.id] = l.t.near[l.s.id] = true;
+    l.el = document.createElementNS(NS, "line");
+    l.el.setAttribute("class", "edge edge-" + l.kind);
+    gEdges.appendChild(l.el);
+  });
+  data.nodes.forEach(function (n) {
+    var g = document.createElementNS(NS, "g"), c = document.createElementNS(NS, "circle");
+    var label = document.createElementNS(NS, "text"), tip = document.createElementNS(NS, "title");
+    var cls = "node";
+    if (n.is_index) cls += " index";
+    if (n.visibility.indexOf("public") < 0) cls += " private";
+    g.setAttribute("class", cls);
+    c.setAttribute("r", n.is_root_index ? 9 : n.is_index ? 7 : 5);
+    label.setAttribute("x", 10); label.setAttribute("y", 4);
+    label.textContent = n.title;
+    tip.textContent = n.title + (n.tags.length ? " (" + n.tags.join(", ") + ")" : "");
+    c.appendChild(tip); g.appendChild(c); g.appendChild(label); gNodes.appendChild(g);
+    n.el = g;
+    g.addEventListener("mouseenter", function () {
+      data.nodes.forEach(function (m) {
+        m.el.classList.toggle("dim", m !== n && !n.near[m.id]);
+      });
+    });
+    g.addEventListener("mouseleave", function () {
+      data.nodes.forEach(function (m) { m.el.classList.remove("dim"); });
+    });
+    g.addEventListener("pointerdown", function (e) {
+      drag = { node: n, x: e.clientX, y: e.clientY, moved: false };
+      g.setPointerCapture(e.pointerId);
+    });
+    g.addEventListener("pointermove", function (e) {
+      if (!drag || drag.node !== n) return;
+      var scale = W / svg.getBoundingClientRect().width;
+      n.x += (e.clientX - drag.x) * scale; n.y += (e.clientY - drag.y) * scale;
+      drag.moved = drag.moved || Math.abs(e.clientX - drag.x) + Math.abs(e.clientY - drag.y) > 2;
+      drag.x = e.clientX; drag.y = e.clientY;
+      heat(0.3);
+    });
+    g.addEventListener("pointerup", function () {
+      if (drag && !drag.moved) window.location.href = n.page;
+      drag = null;
+    });
+  });
+  var drag = null, alpha = 1, running = false;
+  function tick() {
+    var nodes = data.nodes, k = Math.sqrt(W * H / Math.max(nodes.length, 1)) * 0.6;
+    for (var i = 0; i < nodes.length; i++) {
+      for (var j = i + 1; j < nodes.length; j++) {
+        var a = nodes[i], b = nodes[j], dx = a.x - b.x, dy = a.y - b.y;
+        var d2 = dx * dx + dy * dy || 0.01, f = k * k / d2 * 0.05;
+        a.vx += dx * f; a.vy += dy * f; b.vx -= dx * f; b.vy -= dy * f;
+      }
+    }
+    links.forEach(function (l) {
+      var dx = l.t.x - l.s.x, dy = l.t.y - l.s.y, d = Math.sqrt(dx * dx + dy * dy) || 0.01;
+      var f = (d - k) / d * 0.05;
+      l.s.vx += dx * f; l.s.vy += dy * f; l.t.vx -= dx * f; l.t.vy -= dy * f;
+    });
+    nodes.forEach(function (n) {
+      n.vx += (W / 2 - n.x) * 0.005; n.vy += (H / 2 - n.y) * 0.005;
+      if (!drag || drag.node !== n) {
+        n.x += Math.max(-20, Math.min(20, n.vx * alpha));
+        n.y += Math.max(-20, Math.min(20, n.vy * alpha));
+      }
+      n.x = Math.max(10, Math.min(W - 10, n.x)); n.y = Math.max(10, Math.min(H - 10, n.y));
+      n.vx *= 0.6; n.vy *= 0.6;
+      n.el.setAttribute("transform", "translate(" + n.x + "," + n.y + ")");
+    });
+    links.forEach(function (l) {
+      l.el.setAttribute("x1", l.s.x); l.el.setAttribute("y1", l.s.y);
+      l.el.setAttribute("x2", l.t.x); l.el.setAttribute("y2", l.t.y);
+    });
+    alpha *= 0.99;
+    if (alpha > 0.02) requestAnimationFrame(tick); else running = false;
+  }
+  function heat(a) {
+    alpha = Math.max(alpha, a);
+    if (!running) { running = true; requestAnimationFrame(tick); }
+  }
+  heat(1);
+})();"#;
+
+/// The graph page, written for [`GRAPH_PATH`]: `graph`'s `{nodes, links}` embedded as JSON and
+/// drawn by an inline script (no external files, so it works offline and from `file://`). Node
+/// `page`s must already be URLs relative to where the page ends up.
+pub(super) fn graph_page(graph: &Graph, include_css: bool) -> String {
+    let data = serde_json::to_string(graph)
+        .unwrap_or_else(|_| "{\"nodes\":[],\"links\":[]}".to_string())
+        // Keep the data from closing its <script> element.
+        .replace("</", "<\\/");
+    let mut out = String::from(
+        "<!doctype html><html lang=\"en\"><head><meta charset=\"utf-8\" />\
+         <meta name=\"viewport\" content=\"width=device-width,initial-scale=1\" />\
+         <title>Graph</title>",
+    );
+    if include_css {
+        out.push_str(&format!("<link rel=\"stylesheet\" href=\"{CSS_PATH}\" />"));
+    }
+    out.push_str(&format!(
+        "<style>{STYLE}</style></head><body class=\"layout-minimal\"><main class=\"content\">\
+         <h1 class=\"page-title\">Graph</h1>\
+         <p class=\"graph-legend\">{} pages, {} links. Indexes are green; dashed lines are \
+         <code>part_of</code>, dotted lines links in the text. Click a page to open it; \
+         <a href=\"index.html\">back to the home page</a>.</p>\
+         <svg id=\"graph\" role=\"img\" aria-label=\"Document graph\"></svg>\
+         <script type=\"application/json\" id=\"graph-data\">{data}</script>\
+         <script>{SCRIPT}</script></main></body></html>",
+        graph.nodes.len(),
+        graph.links.len()
+    ));
+    out
+}
+
+/// Add a link to the graph page at the end of a page's main content (the root index's).
+pub(super) fn link_graph(html: &str, href: &str) -> String {
+    let link = format!("<p class=\"graph-link\"><a href=\"{href}\">Graph of all pages</a></p>");
+    match html.rfind("</main>") {
+        Some(at) => format!("{}{link}{}", &html[..at], &html[at..]),
+        None => html.to_string(),
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use diaryx_core::{PageOutput, Warning, version_text};
+use serde_yaml::Value;
+
+use crate::diagnostics::normalize;
+use crate::vault::{parse_fields, split_frontmatter};
+
+/// Git history of the vault sources, for the version check and `build --changelog`.
+///
+/// The version check compares each page with its committed copy (`HEAD`): a versioned page
+/// whose `updated` changed while its `version` did not gets a warning. Only files with
+/// uncommitted changes are looked at, so a clean work tree costs a single `git diff`. The
+/// changelog lists the commits touching a page's source (following renames), newest first,
+/// marking those that set a new `version`. Outside a git work tree (or without git installed)
+/// both quietly do nothing.
+pub(super) struct History {
+    /// Top level of the work tree.
+    root: PathBuf,
+    /// Files differing from `HEAD`.
+    changed: HashSet<PathBuf>,
+}
+
+/// One commit touching a page's source.
+struct Change {
+    hash: String,
+    date: String,
+    subject: String,
+    /// `version` set by this commit, if it changed it.
+    version: Option<String>,
+}
+
+impl History {
+    /// `None` when the entry is not inside a git work tree.
+    pub(super) fn open(entry: &Path) -> Option<Self> {
+        let dir = entry.parent().unwrap_or(Path::new("."));
+        let root = PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"])?.trim());
+        // A repository without commits has no HEAD to compare with.
+        let changed = git(&root, &["diff", "HEAD", "--name-only", "-z"])
+            .unwrap_or_default()
+            .split('\0')
+            .filter(|rel| !rel.is_empty())
+            .map(|rel| root.join(rel))
+            .collect();
+        Some(Self { root, changed })
+    }
+
+    /// Warning for a versioned page whose `updated` changed since `HEAD` but whose `version`
+    /// did not.
+    pub(super) fn check_version(&self, page: &PageOutput) -> Option<Warning> {
+        let path = normalize(Path::new(&page.source_path));
+        if !self.changed.contains(&path) {
+            return None;
+        }
+        let committed = git(&self.root, &["show", &format!("HEAD:{}", self.rel(&path)?)])?;
+        let before = parse_fields(split_frontmatter(&committed).0);
+        let after = &page.frontmatter;
+        let version = after.get("version").and_then(version_text)?;
+        let field = |key: &str| before.get(key) == after.get(key);
+        (!field("updated") && field("version")).then(|| {
+            Warning::new(
+                "version",
+                format!(
+                    "'updated' changed since the last commit but 'version' is still {version} ({})",
+                    page.source_path
+                ),
+            )
+        })
+    }
+
+    /// Append a "Changes" section listing the commits of the page's source to its HTML.
+    pub(super) fn append_changelog(&self, page: &mut PageOutput) {
+        let path = normalize(Path::new(&page.source_path));
+        let changes = self.changes(&path);
+        if changes.is_empty() {
+            return;
+        }
+        let mut html = String::from(
+            "\n<section class=\"changelog\">\n<h2>Changes</h2>\n<ol class=\"changelog\">\n",
+        );
+        for change in &changes {
+            html.push_str(&format!(
+                "<li><time datetime=\"{date}\">{date}</time> ",
+                date = escape(&change.date)
+            ));
+            if let Some(version) = &change.version {
+                html.push_str(&format!(
+                    "<span class=\"page-version\">{}</span> ",
+                    escape(version)
+                ));
+            }
+            html.push_str(&format!(
+                "{} <code>{}</code></li>\n",
+                escape(&change.subject),
+                escape(&change.hash)
+            ));
+        }
+        html.push_str("</ol>\n</section>\n");
+        page.html.push_str(&html);
+    }
+
+    /// Commits touching `path`, newest first, with the `version` each one introduced.
+    fn changes(&self, path: &Path) -> Vec<Change> {
+        let Some(rel) = self.rel(path) else {
+            return Vec::new();
+        };
+        let Some(log) = git(
+            &self.root,
+            &[
+                "log",
+                "--follow",
+                "--format=%x1e%h%x1f%as%x1f%s",
+                "--patch",
+                "--unified=0",
+                "--",
+                &rel,
+            ],
+        ) else {
+            return Vec::new();
+        };
+        log.split('\x1e')
+            .filter_map(|record| {
+                let (header, patch) = record.split_once('\n').unwrap_or((record, ""));
+                let mut fields = header.splitn(3, '\x1f');
+                let (hash, date, subject) = (fields.next()?, fields.next()?, fields.next()?);
+                let version = patch
+                    .lines()
+                    .filter_map(|line| line.strip_prefix('+'))
+                    .filter(|line| line.starts_with("version:"))
+                    .find_map(|line| {
+                        let field: Value = serde_yaml::from_str(line).ok()?;
+                        field.get("version").and_then(version_text)
+                    });
+                Some(Change {
+                    hash: hash.to_string(),
+                    date: date.to_string(),
+                    subject: subject.to_string(),
+                    version,
+                })
+            })
+            .collect()
+    }
+
+    /// `path` relative to the work tree, with `/` separators as git expects.
+    fn rel(&self, path: &Path) -> Option<String> {
+        let rel = path.strip_prefix(&self.root).ok()?;
+        Some(
+            rel.components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        )
+    }
+}
+
+/// Run git in `dir`; stdout on success, `None` when git is missing or fails.
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
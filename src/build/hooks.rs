@@ -0,0 +1,164 @@
+use std::fmt;
+use std::process::Command;
+
+use anyhow::{Context, Result};
+
+use crate::BuildOptions;
+
+/// Which `[hooks]` list is running.
+#[derive(Clone, Copy)]
+pub(super) enum Stage {
+    PreBuild,
+    /// After a build that wrote this many pages, with this many warnings.
+    PostBuild {
+        pages: usize,
+        warnings: usize,
+    },
+}
+
+impl Stage {
+    fn name(self) -> &'static str {
+        match self {
+            Stage::PreBuild => "pre_build",
+            Stage::PostBuild { .. } => "post_build",
+        }
+    }
+}
+
+/// A hook command that could not be run or exited with a failure.
+#[derive(Debug)]
+pub(super) struct HookFailed(String);
+
+impl fmt::Display for HookFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for HookFailed {}
+
+/// Run the `[hooks]` commands of `stage` in order through the shell, stopping at the first that
+/// fails. Their output is captured, and printed with `--verbose` (or when a command fails).
+pub(super) fn run(opts: &BuildOptions, stage: Stage) -> Result<()> {
+    let commands = match stage {
+        Stage::PreBuild => &opts.hooks.pre_build,
+        Stage::PostBuild { .. } => &opts.hooks.post_build,
+    };
+    let input = opts.input.to_string_lossy();
+    let output = opts.output.to_string_lossy();
+    let (pages, warnings) = match stage {
+        Stage::PreBuild => (String::new(), String::new()),
+        Stage::PostBuild { pages, warnings } => (pages.to_string(), warnings.to_string()),
+    };
+    for template in commands {
+        let command = expand(template, &input, &output, &pages, &warnings);
+        if opts.verbose {
+            eprintln!("[hook] {}: {command}", stage.name());
+        }
+        let mut cmd = shell(&command);
+        cmd.current_dir(&opts.hooks.dir)
+            .env("DIARYX_HOOK", stage.name())
+            .env("DIARYX_INPUT", &*input)
+            .env("DIARYX_OUTPUT", &*output)
+            .env("DIARYX_PAGES", &pages)
+            .env("DIARYX_WARNINGS", &warnings);
+        let result = cmd.output().with_context(|| {
+            HookFailed(format!("Failed to run {} hook `{command}`", stage.name()))
+        })?;
+        if opts.verbose || !result.status.success() {
+            print_output("stdout", &result.stdout);
+            print_output("stderr", &result.stderr);
+        }
+        if !result.status.success() {
+            return Err(HookFailed(format!(
+                "{} hook `{command}` failed ({})",
+                stage.name(),
+                result.status
+            ))
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// `template` with its placeholders filled in; the paths are quoted for the shell, the counts
+/// (empty before a build) are not.
+fn expand(template: &str, input: &str, output: &str, pages: &str, warnings: &str) -> String {
+    template
+        .replace("{input}", &quote(input))
+        .replace("{output}", &quote(output))
+        .replace("{pages}", pages)
+        .replace("{warnings}", warnings)
+}
+
+fn print_output(stream: &str, bytes: &[u8]) {
+    for line in String::from_utf8_lossy(bytes).lines() {
+        eprintln!("[hook] {stream}: {line}");
+    }
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+/// `text` as a single shell word.
+#[cfg(windows)]
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// `text` as a single shell word.
+#[cfg(not(windows))]
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        assert_eq!(
+            expand(
+                "upload {output} --from {input} # {pages} pages, {warnings} warnings",
+                "vault/index.md",
+                "site",
+                "12",
+                "0"
+            ),
+            format!(
+                "upload {} --from {} # 12 pages, 0 warnings",
+                quote("site"),
+                quote("vault/index.md")
+            )
+        );
+        assert_eq!(expand("echo {pages}", "a", "b", "", ""), "echo ");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn quoted_paths_reach_the_command_intact() {
+        for path in [
+            "plain",
+            "with space/and 'quotes'",
+            "$(touch pwned); `id` \\ \"x\"",
+            "new\nline",
+        ] {
+            let output = shell(&format!("printf %s {}", quote(path)))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), path);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use diaryx_core::{PageOutput, site};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::vault::slugify;
+
+/// Static host whose configuration file a build writes (`build --host`, `[hosting] provider`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HostProvider {
+    /// `_redirects` and `_headers` in the output root.
+    Netlify,
+    /// `vercel.json` in the output root (deploy the output folder as the project).
+    Vercel,
+}
+
+/// Host configuration emitted with the site.
+///
+/// Redirects come from each page's `aliases` (an alias becomes a page URL of its own, next to
+/// the page, that redirects to it: `Trip to Rome` on `pages/rome.html` adds
+/// `/pages/trip-to-rome.html`) and from the `[hosting.redirects]` table. With `clean_urls`, URLs
+/// are written without `.html` and the host is told to serve `x.html` at `/x` (Netlify already
+/// does). `headers` are sent with every response, e.g. a `Content-Security-Policy`.
+#[derive(Debug, Clone)]
+pub struct Hosting {
+    pub provider: HostProvider,
+    pub clean_urls: bool,
+    pub headers: BTreeMap<String, String>,
+    /// Extra redirects, site path -> target, written as given.
+    pub redirects: BTreeMap<String, String>,
+}
+
+/// Redirects collected as pages are written.
+#[derive(Default)]
+pub(super) struct HostPlan {
+    /// Site-relative page paths.
+    pages: Vec<String>,
+    /// Site-relative alias path -> site-relative page path.
+    aliases: Vec<(String, String)>,
+}
+
+impl HostPlan {
+    pub(super) fn add_page(&mut self, rel: &str, page: &PageOutput) {
+        self.pages.push(rel.to_string());
+        let Some(aliases) = page
+            .frontmatter
+            .get("aliases")
+            .and_then(|a| a.as_sequence())
+        else {
+            return;
+        };
+        let dir = rel.rsplit_once('/').map_or("", |(dir, _)| dir);
+        for alias in aliases.iter().filter_map(|a| a.as_str()) {
+            let name = format!("{}.html", slugify(alias));
+            let from = if dir.is_empty() {
+                name
+            } else {
+                format!("{dir}/{name}")
+            };
+            if from != rel {
+                self.aliases.push((from, rel.to_string()));
+            }
+        }
+    }
+}
+
+/// Write the host's configuration into `output`; returns the number of redirects. Aliases that
+/// name a page of the site are skipped, so no page is shadowed. With `page_folders`
+/// (`--clean-urls`), every page lives in a folder of its own and is linked by it.
+pub(super) fn write_host_config(
+    output: &Path,
+    hosting: &Hosting,
+    plan: &HostPlan,
+    page_folders: bool,
+) -> Result<usize> {
+    let url = |rel: &str| {
+        let path = if page_folders {
+            format!("/{}", site::clean_page_path(rel))
+        } else {
+            format!("/{rel}")
+        };
+        match path.strip_suffix("index.html") {
+            Some(dir) if page_folders || hosting.clean_urls => dir.to_string(),
+            _ if hosting.clean_urls => path.strip_suffix(".html").unwrap_or(&path).to_string(),
+            _ => path,
+        }
+    };
+    let mut redirects: Vec<(String, String)> = plan
+        .aliases
+        .iter()
+        .filter(|(from, _)| !plan.pages.contains(from))
+        .map(|(from, to)| (url(from), url(to)))
+        .collect();
+    redirects.sort();
+    redirects.dedup_by(|a, b| a.0 == b.0);
+    redirects.extend(hosting.redirects.clone());
+
+    let write = |name: &str, contents: String| {
+        fs::write(output.join(name), contents).with_context(|| format!("Failed writing {name}"))
+    };
+    match hosting.provider {
+        HostProvider::Netlify => {
+            let mut lines = String::new();
+            for (from, to) in &redirects {
+                lines.push_str(&format!("{from}  {to}  301\n"));
+            }
+            write("_redirects", lines)?;
+            if !hosting.headers.is_empty() {
+                let mut headers = String::from("/*\n");
+                for (name, value) in &hosting.headers {
+                    headers.push_str(&format!("  {name}: {value}\n"));
+                }
+                write("_headers", headers)?;
+            }
+        }
+        HostProvider::Vercel => {
+            let mut config = json!({
+                "redirects": redirects
+                    .iter()
+                    .map(|(from, to)| json!({ "source": from, "destination": to, "permanent": true }))
+                    .collect::<Vec<_>>(),
+            });
+            if hosting.clean_urls {
+                config["cleanUrls"] = json!(true);
+            }
+            if !hosting.headers.is_empty() {
+                config["headers"] = json!([{
+                    "source": "/(.*)",
+                    "headers": hosting
+                        .headers
+                        .iter()
+                        .map(|(key, value)| json!({ "key": key, "value": value }))
+                        .collect::<Vec<_>>(),
+                }]);
+            }
+            write("vercel.json", serde_json::to_string_pretty(&config)?)?;
+        }
+    }
+    Ok(redirects.len())
+}
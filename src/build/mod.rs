@@ -1,11 +1,44 @@
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result, anyhow};
-use diaryx_core::{CoreBuildOptions, PageOutput, build_site};
-use serde_json::json;
+use diaryx_core::remote::RemoteFs;
+use diaryx_core::{
+    CoreBuildOptions, FileMetadata, HeldBack, PageOutput, SiteShape, Warning, build_site_streaming,
+    build_site_streaming_cached, output, site,
+};
+
+use time::OffsetDateTime;
 
 use crate::BuildOptions;
+use crate::profile::Profiler;
+
+mod a11y;
+mod assets;
+mod encrypt;
+mod gh_pages;
+mod graph_page;
+mod history;
+mod hooks;
+mod hosting;
+mod notify;
+mod pipe;
+mod pwa;
+mod query_table;
+mod remote;
+mod report;
+mod size;
+mod storage;
+mod watch;
+
+pub(crate) use encrypt::PageEncryption;
+pub(crate) use gh_pages::GhPages;
+pub(crate) use hosting::{HostProvider, Hosting};
+use notify::{ConsoleNotifier, JsonLinesNotifier, RebuildNotifier};
+pub(crate) use report::OutputFormat;
+pub(crate) use size::SizeBudget;
+pub(crate) use storage::is_store_url;
 
 /// Adapter build module
 ///
@@ -14,174 +47,428 @@ use crate::BuildOptions;
 /// (`diaryx-core`), which performs parsing, traversal, link rewriting, and HTML body rendering.
 ///
 /// High-level steps:
-/// 1. Invoke `diaryx_core::build_site` with a filesystem shim.
+/// 1. Invoke `diaryx_core::build_site_streaming` with a filesystem shim (which reads `s3://` /
+///    `webdav://` vaults from object storage and `.zip` / `.diaryx` archives, decrypted if need be,
+///    in memory, and fetches `http(s)` pages from the hosts allowed
+///    with `--allow-remote`).
 /// 2. (Core already rewrites internal .md links and resource paths.)
-/// 3. Wrap raw HTML bodies in a full document shell (metadata rows minimal for now).
-/// 4. Emit pages to disk (respecting flat vs nested).
-/// 5. Copy attachment assets (non-.md relative resources) planned by core into output/assets/ (or equivalent).
-/// 6. Optionally emit a JSON model.
-/// 7. Enforce `--strict` (treat warnings as errors).
+/// 3. As each page arrives: expand `diaryx-query` blocks into tables, check its `version` against
+///    git history (and append a changelog with `--changelog`), encrypt it when it is not public
+///    (`--encrypt`; core then keeps such pages out of the public ones, and attachments used only
+///    by encrypted pages are left out), wrap its HTML body in a full document shell and write it to disk
+///    (respecting flat vs nested). The output directory is only reset once the first page is
+///    ready, so a build that fails early leaves the previous site untouched.
+/// 4. Copy attachment assets (non-.md relative resources) planned by core into output/assets/,
+///    in parallel, skipping destinations that already hold identical content.
+/// 5. Optionally emit the model (page metadata and the attachment manifest; rendered HTML is
+///    not kept) as JSON, CBOR or MessagePack.
+/// 6. Optionally add the graph page (`--graph-page`), the static host's configuration
+///    ([`Hosting`]) and the GitHub Pages files ([`GhPages`]), check the pages and stylesheets
+///    for accessibility problems (`--check-a11y`), check the output against the `[budget]` and
+///    report its size changes (`--size-report`), then write a (signed) manifest of the output
+///    files.
+/// 7. Enforce `--strict` (treat warnings as errors) and a failing `[budget]`.
 /// 8. Print a completion line (always) including warning count.
+///
+/// The `[hooks]` of `diaryx.toml` run around it: `pre_build` commands before step 1 and
+/// `post_build` commands after a successful build (see [`hooks::run`]).
+///
+/// With `--output -`, [`pipe::write_stdout`] renders the single input document to stdout instead.
+/// With `--watch`, [`watch::run_watch`] repeats the build as sources change, rewriting only the
+/// pages affected by each change, and reports each result to [`RebuildNotifier`]s (the console,
+/// and a JSON event stream with `--watch-events`).
 pub fn run_build(opts: BuildOptions) -> Result<()> {
-    let real_fs = RealFs;
-    let entry_str = opts
+    if opts.output == Path::new("-") {
+        return pipe::write_stdout(&opts);
+    }
+    if opts.watch {
+        let mut notifiers: Vec<Box<dyn RebuildNotifier>> = vec![Box::new(ConsoleNotifier {
+            stderr: opts.watch_events.as_deref() == Some(Path::new("-")),
+            quiet: opts.quiet,
+        })];
+        if let Some(path) = &opts.watch_events {
+            notifiers.push(Box::new(JsonLinesNotifier::open(path)?));
+        }
+        return watch::run_watch(&opts, &mut notifiers);
+    }
+    let json = opts.output_format == OutputFormat::Json;
+    let built = match hooks::run(&opts, hooks::Stage::PreBuild)
+        .and_then(|()| write_site_with(&opts, None))
+    {
+        Ok(built) => built,
+        Err(e) => {
+            if json {
+                report::print_json(&opts, None, Some(&e))?;
+            }
+            return Err(e);
+        }
+    };
+    let strict = enforce_strict(&opts, &built);
+    let post_build = || {
+        hooks::run(
+            &opts,
+            hooks::Stage::PostBuild {
+                pages: built.pages,
+                warnings: built.warnings.len(),
+            },
+        )
+    };
+
+    if json {
+        // The report covers the hooks too, so it is printed once they are done.
+        let result = strict.and_then(|()| post_build());
+        report::print_json(&opts, Some(&built), result.as_ref().err())?;
+        return result;
+    }
+    if !opts.quiet {
+        println!(
+            "[diaryx] build completed -> {} (warnings: {})",
+            opts.output.display(),
+            built.warnings.len()
+        );
+        print_held_back(&opts, &built.held_back);
+    }
+
+    strict?;
+    post_build()
+}
+
+/// List the drafts and scheduled entries a build left out.
+fn print_held_back(opts: &BuildOptions, held_back: &[HeldBack]) {
+    if held_back.is_empty() {
+        return;
+    }
+    println!(
+        "[diaryx] held back {} entr{} (publish with --drafts / --future):",
+        held_back.len(),
+        if held_back.len() == 1 { "y" } else { "ies" }
+    );
+    let root = opts.input.parent().unwrap_or(Path::new(""));
+    for entry in held_back {
+        let path = Path::new(&entry.source_path);
+        let path = path.strip_prefix(root).unwrap_or(path).display();
+        match &entry.published {
+            Some(at) => println!("  scheduled for {at}: {} ({path})", entry.title),
+            None => println!("  draft: {} ({path})", entry.title),
+        }
+    }
+}
+
+/// What a build wrote.
+pub(crate) struct Built {
+    pub(crate) pages: usize,
+    pub(crate) warnings: Vec<Warning>,
+    /// Drafts and entries scheduled for later, left out.
+    pub(crate) held_back: Vec<HeldBack>,
+}
+
+/// Steps 1-7 of [`run_build`]: write the site and return the number of warnings.
+pub(crate) fn write_site(opts: &BuildOptions) -> Result<usize> {
+    let built = write_site_with(opts, None)?;
+    enforce_strict(opts, &built)?;
+    Ok(built.warnings.len())
+}
+
+/// Step 7: with `--strict`, fail a build that produced warnings. The output is written first
+/// (mirrors prior behavior; change policy if you prefer pre-emission fail).
+fn enforce_strict(opts: &BuildOptions, built: &Built) -> Result<()> {
+    if opts.strict && !built.warnings.is_empty() {
+        return Err(crate::exit::StrictWarnings(built.warnings.len()).into());
+    }
+    let over_budget = built
+        .warnings
+        .iter()
+        .filter(|w| w.code == "size-budget")
+        .count();
+    if over_budget > 0 && opts.budget.as_ref().is_some_and(|budget| budget.fail) {
+        return Err(crate::exit::ValidationFailed(format!(
+            "Size budget exceeded ({over_budget} problem(s)); see the warnings"
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Steps 1-6 of [`run_build`], optionally within a watch session: unchanged bodies reuse cached
+/// renders, and a partial rebuild only rewrites the pages its plan selects (the output folder is
+/// not reset).
+fn write_site_with(opts: &BuildOptions, session: Option<&mut watch::Session>) -> Result<Built> {
+    let (cache, mut plan) = match session {
+        Some(watch::Session { cache, plan }) => (Some(cache), Some(plan)),
+        None => (None, None),
+    };
+    let input = opts
         .input
         .to_str()
-        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?
-        .to_string();
+        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?;
+    let vault = storage::VaultFs::open(input, opts.identity.as_deref())?;
+    let entry_str = vault.entry().to_string();
+    let vault_fs = RemoteFs::new(
+        vault,
+        remote::HttpProvider::new(opts.remote_cache.clone(), opts.verbose),
+        opts.allow_remote.clone(),
+    );
 
-    let core_opts = CoreBuildOptions {
-        include_nonpublic: opts.include_nonpublic,
-        flat: opts.flat,
-        strict: opts.strict,
-        rewrite_links: true,
-    };
+    let core_opts = core_options(opts);
+    // Read before the output folder is reset.
+    let previous_sizes = opts
+        .size_report
+        .then(|| crate::sign::manifest_sizes(&opts.output))
+        .flatten();
 
     if opts.verbose {
         eprintln!("[build] core build start");
     }
-    let mut artifacts =
-        build_site(&entry_str, core_opts, &real_fs).with_context(|| "Core build failed")?;
-
-    // (Removed adjust_links_for_nested_layout: core now emits layout-aware links)
+    let mut profiler = Profiler::new(opts.profile);
 
-    // Site emission
-    if opts.output.exists() {
-        fs::remove_dir_all(&opts.output)
-            .with_context(|| format!("Failed removing {}", opts.output.display()))?;
+    let mut output_ready = false;
+    // Slugs of the pages encrypted with `--encrypt`.
+    let mut encrypted: HashSet<String> = HashSet::new();
+    let mut pages_json: Vec<serde_json::Value> = Vec::new();
+    let mut pwa_plan = opts.pwa.then(pwa::PwaPlan::default);
+    let mut host_plan = opts.hosting.is_some().then(hosting::HostPlan::default);
+    let mut queries = query_table::QueryTables::new(&entry_str, &vault_fs, core_opts.clone());
+    let mut page_warnings = Vec::new();
+    let history = (opts.changelog || !opts.no_version_check)
+        .then(|| history::History::open(&opts.input))
+        .flatten();
+    let mut license_rows: Vec<site::LicenseRow> = Vec::new();
+    // Pages without their HTML, for the graph page.
+    let mut graph_pages: Vec<PageOutput> = Vec::new();
+    let partial = plan.as_ref().is_some_and(|plan| plan.is_partial());
+    let sink = |site: &SiteShape, mut page: PageOutput| {
+        if !output_ready {
+            if !partial {
+                prepare_output(opts)?;
+            }
+            output_ready = true;
+        }
+        let has_queries = queries.expand(&mut page, &mut page_warnings)?;
+        if let Some(history) = &history {
+            if !opts.no_version_check
+                && let Some(warning) = history.check_version(&page)
+            {
+                page.warnings.push(warning.clone());
+                page_warnings.push(warning);
+            }
+            if opts.changelog {
+                history.append_changelog(&mut page);
+            }
+        }
+        if let Some(encryption) = &opts.encrypt
+            && encrypt::is_private(&page)
+        {
+            encryption.encrypt_page(&mut page)?;
+            encrypted.insert(page.id.clone());
+        }
+        let write = plan.as_deref_mut().is_none_or(|plan| {
+            plan.page_written(output_path(opts, &page), site, &page, has_queries)
+        });
+        if let Some(plan) = host_plan.as_mut() {
+            plan.add_page(&page.path, &page);
+        }
+        write_page(opts, site, &page, pwa_plan.as_mut(), write)?;
+        if site.multi_page {
+            license_rows.push(site::LicenseRow::new(&page));
+        }
+        if opts.emit_model.is_some() {
+            pages_json.push(site::page_model(&page));
+        }
+        if opts.graph_page && site.multi_page {
+            graph_pages.push(PageOutput {
+                html: String::new(),
+                metadata_html: String::new(),
+                ..page
+            });
+        }
+        Ok(())
+    };
+    let mut summary = match cache {
+        Some(cache) => {
+            build_site_streaming_cached(&entry_str, core_opts.clone(), &vault_fs, cache, sink)
+        }
+        None => build_site_streaming(&entry_str, core_opts.clone(), &vault_fs, sink),
+    }
+    .with_context(|| "Core build failed")?;
+    if let Some(plan) = plan {
+        plan.finish(summary.multi_page, &summary.attachments);
+    }
+    summary.warnings.extend(page_warnings);
+    summary.warnings.extend(vault_fs.take_warnings());
+    if !encrypted.is_empty() {
+        summary.warnings.extend(encrypt::withhold_attachments(
+            &mut summary.attachments,
+            &encrypted,
+        ));
+    }
+    profiler.phase("core build + page writes");
+    if opts.verbose {
+        eprintln!("[build] {} page(s) written", summary.pages_emitted);
     }
-    fs::create_dir_all(&opts.output)
-        .with_context(|| format!("Failed creating {}", opts.output.display()))?;
 
-    if !opts.no_default_css {
-        fs::create_dir_all(opts.output.join("css"))?;
-        fs::write(opts.output.join("css/style.css"), DEFAULT_CSS.as_bytes())
-            .context("Writing CSS failed")?;
-    }
-
-    // Page writing
-    if artifacts.multi_page {
-        if opts.flat {
-            // Root index becomes index.html, others <slug>.html
-            for page in &artifacts.pages {
-                let html_doc =
-                    wrap_full_html(page, artifacts.multi_page, opts.flat, !opts.no_default_css);
-                let out_name = &page.file_name; // already computed in core
-                fs::write(opts.output.join(out_name), html_doc)
-                    .with_context(|| format!("Failed writing page {}", out_name))?;
-            }
+    // Attachment asset copying (core produced a copy plan with rewritten HTML already); runs
+    // with an empty plan too, so assets left from an earlier build are removed
+    let report = assets::copy_attachments(
+        &summary.attachments,
+        vault_fs.local(),
+        &opts.output,
+        opts.verbose,
+    );
+    summary.warnings.extend(report.warnings);
+    if opts.verbose {
+        if summary.attachments.is_empty() {
+            eprintln!(
+                "[build] no attachments to copy ({} stale removed)",
+                report.removed_stale
+            );
         } else {
-            // Nested: root index at output/index.html, others under /pages
-            let pages_dir = opts.output.join("pages");
-            fs::create_dir_all(&pages_dir)
-                .with_context(|| format!("Failed creating {}", pages_dir.display()))?;
-            for page in &artifacts.pages {
-                let html_doc =
-                    wrap_full_html(page, artifacts.multi_page, opts.flat, !opts.no_default_css);
-                if page.is_root_index {
-                    fs::write(opts.output.join("index.html"), html_doc)
-                        .context("Failed writing root index.html")?;
-                } else {
-                    let fname = page
-                        .file_name
-                        .strip_prefix("index.")
-                        .map(|_| format!("{}.html", page.id))
-                        .unwrap_or_else(|| page.file_name.clone());
-                    fs::write(pages_dir.join(fname), html_doc)
-                        .with_context(|| "Failed writing nested page")?;
-                }
+            eprintln!(
+                "[build] attachment copy complete ({} planned, {} copied, {} unchanged, {} stale removed)",
+                summary.attachments.len(),
+                report.copied,
+                report.skipped,
+                report.removed_stale
+            );
+            for line in assets::type_summary(&summary.attachments) {
+                eprintln!("[build]   {line}");
             }
         }
-    } else {
-        // Single page => only one page artifact, designated index.html
-        let page = artifacts.pages.first().unwrap();
-        let html_doc = wrap_full_html(page, false, opts.flat, !opts.no_default_css);
-        fs::write(opts.output.join("index.html"), html_doc)
-            .context("Failed writing single index.html")?;
-    }
-
-    // Attachment asset copying (core produced a copy plan with rewritten HTML already)
-    if !artifacts.attachments.is_empty() {
-        let mut copied = 0usize;
-        for att in &artifacts.attachments {
-            let target_path = opts.output.join(&att.target);
-            if let Some(parent) = target_path.parent() {
-                if let Err(e) = fs::create_dir_all(parent) {
-                    artifacts.warnings.push(format!(
-                        "Failed to create asset directory for '{}': {e}",
-                        target_path.display()
-                    ));
-                    continue;
-                }
+    }
+    profiler.phase("attachments");
+
+    if let Some(html) = site::licenses_page(&license_rows, !opts.no_default_css) {
+        write_extra_page(
+            opts,
+            site::LICENSES_PATH,
+            "Licenses",
+            html,
+            pwa_plan.as_mut(),
+        )?;
+    }
+
+    if !graph_pages.is_empty() {
+        if graph_pages.iter().any(|p| p.path == graph_page::GRAPH_PATH) {
+            summary.warnings.push(Warning::new(
+                "other",
+                format!(
+                    "A page is already written to {}; the graph page was not generated",
+                    graph_page::GRAPH_PATH
+                ),
+            ));
+        } else {
+            let root = Path::new(&entry_str).parent().unwrap_or(Path::new(""));
+            let mut graph = crate::graph::Graph::new(root, &graph_pages, &vault_fs);
+            // Body links are read from the sources; a sealed page's must not show.
+            graph
+                .links
+                .retain(|link| link.kind != "body" || !encrypted.contains(&link.source));
+            for node in &mut graph.nodes {
+                node.backlinks.retain(|slug| !encrypted.contains(slug));
             }
-            match fs::copy(&att.source, &target_path) {
-                Ok(_) => {
-                    copied += 1;
-                    if opts.verbose {
-                        eprintln!(
-                            "[asset] {} -> {}",
-                            att.source,
-                            target_path
-                                .strip_prefix(&opts.output)
-                                .unwrap_or(&target_path)
-                                .display()
-                        );
-                    }
-                }
-                Err(e) => {
-                    artifacts.warnings.push(format!(
-                        "Failed to copy attachment '{}' -> '{}': {e}",
-                        att.source,
-                        target_path.display()
-                    ));
-                }
+            let to_root = output::root_prefix(&output_path_of(opts, graph_page::GRAPH_PATH));
+            for node in &mut graph.nodes {
+                node.page = to_root.clone() + page_url(opts, &output_path_of(opts, &node.page));
             }
+            let html = graph_page::graph_page(&graph, !opts.no_default_css);
+            write_extra_page(
+                opts,
+                graph_page::GRAPH_PATH,
+                "Graph",
+                html,
+                pwa_plan.as_mut(),
+            )?;
+        }
+    }
+
+    if let Some(plan) = &pwa_plan {
+        pwa::write_pwa(
+            &opts.output,
+            plan,
+            &summary.attachments,
+            !opts.no_default_css,
+        )?;
+        if opts.verbose {
+            eprintln!("[build] wrote web app manifest, icons and service worker");
+        }
+    }
+
+    if let (Some(hosting), Some(plan)) = (&opts.hosting, &host_plan) {
+        let redirects = hosting::write_host_config(&opts.output, hosting, plan, opts.clean_urls)?;
+        if opts.verbose {
+            eprintln!(
+                "[build] wrote {:?} configuration ({redirects} redirect(s))",
+                hosting.provider
+            );
         }
+    }
+
+    if let Some(gh) = &opts.gh_pages {
+        gh_pages::write_gh_pages(&opts.output, gh, !opts.no_default_css)?;
         if opts.verbose {
             eprintln!(
-                "[build] attachment copy complete ({} planned, {} copied)",
-                artifacts.attachments.len(),
-                copied
+                "[build] wrote GitHub Pages files (path prefix {}{})",
+                gh.path_prefix,
+                gh.cname
+                    .as_deref()
+                    .map(|c| format!(", CNAME {c}"))
+                    .unwrap_or_default()
             );
         }
-    } else if opts.verbose {
-        eprintln!("[build] no attachments to copy");
     }
 
-    // Optional JSON model
-    if opts.emit_json {
-        let pages_json: Vec<_> = artifacts
-            .pages
-            .iter()
-            .map(|p| {
-                json!({
-                  "id": p.id,
-                  "title": p.title,
-                  "file_name": p.file_name,
-                  "is_root_index": p.is_root_index,
-                  "is_index": p.is_index,
-                  "parents": p.parents,
-                  "children": p.children,
-                  "warnings": p.warnings,
-                  "frontmatter": p.frontmatter, // raw YAML value -> serialized JSON
-                })
-            })
-            .collect();
-
-        let model = json!({
-          "multi_page": artifacts.multi_page,
-          "root_slug": artifacts.root_slug,
-          "pages": pages_json,
-          "warnings": artifacts.warnings,
-        });
-        fs::write(
-            opts.output.join("diaryx-data.json"),
-            serde_json::to_string_pretty(&model).unwrap(),
-        )
-        .context("Failed writing diaryx-data.json")?;
+    // Optional model (attachments hashed as published)
+    if let Some(format) = opts.emit_model {
+        for attachment in &mut summary.attachments {
+            attachment.sha256 = fs::read(opts.output.join(&attachment.target))
+                .ok()
+                .map(|data| crate::archive::sha256_hex(&data));
+        }
+        let model = site::site_model(
+            summary.multi_page,
+            summary.root_slug.as_deref(),
+            pages_json,
+            &summary.attachments,
+            &summary.warnings,
+        );
+        let size = crate::model::write_model(&opts.output, format, &model)?;
+        profiler.phase("model");
+        if opts.verbose {
+            eprintln!("[build] wrote {} ({size} bytes)", format.file_name());
+        }
+    }
+
+    if opts.check_a11y {
+        summary.warnings.extend(a11y::audit(&opts.output)?);
+        profiler.phase("accessibility check");
+    }
+
+    // Sizes once every published file is written (the manifest describes them, so it is left out).
+    if opts.size_report || opts.budget.is_some() {
+        let sizes = size::output_sizes(&opts.output)?;
+        if opts.size_report {
+            for line in size::report(previous_sizes.as_ref(), &sizes) {
+                eprintln!("[size] {line}");
+            }
+        }
+        if let Some(budget) = &opts.budget {
+            summary.warnings.extend(budget.check(&sizes));
+        }
+    }
+
+    // Manifest last, so it covers every file written above.
+    if opts.manifest {
+        let root = crate::sign::write_manifest(&opts.output, opts.sign.as_deref())?;
+        if opts.verbose {
+            eprintln!(
+                "[build] wrote {} (root hash {root}){}",
+                crate::sign::MANIFEST_PATH,
+                if opts.sign.is_some() { ", signed" } else { "" }
+            );
+        }
     }
+    profiler.report();
 
-    let warning_count = artifacts.warnings.len();
+    let warning_count = summary.warnings.len();
 
     if opts.verbose {
         if warning_count > 0 {
@@ -189,7 +476,7 @@ pub fn run_build(opts: BuildOptions) -> Result<()> {
                 "[warn] {} warning(s) encountered during build:",
                 warning_count
             );
-            for w in &artifacts.warnings {
+            for w in &summary.warnings {
                 eprintln!("  - {}", w);
             }
         } else {
@@ -197,30 +484,178 @@ pub fn run_build(opts: BuildOptions) -> Result<()> {
         }
     }
 
-    if opts.strict && warning_count > 0 {
-        // Fail after emitting artifacts (mirrors prior behavior; change policy if you prefer pre-emission fail)
-        return Err(anyhow!(
-            "Strict mode: build failed due to {} warning(s)",
-            warning_count
-        ));
+    Ok(Built {
+        pages: summary.pages_emitted,
+        warnings: summary.warnings,
+        held_back: summary.held_back,
+    })
+}
+
+/// Core options matching the CLI build options.
+fn core_options(opts: &BuildOptions) -> CoreBuildOptions {
+    CoreBuildOptions {
+        include_nonpublic: opts.include_nonpublic,
+        audience: opts.audience.clone(),
+        seal_private: opts.encrypt.is_some(),
+        layout: opts.layout,
+        flat: false,
+        strict: opts.strict,
+        rewrite_links: true,
+        large_body_bytes: opts.large_body_bytes,
+        smart_punctuation: opts.smart_punctuation,
+        figures: opts.figures,
+        pdf_previews: opts.pdf_previews,
+        exclude: opts.exclude.clone(),
+        since: opts.since.clone(),
+        until: opts.until.clone(),
+        tags: opts.tags.clone(),
+        now: Some(OffsetDateTime::now_utc()),
+        drafts: opts.drafts,
+        future: opts.future,
+        as_root: opts.as_root,
+        env: Some(std::env::vars().collect()),
+        infer_dates: opts.infer_dates,
     }
+}
 
-    // Always print final completion line with warning count
-    println!(
-        "[diaryx] build completed -> {} (warnings: {})",
-        opts.output.display(),
-        warning_count
-    );
+/// Reset the output directory and emit shared static files (CSS).
+///
+/// Everything except `assets/` is removed; previously copied attachments are kept so unchanged
+/// files can be skipped (stale ones are pruned after copying).
+fn prepare_output(opts: &BuildOptions) -> Result<()> {
+    if opts.output.is_dir() {
+        for entry in fs::read_dir(&opts.output)
+            .with_context(|| format!("Failed reading {}", opts.output.display()))?
+        {
+            let path = entry?.path();
+            if path.file_name().is_some_and(|n| n == "assets") && path.is_dir() {
+                continue;
+            }
+            let removed = if path.is_dir() {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+            removed.with_context(|| format!("Failed removing {}", path.display()))?;
+        }
+    } else if opts.output.exists() {
+        fs::remove_file(&opts.output)
+            .with_context(|| format!("Failed removing {}", opts.output.display()))?;
+    }
+    fs::create_dir_all(&opts.output)
+        .with_context(|| format!("Failed creating {}", opts.output.display()))?;
+
+    if !opts.no_default_css {
+        let css_path = opts.output.join(site::CSS_PATH);
+        if let Some(parent) = css_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(css_path, site::DEFAULT_CSS.as_bytes()).context("Writing CSS failed")?;
+    }
+    Ok(())
+}
+
+/// Write a single page according to the layout (single page, flat, or nested under pages/).
+/// With `--pwa`, the page also gets the app head tags and is recorded in the plan (even when
+/// `write` is false because a partial rebuild keeps the page already on disk).
+fn write_page(
+    opts: &BuildOptions,
+    site: &SiteShape,
+    page: &PageOutput,
+    pwa_plan: Option<&mut pwa::PwaPlan>,
+    write: bool,
+) -> Result<()> {
+    let mut html_doc = site::wrap_full_html(page, site.multi_page, !opts.no_default_css);
+    if opts.graph_page && site.multi_page && page.is_root_index {
+        html_doc = graph_page::link_graph(&html_doc, graph_page::GRAPH_PATH);
+    }
+    let (rel, mut html_doc) = place(opts, page.path.clone(), html_doc);
+    if let Some(plan) = pwa_plan {
+        plan.add_page(
+            page_url(opts, &rel),
+            &page.title,
+            page.is_root_index,
+            &html_doc,
+        );
+        html_doc = html_doc.replacen("</head>", &(pwa::head_tags(&rel) + "</head>"), 1);
+    }
+    if !write {
+        return Ok(());
+    }
+    let path = opts.output.join(&rel);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed creating {}", parent.display()))?;
+    }
+    fs::write(&path, html_doc).with_context(|| format!("Failed writing page {}", rel))
+}
+
+/// Site-relative path `page` is written to.
+fn output_path(opts: &BuildOptions, page: &PageOutput) -> String {
+    output_path_of(opts, &page.path)
+}
 
+/// Site-relative path a page laid out at `rel` is written to.
+fn output_path_of(opts: &BuildOptions, rel: &str) -> String {
+    if opts.clean_urls {
+        site::clean_page_path(rel)
+    } else {
+        rel.to_string()
+    }
+}
+
+/// Write a generated page (not from the vault) laid out at `rel`, recording it in the PWA plan.
+fn write_extra_page(
+    opts: &BuildOptions,
+    rel: &str,
+    title: &str,
+    html: String,
+    pwa_plan: Option<&mut pwa::PwaPlan>,
+) -> Result<()> {
+    let (rel, mut html) = place(opts, rel.to_string(), html);
+    if let Some(plan) = pwa_plan {
+        plan.add_page(page_url(opts, &rel), title, false, &html);
+        html = html.replacen("</head>", &(pwa::head_tags(&rel) + "</head>"), 1);
+    }
+    let path = opts.output.join(&rel);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed creating {}", parent.display()))?;
+    }
+    fs::write(&path, html).with_context(|| format!("Failed writing {rel}"))?;
+    if opts.verbose {
+        eprintln!("[build] wrote {rel}");
+    }
     Ok(())
 }
 
+/// With `--clean-urls`, move a page written at `rel` into a folder of its own
+/// ([`site::clean_page_path`]), rewriting its relative URLs to match. Returns the final path and
+/// HTML.
+fn place(opts: &BuildOptions, rel: String, html: String) -> (String, String) {
+    if !opts.clean_urls {
+        return (rel, html);
+    }
+    (
+        site::clean_page_path(&rel),
+        site::rewrite_clean_urls(&html, &rel),
+    )
+}
+
+/// The URL a page at `rel` is linked by: with `--clean-urls`, its folder.
+fn page_url<'a>(opts: &BuildOptions, rel: &'a str) -> &'a str {
+    match rel.strip_suffix("index.html") {
+        Some(folder) if opts.clean_urls && !folder.is_empty() => folder,
+        _ => rel,
+    }
+}
+
 /// Real filesystem implementation of the core FileProvider.
-struct RealFs;
+pub(crate) struct RealFs;
 
 impl diaryx_core::FileProvider for RealFs {
     fn read_to_string(&self, path: &str) -> Result<String> {
-        Ok(fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?)
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))
     }
     fn exists(&self, path: &str) -> bool {
         Path::new(path).exists()
@@ -253,65 +688,122 @@ impl diaryx_core::FileProvider for RealFs {
             .file_name()
             .map(|f| f.to_string_lossy().to_string())
     }
-}
-
-/// Adjust internal links produced by core rewriting to account for a nested layout (pages/).
-/// Core rewrites as if everything is in one directory:
-/// - root index: index.html
-/// - other pages: <slug>.html
-/// When we move non-root pages into pages/<slug>.html we must fix:
-/// - In root page: links to <slug>.html -> pages/<slug>.html
-/// - In non-root page content: links to index.html -> ../index.html
-
-/// Wrap the core-rendered HTML content inside a full HTML document + metadata header.
-/// This is intentionally minimal; you can later replicate the full rich metadata grid.
-fn wrap_full_html(page: &PageOutput, multi_page: bool, flat: bool, include_css: bool) -> String {
-    // Desired minimal layout:
-    // 1. Metadata (already HTML from core: page.metadata_html, includes converted markdown links & contents links)
-    // 2. Line break (semantic separation via <hr /> or simple margin in CSS)
-    // 3. Content body
-    //
-    // Removed: Title <h1>, relationship blocks (Part Of / Contents duplicates) and duplicate contents list.
-    let mut out = String::new();
-    out.push_str("<!doctype html><html lang=\"en\"><head><meta charset=\"utf-8\" />");
-    out.push_str("<meta name=\"viewport\" content=\"width=device-width,initial-scale=1\" />");
-    out.push_str("<title>");
-    html_esc_append(&mut out, &page.title);
-    out.push_str("</title>");
-    if include_css {
-        out.push_str("<link rel=\"stylesheet\" href=\"");
-        if multi_page && !flat && !page.is_root_index {
-            out.push_str("../css/style.css");
-        } else {
-            out.push_str("css/style.css");
+    fn metadata(&self, path: &str) -> Option<FileMetadata> {
+        let meta = fs::metadata(path).ok()?;
+        Some(FileMetadata {
+            created: meta.created().ok().map(OffsetDateTime::from),
+            modified: meta.modified().ok().map(OffsetDateTime::from),
+            size: Some(meta.len()),
+        })
+    }
+    fn list_files(&self, dir: &str) -> Option<Vec<String>> {
+        let mut files = Vec::new();
+        let mut pending = vec![dir.to_string()];
+        while let Some(dir) = pending.pop() {
+            let listing = fs::read_dir(if dir.is_empty() { "." } else { &dir }).ok()?;
+            for item in listing.flatten() {
+                let name = item.file_name().to_string_lossy().into_owned();
+                if name.starts_with('.') {
+                    continue;
+                }
+                let path = self.join(&dir, &name);
+                // Symlinked folders are not followed, so a link cycle cannot loop forever.
+                match item.file_type() {
+                    Ok(kind) if kind.is_dir() => pending.push(path),
+                    Ok(_) => files.push(path),
+                    Err(_) => {}
+                }
+            }
         }
-        out.push_str("\" />");
-    }
-    out.push_str("</head><body>");
-    // Metadata list placed directly under body so it becomes a grid item (no wrapper header)
-    out.push_str(&page.metadata_html);
-    out.push_str("<main class=\"content\">");
-    out.push_str(&page.html);
-    out.push_str("</main></body></html>");
-    out
+        Some(files)
+    }
 }
 
-fn html_esc_append(out: &mut String, s: &str) {
-    for ch in s.chars() {
-        match ch {
-            '&' => out.push_str("&amp;"),
-            '<' => out.push_str("&lt;"),
-            '>' => out.push_str("&gt;"),
-            '"' => out.push_str("&quot;"),
-            '\'' => out.push_str("&#39;"),
-            _ => out.push(ch),
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::config::EncryptConfig;
+
+    fn files_under(dir: &Path, out: &mut Vec<(PathBuf, String)>) {
+        for entry in fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                files_under(&path, out);
+            } else {
+                let text = String::from_utf8_lossy(&fs::read(&path).unwrap()).into_owned();
+                out.push((path, text));
+            }
         }
     }
-}
-
-/// Convert a YAML value into a concise single-line string.
 
-// Helper adapter for closure capturing
+    #[test]
+    fn sealed_pages_leak_into_no_other_output() {
+        let vault = tempfile::tempdir().unwrap();
+        let write = |name: &str, text: &str| fs::write(vault.path().join(name), text).unwrap();
+        write(
+            "index.md",
+            "---\ntitle: Journal\nvisibility: public\nthis_file_is_root_index: true\nrecent_entries: 3\ncontents:\n  - \"[Walk](walk.md)\"\n  - \"[Private](secret.md)\"\n---\n```diaryx-query\nselect title, mood\n```\n",
+        );
+        write(
+            "walk.md",
+            "---\ntitle: Walk\nvisibility: public\ncreated: 2025-01-01\nmood: calm\n---\nA walk by the river.\n",
+        );
+        write(
+            "secret.md",
+            "---\ntitle: Secret plans\nvisibility: private\ncreated: 2025-02-01\nauthor: Mallory Hidden\nmood: anxious\ntags: [bankingx]\n---\nMy bank PIN is 4242, see [the walk](walk.md).\n",
+        );
+        let site = vault.path().join("site");
+        let args = crate::Cli::try_parse_from([
+            "diaryx".as_ref(),
+            "build".as_ref(),
+            "--input".as_ref(),
+            vault.path().join("index.md").as_os_str(),
+            "--output".as_ref(),
+            site.as_os_str(),
+            "--include-nonpublic".as_ref(),
+            "--emit-json".as_ref(),
+            "--graph-page".as_ref(),
+        ])
+        .unwrap();
+        let crate::Command::Build(args) = args.command else {
+            unreachable!()
+        };
+        let mut opts = BuildOptions::from_args(&args, None).unwrap();
+        opts.encrypt = Some(
+            PageEncryption::derive(
+                "passphrase",
+                EncryptConfig {
+                    iterations: Some(1000),
+                    hint: None,
+                },
+            )
+            .unwrap(),
+        );
+        write_site(&opts).unwrap();
 
-// Simplified CSS (subset of earlier styling). Extend as needed.
-const DEFAULT_CSS: &str = include_str!("default.css");
+        let mut files = Vec::new();
+        files_under(&site, &mut files);
+        let index = &files
+            .iter()
+            .find(|(path, _)| path == &site.join("index.html"))
+            .unwrap()
+            .1;
+        assert!(index.contains("A walk by the river.") && index.contains("calm"));
+        assert!(
+            files
+                .iter()
+                .any(|(_, text)| text.contains("encrypted-page"))
+        );
+        for (path, text) in &files {
+            for secret in ["4242", "Secret plans", "Mallory", "anxious", "bankingx"] {
+                assert!(
+                    !text.contains(secret),
+                    "{} shows '{secret}'",
+                    path.display()
+                );
+            }
+        }
+    }
+}
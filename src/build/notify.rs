@@ -0,0 +1,126 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use diaryx_core::Warning;
+use serde::Serialize;
+
+/// The result of one build in a `--watch` session.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct RebuildEvent {
+    /// Source files whose change started the build (empty for the first build).
+    pub changed: Vec<String>,
+    /// Wall time of the build, in milliseconds.
+    pub elapsed_ms: u64,
+    #[serde(flatten)]
+    pub outcome: RebuildOutcome,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub(crate) enum RebuildOutcome {
+    Built {
+        /// Every page was rewritten (rather than only those depending on the change).
+        full: bool,
+        /// Pages written, relative to the output folder (`index.html`, `pages/a-note.html`).
+        pages: Vec<String>,
+        /// Attachments were copied again.
+        attachments: bool,
+        warnings: Vec<Warning>,
+    },
+    /// The build stopped with an error; the previous output is left in place.
+    Failed { error: String },
+}
+
+/// Subscriber to the results of a watch session's rebuilds, so whatever shows the site (a
+/// browser reloading it, an editor plugin, an external tool) is told what changed instead of
+/// polling the output folder.
+pub(crate) trait RebuildNotifier {
+    fn notify(&mut self, event: &RebuildEvent) -> Result<()>;
+}
+
+/// The `[watch]` lines printed after each rebuild.
+pub(crate) struct ConsoleNotifier {
+    /// Print to stderr, leaving stdout to an event stream.
+    pub stderr: bool,
+    /// Only report failed rebuilds (`--quiet`).
+    pub quiet: bool,
+}
+
+impl RebuildNotifier for ConsoleNotifier {
+    fn notify(&mut self, event: &RebuildEvent) -> Result<()> {
+        let elapsed = event.elapsed_ms as f64 / 1000.0;
+        let line = match &event.outcome {
+            RebuildOutcome::Failed { error } => {
+                eprintln!("[watch] rebuild failed: {error}");
+                return Ok(());
+            }
+            _ if self.quiet => return Ok(()),
+            RebuildOutcome::Built {
+                pages, warnings, ..
+            } if event.changed.is_empty() => format!(
+                "[watch] built {} page(s) in {elapsed:.2}s (warnings: {}); watching for changes",
+                pages.len(),
+                warnings.len()
+            ),
+            RebuildOutcome::Built {
+                full,
+                pages,
+                warnings,
+                ..
+            } => {
+                let what = if *full {
+                    format!("rebuilt all {} page(s)", pages.len())
+                } else if pages.is_empty() {
+                    "refreshed attachments".to_string()
+                } else {
+                    format!("refreshed {}", pages.join(", "))
+                };
+                format!(
+                    "[watch] {} changed: {what} in {elapsed:.2}s (warnings: {})",
+                    event.changed.join(", "),
+                    warnings.len()
+                )
+            }
+        };
+        if self.stderr {
+            eprintln!("{line}");
+        } else {
+            println!("{line}");
+        }
+        Ok(())
+    }
+}
+
+/// Each event as one line of JSON (`--watch-events`), flushed as soon as it is written so a
+/// reader tailing the stream sees it right away.
+pub(crate) struct JsonLinesNotifier {
+    out: Box<dyn Write>,
+}
+
+impl JsonLinesNotifier {
+    /// Stream to stdout for `-`, else append to the file at `path`.
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        let out: Box<dyn Write> = if path == Path::new("-") {
+            Box::new(io::stdout())
+        } else {
+            let file: File = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            Box::new(file)
+        };
+        Ok(Self { out })
+    }
+}
+
+impl RebuildNotifier for JsonLinesNotifier {
+    fn notify(&mut self, event: &RebuildEvent) -> Result<()> {
+        serde_json::to_writer(&mut self.out, event)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
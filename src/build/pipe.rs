@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+
+use anyhow::{Context, Result, anyhow, bail};
+use diaryx_core::memfs::InMemoryFs;
+use diaryx_core::{PageOutput, SiteShape, build_site_streaming, site};
+
+use super::core_options;
+use crate::BuildOptions;
+
+/// `build --output -`: render one document as a standalone HTML page on stdout.
+///
+/// The document comes from stdin (`--stdin`) or from `--input`, and is built on its own: the
+/// files it links to are not read, so `contents` children and attachments are reported as
+/// missing and no assets are copied. The page embeds the default stylesheet in a `<style>`
+/// element (unless `--no-default-css`), so the output needs nothing next to it. Warnings go to
+/// stderr; with `--strict` any warning fails the build before anything is written.
+pub(super) fn write_stdout(opts: &BuildOptions) -> Result<()> {
+    let path = opts
+        .input
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?
+        .to_string();
+    let text = if opts.stdin {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .context("Failed to read document from stdin")?;
+        text
+    } else {
+        fs::read_to_string(&opts.input)
+            .with_context(|| format!("Failed to read {}", opts.input.display()))?
+    };
+    let vault = InMemoryFs::new(HashMap::from([(path.clone(), text)]));
+
+    let mut page = None;
+    let summary = build_site_streaming(
+        &path,
+        core_options(opts),
+        &vault,
+        |_: &SiteShape, p: PageOutput| {
+            page = Some(p);
+            Ok(())
+        },
+    )
+    .with_context(|| "Core build failed")?;
+    for warning in &summary.warnings {
+        eprintln!("[warn] {warning}");
+    }
+    if opts.strict && !summary.warnings.is_empty() {
+        return Err(crate::exit::StrictWarnings(summary.warnings.len()).into());
+    }
+    let Some(page) = page else {
+        bail!("Nothing to render: the document is not public (pass --include-nonpublic)");
+    };
+
+    let mut html = site::wrap_full_html(&page, false, false);
+    if !opts.no_default_css {
+        let style = format!("<style>\n{}</style>", site::DEFAULT_CSS);
+        html = html.replacen("</head>", &(style + "</head>"), 1);
+    }
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(html.as_bytes())
+        .and_then(|()| stdout.flush())
+        .context("Failed writing to stdout")?;
+    if opts.verbose {
+        eprintln!("[diaryx] rendered {} to stdout", page.title);
+    }
+    Ok(())
+}
@@ -0,0 +1,267 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use diaryx_core::AttachmentPlanEntry;
+use diaryx_core::site::CSS_PATH;
+use serde_json::json;
+
+use crate::archive::crc32;
+
+/// Installable / offline output (`build --pwa`).
+///
+/// Every page links a web app manifest and registers a service worker; after the build, the
+/// manifest, two generated icons and `sw.js` are written to the output root. The worker precaches
+/// every page, the stylesheet and all attachments on install, then serves same-origin requests
+/// cache-first (caching anything else it fetches). Its cache name is derived from the built
+/// content, so publishing a changed site replaces the old cache on the next visit.
+///
+/// All URLs are relative, so the site still works when published under a sub-path. The plan is filled in as pages are written.
+#[derive(Default)]
+pub(super) struct PwaPlan {
+    /// Site-relative page paths.
+    pages: Vec<String>,
+    /// Title of the root page (the app name).
+    name: Option<String>,
+    /// Content fingerprint for the cache name.
+    hash: Fnv,
+}
+
+pub(super) const MANIFEST_PATH: &str = "manifest.webmanifest";
+pub(super) const WORKER_PATH: &str = "sw.js";
+const ICON_SIZES: [u32; 2] = [192, 512];
+const THEME_COLOR: [u8; 3] = [0x1f, 0x29, 0x37];
+const PAGE_COLOR: [u8; 3] = [0xff, 0xff, 0xff];
+
+impl PwaPlan {
+    pub(super) fn add_page(&mut self, rel: &str, title: &str, is_root: bool, html: &str) {
+        if is_root || self.name.is_none() {
+            self.name = Some(title.to_string());
+        }
+        self.pages.push(rel.to_string());
+        self.hash.write(rel.as_bytes());
+        self.hash.write(html.as_bytes());
+    }
+}
+
+/// Head tags for a page at `rel` (site-relative): manifest, icon, theme colour and worker
+/// registration.
+pub(super) fn head_tags(rel: &str) -> String {
+    let prefix = "../".repeat(rel.matches('/').count());
+    format!(
+        "<link rel=\"manifest\" href=\"{prefix}{MANIFEST_PATH}\" />\
+         <meta name=\"theme-color\" content=\"{}\" />\
+         <link rel=\"apple-touch-icon\" href=\"{prefix}icons/icon-192.png\" />\
+         <script>if('serviceWorker' in navigator){{navigator.serviceWorker.register('{prefix}{WORKER_PATH}');}}</script>",
+        hex(THEME_COLOR)
+    )
+}
+
+/// Write the manifest, icons and service worker into `output`.
+pub(super) fn write_pwa(
+    output: &Path,
+    plan: &PwaPlan,
+    attachments: &[AttachmentPlanEntry],
+    include_css: bool,
+) -> Result<()> {
+    let name = plan.name.clone().unwrap_or_else(|| "Journal".to_string());
+    // Home-screen label: the name if short enough, else its first word.
+    let short_name: String = if name.chars().count() <= 12 {
+        name.clone()
+    } else {
+        name.split_whitespace()
+            .next()
+            .unwrap_or(&name)
+            .chars()
+            .take(12)
+            .collect()
+    };
+    let icons: Vec<_> = ICON_SIZES
+        .iter()
+        .map(|size| {
+            json!({
+                "src": format!("icons/icon-{size}.png"),
+                "sizes": format!("{size}x{size}"),
+                "type": "image/png",
+                "purpose": "any"
+            })
+        })
+        .collect();
+    let manifest = json!({
+        "name": name,
+        "short_name": short_name,
+        "start_url": "./",
+        "scope": "./",
+        "display": "standalone",
+        "background_color": hex(PAGE_COLOR),
+        "theme_color": hex(THEME_COLOR),
+        "icons": icons,
+    });
+    write(
+        &output.join(MANIFEST_PATH),
+        serde_json::to_string_pretty(&manifest)?.as_bytes(),
+    )?;
+
+    let mut precache = vec!["./".to_string(), MANIFEST_PATH.to_string()];
+    let mut hash = plan.hash.clone();
+    for size in ICON_SIZES {
+        let rel = format!("icons/icon-{size}.png");
+        write(&output.join(&rel), &icon(size))?;
+        precache.push(rel);
+    }
+    if include_css {
+        precache.push(CSS_PATH.to_string());
+    }
+    precache.extend(plan.pages.iter().cloned());
+    for attachment in attachments {
+        // Content changes show up as size / mtime changes.
+        if let Ok(meta) = fs::metadata(&attachment.source) {
+            hash.write(&meta.len().to_le_bytes());
+            if let Ok(modified) = meta.modified() {
+                hash.write(format!("{modified:?}").as_bytes());
+            }
+        }
+        hash.write(attachment.target.as_bytes());
+        precache.push(attachment.target.replace(' ', "%20"));
+    }
+
+    let worker = SERVICE_WORKER
+        .replace("__CACHE__", &format!("diaryx-{:016x}", hash.0))
+        .replace("__PRECACHE__", &serde_json::to_string(&precache)?);
+    write(&output.join(WORKER_PATH), worker.as_bytes())
+}
+
+const SERVICE_WORKER: &str = r#"// Generated by `diaryx build --pwa`.
+const CACHE = "__CACHE__";
+const PRECACHE = __PRECACHE__;
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(
+    caches.open(CACHE).then((cache) => cache.addAll(PRECACHE)).then(() => self.skipWaiting())
+  );
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    caches.keys()
+      .then((keys) => Promise.all(
+        keys.filter((key) => key.startsWith("diaryx-") && key !== CACHE).map((key) => caches.delete(key))
+      ))
+      .then(() => self.clients.claim())
+  );
+});
+
+self.addEventListener("fetch", (event) => {
+  const request = event.request;
+  if (request.method !== "GET" || new URL(request.url).origin !== self.location.origin) {
+    return;
+  }
+  event.respondWith(
+    caches.match(request, { ignoreSearch: true }).then((hit) => hit || fetch(request).then((response) => {
+      if (response.ok) {
+        const copy = response.clone();
+        caches.open(CACHE).then((cache) => cache.put(request, copy));
+      }
+      return response;
+    }))
+  );
+});
+"#;
+
+fn write(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed creating {}", parent.display()))?;
+    }
+    fs::write(path, bytes).with_context(|| format!("Failed writing {}", path.display()))
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+// --- Icons ------------------------------------------------------------------------------------
+
+/// App icon: a white page with text lines on the theme colour, as a 1-bit palette PNG.
+fn icon(size: u32) -> Vec<u8> {
+    let unit = |frac: f32| (size as f32 * frac) as u32;
+    let (left, right, top, bottom) = (unit(0.28), unit(0.72), unit(0.18), unit(0.82));
+    let is_page = |x: u32, y: u32| {
+        let inside = (left..right).contains(&x) && (top..bottom).contains(&y);
+        // Text lines: bands of theme colour across the middle of the page.
+        let line = (unit(0.34)..unit(0.70)).contains(&x)
+            && y > unit(0.28)
+            && y < unit(0.72)
+            && (y - unit(0.28)) % unit(0.09).max(1) < unit(0.035).max(1);
+        inside && !line
+    };
+
+    let row_bytes = size.div_ceil(8) as usize;
+    let mut raw = Vec::with_capacity((row_bytes + 1) * size as usize);
+    for y in 0..size {
+        raw.push(0); // filter: none
+        let mut row = vec![0u8; row_bytes];
+        for x in 0..size {
+            if is_page(x, y) {
+                row[(x / 8) as usize] |= 0x80 >> (x % 8);
+            }
+        }
+        raw.extend(row);
+    }
+
+    let mut ihdr = Vec::new();
+    ihdr.extend(size.to_be_bytes());
+    ihdr.extend(size.to_be_bytes());
+    ihdr.extend([1, 3, 0, 0, 0]); // 1-bit, palette, deflate, adaptive filtering, no interlace
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut png, b"IHDR", &ihdr);
+    chunk(&mut png, b"PLTE", &[THEME_COLOR, PAGE_COLOR].concat());
+    chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    png.extend(kind);
+    png.extend(data);
+    png.extend(crc32(&[kind.as_slice(), data].concat()).to_be_bytes());
+}
+
+/// zlib stream of uncompressed deflate blocks (icons are small enough not to need compression).
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = data.chunks(0xffff).collect();
+    for (i, block) in blocks.iter().enumerate() {
+        out.push(u8::from(i + 1 == blocks.len()));
+        let len = block.len() as u16;
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(*block);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend(((b << 16) | a).to_be_bytes());
+    out
+}
+
+/// FNV-1a, for a cache name that is stable across builds of identical content.
+#[derive(Clone)]
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use diaryx_core::{
+    CoreBuildOptions, FileProvider, PageOutput, SiteShape, Warning, build_site_streaming, output,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_yaml::Mapping;
+
+use crate::query::{EntryView, Filter, field_values};
+use crate::vault::split_frontmatter;
+
+/// Query tables: fenced ` ```diaryx-query ` blocks in an entry body, replaced at build time by a
+/// table of the matching entries.
+///
+/// ````text
+/// ```diaryx-query
+/// where tags contains book and rating >= 4
+/// select title, created, rating
+/// sort rating desc
+/// limit 10
+/// ```
+/// ````
+///
+/// `where` takes a `--where` filter expression (all entries when omitted), `select` the columns
+/// (frontmatter keys, `path`, or `title`, which links to the entry; default `title`), `sort` a
+/// field with an optional `asc` / `desc`, and `limit` a row count. Only pages that are part of
+/// the built site are listed, in build order unless sorted; with `--encrypt`
+/// ([`CoreBuildOptions::seal_private`]), a public page lists only public ones. The entry index
+/// is gathered the first time a page holds a query, so sites without queries pay nothing.
+pub(super) struct QueryTables<'a, F> {
+    entry: &'a str,
+    fs: &'a F,
+    core_opts: CoreBuildOptions,
+    root: PathBuf,
+    rows: Option<Vec<Row>>,
+}
+
+/// One entry as seen by queries.
+struct Row {
+    /// Site-relative output path.
+    rel: String,
+    title: String,
+    /// Source path relative to the vault root.
+    path: String,
+    fields: Mapping,
+    body: String,
+    public: bool,
+}
+
+/// A parsed query block.
+struct Query {
+    filter: Option<Filter>,
+    columns: Vec<String>,
+    sort: Option<(String, bool)>,
+    limit: Option<usize>,
+}
+
+impl<'a, F: FileProvider> QueryTables<'a, F> {
+    pub(super) fn new(entry: &'a str, fs: &'a F, core_opts: CoreBuildOptions) -> Self {
+        let root = Path::new(entry)
+            .parent()
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        Self {
+            entry,
+            fs,
+            core_opts,
+            root,
+            rows: None,
+        }
+    }
+
+    /// Replace every query block of `page` with its table; a block that does not parse is left
+    /// as is and reported in `warnings`. Returns whether the page holds any query.
+    pub(super) fn expand(
+        &mut self,
+        page: &mut PageOutput,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<bool> {
+        static BLOCK: Lazy<Regex> = Lazy::new(|| {
+            Regex::new(r#"(?s)<pre><code class="language-diaryx-query">(.*?)</code></pre>"#)
+                .unwrap()
+        });
+        if !BLOCK.is_match(&page.html) {
+            return Ok(false);
+        }
+        if self.rows.is_none() {
+            self.rows = Some(self.collect_rows()?);
+        }
+        let public_only = self.core_opts.seal_private && !super::encrypt::is_private(page);
+        let rows: Vec<&Row> = self
+            .rows
+            .iter()
+            .flatten()
+            .filter(|row| row.public || !public_only)
+            .collect();
+        let prefix = output::root_prefix(&page.path);
+        let html = BLOCK.replace_all(&page.html, |caps: &regex::Captures| {
+            match Query::parse(&unescape(&caps[1])) {
+                Ok(query) => query.render(&rows, &prefix),
+                Err(e) => {
+                    let warning = Warning::new(
+                        "query",
+                        format!("Invalid diaryx-query block: {e} ({})", page.source_path),
+                    );
+                    page.warnings.push(warning.clone());
+                    warnings.push(warning);
+                    caps[0].to_string()
+                }
+            }
+        });
+        page.html = html.into_owned();
+        Ok(true)
+    }
+
+    /// Every page of the site (same options as the build itself) with its fields and body.
+    fn collect_rows(&self) -> Result<Vec<Row>> {
+        let mut rows = Vec::new();
+        build_site_streaming(
+            self.entry,
+            self.core_opts.clone(),
+            self.fs,
+            |_: &SiteShape, page: PageOutput| {
+                let text = self.fs.read_to_string(&page.source_path)?;
+                let path = Path::new(&page.source_path);
+                rows.push(Row {
+                    rel: page.path.clone(),
+                    title: page.title.clone(),
+                    path: path
+                        .strip_prefix(&self.root)
+                        .unwrap_or(path)
+                        .to_string_lossy()
+                        .into_owned(),
+                    fields: page.frontmatter.as_mapping().cloned().unwrap_or_default(),
+                    body: split_frontmatter(&text).1.to_string(),
+                    public: !super::encrypt::is_private(&page),
+                });
+                Ok(())
+            },
+        )
+        .with_context(|| "Indexing entries for diaryx-query failed")?;
+        Ok(rows)
+    }
+}
+
+impl Query {
+    fn parse(src: &str) -> Result<Self> {
+        let mut query = Query {
+            filter: None,
+            columns: Vec::new(),
+            sort: None,
+            limit: None,
+        };
+        for line in src.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            match keyword.to_ascii_lowercase().as_str() {
+                "where" => query.filter = Some(Filter::parse(rest)?),
+                "select" => {
+                    query.columns = rest
+                        .split(',')
+                        .map(|c| c.trim().to_string())
+                        .filter(|c| !c.is_empty())
+                        .collect()
+                }
+                "sort" => {
+                    let mut words = rest.split_whitespace();
+                    let field = words
+                        .next()
+                        .ok_or_else(|| anyhow!("`sort` needs a field"))?;
+                    let desc = match words.next().map(str::to_ascii_lowercase).as_deref() {
+                        None | Some("asc") => false,
+                        Some("desc") => true,
+                        Some(other) => {
+                            bail!("Expected asc or desc after the sort field, got '{other}'")
+                        }
+                    };
+                    query.sort = Some((field.to_string(), desc));
+                }
+                "limit" => {
+                    query.limit = Some(
+                        rest.parse()
+                            .map_err(|_| anyhow!("`limit` needs a number, got '{rest}'"))?,
+                    )
+                }
+                other => bail!("Unknown clause '{other}' (expected where, select, sort or limit)"),
+            }
+        }
+        if query.columns.is_empty() {
+            query.columns.push("title".to_string());
+        }
+        Ok(query)
+    }
+
+    fn render(&self, rows: &[&Row], prefix: &str) -> String {
+        let mut matched: Vec<&Row> = rows
+            .iter()
+            .copied()
+            .filter(|row| self.filter.as_ref().is_none_or(|f| f.matches(&row.view())))
+            .collect();
+        if let Some((field, desc)) = &self.sort {
+            matched.sort_by(|a, b| {
+                compare(a.value(field).as_deref(), b.value(field).as_deref(), *desc)
+            });
+        }
+        matched.truncate(self.limit.unwrap_or(usize::MAX));
+        if matched.is_empty() {
+            return "<p class=\"diaryx-query-empty\">No matching entries.</p>".to_string();
+        }
+
+        let mut html = String::from("<table class=\"diaryx-query\"><thead><tr>");
+        for column in &self.columns {
+            html.push_str(&format!("<th>{}</th>", escape(column)));
+        }
+        html.push_str("</tr></thead><tbody>");
+        for row in matched {
+            html.push_str("<tr>");
+            for column in &self.columns {
+                let cell = if column == "title" {
+                    format!(
+                        "<a href=\"{prefix}{}\">{}</a>",
+                        escape(&row.rel),
+                        escape(&row.title)
+                    )
+                } else {
+                    escape(&row.value(column).unwrap_or_default())
+                };
+                html.push_str(&format!("<td>{cell}</td>"));
+            }
+            html.push_str("</tr>");
+        }
+        html.push_str("</tbody></table>");
+        html
+    }
+}
+
+impl Row {
+    fn view(&self) -> EntryView<'_> {
+        EntryView {
+            path: &self.path,
+            fields: &self.fields,
+            body: &self.body,
+        }
+    }
+
+    /// Display text of a column: list items joined, `None` when the field is missing.
+    fn value(&self, field: &str) -> Option<String> {
+        if field == "title" {
+            return Some(self.title.clone());
+        }
+        let values = field_values(&self.view(), field);
+        (!values.is_empty()).then(|| values.join(", "))
+    }
+}
+
+/// Numeric when both sides are numbers, lexical otherwise (ISO dates sort correctly); missing
+/// values sort last in either direction.
+fn compare(a: Option<&str>, b: Option<&str>, desc: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            let ordering = match (a.parse::<f64>(), b.parse::<f64>()) {
+                (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+                _ => a.cmp(b),
+            };
+            if desc { ordering.reverse() } else { ordering }
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Undo the escaping Markdown applies to code block text.
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
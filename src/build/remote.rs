@@ -0,0 +1,135 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use diaryx_core::remote::RemoteProvider;
+use serde::{Deserialize, Serialize};
+
+use crate::archive::sha256_hex;
+
+/// Remote pages over HTTP(S) for `build --allow-remote` (see [`diaryx_core::remote`]).
+///
+/// Responses are kept in a cache folder (`--remote-cache`, by default `diaryx/remote` in the
+/// user's cache directory), one JSON file per URL, and revalidated with `If-None-Match` /
+/// `If-Modified-Since` on the next build; an unchanged page is not downloaded again. When the
+/// host cannot be reached the cached copy is used, so a vault still builds offline.
+pub(super) struct HttpProvider {
+    agent: ureq::Agent,
+    cache_dir: Option<PathBuf>,
+    verbose: bool,
+}
+
+/// A cached response.
+#[derive(Serialize, Deserialize)]
+struct CachedPage {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+impl HttpProvider {
+    pub(super) fn new(cache_dir: Option<PathBuf>, verbose: bool) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(30))
+            // A redirect could lead off the allowlist, which only vets the requested URL.
+            .redirects(0)
+            .user_agent(concat!("diaryx/", env!("CARGO_PKG_VERSION")))
+            .build();
+        Self {
+            agent,
+            cache_dir: cache_dir.or_else(default_cache_dir),
+            verbose,
+        }
+    }
+
+    fn cache_path(&self, url: &str) -> Option<PathBuf> {
+        let dir = self.cache_dir.as_ref()?;
+        Some(dir.join(format!("{}.json", sha256_hex(url.as_bytes()))))
+    }
+
+    fn cached(&self, url: &str) -> Option<CachedPage> {
+        let text = fs::read_to_string(self.cache_path(url)?).ok()?;
+        serde_json::from_str::<CachedPage>(&text)
+            .ok()
+            .filter(|page| page.url == url)
+    }
+
+    /// Best effort: a page that cannot be cached is simply fetched again next time.
+    fn store(&self, page: &CachedPage) {
+        let Some(path) = self.cache_path(&page.url) else {
+            return;
+        };
+        let stored = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, serde_json::to_vec(page).unwrap_or_default()));
+        if let Err(e) = stored
+            && self.verbose
+        {
+            eprintln!("[build] could not cache {}: {e}", page.url);
+        }
+    }
+}
+
+impl RemoteProvider for HttpProvider {
+    fn fetch(&self, url: &str) -> Result<String> {
+        let cached = self.cached(url);
+        let mut request = self.agent.get(url);
+        if let Some(page) = &cached {
+            if let Some(etag) = &page.etag {
+                request = request.set("If-None-Match", etag);
+            }
+            if let Some(modified) = &page.last_modified {
+                request = request.set("If-Modified-Since", modified);
+            }
+        }
+        match request.call() {
+            Ok(response) if response.status() == 304 => {
+                if self.verbose {
+                    eprintln!("[build] {url} unchanged (cached)");
+                }
+                // A 304 is only possible when a cached copy was offered.
+                Ok(cached.map(|page| page.body).unwrap_or_default())
+            }
+            Ok(response) if (300..400).contains(&response.status()) => bail!(
+                "HTTP {} redirect to {} not followed (redirects are not checked against the allowlist; link the final URL instead)",
+                response.status(),
+                response.header("Location").unwrap_or("an unknown location")
+            ),
+            Ok(response) => {
+                let etag = response.header("ETag").map(str::to_string);
+                let last_modified = response.header("Last-Modified").map(str::to_string);
+                let body = response.into_string()?;
+                if self.verbose {
+                    eprintln!("[build] fetched {url} ({} bytes)", body.len());
+                }
+                self.store(&CachedPage {
+                    url: url.to_string(),
+                    etag,
+                    last_modified,
+                    body: body.clone(),
+                });
+                Ok(body)
+            }
+            Err(ureq::Error::Status(code, _)) => bail!("HTTP {code}"),
+            Err(e) => match cached {
+                Some(page) => {
+                    eprintln!("[build] {url} unreachable ({e}); using the cached copy");
+                    Ok(page.body)
+                }
+                None => Err(e.into()),
+            },
+        }
+    }
+}
+
+/// `$XDG_CACHE_HOME/diaryx/remote`, else `~/.cache/diaryx/remote` (`%LOCALAPPDATA%` on Windows).
+fn default_cache_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join("diaryx").join("remote"))
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use diaryx_core::HeldBack;
+use serde::Serialize;
+
+use super::Built;
+use crate::BuildOptions;
+
+/// How `build` reports its result on stdout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// The `[diaryx] build completed` line.
+    #[default]
+    Text,
+    /// One JSON object: status, output folder, page count and coded warnings.
+    Json,
+}
+
+/// The `--output-format json` result of a build.
+#[derive(Serialize)]
+struct BuildReport<'a> {
+    /// `ok`, `strict-failure` (built, but `--strict` or a failing `[budget]` rejected the
+    /// warnings), `hook-failure` (built, but a `post_build` hook failed) or `error`.
+    status: &'static str,
+    output: String,
+    pages: usize,
+    warnings: Vec<Warning<'a>>,
+    /// Drafts and entries scheduled for later that were left out.
+    held_back: &'a [HeldBack],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// The process exit code.
+    exit_code: u8,
+}
+
+#[derive(Serialize)]
+struct Warning<'a> {
+    code: &'static str,
+    message: &'a str,
+}
+
+/// Print the JSON result of a build: what was `built`, and the `error` it ended with, if any.
+pub(super) fn print_json(
+    opts: &BuildOptions,
+    built: Option<&Built>,
+    error: Option<&anyhow::Error>,
+) -> Result<()> {
+    let report = BuildReport {
+        status: match (built, error) {
+            (_, None) => "ok",
+            (Some(_), Some(e)) if e.is::<super::hooks::HookFailed>() => "hook-failure",
+            (Some(_), Some(_)) => "strict-failure",
+            (None, Some(_)) => "error",
+        },
+        output: opts.output.display().to_string(),
+        pages: built.map_or(0, |built| built.pages),
+        warnings: built
+            .map(|built| {
+                built
+                    .warnings
+                    .iter()
+                    .map(|warning| Warning {
+                        code: warning.code,
+                        message: &warning.message,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        held_back: built.map_or(&[], |built| &built.held_back),
+        error: error.map(|e| format!("{e:#}")),
+        exit_code: error.map_or(0, crate::exit::code),
+    };
+    println!("{}", serde_json::to_string(&report)?);
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use diaryx_core::Warning;
+
+use crate::archive::{entry_name, files_under};
+use crate::config::BudgetConfig;
+use crate::exit::ValidationFailed;
+use crate::profile::format_bytes;
+use crate::sign::is_manifest_file;
+
+/// Output size limits (`[budget]` in `diaryx.toml`), in bytes.
+#[derive(Debug, Clone)]
+pub struct SizeBudget {
+    /// Each HTML file.
+    page: Option<u64>,
+    /// Each other file.
+    asset: Option<u64>,
+    /// All files together.
+    total: Option<u64>,
+    /// Fail the build when a limit is exceeded, rather than only warn.
+    pub fail: bool,
+}
+
+impl SizeBudget {
+    /// The budget a `[budget]` table sets; `None` when it sets no limit.
+    pub(crate) fn from_config(config: BudgetConfig) -> Result<Option<Self>> {
+        let parse = |value: Option<String>| value.as_deref().map(parse_size).transpose();
+        let budget = SizeBudget {
+            page: parse(config.page)?,
+            asset: parse(config.asset)?,
+            total: parse(config.total)?,
+            fail: config.fail,
+        };
+        Ok(
+            (budget.page.is_some() || budget.asset.is_some() || budget.total.is_some())
+                .then_some(budget),
+        )
+    }
+
+    /// A warning for each file over its limit, and one for the site over `total`.
+    pub(super) fn check(&self, sizes: &BTreeMap<String, u64>) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        for (path, &size) in sizes {
+            let (limit, kind) = if is_html(path) {
+                (self.page, "page")
+            } else {
+                (self.asset, "asset")
+            };
+            if let Some(limit) = limit.filter(|&limit| size > limit) {
+                warnings.push(Warning::new(
+                    "size-budget",
+                    format!(
+                        "Size budget exceeded: {path} is {} ({kind} budget {})",
+                        format_bytes(size as usize),
+                        format_bytes(limit as usize)
+                    ),
+                ));
+            }
+        }
+        let total: u64 = sizes.values().sum();
+        if let Some(limit) = self.total.filter(|&limit| total > limit) {
+            warnings.push(Warning::new(
+                "size-budget",
+                format!(
+                    "Size budget exceeded: the site is {} (total budget {})",
+                    format_bytes(total as usize),
+                    format_bytes(limit as usize)
+                ),
+            ));
+        }
+        warnings
+    }
+}
+
+/// Size of every file of the site in `output` (the manifest and its signature aside), by
+/// site-relative path.
+pub(super) fn output_sizes(output: &Path) -> Result<BTreeMap<String, u64>> {
+    let mut sizes = BTreeMap::new();
+    for path in files_under(output)? {
+        let rel = entry_name(path.strip_prefix(output).unwrap_or(&path));
+        if !is_manifest_file(&rel) {
+            sizes.insert(rel, fs::metadata(&path).map_or(0, |meta| meta.len()));
+        }
+    }
+    Ok(sizes)
+}
+
+/// `build --size-report`: the files added, removed and grown since the `previous` build (from its
+/// manifest), largest change first, and the total size with its change.
+pub(super) fn report(
+    previous: Option<&BTreeMap<String, u64>>,
+    sizes: &BTreeMap<String, u64>,
+) -> Vec<String> {
+    let total: u64 = sizes.values().sum();
+    let Some(previous) = previous else {
+        return vec![format!(
+            "{} file(s), {}; no previous manifest to compare with",
+            sizes.len(),
+            format_bytes(total as usize)
+        )];
+    };
+    // (change in bytes, line)
+    let mut changes: Vec<(i64, String)> = Vec::new();
+    for (path, &size) in sizes {
+        match previous.get(path) {
+            None => changes.push((size as i64, format!("new      {path} ({})", bytes(size)))),
+            Some(&before) if size > before => changes.push((
+                (size - before) as i64,
+                format!(
+                    "grown    {path} ({} -> {}, +{})",
+                    bytes(before),
+                    bytes(size),
+                    bytes(size - before)
+                ),
+            )),
+            Some(_) => {}
+        }
+    }
+    for (path, &before) in previous {
+        if !sizes.contains_key(path) {
+            changes.push((
+                before as i64,
+                format!("removed  {path} ({})", bytes(before)),
+            ));
+        }
+    }
+    changes.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    let before: u64 = previous.values().sum();
+    let mut lines: Vec<String> = changes.into_iter().map(|(_, line)| line).collect();
+    lines.push(format!(
+        "{} file(s), {} ({}{} since the previous build)",
+        sizes.len(),
+        bytes(total),
+        if total >= before { "+" } else { "-" },
+        bytes(total.abs_diff(before))
+    ));
+    lines
+}
+
+fn bytes(size: u64) -> String {
+    format_bytes(size as usize)
+}
+
+fn is_html(path: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    lower.ends_with(".html") || lower.ends_with(".htm")
+}
+
+/// `200KB`, `1.5 MB`, `4096` (bytes).
+fn parse_size(text: &str) -> Result<u64> {
+    let invalid = |why: &str| ValidationFailed(format!("Invalid size '{text}' in [budget]: {why}"));
+    let trimmed = text.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let factor: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        _ => return Err(invalid("unknown unit").into()),
+    };
+    let number: f64 = number.parse().map_err(|_| invalid("expected e.g. 200KB"))?;
+    Ok((number * factor as f64) as u64)
+}
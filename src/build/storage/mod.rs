@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Result, anyhow, bail};
+use diaryx_core::memfs::normalize_path;
+use diaryx_core::{FileMetadata, FileProvider};
+
+use super::RealFs;
+
+#[cfg(feature = "s3")]
+mod s3;
+#[cfg(feature = "webdav")]
+mod webdav;
+mod zip;
+
+/// Object storage holding a vault, addressed by the vault URL of each file.
+pub(super) trait ObjectStore: Send + Sync {
+    /// Bytes of the object at `url`, or `None` when there is no such object.
+    fn get(&self, url: &str) -> Result<Option<Vec<u8>>>;
+    /// Whether an object exists at `url` (without downloading it).
+    fn exists(&self, url: &str) -> Result<bool>;
+    /// Whether `path` names a file in this store (rather than on the local disk).
+    fn holds(&self, path: &str) -> bool {
+        is_store_url(path)
+    }
+}
+
+/// The filesystem a build reads its vault from: the local disk, an object store when the entry
+/// is an `s3://bucket/key` (feature `s3`) or `webdav://host/path` / `webdavs://host/path`
+/// (feature `webdav`) URL, or a zip archive (`vault.zip`, `vault.diaryx`) mounted read-only.
+///
+/// Paths in a stored vault stay URLs of the same form, so relative links and `contents` resolve
+/// within the bucket or server; files in an archive are addressed below the archive's own path
+/// (`/backups/vault.zip/notes/a.md`). Local paths still go to the disk. Pages are read once per
+/// build and kept in memory, attachments are written straight into the output folder (see
+/// [`VaultFs::read_bytes`]). Nothing is synced or extracted to a local copy first.
+pub(crate) struct VaultFs {
+    store: Option<Box<dyn ObjectStore>>,
+    /// Entry page: the input, or the root index found inside an archive.
+    entry: String,
+    /// URL -> text of a page, or `None` when it does not exist.
+    pages: Mutex<HashMap<String, Option<String>>>,
+    /// URL -> whether the object exists.
+    present: Mutex<HashMap<String, bool>>,
+}
+
+impl VaultFs {
+    /// Filesystem for the vault holding `entry`; `identity` is the age identity file for an
+    /// encrypted archive.
+    pub(crate) fn open(entry: &str, identity: Option<&Path>) -> Result<Self> {
+        let mut entry = entry.to_string();
+        let store: Option<Box<dyn ObjectStore>> = match url_scheme(&entry) {
+            None if zip::is_archive(&entry) => {
+                let (archive, root_index) = zip::ZipStore::open(&entry, identity)?;
+                entry = root_index;
+                Some(Box::new(archive))
+            }
+            None => None,
+            #[cfg(feature = "s3")]
+            Some("s3") => Some(Box::new(s3::S3Store::from_env()?)),
+            #[cfg(not(feature = "s3"))]
+            Some("s3") => bail!("{entry}: this diaryx was built without S3 support (feature `s3`)"),
+            #[cfg(feature = "webdav")]
+            Some("webdav" | "webdavs") => Some(Box::new(webdav::WebDavStore::from_env())),
+            #[cfg(not(feature = "webdav"))]
+            Some("webdav" | "webdavs") => {
+                bail!("{entry}: this diaryx was built without WebDAV support (feature `webdav`)")
+            }
+            Some(scheme) => bail!("Unsupported vault URL scheme '{scheme}://': {entry}"),
+        };
+        Ok(Self {
+            store,
+            entry,
+            pages: Mutex::new(HashMap::new()),
+            present: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Path of the entry page.
+    pub(crate) fn entry(&self) -> &str {
+        &self.entry
+    }
+
+    /// The object store for `path`, when it names a file in one.
+    fn store_for(&self, path: &str) -> Option<&dyn ObjectStore> {
+        self.store.as_deref().filter(|store| store.holds(path))
+    }
+
+    /// Contents of a stored attachment, or `None` when `path` is a local file (copy it instead).
+    pub(crate) fn read_bytes(&self, path: &str) -> Option<Result<Vec<u8>>> {
+        let store = self.store_for(path)?;
+        Some(
+            store
+                .get(path)
+                .and_then(|bytes| bytes.ok_or_else(|| anyhow!("{path} does not exist"))),
+        )
+    }
+}
+
+impl FileProvider for VaultFs {
+    fn read_to_string(&self, path: &str) -> Result<String> {
+        let Some(store) = self.store_for(path) else {
+            return RealFs.read_to_string(path);
+        };
+        if let Some(cached) = self.pages.lock().expect("page cache").get(path) {
+            return cached
+                .clone()
+                .ok_or_else(|| anyhow!("{path} does not exist"));
+        }
+        let text = match store.get(path)? {
+            Some(bytes) => {
+                Some(String::from_utf8(bytes).map_err(|_| anyhow!("{path} is not valid UTF-8"))?)
+            }
+            None => None,
+        };
+        self.pages
+            .lock()
+            .expect("page cache")
+            .insert(path.to_string(), text.clone());
+        text.ok_or_else(|| anyhow!("{path} does not exist"))
+    }
+    fn exists(&self, path: &str) -> bool {
+        let Some(store) = self.store_for(path) else {
+            return RealFs.exists(path);
+        };
+        if let Some(&present) = self.present.lock().expect("existence cache").get(path) {
+            return present;
+        }
+        // A failed request is treated as missing; core reports the broken link.
+        let present = store.exists(path).unwrap_or(false);
+        self.present
+            .lock()
+            .expect("existence cache")
+            .insert(path.to_string(), present);
+        present
+    }
+    fn is_file(&self, path: &str) -> bool {
+        if self.store_for(path).is_some() {
+            !path.ends_with('/') && self.exists(path)
+        } else {
+            RealFs.is_file(path)
+        }
+    }
+    fn join(&self, parent: &str, rel: &str) -> String {
+        match split_url(parent) {
+            Some((origin, dir)) if !rel.contains("://") => {
+                format!("{origin}{}", normalize_path(&format!("{dir}/{rel}")))
+            }
+            // Archive members have no OS to resolve `..` for them.
+            None if self.store_for(parent).is_some() => normalize_path(&RealFs.join(parent, rel)),
+            _ => RealFs.join(parent, rel),
+        }
+    }
+    fn extension_lowercase(&self, path: &str) -> Option<String> {
+        RealFs.extension_lowercase(path)
+    }
+    fn parent(&self, path: &str) -> Option<String> {
+        match split_url(path) {
+            Some((origin, key)) => {
+                let dir = key.rsplit_once('/').map_or("", |(dir, _)| dir);
+                Some(format!("{origin}{dir}"))
+            }
+            None => RealFs.parent(path),
+        }
+    }
+    fn file_name(&self, path: &str) -> Option<String> {
+        match split_url(path) {
+            Some((_, key)) => key
+                .rsplit('/')
+                .next()
+                .filter(|name| !name.is_empty())
+                .map(str::to_string),
+            None => RealFs.file_name(path),
+        }
+    }
+    fn metadata(&self, path: &str) -> Option<FileMetadata> {
+        if self.store_for(path).is_some() {
+            None
+        } else {
+            RealFs.metadata(path)
+        }
+    }
+    fn list_files(&self, dir: &str) -> Option<Vec<String>> {
+        if self.store_for(dir).is_some() {
+            None
+        } else {
+            RealFs.list_files(dir)
+        }
+    }
+}
+
+/// Whether `path` is a vault URL (`s3://`, `webdav://` or `webdavs://`).
+pub(crate) fn is_store_url(path: &str) -> bool {
+    matches!(url_scheme(path), Some("s3" | "webdav" | "webdavs"))
+}
+
+/// Scheme of `path` when it looks like `scheme://...` (a Windows drive path does not).
+fn url_scheme(path: &str) -> Option<&str> {
+    let (scheme, _) = path.split_once("://")?;
+    (scheme.len() > 1
+        && scheme
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'+'))
+    .then_some(scheme)
+}
+
+/// `scheme://authority` and the key (starting with `/`, or empty) of a vault URL.
+fn split_url(url: &str) -> Option<(&str, &str)> {
+    if !is_store_url(url) {
+        return None;
+    }
+    let authority_start = url.find("://")? + 3;
+    let key_start = url[authority_start..]
+        .find('/')
+        .map_or(url.len(), |i| authority_start + i);
+    Some((&url[..key_start], &url[key_start..]))
+}
+
+/// Percent-encode everything but unreserved characters and `/` (RFC 3986, as S3 signs paths).
+#[cfg(any(feature = "s3", feature = "webdav"))]
+fn encode_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~' | b'/') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
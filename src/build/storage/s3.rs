@@ -0,0 +1,186 @@
+use std::env;
+use std::io::Read;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+
+use super::{ObjectStore, encode_path, split_url};
+
+/// S3-compatible storage for `s3://bucket/key` vaults.
+///
+/// Configured like the AWS tools: `AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY` (plus
+/// `AWS_SESSION_TOKEN` for temporary credentials) sign requests with Signature Version 4, and
+/// without them a public bucket is read anonymously. `AWS_REGION` (or `AWS_DEFAULT_REGION`)
+/// defaults to `us-east-1`. `AWS_ENDPOINT_URL` points at another S3-compatible service (MinIO,
+/// R2, B2, ...), which is then addressed path-style (`endpoint/bucket/key`).
+pub(super) struct S3Store {
+    agent: ureq::Agent,
+    credentials: Option<Credentials>,
+    region: String,
+    endpoint: Option<String>,
+}
+
+struct Credentials {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+impl S3Store {
+    pub(super) fn from_env() -> Result<Self> {
+        let var = |name: &str| env::var(name).ok().filter(|v| !v.is_empty());
+        let credentials = match (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY")) {
+            (Some(access_key), Some(secret_key)) => Some(Credentials {
+                access_key,
+                secret_key,
+                session_token: var("AWS_SESSION_TOKEN"),
+            }),
+            (None, None) => None,
+            _ => bail!("Set both AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY (or neither)"),
+        };
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(60))
+            .user_agent(concat!("diaryx/", env!("CARGO_PKG_VERSION")))
+            .build();
+        Ok(Self {
+            agent,
+            credentials,
+            region: var("AWS_REGION")
+                .or_else(|| var("AWS_DEFAULT_REGION"))
+                .unwrap_or_else(|| "us-east-1".to_string()),
+            endpoint: var("AWS_ENDPOINT_URL").map(|e| e.trim_end_matches('/').to_string()),
+        })
+    }
+
+    /// Send a signed `method` request for the object at `url`; `Ok(None)` on 404.
+    fn request(&self, method: &str, url: &str) -> Result<Option<ureq::Response>> {
+        let Some((bucket, key)) = split_url(url).map(|(origin, key)| (&origin[5..], key)) else {
+            bail!("Not an S3 URL: {url}");
+        };
+        let (base, path) = match &self.endpoint {
+            Some(endpoint) => (endpoint.clone(), encode_path(&format!("/{bucket}{key}"))),
+            None => (
+                format!("https://{bucket}.s3.{}.amazonaws.com", self.region),
+                encode_path(key),
+            ),
+        };
+        let mut request = self.agent.request(method, &format!("{base}{path}"));
+        if let Some(credentials) = &self.credentials {
+            let host = base.split_once("://").map_or(base.as_str(), |(_, h)| h);
+            for (name, value) in self.sign(credentials, method, host, &path) {
+                request = request.set(name, &value);
+            }
+        }
+        match request.call() {
+            Ok(response) => Ok(Some(response)),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(ureq::Error::Status(code, response)) => {
+                let body = response.into_string().unwrap_or_default();
+                bail!(
+                    "S3 {method} {url} failed: HTTP {code} {}",
+                    s3_error_code(&body)
+                )
+            }
+            Err(e) => Err(e).with_context(|| format!("S3 {method} {url} failed")),
+        }
+    }
+
+    /// Signature Version 4 headers for a request without a body.
+    fn sign(
+        &self,
+        credentials: &Credentials,
+        method: &str,
+        host: &str,
+        path: &str,
+    ) -> Vec<(&'static str, String)> {
+        let now = OffsetDateTime::now_utc();
+        let amz_date = format!(
+            "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+            now.year(),
+            u8::from(now.month()),
+            now.day(),
+            now.hour(),
+            now.minute(),
+            now.second()
+        );
+        let day = &amz_date[..8];
+        let payload = "UNSIGNED-PAYLOAD";
+
+        let mut headers = vec![
+            ("host", host.to_string()),
+            ("x-amz-content-sha256", payload.to_string()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+            .collect();
+        let canonical_request =
+            format!("{method}\n{path}\n\n{canonical_headers}\n{signed_headers}\n{payload}");
+        let scope = format!("{day}/{}/s3/aws4_request", self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = [day, &self.region, "s3", "aws4_request"].iter().fold(
+            format!("AWS4{}", credentials.secret_key).into_bytes(),
+            |key, part| hmac(&key, part.as_bytes()),
+        );
+        let signature = hex(&hmac(&key, string_to_sign.as_bytes()));
+
+        headers.retain(|(name, _)| *name != "host");
+        headers.push((
+            "Authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+                credentials.access_key
+            ),
+        ));
+        headers
+    }
+}
+
+impl ObjectStore for S3Store {
+    fn get(&self, url: &str) -> Result<Option<Vec<u8>>> {
+        let Some(response) = self.request("GET", url)? else {
+            return Ok(None);
+        };
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut body)
+            .with_context(|| format!("Failed to download {url}"))?;
+        Ok(Some(body))
+    }
+    fn exists(&self, url: &str) -> Result<bool> {
+        Ok(self.request("HEAD", url)?.is_some())
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// The `<Code>` of an S3 XML error body (`AccessDenied`, `NoSuchBucket`, ...), if any.
+fn s3_error_code(body: &str) -> &str {
+    body.split_once("<Code>")
+        .and_then(|(_, rest)| rest.split_once("</Code>"))
+        .map_or("", |(code, _)| code)
+}
@@ -0,0 +1,78 @@
+use std::env;
+use std::io::Read;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+use super::{ObjectStore, encode_path, split_url};
+
+/// WebDAV storage for `webdav://host/path` (plain HTTP) and `webdavs://host/path` (HTTPS) vaults,
+/// e.g. a Nextcloud or ownCloud folder.
+///
+/// `DIARYX_WEBDAV_USER` / `DIARYX_WEBDAV_PASSWORD` (an app password, typically) are sent with
+/// Basic authentication when set.
+pub(super) struct WebDavStore {
+    agent: ureq::Agent,
+    authorization: Option<String>,
+}
+
+impl WebDavStore {
+    pub(super) fn from_env() -> Self {
+        let var = |name: &str| env::var(name).ok().filter(|v| !v.is_empty());
+        let authorization = var("DIARYX_WEBDAV_USER").map(|user| {
+            let password = var("DIARYX_WEBDAV_PASSWORD").unwrap_or_default();
+            format!("Basic {}", STANDARD.encode(format!("{user}:{password}")))
+        });
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(60))
+            .user_agent(concat!("diaryx/", env!("CARGO_PKG_VERSION")))
+            .build();
+        Self {
+            agent,
+            authorization,
+        }
+    }
+
+    /// Send a `method` request for the resource at `url`; `Ok(None)` on 404.
+    fn request(&self, method: &str, url: &str) -> Result<Option<ureq::Response>> {
+        let Some((origin, path)) = split_url(url) else {
+            bail!("Not a WebDAV URL: {url}");
+        };
+        let http_origin = match origin.split_once("://") {
+            Some(("webdavs", host)) => format!("https://{host}"),
+            Some((_, host)) => format!("http://{host}"),
+            None => bail!("Not a WebDAV URL: {url}"),
+        };
+        let mut request = self
+            .agent
+            .request(method, &format!("{http_origin}{}", encode_path(path)));
+        if let Some(authorization) = &self.authorization {
+            request = request.set("Authorization", authorization);
+        }
+        match request.call() {
+            Ok(response) => Ok(Some(response)),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(ureq::Error::Status(code, _)) => bail!("WebDAV {method} {url} failed: HTTP {code}"),
+            Err(e) => Err(e).with_context(|| format!("WebDAV {method} {url} failed")),
+        }
+    }
+}
+
+impl ObjectStore for WebDavStore {
+    fn get(&self, url: &str) -> Result<Option<Vec<u8>>> {
+        let Some(response) = self.request("GET", url)? else {
+            return Ok(None);
+        };
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut body)
+            .with_context(|| format!("Failed to download {url}"))?;
+        Ok(Some(body))
+    }
+    fn exists(&self, url: &str) -> Result<bool> {
+        Ok(self.request("HEAD", url)?.is_some())
+    }
+}
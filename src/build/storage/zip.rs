@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use diaryx_core::memfs::normalize_path;
+
+use super::ObjectStore;
+use crate::archive::read_zip_with_password;
+use crate::vault::{parse_fields, split_frontmatter};
+
+/// A zip archive (`vault.zip`, or a `.diaryx` bundle such as a `diaryx snapshot`) mounted
+/// read-only, so exported or backed-up vaults build without being extracted.
+///
+/// Members are addressed below the archive's path (`/backups/vault.zip/notes/a.md`). The whole
+/// archive is read into memory once. Encrypted vaults are decrypted there too, and no plaintext
+/// source is ever written to disk: an age-encrypted archive (`vault.zip.age`, feature `age`) is
+/// opened with the identity file given by `--identity` or, for passphrase encryption, with
+/// `DIARYX_PASSPHRASE`, which also unlocks password-protected zip entries.
+pub(super) struct ZipStore {
+    /// Normalized archive path followed by `/`.
+    prefix: String,
+    /// Member name -> contents.
+    files: HashMap<String, Vec<u8>>,
+}
+
+/// Environment variable holding the passphrase of an encrypted vault.
+const PASSPHRASE_VAR: &str = "DIARYX_PASSPHRASE";
+
+/// Whether `path` names a zip archive to mount (by extension).
+pub(super) fn is_archive(path: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    let lower = lower.strip_suffix(".age").unwrap_or(&lower);
+    (lower.ends_with(".zip") || lower.ends_with(".diaryx")) && Path::new(path).is_file()
+}
+
+impl ZipStore {
+    /// Mount `archive` and find its root index; returns the store and the entry path.
+    ///
+    /// A snapshot's manifest names its entry. Otherwise the entry is the `.md` file declaring
+    /// `this_file_is_root_index: true` closest to the top of the archive.
+    pub(super) fn open(archive: &str, identity: Option<&Path>) -> Result<(Self, String)> {
+        let mut bytes = fs::read(archive).with_context(|| format!("Failed to read {archive}"))?;
+        let passphrase = env::var(PASSPHRASE_VAR).ok().filter(|p| !p.is_empty());
+        if archive.to_ascii_lowercase().ends_with(".age") {
+            bytes = decrypt_age(&bytes, identity, passphrase.as_deref())
+                .with_context(|| format!("Failed to decrypt {archive}"))?;
+        }
+        let files: HashMap<String, Vec<u8>> = read_zip_with_password(&bytes, passphrase.as_deref())
+            .with_context(|| format!("{archive} is not a readable zip archive"))?
+            .into_iter()
+            .filter(|(name, _)| !name.starts_with("__MACOSX/"))
+            .map(|(name, data)| (normalize_path(&name), data))
+            .collect();
+        let root_index = snapshot_entry(&files)
+            .or_else(|| declared_root(&files))
+            .ok_or_else(|| {
+                anyhow!(
+                    "{archive} holds no root index (a .md file with `this_file_is_root_index: true`)"
+                )
+            })?;
+        if !files.contains_key(&root_index) {
+            bail!("{archive}: the root index {root_index} is missing");
+        }
+        let prefix = format!("{}/", normalize_path(archive));
+        let entry = format!("{prefix}{root_index}");
+        Ok((Self { prefix, files }, entry))
+    }
+
+    /// Member name of `path`, when it lies inside the archive.
+    fn member(&self, path: &str) -> Option<String> {
+        normalize_path(path)
+            .strip_prefix(&self.prefix)
+            .map(str::to_string)
+    }
+}
+
+impl ObjectStore for ZipStore {
+    fn get(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self
+            .member(path)
+            .and_then(|name| self.files.get(&name))
+            .cloned())
+    }
+    fn exists(&self, path: &str) -> Result<bool> {
+        Ok(self
+            .member(path)
+            .is_some_and(|name| self.files.contains_key(&name)))
+    }
+    fn holds(&self, path: &str) -> bool {
+        self.member(path).is_some()
+    }
+}
+
+/// Decrypt an age file in memory, with the passphrase when it was encrypted to one and with the
+/// identity file otherwise.
+#[cfg(feature = "age")]
+fn decrypt_age(bytes: &[u8], identity: Option<&Path>, passphrase: Option<&str>) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    use age::secrecy::SecretString;
+
+    let decryptor = age::Decryptor::new_buffered(bytes)?;
+    let identities: Vec<Box<dyn age::Identity>> = if decryptor.is_scrypt() {
+        let passphrase = passphrase
+            .with_context(|| format!("Encrypted with a passphrase; set {PASSPHRASE_VAR}"))?;
+        vec![Box::new(age::scrypt::Identity::new(SecretString::from(
+            passphrase.to_string(),
+        )))]
+    } else {
+        let identity =
+            identity.context("Encrypted to a key; pass its identity file with --identity")?;
+        age::IdentityFile::from_file(identity.to_string_lossy().into_owned())
+            .with_context(|| format!("Failed to read identity file {}", identity.display()))?
+            .into_identities()?
+    };
+    let mut plain = Vec::new();
+    decryptor
+        .decrypt(identities.iter().map(|identity| identity.as_ref()))?
+        .read_to_end(&mut plain)?;
+    Ok(plain)
+}
+
+#[cfg(not(feature = "age"))]
+fn decrypt_age(_: &[u8], _: Option<&Path>, _: Option<&str>) -> Result<Vec<u8>> {
+    bail!("this diaryx was built without age support (feature `age`)")
+}
+
+/// Entry recorded in a `diaryx snapshot` manifest.
+fn snapshot_entry(files: &HashMap<String, Vec<u8>>) -> Option<String> {
+    let manifest: serde_json::Value = serde_json::from_slice(files.get("manifest.json")?).ok()?;
+    manifest["entry"].as_str().map(normalize_path)
+}
+
+/// The shallowest `.md` member declaring itself the root index (alphabetical among equals).
+fn declared_root(files: &HashMap<String, Vec<u8>>) -> Option<String> {
+    files
+        .iter()
+        .filter(|(name, _)| name.to_ascii_lowercase().ends_with(".md"))
+        .filter(|(_, data)| {
+            let text = String::from_utf8_lossy(data);
+            parse_fields(split_frontmatter(&text).0)
+                .get("this_file_is_root_index")
+                .and_then(serde_yaml::Value::as_bool)
+                .unwrap_or(false)
+        })
+        .map(|(name, _)| name)
+        .min_by_key(|name| (name.matches('/').count(), name.as_str()))
+        .cloned()
+}
@@ -0,0 +1,423 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::Result;
+use diaryx_core::schema::CONFIG_FILE;
+use diaryx_core::{AttachmentPlanEntry, PageOutput, RenderCache, SiteShape};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::notify::{RebuildEvent, RebuildNotifier, RebuildOutcome};
+use super::{Built, enforce_strict, write_site_with};
+use crate::BuildOptions;
+
+/// How often sources are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// State kept between the builds of a watch session.
+#[derive(Default)]
+pub(super) struct Session {
+    /// Markdown renders of the previous build.
+    pub(super) cache: RenderCache,
+    pub(super) plan: RebuildPlan,
+}
+
+/// Which pages a build rewrites, and the site graph it leaves behind for the next one.
+#[derive(Default)]
+pub(super) struct RebuildPlan {
+    /// Graph of the last completed build.
+    graph: SiteGraph,
+    /// Graph recorded by the build in progress.
+    next: SiteGraph,
+    /// `None` rewrites every page.
+    target: Option<Target>,
+    /// Output files rewritten by the build in progress.
+    refreshed: Vec<String>,
+    /// Set when a partial build turned out to add, remove or move pages.
+    stale: bool,
+    /// Every source seen this session, so an entry that dropped out of the site (say, with
+    /// broken frontmatter) is rebuilt once it is fixed.
+    seen: HashSet<String>,
+}
+
+/// A partial rebuild.
+struct Target {
+    /// Slug of the changed entry; `None` when only attachments changed.
+    changed: Option<String>,
+    /// Pages depending on it in the previous graph.
+    dependents: HashSet<String>,
+}
+
+#[derive(Default, Clone)]
+struct SiteGraph {
+    pages: HashMap<String, Node>,
+    multi_page: bool,
+    /// Attachment sources.
+    attachments: Vec<String>,
+}
+
+#[derive(Clone)]
+struct Node {
+    source: String,
+    /// Output path.
+    rel: String,
+    parents: Vec<String>,
+    children: Vec<String>,
+    translations: Vec<String>,
+    /// Slugs of the pages the body links to.
+    links: HashSet<String>,
+    /// Holds `diaryx-query` tables, which list every entry.
+    queries: bool,
+}
+
+impl RebuildPlan {
+    pub(super) fn is_partial(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// Record `page` (written at `rel`) in the new graph; true when the build should write it.
+    pub(super) fn page_written(
+        &mut self,
+        rel: String,
+        site: &SiteShape,
+        page: &PageOutput,
+        queries: bool,
+    ) -> bool {
+        let node = Node {
+            source: page.source_path.clone(),
+            rel: rel.clone(),
+            parents: page.parents.clone(),
+            children: page.children.clone(),
+            translations: page.translations.iter().map(|t| t.id.clone()).collect(),
+            links: linked_slugs(site, &page.html),
+            queries,
+        };
+        let write = match &self.target {
+            None => true,
+            Some(Target { changed: None, .. }) => false,
+            Some(Target {
+                changed: Some(changed),
+                dependents,
+            }) => page.id == *changed || dependents.contains(&page.id) || node.depends_on(changed),
+        };
+        if write {
+            self.refreshed.push(rel);
+        }
+        self.next.pages.insert(page.id.clone(), node);
+        write
+    }
+
+    /// End of a build: the recorded graph replaces the previous one.
+    pub(super) fn finish(&mut self, multi_page: bool, attachments: &[AttachmentPlanEntry]) {
+        let mut next = std::mem::take(&mut self.next);
+        next.multi_page = multi_page;
+        next.attachments = attachments.iter().map(|a| a.source.clone()).collect();
+        self.stale = self.target.is_some() && !next.same_layout(&self.graph);
+        self.seen
+            .extend(next.pages.values().map(|node| node.source.clone()));
+        self.seen.extend(next.attachments.iter().cloned());
+        self.graph = next;
+    }
+
+    /// Plan the next build after `changed` source files were modified.
+    fn plan(&mut self, changed: &[String]) {
+        let slugs: Vec<&String> = self
+            .graph
+            .pages
+            .iter()
+            .filter(|(_, node)| changed.contains(&node.source))
+            .map(|(slug, _)| slug)
+            .collect();
+        self.target = match slugs.as_slice() {
+            [] => Some(Target {
+                changed: None,
+                dependents: HashSet::new(),
+            }),
+            [slug] => Some(Target {
+                changed: Some(slug.to_string()),
+                dependents: self.graph.dependents(slug),
+            }),
+            _ => None,
+        };
+        self.refreshed.clear();
+        self.stale = false;
+    }
+
+    fn plan_full(&mut self) {
+        self.target = None;
+        self.refreshed.clear();
+        self.stale = false;
+    }
+
+    /// Local files the site is built from.
+    fn watched(&self, entry: &str) -> Vec<String> {
+        let mut paths: Vec<String> = std::iter::once(entry.to_string())
+            .chain(self.seen.iter().cloned())
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+}
+
+impl Node {
+    /// Whether this page shows something of page `slug` (its title in the navigation, or a link
+    /// to it).
+    fn depends_on(&self, slug: &str) -> bool {
+        self.queries
+            || self.parents.iter().any(|s| s == slug)
+            || self.children.iter().any(|s| s == slug)
+            || self.translations.iter().any(|s| s == slug)
+            || self.links.contains(slug)
+    }
+}
+
+impl SiteGraph {
+    /// Pages of this graph that depend on `slug`, or that `slug` itself lists.
+    fn dependents(&self, slug: &str) -> HashSet<String> {
+        self.pages
+            .iter()
+            .filter(|(other, node)| other.as_str() != slug && node.depends_on(slug))
+            .map(|(other, _)| other.clone())
+            .chain(self.pages.get(slug).into_iter().flat_map(|node| {
+                node.parents
+                    .iter()
+                    .chain(&node.children)
+                    .chain(&node.translations)
+                    .cloned()
+            }))
+            .collect()
+    }
+
+    /// Same pages at the same output paths.
+    fn same_layout(&self, other: &SiteGraph) -> bool {
+        self.multi_page == other.multi_page
+            && self.pages.len() == other.pages.len()
+            && self
+                .pages
+                .iter()
+                .all(|(slug, node)| other.pages.get(slug).is_some_and(|o| o.rel == node.rel))
+    }
+}
+
+/// Slugs of the site pages `html` links to (`pages/<slug>.html`, `<slug>.html`, `index.html`).
+fn linked_slugs(site: &SiteShape, html: &str) -> HashSet<String> {
+    static PAGE_HREF: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r##"href="(?:[^"#?]*/)?([^"/#?]+)\.html"##).unwrap());
+    PAGE_HREF
+        .captures_iter(html)
+        .filter_map(|caps| match &caps[1] {
+            "index" => site.root_slug.clone(),
+            slug => Some(slug.to_string()),
+        })
+        .collect()
+}
+
+/// `diaryx build --watch`: build the site, then rebuild it whenever a source file changes, until
+/// interrupted.
+///
+/// Sources (the entry, every page's file and every attachment) and the vault's `diaryx.toml` are
+/// polled for modification, so no platform file-watching API is needed. The configuration is read
+/// again by every build, so edits to it apply without restarting; they rebuild the whole site. When a single entry changed, only its page and the pages
+/// depending on it in the previous build's site graph are rewritten: the indexes listing it, the
+/// pages it lists, its translations, pages linking to it and pages with `diaryx-query` tables.
+/// Every source is still re-read, but unchanged bodies reuse their Markdown renders. A changed
+/// attachment is copied again without touching any page. Anything else (several entries at once,
+/// or an edit that adds, removes or renames pages) rebuilds the whole site. The result of every
+/// build, including the first, goes to each of `notifiers`; a failed rebuild keeps the previous
+/// output.
+pub(super) fn run_watch(
+    opts: &BuildOptions,
+    notifiers: &mut [Box<dyn RebuildNotifier>],
+) -> Result<()> {
+    let entry = opts.input.to_string_lossy().into_owned();
+    let configs = config_candidates(&opts.input);
+    let watched = |session: &Session| {
+        let mut paths = session.plan.watched(&entry);
+        paths.extend(configs.iter().cloned());
+        paths
+    };
+    let mut session = Session::default();
+    let started = Instant::now();
+    let built = write_site_with(opts, Some(&mut session))?;
+    let result = enforce_strict(opts, &built).map(|()| built);
+    let event = session.event(Vec::new(), started, result);
+    notify_all(notifiers, &event);
+
+    let mut stamps = modification_times(&watched(&session));
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        if modification_times(&watched(&session)) == stamps {
+            continue;
+        }
+        // Let an editor finish saving before reading.
+        thread::sleep(POLL_INTERVAL);
+        let now = modification_times(&watched(&session));
+        // Modified, created or deleted.
+        let mut changed: Vec<String> = now
+            .keys()
+            .chain(stamps.keys())
+            .filter(|path| now.get(*path) != stamps.get(*path))
+            .cloned()
+            .collect();
+        changed.sort();
+        changed.dedup();
+
+        let started = Instant::now();
+        // The configuration can change how every page renders.
+        if changed.iter().any(|path| configs.contains(path)) {
+            session.plan.plan_full();
+        } else {
+            session.plan.plan(&changed);
+        }
+        let mut result = write_site_with(opts, Some(&mut session));
+        if result.is_ok() && session.plan.stale {
+            session.plan.plan_full();
+            result = write_site_with(opts, Some(&mut session));
+        }
+        let result = result.and_then(|built| enforce_strict(opts, &built).map(|()| built));
+        let event = session.event(changed, started, result);
+        notify_all(notifiers, &event);
+        stamps = modification_times(&watched(&session));
+    }
+}
+
+impl Session {
+    /// Describe the build that just ended.
+    fn event(&self, changed: Vec<String>, started: Instant, result: Result<Built>) -> RebuildEvent {
+        let plan = &self.plan;
+        let outcome = match result {
+            Ok(Built { warnings, .. }) => RebuildOutcome::Built {
+                full: plan.target.is_none(),
+                pages: plan.refreshed.clone(),
+                attachments: plan.target.is_none()
+                    || changed
+                        .iter()
+                        .any(|path| plan.graph.attachments.contains(path)),
+                warnings,
+            },
+            Err(e) => RebuildOutcome::Failed {
+                error: format!("{e:#}"),
+            },
+        };
+        RebuildEvent {
+            changed,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            outcome,
+        }
+    }
+}
+
+/// Hand `event` to every notifier; one failing to deliver it does not stop the session.
+fn notify_all(notifiers: &mut [Box<dyn RebuildNotifier>], event: &RebuildEvent) {
+    for notifier in notifiers {
+        if let Err(e) = notifier.notify(event) {
+            eprintln!("[watch] failed to report rebuild: {e:#}");
+        }
+    }
+}
+
+/// Every place a `diaryx.toml` applying to `input` could be: its folder and each folder above.
+///
+/// Missing ones are watched too, so creating or deleting a configuration is noticed like an edit.
+fn config_candidates(input: &Path) -> HashSet<String> {
+    let input = fs::canonicalize(input).unwrap_or_else(|_| input.to_path_buf());
+    input
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join(CONFIG_FILE).to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Modification time of each path that exists on disk.
+fn modification_times(paths: &[String]) -> HashMap<String, SystemTime> {
+    paths
+        .iter()
+        .filter_map(|path| {
+            let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+            Some((path.clone(), modified))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn a_changed_entry_rewrites_only_the_pages_showing_it() {
+        let vault = tempfile::tempdir().unwrap();
+        let write = |name: &str, text: &str| fs::write(vault.path().join(name), text).unwrap();
+        write(
+            "index.md",
+            "---\ntitle: Home\nthis_file_is_root_index: true\nrecent_entries: 1\ncontents:\n  - \"[2024](year.md)\"\n  - \"[Moods](moods.md)\"\n  - \"[Other](other.md)\"\n---\n",
+        );
+        write(
+            "year.md",
+            "---\ntitle: \"2024\"\ncontents:\n  - \"[Walk](walk.md)\"\n  - \"[Swim](swim.md)\"\n---\n",
+        );
+        write(
+            "walk.md",
+            "---\ntitle: Walk\ncreated: 2024-01-01\n---\nBy the river.\n",
+        );
+        write(
+            "swim.md",
+            "---\ntitle: Swim\ncreated: 2024-06-01\n---\nIn the lake.\n",
+        );
+        write(
+            "moods.md",
+            "---\ntitle: Moods\n---\n```diaryx-query\nselect title\n```\n",
+        );
+        write(
+            "other.md",
+            "---\ntitle: Other\n---\nNothing to do with walks.\n",
+        );
+        let site = vault.path().join("site");
+        let args = crate::Cli::try_parse_from([
+            "diaryx".as_ref(),
+            "build".as_ref(),
+            "--input".as_ref(),
+            vault.path().join("index.md").as_os_str(),
+            "--output".as_ref(),
+            site.as_os_str(),
+            "--include-nonpublic".as_ref(),
+        ])
+        .unwrap();
+        let crate::Command::Build(args) = args.command else {
+            unreachable!()
+        };
+        let opts = BuildOptions::from_args(&args, None).unwrap();
+        let mut session = Session::default();
+        write_site_with(&opts, Some(&mut session)).unwrap();
+
+        // The walk becomes the newest entry: the home page did not show it before, but its new
+        // render lists it among the recent entries, so it is rewritten too.
+        write(
+            "walk.md",
+            "---\ntitle: Walk\ncreated: 2025-01-01\n---\nBy the sea.\n",
+        );
+        let plan = &mut session.plan;
+        let walk = plan.graph.pages["walk"].source.clone();
+        plan.plan(&[walk]);
+        write_site_with(&opts, Some(&mut session)).unwrap();
+
+        let plan = &session.plan;
+        assert!(plan.is_partial() && !plan.stale);
+        let mut refreshed: Vec<&str> = plan
+            .graph
+            .pages
+            .iter()
+            .filter(|(_, node)| plan.refreshed.contains(&node.rel))
+            .map(|(slug, _)| slug.as_str())
+            .collect();
+        refreshed.sort();
+        assert_eq!(refreshed, ["index", "moods", "walk", "year"]);
+        let home = fs::read_to_string(site.join(&plan.graph.pages["index"].rel)).unwrap();
+        assert!(home.contains("By the sea."), "{home}");
+    }
+}
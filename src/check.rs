@@ -0,0 +1,52 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+
+use crate::diagnostics::{DocDiagnostic, lint_document};
+
+/// `check`: lint a single document and print its diagnostics as JSON on stdout.
+///
+/// Meant for editor plugins running on save: only the one document is parsed, and its links are
+/// checked against the files under the vault root, so no vault build happens. The text comes from
+/// stdin (`--stdin`, typically the unsaved buffer) or from `file` on disk. Problems in the document
+/// are reported in the JSON, not through the exit status.
+pub fn run_check(file: Option<&Path>, stdin: bool, vault_root: Option<&Path>) -> Result<()> {
+    let root = match vault_root {
+        Some(root) if !root.is_dir() => bail!("Vault root is not a directory: {}", root.display()),
+        Some(root) => root.to_path_buf(),
+        None => PathBuf::from("."),
+    };
+    // Where the document lives (or would live); links resolve relative to it.
+    let path = match file {
+        Some(file) if file.is_absolute() => file.to_path_buf(),
+        Some(file) if vault_root.is_some() => root.join(file),
+        Some(file) => file.to_path_buf(),
+        None if stdin => root.join("stdin.md"),
+        None => bail!("Pass a FILE to check, or --stdin to read the document from stdin"),
+    };
+    let text = if stdin {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .context("Failed to read document from stdin")?;
+        text
+    } else {
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?
+    };
+
+    let report = CheckReport {
+        path: path.display().to_string(),
+        diagnostics: lint_document(&path, &text),
+    };
+    println!("{}", serde_json::to_string(&report)?);
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct CheckReport {
+    path: String,
+    diagnostics: Vec<DocDiagnostic>,
+}
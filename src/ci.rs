@@ -0,0 +1,224 @@
+/*!
+ * `diaryx ci`: the checks a repository pipeline runs on every push, in one read-only command.
+ *
+ * - `validate`: the frontmatter and graph warnings of a build (every entry, private ones
+ *   included), without writing a site;
+ * - `links`: broken links and missing attachments in each entry, with their line;
+ * - `slugs`: entries whose pages would be written to the same file (two `notes.md` in different
+ *   folders both become `notes.html`), so one would silently replace the other;
+ * - `naming` (only with `[naming] lint = true`): entries whose file names do not follow the
+ *   vault's naming pattern.
+ *
+ * Any problem fails the run with exit code 2; `--output-format json` prints one object with
+ * every check's problems for the pipeline to annotate.
+ */
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use diaryx_core::{CoreBuildOptions, PageOutput, SiteShape, build_site_streaming};
+use serde::Serialize;
+
+use crate::build::{OutputFormat, RealFs};
+use crate::diagnostics::lint_document;
+use crate::exit::ValidationFailed;
+use crate::export::created;
+use crate::naming::Naming;
+
+/// Build warning codes that belong to the `links` check rather than `validate`.
+const LINK_CODES: &[&str] = &["broken-link", "missing-attachment"];
+/// Build warnings the per-entry link lint also finds (with a line), so they are not repeated.
+const LINTED: &[&str] = &[
+    "Entry or referenced path missing",
+    "Attachment not found",
+    "Attachment path is directory",
+];
+
+#[derive(Serialize)]
+struct CiReport {
+    /// `ok` or `failed`.
+    status: &'static str,
+    checks: Vec<Check>,
+    /// The process exit code.
+    exit_code: u8,
+}
+
+#[derive(Serialize)]
+struct Check {
+    name: &'static str,
+    problems: Vec<Problem>,
+}
+
+#[derive(Serialize)]
+struct Problem {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    /// One-based line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<u32>,
+}
+
+/// Run every check on the vault whose root index is `input` and report them in `format`.
+pub fn run_ci(input: &Path, format: OutputFormat, infer_dates: bool) -> Result<()> {
+    let entry = input
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?;
+    let root = input.parent().unwrap_or(Path::new("."));
+    let opts = CoreBuildOptions {
+        include_nonpublic: true,
+        env: Some(std::env::vars().collect()),
+        infer_dates,
+        ..CoreBuildOptions::default()
+    };
+    let naming = Naming::load(root)?;
+    let mut pages: Vec<(String, String)> = Vec::new();
+    let mut names = Vec::new();
+    let summary = build_site_streaming(entry, opts, &RealFs, |_: &SiteShape, page: PageOutput| {
+        let file_name = Path::new(&page.source_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let is_index = page.is_index || page.frontmatter.get("contents").is_some();
+        if let Some(message) = (naming.lint && !is_index)
+            .then(|| naming.check(&file_name, created(&page).map(|c| c.date())))
+            .flatten()
+        {
+            names.push(Problem {
+                code: "file-name",
+                message,
+                path: Some(rel(root, Path::new(&page.source_path))),
+                line: None,
+            });
+        }
+        pages.push((page.path, page.source_path));
+        Ok(())
+    })
+    .with_context(|| "Core build failed")?;
+
+    let (mut validate, mut links) = (Vec::new(), Vec::new());
+    for warning in summary.warnings {
+        if LINTED
+            .iter()
+            .any(|prefix| warning.message.starts_with(prefix))
+        {
+            continue;
+        }
+        let code = warning.code;
+        let problem = Problem {
+            code,
+            message: warning.message,
+            path: None,
+            line: None,
+        };
+        if LINK_CODES.contains(&code) {
+            links.push(problem);
+        } else {
+            validate.push(problem);
+        }
+    }
+    for (_, source) in &pages {
+        let path = Path::new(source);
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read {source}"))?;
+        for diagnostic in lint_document(path, &text) {
+            if LINK_CODES.contains(&diagnostic.code) {
+                links.push(Problem {
+                    code: diagnostic.code,
+                    message: diagnostic.message,
+                    path: Some(rel(root, path)),
+                    line: Some(diagnostic.line + 1),
+                });
+            }
+        }
+    }
+
+    let mut outputs: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (output, source) in &pages {
+        outputs.entry(output).or_default().push(source);
+    }
+    let slugs = outputs
+        .into_iter()
+        .filter(|(_, sources)| sources.len() > 1)
+        .map(|(output, sources)| Problem {
+            code: "slug-collision",
+            message: format!(
+                "{} are all written to {output}",
+                sources
+                    .iter()
+                    .map(|source| rel(root, Path::new(source)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            path: None,
+            line: None,
+        })
+        .collect();
+
+    let mut checks = vec![
+        Check {
+            name: "validate",
+            problems: validate,
+        },
+        Check {
+            name: "links",
+            problems: links,
+        },
+        Check {
+            name: "slugs",
+            problems: slugs,
+        },
+    ];
+    if naming.lint {
+        checks.push(Check {
+            name: "naming",
+            problems: names,
+        });
+    }
+    let failed: Vec<&str> = checks
+        .iter()
+        .filter(|check| !check.problems.is_empty())
+        .map(|check| check.name)
+        .collect();
+    let error = (!failed.is_empty()).then(|| {
+        anyhow::Error::from(ValidationFailed(format!(
+            "CI failed: {}",
+            failed.join(", ")
+        )))
+    });
+
+    match format {
+        OutputFormat::Json => {
+            let report = CiReport {
+                status: if error.is_some() { "failed" } else { "ok" },
+                exit_code: error.as_ref().map_or(0, crate::exit::code),
+                checks,
+            };
+            println!("{}", serde_json::to_string(&report)?);
+        }
+        OutputFormat::Text => {
+            for check in &checks {
+                if check.problems.is_empty() {
+                    println!("[ci] {}: ok", check.name);
+                    continue;
+                }
+                println!("[ci] {}: {} problem(s)", check.name, check.problems.len());
+                for problem in &check.problems {
+                    let at = match (&problem.path, problem.line) {
+                        (Some(path), Some(line)) => format!("{path}:{line}: "),
+                        (Some(path), None) => format!("{path}: "),
+                        _ => String::new(),
+                    };
+                    println!("  {at}[{}] {}", problem.code, problem.message);
+                }
+            }
+        }
+    }
+    error.map_or(Ok(()), Err)
+}
+
+fn rel(root: &Path, path: &Path) -> String {
+    crate::archive::entry_name(path.strip_prefix(root).unwrap_or(path))
+}
@@ -0,0 +1,450 @@
+/*!
+ * Build profiles: named sets of `build` flags declared in the vault's `diaryx.toml`.
+ *
+ * ```toml
+ * [profiles.preview]
+ * include_nonpublic = true
+ * output = "preview"
+ *
+ * [profiles.production]
+ * strict = true
+ * smart_punctuation = true
+ * exclude = ["drafts/"]
+ * manifest = true
+ * ```
+ *
+ * `diaryx build --build-profile production` then builds with those flags. Keys are the long
+ * flag names with `_` for `-`; paths (`output`, `sign`, `identity`, `remote_cache`) are relative
+ * to the folder of the `diaryx.toml`. Flags given on the command line still apply: a switch is
+ * on when either sets it, a value given on the command line replaces the profile's, and lists
+ * (`exclude`, `tags`, `allow_remote`) are combined.
+ *
+ * Values may refer to environment variables as `${NAME}` (`output = "${HOME}/www/diary"`,
+ * `sign = "${DIARYX_SIGNING_KEY}"`), so machine-specific paths and secrets stay out of the vault;
+ * an unset variable becomes empty, with a warning.
+ *
+ * The same file's `[sync]` table configures `diaryx sync` (see [`SyncConfig`]), its
+ * `[gh_pages]` table the files `build --gh-pages` adds (see [`GhPagesConfig`]), its
+ * `[hosting]` table the static host configuration every build writes (see [`HostingConfig`]),
+ * its `[rollover]` table the indexes `diaryx rollover` creates (see [`RolloverConfig`]), its
+ * `[naming]` table how new entry files are named (see [`NamingConfig`]), its `[budget]` table
+ * the output size limits of a build (see [`BudgetConfig`]), its `[usage]` table whether
+ * commands are logged for `diaryx usage` (see [`UsageConfig`]), its `[encrypt]` table the
+ * key derivation of `build --encrypt` (see [`EncryptConfig`]), and its `[hooks]` table the
+ * commands run before and after a build (see [`HooksConfig`]).
+ */
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use diaryx_core::OutputLayout;
+use diaryx_core::interpolate::interpolate;
+use diaryx_core::schema::CONFIG_FILE;
+use serde::Deserialize;
+
+/// The `[profiles]`, `[sync]`, `[gh_pages]`, `[hosting]`, `[rollover]` and `[naming]` of a
+/// `diaryx.toml` (its other tables are read elsewhere).
+#[derive(Debug, Default, Deserialize)]
+struct Config {
+    #[serde(default)]
+    profiles: BTreeMap<String, BuildProfile>,
+    #[serde(default)]
+    sync: SyncConfig,
+    #[serde(default)]
+    gh_pages: GhPagesConfig,
+    #[serde(default)]
+    hosting: HostingConfig,
+    #[serde(default)]
+    rollover: RolloverConfig,
+    #[serde(default)]
+    naming: NamingConfig,
+    #[serde(default)]
+    budget: BudgetConfig,
+    #[serde(default)]
+    usage: UsageConfig,
+    #[serde(default)]
+    encrypt: EncryptConfig,
+    #[serde(default)]
+    hooks: HooksConfig,
+}
+
+/// The `[sync]` table: where `diaryx sync` sends the vault.
+///
+/// ```toml
+/// [sync]
+/// backend = "rsync"               # or "rclone"
+/// remote = "me@host:journal"      # an rsync destination, or an rclone remote (`drive:journal`)
+/// exclude = ["site/"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncConfig {
+    pub backend: Option<crate::sync::Backend>,
+    pub remote: Option<String>,
+    /// Paths (relative to the vault folder) that are not synced; a trailing `/` names a folder.
+    pub exclude: Vec<String>,
+}
+
+/// The `[gh_pages]` table: how `build --gh-pages` publishes the site.
+///
+/// ```toml
+/// [gh_pages]
+/// path_prefix = "/journal/"       # a project site (user.github.io/journal/); default "/"
+/// cname = "diary.example.com"     # a custom domain, written to CNAME
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GhPagesConfig {
+    pub path_prefix: Option<String>,
+    pub cname: Option<String>,
+}
+
+/// The `[hosting]` table: the static host a build writes configuration for.
+///
+/// ```toml
+/// [hosting]
+/// provider = "netlify"            # or "vercel"
+/// clean_urls = true
+///
+/// [hosting.headers]
+/// Content-Security-Policy = "default-src 'self'"
+///
+/// [hosting.redirects]
+/// "/old/trip.html" = "/pages/rome.html"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HostingConfig {
+    pub provider: Option<crate::build::HostProvider>,
+    pub clean_urls: bool,
+    pub headers: BTreeMap<String, String>,
+    pub redirects: BTreeMap<String, String>,
+}
+
+/// The `[rollover]` table: where `diaryx rollover` puts the year and month indexes of a dated
+/// vault, and the templates it fills in. Paths are relative to the vault folder (the root
+/// index's); `{year}`, `{month}` (`08`) and `{month_name}` (`August`) are substituted.
+///
+/// ```toml
+/// [rollover]
+/// year_index = "{year}/{year}.md"                    # the default
+/// month_index = "{year}/{month}/{year}-{month}.md"    # the default
+/// month_template = "templates/month.md"
+/// year_template = "templates/year.md"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RolloverConfig {
+    pub year_index: Option<String>,
+    pub month_index: Option<String>,
+    pub year_template: Option<PathBuf>,
+    pub month_template: Option<PathBuf>,
+}
+
+/// The `[naming]` table: the file name pattern of new entries (see [`crate::naming::Naming`]),
+/// and whether `diaryx ci` checks existing entries against it.
+///
+/// ```toml
+/// [naming]
+/// pattern = "{date}-{slug}.md"    # default "{slug}.md"
+/// lint = true
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NamingConfig {
+    pub pattern: Option<String>,
+    pub lint: bool,
+}
+
+/// The `[budget]` table: size limits every build checks its output against. Sizes are a number
+/// of bytes with an optional unit (`KB`, `MB`, `GB`, powers of 1024); a file over its limit, or
+/// a site over `total`, is a build warning, and fails the build with `fail = true`.
+///
+/// ```toml
+/// [budget]
+/// page = "200KB"                  # each HTML file
+/// asset = "2MB"                   # each other file (images, stylesheets, ...)
+/// total = "50MB"                  # the whole site
+/// fail = true
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BudgetConfig {
+    pub page: Option<String>,
+    pub asset: Option<String>,
+    pub total: Option<String>,
+    pub fail: bool,
+}
+
+/// The `[usage]` table: whether commands run in the vault are logged to `.diaryx/usage.jsonl`
+/// for `diaryx usage` (off unless enabled).
+///
+/// ```toml
+/// [usage]
+/// enabled = true
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UsageConfig {
+    pub enabled: bool,
+}
+
+/// The `[encrypt]` table: how `build --encrypt` derives the key of encrypted pages from the
+/// passphrase (PBKDF2-HMAC-SHA256, as the browser repeats it), and the hint shown with the
+/// passphrase prompt.
+///
+/// ```toml
+/// [encrypt]
+/// iterations = 600000             # the default; more is slower to unlock and to guess
+/// hint = "The usual one"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EncryptConfig {
+    pub iterations: Option<u32>,
+    pub hint: Option<String>,
+}
+
+/// The `[hooks]` table: shell commands `diaryx build --hooks` runs before building (`pre_build`;
+/// one that fails stops the build) and after a successful build (`post_build`), in order, from the
+/// folder of the `diaryx.toml`. `{input}` and `{output}` in a command become the quoted entry
+/// and output paths, and `{pages}` / `{warnings}` the counts of a finished build; the same
+/// values are in the `DIARYX_INPUT`, `DIARYX_OUTPUT`, `DIARYX_PAGES` and `DIARYX_WARNINGS`
+/// environment variables.
+///
+/// ```toml
+/// [hooks]
+/// pre_build = ["git pull --ff-only"]
+/// post_build = ["./scripts/upload.sh {output}", "notify-send 'Diary published'"]
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    pub pre_build: Vec<String>,
+    pub post_build: Vec<String>,
+    /// Folder the commands run in (that of the `diaryx.toml`).
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+impl HooksConfig {
+    pub fn is_empty(&self) -> bool {
+        self.pre_build.is_empty() && self.post_build.is_empty()
+    }
+}
+
+/// One `[profiles.<name>]` table.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildProfile {
+    pub output: Option<PathBuf>,
+    pub include_nonpublic: bool,
+    pub audience: Option<String>,
+    /// `json`, `cbor` or `msgpack`.
+    pub emit_model: Option<String>,
+    pub flat: bool,
+    /// `pages`, `flat`, `mirror` or `dated` (see `build --layout`).
+    pub layout: Option<OutputLayout>,
+    pub verbose: bool,
+    pub strict: bool,
+    pub no_default_css: bool,
+    pub large_body_bytes: Option<usize>,
+    pub smart_punctuation: bool,
+    pub figures: bool,
+    pub pdf_previews: bool,
+    pub infer_dates: bool,
+    pub exclude: Vec<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub tags: Vec<String>,
+    pub drafts: bool,
+    pub future: bool,
+    pub as_root: bool,
+    pub changelog: bool,
+    pub no_version_check: bool,
+    pub pwa: bool,
+    pub graph_page: bool,
+    pub clean_urls: bool,
+    pub gh_pages: bool,
+    pub manifest: bool,
+    pub sign: Option<PathBuf>,
+    pub size_report: bool,
+    pub check_a11y: bool,
+    pub encrypt: bool,
+    pub allow_remote: Vec<String>,
+    pub remote_cache: Option<PathBuf>,
+    pub identity: Option<PathBuf>,
+}
+
+/// The nearest `diaryx.toml` at or above `dir`.
+fn nearest_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Load the `[sync]` table of the nearest `diaryx.toml` at or above `dir` (empty without one),
+/// with `${NAME}` references in `remote` substituted.
+pub fn load_sync(dir: &Path) -> Result<SyncConfig> {
+    Ok(load_interpolated(dir, "sync")?.sync)
+}
+
+/// Load the `[gh_pages]` table of the nearest `diaryx.toml` at or above `dir` (empty without
+/// one), with `${NAME}` references substituted.
+pub fn load_gh_pages(dir: &Path) -> Result<GhPagesConfig> {
+    Ok(load_interpolated(dir, "gh_pages")?.gh_pages)
+}
+
+/// Load the `[hosting]` table of the nearest `diaryx.toml` at or above `dir` (empty without
+/// one), with `${NAME}` references substituted.
+pub fn load_hosting(dir: &Path) -> Result<HostingConfig> {
+    Ok(load_interpolated(dir, "hosting")?.hosting)
+}
+
+/// Load the `[rollover]` table of the nearest `diaryx.toml` at or above `dir` (empty without
+/// one), with `${NAME}` references substituted.
+pub fn load_rollover(dir: &Path) -> Result<RolloverConfig> {
+    Ok(load_interpolated(dir, "rollover")?.rollover)
+}
+
+/// Load the `[naming]` table of the nearest `diaryx.toml` at or above `dir` (empty without one).
+pub fn load_naming(dir: &Path) -> Result<NamingConfig> {
+    Ok(load_interpolated(dir, "naming")?.naming)
+}
+
+/// Load the `[budget]` table of the nearest `diaryx.toml` at or above `dir` (empty without one).
+pub fn load_budget(dir: &Path) -> Result<BudgetConfig> {
+    Ok(load_interpolated(dir, "budget")?.budget)
+}
+
+/// Load the `[usage]` table of the nearest `diaryx.toml` at or above `dir` (empty without one).
+pub fn load_usage(dir: &Path) -> Result<UsageConfig> {
+    Ok(load_interpolated(dir, "usage")?.usage)
+}
+
+/// Load the `[encrypt]` table of the nearest `diaryx.toml` at or above `dir` (empty without
+/// one), with `${NAME}` references substituted.
+pub fn load_encrypt(dir: &Path) -> Result<EncryptConfig> {
+    Ok(load_interpolated(dir, "encrypt")?.encrypt)
+}
+
+/// Load the `[hooks]` table of the nearest `diaryx.toml` at or above `dir` (empty without one).
+/// The commands are taken as written: `${NAME}` is left for the shell to expand when they run.
+pub fn load_hooks(dir: &Path) -> Result<HooksConfig> {
+    let Some(path) = nearest_config(dir) else {
+        return Ok(HooksConfig::default());
+    };
+    let text =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let config: Config =
+        toml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+    Ok(HooksConfig {
+        dir: path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+        ..config.hooks
+    })
+}
+
+/// The nearest `diaryx.toml` at or above `dir` (empty without one), with `${NAME}` references in
+/// its `table` substituted.
+fn load_interpolated(dir: &Path, table: &str) -> Result<Config> {
+    let Some(path) = nearest_config(dir) else {
+        return Ok(Config::default());
+    };
+    let text =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut value: toml::Value =
+        toml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+    let vars: BTreeMap<String, String> = env::vars().collect();
+    let mut unset = Vec::new();
+    if let Some(table) = value.get_mut(table) {
+        interpolate_toml(table, &vars, &mut unset);
+    }
+    unset.dedup();
+    for var in unset {
+        eprintln!("[warn] Environment variable {var} is not set; [{table}] uses an empty value");
+    }
+    value
+        .try_into()
+        .with_context(|| format!("Invalid {}", path.display()))
+}
+
+/// Load profile `name` from the nearest `diaryx.toml` at or above `dir`.
+pub fn load_profile(dir: &Path, name: &str) -> Result<BuildProfile> {
+    let path = nearest_config(dir).ok_or_else(|| {
+        anyhow!(
+            "Build profile '{name}' requested, but there is no {CONFIG_FILE} at or above {}",
+            dir.display()
+        )
+    })?;
+    let text =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut value: toml::Value =
+        toml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+    let vars: BTreeMap<String, String> = env::vars().collect();
+    let mut unset = Vec::new();
+    if let Some(profile) = value
+        .get_mut("profiles")
+        .and_then(|profiles| profiles.get_mut(name))
+    {
+        interpolate_toml(profile, &vars, &mut unset);
+    }
+    unset.dedup();
+    for var in unset {
+        eprintln!(
+            "[warn] Environment variable {var} is not set; profile '{name}' uses an empty value"
+        );
+    }
+    let mut config: Config = value
+        .try_into()
+        .with_context(|| format!("Invalid {}", path.display()))?;
+    let mut profile = config.profiles.remove(name).ok_or_else(|| {
+        let known: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+        anyhow!(
+            "{} declares no profile '{name}' (available: {})",
+            path.display(),
+            if known.is_empty() {
+                "none".to_string()
+            } else {
+                known.join(", ")
+            }
+        )
+    })?;
+    let base = path.parent().unwrap_or(Path::new("."));
+    for path in [
+        &mut profile.output,
+        &mut profile.sign,
+        &mut profile.remote_cache,
+        &mut profile.identity,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        *path = base.join(&*path);
+    }
+    Ok(profile)
+}
+
+/// Substitute `${NAME}` references in every string of a profile (or `[sync]` table).
+fn interpolate_toml(
+    value: &mut toml::Value,
+    vars: &BTreeMap<String, String>,
+    unset: &mut Vec<String>,
+) {
+    match value {
+        toml::Value::String(text) => *text = interpolate(text, vars, unset),
+        toml::Value::Array(items) => {
+            for item in items {
+                interpolate_toml(item, vars, unset);
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, item) in table.iter_mut() {
+                interpolate_toml(item, vars, unset);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,400 @@
+/*!
+ * `diaryx conflicts`: entries left with two versions by `diaryx sync` (a
+ * `<name>.sync-conflict.md` copy next to the entry) or by a git merge (`<<<<<<<` / `=======` /
+ * `>>>>>>>` markers inside it), and a resolver that merges the two.
+ *
+ * Frontmatter is merged field by field: a field only one version has is kept, lists are
+ * unioned (ours first), `updated` takes the later value, and any other differing field takes the
+ * value of the version with the later `updated` (ours when that cannot be told). Bodies that
+ * differ are shown as a diff, and the resolver asks which to keep.
+ */
+
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde_yaml::{Mapping, Value};
+use similar::TextDiff;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::archive::{entry_name, files_under};
+use crate::sync::conflict_original;
+use crate::vault::{
+    EditMode, FileEdit, apply_edits_with_undo, parse_fields, prompt, set_field, split_frontmatter,
+};
+
+/// Where the second version of an entry comes from.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Source {
+    /// A `.sync-conflict` copy written by `diaryx sync`.
+    Sync,
+    /// Git conflict markers in the entry itself.
+    Git,
+}
+
+struct Conflict {
+    path: PathBuf,
+    source: Source,
+    ours: String,
+    theirs: String,
+    /// The `.sync-conflict` copy, removed once the entry is resolved.
+    copy: Option<PathBuf>,
+}
+
+/// Which body a resolution keeps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum BodyChoice {
+    /// The local version (for git: the current branch's).
+    Ours,
+    /// The other version (the remote's, or the merged-in branch's).
+    Theirs,
+    /// Ours, then theirs.
+    Both,
+}
+
+/// `conflicts list`: `<sync|git>\t<path>\t<what differs>` per conflicted entry.
+pub fn run_list(vault_root: &Path) -> Result<()> {
+    let conflicts = find_conflicts(vault_root)?;
+    if conflicts.is_empty() {
+        println!("[conflicts] none");
+    }
+    for conflict in &conflicts {
+        let merged = merge(&conflict.ours, &conflict.theirs);
+        println!(
+            "{}\t{}\t{}",
+            match conflict.source {
+                Source::Sync => "sync",
+                Source::Git => "git",
+            },
+            rel(vault_root, &conflict.path),
+            merged.describe()
+        );
+    }
+    Ok(())
+}
+
+/// `conflicts resolve`: merge each conflicted entry (all of them, or those in `only`), asking
+/// which body to keep unless `body` says. Resolutions are journaled for `undo`; `.sync-conflict`
+/// copies go to the trash.
+pub fn run_resolve(
+    vault_root: &Path,
+    only: &[PathBuf],
+    body: Option<BodyChoice>,
+    dry_run: bool,
+    command: &str,
+) -> Result<()> {
+    let only: Vec<PathBuf> = only
+        .iter()
+        .map(|p| p.canonicalize().unwrap_or_else(|_| p.clone()))
+        .collect();
+    let conflicts: Vec<Conflict> = find_conflicts(vault_root)?
+        .into_iter()
+        .filter(|c| {
+            only.is_empty()
+                || only
+                    .iter()
+                    .any(|p| *p == c.path || Some(p) == c.copy.as_ref())
+        })
+        .collect();
+    if conflicts.is_empty() {
+        println!("[conflicts] nothing to resolve");
+        return Ok(());
+    }
+
+    let mut edits = Vec::new();
+    let mut skipped = 0;
+    for conflict in &conflicts {
+        let name = rel(vault_root, &conflict.path);
+        let merged = merge(&conflict.ours, &conflict.theirs);
+        eprintln!("[conflicts] {name}: {}", merged.describe());
+        let choice = if merged.ours_body == merged.theirs_body {
+            BodyChoice::Ours
+        } else if let Some(choice) = body {
+            choice
+        } else {
+            print!(
+                "{}",
+                TextDiff::from_lines(merged.ours_body.as_str(), merged.theirs_body.as_str())
+                    .unified_diff()
+                    .header(&format!("{name} (ours)"), &format!("{name} (theirs)"))
+            );
+            let choices = [
+                ('o', "ours: keep this version's body"),
+                ('t', "theirs: take the other body"),
+                ('b', "both: ours, then theirs"),
+                ('s', "skip this entry"),
+            ];
+            match prompt(&format!("Which body for {name}?"), &choices)? {
+                'o' => BodyChoice::Ours,
+                't' => BodyChoice::Theirs,
+                'b' => BodyChoice::Both,
+                _ => {
+                    skipped += 1;
+                    continue;
+                }
+            }
+        };
+        let body = match choice {
+            BodyChoice::Ours => merged.ours_body.clone(),
+            BodyChoice::Theirs => merged.theirs_body.clone(),
+            BodyChoice::Both => {
+                let mut both = merged.ours_body.clone();
+                if !both.is_empty() && !both.ends_with('\n') {
+                    both.push('\n');
+                }
+                both.push('\n');
+                both.push_str(&merged.theirs_body);
+                both
+            }
+        };
+        edits.push(FileEdit {
+            path: conflict.path.clone(),
+            before: fs::read_to_string(&conflict.path).unwrap_or_default(),
+            after: Some(merged.frontmatter + &body),
+        });
+        if let Some(copy) = &conflict.copy {
+            edits.push(FileEdit {
+                path: copy.clone(),
+                before: conflict.theirs.clone(),
+                after: None,
+            });
+        }
+    }
+    let mode = EditMode {
+        dry_run,
+        ..EditMode::default()
+    };
+    apply_edits_with_undo(&edits, mode, vault_root, command)?;
+    println!(
+        "[conflicts] {}{} of {} entr{} resolved",
+        if dry_run { "(dry run) " } else { "" },
+        conflicts.len() - skipped,
+        conflicts.len(),
+        if conflicts.len() == 1 { "y" } else { "ies" }
+    );
+    Ok(())
+}
+
+/// Every conflicted entry under `vault_root`, by path.
+fn find_conflicts(vault_root: &Path) -> Result<Vec<Conflict>> {
+    let mut conflicts = Vec::new();
+    for path in files_under(vault_root)? {
+        let name = entry_name(path.strip_prefix(vault_root).unwrap_or(&path));
+        if name.starts_with(".diaryx/")
+            || name.starts_with(".git/")
+            || !name.to_ascii_lowercase().ends_with(".md")
+        {
+            continue;
+        }
+        let read = |path: &Path| match fs::read_to_string(path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+            other => other.with_context(|| format!("Failed to read {}", path.display())),
+        };
+        if let Some(original) = conflict_original(&path) {
+            conflicts.push(Conflict {
+                ours: read(&original)?,
+                theirs: read(&path)?,
+                path: original,
+                source: Source::Sync,
+                copy: Some(path),
+            });
+        } else if let Some((ours, theirs)) = split_git_conflict(&read(&path)?) {
+            conflicts.push(Conflict {
+                path,
+                source: Source::Git,
+                ours,
+                theirs,
+                copy: None,
+            });
+        }
+    }
+    conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(conflicts)
+}
+
+/// The two sides of a text with git conflict markers (`None` without any). The base section
+/// of `diff3`-style conflicts is dropped.
+fn split_git_conflict(text: &str) -> Option<(String, String)> {
+    #[derive(PartialEq)]
+    enum In {
+        Both,
+        Ours,
+        Base,
+        Theirs,
+    }
+    let mut state = In::Both;
+    let (mut ours, mut theirs) = (String::new(), String::new());
+    let mut found = false;
+    for line in text.split_inclusive('\n') {
+        state = match state {
+            In::Both if line.starts_with("<<<<<<< ") || line.trim_end() == "<<<<<<<" => {
+                found = true;
+                In::Ours
+            }
+            In::Ours | In::Base if line.trim_end() == "=======" => In::Theirs,
+            In::Ours if line.starts_with("|||||||") => In::Base,
+            In::Theirs if line.starts_with(">>>>>>>") => In::Both,
+            state => {
+                match state {
+                    In::Both => {
+                        ours.push_str(line);
+                        theirs.push_str(line);
+                    }
+                    In::Ours => ours.push_str(line),
+                    In::Theirs => theirs.push_str(line),
+                    In::Base => {}
+                }
+                state
+            }
+        };
+    }
+    found.then_some((ours, theirs))
+}
+
+/// Two versions of an entry, merged as far as that can be done without asking.
+struct Merged {
+    /// The merged frontmatter block (ours, with the merged fields rewritten).
+    frontmatter: String,
+    ours_body: String,
+    theirs_body: String,
+    /// How each differing field was settled (`tags: union`).
+    fields: Vec<String>,
+}
+
+impl Merged {
+    fn describe(&self) -> String {
+        let mut parts = self.fields.clone();
+        if self.ours_body != self.theirs_body {
+            parts.push("body differs".to_string());
+        }
+        if parts.is_empty() {
+            "identical".to_string()
+        } else {
+            parts.join("; ")
+        }
+    }
+}
+
+fn merge(ours: &str, theirs: &str) -> Merged {
+    let (ours_fm, ours_body) = split_frontmatter(ours);
+    let (theirs_fm, theirs_body) = split_frontmatter(theirs);
+    let (our_fields, their_fields) = (parse_fields(ours_fm), parse_fields(theirs_fm));
+    let theirs_newer = updated(&their_fields) > updated(&our_fields);
+
+    let mut frontmatter = ours_fm.to_string();
+    let mut fields = Vec::new();
+    for (key, theirs) in &their_fields {
+        let Some(key_name) = key.as_str() else {
+            continue;
+        };
+        let (value, how) = match our_fields.get(key) {
+            Some(ours) if ours == theirs => continue,
+            None => (theirs.clone(), "from theirs"),
+            Some(Value::Sequence(ours)) if theirs.is_sequence() => {
+                let mut union = ours.clone();
+                for item in theirs.as_sequence().into_iter().flatten() {
+                    if !union.contains(item) {
+                        union.push(item.clone());
+                    }
+                }
+                (Value::Sequence(union), "union")
+            }
+            Some(ours) if key_name == "updated" => {
+                if theirs_newer {
+                    (theirs.clone(), "later")
+                } else {
+                    (ours.clone(), "later")
+                }
+            }
+            Some(_) if theirs_newer => (theirs.clone(), "theirs (later updated)"),
+            Some(ours) => (ours.clone(), "ours"),
+        };
+        if our_fields.get(key) != Some(&value) {
+            frontmatter = set_field(&frontmatter, key_name, &value);
+        }
+        fields.push(format!("{key_name}: {how}"));
+    }
+    Merged {
+        frontmatter,
+        ours_body: ours_body.to_string(),
+        theirs_body: theirs_body.to_string(),
+        fields,
+    }
+}
+
+/// `updated` as an instant (RFC 3339), for telling which version is later.
+fn updated(fields: &Mapping) -> Option<OffsetDateTime> {
+    let value = fields.get("updated")?.as_str()?;
+    OffsetDateTime::parse(value, &Rfc3339).ok()
+}
+
+fn rel(root: &Path, path: &Path) -> String {
+    entry_name(path.strip_prefix(root).unwrap_or(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frontmatter_merges_field_by_field() {
+        let ours = "---\ntitle: Ours\ntags:\n- a\nupdated: 2024-01-01T00:00:00Z\n---\nBody\n";
+        let theirs = "---\ntitle: Theirs\ntags:\n- b\n- a\nupdated: 2024-01-02T00:00:00Z\nmood: calm\n---\nBody\n";
+        let merged = merge(ours, theirs);
+        let fields = parse_fields(split_frontmatter(&merged.frontmatter).0);
+        assert_eq!(fields["title"], "Theirs");
+        assert_eq!(
+            fields["tags"],
+            serde_yaml::from_str::<Value>("[a, b]").unwrap()
+        );
+        assert_eq!(fields["updated"], "2024-01-02T00:00:00Z");
+        assert_eq!(fields["mood"], "calm");
+        assert_eq!(
+            merged.describe(),
+            "title: theirs (later updated); tags: union; updated: later; mood: from theirs"
+        );
+
+        // Without a later `updated` on their side, ours wins; identical versions merge cleanly.
+        let merged = merge(theirs, ours);
+        let fields = parse_fields(split_frontmatter(&merged.frontmatter).0);
+        assert_eq!(fields["title"], "Theirs");
+        assert_eq!(merge(ours, ours).describe(), "identical");
+    }
+
+    #[test]
+    fn git_markers_split_into_both_sides() {
+        let text =
+            "shared\n<<<<<<< HEAD\nmine\n||||||| base\nold\n=======\nyours\n>>>>>>> other\nend\n";
+        let (ours, theirs) = split_git_conflict(text).unwrap();
+        assert_eq!(ours, "shared\nmine\nend\n");
+        assert_eq!(theirs, "shared\nyours\nend\n");
+        assert!(split_git_conflict("no markers\n").is_none());
+
+        let merged = merge(&ours, &theirs);
+        assert_eq!(merged.describe(), "body differs");
+        assert_eq!(merged.ours_body, "shared\nmine\nend\n");
+    }
+
+    #[test]
+    fn resolving_an_entry_deleted_locally_takes_the_other_copy() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path().canonicalize().unwrap();
+        let copy = root.join("a.sync-conflict.md");
+        fs::write(&copy, "---\ntitle: Kept\n---\nTheir body\n").unwrap();
+        let conflicts = find_conflicts(&root).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].ours.is_empty());
+
+        run_resolve(&root, &[], Some(BodyChoice::Theirs), false, "test").unwrap();
+        let resolved = fs::read_to_string(root.join("a.md")).unwrap();
+        assert_eq!(
+            parse_fields(split_frontmatter(&resolved).0)["title"],
+            "Kept"
+        );
+        assert!(resolved.ends_with("Their body\n"));
+        assert!(!copy.exists());
+        assert!(find_conflicts(&root).unwrap().is_empty());
+    }
+}
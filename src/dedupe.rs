@@ -0,0 +1,176 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use serde_yaml::Value;
+
+use crate::query::{Filter, select_entries};
+use crate::vault::{parse_fields, split_frontmatter};
+
+/// Words per shingle.
+const SHINGLE: usize = 3;
+/// MinHash signature length, split into `BANDS` bands for locality-sensitive hashing.
+const HASHES: usize = 64;
+const BANDS: usize = 16;
+/// Bodies with fewer shingles are too short to compare meaningfully.
+const MIN_SHINGLES: usize = 8;
+
+/// `dedupe`: report candidate duplicate entries (read-only).
+///
+/// Three kinds of candidates are listed: entries whose bodies are identical after normalising
+/// case and whitespace, entries sharing a title and `created` date, and pairs of bodies whose
+/// word-shingle Jaccard similarity is at least `threshold`. Near-duplicates are found with
+/// MinHash/LSH so large vaults are not compared pairwise; candidate pairs are then scored
+/// exactly.
+pub fn run_dedupe(input: &Path, filter: Option<&Filter>, threshold: f64) -> Result<()> {
+    if !(0.0..=1.0).contains(&threshold) {
+        bail!("--threshold must be between 0 and 1");
+    }
+    let root = input.parent().unwrap_or(Path::new("."));
+    let mut names = Vec::new();
+    let mut by_body: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+    let mut by_title: BTreeMap<(String, String), Vec<usize>> = BTreeMap::new();
+    let mut shingles = Vec::new();
+    for entry in select_entries(input, filter)? {
+        let (fm, body) = split_frontmatter(&entry.text);
+        let fields = parse_fields(fm);
+        let i = names.len();
+        names.push(
+            entry
+                .path
+                .strip_prefix(root)
+                .unwrap_or(&entry.path)
+                .display()
+                .to_string(),
+        );
+
+        let words: Vec<String> = body
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        if !words.is_empty() {
+            by_body.entry(hash(&words)).or_default().push(i);
+        }
+        let title = fields.get("title").and_then(Value::as_str);
+        let created = fields.get("created").and_then(Value::as_str);
+        if let (Some(title), Some(created)) = (title, created) {
+            // Date part only: re-imports often shift the time of day.
+            let date = created.get(..10).unwrap_or(created).to_string();
+            by_title
+                .entry((title.trim().to_lowercase(), date))
+                .or_default()
+                .push(i);
+        }
+        let set: HashSet<u64> = words.windows(SHINGLE).map(hash).collect();
+        shingles.push(set);
+    }
+
+    let mut groups = 0;
+    let mut identical: HashSet<(usize, usize)> = HashSet::new();
+    for members in by_body.values().filter(|m| m.len() > 1) {
+        groups += 1;
+        print_group("identical body", members, &names);
+        for (n, &a) in members.iter().enumerate() {
+            for &b in &members[n + 1..] {
+                identical.insert((a, b));
+            }
+        }
+    }
+    for members in by_title.values().filter(|m| m.len() > 1) {
+        groups += 1;
+        print_group("same title and date", members, &names);
+    }
+
+    let mut similar: Vec<(f64, usize, usize)> = candidate_pairs(&shingles)
+        .into_iter()
+        .filter(|pair| !identical.contains(pair))
+        .filter_map(|(a, b)| {
+            let score = jaccard(&shingles[a], &shingles[b]);
+            (score >= threshold).then_some((score, a, b))
+        })
+        .collect();
+    similar.sort_by(|x, y| y.0.total_cmp(&x.0).then((x.1, x.2).cmp(&(y.1, y.2))));
+    for (score, a, b) in similar {
+        groups += 1;
+        print_group(
+            &format!("similar body ({:.0}%)", score * 100.0),
+            &[a, b],
+            &names,
+        );
+    }
+
+    if groups == 0 {
+        println!(
+            "[dedupe] no duplicate candidates among {} entries",
+            names.len()
+        );
+    }
+    Ok(())
+}
+
+fn print_group(reason: &str, members: &[usize], names: &[String]) {
+    println!("{reason}:");
+    for &i in members {
+        println!("  {}", names[i]);
+    }
+}
+
+/// Pairs `(a, b)` with `a < b` whose MinHash signatures agree on at least one whole band.
+fn candidate_pairs(shingles: &[HashSet<u64>]) -> Vec<(usize, usize)> {
+    let rows = HASHES / BANDS;
+    let mut buckets: HashMap<(usize, Vec<u64>), Vec<usize>> = HashMap::new();
+    for (i, set) in shingles.iter().enumerate() {
+        if set.len() < MIN_SHINGLES {
+            continue;
+        }
+        let signature: Vec<u64> = (0..HASHES as u64)
+            .map(|seed| {
+                set.iter()
+                    .map(|&h| mix(h ^ mix(seed)))
+                    .min()
+                    .unwrap_or(u64::MAX)
+            })
+            .collect();
+        for (band, chunk) in signature.chunks(rows).enumerate() {
+            buckets.entry((band, chunk.to_vec())).or_default().push(i);
+        }
+    }
+    let mut pairs: Vec<(usize, usize)> = buckets
+        .into_values()
+        .filter(|b| b.len() > 1)
+        .flat_map(|members| {
+            let mut pairs = Vec::new();
+            for (n, &a) in members.iter().enumerate() {
+                for &b in &members[n + 1..] {
+                    pairs.push((a, b));
+                }
+            }
+            pairs
+        })
+        .collect();
+    pairs.sort_unstable();
+    pairs.dedup();
+    pairs
+}
+
+fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+    let shared = a.intersection(b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// splitmix64 finaliser: cheap independent-looking hash functions from one shingle hash.
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
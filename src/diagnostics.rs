@@ -0,0 +1,329 @@
+use std::path::{Component, Path, PathBuf};
+
+use diaryx_core::schema::Schema;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::build::RealFs;
+
+/// Single-document lint shared by editor integrations (`lsp`, `check`).
+///
+/// Combines the frontmatter warnings core reports for a file (missing required fields, bad
+/// types, invalid YAML, including the fields the vault's `diaryx.toml` declares) with link checks against the real filesystem (broken `.md` links,
+/// missing attachments), and positions each finding in the source text so editors can
+/// underline it. Only the document itself is read; no vault build is needed.
+#[derive(Debug, Clone, Serialize)]
+pub struct DocDiagnostic {
+    /// Zero-based line.
+    pub line: u32,
+    /// Zero-based start / end columns in UTF-16 code units (the LSP convention).
+    pub start: u32,
+    pub end: u32,
+    pub severity: Severity,
+    /// Stable identifier: `frontmatter`, `broken-link` or `missing-attachment`.
+    pub code: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A Markdown link found in a document (frontmatter values included).
+#[derive(Debug, Clone)]
+pub struct LinkRef {
+    pub line: usize,
+    /// Byte offset of the opening `[` within the line.
+    pub link_start: usize,
+    /// Byte range of the link target within the line (without `<` `>`).
+    pub start: usize,
+    pub end: usize,
+    pub target: String,
+    /// Top-level frontmatter key the link sits under, if it is in the frontmatter.
+    pub key: Option<String>,
+}
+
+/// Where a link points once resolved relative to its document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkTarget {
+    pub path: PathBuf,
+    /// True for Diaryx documents (`.md`, or `.html` pages listed in the graph), false for
+    /// attachments.
+    pub is_doc: bool,
+}
+
+/// Lint one document. `path` is where the text lives (or would live) on disk; links are resolved
+/// relative to its directory.
+pub fn lint_document(path: &Path, text: &str) -> Vec<DocDiagnostic> {
+    let mut out = Vec::new();
+    let lines: Vec<&str> = text.lines().collect();
+    let fm_end = frontmatter_end(&lines);
+
+    let path_str = path.to_string_lossy();
+    let schema = match Schema::discover(&path_str, &RealFs) {
+        Ok(schema) => schema,
+        Err(e) => {
+            out.push(whole_line(
+                &lines,
+                0,
+                Severity::Error,
+                "frontmatter",
+                format!("{e:#}"),
+            ));
+            Schema::default()
+        }
+    };
+    let parsed = diaryx_core::parse_document_with_schema(&path_str, text.to_string(), &schema);
+    let suffix = format!(" ({path_str})");
+    for warning in parsed.warnings {
+        let message = warning
+            .message
+            .strip_suffix(&suffix)
+            .unwrap_or(&warning.message)
+            .to_string();
+        let line = quoted_field(&message)
+            .and_then(|field| find_key_line(&lines, fm_end, field))
+            .unwrap_or(0);
+        // Unparseable frontmatter discards every field, so it outranks a single bad value.
+        let severity = if message.contains("YAML frontmatter") {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        out.push(whole_line(&lines, line, severity, "frontmatter", message));
+    }
+
+    for link in scan_links(text) {
+        let Some(target) = resolve_link(path, &link.target, link.key.as_deref()) else {
+            continue;
+        };
+        if target.path.is_file() {
+            continue;
+        }
+        let (code, message) = if target.is_doc {
+            ("broken-link", format!("Broken link: {}", link.target))
+        } else {
+            (
+                "missing-attachment",
+                format!("Attachment not found: {}", link.target),
+            )
+        };
+        let line_text = lines.get(link.line).copied().unwrap_or("");
+        out.push(DocDiagnostic {
+            line: link.line as u32,
+            start: utf16_col(line_text, link.start),
+            end: utf16_col(line_text, link.end),
+            severity: Severity::Warning,
+            code,
+            message,
+        });
+    }
+    out
+}
+
+/// Index of the closing `---` line when the text starts with a frontmatter block.
+pub fn frontmatter_end(lines: &[&str]) -> Option<usize> {
+    if lines.first().map(|l| l.trim_end()) != Some("---") {
+        return None;
+    }
+    lines
+        .iter()
+        .enumerate()
+        .skip(1)
+        .find(|(_, l)| l.trim_end() == "---")
+        .map(|(i, _)| i)
+}
+
+/// Top-level frontmatter key governing `line` (the nearest `key:` at column 0 above it).
+pub fn frontmatter_key_at(lines: &[&str], fm_end: Option<usize>, line: usize) -> Option<String> {
+    let end = fm_end?;
+    if line == 0 || line >= end {
+        return None;
+    }
+    lines[1..=line].iter().rev().find_map(|l| {
+        let (key, _) = l.split_once(':')?;
+        (!key.is_empty() && !key.starts_with([' ', '\t', '-', '#'])).then(|| key.to_string())
+    })
+}
+
+/// Every Markdown link (`[text](target)`, images included) outside fenced code blocks. Targets
+/// may contain raw spaces, as core accepts them; a trailing `"title"` is not part of the target.
+pub fn scan_links(text: &str) -> Vec<LinkRef> {
+    static LINK: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r#"\[[^\]]*\]\(\s*(?:<([^>]*)>|([^)\s]+(?: +[^)\s"']+)*))"#).unwrap()
+    });
+    let lines: Vec<&str> = text.lines().collect();
+    let fm_end = frontmatter_end(&lines);
+    let mut in_fence = false;
+    let mut out = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let in_body = fm_end.is_none_or(|end| i > end);
+        if in_body && line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        for cap in LINK.captures_iter(line) {
+            let Some(m) = cap.get(1).or_else(|| cap.get(2)) else {
+                continue;
+            };
+            out.push(LinkRef {
+                line: i,
+                link_start: cap.get(0).map_or(m.start(), |all| all.start()),
+                start: m.start(),
+                end: m.end(),
+                target: m.as_str().to_string(),
+                key: if in_body {
+                    None
+                } else {
+                    frontmatter_key_at(&lines, fm_end, i)
+                },
+            });
+        }
+    }
+    out
+}
+
+/// Resolve a link target relative to `doc_path`. External URLs, anchors and absolute paths
+/// yield `None`. `contents` / `part_of` entries may omit `.md`, as in core.
+pub fn resolve_link(doc_path: &Path, target: &str, key: Option<&str>) -> Option<LinkTarget> {
+    let core = target.split(['?', '#']).next().unwrap_or(target);
+    if core.is_empty()
+        || core.starts_with('/')
+        || core.contains("://")
+        || core.starts_with("mailto:")
+        || core.starts_with("data:")
+    {
+        return None;
+    }
+    let graph_key = is_graph_key(key);
+    // Core resolves graph entries verbatim but decodes `%20` in body links.
+    let decoded = if graph_key {
+        core.to_string()
+    } else {
+        core.replace("%20", " ")
+    };
+    let dir = doc_path.parent().unwrap_or(Path::new(""));
+    let path = normalize(&dir.join(&decoded));
+    let lower = decoded.to_ascii_lowercase();
+    if lower.ends_with(".md") {
+        return Some(LinkTarget { path, is_doc: true });
+    }
+    if graph_key && Path::new(&decoded).extension().is_none() && !path.is_file() {
+        let mut with_md = path.into_os_string();
+        with_md.push(".md");
+        return Some(LinkTarget {
+            path: PathBuf::from(with_md),
+            is_doc: true,
+        });
+    }
+    if lower.ends_with(".html") || lower.ends_with(".htm") {
+        // Graph entries may list HTML pages; body links may point at generated pages.
+        return graph_key.then_some(LinkTarget { path, is_doc: true });
+    }
+    Some(LinkTarget {
+        path,
+        is_doc: graph_key,
+    })
+}
+
+/// `contents` / `part_of`: frontmatter keys whose links form the vault graph.
+pub fn is_graph_key(key: Option<&str>) -> bool {
+    matches!(key, Some("contents" | "part_of"))
+}
+
+/// Link target from a document in `from_dir` to `to`. Spaces are `%20`-encoded in body links;
+/// graph entries (`key` is `contents` / `part_of`) keep them raw, as core reads those verbatim.
+pub fn link_target(from_dir: &Path, to: &Path, key: Option<&str>) -> String {
+    let from: Vec<_> = from_dir.components().collect();
+    let target: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    let mut parts: Vec<String> = vec!["..".into(); from.len() - common];
+    parts.extend(
+        target[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned()),
+    );
+    let joined = parts.join("/");
+    if is_graph_key(key) {
+        joined
+    } else {
+        joined.replace(' ', "%20")
+    }
+}
+
+/// Lexically collapse `.` and `..` components.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for comp in path.components() {
+        match comp {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    out.push("..");
+                }
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Convert a byte offset within `line` to a UTF-16 column.
+pub fn utf16_col(line: &str, byte: usize) -> u32 {
+    line[..byte.min(line.len())]
+        .chars()
+        .map(|c| c.len_utf16() as u32)
+        .sum()
+}
+
+/// Convert a UTF-16 column within `line` to a byte offset (clamped to the line).
+pub fn byte_at_utf16(line: &str, col: u32) -> usize {
+    let mut units = 0u32;
+    for (i, c) in line.char_indices() {
+        if units >= col {
+            return i;
+        }
+        units += c.len_utf16() as u32;
+    }
+    line.len()
+}
+
+fn whole_line(
+    lines: &[&str],
+    line: usize,
+    severity: Severity,
+    code: &'static str,
+    message: String,
+) -> DocDiagnostic {
+    let text = lines.get(line).copied().unwrap_or("");
+    DocDiagnostic {
+        line: line as u32,
+        start: 0,
+        end: utf16_col(text, text.len()),
+        severity,
+        code,
+        message,
+    }
+}
+
+/// First `'quoted'` word in a core warning (`Field 'author' should be ...`).
+fn quoted_field(message: &str) -> Option<&str> {
+    let rest = message.split_once('\'')?.1;
+    Some(rest.split_once('\'')?.0)
+}
+
+fn find_key_line(lines: &[&str], fm_end: Option<usize>, key: &str) -> Option<usize> {
+    let end = fm_end?;
+    (1..end).find(|&i| {
+        lines[i]
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.starts_with(':'))
+    })
+}
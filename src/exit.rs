@@ -0,0 +1,74 @@
+/*!
+ * Exit status of the `diaryx` process, so scripts and CI can branch on the kind of failure
+ * instead of parsing stderr.
+ *
+ * Errors are classified by what their chain contains: the marker errors below, I/O and network
+ * errors, and parse errors of the vault's YAML / TOML / JSON files. Anything else is internal.
+ */
+
+use std::fmt;
+use std::process::ExitCode;
+
+/// Listed under `diaryx --help`.
+pub const EXIT_CODES_HELP: &str = "\
+Exit status:
+  0  success
+  1  internal error
+  2  validation failure (invalid arguments or configuration, failed verification or `ci`)
+  3  --strict build failed because of warnings
+  4  I/O failure (a file or remote source could not be read or written)";
+
+pub const INTERNAL: u8 = 1;
+pub const VALIDATION: u8 = 2;
+pub const STRICT_WARNINGS: u8 = 3;
+pub const IO: u8 = 4;
+
+/// Input that was read fine but is not acceptable: a bad argument or configuration, a manifest
+/// or snapshot that does not verify.
+#[derive(Debug)]
+pub struct ValidationFailed(pub String);
+
+impl fmt::Display for ValidationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ValidationFailed {}
+
+/// A `--strict` build that produced warnings (their number).
+#[derive(Debug)]
+pub struct StrictWarnings(pub usize);
+
+impl fmt::Display for StrictWarnings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Strict mode: build failed due to {} warning(s)", self.0)
+    }
+}
+
+impl std::error::Error for StrictWarnings {}
+
+/// The exit code for `err`.
+pub fn code(err: &anyhow::Error) -> u8 {
+    let chain = || err.chain();
+    if chain().any(|e| e.is::<StrictWarnings>()) {
+        STRICT_WARNINGS
+    } else if chain().any(|e| {
+        e.is::<ValidationFailed>()
+            || e.is::<toml::de::Error>()
+            || e.is::<serde_yaml::Error>()
+            || e.is::<serde_json::Error>()
+    }) {
+        VALIDATION
+    } else if chain().any(|e| e.is::<std::io::Error>() || e.is::<ureq::Error>()) {
+        IO
+    } else {
+        INTERNAL
+    }
+}
+
+/// Report `err` as `fn main() -> Result<()>` would, and return its exit code.
+pub fn report(err: anyhow::Error) -> ExitCode {
+    eprintln!("Error: {err:?}");
+    ExitCode::from(code(&err))
+}
@@ -0,0 +1,164 @@
+use serde_yaml::Value;
+
+use super::ExportEntry;
+use crate::vault::{parse_fields, split_frontmatter};
+
+/// One flashcard, both sides in Markdown.
+struct Card {
+    front: String,
+    back: String,
+}
+
+/// Anki import file for the cards found in `entries`, plus the number of cards.
+///
+/// Cards come from a `flashcards:` frontmatter list (items with `q`/`a`, `question`/`answer` or
+/// `front`/`back`) and from `Q:` / `A:` blocks in the body; an answer runs until a blank line or
+/// the next `Q:`. Sides are rendered to HTML. Each card gets a GUID derived from the entry slug and
+/// its question, so re-importing an updated export updates cards instead of duplicating them; the
+/// entry's tags become the note's tags.
+pub(super) fn export(entries: &[ExportEntry], deck: Option<&str>) -> (String, usize) {
+    let mut out = String::from("#separator:tab\n#html:true\n#notetype:Basic\n");
+    out.push_str("#guid column:1\n#tags column:4\n");
+    if let Some(deck) = deck {
+        out.push_str(&format!("#deck:{deck}\n"));
+    }
+    let mut count = 0;
+    for entry in entries {
+        let (fm, body) = split_frontmatter(&entry.source);
+        let fields = parse_fields(fm);
+        let tags: Vec<String> = match fields.get("tags") {
+            Some(Value::Sequence(items)) => items.iter().filter_map(Value::as_str).collect(),
+            Some(Value::String(tag)) => vec![tag.as_str()],
+            _ => Vec::new(),
+        }
+        .into_iter()
+        .map(|t| t.split_whitespace().collect::<Vec<_>>().join("_"))
+        .collect();
+
+        let mut cards = frontmatter_cards(fields.get("flashcards"));
+        cards.extend(body_cards(body));
+        for card in cards {
+            let guid = format!(
+                "diaryx-{:016x}",
+                fnv1a(&format!("{}\n{}", entry.page.id, card.front))
+            );
+            let row = [
+                guid,
+                render(&card.front),
+                render(&card.back),
+                tags.join(" "),
+            ];
+            out.push_str(&row.map(|f| field(&f)).join("\t"));
+            out.push('\n');
+            count += 1;
+        }
+    }
+    (out, count)
+}
+
+fn frontmatter_cards(list: Option<&Value>) -> Vec<Card> {
+    let Some(Value::Sequence(items)) = list else {
+        return Vec::new();
+    };
+    let side = |item: &Value, keys: [&str; 3]| {
+        keys.iter().find_map(|k| item.get(k)).and_then(|v| match v {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        })
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            Some(Card {
+                front: side(item, ["q", "question", "front"])?,
+                back: side(item, ["a", "answer", "back"])?,
+            })
+        })
+        .collect()
+}
+
+/// `Q:` / `A:` blocks outside fenced code.
+fn body_cards(body: &str) -> Vec<Card> {
+    enum Side {
+        None,
+        Front,
+        Back,
+    }
+    let mut cards = Vec::new();
+    let mut current: Option<Card> = None;
+    let mut side = Side::None;
+    let mut in_fence = false;
+    let mut finish = |card: &mut Option<Card>| {
+        if let Some(card) = card.take()
+            && !card.front.is_empty()
+            && !card.back.is_empty()
+        {
+            cards.push(card);
+        }
+    };
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if !in_fence && let Some(q) = trimmed.strip_prefix("Q:") {
+            finish(&mut current);
+            current = Some(Card {
+                front: q.trim().to_string(),
+                back: String::new(),
+            });
+            side = Side::Front;
+            continue;
+        }
+        let Some(card) = current.as_mut() else {
+            continue;
+        };
+        if !in_fence && let Some(a) = trimmed.strip_prefix("A:") {
+            card.back = a.trim().to_string();
+            side = Side::Back;
+            continue;
+        }
+        if !in_fence && line.trim().is_empty() {
+            // A question may wrap; a blank line ends the answer (or an unanswered question).
+            if let Side::Back | Side::Front = side {
+                finish(&mut current);
+                side = Side::None;
+            }
+            continue;
+        }
+        let text = match side {
+            Side::Front => &mut card.front,
+            Side::Back => &mut card.back,
+            Side::None => continue,
+        };
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(line);
+    }
+    finish(&mut current);
+    cards
+}
+
+/// Markdown side to HTML for Anki.
+fn render(markdown: &str) -> String {
+    markdown::to_html(markdown).trim().to_string()
+}
+
+/// Quote a field when it contains the separator, a quote or a line break (Anki's CSV rules).
+fn field(text: &str) -> String {
+    if text.contains(['\t', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// FNV-1a: a fixed hash, so GUIDs stay stable across builds and Rust versions.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,156 @@
+use std::fs;
+use std::path::Path;
+
+use diaryx_core::AttachmentPlanEntry;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use time::OffsetDateTime;
+
+use super::{ExportEntry, created};
+use crate::archive::base64;
+use crate::diagnostics::normalize;
+
+/// Images up to this size are embedded as `data:` URIs; larger ones are linked.
+const EMBED_MAX_BYTES: u64 = 64 * 1024;
+
+/// Inline styles per element: many mail clients drop `<style>` blocks.
+const STYLES: &[(&str, &str)] = &[
+    ("h1", "font-size:22px;margin:0 0 12px"),
+    ("h2", "font-size:19px;margin:24px 0 8px"),
+    ("h3", "font-size:16px;margin:20px 0 6px"),
+    ("p", "margin:0 0 12px"),
+    ("a", "color:#2563eb"),
+    (
+        "blockquote",
+        "margin:0 0 12px;padding-left:12px;border-left:3px solid #d1d5db;color:#4b5563",
+    ),
+    (
+        "pre",
+        "background:#f3f4f6;padding:10px;border-radius:4px;overflow:auto;font-size:13px",
+    ),
+    ("code", "font-family:Menlo,Consolas,monospace"),
+    ("img", "max-width:100%;height:auto"),
+    ("hr", "border:0;border-top:1px solid #e5e7eb;margin:24px 0"),
+];
+
+/// Email digest of `entries` (oldest first): one HTML document with inlined CSS, every link made
+/// absolute against `base_url`, and small images embedded so the mail reads offline.
+pub(super) fn export(
+    entries: &[ExportEntry],
+    attachments: &[AttachmentPlanEntry],
+    base_url: &str,
+    since: Option<OffsetDateTime>,
+) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let mut entries: Vec<&ExportEntry> = entries.iter().collect();
+    entries.sort_by_key(|e| created(&e.page));
+
+    let heading = match since {
+        Some(since) => format!("What I wrote since {}", since.date()),
+        None => "What I wrote".to_string(),
+    };
+    let mut body = format!(
+        "<h1>{}</h1>\n<p style=\"color:#6b7280\">{} entr{}</p>\n",
+        escape(&heading),
+        entries.len(),
+        if entries.len() == 1 { "y" } else { "ies" }
+    );
+    for entry in &entries {
+        let page = &entry.page;
+        let date = created(page)
+            .map(|c| c.date().to_string())
+            .unwrap_or_default();
+        body.push_str(&format!(
+            "<hr>\n<h2><a href=\"{base_url}/{}\">{}</a></h2>\n<p style=\"color:#6b7280;font-size:13px\">{date}</p>\n",
+            entry.site_path,
+            escape(&page.title)
+        ));
+        body.push_str(&absolutize(
+            &page.html,
+            &entry.site_path,
+            base_url,
+            attachments,
+        ));
+        body.push('\n');
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n\
+         <body style=\"margin:0;padding:0;background:#ffffff\">\n\
+         <div style=\"max-width:640px;margin:0 auto;padding:24px;font-family:-apple-system,Segoe UI,Helvetica,Arial,sans-serif;font-size:15px;line-height:1.6;color:#111827\">\n\
+         {}</div>\n</body>\n</html>\n",
+        escape(&heading),
+        inline_styles(&body)
+    )
+}
+
+/// Rewrite relative `src` / `href` values of a page to absolute URLs, embedding small images.
+fn absolutize(
+    html: &str,
+    site_path: &str,
+    base_url: &str,
+    attachments: &[AttachmentPlanEntry],
+) -> String {
+    static ATTR: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?i)\b(src|href)="([^"]*)""#).unwrap());
+    let page_dir = Path::new(site_path).parent().unwrap_or(Path::new(""));
+    ATTR.replace_all(html, |caps: &Captures| {
+        let (attr, value) = (&caps[1], &caps[2]);
+        if value.is_empty() || value.starts_with('#') || value.contains(':') {
+            return caps[0].to_string();
+        }
+        let target = if let Some(path) = value.strip_prefix('/') {
+            path.to_string()
+        } else {
+            normalize(&page_dir.join(value))
+                .to_string_lossy()
+                .into_owned()
+        };
+        if attr.eq_ignore_ascii_case("src")
+            && let Some(data) = embed(&target, attachments)
+        {
+            return format!("{attr}=\"{data}\"");
+        }
+        format!("{attr}=\"{base_url}/{target}\"")
+    })
+    .into_owned()
+}
+
+/// `data:` URI for a small image attachment at site path `target`.
+fn embed(target: &str, attachments: &[AttachmentPlanEntry]) -> Option<String> {
+    let plain = target.replace("%20", " ");
+    let attachment = attachments.iter().find(|a| a.target == plain)?;
+    if !attachment.mime.starts_with("image/") || attachment.size? > EMBED_MAX_BYTES {
+        return None;
+    }
+    let bytes = fs::read(&attachment.source).ok()?;
+    Some(format!(
+        "data:{};base64,{}",
+        attachment.mime,
+        base64(&bytes)
+    ))
+}
+
+/// Add the `STYLES` attribute to each matching opening tag.
+fn inline_styles(html: &str) -> String {
+    static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<([a-z0-9]+)(\s[^>]*)?>").unwrap());
+    TAG.replace_all(html, |caps: &Captures| {
+        let name = &caps[1];
+        let attrs = caps.get(2).map_or("", |m| m.as_str());
+        match STYLES.iter().find(|(tag, _)| *tag == name) {
+            Some((_, style)) if !attrs.contains("style=") => {
+                let attrs = attrs.trim_end_matches('/').trim_end();
+                let close = if caps[0].ends_with("/>") { " />" } else { ">" };
+                format!("<{name}{attrs} style=\"{style}\"{close}")
+            }
+            _ => caps[0].to_string(),
+        }
+    })
+    .into_owned()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,203 @@
+use serde_yaml::Value;
+use time::format_description::well_known::Rfc3339;
+use time::{Date, Duration, OffsetDateTime, UtcOffset};
+
+use super::ExportEntry;
+
+/// When an event happens.
+enum When {
+    /// All-day, `end` inclusive.
+    Days { start: Date, end: Date },
+    Timed {
+        start: OffsetDateTime,
+        end: Option<OffsetDateTime>,
+    },
+}
+
+struct Event {
+    uid: String,
+    summary: String,
+    when: When,
+    location: Option<String>,
+    description: Option<String>,
+    url: Option<String>,
+}
+
+/// iCalendar document for `entries`, plus the number of events.
+///
+/// Every entry with a parseable `created` value gets an all-day event on that date, titled with
+/// the entry's title and linking to its page when `base_url` is known. An `event:` field adds
+/// events of its own: a date (`2025-03-01`), a timestamp, a mapping with `start` (or `date`) and
+/// optional `end`, `title`, `location` and `description`, or a list of these. UIDs derive from the
+/// page slug so calendar apps update events on re-import.
+pub(super) fn export(entries: &[ExportEntry], base_url: Option<&str>) -> (String, usize) {
+    let mut events = Vec::new();
+    for entry in entries {
+        let page = &entry.page;
+        let url =
+            base_url.map(|base| format!("{}/{}", base.trim_end_matches('/'), entry.site_path));
+        let field = |key: &str| page.frontmatter.get(key);
+        if let Some(date) = field("created").and_then(parse_when).map(|w| match w {
+            When::Days { start, .. } => start,
+            When::Timed { start, .. } => start.date(),
+        }) {
+            events.push(Event {
+                uid: format!("{}@diaryx", page.id),
+                summary: page.title.clone(),
+                when: When::Days {
+                    start: date,
+                    end: date,
+                },
+                location: None,
+                description: None,
+                url: url.clone(),
+            });
+        }
+        let declared = match field("event") {
+            Some(Value::Sequence(items)) => items.iter().collect(),
+            Some(value) => vec![value],
+            None => Vec::new(),
+        };
+        for (n, value) in declared.into_iter().enumerate() {
+            let text = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+            let when = match value {
+                Value::Mapping(_) => {
+                    let start = value.get("start").or_else(|| value.get("date"));
+                    match (
+                        start.and_then(parse_when),
+                        value.get("end").and_then(parse_when),
+                    ) {
+                        (Some(When::Days { start, .. }), Some(When::Days { end, .. })) => {
+                            When::Days { start, end }
+                        }
+                        (Some(When::Timed { start, .. }), Some(When::Timed { start: end, .. })) => {
+                            When::Timed {
+                                start,
+                                end: Some(end),
+                            }
+                        }
+                        (Some(when), _) => when,
+                        (None, _) => continue,
+                    }
+                }
+                other => match parse_when(other) {
+                    Some(when) => when,
+                    None => continue,
+                },
+            };
+            events.push(Event {
+                uid: format!("{}-event-{}@diaryx", page.id, n + 1),
+                summary: text("title").unwrap_or_else(|| page.title.clone()),
+                when,
+                location: text("location"),
+                description: text("description"),
+                url: url.clone(),
+            });
+        }
+    }
+
+    let stamp = ics_datetime(OffsetDateTime::now_utc());
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Diaryx//diaryx-cli//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    if let Some(root) = entries.iter().find(|e| e.page.is_root_index) {
+        lines.push(format!("X-WR-CALNAME:{}", escape(&root.page.title)));
+    }
+    for event in &events {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", escape(&event.uid)));
+        lines.push(format!("DTSTAMP:{stamp}"));
+        match &event.when {
+            When::Days { start, end } => {
+                lines.push(format!("DTSTART;VALUE=DATE:{}", ics_date(*start)));
+                // DTEND is exclusive.
+                let end = end.max(start).saturating_add(Duration::days(1));
+                lines.push(format!("DTEND;VALUE=DATE:{}", ics_date(end)));
+            }
+            When::Timed { start, end } => {
+                lines.push(format!("DTSTART:{}", ics_datetime(*start)));
+                if let Some(end) = end {
+                    lines.push(format!("DTEND:{}", ics_datetime(*end)));
+                }
+            }
+        }
+        lines.push(format!("SUMMARY:{}", escape(&event.summary)));
+        if let Some(location) = &event.location {
+            lines.push(format!("LOCATION:{}", escape(location)));
+        }
+        if let Some(description) = &event.description {
+            lines.push(format!("DESCRIPTION:{}", escape(description)));
+        }
+        if let Some(url) = &event.url {
+            lines.push(format!("URL:{url}"));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = String::new();
+    for line in lines {
+        fold(&line, &mut out);
+    }
+    (out, events.len())
+}
+
+/// A date (`2025-03-01`) or an RFC 3339 timestamp.
+fn parse_when(value: &Value) -> Option<When> {
+    let text = value.as_str()?.trim();
+    if let Ok(start) = OffsetDateTime::parse(text, &Rfc3339) {
+        return Some(When::Timed { start, end: None });
+    }
+    let format = time::format_description::parse("[year]-[month]-[day]").ok()?;
+    let date = Date::parse(text, &format).ok()?;
+    Some(When::Days {
+        start: date,
+        end: date,
+    })
+}
+
+fn ics_date(date: Date) -> String {
+    format!(
+        "{:04}{:02}{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    )
+}
+
+fn ics_datetime(at: OffsetDateTime) -> String {
+    let utc = at.to_offset(UtcOffset::UTC);
+    format!(
+        "{}T{:02}{:02}{:02}Z",
+        ics_date(utc.date()),
+        utc.hour(),
+        utc.minute(),
+        utc.second()
+    )
+}
+
+/// Escape a TEXT value (RFC 5545 §3.3.11).
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Append a content line, folded at 75 octets (without splitting characters), CRLF-terminated.
+fn fold(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
@@ -0,0 +1,208 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use diaryx_core::{
+    AttachmentPlanEntry, CoreBuildOptions, PageOutput, SiteShape, build_site_streaming, site,
+};
+use time::format_description::well_known::Rfc3339;
+use time::{Date, Duration, OffsetDateTime};
+
+use crate::build::RealFs;
+use crate::query::Filter;
+
+mod anki;
+mod email;
+mod ics;
+mod reader;
+
+/// Export module
+///
+/// `export` turns selected entries into files for other tools. Every format works from the same
+/// selection: a core build of the vault (public pages only unless `include_nonpublic`), narrowed
+/// by an optional `--where` filter, with each page paired with its raw source so formats can use
+/// either the rendered HTML or the Markdown.
+pub struct ExportOptions {
+    pub input: PathBuf,
+    pub format: ExportFormat,
+    pub filter: Option<Filter>,
+    pub include_nonpublic: bool,
+    /// Destination file; stdout when `None`.
+    pub output: Option<PathBuf>,
+    /// Anki deck the cards are imported into.
+    pub deck: Option<String>,
+    /// Public URL of the built site, for links back to pages.
+    pub base_url: Option<String>,
+    /// Links follow the flat output layout (`build --flat`).
+    pub flat: bool,
+    /// Only entries created within this period: `7d`, `2w`, `24h`, or a `YYYY-MM-DD` start date.
+    /// `email-digest` defaults to `7d`.
+    pub since: Option<String>,
+    /// Only entries in this language (`lang`, inherited from the root index).
+    pub lang: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// Flashcards as an Anki import file (tab-separated, with header directives).
+    Anki,
+    /// iCalendar file: an all-day event per entry on its `created` date, plus `event:` fields.
+    Ics,
+    /// One self-contained HTML email summarising the period's entries (see `since`).
+    EmailDigest,
+    /// Portable archive: the whole site plus an offline reader (search, tags, timeline), as a
+    /// folder or, when the output ends in `.zip`, a zip file. Needs `-o`.
+    Reader,
+}
+
+/// One selected entry.
+struct ExportEntry {
+    page: PageOutput,
+    /// Source file contents.
+    source: String,
+    /// Site-relative path of the built page.
+    site_path: String,
+}
+
+pub fn run_export(opts: &ExportOptions) -> Result<()> {
+    let since = match (&opts.since, opts.format) {
+        (Some(spec), _) => Some(parse_since(spec, OffsetDateTime::now_utc())?),
+        (None, ExportFormat::EmailDigest) => Some(parse_since("7d", OffsetDateTime::now_utc())?),
+        (None, _) => None,
+    };
+    if let ExportFormat::Reader = opts.format {
+        let output = opts
+            .output
+            .as_deref()
+            .ok_or_else(|| anyhow!("The reader format needs -o <DIR or FILE.zip>"))?;
+        return reader::export(opts, since, output);
+    }
+    let (entries, attachments) = select(opts, since)?;
+    let (out, summary) = match opts.format {
+        ExportFormat::Anki => {
+            let (tsv, cards) = anki::export(&entries, opts.deck.as_deref());
+            (tsv, format!("{cards} card(s)"))
+        }
+        ExportFormat::Ics => {
+            let (ics, events) = ics::export(&entries, opts.base_url.as_deref());
+            (ics, format!("{events} event(s)"))
+        }
+        ExportFormat::EmailDigest => {
+            let base_url = opts.base_url.as_deref().ok_or_else(|| {
+                anyhow!("email-digest needs --base-url so its links work outside the site")
+            })?;
+            let html = email::export(&entries, &attachments, base_url, since);
+            (html, "digest".to_string())
+        }
+        ExportFormat::Reader => unreachable!("handled above"),
+    };
+    match &opts.output {
+        Some(path) => {
+            fs::write(path, out).with_context(|| format!("Failed writing {}", path.display()))?;
+            eprintln!(
+                "[export] {summary} from {} entr{} -> {}",
+                entries.len(),
+                if entries.len() == 1 { "y" } else { "ies" },
+                path.display()
+            );
+        }
+        None => print!("{out}"),
+    }
+    Ok(())
+}
+
+/// Entries to export (in build order), and the attachment plan of the build.
+fn select(
+    opts: &ExportOptions,
+    since: Option<OffsetDateTime>,
+) -> Result<(Vec<ExportEntry>, Vec<AttachmentPlanEntry>)> {
+    let entry = opts
+        .input
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?;
+    let root = opts.input.parent().unwrap_or(Path::new("."));
+    let core_opts = CoreBuildOptions {
+        include_nonpublic: opts.include_nonpublic,
+        flat: opts.flat,
+        rewrite_links: true,
+        ..CoreBuildOptions::default()
+    };
+    let mut entries = Vec::new();
+    let summary = build_site_streaming(
+        entry,
+        core_opts,
+        &RealFs,
+        |_: &SiteShape, page: PageOutput| {
+            let path = Path::new(&page.source_path);
+            let source = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            if wanted(opts, since, root, &page, &source) {
+                let site_path = page.path.clone();
+                entries.push(ExportEntry {
+                    page,
+                    source,
+                    site_path,
+                });
+            }
+            Ok(())
+        },
+    )
+    .with_context(|| "Core build failed")?;
+    Ok((entries, summary.attachments))
+}
+
+/// Whether a built page is selected by `--since`, `--lang` and `--where`. `noindex` pages are left out of
+/// everything but flashcards: calendars and digests are feeds, and the reader is a search index.
+fn wanted(
+    opts: &ExportOptions,
+    since: Option<OffsetDateTime>,
+    root: &Path,
+    page: &PageOutput,
+    source: &str,
+) -> bool {
+    (matches!(opts.format, ExportFormat::Anki) || !site::is_noindex(page))
+        && since.is_none_or(|since| created(page).is_some_and(|c| c >= since))
+        && opts
+            .lang
+            .as_deref()
+            .is_none_or(|lang| page.lang.as_deref() == Some(lang))
+        && opts
+            .filter
+            .as_ref()
+            .is_none_or(|f| f.matches_source(root, Path::new(&page.source_path), source))
+}
+
+/// When an entry was created: its `created` timestamp, or midnight UTC of a plain date.
+pub(crate) fn created(page: &PageOutput) -> Option<OffsetDateTime> {
+    parse_created(page.frontmatter.get("created")?.as_str()?)
+}
+
+/// A `created` value: an RFC 3339 timestamp, or midnight UTC of a plain date.
+pub(crate) fn parse_created(text: &str) -> Option<OffsetDateTime> {
+    let text = text.trim();
+    if let Ok(at) = OffsetDateTime::parse(text, &Rfc3339) {
+        return Some(at);
+    }
+    let format = time::format_description::parse("[year]-[month]-[day]").ok()?;
+    Some(Date::parse(text, &format).ok()?.midnight().assume_utc())
+}
+
+/// Start of a `--since` period: `<n>h`, `<n>d` or `<n>w` before `now`, or a `YYYY-MM-DD` date.
+fn parse_since(spec: &str, now: OffsetDateTime) -> Result<OffsetDateTime> {
+    let spec = spec.trim();
+    let invalid = || anyhow!("Invalid --since '{spec}': expected e.g. 7d, 2w, 24h or 2025-01-31");
+    if let Ok(format) = time::format_description::parse("[year]-[month]-[day]")
+        && let Ok(date) = Date::parse(spec, &format)
+    {
+        return Ok(date.midnight().assume_utc());
+    }
+    let split = spec.char_indices().last().ok_or_else(invalid)?.0;
+    let n: i64 = spec[..split].parse().map_err(|_| invalid())?;
+    let period = match &spec[split..] {
+        "h" => Duration::hours(n),
+        "d" => Duration::days(n),
+        "w" => Duration::weeks(n),
+        _ => return Err(invalid()),
+    };
+    Ok(now - period)
+}
@@ -0,0 +1,304 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use diaryx_core::{CoreBuildOptions, PageOutput, SiteShape, build_site_streaming, output, site};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::json;
+use time::OffsetDateTime;
+
+use super::{ExportOptions, created, wanted};
+use crate::archive;
+use crate::build::RealFs;
+use crate::tag::entry_tags;
+
+/// Reader entry page, at the bundle root next to the site's `index.html`.
+const READER_PATH: &str = "reader.html";
+/// Search index loaded by the reader; a script rather than JSON so it also loads from `file://`.
+const DATA_PATH: &str = "reader/data.js";
+
+/// Write the portable reader bundle to `output` (a folder, or a zip when it ends in `.zip`).
+///
+/// The bundle holds every built page with its attachments and stylesheet, plus `reader.html`: a
+/// single-file app that searches, filters by tag and lists entries on a timeline. Only entries
+/// selected by `--where` / `--since` are listed in the reader; the pages themselves are all
+/// included so links between them keep working. Nothing needs a server: open `reader.html`
+/// straight from disk.
+pub(super) fn export(
+    opts: &ExportOptions,
+    since: Option<OffsetDateTime>,
+    output: &Path,
+) -> Result<()> {
+    let zip = output
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+    let dir = if zip {
+        std::env::temp_dir().join(format!("diaryx-reader-{}", std::process::id()))
+    } else {
+        output.to_path_buf()
+    };
+    prepare(&dir, zip)?;
+    let written = write_bundle(opts, since, &dir);
+    let listed = match (written, zip) {
+        (Ok(listed), true) => {
+            let zipped = archive::zip_dir(&dir, output);
+            fs::remove_dir_all(&dir).ok();
+            zipped.map(|()| listed)
+        }
+        (written, true) => {
+            fs::remove_dir_all(&dir).ok();
+            written
+        }
+        (written, false) => written,
+    }?;
+    eprintln!(
+        "[export] reader with {listed} entr{} -> {} (open {READER_PATH})",
+        if listed == 1 { "y" } else { "ies" },
+        output.display()
+    );
+    Ok(())
+}
+
+/// Make `dir` an empty folder. An existing folder is only replaced when it holds a previous
+/// bundle, so a mistyped `-o` cannot wipe unrelated files.
+fn prepare(dir: &Path, scratch: bool) -> Result<()> {
+    if dir.exists() {
+        let empty = dir.read_dir().is_ok_and(|mut d| d.next().is_none());
+        if !scratch && !empty && !dir.join(READER_PATH).is_file() {
+            bail!(
+                "{} exists and is not a reader bundle; choose an empty or new folder",
+                dir.display()
+            );
+        }
+        fs::remove_dir_all(dir).with_context(|| format!("Failed removing {}", dir.display()))?;
+    }
+    fs::create_dir_all(dir).with_context(|| format!("Failed creating {}", dir.display()))
+}
+
+/// Write pages, attachments, stylesheet and reader into `dir`; returns the number of listed
+/// entries.
+fn write_bundle(opts: &ExportOptions, since: Option<OffsetDateTime>, dir: &Path) -> Result<usize> {
+    let entry = opts
+        .input
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?;
+    let root = opts.input.parent().unwrap_or(Path::new("."));
+    let core_opts = CoreBuildOptions {
+        include_nonpublic: opts.include_nonpublic,
+        flat: opts.flat,
+        rewrite_links: true,
+        ..CoreBuildOptions::default()
+    };
+    let mut listed = Vec::new();
+    let mut title = None;
+    let summary = build_site_streaming(
+        entry,
+        core_opts,
+        &RealFs,
+        |site: &SiteShape, page: PageOutput| {
+            let rel = page.path.clone();
+            let prefix = output::root_prefix(&rel);
+            let html = site::wrap_full_html(&page, site.multi_page, true).replacen(
+                "</body>",
+                &format!(
+                    "<p class=\"diaryx-reader-link\"><a href=\"{prefix}{READER_PATH}\">Search and timeline</a></p></body>"
+                ),
+                1,
+            );
+            write(&dir.join(&rel), html.as_bytes())?;
+
+            if page.is_root_index || title.is_none() {
+                title = Some(page.title.clone());
+            }
+            let source = fs::read_to_string(&page.source_path)
+                .with_context(|| format!("Failed to read {}", page.source_path))?;
+            if wanted(opts, since, root, &page, &source) {
+                listed.push(json!({
+                    "title": page.title,
+                    "path": rel,
+                    "date": created(&page).map(|c| c.date().to_string()),
+                    "tags": page.frontmatter.as_mapping().map(entry_tags).unwrap_or_default(),
+                    "text": plain_text(&page.html),
+                }));
+            }
+            Ok(())
+        },
+    )
+    .with_context(|| "Core build failed")?;
+
+    for attachment in &summary.attachments {
+        let target = dir.join(&attachment.target);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed creating {}", parent.display()))?;
+        }
+        fs::copy(&attachment.source, &target)
+            .with_context(|| format!("Failed copying {}", attachment.source))?;
+    }
+    write(&dir.join(site::CSS_PATH), site::DEFAULT_CSS.as_bytes())?;
+
+    let count = listed.len();
+    let data = json!({
+        "title": title.unwrap_or_else(|| "Journal".to_string()),
+        "generated": OffsetDateTime::now_utc().date().to_string(),
+        "entries": listed,
+    });
+    write(
+        &dir.join(DATA_PATH),
+        format!(
+            "window.DIARYX_READER = {};\n",
+            serde_json::to_string(&data)?
+        )
+        .as_bytes(),
+    )?;
+    write(&dir.join(READER_PATH), READER_HTML.as_bytes())?;
+    Ok(count)
+}
+
+/// Searchable text of a rendered page: tags stripped, common entities decoded, whitespace
+/// collapsed.
+fn plain_text(html: &str) -> String {
+    static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+    let text = TAG
+        .replace_all(html, " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn write(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed creating {}", parent.display()))?;
+    }
+    fs::write(path, bytes).with_context(|| format!("Failed writing {}", path.display()))
+}
+
+const READER_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Reader</title>
+<style>
+  body { margin: 0; font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; color: #111827; background: #f9fafb; line-height: 1.5; }
+  header { position: sticky; top: 0; background: #1f2937; color: #fff; padding: 12px 20px; }
+  header h1 { margin: 0 0 8px; font-size: 20px; }
+  header a { color: #cbd5e1; font-size: 14px; }
+  #search { width: 100%; box-sizing: border-box; padding: 8px 10px; font-size: 16px; border: 0; border-radius: 4px; }
+  main { max-width: 760px; margin: 0 auto; padding: 12px 20px 40px; }
+  #tags { display: flex; flex-wrap: wrap; gap: 6px; margin: 8px 0 4px; }
+  .tag { border: 1px solid #d1d5db; background: #fff; border-radius: 999px; padding: 2px 10px; font-size: 13px; cursor: pointer; }
+  .tag.on { background: #1f2937; color: #fff; border-color: #1f2937; }
+  #status { color: #6b7280; font-size: 14px; margin: 8px 0; }
+  h2 { font-size: 15px; color: #6b7280; border-bottom: 1px solid #e5e7eb; margin: 24px 0 8px; padding-bottom: 4px; }
+  .entry { margin: 0 0 14px; }
+  .entry a { font-weight: 600; color: #1d4ed8; text-decoration: none; }
+  .entry .meta { color: #6b7280; font-size: 13px; }
+  .entry p { margin: 2px 0 0; font-size: 14px; color: #374151; }
+  mark { background: #fde68a; }
+</style>
+</head>
+<body>
+<header>
+  <h1 id="title">Reader</h1>
+  <input id="search" type="search" placeholder="Search entries" autofocus>
+  <div><a href="index.html">Open the site</a></div>
+</header>
+<main>
+  <div id="tags"></div>
+  <div id="status"></div>
+  <div id="timeline"></div>
+</main>
+<script src="reader/data.js"></script>
+<script>
+(function () {
+  var data = window.DIARYX_READER || { title: "Reader", entries: [] };
+  var entries = data.entries.map(function (e) {
+    e.haystack = (e.title + " " + e.tags.join(" ") + " " + e.text).toLowerCase();
+    return e;
+  });
+  var active = {};
+  var search = document.getElementById("search");
+  document.getElementById("title").textContent = data.title;
+  document.title = data.title;
+
+  function el(tag, className, text) {
+    var node = document.createElement(tag);
+    if (className) node.className = className;
+    if (text !== undefined) node.textContent = text;
+    return node;
+  }
+
+  // Tag chips, most used first.
+  var counts = {};
+  entries.forEach(function (e) { e.tags.forEach(function (t) { counts[t] = (counts[t] || 0) + 1; }); });
+  Object.keys(counts).sort(function (a, b) { return counts[b] - counts[a] || a.localeCompare(b); })
+    .forEach(function (tag) {
+      var chip = el("button", "tag", "#" + tag + " (" + counts[tag] + ")");
+      chip.onclick = function () {
+        active[tag] = !active[tag];
+        chip.classList.toggle("on", active[tag]);
+        render();
+      };
+      document.getElementById("tags").appendChild(chip);
+    });
+
+  // Text around the first match (or the start), with the terms highlighted.
+  function snippet(text, terms) {
+    var lower = text.toLowerCase();
+    var at = terms.length ? Math.max(0, lower.indexOf(terms[0])) : 0;
+    var start = Math.max(0, at - 60);
+    var part = (start > 0 ? "…" : "") + text.slice(start, start + 200) + (start + 200 < text.length ? "…" : "");
+    var p = el("p");
+    if (!terms.length) { p.textContent = part; return p; }
+    var pattern = new RegExp("(" + terms.map(function (t) { return t.replace(/[.*+?^${}()|[\]\\]/g, "\\$&"); }).join("|") + ")", "gi");
+    part.split(pattern).forEach(function (piece, i) {
+      p.appendChild(i % 2 ? el("mark", null, piece) : document.createTextNode(piece));
+    });
+    return p;
+  }
+
+  function render() {
+    var terms = search.value.toLowerCase().split(/\s+/).filter(Boolean);
+    var tags = Object.keys(active).filter(function (t) { return active[t]; });
+    var shown = entries.filter(function (e) {
+      return terms.every(function (t) { return e.haystack.indexOf(t) >= 0; }) &&
+        tags.every(function (t) { return e.tags.indexOf(t) >= 0; });
+    });
+    // Timeline: newest first, grouped by month; undated entries last.
+    shown.sort(function (a, b) { return (b.date || "").localeCompare(a.date || "") || a.title.localeCompare(b.title); });
+    var timeline = document.getElementById("timeline");
+    timeline.textContent = "";
+    var group = null;
+    shown.forEach(function (e) {
+      var month = e.date ? e.date.slice(0, 7) : "Undated";
+      if (month !== group) {
+        group = month;
+        timeline.appendChild(el("h2", null, month));
+      }
+      var item = el("div", "entry");
+      var link = el("a", null, e.title);
+      link.href = e.path;
+      item.appendChild(link);
+      var meta = [e.date].concat(e.tags.map(function (t) { return "#" + t; })).filter(Boolean).join("  ");
+      if (meta) item.appendChild(el("div", "meta", meta));
+      item.appendChild(snippet(e.text, terms));
+      timeline.appendChild(item);
+    });
+    document.getElementById("status").textContent =
+      shown.length + " of " + entries.length + " entries" + (data.generated ? " · archived " + data.generated : "");
+  }
+
+  search.oninput = render;
+  render();
+})();
+</script>
+</body>
+</html>
+"##;
@@ -0,0 +1,239 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
+use diaryx_core::{CoreBuildOptions, FileProvider, PageOutput, SiteShape, build_site_streaming};
+use serde::Serialize;
+use serde_yaml::Value;
+
+use crate::build::RealFs;
+use crate::diagnostics::{normalize, resolve_link, scan_links};
+use crate::tag::entry_tags;
+
+/// `diaryx graph` output format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GraphFormat {
+    /// Graphviz (`dot -Tsvg graph.dot > graph.svg`).
+    Dot,
+    /// `{nodes, links}`, the shape D3's force layout takes.
+    Json,
+}
+
+/// The document graph of a vault: one node per entry of a build, and an edge per link between
+/// two of them.
+///
+/// Edge kinds: `contents` (index -> listed entry, as core resolved it), `part_of` (entry -> the
+/// index it names) and `body` (a Markdown link in the body, i.e. a backlink of its target). An
+/// entry listed in `contents` without a matching `part_of`, or the reverse, shows up as an edge
+/// without its counterpart.
+#[derive(Debug, Serialize)]
+pub(crate) struct Graph {
+    pub nodes: Vec<Node>,
+    pub links: Vec<Edge>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Node {
+    /// Page slug.
+    pub id: String,
+    /// Source path relative to the vault root.
+    pub path: String,
+    /// Site-relative path of the built page.
+    pub page: String,
+    pub title: String,
+    pub visibility: Vec<String>,
+    pub tags: Vec<String>,
+    pub is_index: bool,
+    pub is_root_index: bool,
+    /// Slugs of the entries linking here from their body.
+    pub backlinks: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub(crate) struct Edge {
+    pub source: String,
+    pub target: String,
+    pub kind: &'static str,
+}
+
+impl Graph {
+    /// The graph of `pages`; body links are read from each page's source through `fs` (a source
+    /// that cannot be read contributes no body links).
+    pub(crate) fn new(root: &Path, pages: &[PageOutput], fs: &impl FileProvider) -> Self {
+        let slugs: HashMap<PathBuf, &str> = pages
+            .iter()
+            .map(|p| (normalize(Path::new(&p.source_path)), p.id.as_str()))
+            .collect();
+        let mut edges = BTreeSet::new();
+        for page in pages {
+            for child in &page.children {
+                edges.insert(Edge {
+                    source: page.id.clone(),
+                    target: child.clone(),
+                    kind: "contents",
+                });
+            }
+            let path = Path::new(&page.source_path);
+            let mut link_to = |target: &str, key: Option<&str>, kind: &'static str| {
+                if let Some(slug) = resolve_link(path, target, key)
+                    .and_then(|t| slugs.get(&normalize(&t.path)))
+                    .filter(|slug| **slug != page.id)
+                {
+                    edges.insert(Edge {
+                        source: page.id.clone(),
+                        target: slug.to_string(),
+                        kind,
+                    });
+                }
+            };
+            // `part_of` may be a Markdown link or a bare path.
+            for raw in string_list(page.frontmatter.get("part_of")) {
+                let target = scan_links(&raw)
+                    .into_iter()
+                    .next()
+                    .map_or_else(|| raw.trim().to_string(), |link| link.target);
+                link_to(&target, Some("part_of"), "part_of");
+            }
+            let Ok(text) = fs.read_to_string(&page.source_path) else {
+                continue;
+            };
+            for link in scan_links(&text).into_iter().filter(|l| l.key.is_none()) {
+                link_to(&link.target, None, "body");
+            }
+        }
+
+        let mut backlinks: HashMap<&str, Vec<String>> = HashMap::new();
+        for edge in edges.iter().filter(|e| e.kind == "body") {
+            backlinks
+                .entry(edge.target.as_str())
+                .or_default()
+                .push(edge.source.clone());
+        }
+        let nodes = pages
+            .iter()
+            .map(|page| {
+                let fields = page.frontmatter.as_mapping().cloned().unwrap_or_default();
+                Node {
+                    id: page.id.clone(),
+                    path: relative(root, Path::new(&page.source_path)),
+                    page: page.path.clone(),
+                    title: page.title.clone(),
+                    visibility: string_list(fields.get("visibility")),
+                    tags: entry_tags(&fields),
+                    is_index: page.is_index,
+                    is_root_index: page.is_root_index,
+                    backlinks: backlinks.remove(page.id.as_str()).unwrap_or_default(),
+                }
+            })
+            .collect();
+        Graph {
+            nodes,
+            links: edges.into_iter().collect(),
+        }
+    }
+
+    /// Graphviz source: indexes are folders, non-public entries grey, `part_of` edges dashed
+    /// and body links dotted.
+    pub(crate) fn to_dot(&self) -> String {
+        let mut out = String::from("digraph diaryx {\n  node [shape=box, style=rounded];\n");
+        for node in &self.nodes {
+            let mut attrs = vec![
+                format!("label={}", dot_string(&node.title)),
+                format!("tooltip={}", dot_string(&node.path)),
+            ];
+            if node.is_index {
+                attrs.push("shape=folder".to_string());
+            }
+            if node.is_root_index {
+                attrs.push("penwidth=2".to_string());
+            }
+            if !node.visibility.iter().any(|v| v == "public") {
+                attrs.push("color=gray50, fontcolor=gray50".to_string());
+            }
+            out.push_str(&format!(
+                "  {} [{}];\n",
+                dot_string(&node.id),
+                attrs.join(", ")
+            ));
+        }
+        for edge in &self.links {
+            let style = match edge.kind {
+                "part_of" => " [style=dashed]",
+                "body" => " [style=dotted, color=gray40]",
+                _ => "",
+            };
+            out.push_str(&format!(
+                "  {} -> {}{style};\n",
+                dot_string(&edge.source),
+                dot_string(&edge.target)
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// `graph`: print (or write to `output`) the document graph of the vault whose root index is
+/// `input`.
+pub fn run_graph(
+    input: &Path,
+    format: GraphFormat,
+    include_nonpublic: bool,
+    output: Option<&Path>,
+) -> Result<()> {
+    let root = input.parent().unwrap_or(Path::new("."));
+    let entry = input
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?;
+    let opts = CoreBuildOptions {
+        include_nonpublic,
+        ..CoreBuildOptions::default()
+    };
+    let mut pages = Vec::new();
+    build_site_streaming(entry, opts, &RealFs, |_: &SiteShape, page: PageOutput| {
+        pages.push(page);
+        Ok(())
+    })
+    .with_context(|| "Core build failed")?;
+
+    let graph = Graph::new(root, &pages, &RealFs);
+    let text = match format {
+        GraphFormat::Dot => graph.to_dot(),
+        GraphFormat::Json => serde_json::to_string_pretty(&graph)? + "\n",
+    };
+    match output {
+        Some(path) => {
+            fs::write(path, text).with_context(|| format!("Failed writing {}", path.display()))?
+        }
+        None => print!("{text}"),
+    }
+    Ok(())
+}
+
+/// A string or a list of strings.
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Sequence(items)) => items
+            .iter()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A double-quoted DOT identifier.
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `path` relative to `root` with `/` separators (as is when outside it).
+fn relative(root: &Path, path: &Path) -> String {
+    let rel = path.strip_prefix(root).unwrap_or(path);
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -0,0 +1,249 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use diaryx_core::{CoreBuildOptions, PageOutput, SiteShape, build_site_streaming};
+use rusqlite::{Connection, Transaction, params};
+
+use crate::archive::sha256_hex;
+use crate::build::RealFs;
+use crate::diagnostics::{normalize, resolve_link, scan_links};
+use crate::query::{EntryView, field_values};
+use crate::tag::entry_tags;
+use crate::vault::{parse_fields, split_frontmatter};
+
+/// `index --sqlite`: a SQLite database of the vault for ad-hoc SQL.
+///
+/// Tables (paths are relative to the vault root, with `/`):
+/// - `pages(path, slug, title, created, updated, is_index, is_root_index, frontmatter, body,
+///   hash)`: one row per entry, `frontmatter` as JSON;
+/// - `fields(path, key, value)`: one row per frontmatter value (list items separately);
+/// - `tags(path, tag)`;
+/// - `links(source, target, kind, is_doc)`: links between files, `kind` being the frontmatter
+///   key holding the link (`contents`, `part_of`, ...) or `body`;
+/// - `pages_fts(path, title, body)`: an FTS5 full-text index (`... WHERE pages_fts MATCH 'rain'`);
+/// - `meta(key, value)`: format version and vault root.
+///
+/// Re-running against an existing database is incremental: entries whose source is unchanged
+/// (same SHA-256) are skipped, changed ones are replaced and removed ones deleted.
+const FORMAT: &str = "diaryx-index/1";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+CREATE TABLE IF NOT EXISTS pages (
+    path TEXT PRIMARY KEY,
+    slug TEXT NOT NULL,
+    title TEXT NOT NULL,
+    created TEXT,
+    updated TEXT,
+    is_index INTEGER NOT NULL,
+    is_root_index INTEGER NOT NULL,
+    frontmatter TEXT NOT NULL,
+    body TEXT NOT NULL,
+    hash TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS fields (path TEXT NOT NULL, key TEXT NOT NULL, value TEXT NOT NULL);
+CREATE INDEX IF NOT EXISTS fields_key ON fields (key, value);
+CREATE INDEX IF NOT EXISTS fields_path ON fields (path);
+CREATE TABLE IF NOT EXISTS tags (path TEXT NOT NULL, tag TEXT NOT NULL);
+CREATE INDEX IF NOT EXISTS tags_tag ON tags (tag);
+CREATE INDEX IF NOT EXISTS tags_path ON tags (path);
+CREATE TABLE IF NOT EXISTS links (
+    source TEXT NOT NULL,
+    target TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    is_doc INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS links_source ON links (source);
+CREATE INDEX IF NOT EXISTS links_target ON links (target);
+CREATE VIRTUAL TABLE IF NOT EXISTS pages_fts USING fts5 (path UNINDEXED, title, body);
+";
+
+/// Counts reported after an update.
+#[derive(Default)]
+struct IndexStats {
+    added: usize,
+    updated: usize,
+    unchanged: usize,
+    removed: usize,
+}
+
+/// Create or update the database at `db_path` from the vault rooted at `input`.
+pub fn run_index(input: &Path, db_path: &Path, include_nonpublic: bool) -> Result<()> {
+    let root = input.parent().unwrap_or(Path::new("."));
+    let mut pages = Vec::new();
+    let entry = input
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?;
+    let opts = CoreBuildOptions {
+        include_nonpublic,
+        ..CoreBuildOptions::default()
+    };
+    build_site_streaming(entry, opts, &RealFs, |_: &SiteShape, page: PageOutput| {
+        pages.push(page);
+        Ok(())
+    })
+    .with_context(|| "Core build failed")?;
+
+    let mut conn = Connection::open(db_path)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+    conn.execute_batch(SCHEMA)
+        .with_context(|| format!("{} is not a diaryx index", db_path.display()))?;
+    let tx = conn.transaction()?;
+    let format: Option<String> = tx
+        .query_row("SELECT value FROM meta WHERE key = 'format'", [], |row| {
+            row.get(0)
+        })
+        .ok();
+    if let Some(format) = format
+        && format != FORMAT
+    {
+        bail!(
+            "{} holds a '{format}' index; delete it to rebuild as {FORMAT}",
+            db_path.display()
+        );
+    }
+    tx.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES ('format', ?1), ('root', ?2)",
+        params![FORMAT, root.to_string_lossy()],
+    )?;
+
+    let mut known: HashMap<String, String> = HashMap::new();
+    {
+        let mut stmt = tx.prepare("SELECT path, hash FROM pages")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for row in rows {
+            let (path, hash) = row?;
+            known.insert(path, hash);
+        }
+    }
+
+    let mut stats = IndexStats::default();
+    let mut seen = HashSet::new();
+    for page in &pages {
+        let path = Path::new(&page.source_path);
+        let rel = relative(root, path);
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let hash = sha256_hex(text.as_bytes());
+        seen.insert(rel.clone());
+        match known.get(&rel) {
+            Some(old) if *old == hash => {
+                stats.unchanged += 1;
+                continue;
+            }
+            Some(_) => {
+                delete_page(&tx, &rel)?;
+                stats.updated += 1;
+            }
+            None => stats.added += 1,
+        }
+        insert_page(&tx, root, &rel, page, &text, &hash)?;
+    }
+    for rel in known.keys().filter(|rel| !seen.contains(*rel)) {
+        delete_page(&tx, rel)?;
+        stats.removed += 1;
+    }
+    tx.commit()?;
+
+    println!(
+        "[index] {} -> {} ({} added, {} updated, {} unchanged, {} removed)",
+        input.display(),
+        db_path.display(),
+        stats.added,
+        stats.updated,
+        stats.unchanged,
+        stats.removed
+    );
+    Ok(())
+}
+
+fn insert_page(
+    tx: &Transaction,
+    root: &Path,
+    rel: &str,
+    page: &PageOutput,
+    text: &str,
+    hash: &str,
+) -> Result<()> {
+    let (fm, body) = split_frontmatter(text);
+    let fields = parse_fields(fm);
+    let scalar = |key: &str| page.frontmatter.get(key).and_then(|v| v.as_str());
+    tx.execute(
+        "INSERT INTO pages (path, slug, title, created, updated, is_index, is_root_index, \
+         frontmatter, body, hash) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            rel,
+            page.id,
+            page.title,
+            scalar("created"),
+            scalar("updated"),
+            page.is_index,
+            page.is_root_index,
+            serde_json::to_string(&page.frontmatter)?,
+            body,
+            hash
+        ],
+    )?;
+    tx.execute(
+        "INSERT INTO pages_fts (path, title, body) VALUES (?1, ?2, ?3)",
+        params![rel, page.title, body],
+    )?;
+
+    let view = EntryView {
+        path: rel,
+        fields: &fields,
+        body,
+    };
+    let mut insert_field =
+        tx.prepare("INSERT INTO fields (path, key, value) VALUES (?1, ?2, ?3)")?;
+    for key in fields.keys().filter_map(|k| k.as_str()) {
+        for value in field_values(&view, key) {
+            insert_field.execute(params![rel, key, value])?;
+        }
+    }
+    let mut insert_tag = tx.prepare("INSERT INTO tags (path, tag) VALUES (?1, ?2)")?;
+    for tag in entry_tags(&fields) {
+        insert_tag.execute(params![rel, tag])?;
+    }
+
+    let path = Path::new(&page.source_path);
+    let mut insert_link =
+        tx.prepare("INSERT INTO links (source, target, kind, is_doc) VALUES (?1, ?2, ?3, ?4)")?;
+    for link in scan_links(text) {
+        let Some(target) = resolve_link(path, &link.target, link.key.as_deref()) else {
+            continue;
+        };
+        let kind = link.key.as_deref().unwrap_or("body");
+        insert_link.execute(params![
+            rel,
+            relative(root, &normalize(&target.path)),
+            kind,
+            target.is_doc
+        ])?;
+    }
+    Ok(())
+}
+
+fn delete_page(tx: &Transaction, rel: &str) -> Result<()> {
+    for sql in [
+        "DELETE FROM pages WHERE path = ?1",
+        "DELETE FROM pages_fts WHERE path = ?1",
+        "DELETE FROM fields WHERE path = ?1",
+        "DELETE FROM tags WHERE path = ?1",
+        "DELETE FROM links WHERE source = ?1",
+    ] {
+        tx.execute(sql, params![rel])?;
+    }
+    Ok(())
+}
+
+/// `path` relative to `root` with `/` separators (as is when outside it).
+fn relative(root: &Path, path: &Path) -> String {
+    let rel = path.strip_prefix(root).unwrap_or(path);
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
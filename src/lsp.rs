@@ -0,0 +1,506 @@
+/*!
+ * `diaryx lsp`: a Language Server Protocol server over stdio.
+ *
+ * Provides, for every `.md` file under the workspace root:
+ * - diagnostics (frontmatter problems, broken links, missing attachments) via [`crate::diagnostics`],
+ * - completion of `contents` / `part_of` targets, link targets and known tags,
+ * - go-to-definition on internal links,
+ * - rename of a document with every link to it across the vault updated.
+ *
+ * The vault index (titles and tags) is built by scanning the root once at startup and refreshed
+ * from open buffers as they change. Requests are answered synchronously on one thread; the work
+ * per request is a scan of at most the vault's Markdown files.
+ */
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{Completion, GotoDefinition, Rename, Request as _};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DidSaveTextDocumentParams, DocumentChangeOperation, DocumentChanges,
+    GotoDefinitionParams, GotoDefinitionResponse, InitializeParams, Location, NumberOrString,
+    OneOf, OptionalVersionedTextDocumentIdentifier, Position, PublishDiagnosticsParams, Range,
+    RenameFile, RenameParams, ResourceOp, SaveOptions, ServerCapabilities, TextDocumentEdit,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
+    TextDocumentSyncSaveOptions, TextEdit, Url, WorkspaceEdit,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::diagnostics::{self, LinkRef, Severity};
+
+/// Run the server until the client sends `shutdown` / `exit`.
+pub fn run_lsp() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let init = connection.initialize(serde_json::to_value(capabilities())?)?;
+    let params: InitializeParams =
+        serde_json::from_value(init).context("Invalid initialize params")?;
+    let mut server = Server::new(workspace_root(&params));
+    server.main_loop(&connection)?;
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::FULL),
+                save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
+                    include_text: Some(false),
+                })),
+                ..Default::default()
+            },
+        )),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["(".into(), "[".into(), " ".into()]),
+            ..Default::default()
+        }),
+        definition_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    }
+}
+
+#[allow(deprecated)] // root_uri is still what most clients send
+fn workspace_root(params: &InitializeParams) -> Option<PathBuf> {
+    params
+        .workspace_folders
+        .as_ref()
+        .and_then(|folders| folders.first())
+        .map(|f| &f.uri)
+        .or(params.root_uri.as_ref())
+        .and_then(|uri| uri.to_file_path().ok())
+}
+
+/// What the server knows about a vault document.
+struct IndexedDoc {
+    title: String,
+    tags: Vec<String>,
+}
+
+struct Server {
+    /// Unsaved editor contents, keyed by path.
+    open: HashMap<PathBuf, String>,
+    docs: BTreeMap<PathBuf, IndexedDoc>,
+}
+
+impl Server {
+    fn new(root: Option<PathBuf>) -> Self {
+        let mut server = Self {
+            open: HashMap::new(),
+            docs: BTreeMap::new(),
+        };
+        if let Some(root) = root {
+            let mut files = Vec::new();
+            collect_markdown(&root, &mut files);
+            for path in files {
+                if let Ok(text) = fs::read_to_string(&path) {
+                    server.index(&path, &text);
+                }
+            }
+        }
+        server
+    }
+
+    fn main_loop(&mut self, connection: &Connection) -> Result<()> {
+        for msg in &connection.receiver {
+            match msg {
+                Message::Request(req) => {
+                    if connection.handle_shutdown(&req)? {
+                        return Ok(());
+                    }
+                    let resp = self.handle_request(req);
+                    connection.sender.send(Message::Response(resp))?;
+                }
+                Message::Notification(not) => self.handle_notification(connection, not)?,
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, req: Request) -> Response {
+        match req.method.as_str() {
+            Completion::METHOD => respond(req, |p| self.completion(p)),
+            GotoDefinition::METHOD => respond(req, |p| self.definition(p)),
+            Rename::METHOD => respond(req, |p| self.rename(p)),
+            _ => Response::new_err(
+                req.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unsupported request: {}", req.method),
+            ),
+        }
+    }
+
+    fn handle_notification(&mut self, connection: &Connection, not: Notification) -> Result<()> {
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let p: DidOpenTextDocumentParams = serde_json::from_value(not.params)?;
+                self.update(connection, &p.text_document.uri, p.text_document.text)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let p: DidChangeTextDocumentParams = serde_json::from_value(not.params)?;
+                // Full sync: the last change carries the whole document.
+                if let Some(change) = p.content_changes.into_iter().last() {
+                    self.update(connection, &p.text_document.uri, change.text)?;
+                }
+            }
+            DidSaveTextDocument::METHOD => {
+                let _: DidSaveTextDocumentParams = serde_json::from_value(not.params)?;
+                // A save can create or fix link targets of other open documents.
+                let open: Vec<PathBuf> = self.open.keys().cloned().collect();
+                for path in open {
+                    self.publish(connection, &path)?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let p: DidCloseTextDocumentParams = serde_json::from_value(not.params)?;
+                if let Ok(path) = p.text_document.uri.to_file_path() {
+                    self.open.remove(&path);
+                    if let Ok(text) = fs::read_to_string(&path) {
+                        self.index(&path, &text);
+                    }
+                }
+                send_diagnostics(connection, p.text_document.uri, Vec::new())?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn update(&mut self, connection: &Connection, uri: &Url, text: String) -> Result<()> {
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(());
+        };
+        if !is_markdown(&path) {
+            return Ok(());
+        }
+        self.index(&path, &text);
+        self.open.insert(path.clone(), text);
+        self.publish(connection, &path)
+    }
+
+    fn index(&mut self, path: &Path, text: &str) {
+        let doc = diaryx_core::parse_document(&path.to_string_lossy(), text.to_string());
+        let tags = match doc.frontmatter.get("tags") {
+            Some(serde_yaml::Value::Sequence(items)) => items
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect(),
+            Some(serde_yaml::Value::String(tag)) => vec![tag.clone()],
+            _ => Vec::new(),
+        };
+        let title = doc.title.unwrap_or_else(|| file_stem(path));
+        self.docs
+            .insert(path.to_path_buf(), IndexedDoc { title, tags });
+    }
+
+    fn publish(&self, connection: &Connection, path: &Path) -> Result<()> {
+        let Some(text) = self.open.get(path) else {
+            return Ok(());
+        };
+        let diagnostics = diagnostics::lint_document(path, text)
+            .into_iter()
+            .map(|d| Diagnostic {
+                range: Range::new(Position::new(d.line, d.start), Position::new(d.line, d.end)),
+                severity: Some(match d.severity {
+                    Severity::Error => DiagnosticSeverity::ERROR,
+                    Severity::Warning => DiagnosticSeverity::WARNING,
+                }),
+                code: Some(NumberOrString::String(d.code.to_string())),
+                source: Some("diaryx".into()),
+                message: d.message,
+                ..Default::default()
+            })
+            .collect();
+        send_diagnostics(connection, file_uri(path)?, diagnostics)
+    }
+
+    /// Editor buffer when open, otherwise the file on disk.
+    fn text(&self, path: &Path) -> Result<String> {
+        match self.open.get(path) {
+            Some(text) => Ok(text.clone()),
+            None => fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let pos = params.text_document_position;
+        let path = uri_path(&pos.text_document.uri)?;
+        let text = self.text(&path)?;
+        let lines: Vec<&str> = text.lines().collect();
+        let line_idx = pos.position.line as usize;
+        let line = lines.get(line_idx).copied().unwrap_or("");
+        let before = &line[..diagnostics::byte_at_utf16(line, pos.position.character)];
+        let dir = path.parent().unwrap_or(Path::new(""));
+
+        let in_link_target = before
+            .rfind("](")
+            .is_some_and(|i| !before[i..].contains(')'));
+        let key =
+            diagnostics::frontmatter_key_at(&lines, diagnostics::frontmatter_end(&lines), line_idx);
+        let items: Vec<CompletionItem> = match key.as_deref() {
+            _ if in_link_target => self
+                .other_docs(&path)
+                .map(|(target, doc)| {
+                    let rel = diagnostics::link_target(dir, target, None);
+                    CompletionItem {
+                        label: rel.clone(),
+                        kind: Some(CompletionItemKind::FILE),
+                        detail: Some(doc.title.clone()),
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            Some("contents" | "part_of") => self
+                .other_docs(&path)
+                .map(|(target, doc)| {
+                    let rel = diagnostics::link_target(dir, target, key.as_deref());
+                    CompletionItem {
+                        label: doc.title.clone(),
+                        kind: Some(CompletionItemKind::FILE),
+                        detail: Some(rel.clone()),
+                        filter_text: Some(format!("{} {rel}", doc.title)),
+                        insert_text: Some(format!("\"[{}]({rel})\"", doc.title)),
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            Some("tags") => self
+                .docs
+                .values()
+                .flat_map(|doc| doc.tags.iter())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|tag| CompletionItem {
+                    label: tag.clone(),
+                    kind: Some(CompletionItemKind::KEYWORD),
+                    ..Default::default()
+                })
+                .collect(),
+            _ => return Ok(None),
+        };
+        Ok(Some(CompletionResponse::Array(items)))
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Result<Option<GotoDefinitionResponse>> {
+        let pos = params.text_document_position_params;
+        let path = uri_path(&pos.text_document.uri)?;
+        let text = self.text(&path)?;
+        let Some(link) = link_at(&text, pos.position) else {
+            return Ok(None);
+        };
+        let Some(target) = diagnostics::resolve_link(&path, &link.target, link.key.as_deref())
+        else {
+            return Ok(None);
+        };
+        if !target.path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(GotoDefinitionResponse::Scalar(Location::new(
+            file_uri(&target.path)?,
+            Range::default(),
+        ))))
+    }
+
+    /// Rename the document linked under the cursor (or the current document when the cursor is
+    /// not on a link) and rewrite every link to it across the vault.
+    fn rename(&mut self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let pos = params.text_document_position;
+        let path = uri_path(&pos.text_document.uri)?;
+        let text = self.text(&path)?;
+        let old = match link_at(&text, pos.position)
+            .and_then(|link| diagnostics::resolve_link(&path, &link.target, link.key.as_deref()))
+        {
+            Some(target) if target.is_doc => target.path,
+            Some(_) => bail!("Only links to Markdown documents can be renamed"),
+            None => path.clone(),
+        };
+        if !old.is_file() {
+            bail!("Link target does not exist: {}", old.display());
+        }
+
+        let new_name = params.new_name.trim();
+        if new_name.is_empty() || new_name.contains(['/', '\\']) {
+            bail!("New name must be a file name, not a path: {new_name}");
+        }
+        let new_name = if new_name.to_ascii_lowercase().ends_with(".md") {
+            new_name.to_string()
+        } else {
+            format!("{new_name}.md")
+        };
+        let new = old.with_file_name(&new_name);
+        if new.exists() {
+            bail!("A file named {new_name} already exists");
+        }
+
+        let mut ops = Vec::new();
+        for doc_path in self.docs.keys() {
+            let Ok(doc_text) = self.text(doc_path) else {
+                continue;
+            };
+            let edits = rename_edits(doc_path, &doc_text, &old, &new_name);
+            if !edits.is_empty() {
+                ops.push(DocumentChangeOperation::Edit(TextDocumentEdit {
+                    text_document: OptionalVersionedTextDocumentIdentifier {
+                        uri: file_uri(doc_path)?,
+                        version: None,
+                    },
+                    edits: edits.into_iter().map(OneOf::Left).collect(),
+                }));
+            }
+        }
+        ops.push(DocumentChangeOperation::Op(ResourceOp::Rename(
+            RenameFile {
+                old_uri: file_uri(&old)?,
+                new_uri: file_uri(&new)?,
+                options: None,
+                annotation_id: None,
+            },
+        )));
+
+        if let Some(doc) = self.docs.remove(&old) {
+            self.docs.insert(new, doc);
+        }
+        Ok(Some(WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Operations(ops)),
+            ..Default::default()
+        }))
+    }
+
+    fn other_docs<'a>(
+        &'a self,
+        current: &'a Path,
+    ) -> impl Iterator<Item = (&'a PathBuf, &'a IndexedDoc)> + 'a {
+        self.docs.iter().filter(move |(p, _)| {
+            p.as_path() != current && (self.open.contains_key(*p) || p.is_file())
+        })
+    }
+}
+
+/// Edits replacing the file name of every link in `text` that resolves to `old`.
+fn rename_edits(doc_path: &Path, text: &str, old: &Path, new_name: &str) -> Vec<TextEdit> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut edits = Vec::new();
+    for link in diagnostics::scan_links(text) {
+        let resolved = diagnostics::resolve_link(doc_path, &link.target, link.key.as_deref());
+        if resolved.is_none_or(|t| t.path != old) {
+            continue;
+        }
+        let line = lines[link.line];
+        let raw = &link.target;
+        let core_end = raw.find(['?', '#']).unwrap_or(raw.len());
+        let name_start = raw[..core_end].rfind('/').map_or(0, |i| i + 1);
+        let old_segment = &raw[name_start..core_end];
+        // Extensionless `contents` entries stay extensionless.
+        let mut segment = if Path::new(old_segment).extension().is_none() {
+            new_name.trim_end_matches(".md").to_string()
+        } else {
+            new_name.to_string()
+        };
+        let bracketed = line[..link.start].ends_with('<');
+        let graph = diagnostics::is_graph_key(link.key.as_deref());
+        if raw.contains("%20") || (!graph && !bracketed && !raw.contains(' ')) {
+            segment = segment.replace(' ', "%20");
+        }
+        let start = link.start + name_start;
+        let end = link.start + core_end;
+        edits.push(TextEdit::new(
+            Range::new(
+                Position::new(link.line as u32, diagnostics::utf16_col(line, start)),
+                Position::new(link.line as u32, diagnostics::utf16_col(line, end)),
+            ),
+            segment,
+        ));
+    }
+    edits
+}
+
+/// The link whose `[text](target)` span contains `pos`.
+fn link_at(text: &str, pos: Position) -> Option<LinkRef> {
+    let line = text.lines().nth(pos.line as usize)?;
+    let byte = diagnostics::byte_at_utf16(line, pos.character);
+    diagnostics::scan_links(text)
+        .into_iter()
+        .find(|l| l.line == pos.line as usize && l.link_start <= byte && byte <= l.end)
+}
+
+fn collect_markdown(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || name == "node_modules" || name == "target" {
+            continue;
+        }
+        if path.is_dir() {
+            collect_markdown(&path, out);
+        } else if is_markdown(&path) {
+            out.push(path);
+        }
+    }
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("md"))
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn uri_path(uri: &Url) -> Result<PathBuf> {
+    uri.to_file_path()
+        .map_err(|_| anyhow!("Not a file URI: {uri}"))
+}
+
+fn file_uri(path: &Path) -> Result<Url> {
+    Url::from_file_path(path).map_err(|_| anyhow!("Not an absolute path: {}", path.display()))
+}
+
+fn send_diagnostics(connection: &Connection, uri: Url, diagnostics: Vec<Diagnostic>) -> Result<()> {
+    let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+    connection
+        .sender
+        .send(Message::Notification(Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            params,
+        )))?;
+    Ok(())
+}
+
+/// Decode request params, run `handler`, and turn its result into a response.
+fn respond<P: DeserializeOwned, R: Serialize>(
+    req: Request,
+    handler: impl FnOnce(P) -> Result<R>,
+) -> Response {
+    let params = match serde_json::from_value::<P>(req.params) {
+        Ok(params) => params,
+        Err(e) => {
+            return Response::new_err(req.id, ErrorCode::InvalidParams as i32, e.to_string());
+        }
+    };
+    match handler(params) {
+        Ok(result) => Response::new_ok(req.id, result),
+        Err(e) => Response::new_err(req.id, ErrorCode::RequestFailed as i32, format!("{e:#}")),
+    }
+}
@@ -10,61 +10,312 @@
  *   Code: CC-BY-SA-4.0 (adjust later if you decide to separate code/spec licensing)
  */
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
 
-use anyhow::{Context, Result, bail};
-use clap::{Args, Parser, Subcommand};
+use anyhow::{Context, Result, anyhow, bail};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use diaryx_core::{OutputLayout, remote};
+mod api;
+mod archive;
+mod bench;
 mod build;
+mod check;
+mod ci;
+mod config;
+mod conflicts;
+mod dedupe;
+mod diagnostics;
+mod exit;
+mod export;
+mod graph;
+mod index;
+mod lsp;
+mod merge;
+mod meta;
+mod model;
+mod naming;
+mod open;
+mod orphans;
+mod profile;
+#[cfg(feature = "lint-prose")]
+mod prose;
+mod query;
+mod registry;
+mod replace;
+mod review;
+mod rollover;
+mod schema;
+mod sign;
+mod snapshot;
+mod split;
+mod sync;
+mod tag;
+mod trash;
+mod usage;
+mod validate;
+mod vault;
+
+#[cfg(feature = "heap-profile")]
+#[global_allocator]
+static ALLOC: profile::CountingAlloc = profile::CountingAlloc;
 
 /// Diaryx CLI – utilities for working with Diaryx-formatted Markdown files.
 ///
-/// Current focus: `build` subcommand.
-/// Future: `schema`, `validate`, `watch`, exports, etc.
+/// Publishing: `build` (with `--watch`), `export`, `api`, `snapshot`, `verify` and
+/// `verify-snapshot`. Checking: `validate`, `check`, `ci`, `lint-prose`, `orphans`, `dedupe`,
+/// `schema` and `lsp`. Editing: `open`, `split`, `merge`, `replace`, `meta`, `tag`, `rollover` and
+/// `review-page`, with `undo` and `trash` to take edits back. Looking around: `graph`, `index`,
+/// `inspect` and `usage`. Keeping vaults in step: `sync`, `conflicts` and `vault`. Developer
+/// tooling: `gen-vault` and `bench`.
 #[derive(Parser, Debug)]
-#[command(name = "diaryx", version, about)]
+#[command(name = "diaryx", version, about, after_help = exit::EXIT_CODES_HELP)]
 struct Cli {
     #[command(subcommand)]
     command: Command,
+
+    /// Vault to work on instead of the one the current folder is in: a name registered with
+    /// `diaryx vault add`, or a path to a vault folder.
+    #[arg(long, global = true, value_name = "NAME|PATH")]
+    vault: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
 enum Command {
     /// Build a static HTML site from a single Diaryx file.
     ///
-    /// If the file has `this_file_is_root_index: true`, recursively traverse its `contents`
-    /// lists (and nested index files) to build a multi-page site. Otherwise, produce a single
-    /// page site for just that file (plus attachments).
-    Build(BuildArgs),
+    /// If the file has `this_file_is_root_index: true` (or `--as-root` is given), recursively
+    /// traverse its `contents` lists (and nested index files) to build a multi-page site.
+    /// Otherwise, produce a single page site for just that file (plus attachments).
+    Build(Box<BuildArgs>),
+
+    /// Generate a deterministic synthetic vault (developer tooling).
+    GenVault(GenVaultArgs),
+
+    /// Measure core build throughput over a generated synthetic vault (developer tooling).
+    Bench(BenchArgs),
+
+    /// Serve read-only queries over a vault (entries, pages, search, graph) on localhost HTTP,
+    /// or as an MCP server over stdio with `--mcp`.
+    Api(ApiArgs),
+
+    /// Export selected entries for other tools (`--format anki`: flashcards, `ics`: calendar,
+    /// `email-digest`: an HTML email of recent entries, `reader`: an offline archive of the site).
+    Export(ExportArgs),
+
+    /// Open a page, found by slug, title or alias, in $EDITOR (or its built HTML with `--web`).
+    Open(OpenArgs),
+
+    /// Find and replace text across entry bodies, leaving link targets untouched.
+    Replace(ReplaceArgs),
+
+    /// Split an entry into child entries at headings, turning it into their index.
+    Split(SplitArgs),
+
+    /// Merge entries into one, redirecting links and updating parent indexes.
+    Merge(MergeArgs),
+
+    /// Write a review of one week or month (`--period 2025-W34`, `2025-08`): its entries with
+    /// excerpts, counts, top tags and completed tasks, printed or (`--add`) as a new entry.
+    ReviewPage(ReviewPageArgs),
+
+    /// Create this month's index (and the year's) from the `[rollover]` templates, linked into
+    /// the year index and the root index through `contents` / `part_of`.
+    Rollover(RolloverArgs),
+
+    /// List entries the root index's `contents` traversal never reaches, or (`--fix`) append
+    /// them to an index.
+    Orphans(OrphansArgs),
+
+    /// Report the frontmatter and graph warnings of every entry without building; `--fix` first
+    /// adds the missing side of one-sided `contents` / `part_of` links.
+    Validate(ValidateArgs),
+
+    /// Set or remove frontmatter fields across entries matching a `--where` filter, with
+    /// `--dry-run` diffs and `meta undo`.
+    Meta(MetaArgs),
+
+    /// Add, remove or rename tags across entries, or list them with counts and orphans.
+    Tag(TagArgs),
+
+    /// Revert the most recent `replace`, `split`, `merge`, `meta`, `tag`, `review-page --add`,
+    /// `rollover`, `orphans --fix` or `validate --fix` run from the vault's undo journal
+    /// (`.diaryx/undo`).
+    Undo(UndoArgs),
+
+    /// List, restore or permanently delete files that editing commands removed or rewrote
+    /// (kept in `.diaryx/trash`).
+    Trash(TrashArgs),
+
+    /// Summarize the commands logged in `.diaryx/usage.jsonl` (runs, failures, durations and
+    /// vault size over time); logging is opt-in with `[usage] enabled = true` in `diaryx.toml`.
+    Usage(UsageArgs),
+
+    /// Synchronize the vault's sources with a remote copy (rsync or rclone), carrying over
+    /// changes from either side and keeping both versions of files edited on both.
+    Sync(SyncArgs),
+
+    /// List entries left with two versions by `sync` or a git merge, and merge them (fields
+    /// one by one, bodies by choice).
+    Conflicts(ConflictsArgs),
+
+    /// Register named vaults, so `--vault <NAME>` targets them from any directory.
+    Vault(VaultArgs),
+
+    /// List candidate duplicate entries: identical or near-identical bodies, or the same title
+    /// and date (typical after repeated imports).
+    Dedupe(DedupeArgs),
+
+    /// Write a timestamped, content-hashed zip of the vault sources and a fresh build, with a
+    /// manifest for `verify-snapshot`.
+    Snapshot(SnapshotArgs),
+
+    /// Check a snapshot's files against its manifest (and, optionally, a recorded root hash).
+    VerifySnapshot(VerifySnapshotArgs),
+
+    /// Check spelling and prose of entries with pluggable checkers (built-in dictionary, vale,
+    /// LanguageTool), reporting issues with positions.
+    #[cfg(feature = "lint-prose")]
+    LintProse(LintProseArgs),
+
+    /// Check a built site against its `diaryx-manifest.json` (and its signature with `--pubkey`).
+    Verify(VerifyArgs),
+
+    /// Lint a single document (from disk or stdin) and print diagnostics as JSON.
+    ///
+    /// Only that document is parsed; its links are checked against files under the vault root,
+    /// so this is fast enough for editors to run on every save.
+    Check(CheckArgs),
+
+    /// Check the whole vault for a CI pipeline: build warnings, broken links and slug
+    /// collisions, without writing anything. Exits with 2 when any check finds a problem.
+    Ci(CiArgs),
+
+    /// Print the frontmatter model (built-in fields plus those declared in `diaryx.toml`) as a
+    /// JSON Schema, so editors can validate frontmatter as you type.
+    Schema(SchemaArgs),
+
+    /// Pretty-print a build model (`diaryx-data.json`, `.cbor` or `.msgpack`) as JSON.
+    Inspect(InspectArgs),
+
+    /// Write pages, frontmatter fields, tags, links and full-text content into a SQLite
+    /// database; re-running only refreshes the entries that changed.
+    Index(IndexArgs),
+
+    /// Print the document graph (`contents`, `part_of` and body links between entries, with
+    /// each entry's title, visibility and tags) as Graphviz DOT or as JSON for D3.
+    Graph(GraphArgs),
+
+    /// Run a Language Server Protocol server over stdio (diagnostics, completion,
+    /// go-to-definition and rename for the vault in the editor's workspace).
+    Lsp,
 }
 
 /// Arguments for the `build` subcommand.
 #[derive(Args, Debug)]
 struct BuildArgs {
-    /// Entry Diaryx Markdown file (required).
+    /// Entry Diaryx Markdown file: a local path, an `http(s)` URL, or a vault in object storage
+    /// (`s3://bucket/index.md`, `webdavs://host/path/index.md`). Default: the root index of the
+    /// vault the current folder is in (nearest folder above with `diaryx.toml` or a root index).
+    /// With `--stdin`, only names where the document would live (default: `stdin.md`).
     #[arg(long, value_name = "FILE")]
-    input: PathBuf,
+    input: Option<PathBuf>,
 
-    /// Output directory (will be created or replaced).
-    #[arg(long, default_value = "./site", value_name = "DIR")]
-    output: PathBuf,
+    /// Output directory (will be created or replaced; default `./site`). `-` renders the single
+    /// input document as standalone HTML (stylesheet inlined) on stdout instead.
+    #[arg(long, value_name = "DIR")]
+    output: Option<PathBuf>,
+
+    /// Apply the flags of this profile, declared as `[profiles.<NAME>]` in the nearest
+    /// `diaryx.toml` (e.g. `preview`, `production`). Flags on the command line still apply.
+    /// Named `--build-profile` because `--profile` already turns on the timing report.
+    #[arg(long, value_name = "NAME")]
+    build_profile: Option<String>,
+
+    /// Read one document from stdin (requires `--output -`), for use as a filter.
+    #[arg(long)]
+    stdin: bool,
+
+    /// Keep running and rebuild when a source file changes. Editing one entry rewrites only its
+    /// page and the pages depending on it; the refreshed outputs are listed after each rebuild.
+    #[arg(long, conflicts_with = "stdin")]
+    watch: bool,
+
+    /// With `--watch`, also write the result of every build as one line of JSON (changed sources,
+    /// pages written, warnings or the error) to FILE, for editor plugins, live-reload servers and
+    /// other tools to follow. `-` streams the events to stdout (the text report then goes to
+    /// stderr).
+    #[arg(long, value_name = "FILE", requires = "watch")]
+    watch_events: Option<PathBuf>,
+
+    /// Fetch `http(s)` pages (an entry URL, or `contents` links to URLs) from this host or its
+    /// subdomains. Repeatable; an entry URL's own host is always allowed.
+    #[arg(long, value_name = "HOST")]
+    allow_remote: Vec<String>,
+
+    /// age identity file (private key) decrypting an encrypted vault archive
+    /// (`--input vault.zip.age`). Passphrase-encrypted archives read `DIARYX_PASSPHRASE` instead.
+    #[arg(long, value_name = "FILE")]
+    identity: Option<PathBuf>,
+
+    /// Folder caching fetched remote pages (default: `diaryx/remote` in the user cache folder).
+    #[arg(long, value_name = "DIR")]
+    remote_cache: Option<PathBuf>,
 
     /// Include non-public files (those whose visibility does NOT include `public`).
     /// By default only public-visible files are emitted.
     #[arg(long)]
     include_nonpublic: bool,
 
+    /// Build the site variant for an audience: pages whose visibility lists it (e.g.
+    /// `visibility: [family]`) are included alongside the public ones.
+    #[arg(long, value_name = "NAME", conflicts_with = "include_nonpublic")]
+    audience: Option<String>,
+
+    /// Encrypt the non-public pages included by `--include-nonpublic` or `--audience`: their
+    /// body, metadata and title are published AES-encrypted, with a passphrase prompt that
+    /// decrypts them in the browser, and the public pages do not list them.
+    /// The passphrase is read from `DIARYX_ENCRYPT_PASSPHRASE`; key derivation is set in the
+    /// `[encrypt]` table of `diaryx.toml`.
+    #[arg(long)]
+    encrypt: bool,
+
     /// Emit an intermediate JSON model (diaryx-data.json).
     #[arg(long)]
     emit_json: bool,
 
+    /// Emit the model in this encoding: `json` (same as `--emit-json`), or `cbor` / `msgpack`
+    /// for a compact binary file with the same structure (diaryx-data.cbor / .msgpack).
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with = "emit_json")]
+    emit_model: Option<model::ModelFormat>,
+
     /// Emit all pages directly in the output directory (no pages/ subfolder in multi-page mode).
-    #[arg(long)]
+    /// Same as `--layout flat`.
+    #[arg(long, conflicts_with = "layout")]
     flat: bool,
 
+    /// Where pages are written: `pages` (`pages/<slug>.html`, the default), `flat` (next to the
+    /// root index), `mirror` (the vault's folder structure) or `dated` (`<year>/<month>/<day>/`
+    /// from `created`). Links between pages follow.
+    #[arg(long, value_name = "LAYOUT", value_parser = parse_output_layout)]
+    layout: Option<OutputLayout>,
+
     /// Verbose logging (prints warnings/progress to stderr).
     #[arg(long)]
     verbose: bool,
 
+    /// Do not print the completion line (or, with `--watch`, the report of each rebuild); errors
+    /// are still printed.
+    #[arg(long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// How to report the result on stdout: `text` (the completion line) or `json` (one object
+    /// with `status`, `output`, `pages`, `warnings` as `{code, message}`, and `error` and
+    /// `exit_code` when it failed).
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "text")]
+    output_format: build::OutputFormat,
+
     /// Treat warnings as errors (fail the build if any warning occurs).
     #[arg(long)]
     strict: bool,
@@ -72,6 +323,978 @@ struct BuildArgs {
     /// Do not emit the bundled default CSS file (style.css). Use when providing custom styling.
     #[arg(long)]
     no_default_css: bool,
+
+    /// Print per-phase timings to stderr, and peak heap usage in a build with the `heap-profile`
+    /// feature. To build with a `[profiles.<NAME>]` of `diaryx.toml`, use `--build-profile`.
+    #[arg(long)]
+    profile: bool,
+
+    /// Entries whose body exceeds this many bytes skip attachment scanning (with a warning).
+    /// Defaults to 8 MiB.
+    #[arg(long, value_name = "BYTES")]
+    large_body_bytes: Option<usize>,
+
+    /// Typographic punctuation in bodies and titles: curly quotes and apostrophes, `--` / `---`
+    /// as en / em dashes, `...` as an ellipsis. Code is left alone.
+    #[arg(long)]
+    smart_punctuation: bool,
+
+    /// Render images that stand alone in a paragraph as `<figure>`, captioned by an italic line
+    /// right below the image or by the image's title text.
+    #[arg(long)]
+    figures: bool,
+
+    /// Embed PDF attachments linked on a line of their own as an inline preview with a download
+    /// link. A link titled "preview" or "no-preview" overrides this per link.
+    #[arg(long)]
+    pdf_previews: bool,
+
+    /// Take a missing `created` / `updated` from the file's creation / modification time instead
+    /// of warning; the page's metadata marks them as inferred.
+    #[arg(long)]
+    infer_dates: bool,
+
+    /// Never traverse, build or copy files matching this glob (relative to the entry's folder),
+    /// even when linked; e.g. `templates/**`, `private/`, `*.excalidraw.md`. A pattern without
+    /// `/` matches a file or folder name at any depth. Repeatable.
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Only publish entries created on or after this date (`YYYY-MM-DD`). Indexes are kept only
+    /// when they lead to an included entry and list only included children.
+    #[arg(long, value_name = "DATE")]
+    since: Option<String>,
+
+    /// Only publish entries created on or before this date (`YYYY-MM-DD`, inclusive).
+    #[arg(long, value_name = "DATE")]
+    until: Option<String>,
+
+    /// Only publish entries carrying this tag; repeat to accept any of several tags.
+    #[arg(long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+
+    /// Publish entries marked `draft: true` too (held back by default).
+    #[arg(long)]
+    drafts: bool,
+
+    /// Publish entries whose `published` time is still ahead too (held back until then by
+    /// default).
+    #[arg(long)]
+    future: bool,
+
+    /// Treat the input as the root index even if it does not declare
+    /// `this_file_is_root_index`, building just its subtree (e.g. one section of the vault).
+    #[arg(long)]
+    as_root: bool,
+
+    /// End each page with a "Changes" section listing the git commits of its source file,
+    /// marking those that changed its `version`.
+    #[arg(long)]
+    changelog: bool,
+
+    /// Do not warn about versioned pages whose `updated` changed since the last commit while
+    /// their `version` did not.
+    #[arg(long)]
+    no_version_check: bool,
+
+    /// Make the site an installable web app that works offline: emit a web app manifest, icons
+    /// and a service worker precaching every page and asset.
+    #[arg(long)]
+    pwa: bool,
+
+    /// Add `graph.html`, an interactive view of the document graph (the data of `diaryx graph
+    /// --format json`), linked from the root index.
+    #[arg(long)]
+    graph_page: bool,
+
+    /// Give every page a folder of its own (`pages/walk/index.html` instead of
+    /// `pages/walk.html`) and link pages by folder, so hosts serve them at extensionless URLs.
+    #[arg(long)]
+    clean_urls: bool,
+
+    /// Make the output ready for GitHub Pages: add `.nojekyll`, a `404.html` and, when
+    /// `[gh_pages] cname` is set in `diaryx.toml`, a `CNAME` file.
+    #[arg(long)]
+    gh_pages: bool,
+
+    /// Path the site is served under on GitHub Pages, for the links of its `404.html`
+    /// (`/<repo>/` for a project site; default: `[gh_pages] path_prefix`, else `/`).
+    #[arg(long, value_name = "PREFIX", requires = "gh_pages")]
+    path_prefix: Option<String>,
+
+    /// Write configuration for a static host: redirects from page `aliases`, clean URLs and
+    /// headers (default: `provider` in the `[hosting]` table of `diaryx.toml`, if any).
+    #[arg(long, value_enum, value_name = "HOST")]
+    host: Option<build::HostProvider>,
+
+    /// Write `diaryx-manifest.json` listing every output file with its SHA-256 (see `verify`).
+    #[arg(long)]
+    manifest: bool,
+
+    /// Sign the manifest (implies `--manifest`) with a minisign secret key or an SSH private key.
+    #[arg(long, value_name = "KEY")]
+    sign: Option<PathBuf>,
+
+    /// Print the files added, removed and grown since the previous build (from its manifest;
+    /// implies `--manifest`, so the next build can compare with this one).
+    #[arg(long)]
+    size_report: bool,
+
+    /// Check the written pages and stylesheets for accessibility problems: images without alt
+    /// text, links without text, pages without a language and theme colors below the WCAG AA
+    /// contrast ratio (reported as `a11y` warnings).
+    #[arg(long)]
+    check_a11y: bool,
+
+    /// Run the `pre_build` and `post_build` commands of the `[hooks]` table in `diaryx.toml`.
+    /// They are arbitrary shell commands, so they only run when asked for.
+    #[arg(long)]
+    hooks: bool,
+}
+
+/// Arguments for the `api` subcommand.
+#[derive(Args, Debug)]
+struct ApiArgs {
+    /// Entry Diaryx Markdown file (default: the root index, discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// Port to listen on (always bound to 127.0.0.1).
+    #[arg(long, default_value_t = 8787)]
+    port: u16,
+
+    /// Expose non-public files too (by default only public-visible pages are served).
+    #[arg(long)]
+    include_nonpublic: bool,
+
+    /// Speak the Model Context Protocol over stdio instead of serving HTTP.
+    #[arg(long)]
+    mcp: bool,
+}
+
+/// Arguments for the `export` subcommand.
+#[derive(Args, Debug)]
+struct ExportArgs {
+    /// Root index of the vault (default: discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// Output format.
+    #[arg(long, value_enum)]
+    format: export::ExportFormat,
+
+    /// Only export entries matching this filter (see `meta --where`).
+    #[arg(long = "where", value_name = "EXPR")]
+    filter: Option<String>,
+
+    /// Include entries that are not public.
+    #[arg(long)]
+    include_nonpublic: bool,
+
+    /// Write to this file instead of stdout (`reader`: a folder, or a `.zip` file).
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Anki deck to import the cards into (`anki` only; default: chosen at import).
+    #[arg(long)]
+    deck: Option<String>,
+
+    /// Public URL of the built site, for links to pages (`ics`; required by `email-digest`).
+    #[arg(long, value_name = "URL")]
+    base_url: Option<String>,
+
+    /// Link to pages in the flat output layout (`build --flat`).
+    #[arg(long)]
+    flat: bool,
+
+    /// Only entries created since: `7d`, `2w`, `24h` or a date (`email-digest` default: 7d).
+    #[arg(long, value_name = "PERIOD")]
+    since: Option<String>,
+
+    /// Only entries in this language (their `lang`, or the root index's), e.g. one feed per language.
+    #[arg(long, value_name = "CODE")]
+    lang: Option<String>,
+}
+
+/// Arguments for the `open` subcommand.
+#[derive(Args, Debug)]
+struct OpenArgs {
+    /// Slug, title or alias of the page (a unique partial match also works).
+    #[arg(value_name = "SLUG_OR_TITLE")]
+    query: String,
+
+    /// Entry Diaryx Markdown file the vault graph is walked from (default: the root index,
+    /// discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// Open the built HTML page in the browser instead of the source in $EDITOR.
+    #[arg(long)]
+    web: bool,
+
+    /// Output directory of the built site (used with `--web`).
+    #[arg(long, default_value = "./site", value_name = "DIR")]
+    output: PathBuf,
+
+    /// The site was built with `--flat` (used with `--web`).
+    #[arg(long, conflicts_with = "layout")]
+    flat: bool,
+
+    /// The `--layout` the site was built with (used with `--web`).
+    #[arg(long, value_name = "LAYOUT", value_parser = parse_output_layout)]
+    layout: Option<OutputLayout>,
+}
+
+/// Arguments for the `replace` subcommand.
+#[derive(Args, Debug)]
+struct ReplaceArgs {
+    /// Text to find (a regex with `--regex`).
+    #[arg(long)]
+    from: String,
+
+    /// Replacement text (may use `$1` / `${name}` with `--regex`).
+    #[arg(long)]
+    to: String,
+
+    /// Interpret `--from` as a regular expression.
+    #[arg(long)]
+    regex: bool,
+
+    /// Also replace inside frontmatter (bodies only by default).
+    #[arg(long)]
+    frontmatter: bool,
+
+    #[command(flatten)]
+    edit: EditArgs,
+
+    /// Edit every entry reachable from this root index (default without FILES: discovered from
+    /// the current folder, or `--vault`).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// Specific entries to edit (instead of the whole vault, or in addition to `--input`).
+    #[arg(value_name = "FILES")]
+    files: Vec<PathBuf>,
+}
+
+/// Arguments for the `split` subcommand.
+#[derive(Args, Debug)]
+struct SplitArgs {
+    /// Entry to split.
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+
+    /// Heading level to split at (`h1` .. `h6`).
+    #[arg(long, default_value = "h2", value_parser = parse_heading_level)]
+    by: usize,
+
+    #[command(flatten)]
+    edit: EditArgs,
+}
+
+/// Arguments for the `review-page` subcommand.
+#[derive(Args, Debug)]
+struct ReviewPageArgs {
+    /// Period to review: an ISO week (`2025-W34`) or a month (`2025-08`).
+    #[arg(long, value_name = "PERIOD")]
+    period: String,
+
+    /// Entry Diaryx Markdown file (default: the root index, discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// Include pages marked as non-public.
+    #[arg(long)]
+    include_nonpublic: bool,
+
+    /// Write to this file instead of stdout.
+    #[arg(short, long, value_name = "FILE", conflicts_with = "add")]
+    output: Option<PathBuf>,
+
+    /// Add the review to the vault as `reviews/<period>.md`, listed in the root index.
+    #[arg(long)]
+    add: bool,
+
+    #[command(flatten)]
+    edit: EditArgs,
+}
+
+/// Arguments for the `rollover` subcommand.
+#[derive(Args, Debug)]
+struct RolloverArgs {
+    /// Month to create the indexes for (`2025-09`; default: the current month).
+    #[arg(long, value_name = "YYYY-MM")]
+    month: Option<String>,
+
+    /// Entry Diaryx Markdown file (default: the root index, discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    #[command(flatten)]
+    edit: EditArgs,
+}
+
+/// Arguments for the `orphans` subcommand.
+#[derive(Args, Debug)]
+struct OrphansArgs {
+    /// Append the orphans to an index's `contents` (and set the `part_of` of those without one).
+    #[arg(long)]
+    fix: bool,
+
+    /// Index the orphans are appended to (default: the root index).
+    #[arg(long, value_name = "INDEX", requires = "fix")]
+    into: Option<PathBuf>,
+
+    /// Glob of files that are not entries, relative to the vault folder (repeatable, as
+    /// `build --exclude`).
+    #[arg(long, value_name = "PATTERN")]
+    exclude: Vec<String>,
+
+    /// Entry Diaryx Markdown file (default: the root index, discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    #[command(flatten)]
+    edit: EditArgs,
+}
+
+/// Arguments for the `validate` subcommand.
+#[derive(Args, Debug)]
+struct ValidateArgs {
+    /// Add the missing side of each one-sided `contents` / `part_of` link before validating.
+    #[arg(long)]
+    fix: bool,
+
+    /// Entry Diaryx Markdown file (default: the root index, discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    #[command(flatten)]
+    edit: EditArgs,
+}
+
+/// Arguments for the `usage` subcommand.
+#[derive(Args, Debug)]
+struct UsageArgs {
+    /// Only summarize commands run on or after this date (`YYYY-MM-DD`).
+    #[arg(long, value_name = "DATE")]
+    since: Option<String>,
+
+    /// Delete the usage log.
+    #[arg(long, conflicts_with = "since")]
+    clear: bool,
+
+    /// Root index of the vault (default: discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+}
+
+fn parse_output_layout(s: &str) -> std::result::Result<OutputLayout, String> {
+    OutputLayout::from_name(s).ok_or_else(|| {
+        format!(
+            "expected one of {}, got '{s}'",
+            OutputLayout::NAMES.join(", ")
+        )
+    })
+}
+
+fn parse_heading_level(s: &str) -> std::result::Result<usize, String> {
+    match s.to_ascii_lowercase().strip_prefix('h').map(str::parse) {
+        Some(Ok(level @ 1..=6)) => Ok(level),
+        _ => Err(format!("expected h1 .. h6, got '{s}'")),
+    }
+}
+
+/// Arguments for the `merge` subcommand.
+#[derive(Args, Debug)]
+struct MergeArgs {
+    /// Entries to merge, in body order (at least two).
+    #[arg(value_name = "FILES", required = true)]
+    files: Vec<PathBuf>,
+
+    /// Resulting entry (may be one of FILES; otherwise it must not exist yet).
+    #[arg(long, value_name = "FILE")]
+    into: PathBuf,
+
+    /// Root index of the vault whose links to the merged entries are redirected (default:
+    /// discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// Title of the merged entry (default: the first entry's title).
+    #[arg(long)]
+    title: Option<String>,
+
+    #[command(flatten)]
+    edit: EditArgs,
+}
+
+/// Arguments for the `meta` subcommand.
+#[derive(Args, Debug)]
+struct MetaArgs {
+    #[command(subcommand)]
+    action: MetaAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum MetaAction {
+    /// Set fields (`key=value`, value parsed as YAML) on every matching entry.
+    Set {
+        #[arg(value_name = "KEY=VALUE", required = true, value_parser = meta::parse_assignment)]
+        assignments: Vec<(String, serde_yaml::Value)>,
+
+        #[command(flatten)]
+        select: SelectArgs,
+    },
+
+    /// Remove fields from every matching entry.
+    Unset {
+        #[arg(value_name = "KEY", required = true)]
+        keys: Vec<String>,
+
+        #[command(flatten)]
+        select: SelectArgs,
+    },
+
+    /// Revert the most recent journaled edit (same as `diaryx undo`).
+    Undo {
+        /// Root index of the vault (default: the nearest folder above the current one with an
+        /// undo journal).
+        #[arg(long, value_name = "FILE")]
+        input: Option<PathBuf>,
+
+        /// Revert even if affected files changed since.
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Arguments for the `tag` subcommand.
+#[derive(Args, Debug)]
+struct TagArgs {
+    #[command(subcommand)]
+    action: TagAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum TagAction {
+    /// Add a tag to every matching entry.
+    Add {
+        tag: String,
+
+        #[command(flatten)]
+        select: SelectArgs,
+    },
+
+    /// Remove a tag from every matching entry.
+    Remove {
+        tag: String,
+
+        #[command(flatten)]
+        select: SelectArgs,
+    },
+
+    /// Rename a tag on every matching entry (merging it into NEW where both are present).
+    Rename {
+        old: String,
+        new: String,
+
+        #[command(flatten)]
+        select: SelectArgs,
+    },
+
+    /// List tags used by matching entries.
+    List {
+        /// Root index of the vault (default: discovered from the current folder).
+        #[arg(long, value_name = "FILE")]
+        input: Option<PathBuf>,
+
+        /// Only count entries matching this filter.
+        #[arg(long = "where", value_name = "EXPR")]
+        filter: Option<String>,
+
+        /// Print `<count>\t<tag>`, most used first.
+        #[arg(long, conflicts_with = "orphans")]
+        counts: bool,
+
+        /// Only print tags used by a single entry, with that entry.
+        #[arg(long)]
+        orphans: bool,
+    },
+}
+
+/// Arguments for the `undo` subcommand.
+#[derive(Args, Debug)]
+struct UndoArgs {
+    /// Root index of the vault (default: the nearest folder above the current one with an undo
+    /// journal).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// Revert even if affected files changed since.
+    #[arg(long)]
+    force: bool,
+}
+
+/// Arguments for the `trash` subcommand.
+#[derive(Args, Debug)]
+struct TrashArgs {
+    #[command(subcommand)]
+    action: TrashAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum TrashAction {
+    /// List trashed files, newest first: batch id, deletion time and original path.
+    List {
+        /// Root index of the vault (default: the nearest folder above the current one with a
+        /// trash).
+        #[arg(long, value_name = "FILE")]
+        input: Option<PathBuf>,
+    },
+
+    /// Move a batch (by id) or a single file (by original path) back where it was.
+    Restore {
+        #[arg(value_name = "ID|PATH")]
+        which: String,
+
+        /// Root index of the vault (default: the nearest folder above the current one with a
+        /// trash).
+        #[arg(long, value_name = "FILE")]
+        input: Option<PathBuf>,
+
+        /// Overwrite files that exist again at their original path.
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Permanently delete trashed files.
+    Empty {
+        /// Root index of the vault (default: the nearest folder above the current one with a
+        /// trash).
+        #[arg(long, value_name = "FILE")]
+        input: Option<PathBuf>,
+
+        /// Only delete batches trashed more than this many days ago.
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u64>,
+    },
+}
+
+/// Arguments for the `sync` subcommand.
+#[derive(Args, Debug)]
+struct SyncArgs {
+    /// Root index of the vault (default: discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// Remote to sync with (default: `remote` in the `[sync]` table of `diaryx.toml`).
+    #[arg(long, value_name = "REMOTE")]
+    remote: Option<String>,
+
+    /// Transfer tool (default: `backend` in `[sync]`, else rsync).
+    #[arg(long, value_enum)]
+    backend: Option<sync::Backend>,
+
+    /// Fetch the remote and print what would be pulled, pushed or in conflict, without
+    /// changing either side.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Sync even when the remote is empty or more than half of either side would be deleted
+    /// (otherwise refused, as a sign of a wrong or unmounted remote).
+    #[arg(long)]
+    force: bool,
+}
+
+/// Arguments for the `conflicts` subcommand.
+#[derive(Args, Debug)]
+struct ConflictsArgs {
+    #[command(subcommand)]
+    action: ConflictsAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum ConflictsAction {
+    /// List conflicted entries with what differs between their versions.
+    List {
+        /// Root index of the vault (default: discovered from the current folder).
+        #[arg(long, value_name = "FILE")]
+        input: Option<PathBuf>,
+    },
+
+    /// Merge conflicted entries: frontmatter field by field (lists unioned, the later
+    /// `updated` winning otherwise), and the body you pick from a diff.
+    Resolve {
+        /// Only these entries (or their `.sync-conflict` copies); default: all.
+        #[arg(value_name = "FILES")]
+        files: Vec<PathBuf>,
+
+        /// Root index of the vault (default: discovered from the current folder).
+        #[arg(long, value_name = "FILE")]
+        input: Option<PathBuf>,
+
+        /// Keep this body for every entry instead of asking.
+        #[arg(long, value_enum)]
+        body: Option<conflicts::BodyChoice>,
+
+        /// Print a unified diff of the resolutions instead of writing them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Arguments for the `vault` subcommand.
+#[derive(Args, Debug)]
+struct VaultArgs {
+    #[command(subcommand)]
+    action: VaultAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum VaultAction {
+    /// Register the vault at PATH (its folder or root index) as NAME.
+    Add { name: String, path: PathBuf },
+
+    /// Forget a registered vault (its files are left alone).
+    Remove { name: String },
+
+    /// List registered vaults.
+    List,
+}
+
+/// Entry selection shared by the bulk-editing subcommands (`meta`, `tag`).
+#[derive(Args, Debug)]
+struct SelectArgs {
+    /// Root index of the vault (default: discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// Only touch entries matching this filter, e.g. `tags contains travel and draft != true`
+    /// (default: all entries).
+    #[arg(long = "where", value_name = "EXPR")]
+    filter: Option<String>,
+
+    #[command(flatten)]
+    edit: EditArgs,
+}
+
+/// How the editing subcommands apply their changes.
+#[derive(Args, Debug)]
+struct EditArgs {
+    /// Print a unified diff of the changes instead of writing them.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Print a unified diff of each change as it is written.
+    #[arg(long)]
+    diff: bool,
+
+    /// Show each change as a diff and ask before writing it.
+    #[arg(long, short = 'i', conflicts_with = "dry_run")]
+    interactive: bool,
+}
+
+impl EditArgs {
+    fn mode(&self) -> vault::EditMode {
+        vault::EditMode {
+            dry_run: self.dry_run,
+            diff: self.diff,
+            interactive: self.interactive,
+        }
+    }
+}
+
+/// `--input`, canonicalized, or else the root index of the `--vault` (`home`), or of the vault
+/// the current folder is in.
+fn vault_input(input: Option<&Path>, home: Option<&Path>) -> Result<PathBuf> {
+    if let Some(input) = input {
+        return input
+            .canonicalize()
+            .with_context(|| format!("Input file does not exist: {}", input.display()));
+    }
+    if let Some(index) = home.filter(|home| home.is_file()) {
+        return Ok(index.to_path_buf());
+    }
+    let start = match home {
+        Some(home) => home.to_path_buf(),
+        None => std::env::current_dir()?,
+    };
+    vault::discover_root_index(&start)?.ok_or_else(|| {
+        exit::ValidationFailed(format!(
+            "No vault found in {} or above (no diaryx.toml or root index); pass --input",
+            start.display()
+        ))
+        .into()
+    })
+}
+
+/// Vault folder for `undo` / `trash`: the root index's folder with `--input` or `--vault`, else
+/// the nearest folder above the current one that has `state`.
+fn state_root(input: Option<&Path>, state: &str, home: Option<&Path>) -> Result<PathBuf> {
+    if input.is_some() || home.is_some() {
+        let input = vault_input(input, home)?;
+        return Ok(input.parent().unwrap_or(Path::new(".")).to_path_buf());
+    }
+    let cwd = std::env::current_dir()?;
+    vault::find_state_root(&cwd, state)
+        .ok_or_else(|| anyhow!("No {state} in {} or above", cwd.display()))
+}
+
+/// The command line (without the program name), as recorded in undo journals.
+fn invocation() -> String {
+    std::env::args().skip(1).collect::<Vec<_>>().join(" ")
+}
+
+/// Arguments for the `snapshot` subcommand.
+#[derive(Args, Debug)]
+struct SnapshotArgs {
+    /// Root index of the vault (default: discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// Folder the snapshot archive is written to.
+    #[arg(short, long, default_value = "./snapshots", value_name = "DIR")]
+    output: PathBuf,
+
+    /// Include non-public pages in the built site (sources always include every entry).
+    #[arg(long)]
+    include_nonpublic: bool,
+}
+
+/// Arguments for the `verify-snapshot` subcommand.
+#[derive(Args, Debug)]
+struct VerifySnapshotArgs {
+    /// Snapshot archive written by `diaryx snapshot`.
+    #[arg(value_name = "ARCHIVE")]
+    archive: PathBuf,
+
+    /// Root hash recorded when the snapshot was taken; verification fails if it differs.
+    #[arg(long, value_name = "SHA256")]
+    expect_root: Option<String>,
+}
+
+/// Arguments for the `verify` subcommand.
+#[derive(Args, Debug)]
+struct VerifyArgs {
+    /// Site folder (a build made with `--manifest` or `--sign`).
+    #[arg(value_name = "DIR")]
+    dir: PathBuf,
+
+    /// Public key the manifest was signed for (minisign `.pub` or SSH `.pub`).
+    #[arg(long, value_name = "FILE")]
+    pubkey: Option<PathBuf>,
+}
+
+/// Arguments for the `dedupe` subcommand.
+#[derive(Args, Debug)]
+struct DedupeArgs {
+    /// Root index of the vault (default: discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// Only consider entries matching this filter (see `meta --where`).
+    #[arg(long = "where", value_name = "EXPR")]
+    filter: Option<String>,
+
+    /// Minimum body similarity (Jaccard over 3-word shingles, 0..1) for near-duplicates.
+    #[arg(long, default_value_t = 0.8)]
+    threshold: f64,
+}
+
+/// Arguments for the `lint-prose` subcommand.
+#[cfg(feature = "lint-prose")]
+#[derive(Args, Debug)]
+struct LintProseArgs {
+    /// Entries to check (in addition to `--input`).
+    #[arg(value_name = "FILES")]
+    files: Vec<PathBuf>,
+
+    /// Root index: check every entry of the vault.
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// With `--input`, only check entries matching this filter (see `meta --where`).
+    #[arg(long = "where", value_name = "EXPR", requires = "input")]
+    filter: Option<String>,
+
+    /// Checker to run (repeatable).
+    #[arg(long = "checker", value_enum, default_values_t = [prose::CheckerKind::Spell])]
+    checkers: Vec<prose::CheckerKind>,
+
+    /// Word list for the spell checker, one word per line (repeatable; default:
+    /// /usr/share/dict/words). `.diaryx/dictionary.txt` is always added when present.
+    #[arg(long = "dictionary", value_name = "FILE")]
+    dictionaries: Vec<PathBuf>,
+
+    /// Language for LanguageTool.
+    #[arg(long, default_value = "en-US")]
+    language: String,
+
+    /// Print the issues as JSON.
+    #[arg(long)]
+    json: bool,
+}
+
+/// Arguments for the `check` subcommand.
+#[derive(Args, Debug)]
+struct CheckArgs {
+    /// Document to check. With `--stdin`, only names where the text lives (relative to
+    /// `--vault-root`) so links resolve correctly.
+    #[arg(value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// Read the document text from stdin instead of FILE.
+    #[arg(long)]
+    stdin: bool,
+
+    /// Vault directory that links are resolved against (default: current directory).
+    #[arg(long, value_name = "DIR")]
+    vault_root: Option<PathBuf>,
+}
+
+/// Arguments for the `ci` subcommand.
+#[derive(Args, Debug)]
+struct CiArgs {
+    /// Entry Diaryx Markdown file (default: the root index, discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// `text` prints one line per check and problem; `json` prints a single object with every
+    /// check's problems and the exit code.
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "text")]
+    output_format: build::OutputFormat,
+
+    /// Take a missing `created` / `updated` from file timestamps instead of reporting it (as
+    /// `build --infer-dates` does).
+    #[arg(long)]
+    infer_dates: bool,
+}
+
+/// Arguments for the `schema` subcommand.
+#[derive(Args, Debug)]
+struct SchemaArgs {
+    /// Output format.
+    #[arg(long, value_enum, default_value = "json-schema")]
+    format: schema::SchemaFormat,
+
+    /// Write to this file instead of stdout.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+/// Arguments for the `index` subcommand.
+#[derive(Args, Debug)]
+struct IndexArgs {
+    /// Entry Diaryx Markdown file (default: the root index, discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// SQLite database to create or update.
+    #[arg(long, value_name = "DB")]
+    sqlite: PathBuf,
+
+    /// Include pages marked as non-public.
+    #[arg(long)]
+    include_nonpublic: bool,
+}
+
+/// Arguments for the `graph` subcommand.
+#[derive(Args, Debug)]
+struct GraphArgs {
+    /// Entry Diaryx Markdown file (default: the root index, discovered from the current folder).
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// Output format.
+    #[arg(long, value_enum, default_value = "dot")]
+    format: graph::GraphFormat,
+
+    /// Write to this file instead of stdout.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Include pages marked as non-public.
+    #[arg(long)]
+    include_nonpublic: bool,
+}
+
+/// Arguments for the `inspect` subcommand.
+#[derive(Args, Debug)]
+struct InspectArgs {
+    /// Model file written by `build --emit-json` / `--emit-model`.
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+
+    /// Encoding of the file (default: from its extension).
+    #[arg(long, value_enum)]
+    format: Option<model::ModelFormat>,
+
+    /// Only print the part at this JSON Pointer, e.g. `/pages/0/frontmatter`.
+    #[arg(long, value_name = "POINTER")]
+    pointer: Option<String>,
+}
+
+/// Shape of a synthetic vault (shared by `gen-vault` and `bench`).
+#[derive(Args, Debug)]
+struct SynthArgs {
+    /// Number of leaf entries to generate.
+    #[arg(long, default_value_t = 1000, value_name = "N")]
+    entries: usize,
+
+    /// Maximum entries listed per section index.
+    #[arg(long, default_value_t = 100, value_name = "N")]
+    entries_per_section: usize,
+
+    /// Average number of body links per entry.
+    #[arg(long, default_value_t = 2.0, value_name = "F")]
+    link_density: f64,
+
+    /// Filler paragraphs per entry.
+    #[arg(long, default_value_t = 3, value_name = "N")]
+    paragraphs: usize,
+
+    /// PRNG seed (same seed => identical vault).
+    #[arg(long, default_value_t = 0x5eed)]
+    seed: u64,
+}
+
+impl SynthArgs {
+    fn to_options(&self) -> diaryx_core::synth::SynthVaultOptions {
+        diaryx_core::synth::SynthVaultOptions {
+            entries: self.entries,
+            entries_per_section: self.entries_per_section,
+            link_density: self.link_density,
+            paragraphs: self.paragraphs,
+            seed: self.seed,
+        }
+    }
+}
+
+/// Arguments for the `gen-vault` subcommand.
+#[derive(Args, Debug)]
+struct GenVaultArgs {
+    #[command(flatten)]
+    synth: SynthArgs,
+
+    /// Directory to write the vault into (created if missing).
+    #[arg(long, default_value = "./synthetic-vault", value_name = "DIR")]
+    output: PathBuf,
+}
+
+/// Arguments for the `bench` subcommand.
+#[derive(Args, Debug)]
+struct BenchArgs {
+    #[command(flatten)]
+    synth: SynthArgs,
+
+    /// Number of timed builds.
+    #[arg(long, default_value_t = 5, value_name = "N")]
+    iterations: usize,
+
+    /// Keep the generated scratch vault instead of deleting it.
+    #[arg(long)]
+    keep: bool,
 }
 
 /// Public-facing build options passed to the build layer.
@@ -80,34 +1303,248 @@ struct BuildArgs {
 pub struct BuildOptions {
     pub input: PathBuf,
     pub output: PathBuf,
+    /// Read the document from stdin rather than `input`.
+    pub stdin: bool,
+    /// Rebuild as sources change (`--watch`).
+    pub watch: bool,
+    /// Where to stream rebuild events as JSON lines (`--watch-events`).
+    pub watch_events: Option<PathBuf>,
+    /// Hosts remote pages may be fetched from.
+    pub allow_remote: Vec<String>,
+    pub remote_cache: Option<PathBuf>,
+    /// age identity file for an encrypted vault archive.
+    pub identity: Option<PathBuf>,
     pub include_nonpublic: bool,
-    pub emit_json: bool,
-    pub flat: bool,
+    /// Audience whose pages are included besides the public ones (`--audience`).
+    pub audience: Option<String>,
+    /// Key the non-public pages are encrypted with (`--encrypt`).
+    pub encrypt: Option<build::PageEncryption>,
+    /// Encoding of the emitted model, if any.
+    pub emit_model: Option<model::ModelFormat>,
+    pub layout: OutputLayout,
     pub verbose: bool,
+    /// Suppress the completion line (`--quiet`).
+    pub quiet: bool,
+    pub output_format: build::OutputFormat,
     pub strict: bool,
     pub no_default_css: bool,
+    pub profile: bool,
+    pub large_body_bytes: Option<usize>,
+    pub smart_punctuation: bool,
+    pub figures: bool,
+    pub pdf_previews: bool,
+    pub infer_dates: bool,
+    pub exclude: Vec<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub tags: Vec<String>,
+    /// Publish drafts (`--drafts`).
+    pub drafts: bool,
+    /// Publish entries scheduled for later (`--future`).
+    pub future: bool,
+    pub as_root: bool,
+    pub changelog: bool,
+    pub no_version_check: bool,
+    pub pwa: bool,
+    pub graph_page: bool,
+    /// One folder per page (`--clean-urls`).
+    pub clean_urls: bool,
+    /// GitHub Pages files to add (`--gh-pages`).
+    pub gh_pages: Option<build::GhPages>,
+    /// Static host configuration to write (`--host`, `[hosting]`).
+    pub hosting: Option<build::Hosting>,
+    pub manifest: bool,
+    pub sign: Option<PathBuf>,
+    /// Print the size changes since the previous build's manifest (`--size-report`).
+    pub size_report: bool,
+    /// Output size limits (`[budget]`).
+    pub budget: Option<build::SizeBudget>,
+    /// Check the output for accessibility problems (`--check-a11y`).
+    pub check_a11y: bool,
+    /// Commands run before and after the build (`[hooks]`; none without `--hooks`).
+    pub hooks: config::HooksConfig,
 }
 
 impl BuildOptions {
-    fn from_args(a: &BuildArgs) -> Result<Self> {
-        if !a.input.exists() {
-            bail!("Input file does not exist: {}", a.input.display());
-        }
-        if !a.input.is_file() {
-            bail!("Input must be a file: {}", a.input.display());
+    fn from_args(a: &BuildArgs, home: Option<&Path>) -> Result<Self> {
+        let profile = match &a.build_profile {
+            Some(name) => {
+                let dir = match a.input.as_deref().map(Path::canonicalize) {
+                    Some(Ok(input)) => input.parent().map(Path::to_path_buf),
+                    _ => None,
+                };
+                let dir = match (dir, home) {
+                    (Some(dir), _) => dir,
+                    (None, Some(home)) => home.to_path_buf(),
+                    (None, None) => std::env::current_dir()?,
+                };
+                config::load_profile(&dir, name)?
+            }
+            None => config::BuildProfile::default(),
+        };
+        let output = a
+            .output
+            .clone()
+            .or(profile.output)
+            .unwrap_or_else(|| PathBuf::from("./site"));
+        let input = match &a.input {
+            _ if a.stdin && output != Path::new("-") => {
+                return Err(exit::ValidationFailed(
+                    "--stdin renders a single page to stdout; pass --output -".to_string(),
+                )
+                .into());
+            }
+            Some(input) if a.stdin => input.clone(),
+            None if a.stdin => PathBuf::from("stdin.md"),
+            None => vault_input(None, home)?,
+            Some(input) if input.to_str().is_some_and(remote::is_remote) => input.clone(),
+            Some(input) if input.to_str().is_some_and(build::is_store_url) => input.clone(),
+            Some(input) => {
+                let canonical = input
+                    .canonicalize()
+                    .with_context(|| format!("Input file does not exist: {}", input.display()))?;
+                if !canonical.is_file() {
+                    return Err(exit::ValidationFailed(format!(
+                        "Input must be a file: {}",
+                        input.display()
+                    ))
+                    .into());
+                }
+                canonical
+            }
+        };
+        let mut allow_remote = [profile.allow_remote, a.allow_remote.clone()].concat();
+        if let Some(host) = input.to_str().and_then(remote::url_host) {
+            allow_remote.push(host.to_string());
         }
+        let emit_model = match a
+            .emit_model
+            .or(a.emit_json.then_some(model::ModelFormat::Json))
+        {
+            Some(format) => Some(format),
+            None => profile
+                .emit_model
+                .as_deref()
+                .map(|name| {
+                    model::ModelFormat::from_str(name, true)
+                        .map_err(|_| anyhow!("Unknown emit_model '{name}' in build profile"))
+                })
+                .transpose()?,
+        };
+        let layout = match (
+            a.flat.then_some(OutputLayout::Flat).or(a.layout),
+            profile.layout,
+        ) {
+            (Some(layout), _) | (None, Some(layout)) => layout,
+            (None, None) if profile.flat => OutputLayout::Flat,
+            (None, None) => OutputLayout::default(),
+        };
+        // Folder whose `diaryx.toml` configures the output.
+        let config_dir = match (input.parent(), home) {
+            (Some(dir), _) if input.is_file() => dir.to_path_buf(),
+            (_, Some(home)) => home.to_path_buf(),
+            _ => std::env::current_dir()?,
+        };
+        let gh_pages = if a.gh_pages || profile.gh_pages {
+            let config = config::load_gh_pages(&config_dir)?;
+            Some(build::GhPages::new(
+                a.path_prefix.as_deref().or(config.path_prefix.as_deref()),
+                config.cname,
+            ))
+        } else {
+            None
+        };
+        let hosting_config = config::load_hosting(&config_dir)?;
+        let hosting = a
+            .host
+            .or(hosting_config.provider)
+            .map(|provider| build::Hosting {
+                provider,
+                clean_urls: hosting_config.clean_urls,
+                headers: hosting_config.headers,
+                redirects: hosting_config.redirects,
+            });
+        let sign = a.sign.clone().or(profile.sign);
+        let size_report = a.size_report || profile.size_report;
+        let budget = build::SizeBudget::from_config(config::load_budget(&config_dir)?)?;
+        let include_nonpublic = a.include_nonpublic || profile.include_nonpublic;
+        let audience = a.audience.clone().or(profile.audience);
+        let encrypt = if a.encrypt || profile.encrypt {
+            if !include_nonpublic && audience.is_none() {
+                bail!(
+                    "--encrypt only applies to non-public pages: add --include-nonpublic or --audience"
+                );
+            }
+            if output == Path::new("-") {
+                bail!("--encrypt cannot write to stdout (--output -); build a site folder instead");
+            }
+            Some(build::PageEncryption::from_env(config::load_encrypt(
+                &config_dir,
+            )?)?)
+        } else {
+            None
+        };
+        let hooks = {
+            let hooks = config::load_hooks(&config_dir)?;
+            if a.hooks {
+                hooks
+            } else {
+                // Hooks only run around one-off builds into a folder
+                let one_off = !a.watch && output != Path::new("-");
+                if !hooks.is_empty() && one_off && !a.quiet {
+                    eprintln!(
+                        "[hooks] not running the [hooks] of {}; pass --hooks to run them",
+                        hooks.dir.join(diaryx_core::schema::CONFIG_FILE).display()
+                    );
+                }
+                config::HooksConfig::default()
+            }
+        };
         Ok(Self {
-            input: a
-                .input
-                .canonicalize()
-                .with_context(|| "Failed to canonicalize input path")?,
-            output: a.output.clone(),
-            include_nonpublic: a.include_nonpublic,
-            emit_json: a.emit_json,
-            flat: a.flat,
-            verbose: a.verbose,
-            strict: a.strict,
-            no_default_css: a.no_default_css,
+            input,
+            output,
+            stdin: a.stdin,
+            watch: a.watch,
+            watch_events: a.watch_events.clone(),
+            allow_remote,
+            remote_cache: a.remote_cache.clone().or(profile.remote_cache),
+            identity: a.identity.clone().or(profile.identity),
+            include_nonpublic,
+            audience,
+            encrypt,
+            emit_model,
+            layout,
+            verbose: a.verbose || profile.verbose,
+            quiet: a.quiet,
+            output_format: a.output_format,
+            strict: a.strict || profile.strict,
+            no_default_css: a.no_default_css || profile.no_default_css,
+            profile: a.profile,
+            large_body_bytes: a.large_body_bytes.or(profile.large_body_bytes),
+            smart_punctuation: a.smart_punctuation || profile.smart_punctuation,
+            figures: a.figures || profile.figures,
+            pdf_previews: a.pdf_previews || profile.pdf_previews,
+            infer_dates: a.infer_dates || profile.infer_dates,
+            exclude: [profile.exclude, a.exclude.clone()].concat(),
+            since: a.since.clone().or(profile.since),
+            until: a.until.clone().or(profile.until),
+            tags: [profile.tags, a.tags.clone()].concat(),
+            drafts: a.drafts || profile.drafts,
+            future: a.future || profile.future,
+            as_root: a.as_root || profile.as_root,
+            changelog: a.changelog || profile.changelog,
+            no_version_check: a.no_version_check || profile.no_version_check,
+            pwa: a.pwa || profile.pwa,
+            graph_page: a.graph_page || profile.graph_page,
+            clean_urls: a.clean_urls || profile.clean_urls,
+            gh_pages,
+            hosting,
+            manifest: a.manifest || profile.manifest || sign.is_some() || size_report,
+            sign,
+            size_report,
+            budget,
+            check_a11y: a.check_a11y || profile.check_a11y,
+            hooks,
         })
     }
 }
@@ -127,20 +1564,52 @@ mod build_interface {
     }
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+fn main() -> ExitCode {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let recorder = usage_recorder(&matches, cli.vault.as_deref());
+    let result = run(cli);
+    if let Some(recorder) = recorder {
+        recorder.finish(result.is_ok());
+    }
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => exit::report(e),
+    }
+}
 
+/// Start logging this run when its vault has `[usage]` enabled. Only the subcommand is logged
+/// (with its action, `meta set`), never its arguments.
+fn usage_recorder(matches: &clap::ArgMatches, vault: Option<&str>) -> Option<usage::Recorder> {
+    let (name, sub) = matches.subcommand()?;
+    if name == "usage" {
+        return None;
+    }
+    let command = match sub.subcommand_name() {
+        Some(action) => format!("{name} {action}"),
+        None => name.to_string(),
+    };
+    let dir = match vault {
+        Some(spec) => registry::resolve(spec).ok()?,
+        None => std::env::current_dir().ok()?,
+    };
+    usage::Recorder::start(&command, &dir)
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let home = cli.vault.as_deref().map(registry::resolve).transpose()?;
+    let home = home.as_deref();
     match cli.command {
         Command::Build(args) => {
-            let opts = BuildOptions::from_args(&args)?;
+            let opts = BuildOptions::from_args(&args, home)?;
             if opts.verbose {
                 eprintln!(
-                    "[diaryx] Building site\n  input: {}\n  output: {}\n  include_nonpublic: {}\n  emit_json: {}\n  flat: {}\n  no_default_css: {}\n  strict: {}",
+                    "[diaryx] Building site\n  input: {}\n  output: {}\n  include_nonpublic: {}\n  emit_model: {:?}\n  layout: {}\n  no_default_css: {}\n  strict: {}",
                     opts.input.display(),
                     opts.output.display(),
                     opts.include_nonpublic,
-                    opts.emit_json,
-                    opts.flat,
+                    opts.emit_model,
+                    opts.layout.name(),
                     opts.no_default_css,
                     opts.strict
                 );
@@ -156,6 +1625,361 @@ fn main() -> Result<()> {
                 eprintln!("[diaryx] build complete");
             }
         }
+        Command::GenVault(args) => {
+            bench::run_gen_vault(&args.synth.to_options(), &args.output)?;
+        }
+        Command::Bench(args) => {
+            bench::run_bench(&args.synth.to_options(), args.iterations, args.keep)?;
+        }
+        Command::Api(args) => {
+            let input = vault_input(args.input.as_deref(), home)?;
+            api::run_api(&input, args.port, args.include_nonpublic, args.mcp)?;
+        }
+        Command::Export(args) => {
+            let input = vault_input(args.input.as_deref(), home)?;
+            export::run_export(&export::ExportOptions {
+                input,
+                format: args.format,
+                filter: args
+                    .filter
+                    .as_deref()
+                    .map(query::Filter::parse)
+                    .transpose()?,
+                include_nonpublic: args.include_nonpublic,
+                output: args.output,
+                deck: args.deck,
+                base_url: args.base_url,
+                flat: args.flat,
+                since: args.since,
+                lang: args.lang,
+            })?;
+        }
+        Command::Open(args) => {
+            let input = vault_input(args.input.as_deref(), home)?;
+            let layout = match args.layout {
+                _ if args.flat => OutputLayout::Flat,
+                layout => layout.unwrap_or_default(),
+            };
+            open::run_open(&args.query, &input, args.web, &args.output, layout)?;
+        }
+        Command::Replace(args) => {
+            // Only FILES: just those; otherwise the whole vault (plus any FILES).
+            let input = (args.input.is_some() || args.files.is_empty())
+                .then(|| vault_input(args.input.as_deref(), home))
+                .transpose()?;
+            let mut files = match &input {
+                Some(input) => vault::vault_files(input)?,
+                None => Vec::new(),
+            };
+            files.extend(args.files);
+            let root = match &input {
+                Some(input) => input.parent().unwrap_or(Path::new(".")).to_path_buf(),
+                None => vault::journal_root(&files[0]),
+            };
+            replace::run_replace(&replace::ReplaceOptions {
+                files,
+                from: args.from,
+                to: args.to,
+                regex: args.regex,
+                frontmatter: args.frontmatter,
+                mode: args.edit.mode(),
+                root,
+                command: invocation(),
+            })?;
+        }
+        Command::Split(args) => {
+            split::run_split(&args.file, args.by, args.edit.mode(), &invocation())?;
+        }
+        Command::Merge(args) => {
+            let input = vault_input(args.input.as_deref(), home)?;
+            merge::run_merge(&merge::MergeOptions {
+                sources: args.files,
+                into: args.into,
+                input,
+                title: args.title,
+                mode: args.edit.mode(),
+                command: invocation(),
+            })?;
+        }
+        Command::ReviewPage(args) => {
+            review::run_review(&review::ReviewOptions {
+                input: vault_input(args.input.as_deref(), home)?,
+                period: args.period,
+                include_nonpublic: args.include_nonpublic,
+                output: args.output,
+                add: args.add,
+                mode: args.edit.mode(),
+                command: invocation(),
+            })?;
+        }
+        Command::Rollover(args) => {
+            rollover::run_rollover(&rollover::RolloverOptions {
+                input: vault_input(args.input.as_deref(), home)?,
+                month: args.month,
+                mode: args.edit.mode(),
+                command: invocation(),
+            })?;
+        }
+        Command::Orphans(args) => {
+            orphans::run_orphans(&orphans::OrphansOptions {
+                input: vault_input(args.input.as_deref(), home)?,
+                exclude: args.exclude,
+                fix: args.fix,
+                into: args.into,
+                mode: args.edit.mode(),
+                command: invocation(),
+            })?;
+        }
+        Command::Validate(args) => {
+            validate::run_validate(&validate::ValidateOptions {
+                input: vault_input(args.input.as_deref(), home)?,
+                fix: args.fix,
+                mode: args.edit.mode(),
+                command: invocation(),
+            })?;
+        }
+        Command::Meta(args) => {
+            let (change, select) = match args.action {
+                MetaAction::Set {
+                    assignments,
+                    select,
+                } => (meta::MetaChange::Set(assignments), select),
+                MetaAction::Unset { keys, select } => (meta::MetaChange::Unset(keys), select),
+                MetaAction::Undo { input, force } => {
+                    return vault::undo_last(
+                        &state_root(input.as_deref(), vault::UNDO_DIR, home)?,
+                        force,
+                    );
+                }
+            };
+            let input = vault_input(select.input.as_deref(), home)?;
+            let filter = select
+                .filter
+                .as_deref()
+                .map(query::Filter::parse)
+                .transpose()?;
+            meta::run_meta(
+                &input,
+                &change,
+                filter.as_ref(),
+                select.edit.mode(),
+                &invocation(),
+            )?;
+        }
+        Command::Tag(args) => {
+            let (change, select) = match args.action {
+                TagAction::Add { tag, select } => (tag::TagChange::Add(tag), select),
+                TagAction::Remove { tag, select } => (tag::TagChange::Remove(tag), select),
+                TagAction::Rename { old, new, select } => {
+                    (tag::TagChange::Rename { from: old, to: new }, select)
+                }
+                TagAction::List {
+                    input,
+                    filter,
+                    counts,
+                    orphans,
+                } => {
+                    let input = vault_input(input.as_deref(), home)?;
+                    let filter = filter.as_deref().map(query::Filter::parse).transpose()?;
+                    let listing = if counts {
+                        tag::TagListing::Counts
+                    } else if orphans {
+                        tag::TagListing::Orphans
+                    } else {
+                        tag::TagListing::Names
+                    };
+                    return tag::run_tag_list(&input, filter.as_ref(), listing);
+                }
+            };
+            let input = vault_input(select.input.as_deref(), home)?;
+            let filter = select
+                .filter
+                .as_deref()
+                .map(query::Filter::parse)
+                .transpose()?;
+            tag::run_tag(
+                &input,
+                &change,
+                filter.as_ref(),
+                select.edit.mode(),
+                &invocation(),
+            )?;
+        }
+        Command::Undo(args) => {
+            let root = state_root(args.input.as_deref(), vault::UNDO_DIR, home)?;
+            vault::undo_last(&root, args.force)?;
+        }
+        Command::Trash(args) => match args.action {
+            TrashAction::List { input } => {
+                trash::run_list(&state_root(input.as_deref(), trash::TRASH_DIR, home)?)?
+            }
+            TrashAction::Restore {
+                which,
+                input,
+                force,
+            } => trash::run_restore(
+                &state_root(input.as_deref(), trash::TRASH_DIR, home)?,
+                &which,
+                force,
+            )?,
+            TrashAction::Empty { input, older_than } => trash::run_empty(
+                &state_root(input.as_deref(), trash::TRASH_DIR, home)?,
+                older_than.map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+            )?,
+        },
+        Command::Usage(args) => {
+            let input = vault_input(args.input.as_deref(), home)?;
+            usage::run_usage(
+                input.parent().unwrap_or(Path::new(".")),
+                args.since.as_deref(),
+                args.clear,
+            )?;
+        }
+        Command::Sync(args) => {
+            let input = vault_input(args.input.as_deref(), home)?;
+            let root = input.parent().unwrap_or(Path::new(".")).to_path_buf();
+            let config = config::load_sync(&root)?;
+            let remote = args.remote.or(config.remote).ok_or_else(|| {
+                exit::ValidationFailed(
+                    "No remote to sync with: pass --remote or set `remote` under [sync] in \
+                     diaryx.toml"
+                        .to_string(),
+                )
+            })?;
+            sync::run_sync(&sync::SyncOptions {
+                root,
+                backend: args
+                    .backend
+                    .or(config.backend)
+                    .unwrap_or(sync::Backend::Rsync),
+                remote,
+                exclude: config.exclude,
+                dry_run: args.dry_run,
+                force: args.force,
+            })?;
+        }
+        Command::Conflicts(args) => match args.action {
+            ConflictsAction::List { input } => {
+                let input = vault_input(input.as_deref(), home)?;
+                conflicts::run_list(input.parent().unwrap_or(Path::new(".")))?
+            }
+            ConflictsAction::Resolve {
+                files,
+                input,
+                body,
+                dry_run,
+            } => {
+                let input = vault_input(input.as_deref(), home)?;
+                conflicts::run_resolve(
+                    input.parent().unwrap_or(Path::new(".")),
+                    &files,
+                    body,
+                    dry_run,
+                    &invocation(),
+                )?
+            }
+        },
+        Command::Vault(args) => match args.action {
+            VaultAction::Add { name, path } => registry::run_add(&name, &path)?,
+            VaultAction::Remove { name } => registry::run_remove(&name)?,
+            VaultAction::List => registry::run_list()?,
+        },
+        Command::Dedupe(args) => {
+            let input = vault_input(args.input.as_deref(), home)?;
+            let filter = args
+                .filter
+                .as_deref()
+                .map(query::Filter::parse)
+                .transpose()?;
+            dedupe::run_dedupe(&input, filter.as_ref(), args.threshold)?;
+        }
+        Command::Snapshot(args) => {
+            let input = vault_input(args.input.as_deref(), home)?;
+            snapshot::run_snapshot(&snapshot::SnapshotOptions {
+                input,
+                output_dir: args.output,
+                include_nonpublic: args.include_nonpublic,
+            })?;
+        }
+        Command::VerifySnapshot(args) => {
+            snapshot::run_verify_snapshot(&args.archive, args.expect_root.as_deref())?;
+        }
+        #[cfg(feature = "lint-prose")]
+        Command::LintProse(args) => {
+            let mut files = match &args.input {
+                Some(input) => {
+                    let input = input.canonicalize().with_context(|| {
+                        format!("Input file does not exist: {}", input.display())
+                    })?;
+                    let filter = args
+                        .filter
+                        .as_deref()
+                        .map(query::Filter::parse)
+                        .transpose()?;
+                    query::select_entries(&input, filter.as_ref())?
+                        .into_iter()
+                        .map(|e| e.path)
+                        .collect()
+                }
+                None => Vec::new(),
+            };
+            files.extend(args.files);
+            if files.is_empty() {
+                bail!("Nothing to check: pass --input <root index> and/or entry FILES");
+            }
+            prose::run_lint_prose(&prose::ProseOptions {
+                files,
+                checkers: args.checkers,
+                dictionaries: args.dictionaries,
+                language: args.language,
+                json: args.json,
+            })?;
+        }
+        Command::Verify(args) => {
+            sign::run_verify(&args.dir, args.pubkey.as_deref())?;
+        }
+        Command::Check(args) => {
+            check::run_check(
+                args.file.as_deref(),
+                args.stdin,
+                args.vault_root
+                    .as_deref()
+                    .or(home.filter(|home| home.is_dir())),
+            )?;
+        }
+        Command::Ci(args) => {
+            ci::run_ci(
+                &vault_input(args.input.as_deref(), home)?,
+                args.output_format,
+                args.infer_dates,
+            )?;
+        }
+        Command::Schema(args) => {
+            schema::run_schema(
+                args.format,
+                home.unwrap_or(Path::new(".")),
+                args.output.as_deref(),
+            )?;
+        }
+        Command::Inspect(args) => {
+            model::run_inspect(&args.file, args.format, args.pointer.as_deref())?;
+        }
+        Command::Index(args) => {
+            index::run_index(
+                &vault_input(args.input.as_deref(), home)?,
+                &args.sqlite,
+                args.include_nonpublic,
+            )?;
+        }
+        Command::Graph(args) => {
+            graph::run_graph(
+                &vault_input(args.input.as_deref(), home)?,
+                args.format,
+                args.include_nonpublic,
+                args.output.as_deref(),
+            )?;
+        }
+        Command::Lsp => lsp::run_lsp()?,
     }
 
     Ok(())
@@ -0,0 +1,411 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde_yaml::{Mapping, Value};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::diagnostics::{LinkRef, is_graph_key, link_target, resolve_link, scan_links};
+use crate::vault::{
+    EditMode, FileEdit, apply_all_with_undo, parse_fields, rebase_target, rewrite_link_targets,
+    set_field, skipped_note, split_frontmatter, vault_files,
+};
+
+/// `merge`: combine several entries into one (the inverse of `split`).
+///
+/// Bodies are concatenated in argument order, separated by a thematic break. The frontmatter of
+/// the first entry is kept, except that `tags`, `visibility`, `contents` and `part_of` become the
+/// union over all entries, `created` the earliest and `updated` the latest value. Relative links
+/// are re-based on the target's directory, every link in the vault that pointed at a merged entry
+/// is redirected to the target (dropping duplicate `contents` entries this creates in parent
+/// indexes), and merged entries other than the target are deleted.
+pub struct MergeOptions {
+    pub sources: Vec<PathBuf>,
+    pub into: PathBuf,
+    /// Root index: the vault whose links get redirected.
+    pub input: PathBuf,
+    pub title: Option<String>,
+    pub mode: EditMode,
+    /// The invocation, as recorded in the undo journal.
+    pub command: String,
+}
+
+/// Separator placed between merged bodies.
+const SEPARATOR: &str = "\n---\n\n";
+
+pub fn run_merge(opts: &MergeOptions) -> Result<()> {
+    let (edits, redirected) = merge_edits(opts)?;
+    let root = opts.input.parent().unwrap_or(Path::new("."));
+    let applied = apply_all_with_undo(&edits, opts.mode, root, &opts.command)?;
+    println!(
+        "[merge] {}{} entries -> {} ({} file(s) with redirected links){}",
+        if opts.mode.dry_run { "(dry run) " } else { "" },
+        opts.sources.len(),
+        edits[0].path.display(),
+        redirected,
+        skipped_note(edits.len() - applied.len())
+    );
+    Ok(())
+}
+
+/// The edits of [`run_merge`]: the merged entry first, then the deletion of the other merged
+/// entries and the files whose links were redirected. Also returns the number of the latter.
+fn merge_edits(opts: &MergeOptions) -> Result<(Vec<FileEdit>, usize)> {
+    if opts.sources.len() < 2 {
+        bail!("Merge needs at least two entries");
+    }
+    let sources = opts
+        .sources
+        .iter()
+        .map(|p| {
+            p.canonicalize()
+                .with_context(|| format!("Entry does not exist: {}", p.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let into = match opts.into.canonicalize() {
+        Ok(existing) if sources.contains(&existing) => existing,
+        Ok(_) => bail!(
+            "{} already exists and is not one of the merged entries",
+            opts.into.display()
+        ),
+        Err(_) => {
+            let name = opts
+                .into
+                .file_name()
+                .with_context(|| format!("Invalid target: {}", opts.into.display()))?;
+            let parent = match opts.into.parent() {
+                Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            parent
+                .canonicalize()
+                .with_context(|| format!("Directory does not exist: {}", parent.display()))?
+                .join(name)
+        }
+    };
+    let into_dir = into.parent().unwrap_or(Path::new("/")).to_path_buf();
+
+    // Links to any merged entry now point at the target. `from_file` is where the link was
+    // written, `to_dir` where the text containing it ends up.
+    let redirect = |from_file: &Path, to_dir: &Path, link: &LinkRef| {
+        let key = link.key.as_deref();
+        let resolved = resolve_link(from_file, &link.target, key)?;
+        if !sources.contains(&resolved.path) {
+            return None;
+        }
+        let suffix = link.target.find('#').map_or("", |i| &link.target[i..]);
+        Some(link_target(to_dir, &into, key) + suffix)
+    };
+
+    let mut texts = Vec::new();
+    for path in &sources {
+        texts.push(
+            fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?,
+        );
+    }
+
+    // Combined body and frontmatter.
+    let mut body = String::new();
+    let mut all_fields = Vec::new();
+    for (path, text) in sources.iter().zip(&texts) {
+        let rebased = rewrite_link_targets(text, |link| {
+            redirect(path, &into_dir, link).or_else(|| rebase_target(link, path, &into_dir))
+        });
+        let (fm, part) = split_frontmatter(&rebased);
+        all_fields.push(parse_fields(fm));
+        let part = part.trim_matches('\n');
+        if part.is_empty() {
+            continue;
+        }
+        if !body.is_empty() {
+            body.push_str(SEPARATOR);
+        }
+        body.push_str(part);
+        body.push('\n');
+    }
+    let (first_fm, _) = split_frontmatter(&texts[0]);
+    let mut fm = rewrite_link_targets(first_fm, |link| {
+        redirect(&sources[0], &into_dir, link)
+            .or_else(|| rebase_target(link, &sources[0], &into_dir))
+    });
+    if let Some(title) = &opts.title {
+        fm = set_field(&fm, "title", &Value::String(title.clone()));
+    }
+    for key in ["tags", "visibility", "contents", "part_of"] {
+        if let Some(value) = union_field(&all_fields, key, &into, &into_dir) {
+            fm = set_field(&fm, key, &value);
+        }
+    }
+    for (key, latest) in [("created", false), ("updated", true)] {
+        if let Some(value) = pick_timestamp(&all_fields, key, latest) {
+            fm = set_field(&fm, key, &value);
+        }
+    }
+
+    let mut edits = vec![FileEdit {
+        path: into.clone(),
+        before: if sources.contains(&into) {
+            texts[sources.iter().position(|p| *p == into).unwrap_or(0)].clone()
+        } else {
+            String::new()
+        },
+        after: Some(fm + &body),
+    }];
+    for (path, text) in sources.iter().zip(&texts) {
+        if *path != into {
+            edits.push(FileEdit {
+                path: path.clone(),
+                before: text.clone(),
+                after: None,
+            });
+        }
+    }
+
+    // Incoming links from the rest of the vault.
+    let mut redirected = 0;
+    for file in vault_files(&opts.input)? {
+        let Ok(file) = file.canonicalize() else {
+            continue;
+        };
+        if sources.contains(&file) {
+            continue;
+        }
+        let before = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let after = dedupe_contents(&rewrite_link_targets(&before, |link| {
+            redirect(&file, file.parent().unwrap_or(Path::new("/")), link)
+        }));
+        if after != before {
+            redirected += 1;
+            edits.push(FileEdit {
+                path: file,
+                before,
+                after: Some(after),
+            });
+        }
+    }
+    Ok((edits, redirected))
+}
+
+/// Union of a list-or-scalar field over all entries, in first-seen order. Graph links are
+/// deduplicated by the file they resolve to, and links to the merged entry itself are dropped.
+/// `None` when no entry has the field.
+fn union_field(all: &[Mapping], key: &str, into: &Path, into_dir: &Path) -> Option<Value> {
+    let mut present = false;
+    let mut any_list = false;
+    let mut items: Vec<Value> = Vec::new();
+    let mut seen: Vec<PathBuf> = Vec::new();
+    for fields in all {
+        let values = match fields.get(key) {
+            Some(Value::Sequence(list)) => {
+                any_list = true;
+                list.clone()
+            }
+            Some(Value::Null) | None => continue,
+            Some(other) => vec![other.clone()],
+        };
+        present = true;
+        for value in values {
+            if is_graph_key(Some(key)) {
+                // Already re-based on the target directory.
+                let target = value
+                    .as_str()
+                    .and_then(|s| scan_links(s).into_iter().next())
+                    .and_then(|l| resolve_link(&into_dir.join("_"), &l.target, Some(key)));
+                if let Some(target) = target {
+                    if target.path == into || seen.contains(&target.path) {
+                        continue;
+                    }
+                    seen.push(target.path);
+                }
+            }
+            if !items.contains(&value) {
+                items.push(value);
+            }
+        }
+    }
+    if !present {
+        return None;
+    }
+    Some(match items.len() {
+        1 if !any_list => items.remove(0),
+        _ => Value::Sequence(items),
+    })
+}
+
+/// Earliest (`latest == false`) or latest timestamp value of `key`. RFC 3339 values are compared
+/// as instants; anything else falls back to string order.
+fn pick_timestamp(all: &[Mapping], key: &str, latest: bool) -> Option<Value> {
+    let values: Vec<&Value> = all.iter().filter_map(|f| f.get(key)).collect();
+    let as_key = |v: &Value| {
+        let s = v.as_str().map(str::to_string).unwrap_or_else(|| {
+            serde_yaml::to_string(v)
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        });
+        let instant = OffsetDateTime::parse(&s, &Rfc3339).ok();
+        // Parseable values rank ahead of unparseable ones in both directions.
+        (instant.is_some() == latest, instant, s)
+    };
+    let best = if latest {
+        values.into_iter().max_by_key(|v| as_key(v))
+    } else {
+        values.into_iter().min_by_key(|v| as_key(v))
+    };
+    best.cloned()
+}
+
+/// Drop `contents` entries that now resolve to the same file as an earlier one (a parent that
+/// listed several merged entries ends up listing the target once). Text is unchanged otherwise.
+fn dedupe_contents(text: &str) -> String {
+    let (fm, body) = split_frontmatter(text);
+    let fields = parse_fields(fm);
+    let Some(list) = fields.get("contents").and_then(Value::as_sequence) else {
+        return text.to_string();
+    };
+    let mut seen = Vec::new();
+    let mut kept = Vec::new();
+    for item in list {
+        let target = item
+            .as_str()
+            .and_then(|s| scan_links(s).into_iter().next().map(|l| l.target));
+        match target {
+            Some(t) if seen.contains(&t) => {}
+            Some(t) => {
+                seen.push(t);
+                kept.push(item.clone());
+            }
+            None => kept.push(item.clone()),
+        }
+    }
+    if kept.len() == list.len() {
+        return text.to_string();
+    }
+    set_field(fm, "contents", &Value::Sequence(kept)) + body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, text: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    fn fields_of(edit: &FileEdit) -> Mapping {
+        parse_fields(split_frontmatter(edit.after.as_deref().unwrap()).0)
+    }
+
+    #[test]
+    fn merged_entry_combines_bodies_and_frontmatter() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path().canonicalize().unwrap();
+        let index = write(
+            &root,
+            "index.md",
+            "---\ntitle: Home\nvisibility: public\nthis_file_is_root_index: true\ncontents:\n  - \"[A](a.md)\"\n  - \"[B](sub/b.md)\"\n  - \"[C](c.md)\"\n---\n",
+        );
+        let a = write(
+            &root,
+            "a.md",
+            "---\ntitle: A\nvisibility: public\ntags: [x]\ncreated: 2025-01-02T09:00:00+02:00\nupdated: 2025-01-05T00:00:00Z\npart_of: \"[Home](index.md)\"\n---\nAlpha, see ![map](img/a.png).\n",
+        );
+        let b = write(
+            &root,
+            "sub/b.md",
+            "---\ntitle: B\nvisibility: [public, family]\ntags: [y, x]\ncreated: 2025-01-02T08:00:00Z\nupdated: 2025-02-01T00:00:00Z\npart_of: \"[Home](../index.md)\"\n---\n\nBeta, like [A](../a.md).\n\n",
+        );
+        let c = write(
+            &root,
+            "c.md",
+            "---\ntitle: C\nvisibility: public\npart_of: \"[Home](index.md)\"\n---\nSee [B](sub/b.md#end) and [A](a.md).\n",
+        );
+
+        let (edits, redirected) = merge_edits(&MergeOptions {
+            sources: vec![b.clone(), a.clone()],
+            into: a.clone(),
+            input: index.clone(),
+            title: None,
+            mode: EditMode::default(),
+            command: "merge".to_string(),
+        })
+        .unwrap();
+        let paths: Vec<&Path> = edits.iter().map(|e| e.path.as_path()).collect();
+        assert_eq!(paths, [a.as_path(), &b, &index, &c]);
+        assert_eq!(redirected, 2);
+
+        // Bodies in argument order, links re-based on (and redirected to) the target.
+        let merged = edits[0].after.as_deref().unwrap();
+        assert_eq!(
+            split_frontmatter(merged).1,
+            "Beta, like [A](a.md).\n\n---\n\nAlpha, see ![map](img/a.png).\n"
+        );
+        // The first entry's frontmatter, with unions and the outer timestamps.
+        let fields = fields_of(&edits[0]);
+        assert_eq!(fields.get("title").and_then(Value::as_str), Some("B"));
+        assert_eq!(
+            serde_yaml::to_string(fields.get("tags").unwrap()).unwrap(),
+            "- y\n- x\n"
+        );
+        assert_eq!(
+            serde_yaml::to_string(fields.get("visibility").unwrap()).unwrap(),
+            "- public\n- family\n"
+        );
+        assert_eq!(
+            fields.get("part_of").and_then(Value::as_str),
+            Some("[Home](index.md)")
+        );
+        // Compared as instants: 07:00Z is earlier than 08:00Z.
+        assert_eq!(
+            fields.get("created").and_then(Value::as_str),
+            Some("2025-01-02T09:00:00+02:00")
+        );
+        assert_eq!(
+            fields.get("updated").and_then(Value::as_str),
+            Some("2025-02-01T00:00:00Z")
+        );
+
+        assert!(edits[1].after.is_none());
+        // The parent lists the target once, in place of both merged entries.
+        let contents: Vec<String> = fields_of(&edits[2])
+            .get("contents")
+            .and_then(Value::as_sequence)
+            .unwrap()
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect();
+        assert_eq!(contents, ["[A](a.md)", "[C](c.md)"]);
+        assert_eq!(
+            split_frontmatter(edits[3].after.as_deref().unwrap()).1,
+            "See [B](a.md#end) and [A](a.md).\n"
+        );
+    }
+
+    #[test]
+    fn merging_needs_two_entries_and_a_free_target() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path().canonicalize().unwrap();
+        let a = write(&root, "a.md", "A\n");
+        let b = write(&root, "b.md", "B\n");
+        let other = write(&root, "other.md", "Other\n");
+        let opts = |sources: Vec<PathBuf>, into: &Path| MergeOptions {
+            sources,
+            into: into.to_path_buf(),
+            input: root.join("index.md"),
+            title: None,
+            mode: EditMode::default(),
+            command: "merge".to_string(),
+        };
+        let err = merge_edits(&opts(vec![a.clone()], &a)).unwrap_err();
+        assert!(err.to_string().contains("at least two"));
+        let err = merge_edits(&opts(vec![a, b], &other)).unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+    }
+}
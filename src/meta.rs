@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use serde_yaml::Value;
+
+use crate::query::{Filter, select_entries};
+use crate::vault::{
+    EditMode, FileEdit, apply_edits_with_undo, parse_fields, remove_field, set_field,
+    split_frontmatter,
+};
+
+/// A frontmatter mutation applied by `meta`.
+pub enum MetaChange {
+    /// `key=value` pairs; values are parsed as YAML (`draft=true`, `tags=[a, b]`).
+    Set(Vec<(String, Value)>),
+    Unset(Vec<String>),
+}
+
+/// Parse `key=value` into a key and a YAML value (plain text when it is not valid YAML).
+pub fn parse_assignment(s: &str) -> std::result::Result<(String, Value), String> {
+    let (key, raw) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected key=value, got '{s}'"))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("missing key in '{s}'"));
+    }
+    let value = serde_yaml::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+    Ok((key.to_string(), value))
+}
+
+/// `meta set` / `meta unset`: apply a frontmatter change to every entry of the vault rooted at
+/// `input` that matches `filter` (all entries without one).
+///
+/// Only the touched fields are rewritten; entries already in the requested state are left
+/// byte-for-byte alone. Applied changes are journaled for `undo`.
+pub fn run_meta(
+    input: &Path,
+    change: &MetaChange,
+    filter: Option<&Filter>,
+    mode: EditMode,
+    command: &str,
+) -> Result<()> {
+    let root = input.parent().unwrap_or(Path::new("."));
+    let selected = select_entries(input, filter)?;
+    if selected.is_empty() && filter.is_some() {
+        bail!("No entries match the filter");
+    }
+    let mut edits = Vec::new();
+    for entry in &selected {
+        let (fm, body) = split_frontmatter(&entry.text);
+        let fields = parse_fields(fm);
+        let mut new_fm = fm.to_string();
+        match change {
+            MetaChange::Set(pairs) => {
+                for (key, value) in pairs {
+                    if fields.get(key.as_str()) != Some(value) {
+                        new_fm = set_field(&new_fm, key, value);
+                    }
+                }
+            }
+            MetaChange::Unset(keys) => {
+                for key in keys {
+                    if fields.contains_key(key.as_str()) {
+                        new_fm = remove_field(&new_fm, key);
+                    }
+                }
+            }
+        }
+        if new_fm != fm {
+            edits.push(FileEdit {
+                path: entry.path.clone(),
+                before: entry.text.clone(),
+                after: Some(new_fm + body),
+            });
+        }
+    }
+    let applied = apply_edits_with_undo(&edits, mode, root, command)?;
+    println!(
+        "[meta] {}{} of {} matching entr{} changed",
+        if mode.dry_run { "(dry run) " } else { "" },
+        applied.len(),
+        selected.len(),
+        if selected.len() == 1 { "y" } else { "ies" }
+    );
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::ValueEnum;
+use serde_json::Value;
+
+/// Encodings of the build model (`build --emit-model`, `inspect`).
+///
+/// Every encoding holds the same document as `diaryx-data.json`. JSON is readable but grows
+/// large for big vaults; CBOR (`diaryx-data.cbor`) and MessagePack (`diaryx-data.msgpack`)
+/// carry the same structure compactly for downstream tools. `diaryx inspect` turns any of them
+/// back into pretty-printed JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ModelFormat {
+    Json,
+    Cbor,
+    Msgpack,
+}
+
+impl ModelFormat {
+    /// Site-relative path the model is written to.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ModelFormat::Json => diaryx_core::site::JSON_MODEL_PATH,
+            ModelFormat::Cbor => "diaryx-data.cbor",
+            ModelFormat::Msgpack => "diaryx-data.msgpack",
+        }
+    }
+
+    /// Format of a model file, from its extension.
+    fn of_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "json" => Some(ModelFormat::Json),
+            "cbor" => Some(ModelFormat::Cbor),
+            "msgpack" | "mpk" => Some(ModelFormat::Msgpack),
+            _ => None,
+        }
+    }
+
+    fn encode(self, model: &Value) -> Result<Vec<u8>> {
+        Ok(match self {
+            ModelFormat::Json => serde_json::to_vec_pretty(model)?,
+            ModelFormat::Cbor => serde_cbor::to_vec(model)?,
+            ModelFormat::Msgpack => rmp_serde::to_vec_named(model)?,
+        })
+    }
+
+    fn decode(self, bytes: &[u8]) -> Result<Value> {
+        Ok(match self {
+            ModelFormat::Json => serde_json::from_slice(bytes)?,
+            ModelFormat::Cbor => serde_cbor::from_slice(bytes)?,
+            ModelFormat::Msgpack => rmp_serde::from_slice(bytes)?,
+        })
+    }
+}
+
+/// Write `model` into the site folder `output`; returns the number of bytes written.
+pub(crate) fn write_model(output: &Path, format: ModelFormat, model: &Value) -> Result<usize> {
+    let bytes = format.encode(model)?;
+    fs::write(output.join(format.file_name()), &bytes)
+        .with_context(|| format!("Failed writing {}", format.file_name()))?;
+    Ok(bytes.len())
+}
+
+/// Print a model file as pretty JSON, or only the part at the JSON Pointer `pointer`
+/// (e.g. `/pages/0/frontmatter`). The format comes from the extension unless given.
+pub fn run_inspect(path: &Path, format: Option<ModelFormat>, pointer: Option<&str>) -> Result<()> {
+    let format = format
+        .or_else(|| ModelFormat::of_path(path))
+        .ok_or_else(|| {
+            anyhow!(
+                "Cannot tell the format of {} from its extension; pass --format",
+                path.display()
+            )
+        })?;
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let model = format
+        .decode(&bytes)
+        .with_context(|| format!("{} is not a {format:?} model", path.display()))?;
+    let shown = match pointer {
+        Some(pointer) => match model.pointer(pointer) {
+            Some(value) => value,
+            None => bail!("Nothing at {pointer} in {}", path.display()),
+        },
+        None => &model,
+    };
+    println!("{}", serde_json::to_string_pretty(shown)?);
+    Ok(())
+}
@@ -0,0 +1,202 @@
+/*!
+ * File name conventions for new entries: the `[naming] pattern` of a vault's `diaryx.toml`.
+ *
+ * A pattern is a file name with placeholders: `{slug}` (the title, slugified), `{date}`
+ * (`2025-08-25`), `{year}`, `{month}` and `{day}`, taken from the entry's `created` date. The
+ * default `{slug}.md` is what `split` always used. A name already taken gets a numeric suffix
+ * (`walk-2.md`, `walk-3.md`, ...), so creating an entry never overwrites another.
+ *
+ * With `lint = true`, `diaryx ci` also reports entries whose file names do not follow the
+ * pattern (indexes are exempt).
+ */
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use time::Date;
+
+use crate::config::load_naming;
+use crate::vault::slugify;
+
+const DEFAULT_PATTERN: &str = "{slug}.md";
+const PLACEHOLDERS: [&str; 5] = ["{slug}", "{date}", "{year}", "{month}", "{day}"];
+
+/// The naming convention of a vault.
+#[derive(Debug, Clone)]
+pub struct Naming {
+    pattern: String,
+    /// Matches the file names following `pattern` (see [`Naming::check`]).
+    regex: Regex,
+    /// Whether `ci` checks existing entries.
+    pub lint: bool,
+}
+
+impl Naming {
+    /// The convention of the vault `dir` is in (the default without a `[naming]` table).
+    pub fn load(dir: &Path) -> Result<Naming> {
+        let config = load_naming(dir)?;
+        let pattern = config.pattern.as_deref().unwrap_or(DEFAULT_PATTERN);
+        Ok(Naming {
+            regex: Naming::check_pattern(pattern)?,
+            pattern: pattern.to_string(),
+            lint: config.lint,
+        })
+    }
+
+    /// Validate `pattern`, returning the regex its file names match.
+    fn check_pattern(pattern: &str) -> Result<Regex> {
+        if !pattern.ends_with(".md") || pattern.contains(['/', '\\']) {
+            bail!("[naming] pattern '{pattern}' must be a file name ending in .md");
+        }
+        if !pattern.contains("{slug}") {
+            bail!(
+                "[naming] pattern '{pattern}' must contain {{slug}}, so titles tell entries apart"
+            );
+        }
+        let mut regex = String::from("^");
+        let mut seen = HashSet::new();
+        let mut rest = pattern.strip_suffix(".md").unwrap_or(pattern);
+        while let Some(start) = rest.find(['{', '}']) {
+            regex.push_str(&regex::escape(&rest[..start]));
+            let placeholder = rest[start..]
+                .find('}')
+                .map(|end| &rest[start..=start + end])
+                .filter(|p| PLACEHOLDERS.contains(p));
+            let Some(placeholder) = placeholder else {
+                bail!(
+                    "[naming] pattern '{pattern}' has an unknown placeholder (known: {})",
+                    PLACEHOLDERS.join(", ")
+                );
+            };
+            if !seen.insert(placeholder) {
+                bail!("[naming] pattern '{pattern}' uses {placeholder} more than once");
+            }
+            regex.push_str(match placeholder {
+                "{slug}" => r"[a-z0-9]+(?:-[a-z0-9]+)*?",
+                "{date}" => r"(?P<date>\d{4}-\d{2}-\d{2})",
+                "{year}" => r"(?P<year>\d{4})",
+                "{month}" => r"(?P<month>\d{2})",
+                _ => r"(?P<day>\d{2})",
+            });
+            rest = &rest[start + placeholder.len()..];
+        }
+        regex.push_str(&regex::escape(rest));
+        regex.push_str(r"(?:-\d+)?\.md$");
+        Regex::new(&regex).with_context(|| format!("[naming] pattern '{pattern}' is invalid"))
+    }
+
+    /// File name for an entry titled `title`, created on `created` (today when `None`), without
+    /// a collision suffix.
+    pub fn file_name(&self, title: &str, created: Option<Date>) -> String {
+        let date = created.unwrap_or_else(|| time::OffsetDateTime::now_utc().date());
+        self.pattern
+            .replace("{slug}", &slugify(title))
+            .replace(
+                "{date}",
+                &format!(
+                    "{}-{:02}-{:02}",
+                    date.year(),
+                    date.month() as u8,
+                    date.day()
+                ),
+            )
+            .replace("{year}", &date.year().to_string())
+            .replace("{month}", &format!("{:02}", date.month() as u8))
+            .replace("{day}", &format!("{:02}", date.day()))
+    }
+
+    /// Path in `dir` for a new entry titled `title`: [`Naming::file_name`], suffixed `-2`, `-3`,
+    /// ... until it names neither an existing file nor one in `taken` (which it is added to).
+    pub fn entry_path(
+        &self,
+        dir: &Path,
+        title: &str,
+        created: Option<Date>,
+        taken: &mut HashSet<PathBuf>,
+    ) -> PathBuf {
+        let name = self.file_name(title, created);
+        let stem = name.strip_suffix(".md").unwrap_or(&name);
+        let mut path = dir.join(&name);
+        let mut n = 2;
+        while path.exists() || taken.contains(&path) {
+            path = dir.join(format!("{stem}-{n}.md"));
+            n += 1;
+        }
+        taken.insert(path.clone());
+        path
+    }
+
+    /// Why the file name of an entry created on `created` does not follow the pattern (`None`
+    /// when it does). A collision suffix is allowed; date placeholders must match `created`.
+    pub fn check(&self, file_name: &str, created: Option<Date>) -> Option<String> {
+        let regex = &self.regex;
+        let Some(captures) = regex.captures(file_name) else {
+            return Some(format!("{file_name} does not follow {}", self.pattern));
+        };
+        let created = created?;
+        let expected = self.file_name("x", Some(created));
+        let expected = regex.captures(&expected)?;
+        for part in ["date", "year", "month", "day"] {
+            if let (Some(have), Some(want)) = (captures.name(part), expected.name(part))
+                && have.as_str() != want.as_str()
+            {
+                return Some(format!(
+                    "{file_name}: {{{part}}} is {} but the entry was created on {created}",
+                    have.as_str()
+                ));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naming(pattern: &str) -> Result<Naming> {
+        Ok(Naming {
+            regex: Naming::check_pattern(pattern)?,
+            pattern: pattern.to_string(),
+            lint: true,
+        })
+    }
+
+    #[test]
+    fn bad_patterns_are_config_errors() {
+        for (pattern, problem) in [
+            ("{date}.md", "must contain {slug}"),
+            ("{slug}.txt", "ending in .md"),
+            ("{slug}-{week}.md", "unknown placeholder"),
+            ("{slug}-{slug.md", "unknown placeholder"),
+            ("{date}-{slug}-{date}.md", "uses {date} more than once"),
+            ("{slug}-{slug}.md", "uses {slug} more than once"),
+        ] {
+            let err = naming(pattern).unwrap_err().to_string();
+            assert!(err.contains(problem), "{pattern}: {err}");
+        }
+    }
+
+    #[test]
+    fn names_are_checked_against_the_pattern_and_created_date() {
+        let naming = naming("{date}-{slug}.md").unwrap();
+        let created = Date::from_calendar_date(2025, time::Month::August, 25).ok();
+        assert_eq!(naming.file_name("A Walk", created), "2025-08-25-a-walk.md");
+        assert_eq!(naming.check("2025-08-25-a-walk.md", created), None);
+        assert_eq!(naming.check("2025-08-25-a-walk-2.md", created), None);
+        assert!(
+            naming
+                .check("a-walk.md", created)
+                .unwrap()
+                .contains("does not follow")
+        );
+        assert!(
+            naming
+                .check("2025-08-24-a-walk.md", created)
+                .unwrap()
+                .contains("{date} is 2025-08-24")
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result, anyhow, bail};
+use diaryx_core::{CoreBuildOptions, OutputLayout, PageOutput, SiteShape, build_site_streaming};
+
+use crate::build::RealFs;
+
+/// `open`: find a page by slug, title or alias and open it.
+///
+/// The vault graph is walked from `input` (non-public pages included, since opening a source file
+/// is a local action). By default the source Markdown file is opened in `$VISUAL` / `$EDITOR`;
+/// with `web` the page's HTML in an already built site under `output` is opened in the browser.
+pub fn run_open(
+    query: &str,
+    input: &Path,
+    web: bool,
+    output: &Path,
+    layout: OutputLayout,
+) -> Result<()> {
+    let entry = input
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?;
+    let opts = CoreBuildOptions {
+        include_nonpublic: true,
+        layout,
+        ..CoreBuildOptions::default()
+    };
+    let mut pages = Vec::new();
+    build_site_streaming(entry, opts, &RealFs, |_: &SiteShape, page: PageOutput| {
+        pages.push(Candidate::new(page));
+        Ok(())
+    })
+    .with_context(|| "Core build failed")?;
+
+    let page = resolve(&pages, query)?;
+    if web {
+        let html = output.join(&page.output_path);
+        if !html.is_file() {
+            bail!(
+                "No built page at {} (run `diaryx build` first, with matching --output/--layout)",
+                html.display()
+            );
+        }
+        open_in_browser(&html)
+    } else {
+        open_in_editor(&page.source_path)
+    }
+}
+
+/// What `open` needs to know about a page.
+struct Candidate {
+    id: String,
+    title: String,
+    aliases: Vec<String>,
+    source_path: PathBuf,
+    /// Site-relative path of the built HTML.
+    output_path: String,
+}
+
+impl Candidate {
+    fn new(page: PageOutput) -> Self {
+        let aliases = page
+            .frontmatter
+            .get("aliases")
+            .and_then(|v| v.as_sequence())
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|a| a.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            output_path: page.path.clone(),
+            id: page.id,
+            title: page.title,
+            aliases,
+            source_path: PathBuf::from(page.source_path),
+        }
+    }
+}
+
+/// Exact slug, then exact title or alias (case-insensitive), then a unique partial match on slug,
+/// title or alias. Several equally good matches are an error listing them.
+fn resolve<'a>(pages: &'a [Candidate], query: &str) -> Result<&'a Candidate> {
+    if let Some(page) = pages.iter().find(|p| p.id == query) {
+        return Ok(page);
+    }
+    let q = query.to_lowercase();
+    let exact: Vec<&Candidate> = pages
+        .iter()
+        .filter(|p| p.title.to_lowercase() == q || p.aliases.iter().any(|a| a.to_lowercase() == q))
+        .collect();
+    let matches = if exact.is_empty() {
+        pages
+            .iter()
+            .filter(|p| {
+                p.id.contains(&q)
+                    || p.title.to_lowercase().contains(&q)
+                    || p.aliases.iter().any(|a| a.to_lowercase().contains(&q))
+            })
+            .collect()
+    } else {
+        exact
+    };
+    match matches.as_slice() {
+        [] => bail!("No page matches '{query}'"),
+        [page] => Ok(page),
+        many => {
+            let list: Vec<String> = many
+                .iter()
+                .take(10)
+                .map(|p| format!("  {} ({})", p.id, p.title))
+                .collect();
+            bail!(
+                "'{query}' matches {} pages; use a slug:\n{}",
+                many.len(),
+                list.join("\n")
+            )
+        }
+    }
+}
+
+fn open_in_editor(path: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .ok_or_else(|| anyhow!("Set $EDITOR (or $VISUAL) to open source files"))?;
+    // Allow editors with arguments, e.g. `code --wait`.
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or_default();
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to launch editor '{editor}'"))?;
+    if !status.success() {
+        bail!("Editor '{editor}' exited with {status}");
+    }
+    Ok(())
+}
+
+fn open_in_browser(path: &Path) -> Result<()> {
+    let mut cmd = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.args(["/C", "start", ""]);
+        c
+    } else {
+        Command::new("xdg-open")
+    };
+    let status = cmd
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to open {} in a browser", path.display()))?;
+    if !status.success() {
+        bail!("Browser launcher exited with {status}");
+    }
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde_yaml::Value;
+
+use crate::build::RealFs;
+use crate::diagnostics::{link_target, normalize};
+use crate::vault::{
+    EditMode, FileEdit, apply_all_with_undo, entry_title, parse_fields, set_field, skipped_note,
+    split_frontmatter,
+};
+
+pub struct OrphansOptions {
+    /// Root index of the vault.
+    pub input: PathBuf,
+    /// Patterns of files that are not entries (as `build --exclude`).
+    pub exclude: Vec<String>,
+    /// Append the orphans to `into`'s `contents`.
+    pub fix: bool,
+    /// Index the orphans are appended to; the root index when `None`.
+    pub into: Option<PathBuf>,
+    pub mode: EditMode,
+    pub command: String,
+}
+
+/// `orphans`: list the entries under the root index's folder that its `contents` traversal never
+/// reaches (the `orphan` warnings of a build). With `fix`, append them to an index's `contents`
+/// and point the `part_of` of those that have none at it; the changes are journaled for `undo`.
+pub fn run_orphans(opts: &OrphansOptions) -> Result<()> {
+    let root = opts.input.parent().unwrap_or(Path::new("."));
+    let entry = opts
+        .input
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?;
+    let orphans: Vec<PathBuf> = diaryx_core::find_orphans(entry, &opts.exclude, &RealFs)?
+        .into_iter()
+        .map(|path| normalize(Path::new(&path)))
+        .collect();
+    if orphans.is_empty() {
+        println!("[orphans] every entry is reachable from the root index");
+        return Ok(());
+    }
+    if !opts.fix {
+        for path in &orphans {
+            println!("{}", path.strip_prefix(root).unwrap_or(path).display());
+        }
+        println!(
+            "[orphans] {} entr{} not reachable from the root index (--fix to list them in its contents)",
+            orphans.len(),
+            if orphans.len() == 1 { "y" } else { "ies" }
+        );
+        return Ok(());
+    }
+
+    let index = match &opts.into {
+        Some(into) => normalize(
+            &std::path::absolute(into)
+                .with_context(|| format!("Invalid index path {}", into.display()))?,
+        ),
+        None => normalize(&opts.input),
+    };
+    if orphans.contains(&index) {
+        bail!(
+            "{} is itself an orphan; list it in a reachable index first, or fix into another index",
+            index.display()
+        );
+    }
+    let index_dir = index.parent().unwrap_or(Path::new(""));
+    let before = fs::read_to_string(&index)
+        .with_context(|| format!("Failed to read {}", index.display()))?;
+    let (fm, body) = split_frontmatter(&before);
+    let fields = parse_fields(fm);
+    let index_title = entry_title(&fields, &index);
+    let mut contents: Vec<Value> = fields
+        .get("contents")
+        .and_then(Value::as_sequence)
+        .cloned()
+        .unwrap_or_default();
+
+    let mut edits = Vec::new();
+    for path in &orphans {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let (orphan_fm, orphan_body) = split_frontmatter(&text);
+        let orphan_fields = parse_fields(orphan_fm);
+        contents.push(Value::String(format!(
+            "[{}]({})",
+            entry_title(&orphan_fields, path),
+            link_target(index_dir, path, Some("contents"))
+        )));
+        if !orphan_fields.contains_key("part_of") {
+            let part_of = format!(
+                "[{index_title}]({})",
+                link_target(
+                    path.parent().unwrap_or(Path::new("")),
+                    &index,
+                    Some("part_of")
+                )
+            );
+            edits.push(FileEdit {
+                path: path.clone(),
+                after: Some(set_field(orphan_fm, "part_of", &Value::String(part_of)) + orphan_body),
+                before: text,
+            });
+        }
+    }
+    edits.insert(
+        0,
+        FileEdit {
+            path: index.clone(),
+            after: Some(set_field(fm, "contents", &Value::Sequence(contents)) + body),
+            before,
+        },
+    );
+
+    let applied = apply_all_with_undo(&edits, opts.mode, root, &opts.command)?;
+    println!(
+        "[orphans] {}{} entr{} added to {} ({} file(s) changed){}",
+        if opts.mode.dry_run { "(dry run) " } else { "" },
+        orphans.len(),
+        if orphans.len() == 1 { "y" } else { "ies" },
+        index.strip_prefix(root).unwrap_or(&index).display(),
+        applied.len(),
+        skipped_note(edits.len() - applied.len())
+    );
+    Ok(())
+}
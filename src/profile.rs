@@ -0,0 +1,151 @@
+#[cfg(feature = "heap-profile")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Build profiling support (`build --profile`).
+///
+/// Heap figures come from a thin counting wrapper around the system allocator, installed as the
+/// binary's global allocator with the `heap-profile` feature. It costs two relaxed atomics per
+/// (de)allocation in every command, so it is left out of default builds, whose profile only
+/// reports times.
+#[cfg(feature = "heap-profile")]
+pub struct CountingAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether heap figures are counted (the `heap-profile` feature).
+const COUNTED: bool = cfg!(feature = "heap-profile");
+
+#[cfg(feature = "heap-profile")]
+fn track_grow(bytes: usize) {
+    let now = CURRENT.fetch_add(bytes, Ordering::Relaxed) + bytes;
+    PEAK.fetch_max(now, Ordering::Relaxed);
+}
+
+#[cfg(feature = "heap-profile")]
+fn track_shrink(bytes: usize) {
+    CURRENT.fetch_sub(bytes, Ordering::Relaxed);
+}
+
+#[cfg(feature = "heap-profile")]
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            track_grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            track_grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        track_shrink(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            if new_size >= layout.size() {
+                track_grow(new_size - layout.size());
+            } else {
+                track_shrink(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+/// Collects per-phase wall time and peak heap usage; prints a table to stderr when enabled.
+pub struct Profiler {
+    enabled: bool,
+    start: Instant,
+    last: Instant,
+    baseline_heap: usize,
+    phases: Vec<(&'static str, Duration, usize)>,
+}
+
+impl Profiler {
+    pub fn new(enabled: bool) -> Self {
+        let now = Instant::now();
+        let baseline_heap = CURRENT.load(Ordering::Relaxed);
+        if enabled {
+            PEAK.store(baseline_heap, Ordering::Relaxed);
+        }
+        Self {
+            enabled,
+            start: now,
+            last: now,
+            baseline_heap,
+            phases: Vec::new(),
+        }
+    }
+
+    /// Close the current phase under `name` and start timing the next one.
+    pub fn phase(&mut self, name: &'static str) {
+        if !self.enabled {
+            return;
+        }
+        let now = Instant::now();
+        let peak = PEAK.load(Ordering::Relaxed);
+        self.phases.push((name, now - self.last, peak));
+        // Peak is tracked per phase so each row shows that phase's own high-water mark.
+        PEAK.store(CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+        self.last = now;
+    }
+
+    pub fn report(&self) {
+        if !self.enabled {
+            return;
+        }
+        if !COUNTED {
+            for (name, took, _) in &self.phases {
+                eprintln!("[profile] {name:<24} {took:>12.2?}");
+            }
+            eprintln!("[profile] {:<24} {:>12.2?}", "total", self.start.elapsed());
+            eprintln!("[profile] (peak heap needs a build with `--features heap-profile`)");
+            return;
+        }
+        eprintln!(
+            "[profile] {:<24} {:>12} {:>14}",
+            "phase", "time", "peak heap"
+        );
+        let mut overall_peak = 0usize;
+        for (name, took, peak) in &self.phases {
+            overall_peak = overall_peak.max(*peak);
+            eprintln!(
+                "[profile] {:<24} {:>12.2?} {:>14}",
+                name,
+                took,
+                format_bytes(peak.saturating_sub(self.baseline_heap))
+            );
+        }
+        eprintln!(
+            "[profile] {:<24} {:>12.2?} {:>14}",
+            "total",
+            self.start.elapsed(),
+            format_bytes(overall_peak.saturating_sub(self.baseline_heap))
+        );
+    }
+}
+
+pub(crate) fn format_bytes(bytes: usize) -> String {
+    const KIB: f64 = 1024.0;
+    let b = bytes as f64;
+    if b >= KIB * KIB {
+        format!("{:.1} MiB", b / (KIB * KIB))
+    } else if b >= KIB {
+        format!("{:.1} KiB", b / KIB)
+    } else {
+        format!("{} B", bytes)
+    }
+}
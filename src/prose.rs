@@ -0,0 +1,408 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value as Json;
+
+use crate::vault::split_frontmatter;
+
+/// One prose problem. Lines and columns are 1-based; columns and `length` count characters.
+#[derive(Debug, Serialize)]
+pub struct ProseIssue {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub checker: &'static str,
+    pub rule: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+}
+
+/// A prose checker run by `lint-prose`. Checkers get the raw file text; [`mask_markup`] gives
+/// them the prose with markup blanked out at unchanged positions.
+pub trait ProseChecker {
+    fn name(&self) -> &'static str;
+    fn check(&self, path: &Path, text: &str) -> Result<Vec<ProseIssue>>;
+}
+
+/// Checkers selectable with `--checker`.
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum CheckerKind {
+    /// Built-in dictionary spellcheck.
+    Spell,
+    /// The `vale` CLI (uses the vault's `.vale.ini`).
+    Vale,
+    /// The `languagetool` CLI.
+    Languagetool,
+}
+
+pub struct ProseOptions {
+    pub files: Vec<PathBuf>,
+    pub checkers: Vec<CheckerKind>,
+    /// Word lists for `spell` (one word per line).
+    pub dictionaries: Vec<PathBuf>,
+    /// Language code passed to LanguageTool.
+    pub language: String,
+    pub json: bool,
+}
+
+#[derive(Serialize)]
+struct FileReport<'a> {
+    path: &'a Path,
+    issues: Vec<ProseIssue>,
+}
+
+/// `lint-prose`: run the selected checkers over each file and report issues per entry, as
+/// `path:line:column: [checker/rule] message` lines or (with `json`) one JSON array of reports.
+pub fn run_lint_prose(opts: &ProseOptions) -> Result<()> {
+    let mut checkers: Vec<Box<dyn ProseChecker>> = Vec::new();
+    for kind in &opts.checkers {
+        checkers.push(match kind {
+            CheckerKind::Spell => Box::new(Spellcheck::load(&opts.dictionaries)?),
+            CheckerKind::Vale => Box::new(Vale),
+            CheckerKind::Languagetool => Box::new(LanguageTool {
+                language: opts.language.clone(),
+            }),
+        });
+    }
+
+    let mut reports = Vec::new();
+    let mut total = 0;
+    for path in &opts.files {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut issues = Vec::new();
+        for checker in &checkers {
+            issues.extend(
+                checker
+                    .check(path, &text)
+                    .with_context(|| format!("{} failed on {}", checker.name(), path.display()))?,
+            );
+        }
+        issues.sort_by_key(|i| (i.line, i.column));
+        total += issues.len();
+        if !opts.json {
+            for issue in &issues {
+                println!(
+                    "{}:{}:{}: [{}/{}] {}{}",
+                    path.display(),
+                    issue.line,
+                    issue.column,
+                    issue.checker,
+                    issue.rule,
+                    issue.message,
+                    if issue.suggestions.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" (suggestions: {})", issue.suggestions.join(", "))
+                    }
+                );
+            }
+        }
+        reports.push(FileReport { path, issues });
+    }
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        eprintln!(
+            "[lint-prose] {total} issue(s) in {} file(s)",
+            reports.iter().filter(|r| !r.issues.is_empty()).count()
+        );
+    }
+    Ok(())
+}
+
+/// Blank out everything that is not prose (frontmatter, code, link targets, URLs, HTML tags)
+/// with spaces, one per character, keeping newlines so line/column positions carry over.
+pub fn mask_markup(text: &str) -> String {
+    static INLINE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"`[^`\n]*`|\]\([^)]*\)|<[^>\n]*>|https?://\S+|^ {0,3}\[[^\]]+\]:.*$").unwrap()
+    });
+    let blank = |s: &str| -> String {
+        s.chars()
+            .map(|c| if c == '\n' { '\n' } else { ' ' })
+            .collect()
+    };
+    let (fm, body) = split_frontmatter(text);
+    let mut out = blank(fm);
+    let mut in_fence = false;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+        if fence || in_fence {
+            in_fence ^= fence;
+            out.push_str(&blank(line));
+            continue;
+        }
+        let mut last = 0;
+        for m in INLINE.find_iter(line) {
+            out.push_str(&line[last..m.start()]);
+            // Keep the `]` so link text and target do not run together.
+            match m.as_str().strip_prefix(']') {
+                Some(rest) => {
+                    out.push(']');
+                    out.push_str(&blank(rest));
+                }
+                None => out.push_str(&blank(m.as_str())),
+            }
+            last = m.end();
+        }
+        out.push_str(&line[last..]);
+    }
+    out
+}
+
+/// 1-based (line, column) of a character index into `text`.
+fn position(text: &str, char_index: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut column = 1;
+    for c in text.chars().take(char_index) {
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
+// --- Built-in spellcheck ----------------------------------------------------------------------
+
+/// System word list used when no `--dictionary` is given.
+const SYSTEM_WORDS: &str = "/usr/share/dict/words";
+/// Personal dictionary, relative to the current directory (the vault root by convention).
+const VAULT_WORDS: &str = ".diaryx/dictionary.txt";
+
+struct Spellcheck {
+    words: HashSet<String>,
+}
+
+impl Spellcheck {
+    fn load(dictionaries: &[PathBuf]) -> Result<Self> {
+        let mut paths: Vec<PathBuf> = dictionaries.to_vec();
+        if paths.is_empty() {
+            if !Path::new(SYSTEM_WORDS).exists() {
+                bail!("No word list found at {SYSTEM_WORDS}; pass one with --dictionary FILE");
+            }
+            paths.push(PathBuf::from(SYSTEM_WORDS));
+        }
+        if Path::new(VAULT_WORDS).exists() {
+            paths.push(PathBuf::from(VAULT_WORDS));
+        }
+        let mut words = HashSet::new();
+        for path in &paths {
+            let list = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read dictionary {}", path.display()))?;
+            words.extend(
+                list.lines()
+                    .map(|w| w.trim().to_lowercase())
+                    .filter(|w| !w.is_empty() && !w.starts_with('#')),
+            );
+        }
+        Ok(Spellcheck { words })
+    }
+
+    fn known(&self, word: &str) -> bool {
+        let lower = word.to_lowercase();
+        self.words.contains(&lower)
+            || lower
+                .strip_suffix("'s")
+                .or_else(|| lower.strip_suffix("’s"))
+                .is_some_and(|stem| self.words.contains(stem))
+    }
+
+    /// Known words one edit away (deletion, transposition, substitution or insertion).
+    fn suggest(&self, word: &str) -> Vec<String> {
+        let chars: Vec<char> = word.to_lowercase().chars().collect();
+        let mut found = Vec::new();
+        let mut consider = |candidate: Vec<char>| {
+            let candidate: String = candidate.into_iter().collect();
+            if self.words.contains(&candidate) && !found.contains(&candidate) {
+                found.push(candidate);
+            }
+        };
+        for i in 0..=chars.len() {
+            if i < chars.len() {
+                let mut c = chars.clone();
+                c.remove(i);
+                consider(c);
+            }
+            if i + 1 < chars.len() {
+                let mut c = chars.clone();
+                c.swap(i, i + 1);
+                consider(c);
+            }
+            for letter in 'a'..='z' {
+                if i < chars.len() {
+                    let mut c = chars.clone();
+                    c[i] = letter;
+                    consider(c);
+                }
+                let mut c = chars.clone();
+                c.insert(i, letter);
+                consider(c);
+            }
+        }
+        found.truncate(3);
+        found
+    }
+}
+
+impl ProseChecker for Spellcheck {
+    fn name(&self) -> &'static str {
+        "spell"
+    }
+
+    fn check(&self, _path: &Path, text: &str) -> Result<Vec<ProseIssue>> {
+        static WORD: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"[\p{L}\p{N}][\p{L}\p{N}'’]*").unwrap());
+        let masked = mask_markup(text);
+        let mut issues = Vec::new();
+        for m in WORD.find_iter(&masked) {
+            let word = m.as_str().trim_end_matches(['\'', '’']);
+            let skip = word.chars().any(|c| c.is_numeric())
+                // Acronyms and camelCase identifiers.
+                || word.chars().skip(1).any(char::is_uppercase);
+            if skip || self.known(word) {
+                continue;
+            }
+            let (line, column) = position(&masked, masked[..m.start()].chars().count());
+            issues.push(ProseIssue {
+                line,
+                column,
+                length: word.chars().count(),
+                checker: self.name(),
+                rule: "spelling".to_string(),
+                message: format!("Unknown word '{word}'"),
+                suggestions: self.suggest(word),
+            });
+        }
+        Ok(issues)
+    }
+}
+
+// --- External checkers ------------------------------------------------------------------------
+
+/// Run an external checker, explaining how to get it when it is not installed.
+fn run_tool(mut cmd: Command, tool: &str) -> Result<Vec<u8>> {
+    let output = cmd
+        .output()
+        .with_context(|| format!("Failed to run `{tool}`; is it installed and on PATH?"))?;
+    // Both tools exit non-zero when they find problems; only missing output is a failure.
+    if output.stdout.is_empty() && !output.status.success() {
+        bail!(
+            "`{tool}` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+struct Vale;
+
+impl ProseChecker for Vale {
+    fn name(&self) -> &'static str {
+        "vale"
+    }
+
+    /// Vale understands Markdown itself, so it gets the file as is.
+    fn check(&self, path: &Path, _text: &str) -> Result<Vec<ProseIssue>> {
+        let mut cmd = Command::new("vale");
+        cmd.arg("--output=JSON").arg(path);
+        let report: Json =
+            serde_json::from_slice(&run_tool(cmd, "vale")?).context("Unexpected vale output")?;
+        let alerts = report
+            .as_object()
+            .into_iter()
+            .flat_map(|files| files.values())
+            .filter_map(Json::as_array)
+            .flatten();
+        Ok(alerts
+            .map(|alert| {
+                let span = |i: usize| alert["Span"][i].as_u64().unwrap_or(1) as usize;
+                ProseIssue {
+                    line: alert["Line"].as_u64().unwrap_or(1) as usize,
+                    column: span(0),
+                    length: (span(1) + 1).saturating_sub(span(0)),
+                    checker: self.name(),
+                    rule: alert["Check"].as_str().unwrap_or("vale").to_string(),
+                    message: alert["Message"].as_str().unwrap_or_default().to_string(),
+                    suggestions: Vec::new(),
+                }
+            })
+            .collect())
+    }
+}
+
+struct LanguageTool {
+    language: String,
+}
+
+impl ProseChecker for LanguageTool {
+    fn name(&self) -> &'static str {
+        "languagetool"
+    }
+
+    /// LanguageTool only reads plain text, so it gets the masked prose (via a temporary file).
+    fn check(&self, _path: &Path, text: &str) -> Result<Vec<ProseIssue>> {
+        let masked = mask_markup(text);
+        let tmp = std::env::temp_dir().join(format!("diaryx-prose-{}.txt", std::process::id()));
+        fs::write(&tmp, &masked).with_context(|| format!("Failed writing {}", tmp.display()))?;
+        let mut cmd = Command::new("languagetool");
+        cmd.args(["--json", "-l", &self.language]).arg(&tmp);
+        let output = run_tool(cmd, "languagetool");
+        let _ = fs::remove_file(&tmp);
+        let stdout = output?;
+        // Progress messages may precede the JSON document.
+        let start = stdout.iter().position(|&b| b == b'{').unwrap_or(0);
+        let report: Json =
+            serde_json::from_slice(&stdout[start..]).context("Unexpected languagetool output")?;
+
+        // Offsets are in UTF-16 code units.
+        let mut utf16_to_char = Vec::with_capacity(masked.len() + 1);
+        for (i, c) in masked.chars().enumerate() {
+            utf16_to_char.extend(std::iter::repeat_n(i, c.len_utf16()));
+        }
+        utf16_to_char.push(masked.chars().count());
+        let to_char = |offset: u64| {
+            let offset = offset as usize;
+            utf16_to_char.get(offset).copied().unwrap_or(offset)
+        };
+
+        let matches = report["matches"].as_array().cloned().unwrap_or_default();
+        Ok(matches
+            .iter()
+            .map(|m| {
+                let offset = m["offset"].as_u64().unwrap_or(0);
+                let start = to_char(offset);
+                let end = to_char(offset + m["length"].as_u64().unwrap_or(0));
+                let (line, column) = position(&masked, start);
+                ProseIssue {
+                    line,
+                    column,
+                    length: end.saturating_sub(start),
+                    checker: self.name(),
+                    rule: m["rule"]["id"]
+                        .as_str()
+                        .unwrap_or("languagetool")
+                        .to_string(),
+                    message: m["message"].as_str().unwrap_or_default().to_string(),
+                    suggestions: m["replacements"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|r| r["value"].as_str().map(str::to_string))
+                        .take(3)
+                        .collect(),
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,347 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use diaryx_core::parse_authors;
+use regex::Regex;
+use serde_yaml::{Mapping, Value};
+
+use crate::vault::{parse_fields, split_frontmatter, vault_files};
+
+/// Entry filter expressions (`--where`), shared by commands that act on a subset of the vault.
+///
+/// ```text
+/// tags contains travel and not visibility = private
+/// (created >= 2024-01-01 or title matches "^Trip") and author exists
+/// ```
+///
+/// A condition is `FIELD OP VALUE` or `FIELD exists`, where FIELD is a frontmatter key, `path`
+/// (relative to the vault root) or `body`. `author` sees author names whatever the form
+/// (`Name <email>`, mappings, lists), and `author.email` / `author.url` their contacts.
+/// Operators: `=`, `!=`, `<`, `<=`, `>`, `>=`, `contains`, `matches` (regex). Against a list
+/// field, a condition holds when it holds for any item; `contains` is substring matching on text.
+/// Comparisons are numeric when both sides are numbers and lexical otherwise (which orders ISO
+/// dates correctly); `=`, `!=` and `contains` ignore case. Conditions combine with `and`, `or`,
+/// `not` and parentheses; `and` binds tighter.
+#[derive(Debug, Clone)]
+pub struct Filter(Expr);
+
+/// What a filter can see of one entry.
+pub struct EntryView<'a> {
+    pub path: &'a str,
+    pub fields: &'a Mapping,
+    pub body: &'a str,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Exists(String),
+    Cmp(String, Op, String),
+    Matches(String, Regex),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+impl Filter {
+    pub fn parse(src: &str) -> Result<Self> {
+        let tokens = tokenize(src)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(tok) = parser.tokens.get(parser.pos) {
+            bail!("Unexpected '{}' in filter: {src}", tok.text());
+        }
+        Ok(Filter(expr))
+    }
+
+    pub fn matches(&self, entry: &EntryView) -> bool {
+        eval(&self.0, entry)
+    }
+
+    /// Match an entry's source text; `path` is reported relative to `root`.
+    pub fn matches_source(&self, root: &Path, path: &Path, text: &str) -> bool {
+        let (fm, body) = split_frontmatter(text);
+        let fields = parse_fields(fm);
+        let rel = path.strip_prefix(root).unwrap_or(path).to_string_lossy();
+        self.matches(&EntryView {
+            path: &rel,
+            fields: &fields,
+            body,
+        })
+    }
+}
+
+/// An entry selected by [`select_entries`], with its current source text.
+pub struct SelectedEntry {
+    pub path: PathBuf,
+    pub text: String,
+}
+
+/// Entries of the vault rooted at `input` matching `filter` (all entries without one), in build
+/// order.
+pub fn select_entries(input: &Path, filter: Option<&Filter>) -> Result<Vec<SelectedEntry>> {
+    let root = input.parent().unwrap_or(Path::new("."));
+    let mut selected = Vec::new();
+    for path in vault_files(input)? {
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if filter.is_some_and(|f| !f.matches_source(root, &path, &text)) {
+            continue;
+        }
+        selected.push(SelectedEntry { path, text });
+    }
+    Ok(selected)
+}
+
+fn eval(expr: &Expr, entry: &EntryView) -> bool {
+    match expr {
+        Expr::And(a, b) => eval(a, entry) && eval(b, entry),
+        Expr::Or(a, b) => eval(a, entry) || eval(b, entry),
+        Expr::Not(e) => !eval(e, entry),
+        Expr::Exists(field) => !field_values(entry, field).is_empty(),
+        Expr::Matches(field, re) => field_values(entry, field).iter().any(|v| re.is_match(v)),
+        Expr::Cmp(field, Op::Ne, value) => {
+            !eval(&Expr::Cmp(field.clone(), Op::Eq, value.clone()), entry)
+        }
+        Expr::Cmp(field, op, value) => field_values(entry, field)
+            .iter()
+            .any(|v| compare(v, *op, value)),
+    }
+}
+
+fn compare(actual: &str, op: Op, expected: &str) -> bool {
+    let ordering = match (actual.parse::<f64>(), expected.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b),
+        _ => Some(actual.cmp(expected)),
+    };
+    match op {
+        Op::Eq => actual.eq_ignore_ascii_case(expected) || ordering.is_some_and(|o| o.is_eq()),
+        Op::Ne => unreachable!("`!=` is evaluated as a negated `=`"),
+        Op::Contains => actual.to_lowercase().contains(&expected.to_lowercase()),
+        Op::Lt => ordering.is_some_and(|o| o.is_lt()),
+        Op::Le => ordering.is_some_and(|o| o.is_le()),
+        Op::Gt => ordering.is_some_and(|o| o.is_gt()),
+        Op::Ge => ordering.is_some_and(|o| o.is_ge()),
+    }
+}
+
+/// Text values of a field: one per list item, none when missing or null.
+pub fn field_values(entry: &EntryView, field: &str) -> Vec<String> {
+    match field {
+        "path" => vec![entry.path.to_string()],
+        "body" => vec![entry.body.to_string()],
+        "author" | "author.email" | "author.url" => {
+            let authors = entry
+                .fields
+                .get("author")
+                .map(parse_authors)
+                .unwrap_or_default();
+            authors
+                .into_iter()
+                .filter_map(|a| match field {
+                    "author.email" => a.email,
+                    "author.url" => a.url,
+                    _ => Some(a.name),
+                })
+                .collect()
+        }
+        _ => match entry.fields.get(field) {
+            Some(Value::Sequence(items)) => items.iter().filter_map(scalar_text).collect(),
+            Some(value) => scalar_text(value).into_iter().collect(),
+            None => Vec::new(),
+        },
+    }
+}
+
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Null => None,
+        other => serde_yaml::to_string(other)
+            .ok()
+            .map(|s| s.trim().to_string()),
+    }
+}
+
+// --- Parsing ----------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Sym(&'static str),
+    Open,
+    Close,
+}
+
+impl Token {
+    fn text(&self) -> &str {
+        match self {
+            Token::Word(s) | Token::Quoted(s) => s,
+            Token::Sym(s) => s,
+            Token::Open => "(",
+            Token::Close => ")",
+        }
+    }
+
+    fn is_keyword(&self, kw: &str) -> bool {
+        matches!(self, Token::Word(w) if w.eq_ignore_ascii_case(kw))
+    }
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut text = String::new();
+                let mut closed = false;
+                for (_, ch) in chars.by_ref() {
+                    if ch == c {
+                        closed = true;
+                        break;
+                    }
+                    text.push(ch);
+                }
+                if !closed {
+                    bail!("Unterminated string in filter starting at column {}", i + 1);
+                }
+                tokens.push(Token::Quoted(text));
+            }
+            '=' | '!' | '<' | '>' => {
+                let rest = &src[i..];
+                let sym = ["!=", "<=", ">=", "=", "<", ">"]
+                    .into_iter()
+                    .find(|s| rest.starts_with(s))
+                    .ok_or_else(|| anyhow!("Unexpected '{c}' in filter at column {}", i + 1))?;
+                for _ in 0..sym.len() {
+                    chars.next();
+                }
+                tokens.push(Token::Sym(sym));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&(_, ch)) = chars.peek() {
+                    if ch.is_whitespace() || "()=!<>\"'".contains(ch) {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut left = self.and()?;
+        while self.peek().is_some_and(|t| t.is_keyword("or")) {
+            self.pos += 1;
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut left = self.unary()?;
+        while self.peek().is_some_and(|t| t.is_keyword("and")) {
+            self.pos += 1;
+            left = Expr::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(t) if t.is_keyword("not") => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let inner = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => bail!("Missing ')' in filter"),
+                }
+            }
+            Some(Token::Word(field)) | Some(Token::Quoted(field)) => self.condition(field),
+            Some(tok) => bail!("Expected a field name in filter, found '{}'", tok.text()),
+            None => bail!("Filter ended unexpectedly"),
+        }
+    }
+
+    fn condition(&mut self, field: String) -> Result<Expr> {
+        let op = match self.next() {
+            Some(t) if t.is_keyword("exists") => return Ok(Expr::Exists(field)),
+            Some(t) if t.is_keyword("matches") => {
+                let pattern = self.value()?;
+                let re = Regex::new(&pattern)
+                    .map_err(|e| anyhow!("Invalid regex in filter: {pattern} ({e})"))?;
+                return Ok(Expr::Matches(field, re));
+            }
+            Some(t) if t.is_keyword("contains") => Op::Contains,
+            Some(Token::Sym(sym)) => match sym {
+                "=" => Op::Eq,
+                "!=" => Op::Ne,
+                "<" => Op::Lt,
+                "<=" => Op::Le,
+                ">" => Op::Gt,
+                _ => Op::Ge,
+            },
+            Some(tok) => bail!(
+                "Expected an operator after '{field}' in filter, found '{}'",
+                tok.text()
+            ),
+            None => bail!("Expected an operator after '{field}' in filter"),
+        };
+        Ok(Expr::Cmp(field, op, self.value()?))
+    }
+
+    fn value(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(v)) | Some(Token::Quoted(v)) => Ok(v),
+            _ => bail!("Expected a value in filter"),
+        }
+    }
+}
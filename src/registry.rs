@@ -0,0 +1,139 @@
+/*!
+ * Named vaults, for people who keep several journals: `diaryx vault add personal ~/journal`
+ * records the folder in `vaults.toml` under the user's config folder, and `--vault personal`
+ * then targets it from any directory.
+ *
+ * ```toml
+ * [vaults]
+ * personal = "/home/ada/journal"
+ * work = "/home/ada/work/notes/index.md"
+ * ```
+ */
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::exit::ValidationFailed;
+use crate::vault::discover_root_index;
+
+const REGISTRY_FILE: &str = "vaults.toml";
+
+#[derive(Default, Serialize, Deserialize)]
+struct Registry {
+    #[serde(default)]
+    vaults: BTreeMap<String, PathBuf>,
+}
+
+/// `$XDG_CONFIG_HOME/diaryx`, else `~/.config/diaryx` (`%APPDATA%\diaryx` on Windows).
+fn config_dir() -> Result<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or_else(|| anyhow!("No config folder: set XDG_CONFIG_HOME or HOME"))?;
+    Ok(base.join("diaryx"))
+}
+
+fn load() -> Result<(PathBuf, Registry)> {
+    let path = config_dir()?.join(REGISTRY_FILE);
+    let registry = match fs::read_to_string(&path) {
+        Ok(text) => toml::from_str(&text)
+            .with_context(|| format!("Invalid vault registry {}", path.display()))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Registry::default(),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+    Ok((path, registry))
+}
+
+fn save(path: &Path, registry: &Registry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed creating {}", parent.display()))?;
+    }
+    fs::write(path, toml::to_string(registry)?)
+        .with_context(|| format!("Failed writing {}", path.display()))
+}
+
+/// The vault `--vault` names: a registered name, else a path to a vault folder (or a file in it).
+pub fn resolve(spec: &str) -> Result<PathBuf> {
+    let (_, registry) = load()?;
+    if let Some(path) = registry.vaults.get(spec) {
+        return Ok(path.clone());
+    }
+    let path = Path::new(spec);
+    if path.exists() {
+        return path
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", path.display()));
+    }
+    let known: Vec<&str> = registry.vaults.keys().map(String::as_str).collect();
+    Err(ValidationFailed(if known.is_empty() {
+        format!("Unknown vault '{spec}' (none registered; see `diaryx vault add`)")
+    } else {
+        format!("Unknown vault '{spec}' (registered: {})", known.join(", "))
+    })
+    .into())
+}
+
+/// `vault add`: register the vault at `path` (its folder, or its root index) as `name`.
+pub fn run_add(name: &str, path: &Path) -> Result<()> {
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(ValidationFailed(format!("Invalid vault name '{name}'")).into());
+    }
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Vault does not exist: {}", path.display()))?;
+    let index = if path.is_file() {
+        path.clone()
+    } else {
+        discover_root_index(&path)?.ok_or_else(|| {
+            ValidationFailed(format!(
+                "{} is not in a vault (no diaryx.toml or root index at or above it)",
+                path.display()
+            ))
+        })?
+    };
+    let (file, mut registry) = load()?;
+    let replaced = registry.vaults.insert(name.to_string(), path.clone());
+    save(&file, &registry)?;
+    println!(
+        "[vault] {} '{name}' -> {} (root index {})",
+        if replaced.is_some() {
+            "updated"
+        } else {
+            "registered"
+        },
+        path.display(),
+        index.display()
+    );
+    Ok(())
+}
+
+/// `vault remove`: forget `name` (the vault itself is left alone).
+pub fn run_remove(name: &str) -> Result<()> {
+    let (file, mut registry) = load()?;
+    if registry.vaults.remove(name).is_none() {
+        return Err(ValidationFailed(format!("No vault named '{name}'")).into());
+    }
+    save(&file, &registry)?;
+    println!("[vault] removed '{name}'");
+    Ok(())
+}
+
+/// `vault list`: `<name>\t<path>` per registered vault, by name.
+pub fn run_list() -> Result<()> {
+    let (file, registry) = load()?;
+    if registry.vaults.is_empty() {
+        println!("[vault] none registered ({})", file.display());
+    }
+    for (name, path) in &registry.vaults {
+        println!("{name}\t{}", path.display());
+    }
+    Ok(())
+}
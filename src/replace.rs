@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use once_cell::sync::Lazy;
+use regex::{NoExpand, Regex};
+
+use crate::vault::{EditMode, FileEdit, apply_edits_with_undo, split_frontmatter};
+
+/// `replace`: find-and-replace across entry bodies (and optionally frontmatter).
+///
+/// Replacement is link-aware: link destinations (`[text](target)`, reference definitions and
+/// `<scheme:...>` autolinks) are never touched, so renaming a phrase cannot break a link whose
+/// file name happens to contain it; link text is replaced like any other prose.
+pub struct ReplaceOptions {
+    pub files: Vec<PathBuf>,
+    pub from: String,
+    pub to: String,
+    /// Treat `from` as a regex (`to` may then use `$1` / `${name}`).
+    pub regex: bool,
+    /// Also replace inside frontmatter values.
+    pub frontmatter: bool,
+    pub mode: EditMode,
+    /// Folder whose `.diaryx/undo` journal records the applied edits.
+    pub root: PathBuf,
+    /// The invocation, as recorded in the journal.
+    pub command: String,
+}
+
+pub fn run_replace(opts: &ReplaceOptions) -> Result<()> {
+    if opts.from.is_empty() {
+        bail!("--from must not be empty");
+    }
+    let pattern = if opts.regex {
+        opts.from.clone()
+    } else {
+        regex::escape(&opts.from)
+    };
+    let re = Regex::new(&pattern).with_context(|| format!("Invalid regex: {}", opts.from))?;
+
+    let mut edits = Vec::new();
+    let mut counts = Vec::new();
+    for path in &opts.files {
+        let before = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let (fm, body) = split_frontmatter(&before);
+        let (new_fm, fm_count) = if opts.frontmatter {
+            replace_outside_links(fm, &re, &opts.to, opts.regex)
+        } else {
+            (fm.to_string(), 0)
+        };
+        let (new_body, body_count) = replace_outside_links(body, &re, &opts.to, opts.regex);
+        if fm_count + body_count == 0 {
+            continue;
+        }
+        counts.push(fm_count + body_count);
+        edits.push(FileEdit {
+            path: path.clone(),
+            before: before.clone(),
+            after: Some(new_fm + &new_body),
+        });
+    }
+
+    let applied = apply_edits_with_undo(&edits, opts.mode, &opts.root, &opts.command)?;
+    let total: usize = edits
+        .iter()
+        .zip(&counts)
+        .filter(|(edit, _)| applied.iter().any(|a| std::ptr::eq(*a, *edit)))
+        .map(|(_, n)| n)
+        .sum();
+    println!(
+        "[replace] {}{} replacement(s) in {} of {} file(s)",
+        if opts.mode.dry_run { "(dry run) " } else { "" },
+        total,
+        applied.len(),
+        opts.files.len()
+    );
+    Ok(())
+}
+
+/// Replace matches of `re` in `text`, skipping link destinations. Returns the new text and the
+/// number of replacements.
+fn replace_outside_links(text: &str, re: &Regex, to: &str, expand: bool) -> (String, usize) {
+    static LINK_TARGET: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?m)\]\([^)]*\)|<[A-Za-z][A-Za-z0-9+.-]*:[^>\s]*>|^ {0,3}\[[^\]]+\]:\s*\S+")
+            .unwrap()
+    });
+    let mut out = String::with_capacity(text.len());
+    let mut count = 0;
+    let mut last = 0;
+    let mut replace_segment = |segment: &str, out: &mut String| {
+        count += re.find_iter(segment).count();
+        if expand {
+            out.push_str(&re.replace_all(segment, to));
+        } else {
+            out.push_str(&re.replace_all(segment, NoExpand(to)));
+        }
+    };
+    for protected in LINK_TARGET.find_iter(text) {
+        replace_segment(&text[last..protected.start()], &mut out);
+        out.push_str(protected.as_str());
+        last = protected.end();
+    }
+    replace_segment(&text[last..], &mut out);
+    (out, count)
+}
@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use diaryx_core::{CoreBuildOptions, PageOutput, SiteShape, build_site_streaming, excerpt};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_yaml::Value;
+use time::format_description::well_known::Rfc3339;
+use time::{Date, Duration, Month, OffsetDateTime, Weekday};
+
+use crate::build::RealFs;
+use crate::diagnostics::link_target;
+use crate::export::created;
+use crate::tag::entry_tags;
+use crate::vault::{
+    EditMode, FileEdit, apply_all_with_undo, parse_fields, set_field, skipped_note,
+    split_frontmatter,
+};
+
+/// Folder (under the vault root) that `review-page --add` writes reviews into.
+const REVIEW_DIR: &str = "reviews";
+
+/// Tags listed in a review's stats.
+const TOP_TAGS: usize = 5;
+
+pub struct ReviewOptions {
+    /// Root index of the vault.
+    pub input: PathBuf,
+    /// `2025-W34` (ISO week) or `2025-08` (month).
+    pub period: String,
+    pub include_nonpublic: bool,
+    /// Destination file; stdout when `None` (ignored with `add`).
+    pub output: Option<PathBuf>,
+    /// Write the review as a new entry under `reviews/` and list it in the root index.
+    pub add: bool,
+    pub mode: EditMode,
+    pub command: String,
+}
+
+/// A calendar period: `start` inclusive, `end` exclusive.
+struct Period {
+    /// As given, normalized (`2025-W34`, `2025-08`).
+    label: String,
+    /// Human name (`Week 34, 2025`, `August 2025`).
+    name: String,
+    start: Date,
+    end: Date,
+}
+
+/// One entry created within the period.
+struct Entry {
+    title: String,
+    path: PathBuf,
+    date: Date,
+    excerpt: String,
+    words: usize,
+    tags: Vec<String>,
+    /// Task list items: (done, text).
+    tasks: Vec<(bool, String)>,
+}
+
+/// `review-page`: a Markdown review of the entries created in one week or month (titles with
+/// excerpts, counts, top tags and the tasks ticked off), printed, written to `output`, or with
+/// `add` scaffolded as a new entry `reviews/<period>.md` that the root index lists in
+/// `contents` (journaled for `undo`). Index pages are not reviewed, nor are earlier reviews.
+pub fn run_review(opts: &ReviewOptions) -> Result<()> {
+    let period = parse_period(&opts.period)?;
+    let root = opts.input.parent().unwrap_or(Path::new("."));
+    let entries = collect(opts, &period)?;
+
+    if !opts.add {
+        let dir = match &opts.output {
+            Some(path) => std::path::absolute(path)?
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            None => root.to_path_buf(),
+        };
+        let text = format!(
+            "# Review: {}\n\n{}",
+            period.name,
+            review_body(&period, &entries, &dir)
+        );
+        match &opts.output {
+            Some(path) => {
+                fs::write(path, text)
+                    .with_context(|| format!("Failed writing {}", path.display()))?;
+                eprintln!(
+                    "[review-page] {} entr{} -> {}",
+                    entries.len(),
+                    if entries.len() == 1 { "y" } else { "ies" },
+                    path.display()
+                );
+            }
+            None => print!("{text}"),
+        }
+        return Ok(());
+    }
+
+    let dir = root.join(REVIEW_DIR);
+    let path = dir.join(format!("{}.md", period.label.to_ascii_lowercase()));
+    if path.exists() {
+        bail!(
+            "{} already exists; remove it or write the review elsewhere with -o",
+            path.display()
+        );
+    }
+    let index_text = fs::read_to_string(&opts.input)
+        .with_context(|| format!("Failed to read {}", opts.input.display()))?;
+    let (index_fm, index_body) = split_frontmatter(&index_text);
+    let index_fields = parse_fields(index_fm);
+    let index_title = index_fields
+        .get("title")
+        .and_then(Value::as_str)
+        .unwrap_or("Index")
+        .to_string();
+    let index_name = opts
+        .input
+        .file_name()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let now = OffsetDateTime::now_utc()
+        .replace_nanosecond(0)?
+        .format(&Rfc3339)?;
+    let mut fm = set_field(
+        "",
+        "title",
+        &Value::String(format!("Review: {}", period.name)),
+    );
+    // Authorship and audience follow the root index.
+    for key in ["author", "visibility", "format"] {
+        if let Some(value) = index_fields.get(key) {
+            fm = set_field(&fm, key, value);
+        }
+    }
+    fm = set_field(&fm, "created", &Value::String(now.clone()));
+    fm = set_field(&fm, "updated", &Value::String(now));
+    fm = set_field(
+        &fm,
+        "tags",
+        &Value::Sequence(vec![Value::String("review".into())]),
+    );
+    fm = set_field(
+        &fm,
+        "part_of",
+        &Value::String(format!("[{index_title}](../{index_name})")),
+    );
+
+    let mut contents: Vec<Value> = index_fields
+        .get("contents")
+        .and_then(Value::as_sequence)
+        .cloned()
+        .unwrap_or_default();
+    contents.push(Value::String(format!(
+        "[Review: {}]({REVIEW_DIR}/{})",
+        period.name,
+        path.file_name().unwrap_or_default().to_string_lossy()
+    )));
+    let edits = vec![
+        FileEdit {
+            path: opts.input.clone(),
+            before: index_text.clone(),
+            after: Some(set_field(index_fm, "contents", &Value::Sequence(contents)) + index_body),
+        },
+        FileEdit {
+            path: path.clone(),
+            before: String::new(),
+            after: Some(fm + "\n" + &review_body(&period, &entries, &dir)),
+        },
+    ];
+    let applied = apply_all_with_undo(&edits, opts.mode, root, &opts.command)?;
+    println!(
+        "[review-page] {}{} entr{} -> {}{}",
+        if opts.mode.dry_run { "(dry run) " } else { "" },
+        entries.len(),
+        if entries.len() == 1 { "y" } else { "ies" },
+        path.display(),
+        skipped_note(edits.len() - applied.len())
+    );
+    Ok(())
+}
+
+/// Non-index entries created within `period`, oldest first.
+fn collect(opts: &ReviewOptions, period: &Period) -> Result<Vec<Entry>> {
+    let entry = opts
+        .input
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?;
+    let core_opts = CoreBuildOptions {
+        include_nonpublic: opts.include_nonpublic,
+        ..CoreBuildOptions::default()
+    };
+    let mut entries = Vec::new();
+    build_site_streaming(
+        entry,
+        core_opts,
+        &RealFs,
+        |_: &SiteShape, page: PageOutput| {
+            let Some(date) = created(&page).map(OffsetDateTime::date) else {
+                return Ok(());
+            };
+            let fields = page.frontmatter.as_mapping().cloned().unwrap_or_default();
+            let tags = entry_tags(&fields);
+            if page.is_index
+                || date < period.start
+                || date >= period.end
+                || tags.iter().any(|t| t.eq_ignore_ascii_case("review"))
+            {
+                return Ok(());
+            }
+            let path = PathBuf::from(&page.source_path);
+            let source = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let (_, body) = split_frontmatter(&source);
+            let html = path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"));
+            entries.push(Entry {
+                title: page.title.clone(),
+                excerpt: excerpt(body, html),
+                words: body.split_whitespace().count(),
+                tasks: tasks(body),
+                tags,
+                date,
+                path,
+            });
+            Ok(())
+        },
+    )
+    .with_context(|| "Core build failed")?;
+    entries.sort_by(|a, b| (a.date, &a.title).cmp(&(b.date, &b.title)));
+    Ok(entries)
+}
+
+/// The review below its title: date range, stats, entries and tasks. Links are relative to
+/// `dir`, the folder the review is written to.
+fn review_body(period: &Period, entries: &[Entry], dir: &Path) -> String {
+    let last = period.end - Duration::days(1);
+    let mut out = format!("{} to {}\n\n## Stats\n\n", period.start, last);
+    let mut days: Vec<Date> = entries.iter().map(|e| e.date).collect();
+    days.dedup();
+    let done = entries
+        .iter()
+        .flat_map(|e| &e.tasks)
+        .filter(|(done, _)| *done)
+        .count();
+    let open = entries.iter().map(|e| e.tasks.len()).sum::<usize>() - done;
+    out.push_str(&format!(
+        "- Entries: {} on {} day{}\n- Words: {}\n- Tasks: {done} completed, {open} open\n",
+        entries.len(),
+        days.len(),
+        if days.len() == 1 { "" } else { "s" },
+        entries.iter().map(|e| e.words).sum::<usize>()
+    ));
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for tag in entries.iter().flat_map(|e| &e.tags) {
+        *counts.entry(tag.to_lowercase()).or_default() += 1;
+    }
+    let mut tags: Vec<(String, usize)> = counts.into_iter().collect();
+    tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    if !tags.is_empty() {
+        let top: Vec<String> = tags
+            .iter()
+            .take(TOP_TAGS)
+            .map(|(tag, n)| format!("{tag} ({n})"))
+            .collect();
+        out.push_str(&format!("- Top tags: {}\n", top.join(", ")));
+    }
+
+    out.push_str("\n## Entries\n\n");
+    if entries.is_empty() {
+        out.push_str("No entries in this period.\n");
+    }
+    let link =
+        |entry: &Entry| format!("[{}]({})", entry.title, link_target(dir, &entry.path, None));
+    for entry in entries {
+        out.push_str(&format!("### {} ({})\n\n", link(entry), entry.date));
+        if !entry.excerpt.is_empty() {
+            out.push_str(&format!("{}\n\n", entry.excerpt));
+        }
+    }
+
+    for (heading, state) in [("Completed tasks", true), ("Open tasks", false)] {
+        let items: Vec<String> = entries
+            .iter()
+            .flat_map(|e| {
+                e.tasks
+                    .iter()
+                    .filter(|(done, _)| *done == state)
+                    .map(move |(_, text)| {
+                        format!(
+                            "- [{}] {text} ({})\n",
+                            if state { "x" } else { " " },
+                            link(e)
+                        )
+                    })
+            })
+            .collect();
+        if !items.is_empty() {
+            out.push_str(&format!("## {heading}\n\n{}\n", items.concat()));
+        }
+    }
+    out.trim_end().to_string() + "\n"
+}
+
+/// Task list items of a body (outside fenced code): `- [x] done` / `- [ ] open`.
+fn tasks(body: &str) -> Vec<(bool, String)> {
+    static TASK: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^\s*(?:[-*+]|\d+[.)])\s+\[([ xX])\]\s+(.+)$").unwrap());
+    let mut in_fence = false;
+    let mut out = Vec::new();
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if let Some(c) = TASK.captures(line).filter(|_| !in_fence) {
+            out.push((&c[1] != " ", c[2].trim().to_string()));
+        }
+    }
+    out
+}
+
+/// `YYYY-Www` (ISO 8601 week, Monday to Sunday) or `YYYY-MM` (calendar month).
+fn parse_period(spec: &str) -> Result<Period> {
+    let spec = spec.trim();
+    let invalid = || anyhow!("Invalid --period '{spec}': expected e.g. 2025-W34 or 2025-08");
+    let (year, rest) = spec.split_once('-').ok_or_else(invalid)?;
+    let year: i32 = year.parse().map_err(|_| invalid())?;
+    if let Some(week) = rest.strip_prefix(['W', 'w']) {
+        let week: u8 = week.parse().map_err(|_| invalid())?;
+        let start = Date::from_iso_week_date(year, week, Weekday::Monday).map_err(|_| invalid())?;
+        return Ok(Period {
+            label: format!("{year}-W{week:02}"),
+            name: format!("Week {week}, {year}"),
+            start,
+            end: start + Duration::weeks(1),
+        });
+    }
+    let month: u8 = rest.parse().map_err(|_| invalid())?;
+    let month = Month::try_from(month).map_err(|_| invalid())?;
+    let start = Date::from_calendar_date(year, month, 1).map_err(|_| invalid())?;
+    let end = match month {
+        Month::December => Date::from_calendar_date(year + 1, Month::January, 1),
+        _ => Date::from_calendar_date(year, month.next(), 1),
+    }
+    .map_err(|_| invalid())?;
+    Ok(Period {
+        label: format!("{year}-{:02}", month as u8),
+        name: format!("{month} {year}"),
+        start,
+        end,
+    })
+}
@@ -0,0 +1,245 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde_yaml::Value;
+use time::format_description::well_known::Rfc3339;
+use time::{Month, OffsetDateTime};
+
+use crate::config::load_rollover;
+use crate::diagnostics::{link_target, normalize, resolve_link, scan_links};
+use crate::vault::{
+    EditMode, FileEdit, apply_all_with_undo, parse_fields, set_field, skipped_note,
+    split_frontmatter,
+};
+
+const DEFAULT_YEAR_INDEX: &str = "{year}/{year}.md";
+const DEFAULT_MONTH_INDEX: &str = "{year}/{month}/{year}-{month}.md";
+
+pub struct RolloverOptions {
+    /// Root index of the vault.
+    pub input: PathBuf,
+    /// `YYYY-MM`; the current month when `None`.
+    pub month: Option<String>,
+    pub mode: EditMode,
+    pub command: String,
+}
+
+/// Files read or written so far: path, contents on disk (empty for a new file), new contents.
+type Files = Vec<(PathBuf, String, String)>;
+
+/// `rollover`: make sure the month's index exists and is reachable. The year index (under the
+/// root index) and the month index (under the year index) are created from their templates in
+/// `[rollover]` when missing, with `part_of` pointing at their parent, and each is appended to
+/// its parent's `contents` unless already listed there. Applied changes are journaled for `undo`;
+/// running it again for the same month changes nothing.
+pub fn run_rollover(opts: &RolloverOptions) -> Result<()> {
+    let (year, month) = match &opts.month {
+        Some(spec) => parse_month(spec)?,
+        None => {
+            let today = OffsetDateTime::now_utc().date();
+            (today.year(), today.month())
+        }
+    };
+    let root = opts.input.parent().unwrap_or(Path::new("."));
+    let config = load_rollover(root)?;
+    let fill = |pattern: &str| {
+        pattern
+            .replace("{year}", &year.to_string())
+            .replace("{month}", &format!("{:02}", month as u8))
+            .replace("{month_name}", &month.to_string())
+    };
+    let year_path = normalize(&root.join(fill(
+        config.year_index.as_deref().unwrap_or(DEFAULT_YEAR_INDEX),
+    )));
+    let month_path = normalize(&root.join(fill(
+        config.month_index.as_deref().unwrap_or(DEFAULT_MONTH_INDEX),
+    )));
+    if year_path == month_path || year_path == opts.input || month_path == opts.input {
+        bail!(
+            "[rollover] year_index and month_index must name two different files, neither of them the root index"
+        );
+    }
+    let template = |path: &Option<PathBuf>| -> Result<Option<String>> {
+        path.as_ref()
+            .map(|path| {
+                let path = root.join(path);
+                fs::read_to_string(&path)
+                    .map(|text| fill(&text))
+                    .with_context(|| format!("Failed to read template {}", path.display()))
+            })
+            .transpose()
+    };
+
+    let mut files: Files = Vec::new();
+    let year_title = year.to_string();
+    let month_title = format!("{month} {year}");
+    ensure_index(
+        &mut files,
+        &opts.input,
+        &year_path,
+        &year_title,
+        template(&config.year_template)?,
+    )?;
+    ensure_index(
+        &mut files,
+        &year_path,
+        &month_path,
+        &month_title,
+        template(&config.month_template)?,
+    )?;
+
+    let edits: Vec<FileEdit> = files
+        .into_iter()
+        .filter(|(_, before, after)| before != after)
+        .map(|(path, before, after)| FileEdit {
+            path,
+            before,
+            after: Some(after),
+        })
+        .collect();
+    if edits.is_empty() {
+        println!(
+            "[rollover] {} is up to date ({})",
+            month_title,
+            month_path.display()
+        );
+        return Ok(());
+    }
+    let applied = apply_all_with_undo(&edits, opts.mode, root, &opts.command)?;
+    println!(
+        "[rollover] {}{} -> {} ({} file(s) changed){}",
+        if opts.mode.dry_run { "(dry run) " } else { "" },
+        month_title,
+        month_path.display(),
+        applied.len(),
+        skipped_note(edits.len() - applied.len())
+    );
+    Ok(())
+}
+
+/// Create `child` (from `template`, or a bare index titled `title`) unless it exists, and list
+/// it in `parent`'s `contents` unless it already is.
+fn ensure_index(
+    files: &mut Files,
+    parent: &Path,
+    child: &Path,
+    title: &str,
+    template: Option<String>,
+) -> Result<()> {
+    let parent_text = current(files, parent)?;
+    let (parent_fm, parent_body) = split_frontmatter(&parent_text);
+    let parent_fields = parse_fields(parent_fm);
+    let parent_title = parent_fields
+        .get("title")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            parent
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+    let child_dir = child.parent().unwrap_or(Path::new(""));
+    let parent_dir = parent.parent().unwrap_or(Path::new(""));
+
+    let title = if child.exists() || files.iter().any(|(p, ..)| p == child) {
+        let fields = parse_fields(split_frontmatter(&current(files, child)?).0);
+        fields
+            .get("title")
+            .and_then(Value::as_str)
+            .unwrap_or(title)
+            .to_string()
+    } else {
+        let text = template.unwrap_or_default();
+        let (fm, body) = split_frontmatter(&text);
+        let fields = parse_fields(fm);
+        let mut fm = fm.to_string();
+        if !fields.contains_key("title") {
+            fm = set_field(&fm, "title", &Value::String(title.to_string()));
+        }
+        // Authorship and audience follow the parent index unless the template sets them.
+        for key in ["author", "visibility", "format"] {
+            if let Some(value) = parent_fields.get(key).filter(|_| !fields.contains_key(key)) {
+                fm = set_field(&fm, key, value);
+            }
+        }
+        let now = OffsetDateTime::now_utc()
+            .replace_nanosecond(0)?
+            .format(&Rfc3339)?;
+        for key in ["created", "updated"] {
+            if !fields.contains_key(key) {
+                fm = set_field(&fm, key, &Value::String(now.clone()));
+            }
+        }
+        if !fields.contains_key("contents") {
+            fm = set_field(&fm, "contents", &Value::Sequence(Vec::new()));
+        }
+        fm = set_field(
+            &fm,
+            "part_of",
+            &Value::String(format!(
+                "[{parent_title}]({})",
+                link_target(child_dir, parent, Some("part_of"))
+            )),
+        );
+        let title = parse_fields(&fm)
+            .get("title")
+            .and_then(Value::as_str)
+            .unwrap_or(title)
+            .to_string();
+        files.push((child.to_path_buf(), String::new(), fm + body));
+        title
+    };
+
+    let mut contents: Vec<Value> = parent_fields
+        .get("contents")
+        .and_then(Value::as_sequence)
+        .cloned()
+        .unwrap_or_default();
+    let listed = contents.iter().filter_map(Value::as_str).any(|raw| {
+        let target = scan_links(raw)
+            .into_iter()
+            .next()
+            .map_or_else(|| raw.trim().to_string(), |link| link.target);
+        resolve_link(parent, &target, Some("contents")).is_some_and(|t| normalize(&t.path) == child)
+    });
+    if !listed {
+        contents.push(Value::String(format!(
+            "[{title}]({})",
+            link_target(parent_dir, child, Some("contents"))
+        )));
+        let after = set_field(parent_fm, "contents", &Value::Sequence(contents)) + parent_body;
+        set_current(files, parent, after)?;
+    }
+    Ok(())
+}
+
+/// Contents of `path` as edited so far.
+fn current(files: &mut Files, path: &Path) -> Result<String> {
+    if let Some((.., after)) = files.iter().find(|(p, ..)| p == path) {
+        return Ok(after.clone());
+    }
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    files.push((path.to_path_buf(), text.clone(), text.clone()));
+    Ok(text)
+}
+
+fn set_current(files: &mut Files, path: &Path, text: String) -> Result<()> {
+    current(files, path)?;
+    if let Some((.., after)) = files.iter_mut().find(|(p, ..)| p == path) {
+        *after = text;
+    }
+    Ok(())
+}
+
+/// `YYYY-MM`.
+fn parse_month(spec: &str) -> Result<(i32, Month)> {
+    let spec = spec.trim();
+    let invalid = || anyhow!("Invalid --month '{spec}': expected e.g. 2025-09");
+    let (year, month) = spec.split_once('-').ok_or_else(invalid)?;
+    let year: i32 = year.parse().map_err(|_| invalid())?;
+    let month: u8 = month.parse().map_err(|_| invalid())?;
+    Ok((year, Month::try_from(month).map_err(|_| invalid())?))
+}
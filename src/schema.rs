@@ -0,0 +1,41 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
+use diaryx_core::schema::{CONFIG_FILE, Schema};
+
+use crate::build::RealFs;
+
+/// `schema`: print the frontmatter model for editors.
+///
+/// `json-schema` is a JSON Schema of the built-in fields plus those declared in the nearest
+/// `diaryx.toml` at or above the vault folder, e.g. for the VS Code YAML extension's
+/// `yaml.schemas` setting.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SchemaFormat {
+    JsonSchema,
+}
+
+/// Print (or write to `output`) the schema for the vault at `vault`: a folder, or any file in it.
+pub fn run_schema(format: SchemaFormat, vault: &Path, output: Option<&Path>) -> Result<()> {
+    // Discovery starts from a file's folder; for a folder, name its would-be config.
+    let start: PathBuf = if vault.is_dir() {
+        vault.join(CONFIG_FILE)
+    } else {
+        vault.to_path_buf()
+    };
+    let start = start
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 path: {}", start.display()))?;
+    let schema = Schema::discover(start, &RealFs)?;
+    let text = match format {
+        SchemaFormat::JsonSchema => serde_json::to_string_pretty(&schema.json_schema())?,
+    };
+    match output {
+        Some(path) => fs::write(path, text + "\n")
+            .with_context(|| format!("Failed writing {}", path.display()))?,
+        None => println!("{text}"),
+    }
+    Ok(())
+}
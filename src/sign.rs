@@ -0,0 +1,355 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::archive::{self, ManifestFile, entry_name, root_hash};
+use crate::exit::ValidationFailed;
+
+/// Build manifest (`diaryx-manifest.json` at the site root, `build --manifest` / `--sign`).
+///
+/// Lists every file of the built site with its size and SHA-256, plus their root hash. With
+/// `--sign <KEY>` the manifest is also signed, so anyone holding the public key can check with
+/// `diaryx verify` that a published copy of the site was not altered. Signing uses an external
+/// tool chosen from the key file: minisign (`minisign -G` keys) or OpenSSH (`ssh-keygen -Y`,
+/// any SSH key). The signature sits next to the manifest as `.minisig` or `.sig`.
+#[derive(Serialize, Deserialize)]
+struct BuildManifest {
+    format: String,
+    created: String,
+    diaryx_version: String,
+    files: Vec<ManifestFile>,
+    root_hash: String,
+}
+
+pub(crate) const MANIFEST_PATH: &str = "diaryx-manifest.json";
+const FORMAT: &str = "diaryx-build-manifest/1";
+/// SSH signature namespace, so the signature cannot be replayed for another purpose.
+const SSH_NAMESPACE: &str = "diaryx-manifest";
+
+#[derive(Clone, Copy)]
+enum Scheme {
+    Minisign,
+    Ssh,
+}
+
+impl Scheme {
+    /// Minisign key files (secret and public) start with an "untrusted comment" line; anything
+    /// else is taken to be an SSH key.
+    fn of_key(key: &Path) -> Result<Scheme> {
+        let text = fs::read_to_string(key)
+            .with_context(|| format!("Failed to read key {}", key.display()))?;
+        Ok(if text.starts_with("untrusted comment:") {
+            Scheme::Minisign
+        } else {
+            Scheme::Ssh
+        })
+    }
+
+    fn signature_path(self, manifest: &Path) -> PathBuf {
+        let ext = match self {
+            Scheme::Minisign => "minisig",
+            Scheme::Ssh => "sig",
+        };
+        PathBuf::from(format!("{}.{ext}", manifest.display()))
+    }
+}
+
+/// Write the manifest for the site in `output` (and sign it with `sign_key`); returns the root
+/// hash.
+pub(crate) fn write_manifest(output: &Path, sign_key: Option<&Path>) -> Result<String> {
+    let mut files = Vec::new();
+    for path in archive::files_under(output)? {
+        let rel = entry_name(path.strip_prefix(output).unwrap_or(&path));
+        if is_manifest_file(&rel) {
+            continue;
+        }
+        let data = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        files.push(ManifestFile::new(rel, &data));
+    }
+    let manifest = BuildManifest {
+        format: FORMAT.to_string(),
+        created: OffsetDateTime::now_utc().format(&Rfc3339)?,
+        diaryx_version: env!("CARGO_PKG_VERSION").to_string(),
+        root_hash: root_hash(&files),
+        files,
+    };
+    let path = output.join(MANIFEST_PATH);
+    fs::write(&path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Failed writing {}", path.display()))?;
+    if let Some(key) = sign_key {
+        sign(&path, key, &manifest.root_hash)?;
+    }
+    Ok(manifest.root_hash)
+}
+
+/// Size of each file listed in the manifest of the site in `dir`, by site-relative path (`None`
+/// without a readable manifest), for `build --size-report`.
+pub(crate) fn manifest_sizes(dir: &Path) -> Option<BTreeMap<String, u64>> {
+    let manifest: BuildManifest =
+        serde_json::from_slice(&fs::read(dir.join(MANIFEST_PATH)).ok()?).ok()?;
+    (manifest.format == FORMAT).then(|| {
+        manifest
+            .files
+            .into_iter()
+            .map(|file| (file.path, file.size))
+            .collect()
+    })
+}
+
+pub(crate) fn is_manifest_file(rel: &str) -> bool {
+    rel == MANIFEST_PATH
+        || rel.strip_prefix(MANIFEST_PATH) == Some(".minisig")
+        || rel.strip_prefix(MANIFEST_PATH) == Some(".sig")
+}
+
+fn sign(manifest: &Path, key: &Path, root: &str) -> Result<()> {
+    let scheme = Scheme::of_key(key)?;
+    let signature = scheme.signature_path(manifest);
+    // ssh-keygen refuses to overwrite; minisign would, but start clean either way.
+    let _ = fs::remove_file(&signature);
+    let mut cmd = match scheme {
+        Scheme::Minisign => {
+            let mut cmd = Command::new("minisign");
+            cmd.arg("-S")
+                .arg("-s")
+                .arg(key)
+                .arg("-m")
+                .arg(manifest)
+                .args(["-t", &format!("diaryx build manifest, root {root}")]);
+            cmd
+        }
+        Scheme::Ssh => {
+            let mut cmd = Command::new("ssh-keygen");
+            cmd.args(["-Y", "sign", "-n", SSH_NAMESPACE, "-f"])
+                .arg(key)
+                .arg(manifest);
+            cmd
+        }
+    };
+    // Inherit the terminal so the tool can ask for the key's passphrase.
+    let status = run(&mut cmd)?;
+    if !status.success() || !signature.is_file() {
+        bail!("Signing {} failed", manifest.display());
+    }
+    Ok(())
+}
+
+/// Check a site folder against its manifest, and its signature against `pubkey` when given.
+/// Problems are listed and turn into an error.
+pub fn run_verify(dir: &Path, pubkey: Option<&Path>) -> Result<()> {
+    let manifest_path = dir.join(MANIFEST_PATH);
+    let manifest: BuildManifest = serde_json::from_slice(
+        &fs::read(&manifest_path)
+            .with_context(|| format!("No {MANIFEST_PATH} in {}", dir.display()))?,
+    )
+    .context("Invalid build manifest")?;
+    if manifest.format != FORMAT {
+        bail!("Unsupported manifest format '{}'", manifest.format);
+    }
+
+    let mut problems = Vec::new();
+    let mut present: BTreeSet<String> = archive::files_under(dir)?
+        .iter()
+        .map(|path| entry_name(path.strip_prefix(dir).unwrap_or(path)))
+        .filter(|rel| !is_manifest_file(rel))
+        .collect();
+    for file in &manifest.files {
+        if !site_relative(&file.path) {
+            problems.push(format!("path outside the site: {}", file.path));
+            continue;
+        }
+        let path = dir.join(&file.path);
+        match fs::read(&path) {
+            Err(_) => problems.push(format!("missing: {}", file.path)),
+            Ok(data) if !file.matches(&data) => problems.push(format!("modified: {}", file.path)),
+            Ok(_) => {}
+        }
+        present.remove(&file.path);
+    }
+    for extra in &present {
+        problems.push(format!("not in manifest: {extra}"));
+    }
+    let computed = root_hash(&manifest.files);
+    if computed != manifest.root_hash {
+        problems.push("manifest root hash does not match its file list".to_string());
+    }
+
+    let signature = match pubkey {
+        Some(pubkey) => {
+            let scheme = Scheme::of_key(pubkey)?;
+            let signature = scheme.signature_path(&manifest_path);
+            if !signature.is_file() {
+                problems.push(format!("not signed: {} is missing", signature.display()));
+            } else if !check_signature(scheme, &manifest_path, &signature, pubkey)? {
+                problems.push("signature does not match the manifest and key".to_string());
+            }
+            "signature valid"
+        }
+        None if [Scheme::Minisign, Scheme::Ssh]
+            .iter()
+            .any(|s| s.signature_path(&manifest_path).is_file()) =>
+        {
+            "signature not checked (pass --pubkey)"
+        }
+        None => "unsigned",
+    };
+
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("  - {problem}");
+        }
+        return Err(ValidationFailed(format!(
+            "Verification failed: {} problem(s) in {}",
+            problems.len(),
+            dir.display()
+        ))
+        .into());
+    }
+    println!(
+        "[verify] OK: {} file(s), built {}, root hash {computed}, {signature}",
+        manifest.files.len(),
+        manifest.created
+    );
+    Ok(())
+}
+
+/// Whether a manifest path names a file inside the site folder (no `..`, root or drive).
+fn site_relative(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+fn check_signature(
+    scheme: Scheme,
+    manifest: &Path,
+    signature: &Path,
+    pubkey: &Path,
+) -> Result<bool> {
+    let status = match scheme {
+        Scheme::Minisign => run(Command::new("minisign")
+            .arg("-Vq")
+            .arg("-p")
+            .arg(pubkey)
+            .arg("-m")
+            .arg(manifest)
+            .arg("-x")
+            .arg(signature))?,
+        Scheme::Ssh => {
+            // ssh-keygen checks against an allowed-signers list; build a one-line one.
+            let key = fs::read_to_string(pubkey)
+                .with_context(|| format!("Failed to read key {}", pubkey.display()))?;
+            let key: Vec<&str> = key.split_whitespace().take(2).collect();
+            if key.len() != 2 {
+                return Err(anyhow!("{} is not an SSH public key", pubkey.display()));
+            }
+            // A fresh file of our own (created exclusively, removed on drop), so nothing else in
+            // the temp folder can stand in for it.
+            let mut signers = tempfile::Builder::new()
+                .prefix("diaryx-signers-")
+                .tempfile()
+                .context("Failed creating the allowed-signers file")?;
+            writeln!(signers, "diaryx {}", key.join(" "))
+                .with_context(|| format!("Failed writing {}", signers.path().display()))?;
+            let manifest_file = fs::File::open(manifest)
+                .with_context(|| format!("Failed to read {}", manifest.display()))?;
+            run(Command::new("ssh-keygen")
+                .args(["-Y", "verify", "-I", "diaryx", "-n", SSH_NAMESPACE, "-f"])
+                .arg(signers.path())
+                .arg("-s")
+                .arg(signature)
+                .stdin(manifest_file)
+                .stdout(Stdio::null()))?
+        }
+    };
+    Ok(status.success())
+}
+
+fn run(cmd: &mut Command) -> Result<std::process::ExitStatus> {
+    let tool = cmd.get_program().to_string_lossy().into_owned();
+    cmd.status()
+        .with_context(|| format!("Failed to run `{tool}`; is it installed and on PATH?"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("assets")).unwrap();
+        fs::write(dir.path().join("index.html"), "<h1>Journal</h1>").unwrap();
+        fs::write(dir.path().join("assets/a.png"), [0u8, 1, 2]).unwrap();
+        dir
+    }
+
+    fn manifest(dir: &Path) -> BuildManifest {
+        serde_json::from_slice(&fs::read(dir.join(MANIFEST_PATH)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn manifest_pins_every_file_by_root_hash() {
+        let dir = site();
+        let root = write_manifest(dir.path(), None).unwrap();
+        let written = manifest(dir.path());
+        let paths: Vec<&str> = written.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["assets/a.png", "index.html"]);
+        assert_eq!(written.root_hash, root);
+        // The sorted `sha256sum` lines of the files, hashed
+        let mut lines = [
+            format!("{}  assets/a.png\n", archive::sha256_hex(&[0, 1, 2])),
+            format!("{}  index.html\n", archive::sha256_hex(b"<h1>Journal</h1>")),
+        ];
+        lines.sort();
+        assert_eq!(root, archive::sha256_hex(lines.concat().as_bytes()));
+        run_verify(dir.path(), None).unwrap();
+    }
+
+    #[test]
+    fn verify_reports_changed_files() {
+        let dir = site();
+        write_manifest(dir.path(), None).unwrap();
+        fs::write(dir.path().join("index.html"), "<h1>Defaced</h1>").unwrap();
+        assert!(run_verify(dir.path(), None).is_err());
+
+        let dir = site();
+        write_manifest(dir.path(), None).unwrap();
+        fs::write(dir.path().join("extra.html"), "").unwrap();
+        assert!(run_verify(dir.path(), None).is_err());
+    }
+
+    #[test]
+    fn verify_stays_inside_the_site() {
+        let outer = tempfile::tempdir().unwrap();
+        fs::write(outer.path().join("secret.txt"), "outside").unwrap();
+        let dir = outer.path().join("site");
+        fs::create_dir(&dir).unwrap();
+        let files = vec![ManifestFile::new("../secret.txt".into(), b"outside")];
+        let forged = BuildManifest {
+            format: FORMAT.to_string(),
+            created: String::new(),
+            diaryx_version: String::new(),
+            root_hash: root_hash(&files),
+            files,
+        };
+        fs::write(
+            dir.join(MANIFEST_PATH),
+            serde_json::to_vec(&forged).unwrap(),
+        )
+        .unwrap();
+        assert!(run_verify(&dir, None).is_err());
+
+        assert!(site_relative("assets/a.png"));
+        assert!(!site_relative("/etc/passwd"));
+        assert!(!site_relative("assets/../../x"));
+        assert!(!site_relative(""));
+    }
+}
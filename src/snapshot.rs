@@ -0,0 +1,271 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use diaryx_core::{CoreBuildOptions, OutputLayout, PageOutput, SiteShape, build_site_streaming};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::BuildOptions;
+use crate::archive::{self, ManifestFile, ZipWriter, entry_name, root_hash};
+use crate::build::{RealFs, write_site};
+use crate::diagnostics::normalize;
+use crate::exit::ValidationFailed;
+use crate::vault::vault_files;
+
+/// Snapshot manifest (`manifest.json` inside the archive).
+///
+/// A snapshot is a zip holding the vault sources under `source/` (every entry reachable from the
+/// root index, non-public included, plus the attachments they reference) and a fresh build under
+/// `site/`. The manifest lists each file with its size and SHA-256, plus their root hash
+/// ([`archive::root_hash`]), so one short value pins the whole archive. Keep it
+/// somewhere else too (a notebook, an email to yourself): `verify-snapshot --expect-root` then
+/// also catches an archive rewritten together with its manifest.
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format: String,
+    created: String,
+    diaryx_version: String,
+    /// Archive path of the root index.
+    entry: String,
+    files: Vec<ManifestFile>,
+    root_hash: String,
+}
+
+const FORMAT: &str = "diaryx-snapshot/1";
+const MANIFEST_PATH: &str = "manifest.json";
+
+pub struct SnapshotOptions {
+    /// Root index (canonicalized).
+    pub input: PathBuf,
+    /// Folder the archive is written to.
+    pub output_dir: PathBuf,
+    /// Build non-public pages into `site/` as well.
+    pub include_nonpublic: bool,
+}
+
+/// Write `diaryx-snapshot-<UTC timestamp>.zip` into the output folder and print its root hash.
+pub fn run_snapshot(opts: &SnapshotOptions) -> Result<()> {
+    let now = OffsetDateTime::now_utc();
+    let vault_root = opts.input.parent().unwrap_or(Path::new("."));
+
+    let mut files: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    for path in source_files(&opts.input)? {
+        let Ok(rel) = path.strip_prefix(vault_root) else {
+            eprintln!(
+                "[snapshot] skipping {} (outside the vault folder)",
+                path.display()
+            );
+            continue;
+        };
+        let data = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        files.insert(format!("source/{}", entry_name(rel)), data);
+    }
+
+    let scratch = std::env::temp_dir().join(format!("diaryx-snapshot-{}", std::process::id()));
+    let built = write_site(&BuildOptions {
+        input: opts.input.clone(),
+        output: scratch.clone(),
+        stdin: false,
+        watch: false,
+        quiet: false,
+        output_format: Default::default(),
+        watch_events: None,
+        allow_remote: Vec::new(),
+        remote_cache: None,
+        identity: None,
+        include_nonpublic: opts.include_nonpublic,
+        audience: None,
+        encrypt: None,
+        emit_model: None,
+        layout: OutputLayout::default(),
+        verbose: false,
+        strict: false,
+        no_default_css: false,
+        profile: false,
+        large_body_bytes: None,
+        smart_punctuation: false,
+        figures: false,
+        pdf_previews: false,
+        infer_dates: false,
+        exclude: Vec::new(),
+        since: None,
+        until: None,
+        tags: Vec::new(),
+        drafts: false,
+        future: false,
+        as_root: false,
+        changelog: false,
+        no_version_check: true,
+        pwa: false,
+        graph_page: false,
+        clean_urls: false,
+        gh_pages: None,
+        hosting: None,
+        hooks: Default::default(),
+        manifest: false,
+        sign: None,
+        size_report: false,
+        check_a11y: false,
+        budget: None,
+    })
+    .and_then(|_| {
+        for path in archive::files_under(&scratch)? {
+            let rel = path.strip_prefix(&scratch).unwrap_or(&path);
+            let data =
+                fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            files.insert(format!("site/{}", entry_name(rel)), data);
+        }
+        Ok(())
+    });
+    fs::remove_dir_all(&scratch).ok();
+    built.context("Building the site for the snapshot failed")?;
+
+    let manifest_files: Vec<ManifestFile> = files
+        .iter()
+        .map(|(path, data)| ManifestFile::new(path.clone(), data))
+        .collect();
+    let entry = opts
+        .input
+        .strip_prefix(vault_root)
+        .map(|rel| format!("source/{}", entry_name(rel)))
+        .unwrap_or_default();
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        created: now.format(&Rfc3339)?,
+        diaryx_version: env!("CARGO_PKG_VERSION").to_string(),
+        entry,
+        root_hash: root_hash(&manifest_files),
+        files: manifest_files,
+    };
+
+    fs::create_dir_all(&opts.output_dir)
+        .with_context(|| format!("Failed creating {}", opts.output_dir.display()))?;
+    let name = format!(
+        "diaryx-snapshot-{:04}{:02}{:02}T{:02}{:02}{:02}Z.zip",
+        now.year(),
+        u8::from(now.month()),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second()
+    );
+    let path = opts.output_dir.join(name);
+    if path.exists() {
+        bail!("{} already exists", path.display());
+    }
+    let mut zip = ZipWriter::new(Vec::new());
+    zip.add(
+        MANIFEST_PATH,
+        serde_json::to_string_pretty(&manifest)?.as_bytes(),
+    )?;
+    for (name, data) in &files {
+        zip.add(name, data)?;
+    }
+    fs::write(&path, zip.finish()?)
+        .with_context(|| format!("Failed writing {}", path.display()))?;
+
+    println!(
+        "[snapshot] {} file(s) -> {}\n[snapshot] root hash {}",
+        manifest.files.len(),
+        path.display(),
+        manifest.root_hash
+    );
+    Ok(())
+}
+
+/// Check every file of a snapshot against its manifest (and the root hash against `expect_root`
+/// when given). Problems are listed and turn into an error.
+pub fn run_verify_snapshot(archive_path: &Path, expect_root: Option<&str>) -> Result<()> {
+    let bytes = fs::read(archive_path)
+        .with_context(|| format!("Failed to read {}", archive_path.display()))?;
+    let mut entries: BTreeMap<String, Vec<u8>> = archive::read_zip(&bytes)
+        .with_context(|| format!("{} is not a readable snapshot", archive_path.display()))?
+        .into_iter()
+        .collect();
+    let manifest: Manifest = serde_json::from_slice(
+        &entries
+            .remove(MANIFEST_PATH)
+            .ok_or_else(|| anyhow!("Snapshot has no {MANIFEST_PATH}"))?,
+    )
+    .context("Invalid snapshot manifest")?;
+    if manifest.format != FORMAT {
+        bail!("Unsupported snapshot format '{}'", manifest.format);
+    }
+
+    let mut problems = Vec::new();
+    for file in &manifest.files {
+        match entries.remove(&file.path) {
+            None => problems.push(format!("missing: {}", file.path)),
+            Some(data) if !file.matches(&data) => problems.push(format!("modified: {}", file.path)),
+            Some(_) => {}
+        }
+    }
+    for extra in entries.keys() {
+        problems.push(format!("not in manifest: {extra}"));
+    }
+    let computed = root_hash(&manifest.files);
+    if computed != manifest.root_hash {
+        problems.push("manifest root hash does not match its file list".to_string());
+    }
+    if let Some(expected) = expect_root
+        && !expected.trim().eq_ignore_ascii_case(&computed)
+    {
+        problems.push(format!(
+            "root hash {computed} differs from the expected {}",
+            expected.trim()
+        ));
+    }
+
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("  - {problem}");
+        }
+        return Err(ValidationFailed(format!(
+            "Snapshot verification failed: {} problem(s) in {}",
+            problems.len(),
+            archive_path.display()
+        ))
+        .into());
+    }
+    println!(
+        "[verify] OK: {} file(s), created {}, root hash {}",
+        manifest.files.len(),
+        manifest.created,
+        computed
+    );
+    Ok(())
+}
+
+/// Markdown entries reachable from `input` plus the attachments they reference (non-public
+/// included), sorted and deduplicated.
+fn source_files(input: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = vault_files(input)?;
+    let entry = input
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?;
+    let core_opts = CoreBuildOptions {
+        include_nonpublic: true,
+        rewrite_links: true,
+        ..CoreBuildOptions::default()
+    };
+    let summary =
+        build_site_streaming(entry, core_opts, &RealFs, |_: &SiteShape, _: PageOutput| {
+            Ok(())
+        })
+        .with_context(|| "Core build failed")?;
+    paths.extend(
+        summary
+            .attachments
+            .into_iter()
+            .map(|a| PathBuf::from(a.source)),
+    );
+    for path in &mut paths {
+        *path = normalize(path);
+    }
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
@@ -0,0 +1,257 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde_yaml::Value;
+
+use crate::export::parse_created;
+use crate::naming::Naming;
+use crate::vault::{
+    EditMode, FileEdit, apply_all_with_undo, journal_root, parse_fields, rebase_target,
+    remove_field, rewrite_link_targets, set_field, skipped_note, split_frontmatter,
+};
+
+/// `split`: break an entry into child entries at headings of one level.
+///
+/// Each section (heading included) becomes `<stem>/<heading-slug>.md` next to the original (or
+/// the name the vault's `[naming] pattern` gives it), with the original's frontmatter minus its
+/// graph fields, the heading as `title`, and `part_of` pointing back. Relative links inside moved sections are re-based on the new directory so
+/// attachments keep resolving. The original keeps the text before the first heading and gains
+/// the children in `contents`, which turns it into an index. Applied changes are journaled for
+/// `undo`.
+pub fn run_split(path: &Path, level: usize, mode: EditMode, command: &str) -> Result<()> {
+    let (edits, dir) = split_edits(path, level)?;
+    let sections = edits.len() - 1;
+    let applied = apply_all_with_undo(&edits, mode, &journal_root(path), command)?;
+    println!(
+        "[split] {}{} -> {} entr{} in {}{}",
+        if mode.dry_run { "(dry run) " } else { "" },
+        path.display(),
+        sections,
+        if sections == 1 { "y" } else { "ies" },
+        dir.display(),
+        skipped_note(edits.len() - applied.len())
+    );
+    Ok(())
+}
+
+/// The edits of [`run_split`]: the rewritten original first, then one new entry per section.
+/// Also returns the folder the new entries go in.
+fn split_edits(path: &Path, level: usize) -> Result<(Vec<FileEdit>, PathBuf)> {
+    let before =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let (fm, body) = split_frontmatter(&before);
+    let fields = parse_fields(fm);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = path
+        .file_name()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let title = fields
+        .get("title")
+        .and_then(Value::as_str)
+        .unwrap_or(&stem)
+        .to_string();
+
+    let (preamble, sections) = split_sections(body, level);
+    if sections.is_empty() {
+        bail!("No h{level} headings found in {}", path.display());
+    }
+
+    let dir = path.with_file_name(&stem);
+    let naming = Naming::load(path.parent().unwrap_or(Path::new(".")))?;
+    // Children keep the original's `created`, so dated names match it.
+    let created = fields
+        .get("created")
+        .and_then(Value::as_str)
+        .and_then(parse_created)
+        .map(|c| c.date());
+    let mut used = HashSet::new();
+    let mut edits = Vec::new();
+    let mut contents: Vec<Value> = fields
+        .get("contents")
+        .and_then(Value::as_sequence)
+        .cloned()
+        .unwrap_or_default();
+    for section in &sections {
+        let child_path = naming.entry_path(&dir, &section.heading, created, &mut used);
+        let child_name = child_path
+            .file_name()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut child_fm = fm.to_string();
+        for key in ["contents", "part_of", "this_file_is_root_index"] {
+            child_fm = remove_field(&child_fm, key);
+        }
+        child_fm = set_field(&child_fm, "title", &Value::String(section.heading.clone()));
+        child_fm = set_field(
+            &child_fm,
+            "part_of",
+            &Value::String(format!("[{title}](../{file_name})")),
+        );
+        let child_body =
+            rewrite_link_targets(&section.text, |link| rebase_target(link, path, &dir));
+        edits.push(FileEdit {
+            path: child_path,
+            before: String::new(),
+            after: Some(child_fm + &child_body),
+        });
+        contents.push(Value::String(format!(
+            "[{}]({stem}/{child_name})",
+            section.heading
+        )));
+    }
+
+    let index_fm = set_field(fm, "contents", &Value::Sequence(contents));
+    edits.insert(
+        0,
+        FileEdit {
+            path: path.to_path_buf(),
+            before: before.clone(),
+            after: Some(index_fm + &preamble),
+        },
+    );
+    Ok((edits, dir))
+}
+
+struct Section {
+    heading: String,
+    /// Section text, heading line included.
+    text: String,
+}
+
+/// Split a body at ATX headings of exactly `level` (outside fenced code). Returns the text
+/// before the first heading and the sections.
+fn split_sections(body: &str, level: usize) -> (String, Vec<Section>) {
+    let mut preamble = String::new();
+    let mut sections: Vec<Section> = Vec::new();
+    let mut in_fence = false;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        let heading = (!in_fence && line.len() - trimmed.len() <= 3)
+            .then(|| heading_text(trimmed, level))
+            .flatten();
+        match (heading, sections.last_mut()) {
+            (Some(heading), _) => sections.push(Section {
+                heading,
+                text: line.to_string(),
+            }),
+            (None, Some(section)) => section.text.push_str(line),
+            (None, None) => preamble.push_str(line),
+        }
+    }
+    (preamble, sections)
+}
+
+/// Text of an ATX heading of exactly `level` (`## Title ##` -> `Title`).
+fn heading_text(line: &str, level: usize) -> Option<String> {
+    let rest = line.strip_prefix(&"#".repeat(level))?;
+    if !(rest.starts_with([' ', '\t']) || rest.trim().is_empty()) {
+        return None; // deeper heading or `#tag`
+    }
+    let text = rest.trim().trim_end_matches('#').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields_of(text: &str) -> serde_yaml::Mapping {
+        parse_fields(split_frontmatter(text).0)
+    }
+
+    fn strings(value: Option<&Value>) -> Vec<&str> {
+        value
+            .and_then(Value::as_sequence)
+            .map(|items| items.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn sections_become_entries_listed_by_the_original() {
+        let vault = tempfile::tempdir().unwrap();
+        let path = vault.path().join("walk.md");
+        fs::write(
+            &path,
+            "---\ntitle: Walk\ncreated: 2025-03-01\ntags: [outdoors]\npart_of: \"[Home](index.md)\"\ncontents:\n  - \"[Old](old.md)\"\n---\nIntro.\n\n## Morning\nSun, see ![map](img/map.png).\n### Detail\n```\n## not a heading\n```\n# Top level stays\n## Notes ##\none\n## Notes\ntwo\n",
+        )
+        .unwrap();
+        // A name already taken on disk gets a suffix too.
+        fs::create_dir(vault.path().join("walk")).unwrap();
+        fs::write(vault.path().join("walk/morning.md"), "taken").unwrap();
+
+        let (edits, dir) = split_edits(&path, 2).unwrap();
+        assert_eq!(dir, vault.path().join("walk"));
+        let paths: Vec<&Path> = edits.iter().map(|e| e.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            [
+                path.clone(),
+                dir.join("morning-2.md"),
+                dir.join("notes.md"),
+                dir.join("notes-2.md"),
+            ]
+        );
+
+        let index = edits[0].after.as_deref().unwrap();
+        assert_eq!(split_frontmatter(index).1, "Intro.\n\n");
+        let fields = fields_of(index);
+        assert_eq!(fields.get("title").and_then(Value::as_str), Some("Walk"));
+        assert_eq!(
+            fields.get("part_of").and_then(Value::as_str),
+            Some("[Home](index.md)")
+        );
+        assert_eq!(
+            strings(fields.get("contents")),
+            [
+                "[Old](old.md)",
+                "[Morning](walk/morning-2.md)",
+                "[Notes](walk/notes.md)",
+                "[Notes](walk/notes-2.md)",
+            ]
+        );
+
+        let morning = edits[1].after.as_deref().unwrap();
+        assert_eq!(edits[1].before, "");
+        let fields = fields_of(morning);
+        assert_eq!(fields.get("title").and_then(Value::as_str), Some("Morning"));
+        assert_eq!(
+            fields.get("part_of").and_then(Value::as_str),
+            Some("[Walk](../walk.md)")
+        );
+        assert_eq!(strings(fields.get("tags")), ["outdoors"]);
+        assert!(!fields.contains_key("contents"));
+        assert_eq!(
+            split_frontmatter(morning).1,
+            "## Morning\nSun, see ![map](../img/map.png).\n### Detail\n```\n## not a heading\n```\n# Top level stays\n"
+        );
+        assert_eq!(
+            split_frontmatter(edits[3].after.as_deref().unwrap()).1,
+            "## Notes\ntwo\n"
+        );
+    }
+
+    #[test]
+    fn only_headings_of_the_level_split() {
+        let (preamble, sections) = split_sections("# A\n## a1\n#tag\n# B ##\n", 1);
+        assert_eq!(preamble, "");
+        let headings: Vec<&str> = sections.iter().map(|s| s.heading.as_str()).collect();
+        assert_eq!(headings, ["A", "B"]);
+        assert_eq!(sections[0].text, "# A\n## a1\n#tag\n");
+
+        let vault = tempfile::tempdir().unwrap();
+        let path = vault.path().join("flat.md");
+        fs::write(&path, "---\ntitle: Flat\n---\n### Deep only\n").unwrap();
+        let err = split_edits(&path, 2).unwrap_err();
+        assert!(err.to_string().contains("No h2 headings"), "{err}");
+    }
+}
@@ -0,0 +1,571 @@
+/*!
+ * `diaryx sync`: two-way synchronization of the vault's sources (not the built site) with a
+ * remote copy, through `rsync` (a local folder or `host:path` over ssh) or `rclone` (any rclone
+ * remote).
+ *
+ * The remote is first mirrored into `.diaryx/sync/remote`, so every decision is made locally.
+ * Each file is compared with its content hash at the last sync (kept in
+ * `.diaryx/sync/state.json`):
+ *
+ * - changed on one side only: that side's version is copied over (a deletion is carried over
+ *   too, locally into the trash);
+ * - changed on both sides: a conflict. The local version stays, the remote one is saved next
+ *   to it as `<name>.sync-conflict.<ext>`, and the file is left out of syncs until that copy is
+ *   removed, after which the local version is sent. A file that is new on both sides is not a
+ *   conflict when one copy has a strictly later `updated` timestamp: that copy wins.
+ *
+ * Finally the mirror, with the local changes applied, is pushed back to the remote. The push
+ * skips remote files that are newer than their mirrored copy (changed during the sync, picked up
+ * by the next one), but it does delete what the mirror lacks: a file created on the remote while
+ * a sync runs is removed, so only one machine should sync a remote at a time.
+ *
+ * A sync that would delete most of either side (an empty, unmounted or wrong remote looks like
+ * every file was deleted there) is refused unless `--force` is given; see [`mass_deletion`].
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::archive::{entry_name, files_under, sha256_hex};
+use crate::trash;
+use crate::vault::{parse_fields, split_frontmatter};
+
+/// Folder (under the vault root) with the remote mirror and the sync state.
+const SYNC_DIR: &str = ".diaryx/sync";
+/// Marker in the name of a conflict copy (`notes/a.sync-conflict.md`).
+const CONFLICT_MARKER: &str = ".sync-conflict";
+/// Without `--force`, a sync may delete at most this share (in percent) of either side's files.
+const MAX_DELETED_PERCENT: usize = 50;
+
+/// Transfer tool used to reach the remote.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// `rsync -a`: a local folder, or `[user@]host:path` over ssh.
+    Rsync,
+    /// `rclone sync`: any configured rclone remote (`drive:journal`).
+    Rclone,
+}
+
+impl Backend {
+    /// Make the local `mirror` an exact copy of `remote`.
+    fn fetch(self, remote: &str, mirror: &str) -> Result<()> {
+        self.mirror(remote, mirror, &[])
+    }
+
+    /// Send `mirror` to `remote`, leaving alone remote files newer than their mirrored copy.
+    fn push(self, mirror: &str, remote: &str) -> Result<()> {
+        self.mirror(mirror, remote, &["--update"])
+    }
+
+    /// Make `to` a copy of `from` (either may be the remote), leaving out the vault-local
+    /// `.diaryx` folder.
+    fn mirror(self, from: &str, to: &str, flags: &[&str]) -> Result<()> {
+        let mut cmd = match self {
+            Backend::Rsync => {
+                let mut cmd = Command::new("rsync");
+                cmd.args(["-a", "--delete", "--exclude=/.diaryx/"])
+                    .args(flags)
+                    .arg("--")
+                    .arg(format!("{}/", from.trim_end_matches('/')))
+                    .arg(format!("{}/", to.trim_end_matches('/')));
+                cmd
+            }
+            Backend::Rclone => {
+                let mut cmd = Command::new("rclone");
+                cmd.args(["sync", "--exclude", "/.diaryx/**"])
+                    .args(flags)
+                    .args(["--", from, to]);
+                cmd
+            }
+        };
+        let tool = cmd.get_program().to_string_lossy().into_owned();
+        let status = cmd
+            .status()
+            .with_context(|| format!("Failed to run `{tool}`; is it installed and on PATH?"))?;
+        if !status.success() {
+            bail!("`{tool}` failed copying {from} to {to} ({status})");
+        }
+        Ok(())
+    }
+}
+
+pub struct SyncOptions {
+    /// Vault folder (the root index's folder).
+    pub root: PathBuf,
+    pub backend: Backend,
+    pub remote: String,
+    /// Relative paths left out of the sync; a trailing `/` names a folder.
+    pub exclude: Vec<String>,
+    /// Only print what would be done.
+    pub dry_run: bool,
+    /// Sync even when it deletes most of either side.
+    pub force: bool,
+}
+
+/// What the last sync left behind.
+#[derive(Default, Serialize, Deserialize)]
+struct SyncState {
+    remote: String,
+    /// Relative path -> SHA-256 of the contents both sides agreed on.
+    files: BTreeMap<String, String>,
+    /// Relative path -> SHA-256 of the remote version when the conflict was found.
+    #[serde(default)]
+    conflicts: BTreeMap<String, String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    Pull,
+    Push,
+    DeleteLocal,
+    DeleteRemote,
+    Conflict,
+}
+
+/// Run one sync of `opts.root` with `opts.remote`.
+pub fn run_sync(opts: &SyncOptions) -> Result<()> {
+    let sync_dir = opts.root.join(SYNC_DIR);
+    let mirror = sync_dir.join("remote");
+    let state_path = sync_dir.join("state.json");
+    let mut state: SyncState = match fs::read_to_string(&state_path) {
+        Ok(text) => serde_json::from_str(&text)
+            .with_context(|| format!("Corrupt sync state {}", state_path.display()))?,
+        Err(_) => SyncState::default(),
+    };
+    if state.remote != opts.remote {
+        // A different remote: nothing is known about what it holds.
+        state = SyncState {
+            remote: opts.remote.clone(),
+            ..SyncState::default()
+        };
+        let _ = fs::remove_dir_all(&mirror);
+    }
+
+    fs::create_dir_all(&mirror).with_context(|| format!("Failed creating {}", mirror.display()))?;
+    let mirror_str = mirror.to_string_lossy().into_owned();
+    opts.backend
+        .fetch(&opts.remote, &mirror_str)
+        .with_context(|| format!("Fetching {} failed", opts.remote))?;
+
+    let local = hashes(&opts.root, &opts.exclude)?;
+    let remote = hashes(&mirror, &opts.exclude)?;
+    let plan = plan(
+        &local,
+        &remote,
+        &state,
+        |path| conflict_copy(&opts.root.join(path)).exists(),
+        |path| newer(&opts.root.join(path), &mirror.join(path)),
+    );
+
+    let count = |wanted: Action| plan.iter().filter(|(_, a)| *a == wanted).count();
+    for (path, action) in &plan {
+        let verb = match action {
+            Action::Pull => "pull",
+            Action::Push => "push",
+            Action::DeleteLocal => "delete locally",
+            Action::DeleteRemote => "delete remotely",
+            Action::Conflict => "CONFLICT",
+        };
+        println!("[sync] {verb} {path}");
+    }
+    let summary = format!(
+        "{} pulled, {} pushed, {} deleted locally, {} deleted remotely, {} conflict(s)",
+        count(Action::Pull),
+        count(Action::Push),
+        count(Action::DeleteLocal),
+        count(Action::DeleteRemote),
+        count(Action::Conflict)
+    );
+    let refused = (!opts.force)
+        .then(|| mass_deletion(&plan, &local, &remote))
+        .flatten();
+    if opts.dry_run {
+        println!("[sync] (dry run) {summary}");
+        if let Some(problem) = refused {
+            println!("[sync] (dry run) the sync would be refused without --force: {problem}");
+        }
+        return Ok(());
+    }
+    if let Some(problem) = refused {
+        bail!(
+            "Refusing to sync with {}: {problem}. Check that the remote is the right one (and \
+             mounted); pass --force if these deletions are intended",
+            opts.remote
+        );
+    }
+
+    let mut trashed = Vec::new();
+    for (path, action) in &plan {
+        let (here, there) = (opts.root.join(path), mirror.join(path));
+        match action {
+            Action::Pull => copy(&there, &here)?,
+            Action::Push => {
+                copy(&here, &there)?;
+                state.conflicts.remove(path);
+            }
+            Action::DeleteLocal => trashed.push(here),
+            Action::DeleteRemote => {
+                fs::remove_file(&there)
+                    .with_context(|| format!("Failed deleting {}", there.display()))?;
+                state.conflicts.remove(path);
+            }
+            Action::Conflict => {
+                copy(&there, &conflict_copy(&here))?;
+                state
+                    .conflicts
+                    .insert(path.clone(), remote.get(path).cloned().unwrap_or_default());
+            }
+        }
+    }
+    let trashed: Vec<&Path> = trashed.iter().map(PathBuf::as_path).collect();
+    trash::discard(&opts.root, &trashed, &format!("sync {}", opts.remote))?;
+    opts.backend
+        .push(&mirror_str, &opts.remote)
+        .with_context(|| format!("Sending to {} failed", opts.remote))?;
+
+    let local = hashes(&opts.root, &opts.exclude)?;
+    let remote = hashes(&mirror, &opts.exclude)?;
+    state
+        .conflicts
+        .retain(|path, _| local.get(path) != remote.get(path));
+    state.files = local
+        .into_iter()
+        .filter(|(path, hash)| remote.get(path) == Some(hash))
+        .collect();
+    fs::write(&state_path, serde_json::to_string_pretty(&state)?)
+        .with_context(|| format!("Failed writing {}", state_path.display()))?;
+    println!("[sync] {summary}");
+    if !state.conflicts.is_empty() {
+        eprintln!(
+            "[sync] {} file(s) in conflict; merge the *{CONFLICT_MARKER}.* copies into them and \
+             delete the copies, then sync again",
+            state.conflicts.len()
+        );
+    }
+    Ok(())
+}
+
+/// What to do with each file that differs between `local` and `remote` (relative path -> hash),
+/// given what both sides held after the last sync. `pending` tells whether a conflicted file's
+/// conflict copy is still there; `newer` whether the local copy of a file new on both sides is
+/// the later one (`None`: neither is).
+fn plan(
+    local: &BTreeMap<String, String>,
+    remote: &BTreeMap<String, String>,
+    state: &SyncState,
+    pending: impl Fn(&str) -> bool,
+    newer: impl Fn(&str) -> Option<bool>,
+) -> Vec<(String, Action)> {
+    let paths: BTreeSet<&String> = local
+        .keys()
+        .chain(remote.keys())
+        .chain(state.files.keys())
+        .collect();
+
+    let mut plan = Vec::new();
+    for path in paths {
+        let (l, r) = (local.get(path), remote.get(path));
+        let base = state.files.get(path);
+        let action = if l == r {
+            continue;
+        } else if let Some(at_conflict) = state.conflicts.get(path) {
+            if pending(path) {
+                continue;
+            } else if r == Some(at_conflict) || r.is_none() {
+                // Resolved locally (the conflict copy is gone): the local version goes out.
+                if l.is_some() {
+                    Action::Push
+                } else {
+                    Action::DeleteRemote
+                }
+            } else {
+                Action::Conflict
+            }
+        } else if l == base {
+            if r.is_some() {
+                Action::Pull
+            } else {
+                Action::DeleteLocal
+            }
+        } else if r == base {
+            if l.is_some() {
+                Action::Push
+            } else {
+                Action::DeleteRemote
+            }
+        } else if l.is_none() {
+            // Deleted here, edited there: keep the edit.
+            Action::Pull
+        } else if r.is_none() {
+            Action::Push
+        } else if base.is_none() {
+            match newer(path) {
+                Some(true) => Action::Push,
+                Some(false) => Action::Pull,
+                None => Action::Conflict,
+            }
+        } else {
+            Action::Conflict
+        };
+        plan.push((path.clone(), action));
+    }
+    plan
+}
+
+/// Why `plan` looks like a mistake rather than deliberate deletions: the remote holds no files
+/// at all while local ones would be deleted, or more than [`MAX_DELETED_PERCENT`] of either
+/// side's files would be deleted. `None` when it looks fine.
+fn mass_deletion(
+    plan: &[(String, Action)],
+    local: &BTreeMap<String, String>,
+    remote: &BTreeMap<String, String>,
+) -> Option<String> {
+    let count = |wanted: Action| plan.iter().filter(|(_, a)| *a == wanted).count();
+    let (here, there) = (count(Action::DeleteLocal), count(Action::DeleteRemote));
+    if remote.is_empty() && here > 0 {
+        return Some(format!(
+            "the remote has no files, so all {here} local file(s) would be deleted"
+        ));
+    }
+    for (deleted, total, side) in [
+        (here, local.len(), "local"),
+        (there, remote.len(), "remote"),
+    ] {
+        if deleted * 100 > total * MAX_DELETED_PERCENT {
+            return Some(format!(
+                "{deleted} of {total} {side} file(s) would be deleted"
+            ));
+        }
+    }
+    None
+}
+
+/// Relative path -> SHA-256 of every synced file under `dir`.
+fn hashes(dir: &Path, exclude: &[String]) -> Result<BTreeMap<String, String>> {
+    let mut out = BTreeMap::new();
+    for path in files_under(dir)? {
+        let rel = entry_name(path.strip_prefix(dir).unwrap_or(&path));
+        let skipped = rel.starts_with(".diaryx/")
+            || rel.starts_with(".git/")
+            || rel.contains(CONFLICT_MARKER)
+            || exclude.iter().any(|pattern| {
+                rel == *pattern || (pattern.ends_with('/') && rel.starts_with(pattern.as_str()))
+            });
+        if skipped {
+            continue;
+        }
+        let data = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        out.insert(rel, sha256_hex(&data));
+    }
+    Ok(out)
+}
+
+/// `notes/a.md` -> `notes/a.sync-conflict.md`.
+pub fn conflict_copy(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}{CONFLICT_MARKER}.{}", ext.to_string_lossy()),
+        None => format!("{stem}{CONFLICT_MARKER}"),
+    };
+    path.with_file_name(name)
+}
+
+/// `notes/a.sync-conflict.md` -> `notes/a.md`; `None` for a path that is not a conflict copy.
+pub fn conflict_original(copy: &Path) -> Option<PathBuf> {
+    let name = copy.file_name()?.to_str()?;
+    let (stem, ext) = name.split_once(CONFLICT_MARKER)?;
+    Some(copy.with_file_name(format!("{stem}{ext}")))
+}
+
+/// Whether the local copy's `updated` is later than the remote copy's (`None` when either
+/// lacks an RFC 3339 `updated`, or they are equal).
+fn newer(local: &Path, remote: &Path) -> Option<bool> {
+    let updated = |path: &Path| {
+        let text = fs::read_to_string(path).ok()?;
+        let fields = parse_fields(split_frontmatter(&text).0);
+        let value = fields.get("updated")?.as_str()?.to_string();
+        OffsetDateTime::parse(&value, &Rfc3339).ok()
+    };
+    let (here, there) = (updated(local)?, updated(remote)?);
+    (here != there).then_some(here > there)
+}
+
+fn copy(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed creating {}", parent.display()))?;
+    }
+    fs::copy(from, to)
+        .with_context(|| format!("Failed copying {} to {}", from.display(), to.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(path, hash)| (path.to_string(), hash.to_string()))
+            .collect()
+    }
+
+    fn actions(
+        local: &[(&str, &str)],
+        remote: &[(&str, &str)],
+        state: &SyncState,
+    ) -> Vec<(String, Action)> {
+        plan(
+            &files(local),
+            &files(remote),
+            state,
+            |path| path == "pending.md",
+            |path| match path {
+                "later-here.md" => Some(true),
+                "later-there.md" => Some(false),
+                _ => None,
+            },
+        )
+    }
+
+    fn state(synced: &[(&str, &str)], conflicts: &[(&str, &str)]) -> SyncState {
+        SyncState {
+            remote: "backup:journal".to_string(),
+            files: files(synced),
+            conflicts: files(conflicts),
+        }
+    }
+
+    fn only(path: &str, action: Action) -> Vec<(String, Action)> {
+        vec![(path.to_string(), action)]
+    }
+
+    #[test]
+    fn one_sided_changes_carry_over() {
+        let base = state(&[("a.md", "1")], &[]);
+        assert!(actions(&[("a.md", "1")], &[("a.md", "1")], &base).is_empty());
+        assert_eq!(
+            actions(&[("a.md", "1")], &[("a.md", "2")], &base),
+            only("a.md", Action::Pull)
+        );
+        assert_eq!(
+            actions(&[("a.md", "2")], &[("a.md", "1")], &base),
+            only("a.md", Action::Push)
+        );
+        assert_eq!(
+            actions(&[("a.md", "1")], &[], &base),
+            only("a.md", Action::DeleteLocal)
+        );
+        assert_eq!(
+            actions(&[], &[("a.md", "1")], &base),
+            only("a.md", Action::DeleteRemote)
+        );
+        // New on one side only
+        let empty = state(&[], &[]);
+        assert_eq!(
+            actions(&[], &[("b.md", "1")], &empty),
+            only("b.md", Action::Pull)
+        );
+        assert_eq!(
+            actions(&[("b.md", "1")], &[], &empty),
+            only("b.md", Action::Push)
+        );
+    }
+
+    #[test]
+    fn edits_on_both_sides_conflict_unless_one_is_a_deletion() {
+        let base = state(&[("a.md", "1")], &[]);
+        assert_eq!(
+            actions(&[("a.md", "2")], &[("a.md", "3")], &base),
+            only("a.md", Action::Conflict)
+        );
+        assert_eq!(
+            actions(&[], &[("a.md", "3")], &base),
+            only("a.md", Action::Pull)
+        );
+        assert_eq!(
+            actions(&[("a.md", "2")], &[], &base),
+            only("a.md", Action::Push)
+        );
+    }
+
+    #[test]
+    fn files_new_on_both_sides_go_to_the_later_update() {
+        let empty = state(&[], &[]);
+        let both = |path| actions(&[(path, "1")], &[(path, "2")], &empty);
+        assert_eq!(both("later-here.md"), only("later-here.md", Action::Push));
+        assert_eq!(both("later-there.md"), only("later-there.md", Action::Pull));
+        assert_eq!(both("same.md"), only("same.md", Action::Conflict));
+    }
+
+    #[test]
+    fn conflicts_wait_for_their_copy_to_be_removed() {
+        let conflicted = state(&[], &[("pending.md", "3"), ("a.md", "3")]);
+        // Copy still there: skipped
+        assert!(actions(&[("pending.md", "2")], &[("pending.md", "3")], &conflicted).is_empty());
+        // Copy removed, remote unchanged since: the local version goes out
+        assert_eq!(
+            actions(&[("a.md", "4")], &[("a.md", "3")], &conflicted),
+            only("a.md", Action::Push)
+        );
+        assert_eq!(
+            actions(&[], &[("a.md", "3")], &conflicted),
+            only("a.md", Action::DeleteRemote)
+        );
+        // ... but the remote changed again meanwhile: a new conflict
+        assert_eq!(
+            actions(&[("a.md", "4")], &[("a.md", "5")], &conflicted),
+            only("a.md", Action::Conflict)
+        );
+    }
+
+    #[test]
+    fn an_empty_remote_deletes_nothing_without_force() {
+        let synced = [("a.md", "1"), ("b.md", "2"), ("notes/c.md", "3")];
+        let base = state(&synced, &[]);
+        let local = files(&synced);
+        let empty = files(&[]);
+        let plan = plan(&local, &empty, &base, |_| false, |_| None);
+        assert_eq!(plan.len(), 3);
+        assert!(plan.iter().all(|(_, a)| *a == Action::DeleteLocal));
+        assert!(
+            mass_deletion(&plan, &local, &empty)
+                .unwrap()
+                .contains("remote has no files")
+        );
+
+        // Most of one side going away is refused too; a few deletions are not.
+        let remote = files(&[("a.md", "1")]);
+        let plan = super::plan(&local, &remote, &base, |_| false, |_| None);
+        assert!(mass_deletion(&plan, &local, &remote).is_some());
+        let remote = files(&[("a.md", "1"), ("b.md", "2")]);
+        let plan = super::plan(&local, &remote, &base, |_| false, |_| None);
+        assert_eq!(plan, only("notes/c.md", Action::DeleteLocal));
+        assert!(mass_deletion(&plan, &local, &remote).is_none());
+    }
+
+    #[test]
+    fn conflict_copies_name_their_original() {
+        let copy = conflict_copy(Path::new("notes/a.md"));
+        assert_eq!(copy, Path::new("notes/a.sync-conflict.md"));
+        assert_eq!(conflict_original(&copy).unwrap(), Path::new("notes/a.md"));
+        assert_eq!(
+            conflict_copy(Path::new("README")),
+            Path::new("README.sync-conflict")
+        );
+        assert!(conflict_original(Path::new("notes/a.md")).is_none());
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use serde_yaml::Value;
+
+use crate::query::{Filter, select_entries};
+use crate::vault::{
+    EditMode, FileEdit, apply_edits_with_undo, parse_fields, remove_field, set_field,
+    split_frontmatter,
+};
+
+/// A change to entry `tags` applied by `tag`. Tag names compare case-insensitively.
+pub enum TagChange {
+    Add(String),
+    Remove(String),
+    /// Rename everywhere; entries that already carry the new tag just lose the old one.
+    Rename {
+        from: String,
+        to: String,
+    },
+}
+
+/// `tag add|remove|rename`: edit the `tags` list of every matching entry. A list emptied by
+/// `remove` drops the field. Applied changes are journaled for `undo`.
+pub fn run_tag(
+    input: &Path,
+    change: &TagChange,
+    filter: Option<&Filter>,
+    mode: EditMode,
+    command: &str,
+) -> Result<()> {
+    let root = input.parent().unwrap_or(Path::new("."));
+    let selected = select_entries(input, filter)?;
+    if selected.is_empty() && filter.is_some() {
+        bail!("No entries match the filter");
+    }
+    let mut edits = Vec::new();
+    for entry in &selected {
+        let (fm, body) = split_frontmatter(&entry.text);
+        let old = entry_tags(&parse_fields(fm));
+        let has = |tag: &str| old.iter().any(|t| t.eq_ignore_ascii_case(tag));
+        let new: Vec<String> = match change {
+            TagChange::Add(tag) if !has(tag) => old.iter().cloned().chain([tag.clone()]).collect(),
+            TagChange::Remove(tag) | TagChange::Rename { from: tag, .. } if has(tag) => {
+                let replacement = match change {
+                    TagChange::Rename { to, .. } => Some(to),
+                    _ => None,
+                };
+                let mut kept: Vec<String> = Vec::new();
+                for t in &old {
+                    let t = match replacement {
+                        _ if !t.eq_ignore_ascii_case(tag) => t,
+                        Some(to) => to,
+                        None => continue,
+                    };
+                    if !kept.iter().any(|k| k.eq_ignore_ascii_case(t)) {
+                        kept.push(t.clone());
+                    }
+                }
+                kept
+            }
+            _ => continue,
+        };
+        let new_fm = if new.is_empty() {
+            remove_field(fm, "tags")
+        } else {
+            set_field(
+                fm,
+                "tags",
+                &Value::Sequence(new.into_iter().map(Value::String).collect()),
+            )
+        };
+        edits.push(FileEdit {
+            path: entry.path.clone(),
+            before: entry.text.clone(),
+            after: Some(new_fm + body),
+        });
+    }
+    let applied = apply_edits_with_undo(&edits, mode, root, command)?;
+    println!(
+        "[tag] {}{} of {} matching entr{} changed",
+        if mode.dry_run { "(dry run) " } else { "" },
+        applied.len(),
+        selected.len(),
+        if selected.len() == 1 { "y" } else { "ies" }
+    );
+    Ok(())
+}
+
+/// What `tag list` prints.
+#[derive(Clone, Copy)]
+pub enum TagListing {
+    /// One tag per line, sorted by name.
+    Names,
+    /// `<count>\t<tag>`, most used first.
+    Counts,
+    /// `<tag>\t<entry>` for tags used by a single entry (usually typos or one-off leftovers).
+    Orphans,
+}
+
+/// `tag list`: tags used by matching entries. Spellings differing only in case are reported
+/// together under the first one seen.
+pub fn run_tag_list(input: &Path, filter: Option<&Filter>, listing: TagListing) -> Result<()> {
+    let root = input.parent().unwrap_or(Path::new("."));
+    // Lowercased tag -> (display spelling, entries using it).
+    let mut tags: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+    for entry in select_entries(input, filter)? {
+        let (fm, _) = split_frontmatter(&entry.text);
+        let rel = entry
+            .path
+            .strip_prefix(root)
+            .unwrap_or(&entry.path)
+            .display()
+            .to_string();
+        for tag in entry_tags(&parse_fields(fm)) {
+            let (_, entries) = tags
+                .entry(tag.to_lowercase())
+                .or_insert_with(|| (tag.clone(), Vec::new()));
+            if !entries.contains(&rel) {
+                entries.push(rel.clone());
+            }
+        }
+    }
+    match listing {
+        TagListing::Names => {
+            for (tag, _) in tags.values() {
+                println!("{tag}");
+            }
+        }
+        TagListing::Counts => {
+            let mut counted: Vec<_> = tags.values().collect();
+            counted.sort_by_key(|(_, entries)| std::cmp::Reverse(entries.len()));
+            for (tag, entries) in counted {
+                println!("{}\t{tag}", entries.len());
+            }
+        }
+        TagListing::Orphans => {
+            for (tag, entries) in tags.values() {
+                if let [entry] = entries.as_slice() {
+                    println!("{tag}\t{entry}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Tags of an entry: the `tags` list, or a single scalar tag.
+pub(crate) fn entry_tags(fields: &serde_yaml::Mapping) -> Vec<String> {
+    let text = |v: &Value| match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    };
+    match fields.get("tags") {
+        Some(Value::Sequence(items)) => items.iter().filter_map(text).collect(),
+        Some(other) => text(other).into_iter().collect(),
+        None => Vec::new(),
+    }
+}
@@ -0,0 +1,327 @@
+/*!
+ * `.diaryx/trash`: files that editing commands delete (the entries `merge` folds into its
+ * target, files an `undo` takes back) are moved here instead of being removed, and so are the
+ * previous versions of the files they rewrite, so a mistaken edit or deletion can be recovered
+ * even after the undo journal has moved on.
+ *
+ * Each deletion is one batch: `<stamp>/` holds the files at their paths relative to the vault
+ * root, and `<stamp>.json` records where they came from, when, and which command removed them.
+ */
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Directory (under the vault root) holding trashed files.
+pub const TRASH_DIR: &str = ".diaryx/trash";
+
+/// One deletion, as recorded next to its files.
+#[derive(Serialize, Deserialize)]
+struct TrashBatch {
+    deleted: String,
+    command: String,
+    files: Vec<TrashFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TrashFile {
+    /// Where the file lived (absolute).
+    original: PathBuf,
+    /// Where it is kept, relative to the batch folder.
+    stored: PathBuf,
+}
+
+/// Move `files` into a new trash batch under `vault_root`.
+pub fn discard(vault_root: &Path, files: &[&Path], command: &str) -> Result<()> {
+    if files.is_empty() {
+        return Ok(());
+    }
+    let vault_root = std::path::absolute(vault_root)?;
+    let dir = vault_root.join(TRASH_DIR);
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let mut id = format!("{stamp:015}");
+    let mut n = 1;
+    while dir.join(&id).exists() || dir.join(format!("{id}.json")).exists() {
+        id = format!("{stamp:015}-{n}");
+        n += 1;
+    }
+    let batch_dir = dir.join(&id);
+    let mut batch = TrashBatch {
+        deleted: OffsetDateTime::now_utc().format(&Rfc3339)?,
+        command: command.to_string(),
+        files: Vec::new(),
+    };
+    let record = dir.join(format!("{id}.json"));
+    for file in files {
+        let original = std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf());
+        let stored = match original.strip_prefix(&vault_root) {
+            Ok(rel) => rel.to_path_buf(),
+            Err(_) => PathBuf::from(original.file_name().unwrap_or_default()),
+        };
+        let stored = unique_in(&batch, stored);
+        move_file(&original, &batch_dir.join(&stored))?;
+        batch.files.push(TrashFile { original, stored });
+        // Recorded after every move, so a failure part-way leaves the moved files restorable.
+        fs::write(&record, serde_json::to_string_pretty(&batch)?)
+            .with_context(|| format!("Failed writing {}", record.display()))?;
+    }
+    Ok(())
+}
+
+/// `stored`, or `<stem>-<n>.<ext>` beside it when another file of `batch` is kept there (files
+/// from outside the vault are kept by name only).
+fn unique_in(batch: &TrashBatch, stored: PathBuf) -> PathBuf {
+    let taken = |path: &Path| batch.files.iter().any(|f| f.stored == path);
+    if !taken(&stored) {
+        return stored;
+    }
+    let stem = stored.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = stored
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| stored.with_file_name(format!("{stem}-{n}{ext}")))
+        .find(|candidate| !taken(candidate))
+        .unwrap_or(stored)
+}
+
+/// `trash list`: every trashed file, newest batch first, as `<id>\t<deleted>\t<original path>`.
+pub fn run_list(vault_root: &Path) -> Result<()> {
+    let batches = batches(vault_root)?;
+    if batches.is_empty() {
+        println!("[trash] empty");
+    }
+    for (id, batch) in batches.iter().rev() {
+        for file in &batch.files {
+            println!("{id}\t{}\t{}", batch.deleted, file.original.display());
+        }
+    }
+    Ok(())
+}
+
+/// `trash restore`: move the files of batch `which` (or, when `which` is a path, the most
+/// recently trashed copy of that file) back where they were. Files that exist again are left
+/// alone (and the restore refused) unless `force` is set.
+pub fn run_restore(vault_root: &Path, which: &str, force: bool) -> Result<()> {
+    let dir = vault_root.join(TRASH_DIR);
+    let batches = batches(vault_root)?;
+    // The file is gone, so only its folder can be canonicalized (`../notes/a.md`).
+    let path = Path::new(which);
+    let wanted = path
+        .parent()
+        .map(|p| {
+            if p.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                p
+            }
+        })
+        .and_then(|p| p.canonicalize().ok())
+        .zip(path.file_name())
+        .map(|(dir, name)| dir.join(name));
+    let (id, files) = match batches.iter().find(|(id, _)| id == which) {
+        Some((id, batch)) => (id, batch.files.iter().collect::<Vec<_>>()),
+        None => {
+            let Some((id, file)) = batches.iter().rev().find_map(|(id, batch)| {
+                batch
+                    .files
+                    .iter()
+                    .find(|f| Some(&f.original) == wanted.as_ref())
+                    .map(|f| (id, f))
+            }) else {
+                bail!("Nothing in the trash matches '{which}' (see `diaryx trash list`)");
+            };
+            (id, vec![file])
+        }
+    };
+    let existing: Vec<String> = files
+        .iter()
+        .filter(|f| f.original.exists())
+        .map(|f| format!("  {}", f.original.display()))
+        .collect();
+    if !existing.is_empty() && !force {
+        bail!(
+            "Files exist again; re-run with --force to overwrite them:\n{}",
+            existing.join("\n")
+        );
+    }
+    let batch_dir = dir.join(id);
+    for file in &files {
+        move_file(&batch_dir.join(&file.stored), &file.original)?;
+        println!("[trash] restored {}", file.original.display());
+    }
+    forget_restored(&dir, id)
+}
+
+/// `trash empty`: delete trashed files for good, all of them or only batches older than
+/// `older_than`.
+pub fn run_empty(vault_root: &Path, older_than: Option<Duration>) -> Result<()> {
+    let dir = vault_root.join(TRASH_DIR);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut removed = 0;
+    for (id, batch) in batches(vault_root)? {
+        if let Some(age) = older_than {
+            let millis: u128 = id.split('-').next().unwrap_or("").parse().unwrap_or(0);
+            if now.as_millis().saturating_sub(millis) < age.as_millis() {
+                continue;
+            }
+        }
+        let batch_dir = dir.join(&id);
+        if batch_dir.exists() {
+            fs::remove_dir_all(&batch_dir)
+                .with_context(|| format!("Failed removing {}", batch_dir.display()))?;
+        }
+        let record = dir.join(format!("{id}.json"));
+        fs::remove_file(&record)
+            .with_context(|| format!("Failed removing {}", record.display()))?;
+        removed += batch.files.len();
+    }
+    println!("[trash] permanently deleted {removed} file(s)");
+    Ok(())
+}
+
+/// Batches under `vault_root`, oldest first (ids are zero-padded timestamps).
+fn batches(vault_root: &Path) -> Result<Vec<(String, TrashBatch)>> {
+    let dir = vault_root.join(TRASH_DIR);
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut records: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "json"))
+        .collect();
+    records.sort();
+    records
+        .into_iter()
+        .map(|path| {
+            let id = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let batch = serde_json::from_str(
+                &fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?,
+            )
+            .with_context(|| format!("Corrupt trash record {}", path.display()))?;
+            Ok((id, batch))
+        })
+        .collect()
+}
+
+/// Drop restored files from batch `id`'s record, and the batch once it is empty.
+fn forget_restored(dir: &Path, id: &str) -> Result<()> {
+    let record = dir.join(format!("{id}.json"));
+    let mut batch: TrashBatch = serde_json::from_str(
+        &fs::read_to_string(&record)
+            .with_context(|| format!("Failed to read {}", record.display()))?,
+    )
+    .with_context(|| format!("Corrupt trash record {}", record.display()))?;
+    let batch_dir = dir.join(id);
+    batch.files.retain(|f| batch_dir.join(&f.stored).exists());
+    if batch.files.is_empty() {
+        let _ = fs::remove_dir_all(&batch_dir);
+        fs::remove_file(&record).with_context(|| format!("Failed removing {}", record.display()))
+    } else {
+        fs::write(&record, serde_json::to_string_pretty(&batch)?)
+            .with_context(|| format!("Failed writing {}", record.display()))
+    }
+}
+
+/// Rename `from` to `to`, creating `to`'s folder, copying when they are on different devices.
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed creating {}", parent.display()))?;
+    }
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)
+            .with_context(|| format!("Failed moving {} to {}", from.display(), to.display()))?;
+        fs::remove_file(from).with_context(|| format!("Failed deleting {}", from.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, text: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn discarded_files_are_restored_in_place() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path().canonicalize().unwrap();
+        let (a, b) = (root.join("a.md"), root.join("notes/b.md"));
+        write(&a, "a");
+        write(&b, "b");
+
+        discard(&root, &[&a, &b], "merge").unwrap();
+        assert!(!a.exists() && !b.exists());
+        let trashed = batches(&root).unwrap();
+        assert_eq!(trashed.len(), 1);
+        let (id, batch) = &trashed[0];
+        assert_eq!(batch.command, "merge");
+        assert_eq!(batch.files[1].stored, Path::new("notes/b.md"));
+
+        // One file by path, and the rest of the batch by id
+        run_restore(&root, &b.to_string_lossy(), false).unwrap();
+        assert_eq!(fs::read_to_string(&b).unwrap(), "b");
+        write(&a, "new a");
+        assert!(run_restore(&root, id, false).is_err());
+        run_restore(&root, id, true).unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "a");
+        assert!(batches(&root).unwrap().is_empty());
+    }
+
+    #[test]
+    fn files_from_outside_the_vault_keep_distinct_copies() {
+        let vault = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        let root = vault.path().canonicalize().unwrap();
+        let (first, second) = (
+            elsewhere.path().join("one/a.md"),
+            elsewhere.path().join("two/a.md"),
+        );
+        write(&first, "first");
+        write(&second, "second");
+
+        discard(&root, &[&first, &second], "undo").unwrap();
+        let (id, batch) = batches(&root).unwrap().remove(0);
+        let stored: Vec<&Path> = batch.files.iter().map(|f| f.stored.as_path()).collect();
+        assert_eq!(stored, [Path::new("a.md"), Path::new("a-2.md")]);
+
+        run_restore(&root, &id, false).unwrap();
+        assert_eq!(fs::read_to_string(&first).unwrap(), "first");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "second");
+    }
+
+    #[test]
+    fn empty_keeps_recent_batches_when_asked() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path().canonicalize().unwrap();
+        let a = root.join("a.md");
+        write(&a, "a");
+        discard(&root, &[&a], "merge").unwrap();
+
+        run_empty(&root, Some(Duration::from_secs(3600))).unwrap();
+        assert_eq!(batches(&root).unwrap().len(), 1);
+        run_empty(&root, None).unwrap();
+        assert!(batches(&root).unwrap().is_empty());
+        assert_eq!(fs::read_dir(root.join(TRASH_DIR)).unwrap().count(), 0);
+    }
+}
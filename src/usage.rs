@@ -0,0 +1,231 @@
+/*!
+ * `.diaryx/usage.jsonl`: a local log of the commands run in a vault, for `diaryx usage` to
+ * summarize. Logging is off unless the vault's `diaryx.toml` sets `[usage] enabled = true`.
+ *
+ * Each line records when a command ran, which one (`build`, `meta set`; never its arguments or
+ * paths), how long it took, whether it succeeded, and the size of the vault at the time (entry
+ * count and bytes). Nothing is sent anywhere, and `sync` leaves `.diaryx` out, so the log stays
+ * on this machine; `diaryx usage --clear` deletes it.
+ */
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::{Date, OffsetDateTime};
+
+use crate::profile::format_bytes;
+
+/// The log, under the vault root.
+pub const USAGE_LOG: &str = ".diaryx/usage.jsonl";
+
+/// One command run, as logged.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    /// When it started (RFC 3339, UTC).
+    time: String,
+    command: String,
+    duration_ms: u64,
+    ok: bool,
+    /// Markdown files in the vault.
+    entries: usize,
+    /// Their total size.
+    bytes: u64,
+}
+
+/// A command being timed for the log.
+pub struct Recorder {
+    vault_root: PathBuf,
+    command: String,
+    time: OffsetDateTime,
+    started: Instant,
+}
+
+impl Recorder {
+    /// Start timing `command` when the vault in (or above) `dir` has usage logging enabled;
+    /// `None` otherwise, or when `dir` is in no vault.
+    pub fn start(command: &str, dir: &Path) -> Option<Self> {
+        let index = crate::vault::discover_root_index(dir).ok()??;
+        let vault_root = index.parent().unwrap_or(Path::new(".")).to_path_buf();
+        if !crate::config::load_usage(&vault_root).ok()?.enabled {
+            return None;
+        }
+        Some(Recorder {
+            vault_root,
+            command: command.to_string(),
+            time: OffsetDateTime::now_utc(),
+            started: Instant::now(),
+        })
+    }
+
+    /// Append the run to the log. A log that cannot be written only produces a warning.
+    pub fn finish(self, ok: bool) {
+        let duration = self.started.elapsed();
+        if let Err(e) = self.append(duration, ok) {
+            eprintln!("[warn] Could not write the usage log: {e:#}");
+        }
+    }
+
+    fn append(&self, duration: Duration, ok: bool) -> Result<()> {
+        let (entries, bytes) = vault_size(&self.vault_root);
+        let record = Record {
+            time: self.time.replace_nanosecond(0)?.format(&Rfc3339)?,
+            command: self.command.clone(),
+            duration_ms: duration.as_millis() as u64,
+            ok,
+            entries,
+            bytes,
+        };
+        let path = self.vault_root.join(USAGE_LOG);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Number and total size of the Markdown files under `root`, hidden folders left out.
+fn vault_size(root: &Path) -> (usize, u64) {
+    let (mut entries, mut bytes) = (0, 0);
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(read) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in read.flatten() {
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                stack.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("md"))
+            {
+                entries += 1;
+                bytes += meta.len();
+            }
+        }
+    }
+    (entries, bytes)
+}
+
+/// Runs of one command.
+#[derive(Default)]
+struct CommandStats {
+    runs: usize,
+    failed: usize,
+    total: Duration,
+    longest: Duration,
+}
+
+/// `usage`: summarize the vault's usage log (runs since `since`, a `YYYY-MM-DD` date, when
+/// given): runs, failures and durations per command, and how the vault grew. With `clear`,
+/// delete the log instead.
+pub fn run_usage(vault_root: &Path, since: Option<&str>, clear: bool) -> Result<()> {
+    let path = vault_root.join(USAGE_LOG);
+    let enabled = crate::config::load_usage(vault_root)?.enabled;
+    if clear {
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to delete {}", path.display()))?;
+            println!("[usage] deleted {}", path.display());
+        } else {
+            println!("[usage] no usage log to delete");
+        }
+        return Ok(());
+    }
+    let since = since
+        .map(|text| {
+            let format = time::format_description::parse("[year]-[month]-[day]")?;
+            Date::parse(text, &format)
+                .map_err(|_| anyhow!("Invalid --since '{text}': expected a date like 2025-01-31"))
+        })
+        .transpose()?;
+
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let records: Vec<Record> = text
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Record>(line).ok())
+        .filter(|record| {
+            since.is_none_or(|since| {
+                OffsetDateTime::parse(&record.time, &Rfc3339).is_ok_and(|at| at.date() >= since)
+            })
+        })
+        .collect();
+    if !enabled {
+        println!(
+            "[usage] usage logging is off; set `enabled = true` in the [usage] table of diaryx.toml to log commands run in this vault"
+        );
+    }
+    let (Some(first), Some(last)) = (records.first(), records.last()) else {
+        println!("[usage] no commands logged");
+        return Ok(());
+    };
+
+    let mut commands: BTreeMap<&str, CommandStats> = BTreeMap::new();
+    for record in &records {
+        let stats = commands.entry(&record.command).or_default();
+        let duration = Duration::from_millis(record.duration_ms);
+        stats.runs += 1;
+        stats.failed += usize::from(!record.ok);
+        stats.total += duration;
+        stats.longest = stats.longest.max(duration);
+    }
+    let mut commands: Vec<(&str, CommandStats)> = commands.into_iter().collect();
+    commands.sort_by(|a, b| b.1.runs.cmp(&a.1.runs).then(a.0.cmp(b.0)));
+
+    let day = |record: &Record| record.time.get(..10).unwrap_or(&record.time).to_string();
+    println!(
+        "[usage] {} command(s) from {} to {}, {} failed",
+        records.len(),
+        day(first),
+        day(last),
+        records.iter().filter(|record| !record.ok).count()
+    );
+    let width = commands
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, stats) in &commands {
+        println!(
+            "  {name:<width$}  {:>5} run(s)  {:>4} failed  total {:>10.2?}  average {:>10.2?}  longest {:>10.2?}",
+            stats.runs,
+            stats.failed,
+            stats.total,
+            stats.total / stats.runs as u32,
+            stats.longest
+        );
+    }
+    println!(
+        "  vault: {} entries ({}) on {}, {} entries ({}) on {}",
+        first.entries,
+        format_bytes(first.bytes as usize),
+        day(first),
+        last.entries,
+        format_bytes(last.bytes as usize),
+        day(last)
+    );
+    Ok(())
+}
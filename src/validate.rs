@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use diaryx_core::{find_part_of_mismatches, validate_graph};
+use serde_yaml::Value;
+
+use crate::build::RealFs;
+use crate::diagnostics::link_target;
+use crate::exit::ValidationFailed;
+use crate::vault::{
+    EditMode, FileEdit, apply_all_with_undo, entry_title, parse_fields, set_field, skipped_note,
+    split_frontmatter,
+};
+
+pub struct ValidateOptions {
+    /// Root index of the vault.
+    pub input: PathBuf,
+    /// Patch one-sided `contents` / `part_of` links first.
+    pub fix: bool,
+    pub mode: EditMode,
+    pub command: String,
+}
+
+/// `validate`: report the frontmatter and graph warnings of every entry (private ones included)
+/// without building, failing when there are any. With `fix`, first add the missing side of each
+/// `contents` / `part_of` link that only goes one way: the index to the child's `part_of`, or
+/// the child to the index's `contents`. The fixes are journaled for `undo`.
+pub fn run_validate(opts: &ValidateOptions) -> Result<()> {
+    let root = opts.input.parent().unwrap_or(Path::new("."));
+    let entry = opts
+        .input
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?;
+    if opts.fix {
+        fix_part_of(opts, entry, root)?;
+    }
+
+    let report = validate_graph(entry, true, &RealFs)?;
+    if report.diagnostics.is_empty() {
+        println!("[validate] ok ({} page(s))", report.page_count);
+        return Ok(());
+    }
+    println!("[validate] {} problem(s)", report.diagnostics.len());
+    for diagnostic in &report.diagnostics {
+        println!("  [{}] {}", diagnostic.code, diagnostic.message);
+    }
+    Err(ValidationFailed(format!(
+        "Validation found {} problem(s)",
+        report.diagnostics.len()
+    ))
+    .into())
+}
+
+fn fix_part_of(opts: &ValidateOptions, entry: &str, root: &Path) -> Result<()> {
+    let mismatches = find_part_of_mismatches(entry, &RealFs)?;
+    // path -> (contents on disk, new contents)
+    let mut files: BTreeMap<PathBuf, (String, String)> = BTreeMap::new();
+    for m in &mismatches {
+        let (parent, child) = (PathBuf::from(&m.parent), PathBuf::from(&m.child));
+        let (path, key, target) = match m.missing {
+            "part_of" => (child, "part_of", parent),
+            _ => (parent, "contents", child),
+        };
+        let target_text = match files.get(&target) {
+            Some((_, after)) => after.clone(),
+            None => fs::read_to_string(&target)
+                .with_context(|| format!("Failed to read {}", target.display()))?,
+        };
+        let link = format!(
+            "[{}]({})",
+            entry_title(&parse_fields(split_frontmatter(&target_text).0), &target),
+            link_target(path.parent().unwrap_or(Path::new("")), &target, Some(key))
+        );
+        if !files.contains_key(&path) {
+            let text = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            files.insert(path.clone(), (text.clone(), text));
+        }
+        if let Some((_, after)) = files.get_mut(&path) {
+            *after = add_link(after, key, link);
+        }
+    }
+    if files.is_empty() {
+        println!("[validate] contents and part_of links agree");
+        return Ok(());
+    }
+
+    let edits: Vec<FileEdit> = files
+        .into_iter()
+        .map(|(path, (before, after))| FileEdit {
+            path,
+            before,
+            after: Some(after),
+        })
+        .collect();
+    let applied = apply_all_with_undo(&edits, opts.mode, root, &opts.command)?;
+    println!(
+        "[validate] {}fixed {} one-sided contents / part_of link(s) ({} file(s) changed){}",
+        if opts.mode.dry_run { "(dry run) " } else { "" },
+        mismatches.len(),
+        applied.len(),
+        skipped_note(edits.len() - applied.len())
+    );
+    Ok(())
+}
+
+/// Add `link` to the `key` field of `text`: a single `part_of` stays a string, a second one
+/// makes it a list; `contents` is always a list.
+fn add_link(text: &str, key: &str, link: String) -> String {
+    let (fm, body) = split_frontmatter(text);
+    let value = match parse_fields(fm).get(key) {
+        None | Some(Value::Null) if key == "part_of" => Value::String(link),
+        None | Some(Value::Null) => Value::Sequence(vec![Value::String(link)]),
+        Some(Value::Sequence(items)) => {
+            let mut items = items.clone();
+            items.push(Value::String(link));
+            Value::Sequence(items)
+        }
+        Some(other) => Value::Sequence(vec![other.clone(), Value::String(link)]),
+    };
+    set_field(fm, key, &value) + body
+}
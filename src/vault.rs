@@ -0,0 +1,792 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, anyhow, bail};
+use diaryx_core::{CoreBuildOptions, PageOutput, SiteShape, build_site_streaming};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+
+use crate::build::RealFs;
+use crate::diagnostics::{LinkRef, link_target, resolve_link, scan_links};
+use crate::trash;
+
+/// Shared plumbing for commands that edit vault sources in place (`replace`, ...). Edits are
+/// computed as whole-file before/after pairs, so every command gets the same `--dry-run` / `--diff` /
+/// `--interactive` preview and the same write path (per file, or all or nothing for edits that
+/// belong together).
+///
+/// `vault_files` lists every Markdown file reachable from `input` through the `contents` graph
+/// (non-public included), in build order.
+pub fn vault_files(input: &Path) -> Result<Vec<PathBuf>> {
+    let entry = input
+        .to_str()
+        .ok_or_else(|| anyhow!("Non-UTF8 entry path"))?;
+    let opts = CoreBuildOptions {
+        include_nonpublic: true,
+        ..CoreBuildOptions::default()
+    };
+    let mut files = Vec::new();
+    build_site_streaming(entry, opts, &RealFs, |_: &SiteShape, page: PageOutput| {
+        files.push(PathBuf::from(page.source_path));
+        Ok(())
+    })
+    .with_context(|| "Core build failed")?;
+    Ok(files)
+}
+
+/// Split raw text into the frontmatter block (both `---` fences and the trailing newline
+/// included, empty when there is none) and the body, without reformatting either.
+pub fn split_frontmatter(text: &str) -> (&str, &str) {
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return ("", text);
+    };
+    let mut offset = text.len() - rest.len();
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        if line.trim_end() == "---" {
+            return text.split_at(offset);
+        }
+    }
+    ("", text)
+}
+
+/// A pending change to one file.
+#[derive(Debug)]
+pub struct FileEdit {
+    pub path: PathBuf,
+    /// Current contents (empty for a new file).
+    pub before: String,
+    /// New contents; `None` deletes the file.
+    pub after: Option<String>,
+}
+
+/// How a command applies its edits: the `--dry-run` / `--diff` / `--interactive` flags shared
+/// by every editing subcommand.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EditMode {
+    /// Print the diffs without writing anything.
+    pub dry_run: bool,
+    /// Print each diff, then apply it.
+    pub diff: bool,
+    /// Print each diff and ask before applying it.
+    pub interactive: bool,
+}
+
+/// The answer to a [`prompt`]; tests stand in for stdin with their own.
+type Ask<'a> = &'a mut dyn FnMut(&str, &[(char, &str)]) -> Result<char>;
+
+/// Print a unified diff per edit when `mode` shows them, and with `interactive` prompt for each
+/// one (`y` apply, `n` skip, `a` apply this and the rest, `q` skip this and the rest). `atomic`
+/// edits belong together: they are all shown first, then a single question applies all or none.
+fn preview_edits<'a>(
+    edits: &'a [FileEdit],
+    mode: EditMode,
+    atomic: bool,
+    ask_with: Ask,
+) -> Result<Vec<&'a FileEdit>> {
+    let mut accepted = Vec::new();
+    let mut ask = mode.interactive && !mode.dry_run;
+    if atomic {
+        for edit in edits {
+            print_diff(edit, mode.dry_run || mode.diff || ask);
+        }
+        if ask {
+            let question = format!(
+                "Apply all {} changes? They belong together, so it is all or none",
+                edits.len()
+            );
+            let choices = [('y', "yes, apply them all"), ('n', "no, apply none")];
+            if ask_with(&question, &choices)? != 'y' {
+                return Ok(Vec::new());
+            }
+        }
+        return Ok(edits.iter().collect());
+    }
+    for (i, edit) in edits.iter().enumerate() {
+        print_diff(edit, mode.dry_run || mode.diff || ask);
+        if ask {
+            let question = format!(
+                "[{}/{}] Apply this change to {}?",
+                i + 1,
+                edits.len(),
+                edit.path.display()
+            );
+            let choices = [
+                ('y', "yes, apply it"),
+                ('n', "no, skip it"),
+                ('a', "all: apply this and the rest"),
+                ('q', "quit: skip this and the rest"),
+            ];
+            match ask_with(&question, &choices)? {
+                'y' => {}
+                'a' => ask = false,
+                'q' => break,
+                _ => continue,
+            }
+        }
+        accepted.push(edit);
+    }
+    Ok(accepted)
+}
+
+/// Print the unified diff of `edit` when `show` is set.
+fn print_diff(edit: &FileEdit, show: bool) {
+    if show {
+        let name = edit.path.display().to_string();
+        print!(
+            "{}",
+            TextDiff::from_lines(
+                edit.before.as_str(),
+                edit.after.as_deref().unwrap_or_default()
+            )
+            .unified_diff()
+            .header(&name, &name)
+        );
+        if edit.after.is_none() {
+            println!("(delete {name})");
+        }
+    }
+}
+
+/// Ask `question` on stderr and read answers from stdin until one is a listed choice (its
+/// letter, or the first word of its help). End of input picks the last choice.
+pub fn prompt(question: &str, choices: &[(char, &str)]) -> Result<char> {
+    let stdin = io::stdin();
+    let letters: String = choices
+        .iter()
+        .map(|(c, _)| c.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    loop {
+        eprint!("{question} [{letters}] ");
+        io::stderr().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            eprintln!();
+            return Ok(choices.last().map_or('q', |(c, _)| *c));
+        }
+        let answer = line.trim().to_ascii_lowercase();
+        let picked = choices.iter().find(|(c, help)| {
+            answer == c.to_string()
+                || help
+                    .split_whitespace()
+                    .next()
+                    .map(|w| w.trim_end_matches([',', ':']))
+                    == Some(answer.as_str())
+        });
+        match picked {
+            Some((c, _)) => return Ok(*c),
+            None => eprintln!(
+                "{}",
+                choices
+                    .iter()
+                    .map(|(c, help)| format!("{c}: {help}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// `", N file(s) skipped"` for a summary line when `--interactive` declined some edits.
+pub fn skipped_note(skipped: usize) -> String {
+    if skipped == 0 {
+        String::new()
+    } else {
+        format!(", {skipped} file(s) skipped")
+    }
+}
+
+/// Write the new contents. The files deleted, and the previous versions of those overwritten,
+/// are moved to the vault's trash first, in one batch.
+fn write_edits(edits: &[&FileEdit], vault_root: &Path, command: &str) -> Result<()> {
+    let replaced: Vec<&Path> = edits
+        .iter()
+        .filter(|edit| edit.path.is_file())
+        .map(|edit| edit.path.as_path())
+        .collect();
+    trash::discard(vault_root, &replaced, command)?;
+    for edit in edits {
+        if let Some(after) = &edit.after {
+            if let Some(parent) = edit.path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed creating {}", parent.display()))?;
+            }
+            fs::write(&edit.path, after)
+                .with_context(|| format!("Failed writing {}", edit.path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Directory (under the vault root) holding undo journals.
+pub const UNDO_DIR: &str = ".diaryx/undo";
+/// Journals kept; older ones are pruned when a new one is recorded.
+const UNDO_KEEP: usize = 20;
+
+/// One applied command, as recorded for `undo`.
+#[derive(Serialize, Deserialize)]
+struct UndoJournal {
+    command: String,
+    files: Vec<UndoFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct UndoFile {
+    path: PathBuf,
+    /// `None`: the file did not exist before.
+    before: Option<String>,
+    /// `None`: the command deleted the file.
+    after: Option<String>,
+}
+
+/// Preview the edits as `mode` asks, then (unless `dry_run`) record them in an undo journal
+/// under `vault_root`, so [`undo_last`] can revert them, and write the new contents / trash the
+/// deleted files. Returns the edits that were (or, for a dry run, would be) applied: with
+/// `interactive`, only those the user accepted.
+pub fn apply_edits_with_undo<'a>(
+    edits: &'a [FileEdit],
+    mode: EditMode,
+    vault_root: &Path,
+    command: &str,
+) -> Result<Vec<&'a FileEdit>> {
+    apply_with_undo(edits, mode, vault_root, command, false, &mut prompt)
+}
+
+/// [`apply_edits_with_undo`] for edits that only make sense together (a split's new entries and
+/// the index listing them): `--interactive` asks once, and applies all of them or none.
+pub fn apply_all_with_undo<'a>(
+    edits: &'a [FileEdit],
+    mode: EditMode,
+    vault_root: &Path,
+    command: &str,
+) -> Result<Vec<&'a FileEdit>> {
+    apply_with_undo(edits, mode, vault_root, command, true, &mut prompt)
+}
+
+fn apply_with_undo<'a>(
+    edits: &'a [FileEdit],
+    mode: EditMode,
+    vault_root: &Path,
+    command: &str,
+    atomic: bool,
+    ask: Ask,
+) -> Result<Vec<&'a FileEdit>> {
+    let accepted = preview_edits(edits, mode, atomic, ask)?;
+    if !mode.dry_run && !accepted.is_empty() {
+        let journal = UndoJournal {
+            command: command.to_string(),
+            files: accepted
+                .iter()
+                .map(|e| UndoFile {
+                    // Absolute, so `undo` works from any directory.
+                    path: std::path::absolute(&e.path).unwrap_or_else(|_| e.path.clone()),
+                    before: e.path.exists().then(|| e.before.clone()),
+                    after: e.after.clone(),
+                })
+                .collect(),
+        };
+        let dir = vault_root.join(UNDO_DIR);
+        fs::create_dir_all(&dir).with_context(|| format!("Failed creating {}", dir.display()))?;
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let path = dir.join(format!("{stamp:015}.json"));
+        fs::write(&path, serde_json::to_string(&journal)?)
+            .with_context(|| format!("Failed writing {}", path.display()))?;
+        let journals = undo_journals(&dir)?;
+        for old in journals
+            .iter()
+            .take(journals.len().saturating_sub(UNDO_KEEP))
+        {
+            let _ = fs::remove_file(old);
+        }
+        write_edits(&accepted, vault_root, command)?;
+    }
+    Ok(accepted)
+}
+
+/// The root index of the vault `dir` is in, found like git finds its repository: the nearest
+/// folder at or above `dir` with a `diaryx.toml` or a root index (a `.md` file declaring
+/// `this_file_is_root_index: true`). In that folder the declared root index is used, else
+/// `index.md`.
+pub fn discover_root_index(dir: &Path) -> Result<Option<PathBuf>> {
+    for folder in dir.ancestors() {
+        let declared = declared_root_index(folder);
+        let config = folder.join(diaryx_core::schema::CONFIG_FILE).is_file();
+        if declared.is_none() && !config {
+            continue;
+        }
+        let index = declared
+            .or_else(|| Some(folder.join("index.md")).filter(|p| p.is_file()))
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} has no root index beside it (a .md file with \
+                     `this_file_is_root_index: true`, or index.md); pass --input",
+                    folder.join(diaryx_core::schema::CONFIG_FILE).display()
+                )
+            })?;
+        return Ok(Some(index));
+    }
+    Ok(None)
+}
+
+/// The `.md` file in `folder` declaring `this_file_is_root_index: true` (alphabetically first).
+fn declared_root_index(folder: &Path) -> Option<PathBuf> {
+    let mut candidates: Vec<PathBuf> = fs::read_dir(folder)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("md")) && p.is_file())
+        .collect();
+    candidates.sort();
+    candidates.into_iter().find(|path| {
+        let Ok(text) = fs::read_to_string(path) else {
+            return false;
+        };
+        parse_fields(split_frontmatter(&text).0)
+            .get("this_file_is_root_index")
+            .and_then(serde_yaml::Value::as_bool)
+            .unwrap_or(false)
+    })
+}
+
+/// Where a command that was only given files (no root index) journals its edits: the nearest
+/// folder above `file` that already has a `.diaryx` folder, a `diaryx.toml` or a root index,
+/// else the file's own folder.
+pub fn journal_root(file: &Path) -> PathBuf {
+    let dir = std::path::absolute(file)
+        .ok()
+        .and_then(|p| p.parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| PathBuf::from("."));
+    dir.ancestors()
+        .find(|d| {
+            d.join(".diaryx").is_dir()
+                || d.join(diaryx_core::schema::CONFIG_FILE).is_file()
+                || declared_root_index(d).is_some()
+        })
+        .map(Path::to_path_buf)
+        .unwrap_or(dir)
+}
+
+/// The nearest folder at or above `dir` that has `state` (`UNDO_DIR`, `TRASH_DIR`), for
+/// `undo` / `trash` run without `--input`.
+pub fn find_state_root(dir: &Path, state: &str) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|d| d.join(state).is_dir())
+        .map(Path::to_path_buf)
+}
+
+/// Revert the most recent journaled command. Files changed since then are left alone (and the
+/// undo refused) unless `force` is set.
+pub fn undo_last(vault_root: &Path, force: bool) -> Result<()> {
+    let dir = vault_root.join(UNDO_DIR);
+    let journals = undo_journals(&dir)?;
+    let Some(latest) = journals.last() else {
+        bail!("Nothing to undo (no journals in {})", dir.display());
+    };
+    let journal: UndoJournal = serde_json::from_str(
+        &fs::read_to_string(latest)
+            .with_context(|| format!("Failed to read {}", latest.display()))?,
+    )
+    .with_context(|| format!("Corrupt undo journal {}", latest.display()))?;
+
+    let modified: Vec<String> = journal
+        .files
+        .iter()
+        .filter(|f| fs::read_to_string(&f.path).ok() != f.after)
+        .map(|f| format!("  {}", f.path.display()))
+        .collect();
+    if !modified.is_empty() && !force {
+        bail!(
+            "Files changed since `{}`; re-run with --force to overwrite them:\n{}",
+            journal.command,
+            modified.join("\n")
+        );
+    }
+    let edits: Vec<FileEdit> = journal
+        .files
+        .into_iter()
+        .map(|f| FileEdit {
+            before: fs::read_to_string(&f.path).unwrap_or_default(),
+            after: f.before,
+            path: f.path,
+        })
+        .collect();
+    write_edits(
+        &edits.iter().collect::<Vec<_>>(),
+        vault_root,
+        &format!("undo {}", journal.command),
+    )?;
+    // Folders the command created (`split`) go with the files it created.
+    for edit in edits.iter().filter(|e| e.after.is_none()) {
+        if let Some(parent) = edit.path.parent() {
+            let _ = fs::remove_dir(parent);
+        }
+    }
+    fs::remove_file(latest).with_context(|| format!("Failed removing {}", latest.display()))?;
+    println!(
+        "[undo] reverted `{}` ({} file(s))",
+        journal.command,
+        edits.len()
+    );
+    Ok(())
+}
+
+/// Journal files, oldest first (names are zero-padded timestamps).
+fn undo_journals(dir: &Path) -> Result<Vec<PathBuf>> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let mut journals: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "json"))
+        .collect();
+    journals.sort();
+    Ok(journals)
+}
+
+/// Rewrite link targets in `text` (a whole file, or a body fragment): `rewrite` returns the new
+/// target, or `None` to leave a link alone.
+pub fn rewrite_link_targets(
+    text: &str,
+    mut rewrite: impl FnMut(&LinkRef) -> Option<String>,
+) -> String {
+    let links = scan_links(text);
+    let mut out = String::with_capacity(text.len());
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let mut line = line.to_string();
+        // Right to left so earlier byte offsets stay valid.
+        for link in links.iter().rev().filter(|l| l.line == i) {
+            if let Some(new) = rewrite(link) {
+                line.replace_range(link.start..link.end, &new);
+            }
+        }
+        out.push_str(&line);
+    }
+    out
+}
+
+/// Target for `link` (found in `from_file`) once the text moves into `to_dir`, keeping any
+/// `?query` / `#fragment`. `None` for links that are not relative file paths.
+pub fn rebase_target(link: &LinkRef, from_file: &Path, to_dir: &Path) -> Option<String> {
+    let key = link.key.as_deref();
+    let resolved = resolve_link(from_file, &link.target, key)?;
+    let suffix = link
+        .target
+        .find(['?', '#'])
+        .map_or("", |i| &link.target[i..]);
+    Some(link_target(to_dir, &resolved.path, key) + suffix)
+}
+
+/// Parsed frontmatter block (as returned by [`split_frontmatter`]); empty or invalid YAML yields
+/// an empty mapping.
+pub fn parse_fields(fm: &str) -> serde_yaml::Mapping {
+    let inner = fm_inner(fm);
+    serde_yaml::from_str::<serde_yaml::Value>(inner)
+        .ok()
+        .and_then(|v| v.as_mapping().cloned())
+        .unwrap_or_default()
+}
+
+/// `title` from parsed frontmatter `fields`, else the file name of `path` without `.md`.
+pub fn entry_title(fields: &serde_yaml::Mapping, path: &Path) -> String {
+    fields
+        .get("title")
+        .and_then(serde_yaml::Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+}
+
+/// Set a top-level frontmatter field, rewriting only that field's lines (other fields keep their
+/// formatting and order). A missing field is appended; a missing block is created.
+pub fn set_field(fm: &str, key: &str, value: &serde_yaml::Value) -> String {
+    let mut single = serde_yaml::Mapping::new();
+    single.insert(serde_yaml::Value::String(key.to_string()), value.clone());
+    let rendered = serde_yaml::to_string(&single).unwrap_or_default();
+    let (before, after) = without_field(fm, key);
+    let mut out = String::from("---\n");
+    out.push_str(&before);
+    out.push_str(&rendered);
+    out.push_str(&after);
+    out.push_str("---\n");
+    out
+}
+
+/// Remove a top-level frontmatter field (no-op when absent).
+pub fn remove_field(fm: &str, key: &str) -> String {
+    if fm.is_empty() {
+        return String::new();
+    }
+    let (before, after) = without_field(fm, key);
+    format!("---\n{before}{after}---\n")
+}
+
+/// Lines of the block before and after `key`'s entry (key line plus indented / list
+/// continuation lines). When the key is absent, everything is "before".
+fn without_field(fm: &str, key: &str) -> (String, String) {
+    let mut before = String::new();
+    let mut after = String::new();
+    let mut state = 0; // 0: before the field, 1: inside it, 2: after it
+    for line in fm_inner(fm).split_inclusive('\n') {
+        let top_level_key = !line.starts_with([' ', '\t', '-', '#'])
+            && line
+                .split_once(':')
+                .is_some_and(|(k, _)| !k.trim().is_empty());
+        if state == 0 && top_level_key && line.split_once(':').map(|(k, _)| k.trim()) == Some(key) {
+            state = 1;
+            continue;
+        }
+        if state == 1 {
+            // A comment at the margin belongs to what follows, not to the removed value.
+            if top_level_key || line.starts_with('#') {
+                state = 2;
+            } else {
+                continue;
+            }
+        }
+        let line = if line.ends_with('\n') {
+            line.to_string()
+        } else {
+            format!("{line}\n")
+        };
+        if state == 0 {
+            before.push_str(&line);
+        } else {
+            after.push_str(&line);
+        }
+    }
+    (before, after)
+}
+
+/// YAML between the fences of a frontmatter block.
+fn fm_inner(fm: &str) -> &str {
+    let Some(rest) = fm
+        .strip_prefix("---\n")
+        .or_else(|| fm.strip_prefix("---\r\n"))
+    else {
+        return "";
+    };
+    let body = rest.trim_end_matches(['\n', '\r']);
+    let body = body.strip_suffix("---").unwrap_or(body);
+    &rest[..body.len()]
+}
+
+/// File-name-safe slug: lowercase ASCII alphanumerics joined by `-`.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "untitled".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(text: &str) -> serde_yaml::Value {
+        serde_yaml::Value::String(text.to_string())
+    }
+
+    #[test]
+    fn set_field_rewrites_only_its_own_lines() {
+        let fm = "---\ntitle: Walk   # kept as written\n# about tags\ntags:\n  - a\n  - b\n# about visibility\nvisibility: public\n---\n";
+        let tags = serde_yaml::Value::Sequence(vec![string("c")]);
+        assert_eq!(
+            set_field(fm, "tags", &tags),
+            "---\ntitle: Walk   # kept as written\n# about tags\ntags:\n- c\n# about visibility\nvisibility: public\n---\n"
+        );
+        // The last field, and a new one (appended).
+        assert_eq!(
+            set_field(fm, "visibility", &string("private")),
+            "---\ntitle: Walk   # kept as written\n# about tags\ntags:\n  - a\n  - b\n# about visibility\nvisibility: private\n---\n"
+        );
+        assert_eq!(
+            set_field("---\ntitle: Walk\n---\n", "created", &string("2025-01-01")),
+            "---\ntitle: Walk\ncreated: 2025-01-01\n---\n"
+        );
+        // A key that merely starts like another is a different key.
+        assert_eq!(
+            set_field("---\ntag: x\ntags: [y]\n---\n", "tag", &string("z")),
+            "---\ntag: z\ntags: [y]\n---\n"
+        );
+    }
+
+    #[test]
+    fn set_field_creates_a_missing_block() {
+        assert_eq!(
+            set_field("", "title", &string("Walk")),
+            "---\ntitle: Walk\n---\n"
+        );
+        let (fm, body) = split_frontmatter("No frontmatter here.\n");
+        assert_eq!(fm, "");
+        assert_eq!(
+            set_field(fm, "title", &string("Walk")) + body,
+            "---\ntitle: Walk\n---\nNo frontmatter here.\n"
+        );
+    }
+
+    #[test]
+    fn remove_field_takes_its_continuation_lines() {
+        let fm = "---\ncontents:\n- '[A](a.md)'\n-   '[B](b.md)'\nsummary: >\n  folded\n  text\ntitle: Walk\n---\n";
+        assert_eq!(
+            remove_field(fm, "contents"),
+            "---\nsummary: >\n  folded\n  text\ntitle: Walk\n---\n"
+        );
+        assert_eq!(
+            remove_field(fm, "summary"),
+            "---\ncontents:\n- '[A](a.md)'\n-   '[B](b.md)'\ntitle: Walk\n---\n"
+        );
+        assert_eq!(remove_field(fm, "title"), fm.replace("title: Walk\n", ""));
+        assert_eq!(remove_field(fm, "missing"), fm);
+        assert_eq!(remove_field("", "title"), "");
+    }
+
+    fn interactive() -> EditMode {
+        EditMode {
+            interactive: true,
+            ..EditMode::default()
+        }
+    }
+
+    #[test]
+    fn undo_reverts_created_modified_and_deleted_files() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        let (a, b, c) = (root.join("a.md"), root.join("new/b.md"), root.join("c.md"));
+        fs::write(&a, "before\n").unwrap();
+        fs::write(&c, "doomed\n").unwrap();
+        let edits = vec![
+            FileEdit {
+                path: a.clone(),
+                before: "before\n".to_string(),
+                after: Some("after\n".to_string()),
+            },
+            FileEdit {
+                path: b.clone(),
+                before: String::new(),
+                after: Some("created\n".to_string()),
+            },
+            FileEdit {
+                path: c.clone(),
+                before: "doomed\n".to_string(),
+                after: None,
+            },
+        ];
+        apply_edits_with_undo(&edits, EditMode::default(), root, "test").unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "after\n");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "created\n");
+        assert!(!c.exists());
+        assert_eq!(undo_journals(&root.join(UNDO_DIR)).unwrap().len(), 1);
+        // The overwritten and the deleted file are kept in one trash batch.
+        let batch = fs::read_dir(root.join(trash::TRASH_DIR))
+            .unwrap()
+            .flatten()
+            .find(|item| item.path().is_dir())
+            .unwrap()
+            .path();
+        assert_eq!(fs::read_to_string(batch.join("a.md")).unwrap(), "before\n");
+        assert_eq!(fs::read_to_string(batch.join("c.md")).unwrap(), "doomed\n");
+
+        undo_last(root, false).unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "before\n");
+        assert!(!b.exists() && !root.join("new").exists());
+        assert_eq!(fs::read_to_string(&c).unwrap(), "doomed\n");
+        // The journal is used up.
+        assert!(undo_journals(&root.join(UNDO_DIR)).unwrap().is_empty());
+        assert!(undo_last(root, false).is_err());
+    }
+
+    #[test]
+    fn undo_refuses_files_changed_since() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        let a = root.join("a.md");
+        fs::write(&a, "before\n").unwrap();
+        let edits = [FileEdit {
+            path: a.clone(),
+            before: "before\n".to_string(),
+            after: Some("after\n".to_string()),
+        }];
+        apply_edits_with_undo(&edits, EditMode::default(), root, "test").unwrap();
+        fs::write(&a, "edited by hand\n").unwrap();
+        let err = undo_last(root, false).unwrap_err();
+        assert!(err.to_string().contains("--force"), "{err}");
+        assert_eq!(fs::read_to_string(&a).unwrap(), "edited by hand\n");
+        undo_last(root, true).unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "before\n");
+    }
+
+    #[test]
+    fn declining_a_transaction_applies_nothing() {
+        let vault = tempfile::tempdir().unwrap();
+        let index = vault.path().join("walk.md");
+        fs::write(&index, "# Morning\n").unwrap();
+        let edits = vec![
+            FileEdit {
+                path: index.clone(),
+                before: "# Morning\n".to_string(),
+                after: Some("---\ncontents:\n- '[Morning](walk/morning.md)'\n---\n".to_string()),
+            },
+            FileEdit {
+                path: vault.path().join("walk/morning.md"),
+                before: String::new(),
+                after: Some("# Morning\n".to_string()),
+            },
+        ];
+
+        let mut questions = 0;
+        let mut decline = |_: &str, _: &[(char, &str)]| {
+            questions += 1;
+            Ok('n')
+        };
+        let applied = apply_with_undo(
+            &edits,
+            interactive(),
+            vault.path(),
+            "split",
+            true,
+            &mut decline,
+        )
+        .unwrap();
+        assert!(applied.is_empty());
+        assert_eq!(questions, 1);
+        assert_eq!(fs::read_to_string(&index).unwrap(), "# Morning\n");
+        assert!(!vault.path().join("walk").exists());
+        assert!(!vault.path().join(UNDO_DIR).exists());
+
+        // Accepted, all of it lands.
+        let applied = apply_with_undo(
+            &edits,
+            interactive(),
+            vault.path(),
+            "split",
+            true,
+            &mut |_, _| Ok('y'),
+        )
+        .unwrap();
+        assert_eq!(applied.len(), 2);
+        assert!(vault.path().join("walk/morning.md").is_file());
+    }
+}